@@ -1,10 +1,14 @@
+use crate::compaction::compact_messages;
 use crate::tools::{IntoToolCallExecutor, ToolExecutor};
+use crate::usage::LlmCallUsage;
 use anyhow::Result;
+use database::enums::ModelRouteMode;
 use litellm::{
     AgentMessage, ChatCompletionChunk, ChatCompletionRequest, DeltaToolCall, FunctionCall,
     LiteLLMClient, MessageProgress, Metadata, Tool, ToolCall, ToolChoice,
 };
 use once_cell::sync::Lazy;
+use query_engine::data_source_query_routes::query_cancellation::CancellationToken;
 use serde_json::Value;
 use std::time::{Duration, Instant};
 use std::{collections::HashMap, env, sync::Arc};
@@ -143,6 +147,11 @@ pub trait ModeProvider {
         &self,
         state: &HashMap<String, Value>,
     ) -> Result<ModeConfiguration>;
+
+    /// The mode the usage of LLM calls made in the given state is reported under
+    fn mode_for_state(&self, _state: &HashMap<String, Value>) -> Option<ModelRouteMode> {
+        None
+    }
 }
 // --- End ModeProvider Trait ---
 
@@ -176,6 +185,10 @@ pub struct Agent {
     terminating_tool_names: Arc<RwLock<Vec<String>>>,
     /// Provider for mode-specific logic (prompt, model, tools, termination)
     mode_provider: Arc<dyn ModeProvider + Send + Sync>,
+    /// Token usage of the LLM calls made by this agent, its sub-agents and their tools
+    usage: Arc<RwLock<Vec<LlmCallUsage>>>,
+    /// Cancelled on shutdown to stop the warehouse queries the agent's tools are running
+    query_cancellation: CancellationToken,
 }
 
 impl Agent {
@@ -209,6 +222,8 @@ impl Agent {
             name,
             terminating_tool_names: Arc::new(RwLock::new(Vec::new())), // Initialize empty list
             mode_provider,                                             // Store the provider
+            usage: Arc::new(RwLock::new(Vec::new())),
+            query_cancellation: CancellationToken::new(),
         }
     }
 
@@ -236,6 +251,8 @@ impl Agent {
             name,
             terminating_tool_names: Arc::new(RwLock::new(Vec::new())), // Sub-agent starts with empty term tools?
             mode_provider: Arc::clone(&mode_provider),                 // Share provider
+            usage: Arc::clone(&existing_agent.usage), // Shared usage
+            query_cancellation: existing_agent.query_cancellation.clone(), // Shared cancellation
         }
     }
 
@@ -344,6 +361,11 @@ impl Agent {
         self.user_id
     }
 
+    /// Token to pass to the queries run on the agent's behalf. It fires when the agent shuts down.
+    pub fn query_cancellation(&self) -> &CancellationToken {
+        &self.query_cancellation
+    }
+
     pub fn get_session_id(&self) -> Uuid {
         self.session_id
     }
@@ -352,6 +374,20 @@ impl Agent {
         &self.model
     }
 
+    /// Records the token usage of an LLM call. Calls without a mode are attributed to the mode
+    /// the agent is in now.
+    pub async fn record_usage(&self, mut usage: LlmCallUsage) {
+        if usage.mode.is_none() {
+            usage.mode = self.mode_provider.mode_for_state(&*self.state.read().await);
+        }
+        self.usage.write().await.push(usage);
+    }
+
+    /// Takes the usage recorded so far, leaving none behind
+    pub async fn take_usage(&self) -> Vec<LlmCallUsage> {
+        std::mem::take(&mut *self.usage.write().await)
+    }
+
     /// Get the complete conversation history of the current thread
     pub async fn get_conversation_history(&self) -> Option<Vec<AgentMessage>> {
         self.current_thread
@@ -641,6 +677,7 @@ impl Agent {
             .mode_provider
             .get_configuration_for_state(&state)
             .await?;
+        let usage_mode = agent.mode_provider.mode_for_state(&state);
 
         // Apply Tool Loading via the closure provided by the mode
         agent.clear_tools().await; // Clear previous mode's tools
@@ -672,6 +709,9 @@ impl Agent {
                 .filter(|msg| !matches!(msg, AgentMessage::Developer { .. }))
                 .cloned(),
         );
+        // Long chats are compacted to fit the model's context window. The full history is kept
+        // so fallback models are compacted to their own window.
+        let compacted_messages = compact_messages(llm_messages.clone(), &mode_config.model);
         // --- End Prepare LLM Messages ---
 
         // Collect all enabled tools and their schemas
@@ -685,9 +725,9 @@ impl Agent {
             .cloned();
 
         // Create the tool-enabled request
-        let request = ChatCompletionRequest {
+        let mut request = ChatCompletionRequest {
             model: mode_config.model, // Use the model from mode config
-            messages: llm_messages,
+            messages: compacted_messages,
             tools: if tools.is_empty() { None } else { Some(tools) },
             tool_choice: Some(ToolChoice::Required), // Or adjust based on mode?
             stream: Some(true),                      // Enable streaming
@@ -745,62 +785,77 @@ impl Agent {
             }
         };
 
-        // The retry operation now wraps the actual result or a permanent error in an outer Ok
-        // Retriable errors are returned as the Err variant for Retry::spawn
-        let stream_rx_result = Retry::spawn(retry_strategy, || {
-            // Clone necessary data for the closure
-            let agent_clone = agent.clone();
-            let request_clone = request.clone();
-            let retry_condition_clone = retry_condition; // Clone the condition closure
-            async move {
-                match agent_clone
-                    .llm_client
-                    .stream_chat_completion(request_clone)
-                    .await
-                {
-                    Ok(rx) => Ok(Ok(rx)), // Outer Ok, Inner Ok: Success
-                    Err(e) => {
-                        if retry_condition_clone(&e) {
-                            // Check if error is retriable
-                            Err(e) // Outer Err: Signal retry
-                        } else {
-                            // Outer Ok, Inner Err: Permanent failure, stop retrying
-                            Ok(Err(e))
+        // Models to fall back to, in order, if the provider of the current one errors
+        let mut fallback_models = mode_config.fallback_models.into_iter();
+
+        // Get the streaming response from the LLM
+        let mut stream_rx: mpsc::Receiver<Result<ChatCompletionChunk>> = loop {
+            // The retry operation now wraps the actual result or a permanent error in an outer Ok
+            // Retriable errors are returned as the Err variant for Retry::spawn
+            let stream_rx_result = Retry::spawn(retry_strategy.clone(), || {
+                // Clone necessary data for the closure
+                let agent_clone = agent.clone();
+                let request_clone = request.clone();
+                let retry_condition_clone = retry_condition; // Clone the condition closure
+                async move {
+                    match agent_clone
+                        .llm_client
+                        .stream_chat_completion(request_clone)
+                        .await
+                    {
+                        Ok(rx) => Ok(Ok(rx)), // Outer Ok, Inner Ok: Success
+                        Err(e) => {
+                            if retry_condition_clone(&e) {
+                                // Check if error is retriable
+                                Err(e) // Outer Err: Signal retry
+                            } else {
+                                // Outer Ok, Inner Err: Permanent failure, stop retrying
+                                Ok(Err(e))
+                            }
                         }
                     }
                 }
-            }
-        })
-        .await;
-        // --- End Retry Logic ---
+            })
+            .await;
 
-        // Get the streaming response from the LLM
-        // Handle the nested result from the retry logic
-        let mut stream_rx: mpsc::Receiver<Result<ChatCompletionChunk>> = match stream_rx_result {
-            Ok(Ok(rx)) => rx, // Success case
-            Ok(Err(permanent_error)) => {
-                // Permanent error case (non-retriable)
-                let error_message = format!(
-                    "Error starting LLM stream (non-retriable): {:?}",
+            // Handle the nested result from the retry logic
+            let error = match stream_rx_result {
+                Ok(Ok(rx)) => break rx, // Success case
+                Ok(Err(permanent_error)) => {
+                    // Permanent error case (non-retriable)
+                    let error_message = format!(
+                        "Error starting LLM stream with {} (non-retriable): {:?}",
+                        request.model, permanent_error
+                    );
+                    tracing::error!(agent_name = %agent.name, chat_id = %agent.session_id, user_id = %agent.user_id, "{}", error_message);
                     permanent_error
-                );
-                tracing::error!(agent_name = %agent.name, chat_id = %agent.session_id, user_id = %agent.user_id, "{}", error_message);
-                return Err(permanent_error); // Return the permanent error
-            }
-            Err(last_retriable_error) => {
-                // Error after retries exhausted
-                let error_message = format!(
-                    "Error starting LLM stream after multiple retries: {:?}",
+                }
+                Err(last_retriable_error) => {
+                    // Error after retries exhausted
+                    let error_message = format!(
+                        "Error starting LLM stream with {} after multiple retries: {:?}",
+                        request.model, last_retriable_error
+                    );
+                    tracing::error!(agent_name = %agent.name, chat_id = %agent.session_id, user_id = %agent.user_id, "{}", error_message);
                     last_retriable_error
-                );
-                tracing::error!(agent_name = %agent.name, chat_id = %agent.session_id, user_id = %agent.user_id, "{}", error_message);
-                return Err(last_retriable_error); // Return the last retriable error
+                }
+            };
+
+            match fallback_models.next() {
+                Some(fallback_model) => {
+                    tracing::warn!(agent_name = %agent.name, chat_id = %agent.session_id, "Falling back from model {} to {}", request.model, fallback_model);
+                    request.messages = compact_messages(llm_messages.clone(), &fallback_model);
+                    request.model = fallback_model;
+                }
+                None => return Err(error), // Every model failed
             }
         };
 
         // Process the streaming chunks
         let mut buffer = MessageBuffer::new();
         let mut _is_complete = false;
+        let mut usage = None; // Sent with the last chunk
+
         const STREAM_TIMEOUT_SECS: u64 = 120; // Timeout after 120 seconds of inactivity
 
         loop {
@@ -812,6 +867,10 @@ impl Agent {
                     // Received a message within timeout
                     match chunk_result {
                         Ok(chunk) => {
+                            if chunk.usage.is_some() {
+                                usage = chunk.usage.clone();
+                            }
+
                             if chunk.choices.is_empty() {
                                 continue;
                             }
@@ -909,6 +968,20 @@ impl Agent {
         // Flush any remaining buffered content or tool calls before creating final message
         buffer.flush(&agent).await?;
 
+        match usage {
+            Some(usage) => {
+                agent
+                    .record_usage(LlmCallUsage::chat(&request.model, &usage).in_mode(usage_mode))
+                    .await
+            }
+            None => warn!(
+                agent_name = %agent.name,
+                chat_id = %agent.session_id,
+                "LLM stream for {} ended without usage",
+                request.model
+            ),
+        }
+
         // Create and send the final message
         let final_tool_calls: Option<Vec<ToolCall>> = if !buffer.tool_calls.is_empty() {
             Some(
@@ -1204,6 +1277,9 @@ impl Agent {
 
     /// Signal shutdown to all receivers
     pub async fn shutdown(&self) -> Result<()> {
+        // Stop any queries the agent's tools still have running on the warehouse
+        self.query_cancellation.cancel();
+
         // Send shutdown signal
         self.shutdown_tx.read().await.send(())?;
         Ok(())
@@ -1325,6 +1401,7 @@ mod tests {
             Ok(ModeConfiguration {
                 prompt: "Test Prompt".to_string(),
                 model: "test-model".to_string(),
+                fallback_models: vec![],
                 tool_loader: Box::new(|_agent_arc| Box::pin(async { Ok(()) })), // No-op loader
                 terminating_tools: vec![],
             })
@@ -1,10 +1,10 @@
 use anyhow::Result;
 use chrono::Local;
+use database::enums::ModelRouteMode;
 use dataset_security::get_permissioned_datasets;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
-use std::env;
 use std::sync::Arc;
 use tokio::sync::broadcast;
 use uuid::Uuid;
@@ -14,6 +14,7 @@ use crate::agents::modes::{
     // Assuming modes/mod.rs is one level up
     self, // Import the module itself for functions like determine_agent_state
     determine_agent_state,
+    model_routes::{route_mode, ModelRoutes},
     AgentState,
     ModeAgentData,
     ModeConfiguration,
@@ -52,6 +53,7 @@ pub struct BusterSuperAgentInput {
 #[derive(Clone)]
 struct BusterModeProvider {
     agent_data: ModeAgentData,
+    model_routes: ModelRoutes,
 }
 
 #[async_trait::async_trait]
@@ -70,7 +72,7 @@ impl ModeProvider for BusterModeProvider {
 
         // Call the appropriate get_configuration function based on the mode
         // Pass the extracted syntax (or None) to all modes
-        let mode_config = match current_mode {
+        let mut mode_config = match current_mode {
             AgentState::Initializing => {
                 modes::initialization::get_configuration(&self.agent_data, data_source_syntax)
             }
@@ -89,8 +91,15 @@ impl ModeProvider for BusterModeProvider {
             }
         };
 
+        // The organization's model routes override the models the modes define
+        self.model_routes.apply(current_mode, &mut mode_config);
+
         Ok(mode_config)
     }
+
+    fn mode_for_state(&self, state: &HashMap<String, Value>) -> Option<ModelRouteMode> {
+        Some(route_mode(determine_agent_state(state)))
+    }
 }
 
 // --- BusterMultiAgent ---
@@ -167,14 +176,14 @@ impl BusterMultiAgent {
             todays_date,
         };
 
-        // Create the mode provider
-        let mode_provider = Arc::new(BusterModeProvider { agent_data });
+        let model_routes = ModelRoutes::load_for_user(&user_id).await;
+        let model = model_routes.model_or(route_mode(AgentState::Initializing), "o4-mini");
 
-        let model = if env::var("ENVIRONMENT").unwrap_or_else(|_| "development".to_string()) == "local" {
-            "o4-mini".to_string()
-        } else {
-            "o4-mini".to_string()
-        };
+        // Create the mode provider
+        let mode_provider = Arc::new(BusterModeProvider {
+            agent_data,
+            model_routes,
+        });
 
         // Create agent, passing the provider
         let agent = Arc::new(Agent::new(
@@ -34,6 +34,10 @@ pub fn get_configuration(agent_data: &ModeAgentData, data_source_syntax: Option<
         "mysql" | "mariadb" => MYSQL_MARIADB_DIALECT_GUIDANCE.to_string(),
         "sqlserver" => SQLSERVER_DIALECT_GUIDANCE.to_string(),
         "databricks" => DATABRICKS_DIALECT_GUIDANCE.to_string(),
+        "duckdb" => DUCKDB_DIALECT_GUIDANCE.to_string(),
+        "sqlite" => SQLITE_DIALECT_GUIDANCE.to_string(),
+        "clickhouse" => CLICKHOUSE_DIALECT_GUIDANCE.to_string(),
+        "trino" => TRINO_DIALECT_GUIDANCE.to_string(),
         "supabase" => POSTGRES_DIALECT_GUIDANCE.to_string(), // Supabase uses Postgres
         "postgres" => POSTGRES_DIALECT_GUIDANCE.to_string(), // Explicit postgres case
         _ => POSTGRES_DIALECT_GUIDANCE.to_string(), // Default to Postgres for any others
@@ -166,6 +170,7 @@ pub fn get_configuration(agent_data: &ModeAgentData, data_source_syntax: Option<
     ModeConfiguration {
         prompt,
         model,
+        fallback_models: Vec::new(),
         tool_loader,
         terminating_tools,
     }
@@ -238,6 +243,43 @@ const DATABRICKS_DIALECT_GUIDANCE: &str = r##"
   - **Current Date/Time**: `current_date()`, `current_timestamp()`.
 "##;
 
+const DUCKDB_DIALECT_GUIDANCE: &str = r##"
+- **Date/Time Functions (DuckDB)**:
+  - **`DATE_TRUNC`**: `DATE_TRUNC('day', column)`, `DATE_TRUNC('week', column)`, `DATE_TRUNC('month', column)`. Week starts Monday.
+  - **`EXTRACT`**: `EXTRACT(DOW FROM column)` (0=Sun), `EXTRACT(ISODOW FROM column)` (1=Mon), `EXTRACT(EPOCH FROM column)`.
+  - **DateAdd/DateDiff**: Use `column + INTERVAL 1 DAY`, `DATE_DIFF('day', start_date, end_date)`.
+  - **Intervals**: Use `INTERVAL 1 DAY`, `INTERVAL '1 month'`.
+  - **Current Date/Time**: `CURRENT_DATE`, `CURRENT_TIMESTAMP`, `NOW()`.
+"##;
+
+const SQLITE_DIALECT_GUIDANCE: &str = r##"
+- **Date/Time Functions (SQLite)**:
+  - Dates are stored as text; there is no `DATE_TRUNC`. Use `strftime('%Y-%m-01', column)` for month truncation and `date(column, 'weekday 0', '-6 days')` for Monday-start weeks.
+  - **Parts**: `strftime('%w', column)` (0=Sun), `strftime('%W', column)` for week number, `strftime('%s', column)` for epoch seconds.
+  - **DateAdd/DateDiff**: Use `date(column, '+1 day')`, `julianday(end_date) - julianday(start_date)`.
+  - **Current Date/Time**: `date('now')`, `datetime('now')`.
+"##;
+
+const CLICKHOUSE_DIALECT_GUIDANCE: &str = r##"
+- **Date/Time Functions (ClickHouse)**:
+  - **Truncation**: `toStartOfDay(column)`, `toMonday(column)`, `toStartOfMonth(column)`, or `date_trunc('month', column)`.
+  - **Parts**: `toDayOfWeek(column)` (1=Mon), `toISOWeek(column)`, `toUnixTimestamp(column)` for epoch seconds.
+  - **DateAdd/DateDiff**: Use `addDays(column, 1)`, `column - INTERVAL 1 MONTH`, `dateDiff('day', start_date, end_date)`.
+  - **Intervals**: Use `INTERVAL 1 DAY`, `INTERVAL 1 MONTH`.
+  - **Current Date/Time**: `today()`, `now()`.
+- Function names are case-sensitive (e.g. `toStartOfMonth`, not `TOSTARTOFMONTH`).
+"##;
+
+const TRINO_DIALECT_GUIDANCE: &str = r##"
+- **Date/Time Functions (Trino/Presto)**:
+  - **`DATE_TRUNC`**: `date_trunc('day', column)`, `date_trunc('week', column)`, `date_trunc('month', column)`. Week starts Monday.
+  - **Parts**: `day_of_week(column)` (1=Mon), `week(column)`, `to_unixtime(column)` for epoch seconds.
+  - **DateAdd/DateDiff**: Use `date_add('day', 1, column)`, `date_diff('day', start_date, end_date)`.
+  - **Intervals**: Use `INTERVAL '1' DAY`, `INTERVAL '1' MONTH` (the quantity is a quoted string).
+  - **Current Date/Time**: `current_date`, `current_timestamp`, `now()`.
+- Tables are addressed as `catalog.schema.table`.
+"##;
+
 // Keep the prompt template constant, but add the guidance placeholder
 const PROMPT: &str = r##"### Role & Task
 You are Buster, an expert analytics and data engineer. Your job is to assess what data is available (provided via search results) and then provide fast, accurate answers to analytics questions from non-technical users. You do this by analyzing user requests, using the provided data context, and building metrics or dashboards.
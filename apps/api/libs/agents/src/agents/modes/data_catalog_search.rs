@@ -79,6 +79,7 @@ pub fn get_configuration(agent_data: &ModeAgentData, _data_source_syntax: Option
     ModeConfiguration {
         prompt,
         model,
+        fallback_models: Vec::new(),
         tool_loader,
         terminating_tools,
     }
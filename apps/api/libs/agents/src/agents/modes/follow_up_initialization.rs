@@ -192,6 +192,7 @@ pub fn get_configuration(agent_data: &ModeAgentData) -> ModeConfiguration {
     ModeConfiguration {
         prompt,
         model,
+        fallback_models: Vec::new(),
         tool_loader,
         terminating_tools,
     }
@@ -86,6 +86,7 @@ pub fn get_configuration(
     ModeConfiguration {
         prompt,
         model,
+        fallback_models: Vec::new(),
         tool_loader,
         terminating_tools,
     }
@@ -10,6 +10,7 @@ pub mod analysis;
 pub mod data_catalog_search;
 pub mod follow_up_initialization;
 pub mod initialization;
+pub mod model_routes;
 pub mod planning;
 pub mod review;
 
@@ -33,6 +34,8 @@ pub struct ModeConfiguration {
     pub prompt: String,
     /// The specific LLM model identifier (e.g., "gemini-2.5-pro-exp-03-25") to use for this mode.
     pub model: String,
+    /// Models to try, in order, if the provider of `model` errors when the request is made.
+    pub fallback_models: Vec<String>,
     /// An async function/closure responsible for clearing existing tools
     /// and loading the specific tools required for this mode onto the agent.
     pub tool_loader:
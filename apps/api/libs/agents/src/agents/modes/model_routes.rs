@@ -0,0 +1,190 @@
+use anyhow::Result;
+use database::{
+    enums::ModelRouteMode,
+    pool::get_pg_pool,
+    schema::{model_routes, users_to_organizations},
+};
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl};
+use diesel_async::RunQueryDsl;
+use std::{collections::HashMap, env};
+use uuid::Uuid;
+
+use super::{AgentState, ModeConfiguration};
+
+/// The models an organization routes each agent mode to. Modes without a route use the
+/// organization's default route, or the model the mode defines if there is none.
+#[derive(Debug, Clone, Default)]
+pub struct ModelRoutes {
+    routes: HashMap<ModelRouteMode, Vec<String>>,
+}
+
+impl ModelRoutes {
+    pub fn new(routes: impl IntoIterator<Item = (ModelRouteMode, Vec<String>)>) -> Self {
+        Self {
+            routes: routes
+                .into_iter()
+                .filter(|(_, models)| !models.is_empty())
+                .collect(),
+        }
+    }
+
+    /// Loads the routes of the user's organization. If they can't be loaded the modes use
+    /// their own models, so chats keep working.
+    pub async fn load_for_user(user_id: &Uuid) -> Self {
+        match Self::try_load_for_user(user_id).await {
+            Ok(routes) => routes,
+            Err(e) => {
+                tracing::warn!("Failed to load model routes for user {}: {}", user_id, e);
+                Self::default()
+            }
+        }
+    }
+
+    async fn try_load_for_user(user_id: &Uuid) -> Result<Self> {
+        let mut conn = get_pg_pool().get().await?;
+
+        let organization_id = users_to_organizations::table
+            .filter(users_to_organizations::user_id.eq(user_id))
+            .filter(users_to_organizations::deleted_at.is_null())
+            .select(users_to_organizations::organization_id)
+            .first::<Uuid>(&mut conn)
+            .await
+            .optional()?;
+
+        let organization_id = match organization_id {
+            Some(organization_id) => organization_id,
+            None => return Ok(Self::default()),
+        };
+
+        let routes = model_routes::table
+            .filter(model_routes::organization_id.eq(organization_id))
+            .select((model_routes::mode, model_routes::models))
+            .load::<(ModelRouteMode, Vec<String>)>(&mut conn)
+            .await?;
+
+        Ok(Self::new(routes))
+    }
+
+    /// The models routed for the mode in order of preference, or the default route's
+    pub fn models(&self, mode: ModelRouteMode) -> Option<&[String]> {
+        self.routes
+            .get(&mode)
+            .or_else(|| self.routes.get(&ModelRouteMode::Default))
+            .map(Vec::as_slice)
+    }
+
+    /// The preferred model routed for the mode, or `model` if the organization routes none
+    pub fn model_or(&self, mode: ModelRouteMode, model: &str) -> String {
+        self.models(mode)
+            .and_then(|models| models.first())
+            .map_or_else(|| model.to_string(), Clone::clone)
+    }
+
+    /// The model for short calls outside the agent loop, such as conversation titles
+    pub fn auxiliary_model(&self) -> String {
+        let model =
+            if env::var("ENVIRONMENT").unwrap_or_else(|_| "development".to_string()) == "local" {
+                "gpt-4.1-nano"
+            } else {
+                "gemini-2.0-flash-001"
+            };
+
+        self.model_or(ModelRouteMode::Auxiliary, model)
+    }
+
+    /// Points the mode's configuration at the routed models: the first becomes the model and
+    /// the rest its fallbacks
+    pub fn apply(&self, state: AgentState, config: &mut ModeConfiguration) {
+        let models = self.models(route_mode(state));
+
+        if let Some((model, fallback_models)) = models.and_then(|models| models.split_first()) {
+            config.model = model.clone();
+            config.fallback_models = fallback_models.to_vec();
+        }
+    }
+}
+
+/// The model route mode that covers an agent state
+pub(crate) fn route_mode(state: AgentState) -> ModelRouteMode {
+    match state {
+        AgentState::Initializing => ModelRouteMode::Initialization,
+        AgentState::DataCatalogSearch => ModelRouteMode::DataCatalogSearch,
+        AgentState::Planning => ModelRouteMode::Planning,
+        AgentState::AnalysisExecution => ModelRouteMode::Analysis,
+        AgentState::Review => ModelRouteMode::Review,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ModeConfiguration {
+        ModeConfiguration {
+            prompt: String::new(),
+            model: "o4-mini".to_string(),
+            fallback_models: vec![],
+            tool_loader: Box::new(|_agent_arc| Box::pin(async { Ok(()) })),
+            terminating_tools: vec![],
+        }
+    }
+
+    fn models(models: &[&str]) -> Vec<String> {
+        models.iter().map(|model| model.to_string()).collect()
+    }
+
+    #[test]
+    fn test_mode_route_takes_precedence_over_default() {
+        let routes = ModelRoutes::new([
+            (
+                ModelRouteMode::Default,
+                models(&["azure/gpt-4.1", "bedrock/anthropic.claude-3-7-sonnet"]),
+            ),
+            (ModelRouteMode::Review, models(&["azure/gpt-4.1-mini"])),
+        ]);
+
+        let mut review = config();
+        routes.apply(AgentState::Review, &mut review);
+        assert_eq!(review.model, "azure/gpt-4.1-mini");
+        assert!(review.fallback_models.is_empty());
+
+        let mut planning = config();
+        routes.apply(AgentState::Planning, &mut planning);
+        assert_eq!(planning.model, "azure/gpt-4.1");
+        assert_eq!(
+            planning.fallback_models,
+            models(&["bedrock/anthropic.claude-3-7-sonnet"])
+        );
+    }
+
+    #[test]
+    fn test_auxiliary_calls_follow_the_default_route() {
+        let routes = ModelRoutes::new([(ModelRouteMode::Default, models(&["azure/gpt-4.1"]))]);
+        assert_eq!(routes.auxiliary_model(), "azure/gpt-4.1");
+
+        let routes = ModelRoutes::new([
+            (ModelRouteMode::Default, models(&["azure/gpt-4.1"])),
+            (ModelRouteMode::Auxiliary, models(&["azure/gpt-4.1-nano"])),
+        ]);
+        assert_eq!(routes.auxiliary_model(), "azure/gpt-4.1-nano");
+        assert_eq!(
+            routes.model_or(ModelRouteMode::Initialization, "o4-mini"),
+            "azure/gpt-4.1"
+        );
+
+        assert_eq!(
+            ModelRoutes::default().model_or(ModelRouteMode::Initialization, "o4-mini"),
+            "o4-mini"
+        );
+    }
+
+    #[test]
+    fn test_modes_keep_their_model_without_routes() {
+        let routes = ModelRoutes::new([(ModelRouteMode::Analysis, vec![])]);
+
+        let mut analysis = config();
+        routes.apply(AgentState::AnalysisExecution, &mut analysis);
+        assert_eq!(analysis.model, "o4-mini");
+        assert!(analysis.fallback_models.is_empty());
+    }
+}
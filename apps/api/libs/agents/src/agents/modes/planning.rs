@@ -101,6 +101,7 @@ pub fn get_configuration(
     ModeConfiguration {
         prompt,
         model,
+        fallback_models: Vec::new(),
         tool_loader,
         terminating_tools: vec![Done::get_name(), MessageUserClarifyingQuestion::get_name()],
     }
@@ -78,6 +78,7 @@ pub fn get_configuration(
     ModeConfiguration {
         prompt,
         model,
+        fallback_models: Vec::new(),
         tool_loader,
         terminating_tools,
     }
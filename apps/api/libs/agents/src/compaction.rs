@@ -0,0 +1,565 @@
+//! Compaction of long conversations so they fit the model's context window.
+//!
+//! When the estimated size of a request's messages goes over the model's budget, the tool
+//! outputs of earlier turns are shrunk first: SQL result rows are dropped, as is the YAML of
+//! file versions that were replaced later on, and other large outputs are cut short. If that
+//! isn't enough, the oldest turns are replaced by a memory message listing what the user asked,
+//! what they were told, and the id, version and latest YAML of every file made along the way,
+//! so `update_metrics` and `update_dashboards` can still be called on them.
+//!
+//! Only the messages sent to the model are compacted, the thread itself keeps every message.
+//!
+//! Budgets are about half of each model's context window, which leaves room for the tool
+//! schemas, reasoning and the response. Deployments that serve models under their own names can
+//! set `LLM_CONTEXT_BUDGETS` to a JSON object such as `{"azure/analysis": 60000}`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+};
+
+use litellm::AgentMessage;
+use once_cell::sync::Lazy;
+use serde_json::Value;
+
+// Matched against model names with the longest match winning, like the prices in `usage`
+const CONTEXT_BUDGETS: &[(&str, usize)] = &[
+    ("gpt-4.1", 500_000),
+    ("gpt-4o", 64_000),
+    ("o3", 100_000),
+    ("o4-mini", 100_000),
+    ("claude", 100_000),
+    ("gemini-2.0-flash", 500_000),
+    ("gemini-2.5", 500_000),
+];
+
+/// Budget of models that aren't listed
+const DEFAULT_CONTEXT_BUDGET: usize = 60_000;
+
+/// Rough size of a token for English text and JSON
+const CHARS_PER_TOKEN: usize = 4;
+
+/// Tokens each message costs on top of its content
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+
+/// Outputs of other tools longer than this are cut short
+const LARGE_TOOL_OUTPUT_CHARS: usize = 4_000;
+
+/// Requests and responses are cut to this length in the memory message
+const SUMMARY_TEXT_CHARS: usize = 600;
+
+/// Turns kept as they are when older ones are summarized, if they fit
+const KEEP_RECENT_TURNS: usize = 2;
+
+/// Tools whose outputs list the files they created or updated
+const FILE_TOOLS: &[&str] = &[
+    "create_metrics",
+    "update_metrics",
+    "create_dashboards",
+    "update_dashboards",
+];
+
+const SEARCH_DATA_CATALOG: &str = "search_data_catalog";
+
+static BUDGET_OVERRIDES: Lazy<HashMap<String, usize>> = Lazy::new(|| {
+    let Ok(budgets) = env::var("LLM_CONTEXT_BUDGETS") else {
+        return HashMap::new();
+    };
+
+    serde_json::from_str(&budgets).unwrap_or_else(|e| {
+        tracing::error!("Ignoring LLM_CONTEXT_BUDGETS, it isn't a valid budget list: {}", e);
+        HashMap::new()
+    })
+});
+
+/// The number of tokens of messages a request to the model may carry before it's compacted
+pub fn context_budget(model: &str) -> usize {
+    if let Some(budget) = BUDGET_OVERRIDES.get(model) {
+        return *budget;
+    }
+
+    let model = model.to_lowercase();
+    CONTEXT_BUDGETS
+        .iter()
+        .filter(|(name, _)| model.contains(name))
+        .max_by_key(|(name, _)| name.len())
+        .map_or(DEFAULT_CONTEXT_BUDGET, |(_, budget)| *budget)
+}
+
+/// A rough estimate of the tokens the messages take up, without a tokenizer
+pub fn estimate_tokens(messages: &[AgentMessage]) -> usize {
+    messages
+        .iter()
+        .map(|message| {
+            let chars = match message {
+                AgentMessage::Developer { content, .. }
+                | AgentMessage::User { content, .. }
+                | AgentMessage::Tool { content, .. } => content.len(),
+                AgentMessage::Assistant {
+                    content,
+                    tool_calls,
+                    ..
+                } => {
+                    content.as_ref().map_or(0, String::len)
+                        + tool_calls.iter().flatten().fold(0, |len, call| {
+                            len + call.function.name.len() + call.function.arguments.len()
+                        })
+                }
+                AgentMessage::Done => 0,
+            };
+            chars.div_ceil(CHARS_PER_TOKEN) + MESSAGE_OVERHEAD_TOKENS
+        })
+        .sum()
+}
+
+/// Compacts the messages of a request to the model's budget. Messages that fit are returned as
+/// they are.
+pub fn compact_messages(messages: Vec<AgentMessage>, model: &str) -> Vec<AgentMessage> {
+    let budget = context_budget(model);
+    let before = estimate_tokens(&messages);
+    if before <= budget {
+        return messages;
+    }
+
+    let compacted = compact_to_budget(messages, budget);
+    let after = estimate_tokens(&compacted);
+    if after > budget {
+        tracing::warn!(
+            model,
+            before,
+            after,
+            budget,
+            "Conversation is still over the context budget after compaction"
+        );
+    } else {
+        tracing::info!(model, before, after, budget, "Compacted conversation");
+    }
+    compacted
+}
+
+fn compact_to_budget(mut messages: Vec<AgentMessage>, budget: usize) -> Vec<AgentMessage> {
+    if estimate_tokens(&messages) <= budget {
+        return messages;
+    }
+
+    // The system prompt comes first and is never compacted
+    let start = messages
+        .iter()
+        .position(|message| !matches!(message, AgentMessage::Developer { .. }))
+        .unwrap_or(messages.len());
+    let turns: Vec<usize> = (start..messages.len())
+        .filter(|&index| matches!(messages[index], AgentMessage::User { .. }))
+        .collect();
+    let current_turn = turns.last().copied().unwrap_or(start);
+
+    shrink_tool_outputs(&mut messages, current_turn);
+    if estimate_tokens(&messages) <= budget {
+        return messages;
+    }
+
+    for keep in (1..=KEEP_RECENT_TURNS).rev() {
+        let Some(&split) = turns.len().checked_sub(keep).and_then(|i| turns.get(i)) else {
+            continue;
+        };
+        if split == start {
+            continue;
+        }
+
+        let mut compacted = messages[..start].to_vec();
+        compacted.push(AgentMessage::developer(summarize(&messages[start..split])));
+        compacted.extend_from_slice(&messages[split..]);
+
+        if keep == 1 || estimate_tokens(&compacted) <= budget {
+            messages = compacted;
+            break;
+        }
+    }
+    if estimate_tokens(&messages) <= budget {
+        return messages;
+    }
+
+    // Last resort: earlier steps of the current turn, keeping the results the model is about to
+    // read for the first time
+    let last_assistant = messages
+        .iter()
+        .rposition(|message| matches!(message, AgentMessage::Assistant { .. }))
+        .unwrap_or(0);
+    shrink_tool_outputs(&mut messages, last_assistant);
+    messages
+}
+
+/// Shrinks the outputs of the tool messages before `end`
+fn shrink_tool_outputs(messages: &mut [AgentMessage], end: usize) {
+    let names = tool_names(messages);
+
+    // Walk back from the newest message so a file's later versions are seen before its earlier
+    // ones, even past `end`
+    let mut newer_versions = HashSet::new();
+    for index in (0..messages.len()).rev() {
+        let AgentMessage::Tool {
+            content,
+            tool_call_id,
+            name,
+            ..
+        } = &mut messages[index]
+        else {
+            continue;
+        };
+        let name = name
+            .clone()
+            .or_else(|| names.get(tool_call_id.as_str()).cloned())
+            .unwrap_or_default();
+        let compact = index < end;
+
+        if FILE_TOOLS.contains(&name.as_str()) {
+            let Ok(mut output) = serde_json::from_str::<Value>(content) else {
+                continue;
+            };
+            for file in output["files"].as_array_mut().into_iter().flatten() {
+                let replaced = match file["id"].as_str() {
+                    Some(id) => !newer_versions.insert(id.to_string()),
+                    None => false,
+                };
+                if let (true, Some(file)) = (compact, file.as_object_mut()) {
+                    file.remove("results");
+                    if replaced {
+                        file.remove("yml_content");
+                    }
+                }
+            }
+            if compact {
+                *content = output.to_string();
+            }
+        } else if !compact {
+            continue;
+        } else if name == SEARCH_DATA_CATALOG {
+            let Ok(mut output) = serde_json::from_str::<Value>(content) else {
+                continue;
+            };
+            // The model can search again if it needs the dataset definitions
+            for result in output["results"].as_array_mut().into_iter().flatten() {
+                if let Some(result) = result.as_object_mut() {
+                    result.remove("yml_content");
+                }
+            }
+            *content = output.to_string();
+        } else if content.chars().count() > LARGE_TOOL_OUTPUT_CHARS {
+            *content = truncate(content, LARGE_TOOL_OUTPUT_CHARS);
+        }
+    }
+}
+
+/// The memory message that stands in for the given messages
+fn summarize(messages: &[AgentMessage]) -> String {
+    let names = tool_names(messages);
+
+    let mut requests: Vec<(String, Option<String>)> = Vec::new();
+    let mut files: Vec<Value> = Vec::new();
+    let mut datasets: Vec<(String, String)> = Vec::new();
+
+    for message in messages {
+        match message {
+            AgentMessage::User { content, .. } => {
+                requests.push((truncate(content, SUMMARY_TEXT_CHARS), None));
+            }
+            AgentMessage::Assistant {
+                content,
+                tool_calls,
+                ..
+            } => {
+                let response = tool_calls
+                    .iter()
+                    .flatten()
+                    .find_map(|call| {
+                        let arguments = serde_json::from_str::<Value>(&call.function.arguments).ok()?;
+                        match call.function.name.as_str() {
+                            "done" => arguments["final_response"].as_str().map(String::from),
+                            "message_user_clarifying_question" => {
+                                arguments["text"].as_str().map(String::from)
+                            }
+                            _ => None,
+                        }
+                    })
+                    .or_else(|| content.clone().filter(|content| !content.trim().is_empty()));
+                if let (Some(response), Some((_, last))) = (response, requests.last_mut()) {
+                    *last = Some(truncate(&response, SUMMARY_TEXT_CHARS));
+                }
+            }
+            AgentMessage::Tool {
+                content,
+                tool_call_id,
+                name,
+                ..
+            } => {
+                let name = name
+                    .as_deref()
+                    .or_else(|| names.get(tool_call_id.as_str()).map(String::as_str))
+                    .unwrap_or_default();
+                let Ok(output) = serde_json::from_str::<Value>(content) else {
+                    continue;
+                };
+
+                if FILE_TOOLS.contains(&name) {
+                    for file in output["files"].as_array().into_iter().flatten() {
+                        match files.iter_mut().find(|known| known["id"] == file["id"]) {
+                            Some(known) => *known = file.clone(),
+                            None => files.push(file.clone()),
+                        }
+                    }
+                } else if name == SEARCH_DATA_CATALOG {
+                    for dataset in output["results"].as_array().into_iter().flatten() {
+                        let id = dataset["id"].as_str().unwrap_or_default().to_string();
+                        if !datasets.iter().any(|(known, _)| *known == id) {
+                            let name = dataset["name"].as_str().unwrap_or("unnamed").to_string();
+                            datasets.push((id, name));
+                        }
+                    }
+                }
+            }
+            AgentMessage::Developer { .. } | AgentMessage::Done => {}
+        }
+    }
+
+    let mut summary = String::from(
+        "The earlier part of this conversation was compacted to fit the context window. \
+         This is what happened in it.\n",
+    );
+
+    if !requests.is_empty() {
+        summary.push_str("\nEarlier requests:\n");
+        for (number, (request, response)) in requests.iter().enumerate() {
+            summary.push_str(&format!("{}. User: {}\n", number + 1, request));
+            if let Some(response) = response {
+                summary.push_str(&format!("   Response: {}\n", response));
+            }
+        }
+    }
+
+    if !files.is_empty() {
+        summary.push_str(
+            "\nFiles created or updated. Use these ids to update them with update_metrics or \
+             update_dashboards:\n",
+        );
+        for file in &files {
+            summary.push_str(&format!(
+                "- {} \"{}\" (id: {}, version {})\n",
+                file["file_type"].as_str().unwrap_or("file"),
+                file["name"].as_str().unwrap_or_default(),
+                file["id"].as_str().unwrap_or_default(),
+                file["version_number"],
+            ));
+            match file["yml_content"].as_str() {
+                Some(yml) => summary.push_str(&format!("```yaml\n{}\n```\n", yml.trim_end())),
+                None => summary.push_str("  Its latest version is further down.\n"),
+            }
+        }
+    }
+
+    if !datasets.is_empty() {
+        summary.push_str("\nDatasets found in the data catalog:\n");
+        for (id, name) in &datasets {
+            summary.push_str(&format!("- {} (id: {})\n", name, id));
+        }
+    }
+
+    summary
+}
+
+/// Names of the tools called, by tool call id, for tool messages that don't carry their name
+fn tool_names(messages: &[AgentMessage]) -> HashMap<String, String> {
+    messages
+        .iter()
+        .filter_map(|message| match message {
+            AgentMessage::Assistant {
+                tool_calls: Some(calls),
+                ..
+            } => Some(calls),
+            _ => None,
+        })
+        .flatten()
+        .map(|call| (call.id.clone(), call.function.name.clone()))
+        .collect()
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!(
+            "{}… [{} more characters removed to save space]",
+            &text[..end],
+            text[end..].chars().count()
+        ),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use litellm::{FunctionCall, MessageProgress, ToolCall};
+    use serde_json::json;
+
+    fn call(id: &str, name: &str, arguments: Value) -> AgentMessage {
+        AgentMessage::assistant(
+            None,
+            None,
+            Some(vec![ToolCall {
+                id: id.to_string(),
+                call_type: "function".to_string(),
+                function: FunctionCall {
+                    name: name.to_string(),
+                    arguments: arguments.to_string(),
+                },
+                code_interpreter: None,
+                retrieval: None,
+            }]),
+            MessageProgress::Complete,
+            None,
+            None,
+        )
+    }
+
+    fn result(id: &str, name: &str, output: Value) -> AgentMessage {
+        AgentMessage::tool(
+            None,
+            output.to_string(),
+            id,
+            Some(name.to_string()),
+            MessageProgress::Complete,
+        )
+    }
+
+    fn metric(id: &str, version: i32, yml: &str) -> Value {
+        json!({
+            "id": id,
+            "name": "Revenue by month",
+            "file_type": "metric",
+            "yml_content": yml,
+            "result_message": "12 records were returned",
+            "results": (0..12).map(|month| json!({"month": month, "revenue": 1000})).collect::<Vec<_>>(),
+            "version_number": version,
+        })
+    }
+
+    const METRIC_ID: &str = "6f1d9c0e-5d7b-4a8e-9f65-2b0c3c1a7e11";
+
+    /// Two turns that create and then update a metric, and a third that asks about it
+    fn conversation() -> Vec<AgentMessage> {
+        vec![
+            AgentMessage::developer("You are a data analyst."),
+            AgentMessage::user(format!(
+                "Chart revenue by month. {}",
+                "Only count orders that were paid and not refunded. ".repeat(40)
+            )),
+            call("call_1", "search_data_catalog", json!({"specific_queries": ["revenue"]})),
+            result(
+                "call_1",
+                "search_data_catalog",
+                json!({
+                    "message": "Found 1 dataset",
+                    "results": [{"id": "b7c3", "name": "orders", "yml_content": "x".repeat(2_000)}],
+                }),
+            ),
+            call("call_2", "create_metrics", json!({"files": []})),
+            result(
+                "call_2",
+                "create_metrics",
+                json!({"message": "Created 1 metric", "files": [metric(METRIC_ID, 1, "name: Revenue by month")]}),
+            ),
+            call("call_3", "done", json!({"final_response": "Here is revenue by month."})),
+            AgentMessage::user("Make it a bar chart"),
+            call("call_4", "update_metrics", json!({"files": []})),
+            result(
+                "call_4",
+                "update_metrics",
+                json!({"message": "Updated 1 metric", "files": [metric(METRIC_ID, 2, "name: Revenue by month\nchart: bar")]}),
+            ),
+            call("call_5", "done", json!({"final_response": "Switched it to a bar chart."})),
+            AgentMessage::user("Which month was best?"),
+        ]
+    }
+
+    #[test]
+    fn test_context_budget_prefers_the_most_specific_name() {
+        assert_eq!(context_budget("gpt-4o-mini"), 64_000);
+        assert_eq!(context_budget("bedrock/anthropic.claude-3-7-sonnet"), 100_000);
+        assert_eq!(context_budget("gemini-2.0-flash-001"), 500_000);
+        assert_eq!(context_budget("my-fine-tune"), DEFAULT_CONTEXT_BUDGET);
+    }
+
+    #[test]
+    fn test_messages_within_budget_are_unchanged() {
+        let messages = conversation();
+        let compacted = compact_to_budget(messages.clone(), usize::MAX);
+
+        assert_eq!(
+            serde_json::to_value(&compacted).unwrap(),
+            serde_json::to_value(&messages).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_shrink_tool_outputs() {
+        let mut messages = conversation();
+        let current_turn = messages.len() - 1;
+        shrink_tool_outputs(&mut messages, current_turn);
+
+        let output = |index: usize| -> Value {
+            serde_json::from_str(&messages[index].get_content().unwrap()).unwrap()
+        };
+
+        // The first version was replaced, so only its id is left
+        let first = &output(5)["files"][0];
+        assert_eq!(first["id"], METRIC_ID);
+        assert!(first.get("results").is_none());
+        assert!(first.get("yml_content").is_none());
+
+        let latest = &output(9)["files"][0];
+        assert!(latest.get("results").is_none());
+        assert_eq!(latest["yml_content"], "name: Revenue by month\nchart: bar");
+
+        assert_eq!(output(3)["results"][0], json!({"id": "b7c3", "name": "orders"}));
+    }
+
+    #[test]
+    fn test_older_turns_are_summarized() {
+        let messages = conversation();
+        // Just short of fitting once the tool outputs are shrunk
+        let mut shrunk = messages.clone();
+        shrink_tool_outputs(&mut shrunk, messages.len() - 1);
+        let compacted = compact_to_budget(messages, estimate_tokens(&shrunk) - 1);
+
+        assert_eq!(compacted.len(), 7);
+        assert_eq!(compacted[0].get_content().unwrap(), "You are a data analyst.");
+        assert_eq!(compacted[2].get_content().unwrap(), "Make it a bar chart");
+
+        let summary = compacted[1].get_content().unwrap();
+        assert!(summary.contains("1. User: Chart revenue by month. Only count orders"));
+        assert!(summary.contains("more characters removed to save space"));
+        assert!(summary.contains("Response: Here is revenue by month."));
+        assert!(summary.contains(&format!("id: {}, version 1", METRIC_ID)));
+        // The update in the kept turn has the current YAML
+        assert!(summary.contains("Its latest version is further down."));
+        assert!(summary.contains("- orders (id: b7c3)"));
+    }
+
+    #[test]
+    fn test_only_the_current_turn_is_kept_when_needed() {
+        let compacted = compact_to_budget(conversation(), 1);
+
+        assert_eq!(compacted.len(), 3);
+        let summary = compacted[1].get_content().unwrap();
+        assert!(summary.contains("2. User: Make it a bar chart"));
+        assert!(summary.contains("version 2"));
+        assert!(summary.contains("chart: bar"));
+        assert_eq!(compacted[2].get_content().unwrap(), "Which month was best?");
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(
+            truncate("abcdéfgh", 5),
+            "abcdé… [3 more characters removed to save space]"
+        );
+    }
+}
@@ -4,13 +4,17 @@
 
 mod agent;
 mod agents;
+mod compaction;
 mod models;
 pub mod tools;
+mod usage;
 
 // Re-export public API
 pub use agent::{Agent, AgentError, AgentExt};
 pub use agents::*;
+pub use compaction::{compact_messages, context_budget, estimate_tokens};
 pub use models::*;
+pub use usage::{model_price, LlmCallUsage, ModelPrice};
 
 // Re-export the ToolExecutor trait for convenience
 pub use tools::ToolExecutor;
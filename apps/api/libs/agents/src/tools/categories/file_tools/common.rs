@@ -1,7 +1,7 @@
 use anyhow::{anyhow, bail, Result};
 use chrono::Utc;
 use database::{
-    enums::Verification,
+    enums::{AuditSurface, Verification},
     models::{DashboardFile, MetricFile},
     organization::get_user_organization_id,
     pool::get_pg_pool,
@@ -9,7 +9,15 @@ use database::{
     types::{data_metadata::DataMetadata, DashboardYml, MetricYml, VersionHistory},
 };
 use indexmap::IndexMap;
-use query_engine::{data_source_query_routes::query_engine::query_engine, data_types::DataType};
+use query_engine::{
+    data_source_query_routes::{
+        query_audit::{audit_query, QueryAuditContext},
+        query_cancellation::CancellationToken,
+        query_engine::query_engine_for_user,
+        query_quotas::QueryQuotaError,
+    },
+    data_types::DataType,
+};
 use serde_json::Value;
 use serde_yaml;
 use tracing::{debug, error, warn};
@@ -39,6 +47,7 @@ pub async fn validate_sql(
     data_source_id: &Uuid,
     data_source_dialect: &str,
     user_id: &Uuid,
+    cancellation: &CancellationToken,
 ) -> Result<(
     String,
     Vec<IndexMap<String, DataType>>,
@@ -97,19 +106,35 @@ pub async fn validate_sql(
     }
 
     // Try to execute the query
-    let query_result = match query_engine(data_source_id, sql, Some(15)).await {
+    let audit_context = QueryAuditContext::new(*user_id, AuditSurface::AgentTool);
+    let query_result = match audit_query(
+        &audit_context,
+        data_source_id,
+        sql,
+        query_engine_for_user(user_id, data_source_id, sql, Some(15), cancellation),
+    )
+    .await
+    {
         Ok(result) => result,
+        // The SQL itself may be fine; rewriting it won't help until the quota frees up
+        Err(e) if e.downcast_ref::<QueryQuotaError>().is_some() => {
+            return Err(anyhow!("SQL could not be run: {}", e))
+        }
         Err(e) => return Err(anyhow!("SQL validation failed: {}", e)),
     };
 
     let num_records = query_result.data.len();
-    let message = if num_records == 0 {
+    let mut message = if num_records == 0 {
         "No records were found".to_string()
     } else if num_records > 13 {
         format!("{} records were returned (showing first 13)", num_records)
     } else {
         format!("{} records were returned", num_records)
     };
+    // Let the agent see complexity and row limit warnings so it can adjust the query
+    if !query_result.warnings.is_empty() {
+        message = format!("{}. Warnings: {}", message, query_result.warnings.join("; "));
+    }
     let return_records = query_result.data.into_iter().take(13).collect();
 
     // Return validated IDs along with other results
@@ -867,6 +892,7 @@ pub async fn process_metric_file(
     data_source_id: Uuid,
     data_source_dialect: String,
     user_id: &Uuid,
+    cancellation: &CancellationToken,
 ) -> Result<
     (
         MetricFile,
@@ -890,7 +916,15 @@ pub async fn process_metric_file(
 
     // Validate SQL and get results + validated dataset IDs
     let (message, results, metadata, validated_dataset_ids) =
-        match validate_sql(&metric_yml.sql, &data_source_id, &data_source_dialect, user_id).await {
+        match validate_sql(
+            &metric_yml.sql,
+            &data_source_id,
+            &data_source_dialect,
+            user_id,
+            cancellation,
+        )
+        .await
+        {
             Ok(results) => results,
             Err(e) => return Err(format!("Invalid SQL query: {}", e)),
         };
@@ -1265,7 +1299,14 @@ mod tests {
     #[tokio::test]
     async fn test_validate_sql_empty() {
         let dataset_id = Uuid::new_v4();
-        let result = validate_sql("", &dataset_id, "sql", &Uuid::new_v4()).await;
+        let result = validate_sql(
+            "",
+            &dataset_id,
+            "sql",
+            &Uuid::new_v4(),
+            &CancellationToken::new(),
+        )
+        .await;
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("cannot be empty"));
     }
@@ -1583,6 +1624,7 @@ async fn process_metric_file_update(
     duration: i64,
     user_id: &Uuid,
     data_source_id: &Uuid,
+    cancellation: &CancellationToken,
 ) -> Result<(
     MetricFile,
     MetricYml,
@@ -1611,7 +1653,7 @@ async fn process_metric_file_update(
     // Check if SQL or metadata has changed
     if file.content.sql != new_yml.sql {
         // SQL changed or metadata missing, perform validation
-        match validate_sql(&new_yml.sql, data_source_id, "sql", user_id).await {
+        match validate_sql(&new_yml.sql, data_source_id, "sql", user_id, cancellation).await {
             Ok((message, validation_results, metadata, validated_ids)) => {
                 // Update file record
                 file.content = new_yml.clone();
@@ -118,6 +118,7 @@ impl ToolExecutor for CreateMetricFilesTool {
                     data_source_id,
                     data_source_dialect,
                     &user_id,
+                    self.agent.query_cancellation(),
                 )
                 .await;
                 (file.name, result)
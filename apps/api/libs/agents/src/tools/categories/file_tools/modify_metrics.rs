@@ -13,7 +13,13 @@ use diesel::{upsert::excluded, ExpressionMethods, QueryDsl};
 use diesel_async::RunQueryDsl;
 use futures::future::join_all;
 use indexmap::IndexMap;
-use query_engine::{data_source_query_routes::query_engine::query_engine, data_types::DataType};
+use query_engine::{
+    data_source_query_routes::{
+        query_cache::invalidate_metric_cache, query_cancellation::CancellationToken,
+        query_engine::query_engine,
+    },
+    data_types::DataType,
+};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tracing::{debug, error, info};
@@ -76,6 +82,7 @@ async fn process_metric_file_update(
     user_id: &Uuid,
     data_source_id: &Uuid,
     data_source_dialect: &str,
+    cancellation: &CancellationToken,
 ) -> Result<(
     MetricFile,
     MetricYml,
@@ -154,7 +161,15 @@ async fn process_metric_file_update(
                 );
             }
 
-            match validate_sql(&new_yml.sql, &data_source_id, &data_source_dialect, user_id).await {
+            match validate_sql(
+                &new_yml.sql,
+                &data_source_id,
+                &data_source_dialect,
+                user_id,
+                cancellation,
+            )
+            .await
+            {
                 Ok((message, validation_results, metadata, validated_dataset_ids)) => {
                     // Update file record
                     file.content = new_yml.clone();
@@ -304,6 +319,7 @@ impl ToolExecutor for ModifyMetricFilesTool {
                                     &user_id, // Pass user_id reference
                                     &data_source_id,
                                     &data_source_dialect,
+                                    self.agent.query_cancellation(),
                                 ).await;
                                 
                                 (file.name, result) // Return file name along with result
@@ -375,6 +391,13 @@ impl ToolExecutor for ModifyMetricFilesTool {
             {
                 Ok(_) => {
                     debug!("Successfully updated metric files with versioning and metadata");
+
+                    // Cached results belong to the previous versions
+                    for metric_file in &batch.files {
+                        if let Err(e) = invalidate_metric_cache(&metric_file.id).await {
+                            tracing::warn!("Failed to invalidate query cache for metric {}: {}", metric_file.id, e);
+                        }
+                    }
                     
                     // --- Insert into metric_files_to_datasets --- 
                     let mut join_table_records = Vec::new();
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 use std::{env, sync::Arc, time::Instant};
-use database::enums::DataSourceType;
+use database::enums::{ColumnPolicyAction, DataSourceType};
 
 use anyhow::{Context, Result};
 use async_trait::async_trait;
@@ -17,13 +17,14 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
-use dataset_security::{get_permissioned_datasets, PermissionedDataset};
+use query_engine::data_source_query_routes::row_level_security::get_row_filters;
+use dataset_security::{get_permissioned_datasets, get_restricted_columns, PermissionedDataset, RestrictedColumn};
 use stored_values;
 
 // Import SemanticLayerSpec
 use semantic_layer::models::Model;
 
-use crate::{agent::Agent, tools::ToolExecutor};
+use crate::{agent::Agent, tools::ToolExecutor, usage::LlmCallUsage};
 
 // NEW: Structure to represent found values with their source information
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Hash)]
@@ -201,6 +202,12 @@ impl ToolExecutor for SearchDataCatalogTool {
         // Get all datasets
         let all_datasets = Self::get_datasets(&user_id).await?;
 
+        // Columns the user may only see masked, or not at all
+        let dataset_ids: Vec<Uuid> = all_datasets.iter().map(|d| d.id).collect();
+        let restricted_columns = get_restricted_columns(&user_id, &dataset_ids)
+            .await
+            .context("Failed to load column policies for data catalog search")?;
+
         // Check if datasets were fetched and are not empty
         if all_datasets.is_empty() {
             info!("No datasets found for the organization or user.");
@@ -219,6 +226,12 @@ impl ToolExecutor for SearchDataCatalogTool {
         let target_data_source_id = all_datasets[0].data_source_id;
         debug!(data_source_id = %target_data_source_id, "Extracted data source ID");
         
+        // Stored values are sampled from whole tables, so none are shown from tables whose
+        // rows are filtered for this user
+        let row_filters = get_row_filters(&user_id, &target_data_source_id)
+            .await
+            .context("Failed to load row filters for data catalog search")?;
+
         // Cache the data_source_id in agent state
         self.agent.set_state_value(
             "data_source_id".to_string(),
@@ -275,8 +288,9 @@ impl ToolExecutor for SearchDataCatalogTool {
         // Generate embeddings for all valid terms concurrently using batching
         let term_embeddings: HashMap<String, Vec<f32>> = if !valid_value_search_terms.is_empty() {
             let embedding_terms = valid_value_search_terms.clone();
+            let embedding_agent = self.agent.clone();
             let embedding_batch_future = tokio::spawn(async move {
-                generate_embeddings_batch(embedding_terms).await
+                generate_embeddings_batch(embedding_terms, &embedding_agent).await
             });
 
             match embedding_batch_future.await? {
@@ -345,9 +359,17 @@ impl ToolExecutor for SearchDataCatalogTool {
             }
         }
         
-        // Flatten all found values into a single list
+        // Flatten all found values into a single list. Stored values are raw column contents, so
+        // none are shown for columns under a column policy.
         let all_found_values: Vec<FoundValueInfo> = found_values_by_term.values()
             .flat_map(|values| values.clone())
+            .filter(|value| !row_filters.filters_table(&value.schema_name, &value.table_name))
+            .filter(|value| {
+                !restricted_columns.iter().any(|column| {
+                    column.table_name.eq_ignore_ascii_case(&value.table_name)
+                        && column.column_name.eq_ignore_ascii_case(&value.column_name)
+                })
+            })
             .collect();
         
         debug!(value_count = all_found_values.len(), "Total found values across all terms after search");
@@ -357,10 +379,31 @@ impl ToolExecutor for SearchDataCatalogTool {
         // Convert all datasets to search results
         let all_search_results: Vec<DatasetSearchResult> = all_datasets
             .into_iter()
-            .map(|dataset| DatasetSearchResult {
-                id: dataset.id,
-                name: Some(dataset.name),
-                yml_content: dataset.yml_content,
+            .map(|dataset| {
+                let dataset_restrictions: Vec<&RestrictedColumn> = restricted_columns
+                    .iter()
+                    .filter(|column| column.dataset_id == dataset.id)
+                    .collect();
+
+                // Denied columns are removed so the agent never sees them
+                let yml_content = match dataset.yml_content {
+                    Some(yml) if !dataset_restrictions.is_empty() => {
+                        match apply_column_policies_to_yml(&yml, &dataset_restrictions) {
+                            Ok(restricted_yml) => Some(restricted_yml),
+                            Err(e) => {
+                                warn!(dataset_id = %dataset.id, error = %e, "Failed to apply column policies to dataset YML, withholding it");
+                                None
+                            }
+                        }
+                    }
+                    yml => yml,
+                };
+
+                DatasetSearchResult {
+                    id: dataset.id,
+                    name: Some(dataset.name),
+                    yml_content,
+                }
             })
             .collect();
 
@@ -492,7 +535,10 @@ async fn get_search_data_catalog_description() -> String {
 }
 
 // NEW: Helper function to generate embeddings for multiple texts in a batch
-async fn generate_embeddings_batch(texts: Vec<String>) -> Result<Vec<(String, Vec<f32>)>> {
+async fn generate_embeddings_batch(
+    texts: Vec<String>,
+    agent: &Agent,
+) -> Result<Vec<(String, Vec<f32>)>> {
     if texts.is_empty() {
         return Ok(vec![]);
     }
@@ -513,7 +559,13 @@ async fn generate_embeddings_batch(texts: Vec<String>) -> Result<Vec<(String, Ve
         .generate_embeddings(embedding_request)
         .await
         .context("Failed to generate embeddings batch")?;
-        
+    agent
+        .record_usage(
+            LlmCallUsage::embeddings(&embedding_response.model, &embedding_response.usage)
+                .for_tool("search_data_catalog"),
+        )
+        .await;
+
     if embedding_response.data.len() != texts.len() {
         warn!(
             "Mismatch between input text count ({}) and returned embedding count ({})",
@@ -751,6 +803,73 @@ fn extract_database_info_from_yaml(yml_content: &str) -> Result<HashMap<String,
     Ok(database_info)
 }
 
+/// Removes denied columns from a dataset's YML, along with the metrics, filters and relationships
+/// built on them, and marks masked columns so the agent knows their values aren't the real ones.
+fn apply_column_policies_to_yml(
+    yml_content: &str,
+    restricted_columns: &[&RestrictedColumn],
+) -> Result<String> {
+    let mut root_yaml_val: serde_yaml::Value = serde_yaml::from_str(yml_content)
+        .context("Failed to parse dataset YAML for applying column policies")?;
+
+    let denied: Vec<&str> = restricted_columns
+        .iter()
+        .filter(|column| column.action == ColumnPolicyAction::Deny)
+        .map(|column| column.column_name.as_str())
+        .collect();
+    let is_denied = |name: &str| denied.iter().any(|d| d.eq_ignore_ascii_case(name));
+    let references_denied = |expr: &str| {
+        expr.split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .any(&is_denied)
+    };
+
+    let restrict_model = |model_yaml: &mut serde_yaml::Value| {
+        for key in ["dimensions", "measures"] {
+            if let Some(columns) = model_yaml.get_mut(key).and_then(|c| c.as_sequence_mut()) {
+                columns.retain(|column| {
+                    !column.get("name").and_then(|n| n.as_str()).is_some_and(&is_denied)
+                });
+                for column in columns.iter_mut() {
+                    let name = column.get("name").and_then(|n| n.as_str()).unwrap_or_default();
+                    let masked_action = restricted_columns
+                        .iter()
+                        .find(|r| r.column_name.eq_ignore_ascii_case(name))
+                        .map(|r| r.action);
+                    if let (Some(action), Some(column_map)) = (masked_action, column.as_mapping_mut()) {
+                        if let Ok(action_value) = serde_yaml::to_value(action) {
+                            column_map.insert(serde_yaml::Value::String("masking".to_string()), action_value);
+                        }
+                    }
+                }
+            }
+        }
+
+        for key in ["metrics", "filters"] {
+            if let Some(items) = model_yaml.get_mut(key).and_then(|i| i.as_sequence_mut()) {
+                items.retain(|item| {
+                    !item.get("expr").and_then(|e| e.as_str()).is_some_and(&references_denied)
+                });
+            }
+        }
+
+        if let Some(relationships) = model_yaml.get_mut("relationships").and_then(|r| r.as_sequence_mut()) {
+            relationships.retain(|relationship| {
+                !["source_col", "ref_col"].iter().any(|key| {
+                    relationship.get(*key).and_then(|c| c.as_str()).is_some_and(&is_denied)
+                })
+            });
+        }
+    };
+
+    match root_yaml_val.get_mut("models").and_then(|m| m.as_sequence_mut()) {
+        Some(models) => models.iter_mut().for_each(restrict_model),
+        None => restrict_model(&mut root_yaml_val),
+    }
+
+    serde_yaml::to_string(&root_yaml_val)
+        .context("Failed to convert YAML with column policies applied back to string")
+}
+
 // Helper function to inject values into a single model represented by serde_yaml::Value
 fn inject_values_into_single_model_yaml(
     model_yaml: &mut serde_yaml::Value,
@@ -908,4 +1027,51 @@ async fn inject_prefound_values_into_yml(
     
     serde_yaml::to_string(&root_yaml_val)
         .context("Failed to convert updated YAML with injected values back to string")
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn restricted(column_name: &str, action: ColumnPolicyAction) -> RestrictedColumn {
+        RestrictedColumn {
+            dataset_id: Uuid::new_v4(),
+            table_name: "customers".to_string(),
+            column_name: column_name.to_string(),
+            action,
+        }
+    }
+
+    #[test]
+    fn test_column_policies_hide_denied_columns_from_yml() {
+        let yml = r#"
+name: customers
+dimensions:
+  - name: id
+  - name: email
+  - name: ssn
+measures:
+  - name: lifetime_value
+metrics:
+  - name: verified_customers
+    expr: "COUNT(CASE WHEN ssn IS NOT NULL THEN id END)"
+  - name: customer_count
+    expr: "COUNT(id)"
+relationships:
+  - name: identity
+    source_col: ssn
+    ref_col: ssn
+"#;
+        let ssn = restricted("ssn", ColumnPolicyAction::Deny);
+        let email = restricted("email", ColumnPolicyAction::Hash);
+
+        let restricted_yml = apply_column_policies_to_yml(yml, &[&ssn, &email]).unwrap();
+        let model: serde_yaml::Value = serde_yaml::from_str(&restricted_yml).unwrap();
+
+        assert!(!restricted_yml.contains("ssn"), "{}", restricted_yml);
+        let dimensions = model["dimensions"].as_sequence().unwrap();
+        assert_eq!(dimensions.len(), 2);
+        assert_eq!(dimensions[1]["masking"].as_str(), Some("hash"));
+        assert_eq!(model["metrics"].as_sequence().unwrap().len(), 1);
+        assert!(model["relationships"].as_sequence().unwrap().is_empty());
+    }
+}
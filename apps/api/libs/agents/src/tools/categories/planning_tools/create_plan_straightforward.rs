@@ -47,8 +47,8 @@ impl ToolExecutor for CreatePlanStraightforward {
 
         match generate_todos_from_plan(
             &params.plan,
-            self.agent.get_user_id(),
-            self.agent.get_session_id(),
+            &self.agent,
+            &self.get_name(),
         )
         .await
         {
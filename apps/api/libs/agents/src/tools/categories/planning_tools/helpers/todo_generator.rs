@@ -1,18 +1,18 @@
-use std::env;
-
 use anyhow::Result;
 use litellm::{AgentMessage, ChatCompletionRequest, LiteLLMClient, Metadata, ResponseFormat};
 use serde_json::Value;
 use tracing::{error, warn};
 use uuid::Uuid;
 
+use crate::{agent::Agent, agents::modes::model_routes::ModelRoutes, usage::LlmCallUsage};
+
 /// Generates a list of todo items (as JSON Values for agent state) from a plan string using an LLM.
 ///
 /// # Arguments
 ///
 /// * `plan` - The plan string generated by the primary LLM.
-/// * `user_id` - The ID of the user.
-/// * `session_id` - The ID of the current session.
+/// * `agent` - The agent the plan was made for. The LLM call's usage is recorded on it.
+/// * `tool_name` - The name of the planning tool, which the usage is attributed to.
 ///
 /// # Returns
 ///
@@ -20,8 +20,8 @@ use uuid::Uuid;
 /// (`{"completed": false, "todo": "..."}`), or an error if generation or parsing fails.
 pub async fn generate_todos_from_plan(
     plan: &str,
-    user_id: Uuid,
-    session_id: Uuid,
+    agent: &Agent,
+    tool_name: &str,
 ) -> Result<Vec<Value>> {
     let llm_client = LiteLLMClient::new(None, None);
 
@@ -63,22 +63,20 @@ Example Output for the above plan: `["Create line chart visualization 'Daily Tra
         plan
     );
 
-    let model = if env::var("ENVIRONMENT").unwrap_or_else(|_| "development".to_string()) == "local" {
-        "gpt-4.1-nano".to_string()
-    } else {
-        "gemini-2.0-flash-001".to_string()
-    };
+    let model = ModelRoutes::load_for_user(&agent.get_user_id())
+        .await
+        .auxiliary_model();
 
     let request = ChatCompletionRequest {
-        model,
+        model: model.clone(),
         messages: vec![AgentMessage::User { id: None, content: prompt, name: None }],
         stream: Some(false),
         response_format: Some(ResponseFormat { type_: "json_object".to_string(), json_schema: None }),
         store: Some(true),
         metadata: Some(Metadata {
             generation_name: "generate_todos_from_plan".to_string(),
-            user_id: user_id.to_string(),
-            session_id: session_id.to_string(),
+            user_id: agent.get_user_id().to_string(),
+            session_id: agent.get_session_id().to_string(),
             trace_id: Uuid::new_v4().to_string(),
         }),
         max_completion_tokens: Some(1024),
@@ -87,6 +85,9 @@ Example Output for the above plan: `["Create line chart visualization 'Daily Tra
     };
 
     let response = llm_client.chat_completion(request).await?;
+    agent
+        .record_usage(LlmCallUsage::chat(model, &response.usage).for_tool(tool_name))
+        .await;
 
     let content = match response.choices.get(0).and_then(|c| c.message.get_content()) {
         Some(content) => content,
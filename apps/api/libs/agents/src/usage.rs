@@ -0,0 +1,179 @@
+//! Token usage and cost of the LLM calls made during an agent run.
+//!
+//! Costs are computed from list prices in US dollars per million tokens, which is the same
+//! number as millionths of a dollar per token. Deployments that serve models under their own
+//! names, or pay other prices, can set `LLM_MODEL_PRICES` to a JSON object such as
+//! `{"azure/analysis": {"input": 2.0, "output": 8.0}}`.
+
+use std::{collections::HashMap, env};
+
+use database::enums::ModelRouteMode;
+use litellm::{EmbeddingUsage, Usage};
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+
+/// List price of a model in US dollars per million tokens
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct ModelPrice {
+    pub input: f64,
+    #[serde(default)]
+    pub output: f64,
+}
+
+const fn price(input: f64, output: f64) -> ModelPrice {
+    ModelPrice { input, output }
+}
+
+// Matched against model names with the longest match winning, so "gpt-4.1-mini" isn't priced
+// as "gpt-4.1" and provider prefixes like "azure/" or "bedrock/anthropic." don't matter
+const MODEL_PRICES: &[(&str, ModelPrice)] = &[
+    ("gpt-4.1", price(2.0, 8.0)),
+    ("gpt-4.1-mini", price(0.4, 1.6)),
+    ("gpt-4.1-nano", price(0.1, 0.4)),
+    ("gpt-4o", price(2.5, 10.0)),
+    ("gpt-4o-mini", price(0.15, 0.6)),
+    ("o3", price(2.0, 8.0)),
+    ("o3-mini", price(1.1, 4.4)),
+    ("o4-mini", price(1.1, 4.4)),
+    ("claude-3-5-haiku", price(0.8, 4.0)),
+    ("claude-3-5-sonnet", price(3.0, 15.0)),
+    ("claude-3-7-sonnet", price(3.0, 15.0)),
+    ("claude-sonnet-4", price(3.0, 15.0)),
+    ("claude-opus-4", price(15.0, 75.0)),
+    ("gemini-2.0-flash", price(0.1, 0.4)),
+    ("gemini-2.5-flash", price(0.3, 2.5)),
+    ("gemini-2.5-pro", price(1.25, 10.0)),
+    ("text-embedding-3-small", price(0.02, 0.0)),
+    ("text-embedding-3-large", price(0.13, 0.0)),
+];
+
+static PRICE_OVERRIDES: Lazy<HashMap<String, ModelPrice>> = Lazy::new(|| {
+    let Ok(prices) = env::var("LLM_MODEL_PRICES") else {
+        return HashMap::new();
+    };
+
+    serde_json::from_str(&prices).unwrap_or_else(|e| {
+        tracing::error!("Ignoring LLM_MODEL_PRICES, it isn't a valid price list: {}", e);
+        HashMap::new()
+    })
+});
+
+/// The price of a model, from `LLM_MODEL_PRICES` if it's listed there
+pub fn model_price(model: &str) -> Option<ModelPrice> {
+    if let Some(price) = PRICE_OVERRIDES.get(model) {
+        return Some(*price);
+    }
+
+    let model = model.to_lowercase();
+    MODEL_PRICES
+        .iter()
+        .filter(|(name, _)| model.contains(name))
+        .max_by_key(|(name, _)| name.len())
+        .map(|(_, price)| *price)
+}
+
+/// Token usage of one LLM call made during an agent run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LlmCallUsage {
+    pub model: String,
+    /// The mode the agent was in when the call was made
+    pub mode: Option<ModelRouteMode>,
+    /// The tool that made the call, or None for the agent's own calls
+    pub tool_name: Option<String>,
+    pub prompt_tokens: i32,
+    pub completion_tokens: i32,
+    /// Included in `completion_tokens`
+    pub reasoning_tokens: i32,
+    pub total_tokens: i32,
+}
+
+impl LlmCallUsage {
+    pub fn chat(model: impl Into<String>, usage: &Usage) -> Self {
+        Self {
+            model: model.into(),
+            mode: None,
+            tool_name: None,
+            prompt_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            reasoning_tokens: usage
+                .completion_tokens_details
+                .as_ref()
+                .map_or(0, |details| details.reasoning_tokens),
+            total_tokens: usage.total_tokens,
+        }
+    }
+
+    pub fn embeddings(model: impl Into<String>, usage: &EmbeddingUsage) -> Self {
+        Self {
+            model: model.into(),
+            mode: None,
+            tool_name: None,
+            prompt_tokens: usage.prompt_tokens as i32,
+            completion_tokens: 0,
+            reasoning_tokens: 0,
+            total_tokens: usage.total_tokens as i32,
+        }
+    }
+
+    pub fn in_mode(mut self, mode: Option<ModelRouteMode>) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn for_tool(mut self, tool_name: impl Into<String>) -> Self {
+        self.tool_name = Some(tool_name.into());
+        self
+    }
+
+    /// What the call cost in millionths of a US dollar. Calls to models without a known
+    /// price are counted as free.
+    pub fn cost_micros(&self) -> i64 {
+        match model_price(&self.model) {
+            Some(price) => (self.prompt_tokens as f64 * price.input
+                + self.completion_tokens as f64 * price.output)
+                .round() as i64,
+            None => {
+                tracing::warn!("No price known for model {}, counting its usage as free", self.model);
+                0
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usage(model: &str, prompt_tokens: i32, completion_tokens: i32) -> LlmCallUsage {
+        LlmCallUsage {
+            model: model.to_string(),
+            mode: None,
+            tool_name: None,
+            prompt_tokens,
+            completion_tokens,
+            reasoning_tokens: 0,
+            total_tokens: prompt_tokens + completion_tokens,
+        }
+    }
+
+    #[test]
+    fn test_model_price_prefers_the_most_specific_name() {
+        assert_eq!(model_price("gpt-4.1-mini"), Some(price(0.4, 1.6)));
+        assert_eq!(model_price("azure/gpt-4.1"), Some(price(2.0, 8.0)));
+        assert_eq!(
+            model_price("bedrock/us.anthropic.claude-3-7-sonnet-20250219-v1:0"),
+            Some(price(3.0, 15.0))
+        );
+        assert_eq!(model_price("gemini-2.0-flash-001"), Some(price(0.1, 0.4)));
+        assert_eq!(model_price("my-fine-tune"), None);
+    }
+
+    #[test]
+    fn test_cost_micros() {
+        // 1,000 prompt tokens at $1.10/M and 500 completion tokens at $4.40/M
+        assert_eq!(usage("o4-mini", 1_000, 500).cost_micros(), 3_300);
+        assert_eq!(usage("text-embedding-3-small", 12, 0).cost_micros(), 0);
+        assert_eq!(usage("text-embedding-3-small", 100, 0).cost_micros(), 2);
+        assert_eq!(usage("my-fine-tune", 1_000, 500).cost_micros(), 0);
+    }
+}
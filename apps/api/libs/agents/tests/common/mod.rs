@@ -0,0 +1,356 @@
+//! Harness for driving `BusterMultiAgent` end to end against the local Postgres.
+//!
+//! Every run gets its own organization, workspace admin, data source and dataset. The data
+//! source is a stand-in: it points back at the test database, where `agent_replay.orders`
+//! holds a small fixed table, so the SQL the agent writes runs for real and returns the same
+//! rows every time.
+//!
+//! LLM traffic is replayed from `tests/fixtures` by default. To record or refresh fixtures,
+//! run the tests with a real LLM configured:
+//!
+//! ```text
+//! LLM_TRAFFIC_MODE=record LLM_API_KEY=... LLM_BASE_URL=... cargo test -p agents
+//! ```
+
+use std::{collections::HashSet, env, path::PathBuf, sync::Arc, time::Duration};
+
+use agents::{AgentThread, BusterMultiAgent};
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use database::{
+    enums::{
+        DataSourceOnboardingStatus, DataSourceType, DatasetType, SharingSetting,
+        UserOrganizationRole, UserOrganizationStatus,
+    },
+    models::{DataSource, Dataset, Organization, User, UserToOrganization},
+    pool::get_pg_pool,
+    schema::{
+        asset_permissions, dashboard_files, data_sources, datasets, metric_files, organizations,
+        users, users_to_organizations,
+    },
+    vault::{create_secret, delete_secret},
+};
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use litellm::{AgentMessage, MessageProgress};
+use uuid::Uuid;
+
+/// How long a single agent run may take before the test fails
+const RUN_TIMEOUT: Duration = Duration::from_secs(300);
+
+const ORDERS_YML: &str = r#"name: orders
+description: One row per customer order, with the order's date, region and revenue.
+schema: agent_replay
+dimensions:
+  - name: order_date
+    description: The day the order was placed
+    type: date
+  - name: region
+    description: The sales region the order was placed in
+    type: string
+    searchable: true
+measures:
+  - name: revenue
+    description: The order's revenue in USD
+    type: number
+"#;
+
+/// Points LLM traffic at the recorded fixtures. Tests replay them unless `LLM_TRAFFIC_MODE` is
+/// already set, and the integrations that would call out to other services are disabled.
+pub fn configure_llm_traffic() {
+    if env::var("LLM_TRAFFIC_MODE").is_err() {
+        env::set_var("LLM_TRAFFIC_MODE", "replay");
+    }
+    if env::var("LLM_FIXTURES_DIR").is_err() {
+        env::set_var("LLM_FIXTURES_DIR", fixtures_dir());
+    }
+
+    env::remove_var("USE_BRAINTRUST_PROMPTS");
+    env::remove_var("RAINDROP_WRITE_KEY");
+}
+
+fn fixtures_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+}
+
+/// What the agent did during a run
+#[derive(Debug, Default)]
+pub struct Transcript {
+    /// Names of the tools the agent called, in the order they completed
+    pub tool_calls: Vec<String>,
+    /// Text of the assistant's final message
+    pub response: Option<String>,
+}
+
+impl Transcript {
+    pub fn called(&self, tool_name: &str) -> bool {
+        self.tool_calls.iter().any(|name| name == tool_name)
+    }
+}
+
+pub struct AgentHarness {
+    pub organization_id: Uuid,
+    pub user_id: Uuid,
+    pub data_source_id: Uuid,
+    pub dataset_id: Uuid,
+}
+
+impl AgentHarness {
+    /// Seeds an organization with a workspace admin and the stand-in data source
+    pub async fn new() -> Result<Self> {
+        let mut conn = get_pg_pool().get().await?;
+        let now = Utc::now();
+
+        let organization_id = Uuid::new_v4();
+        let user_id = Uuid::new_v4();
+        let data_source_id = Uuid::new_v4();
+        let dataset_id = Uuid::new_v4();
+
+        diesel::insert_into(organizations::table)
+            .values(&Organization {
+                id: organization_id,
+                name: "Agent Replay".to_string(),
+                domain: None,
+                created_at: now,
+                updated_at: now,
+                deleted_at: None,
+                payment_required: false,
+                domains: None,
+                restrict_new_user_invitations: false,
+                default_role: UserOrganizationRole::RestrictedQuerier,
+                organization_color_palettes: serde_json::json!([]),
+            })
+            .execute(&mut conn)
+            .await?;
+
+        diesel::insert_into(users::table)
+            .values(&User {
+                id: user_id,
+                email: format!("agent-replay-{}@example.com", user_id),
+                name: Some("Agent Replay".to_string()),
+                config: serde_json::json!({}),
+                created_at: now,
+                updated_at: now,
+                attributes: serde_json::json!({}),
+                avatar_url: None,
+            })
+            .execute(&mut conn)
+            .await?;
+
+        diesel::insert_into(users_to_organizations::table)
+            .values(&UserToOrganization {
+                user_id,
+                organization_id,
+                role: UserOrganizationRole::WorkspaceAdmin,
+                sharing_setting: SharingSetting::None,
+                edit_sql: true,
+                upload_csv: true,
+                export_assets: true,
+                email_slack_enabled: true,
+                created_at: now,
+                updated_at: now,
+                deleted_at: None,
+                created_by: user_id,
+                updated_by: user_id,
+                deleted_by: None,
+                status: UserOrganizationStatus::Active,
+            })
+            .execute(&mut conn)
+            .await?;
+
+        seed_orders_table().await?;
+
+        diesel::insert_into(data_sources::table)
+            .values(&DataSource {
+                id: data_source_id,
+                name: "Agent Replay Warehouse".to_string(),
+                type_: DataSourceType::Postgres,
+                secret_id: data_source_id,
+                onboarding_status: DataSourceOnboardingStatus::Completed,
+                onboarding_error: None,
+                organization_id,
+                created_by: user_id,
+                updated_by: user_id,
+                created_at: now,
+                updated_at: now,
+                deleted_at: None,
+                env: "dev".to_string(),
+            })
+            .execute(&mut conn)
+            .await?;
+        create_secret(&stand_in_credentials()?, &data_source_id.to_string()).await?;
+
+        diesel::insert_into(datasets::table)
+            .values(&Dataset {
+                id: dataset_id,
+                name: "orders".to_string(),
+                database_name: "orders".to_string(),
+                when_to_use: None,
+                when_not_to_use: None,
+                type_: DatasetType::Table,
+                definition: String::new(),
+                schema: "agent_replay".to_string(),
+                enabled: true,
+                imported: false,
+                data_source_id,
+                organization_id,
+                created_by: user_id,
+                updated_by: user_id,
+                created_at: now,
+                updated_at: now,
+                deleted_at: None,
+                model: None,
+                yml_file: Some(ORDERS_YML.to_string()),
+                database_identifier: None,
+            })
+            .execute(&mut conn)
+            .await?;
+
+        Ok(Self {
+            organization_id,
+            user_id,
+            data_source_id,
+            dataset_id,
+        })
+    }
+
+    /// Runs the agent on a new chat and collects what it did
+    pub async fn run(&self, prompt: &str) -> Result<Transcript> {
+        let agent = Arc::new(BusterMultiAgent::new(self.user_id, Uuid::new_v4(), false).await?);
+        let mut thread = AgentThread::new(None, self.user_id, vec![AgentMessage::user(prompt)]);
+
+        let mut rx = agent.run(&mut thread).await?;
+        let mut transcript = Transcript::default();
+        let mut completed_tool_calls = HashSet::new();
+
+        let collect = async {
+            while let Ok(message) = rx.recv().await {
+                match message.map_err(|e| anyhow!("Agent failed: {}", e.0))? {
+                    AgentMessage::Done => break,
+                    AgentMessage::Tool {
+                        tool_call_id,
+                        name,
+                        progress: MessageProgress::Complete,
+                        ..
+                    } => {
+                        if completed_tool_calls.insert(tool_call_id) {
+                            transcript.tool_calls.extend(name);
+                        }
+                    }
+                    AgentMessage::Assistant {
+                        content: Some(content),
+                        progress: MessageProgress::Complete,
+                        ..
+                    } => transcript.response = Some(content),
+                    _ => {}
+                }
+            }
+            Ok::<_, anyhow::Error>(())
+        };
+
+        let result = tokio::time::timeout(RUN_TIMEOUT, collect).await;
+        agent.shutdown().await?;
+
+        // A tool may have carried on without the response, so the run can't be trusted
+        let missing = litellm::take_missing_fixtures();
+        if !missing.is_empty() {
+            let missing: Vec<String> = missing
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            return Err(anyhow!(
+                "No recorded LLM responses for {}. Record them with LLM_TRAFFIC_MODE=record.",
+                missing.join(", ")
+            ));
+        }
+        result.map_err(|_| anyhow!("Agent run timed out after {:?}", RUN_TIMEOUT))??;
+
+        Ok(transcript)
+    }
+
+    /// Removes everything the harness and the agent created
+    pub async fn cleanup(&self) -> Result<()> {
+        let mut conn = get_pg_pool().get().await?;
+
+        diesel::delete(asset_permissions::table)
+            .filter(asset_permissions::created_by.eq(self.user_id))
+            .execute(&mut conn)
+            .await?;
+        diesel::delete(dashboard_files::table)
+            .filter(dashboard_files::organization_id.eq(self.organization_id))
+            .execute(&mut conn)
+            .await?;
+        diesel::delete(metric_files::table)
+            .filter(metric_files::organization_id.eq(self.organization_id))
+            .execute(&mut conn)
+            .await?;
+        diesel::delete(datasets::table)
+            .filter(datasets::id.eq(self.dataset_id))
+            .execute(&mut conn)
+            .await?;
+        diesel::delete(data_sources::table)
+            .filter(data_sources::id.eq(self.data_source_id))
+            .execute(&mut conn)
+            .await?;
+        delete_secret(&self.data_source_id).await?;
+        diesel::delete(users_to_organizations::table)
+            .filter(users_to_organizations::organization_id.eq(self.organization_id))
+            .execute(&mut conn)
+            .await?;
+        diesel::delete(users::table)
+            .filter(users::id.eq(self.user_id))
+            .execute(&mut conn)
+            .await?;
+        diesel::delete(organizations::table)
+            .filter(organizations::id.eq(self.organization_id))
+            .execute(&mut conn)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// Creates the table the stand-in data source serves. The rows never change so the query
+/// results the agent sees, and with them the fixtures it replays, stay stable.
+async fn seed_orders_table() -> Result<()> {
+    let mut conn = get_pg_pool().get().await?;
+
+    for statement in [
+        "CREATE SCHEMA IF NOT EXISTS agent_replay",
+        "CREATE TABLE IF NOT EXISTS agent_replay.orders (
+            id INTEGER PRIMARY KEY,
+            order_date DATE NOT NULL,
+            region TEXT NOT NULL,
+            revenue NUMERIC(10, 2) NOT NULL
+        )",
+        "INSERT INTO agent_replay.orders (id, order_date, region, revenue) VALUES
+            (1, '2024-01-05', 'North', 120.00),
+            (2, '2024-01-18', 'South', 80.50),
+            (3, '2024-02-02', 'North', 210.00),
+            (4, '2024-02-20', 'West', 45.25),
+            (5, '2024-03-11', 'South', 150.00),
+            (6, '2024-03-29', 'West', 99.99)
+        ON CONFLICT (id) DO NOTHING",
+    ] {
+        diesel::sql_query(statement).execute(&mut conn).await?;
+    }
+
+    Ok(())
+}
+
+/// Credentials that point the stand-in data source at the test database
+fn stand_in_credentials() -> Result<String> {
+    let url = reqwest::Url::parse(&env::var("DATABASE_URL")?)?;
+
+    Ok(serde_json::json!({
+        "host": url.host_str().unwrap_or("localhost"),
+        "port": url.port().unwrap_or(5432),
+        "username": url.username(),
+        "password": url.password().unwrap_or_default(),
+        "default_database": url.path().trim_start_matches('/'),
+        "default_schema": "agent_replay",
+    })
+    .to_string())
+}
@@ -0,0 +1 @@
+pub mod revenue_by_month;
@@ -0,0 +1,26 @@
+use anyhow::Result;
+
+use crate::common::AgentHarness;
+
+#[tokio::test]
+async fn test_revenue_by_month_creates_a_metric() -> Result<()> {
+    let harness = AgentHarness::new().await?;
+
+    let transcript = harness.run("Show me revenue by month").await;
+    harness.cleanup().await?;
+    let transcript = transcript?;
+
+    assert_eq!(
+        transcript.tool_calls.first().map(String::as_str),
+        Some("search_data_catalog")
+    );
+    assert!(transcript.called("create_plan_straightforward"));
+    assert!(transcript.called("create_metrics"));
+    assert_eq!(
+        transcript.tool_calls.last().map(String::as_str),
+        Some("done")
+    );
+    assert!(transcript.response.is_some());
+
+    Ok(())
+}
@@ -536,6 +536,10 @@ pub enum DataSourceType {
     Snowflake,
     SqlServer,
     Supabase,
+    DuckDb,
+    Sqlite,
+    ClickHouse,
+    Trino,
 }
 
 impl DataSourceType {
@@ -550,6 +554,10 @@ impl DataSourceType {
             "snowflake" => Some(DataSourceType::Snowflake),
             "sqlserver" => Some(DataSourceType::SqlServer),
             "supabase" => Some(DataSourceType::Supabase),
+            "duckdb" => Some(DataSourceType::DuckDb),
+            "sqlite" => Some(DataSourceType::Sqlite),
+            "clickhouse" => Some(DataSourceType::ClickHouse),
+            "trino" => Some(DataSourceType::Trino),
             _ => None,
         }
     }
@@ -565,6 +573,10 @@ impl DataSourceType {
             DataSourceType::Snowflake => "snowflake",
             DataSourceType::SqlServer => "sqlserver",
             DataSourceType::Supabase => "supabase",
+            DataSourceType::DuckDb => "duckdb",
+            DataSourceType::Sqlite => "sqlite",
+            DataSourceType::ClickHouse => "clickhouse",
+            DataSourceType::Trino => "trino",
         }
     }
 
@@ -579,6 +591,10 @@ impl DataSourceType {
             DataSourceType::Snowflake => "snowflake",
             DataSourceType::SqlServer => "sqlserver",
             DataSourceType::Supabase => "supabase",
+            DataSourceType::DuckDb => "duckdb",
+            DataSourceType::Sqlite => "sqlite",
+            DataSourceType::ClickHouse => "clickhouse",
+            DataSourceType::Trino => "trino",
         })
     }
 }
@@ -603,6 +619,10 @@ impl ToSql<Text, Pg> for DataSourceType {
             DataSourceType::Snowflake => out.write_all(b"snowflake")?,
             DataSourceType::SqlServer => out.write_all(b"sqlserver")?,
             DataSourceType::Supabase => out.write_all(b"supabase")?,
+            DataSourceType::DuckDb => out.write_all(b"duckdb")?,
+            DataSourceType::Sqlite => out.write_all(b"sqlite")?,
+            DataSourceType::ClickHouse => out.write_all(b"clickhouse")?,
+            DataSourceType::Trino => out.write_all(b"trino")?,
         }
         Ok(IsNull::No)
     }
@@ -620,6 +640,10 @@ impl FromSql<Text, Pg> for DataSourceType {
             b"snowflake" => Ok(DataSourceType::Snowflake),
             b"sqlserver" => Ok(DataSourceType::SqlServer),
             b"supabase" => Ok(DataSourceType::Supabase),
+            b"duckdb" => Ok(DataSourceType::DuckDb),
+            b"sqlite" => Ok(DataSourceType::Sqlite),
+            b"clickhouse" => Ok(DataSourceType::ClickHouse),
+            b"trino" => Ok(DataSourceType::Trino),
             _ => Err("Unrecognized enum variant".into()),
         }
     }
@@ -843,3 +867,378 @@ impl FromSql<sql_types::WorkspaceSharingEnum, Pg> for WorkspaceSharing {
         }
     }
 }
+
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    diesel::AsExpression,
+    diesel::FromSqlRow,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryPolicyEnforcement {
+    Warn,
+    Reject,
+}
+
+impl ToSql<Text, Pg> for QueryPolicyEnforcement {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            QueryPolicyEnforcement::Warn => out.write_all(b"warn")?,
+            QueryPolicyEnforcement::Reject => out.write_all(b"reject")?,
+        }
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for QueryPolicyEnforcement {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"warn" => Ok(QueryPolicyEnforcement::Warn),
+            b"reject" => Ok(QueryPolicyEnforcement::Reject),
+            _ => Err("Unrecognized QueryPolicyEnforcement variant".into()),
+        }
+    }
+}
+
+/// What a column policy does to a dataset column. Variants are ordered from least to most
+/// restrictive; when several policies apply to a user, the most restrictive one wins.
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    diesel::AsExpression,
+    diesel::FromSqlRow,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnPolicyAction {
+    Partial,
+    Hash,
+    Null,
+    Deny,
+}
+
+impl ToSql<Text, Pg> for ColumnPolicyAction {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            ColumnPolicyAction::Partial => out.write_all(b"partial")?,
+            ColumnPolicyAction::Hash => out.write_all(b"hash")?,
+            ColumnPolicyAction::Null => out.write_all(b"null")?,
+            ColumnPolicyAction::Deny => out.write_all(b"deny")?,
+        }
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for ColumnPolicyAction {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"partial" => Ok(ColumnPolicyAction::Partial),
+            b"hash" => Ok(ColumnPolicyAction::Hash),
+            b"null" => Ok(ColumnPolicyAction::Null),
+            b"deny" => Ok(ColumnPolicyAction::Deny),
+            _ => Err("Unrecognized ColumnPolicyAction variant".into()),
+        }
+    }
+}
+
+/// How SQL written against the semantic layer is validated before its metrics and filters
+/// are substituted
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    diesel::AsExpression,
+    diesel::FromSqlRow,
+)]
+#[diesel(sql_type = sql_types::SemanticValidationModeEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SemanticValidationMode {
+    Strict,
+    Flexible,
+}
+
+impl ToSql<sql_types::SemanticValidationModeEnum, Pg> for SemanticValidationMode {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            SemanticValidationMode::Strict => out.write_all(b"strict")?,
+            SemanticValidationMode::Flexible => out.write_all(b"flexible")?,
+        }
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<sql_types::SemanticValidationModeEnum, Pg> for SemanticValidationMode {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"strict" => Ok(SemanticValidationMode::Strict),
+            b"flexible" => Ok(SemanticValidationMode::Flexible),
+            _ => Err("Unrecognized SemanticValidationMode variant".into()),
+        }
+    }
+}
+
+/// What an audit log entry records
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    diesel::AsExpression,
+    diesel::FromSqlRow,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditEventType {
+    Query,
+    AssetView,
+    AssetShare,
+}
+
+impl AuditEventType {
+    pub fn to_str(&self) -> &'static str {
+        match *self {
+            AuditEventType::Query => "query",
+            AuditEventType::AssetView => "asset_view",
+            AuditEventType::AssetShare => "asset_share",
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for AuditEventType {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.to_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for AuditEventType {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"query" => Ok(AuditEventType::Query),
+            b"asset_view" => Ok(AuditEventType::AssetView),
+            b"asset_share" => Ok(AuditEventType::AssetShare),
+            _ => Err("Unrecognized AuditEventType variant".into()),
+        }
+    }
+}
+
+/// Where an audited query or asset access originated
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    diesel::AsExpression,
+    diesel::FromSqlRow,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditSurface {
+    AgentTool,
+    MetricData,
+    RunSql,
+    PublicShare,
+    Api,
+}
+
+impl AuditSurface {
+    pub fn to_str(&self) -> &'static str {
+        match *self {
+            AuditSurface::AgentTool => "agent_tool",
+            AuditSurface::MetricData => "metric_data",
+            AuditSurface::RunSql => "run_sql",
+            AuditSurface::PublicShare => "public_share",
+            AuditSurface::Api => "api",
+        }
+    }
+}
+
+impl ToSql<Text, Pg> for AuditSurface {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        out.write_all(self.to_str().as_bytes())?;
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for AuditSurface {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"agent_tool" => Ok(AuditSurface::AgentTool),
+            b"metric_data" => Ok(AuditSurface::MetricData),
+            b"run_sql" => Ok(AuditSurface::RunSql),
+            b"public_share" => Ok(AuditSurface::PublicShare),
+            b"api" => Ok(AuditSurface::Api),
+            _ => Err("Unrecognized AuditSurface variant".into()),
+        }
+    }
+}
+
+/// Where a data source credential rotation is in its stage, test, swap and rollback workflow
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    diesel::AsExpression,
+    diesel::FromSqlRow,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialRotationStatus {
+    Staged,     // New credentials stored, connection test not finished
+    Ready,      // Connection test passed, waiting to be swapped in
+    Failed,     // Connection test failed
+    Swapped,    // New credentials are active; the previous ones are kept for rollback
+    RolledBack, // Previous credentials were restored
+    Cancelled,  // Superseded by a newer rotation before being swapped in
+}
+
+impl ToSql<Text, Pg> for CredentialRotationStatus {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            CredentialRotationStatus::Staged => out.write_all(b"staged")?,
+            CredentialRotationStatus::Ready => out.write_all(b"ready")?,
+            CredentialRotationStatus::Failed => out.write_all(b"failed")?,
+            CredentialRotationStatus::Swapped => out.write_all(b"swapped")?,
+            CredentialRotationStatus::RolledBack => out.write_all(b"rolled_back")?,
+            CredentialRotationStatus::Cancelled => out.write_all(b"cancelled")?,
+        }
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for CredentialRotationStatus {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"staged" => Ok(CredentialRotationStatus::Staged),
+            b"ready" => Ok(CredentialRotationStatus::Ready),
+            b"failed" => Ok(CredentialRotationStatus::Failed),
+            b"swapped" => Ok(CredentialRotationStatus::Swapped),
+            b"rolled_back" => Ok(CredentialRotationStatus::RolledBack),
+            b"cancelled" => Ok(CredentialRotationStatus::Cancelled),
+            _ => Err("Unrecognized CredentialRotationStatus variant".into()),
+        }
+    }
+}
+
+/// Kind of resource an identity provider provisions over SCIM
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    diesel::AsExpression,
+    diesel::FromSqlRow,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "lowercase")]
+pub enum ScimResourceType {
+    User,  // A user and their organization membership
+    Group, // A team
+}
+
+impl ToSql<Text, Pg> for ScimResourceType {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            ScimResourceType::User => out.write_all(b"user")?,
+            ScimResourceType::Group => out.write_all(b"group")?,
+        }
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for ScimResourceType {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"user" => Ok(ScimResourceType::User),
+            b"group" => Ok(ScimResourceType::Group),
+            _ => Err("Unrecognized ScimResourceType variant".into()),
+        }
+    }
+}
+
+/// Agent mode an organization routes to its own models. `Default` applies to modes without
+/// a route of their own. `Auxiliary` covers short calls outside the agent loop, such as
+/// conversation titles.
+#[derive(
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    diesel::AsExpression,
+    diesel::FromSqlRow,
+)]
+#[diesel(sql_type = Text)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelRouteMode {
+    Default,
+    Initialization,
+    DataCatalogSearch,
+    Planning,
+    Analysis,
+    Review,
+    Auxiliary,
+}
+
+impl ToSql<Text, Pg> for ModelRouteMode {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        match *self {
+            ModelRouteMode::Default => out.write_all(b"default")?,
+            ModelRouteMode::Initialization => out.write_all(b"initialization")?,
+            ModelRouteMode::DataCatalogSearch => out.write_all(b"data_catalog_search")?,
+            ModelRouteMode::Planning => out.write_all(b"planning")?,
+            ModelRouteMode::Analysis => out.write_all(b"analysis")?,
+            ModelRouteMode::Review => out.write_all(b"review")?,
+            ModelRouteMode::Auxiliary => out.write_all(b"auxiliary")?,
+        }
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Text, Pg> for ModelRouteMode {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        match bytes.as_bytes() {
+            b"default" => Ok(ModelRouteMode::Default),
+            b"initialization" => Ok(ModelRouteMode::Initialization),
+            b"data_catalog_search" => Ok(ModelRouteMode::DataCatalogSearch),
+            b"planning" => Ok(ModelRouteMode::Planning),
+            b"analysis" => Ok(ModelRouteMode::Analysis),
+            b"review" => Ok(ModelRouteMode::Review),
+            b"auxiliary" => Ok(ModelRouteMode::Auxiliary),
+            _ => Err("Unrecognized ModelRouteMode variant".into()),
+        }
+    }
+}
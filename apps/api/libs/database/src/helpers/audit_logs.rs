@@ -0,0 +1,134 @@
+use anyhow::Result;
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::enums::{AssetType, AuditEventType, AuditSurface};
+use crate::models::AuditLog;
+use crate::pool::get_pg_pool;
+use crate::schema::{
+    audit_logs, chats, collections, dashboard_files, metric_files, users_to_organizations,
+};
+
+/// Appends an entry to the audit log. Entries can't be updated or deleted afterwards.
+pub async fn record_audit_log(audit_log: &AuditLog) -> Result<()> {
+    let mut conn = get_pg_pool().get().await?;
+
+    diesel::insert_into(audit_logs::table)
+        .values(audit_log)
+        .execute(&mut conn)
+        .await?;
+
+    Ok(())
+}
+
+/// Records a view or share of an asset by a user.
+///
+/// The entry is filed under the asset's organization. Access by a user outside that
+/// organization can only have come through a public link, so it's attributed to the
+/// public share surface. Failures are logged rather than returned so auditing never
+/// blocks the request it describes.
+pub async fn record_asset_access(
+    user_id: &Uuid,
+    asset_id: &Uuid,
+    asset_type: AssetType,
+    event_type: AuditEventType,
+    details: Value,
+) {
+    if let Err(e) =
+        try_record_asset_access(user_id, asset_id, asset_type, event_type, details).await
+    {
+        tracing::error!(
+            "Failed to record audit log for {:?} {}: {}",
+            asset_type,
+            asset_id,
+            e
+        );
+    }
+}
+
+async fn try_record_asset_access(
+    user_id: &Uuid,
+    asset_id: &Uuid,
+    asset_type: AssetType,
+    event_type: AuditEventType,
+    details: Value,
+) -> Result<()> {
+    let organization_id = match get_asset_organization_id(asset_id, asset_type).await? {
+        Some(organization_id) => organization_id,
+        None => return Ok(()),
+    };
+
+    let mut conn = get_pg_pool().get().await?;
+    let is_member = users_to_organizations::table
+        .filter(users_to_organizations::user_id.eq(user_id))
+        .filter(users_to_organizations::organization_id.eq(organization_id))
+        .filter(users_to_organizations::deleted_at.is_null())
+        .select(users_to_organizations::user_id)
+        .first::<Uuid>(&mut conn)
+        .await
+        .optional()?
+        .is_some();
+    drop(conn);
+
+    let surface = if is_member {
+        AuditSurface::Api
+    } else {
+        AuditSurface::PublicShare
+    };
+
+    record_audit_log(&AuditLog {
+        id: Uuid::new_v4(),
+        organization_id,
+        user_id: Some(*user_id),
+        event_type,
+        surface,
+        data_source_id: None,
+        query_text: None,
+        query_hash: None,
+        row_count: None,
+        duration_ms: None,
+        error: None,
+        asset_id: Some(*asset_id),
+        asset_type: Some(asset_type),
+        details,
+        created_at: Utc::now(),
+    })
+    .await
+}
+
+async fn get_asset_organization_id(asset_id: &Uuid, asset_type: AssetType) -> Result<Option<Uuid>> {
+    let mut conn = get_pg_pool().get().await?;
+
+    let organization_id = match asset_type {
+        AssetType::MetricFile => metric_files::table
+            .filter(metric_files::id.eq(asset_id))
+            .select(metric_files::organization_id)
+            .first::<Uuid>(&mut conn)
+            .await
+            .optional()?,
+        AssetType::DashboardFile => dashboard_files::table
+            .filter(dashboard_files::id.eq(asset_id))
+            .select(dashboard_files::organization_id)
+            .first::<Uuid>(&mut conn)
+            .await
+            .optional()?,
+        AssetType::Collection => collections::table
+            .filter(collections::id.eq(asset_id))
+            .select(collections::organization_id)
+            .first::<Uuid>(&mut conn)
+            .await
+            .optional()?,
+        AssetType::Chat => chats::table
+            .filter(chats::id.eq(asset_id))
+            .select(chats::organization_id)
+            .first::<Uuid>(&mut conn)
+            .await
+            .optional()?,
+        _ => None,
+    };
+
+    Ok(organization_id)
+}
@@ -3,6 +3,7 @@ pub mod pool;
 pub mod models;
 pub mod schema;
 pub mod vault;
+pub mod secrets;
 pub mod helpers;
 pub mod types;
 pub mod supabase;
@@ -18,6 +18,11 @@ pub struct ApiKey {
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
     pub deleted_at: Option<DateTime<Utc>>,
+    pub scopes: Option<Vec<String>>, // None for keys with the owner's full access
+    pub data_source_ids: Vec<Uuid>,  // Data sources a key with the sql:run scope may query
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub rate_limit_per_minute: Option<i32>,
 }
 
 #[derive(Queryable, Insertable, Identifiable, Debug, Clone, Serialize)]
@@ -699,6 +704,63 @@ pub struct DatasetPermission {
     pub deleted_at: Option<DateTime<Utc>>,
 }
 
+#[derive(
+    Queryable,
+    Insertable,
+    Identifiable,
+    Associations,
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    Selectable,
+    AsChangeset,
+)]
+#[diesel(belongs_to(Dataset))]
+#[diesel(table_name = dataset_row_filter_policies)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DatasetRowFilterPolicy {
+    pub id: Uuid,
+    pub dataset_id: Uuid,
+    pub organization_id: Uuid,
+    pub filter_expression: String, // May reference `{{user.attributes.<key>}}`, resolved per user at query time
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(
+    Queryable,
+    Insertable,
+    Identifiable,
+    Associations,
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    Selectable,
+    AsChangeset,
+)]
+#[diesel(belongs_to(Dataset))]
+#[diesel(belongs_to(DatasetColumn))]
+#[diesel(table_name = dataset_column_policies)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DatasetColumnPolicy {
+    pub id: Uuid,
+    pub dataset_column_id: Uuid,
+    pub dataset_id: Uuid,
+    pub organization_id: Uuid,
+    pub action: ColumnPolicyAction,
+    // Exactly one of these is set: the policy applies to a permission group or an organization role
+    pub permission_group_id: Option<Uuid>,
+    pub role: Option<UserOrganizationRole>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
 #[derive(Queryable, Insertable, Debug)]
 #[diesel(table_name = dataset_groups_permissions)]
 pub struct DatasetGroupPermission {
@@ -836,3 +898,282 @@ pub struct StoredValuesSyncJob {
     pub status: String,
     pub error_message: Option<String>,
 }
+
+#[derive(
+    Queryable,
+    Insertable,
+    Identifiable,
+    Associations,
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    Selectable,
+    AsChangeset,
+)]
+#[diesel(belongs_to(Organization))]
+#[diesel(primary_key(organization_id))]
+#[diesel(table_name = query_complexity_policies)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct QueryComplexityPolicy {
+    pub organization_id: Uuid,
+    pub max_score: Option<i32>,
+    pub max_joins: Option<i32>,
+    pub max_subquery_depth: Option<i32>,
+    pub reject_cartesian_products: bool,
+    pub large_tables: Vec<String>, // Tables that must not be scanned without a WHERE clause
+    pub enforcement: QueryPolicyEnforcement,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(
+    Queryable,
+    Insertable,
+    Identifiable,
+    Associations,
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    Selectable,
+    AsChangeset,
+)]
+#[diesel(belongs_to(Organization))]
+#[diesel(primary_key(organization_id))]
+#[diesel(table_name = semantic_query_settings)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct SemanticQuerySettings {
+    pub organization_id: Uuid,
+    pub validation_mode: SemanticValidationMode,
+    pub strict_for_restricted_queriers: bool, // Restricted queriers can only run strict semantic queries
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Queryable, Insertable, Identifiable, Debug, Clone, Serialize, Deserialize, Selectable)]
+#[diesel(table_name = audit_logs)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct AuditLog {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub user_id: Option<Uuid>, // None for anonymous public-link access
+    pub event_type: AuditEventType,
+    pub surface: AuditSurface,
+    pub data_source_id: Option<Uuid>,
+    pub query_text: Option<String>,
+    pub query_hash: Option<String>, // sha256 of the normalized query text
+    pub row_count: Option<i64>,
+    pub duration_ms: Option<i64>,
+    pub error: Option<String>,
+    pub asset_id: Option<Uuid>,
+    pub asset_type: Option<AssetType>,
+    pub details: Value,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(
+    Queryable,
+    Insertable,
+    Identifiable,
+    Associations,
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    Selectable,
+    AsChangeset,
+)]
+#[diesel(belongs_to(DataSource))]
+#[diesel(primary_key(data_source_id))]
+#[diesel(table_name = data_source_cache_settings)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DataSourceCacheSettings {
+    pub data_source_id: Uuid,
+    pub ttl_seconds: i32, // 0 disables result caching for the data source
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(
+    Queryable,
+    Insertable,
+    Identifiable,
+    Associations,
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    Selectable,
+    AsChangeset,
+)]
+#[diesel(belongs_to(DataSource))]
+#[diesel(primary_key(data_source_id))]
+#[diesel(table_name = data_source_query_settings)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DataSourceQuerySettings {
+    pub data_source_id: Uuid,
+    pub statement_timeout_seconds: i32, // Queries running longer are cancelled on the warehouse
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(
+    Queryable,
+    Insertable,
+    Identifiable,
+    Associations,
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    Selectable,
+    AsChangeset,
+)]
+#[diesel(belongs_to(DataSource))]
+#[diesel(table_name = data_source_credential_rotations)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct DataSourceCredentialRotation {
+    pub id: Uuid,
+    pub data_source_id: Uuid,
+    pub organization_id: Uuid,
+    pub status: CredentialRotationStatus,
+    pub test_error: Option<String>,
+    pub tested_at: Option<DateTime<Utc>>,
+    pub swapped_at: Option<DateTime<Utc>>,
+    pub rollback_expires_at: Option<DateTime<Utc>>, // Previous credentials can be restored until then
+    pub rolled_back_at: Option<DateTime<Utc>>,
+    pub created_by: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(
+    Queryable,
+    Insertable,
+    Identifiable,
+    Associations,
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    Selectable,
+    AsChangeset,
+)]
+#[diesel(belongs_to(Organization))]
+#[diesel(table_name = query_quotas)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct QueryQuota {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub data_source_id: Option<Uuid>, // None for the organization-wide quota
+    pub max_concurrent_queries: Option<i32>,
+    pub max_queries_per_minute: Option<i32>,
+    pub max_rows_returned: Option<i64>,
+    pub max_bytes_scanned: Option<i64>, // Only enforced by warehouses that report bytes scanned up front
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(
+    Queryable,
+    Insertable,
+    Identifiable,
+    Associations,
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    Selectable,
+    AsChangeset,
+)]
+#[diesel(belongs_to(Organization))]
+#[diesel(primary_key(organization_id, resource_type, resource_id))]
+#[diesel(table_name = scim_external_ids)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ScimExternalId {
+    pub organization_id: Uuid,
+    pub resource_type: ScimResourceType,
+    pub resource_id: Uuid, // User or team id
+    pub external_id: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(
+    Queryable,
+    Insertable,
+    Identifiable,
+    Associations,
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    Selectable,
+    AsChangeset,
+)]
+#[diesel(belongs_to(Organization))]
+#[diesel(table_name = model_routes)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct ModelRoute {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub mode: ModelRouteMode,
+    pub models: Vec<String>, // In order of preference
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(
+    Queryable,
+    Insertable,
+    Identifiable,
+    Associations,
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    Selectable,
+)]
+#[diesel(belongs_to(Organization))]
+#[diesel(table_name = llm_usage)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct LlmUsage {
+    pub id: Uuid,
+    pub organization_id: Uuid,
+    pub user_id: Uuid,
+    pub chat_id: Option<Uuid>,
+    pub message_id: Option<Uuid>,
+    pub model: String,
+    pub mode: Option<ModelRouteMode>, // The agent mode the call was made in
+    pub tool_name: Option<String>,    // Set when a tool made the call rather than the agent
+    pub prompt_tokens: i32,
+    pub completion_tokens: i32,
+    pub reasoning_tokens: i32,
+    pub total_tokens: i32,
+    pub cost_micros: i64, // Millionths of a US dollar
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(
+    Queryable,
+    Insertable,
+    Identifiable,
+    Associations,
+    Debug,
+    Clone,
+    Serialize,
+    Deserialize,
+    Selectable,
+    AsChangeset,
+)]
+#[diesel(belongs_to(Organization))]
+#[diesel(primary_key(organization_id))]
+#[diesel(table_name = llm_budgets)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct LlmBudget {
+    pub organization_id: Uuid,
+    pub monthly_budget_micros: i64, // Millionths of a US dollar
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
@@ -56,6 +56,10 @@ pub mod sql_types {
     #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
     #[diesel(postgres_type(name = "chat_type_enum"))]
     pub struct ChatTypeEnum;
+
+    #[derive(diesel::query_builder::QueryId, Clone, diesel::sql_types::SqlType)]
+    #[diesel(postgres_type(name = "semantic_validation_mode_enum"))]
+    pub struct SemanticValidationModeEnum;
 }
 
 diesel::table! {
@@ -67,6 +71,11 @@ diesel::table! {
         created_at -> Timestamptz,
         updated_at -> Timestamptz,
         deleted_at -> Nullable<Timestamptz>,
+        scopes -> Nullable<Array<Text>>,
+        data_source_ids -> Array<Uuid>,
+        expires_at -> Nullable<Timestamptz>,
+        last_used_at -> Nullable<Timestamptz>,
+        rate_limit_per_minute -> Nullable<Int4>,
     }
 }
 
@@ -90,6 +99,29 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::AssetTypeEnum;
+
+    audit_logs (id) {
+        id -> Uuid,
+        organization_id -> Uuid,
+        user_id -> Nullable<Uuid>,
+        event_type -> Text,
+        surface -> Text,
+        data_source_id -> Nullable<Uuid>,
+        query_text -> Nullable<Text>,
+        query_hash -> Nullable<Text>,
+        row_count -> Nullable<Int8>,
+        duration_ms -> Nullable<Int8>,
+        error -> Nullable<Text>,
+        asset_id -> Nullable<Uuid>,
+        asset_type -> Nullable<AssetTypeEnum>,
+        details -> Jsonb,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::WorkspaceSharingEnum;
@@ -210,6 +242,41 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    data_source_cache_settings (data_source_id) {
+        data_source_id -> Uuid,
+        ttl_seconds -> Int4,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    data_source_credential_rotations (id) {
+        id -> Uuid,
+        data_source_id -> Uuid,
+        organization_id -> Uuid,
+        status -> Text,
+        test_error -> Nullable<Text>,
+        tested_at -> Nullable<Timestamptz>,
+        swapped_at -> Nullable<Timestamptz>,
+        rollback_expires_at -> Nullable<Timestamptz>,
+        rolled_back_at -> Nullable<Timestamptz>,
+        created_by -> Uuid,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    data_source_query_settings (data_source_id) {
+        data_source_id -> Uuid,
+        statement_timeout_seconds -> Int4,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::DataSourceOnboardingStatusEnum;
@@ -232,6 +299,25 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::UserOrganizationRoleEnum;
+
+    dataset_column_policies (id) {
+        id -> Uuid,
+        dataset_column_id -> Uuid,
+        dataset_id -> Uuid,
+        organization_id -> Uuid,
+        action -> Text,
+        permission_group_id -> Nullable<Uuid>,
+        role -> Nullable<UserOrganizationRoleEnum>,
+        created_by -> Uuid,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        deleted_at -> Nullable<Timestamptz>,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::StoredValuesStatusEnum;
@@ -295,6 +381,19 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    dataset_row_filter_policies (id) {
+        id -> Uuid,
+        dataset_id -> Uuid,
+        organization_id -> Uuid,
+        filter_expression -> Text,
+        created_by -> Uuid,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+        deleted_at -> Nullable<Timestamptz>,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::DatasetTypeEnum;
@@ -353,6 +452,34 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    llm_budgets (organization_id) {
+        organization_id -> Uuid,
+        monthly_budget_micros -> Int8,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    llm_usage (id) {
+        id -> Uuid,
+        organization_id -> Uuid,
+        user_id -> Uuid,
+        chat_id -> Nullable<Uuid>,
+        message_id -> Nullable<Uuid>,
+        model -> Text,
+        mode -> Nullable<Text>,
+        tool_name -> Nullable<Text>,
+        prompt_tokens -> Int4,
+        completion_tokens -> Int4,
+        reasoning_tokens -> Int4,
+        total_tokens -> Int4,
+        cost_micros -> Int8,
+        created_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     messages (id) {
         id -> Uuid,
@@ -469,6 +596,17 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    model_routes (id) {
+        id -> Uuid,
+        organization_id -> Uuid,
+        mode -> Text,
+        models -> Array<Text>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     use diesel::sql_types::*;
     use super::sql_types::WorkspaceSharingEnum;
@@ -549,6 +687,58 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    query_complexity_policies (organization_id) {
+        organization_id -> Uuid,
+        max_score -> Nullable<Int4>,
+        max_joins -> Nullable<Int4>,
+        max_subquery_depth -> Nullable<Int4>,
+        reject_cartesian_products -> Bool,
+        large_tables -> Array<Text>,
+        enforcement -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    query_quotas (id) {
+        id -> Uuid,
+        organization_id -> Uuid,
+        data_source_id -> Nullable<Uuid>,
+        max_concurrent_queries -> Nullable<Int4>,
+        max_queries_per_minute -> Nullable<Int4>,
+        max_rows_returned -> Nullable<Int8>,
+        max_bytes_scanned -> Nullable<Int8>,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    scim_external_ids (organization_id, resource_type, resource_id) {
+        organization_id -> Uuid,
+        resource_type -> Text,
+        resource_id -> Uuid,
+        external_id -> Text,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use super::sql_types::SemanticValidationModeEnum;
+
+    semantic_query_settings (organization_id) {
+        organization_id -> Uuid,
+        validation_mode -> SemanticValidationModeEnum,
+        strict_for_restricted_queriers -> Bool,
+        created_at -> Timestamptz,
+        updated_at -> Timestamptz,
+    }
+}
+
 diesel::table! {
     sql_evaluations (id) {
         id -> Uuid,
@@ -722,18 +912,36 @@ diesel::joinable!(chats -> organizations (organization_id));
 diesel::joinable!(collections -> organizations (organization_id));
 diesel::joinable!(dashboard_versions -> dashboards (dashboard_id));
 diesel::joinable!(dashboards -> organizations (organization_id));
+diesel::joinable!(data_source_cache_settings -> data_sources (data_source_id));
+diesel::joinable!(data_source_credential_rotations -> data_sources (data_source_id));
+diesel::joinable!(data_source_credential_rotations -> organizations (organization_id));
+diesel::joinable!(data_source_credential_rotations -> users (created_by));
+diesel::joinable!(data_source_query_settings -> data_sources (data_source_id));
 diesel::joinable!(data_sources -> organizations (organization_id));
+diesel::joinable!(dataset_column_policies -> dataset_columns (dataset_column_id));
+diesel::joinable!(dataset_column_policies -> datasets (dataset_id));
+diesel::joinable!(dataset_column_policies -> organizations (organization_id));
+diesel::joinable!(dataset_column_policies -> permission_groups (permission_group_id));
+diesel::joinable!(dataset_column_policies -> users (created_by));
 diesel::joinable!(dataset_groups -> organizations (organization_id));
 diesel::joinable!(dataset_groups_permissions -> dataset_groups (dataset_group_id));
 diesel::joinable!(dataset_groups_permissions -> organizations (organization_id));
 diesel::joinable!(dataset_permissions -> datasets (dataset_id));
 diesel::joinable!(dataset_permissions -> organizations (organization_id));
+diesel::joinable!(dataset_row_filter_policies -> datasets (dataset_id));
+diesel::joinable!(dataset_row_filter_policies -> organizations (organization_id));
+diesel::joinable!(dataset_row_filter_policies -> users (created_by));
 diesel::joinable!(datasets -> data_sources (data_source_id));
 diesel::joinable!(datasets -> organizations (organization_id));
 diesel::joinable!(datasets_to_dataset_groups -> dataset_groups (dataset_group_id));
 diesel::joinable!(datasets_to_dataset_groups -> datasets (dataset_id));
 diesel::joinable!(datasets_to_permission_groups -> datasets (dataset_id));
 diesel::joinable!(datasets_to_permission_groups -> permission_groups (permission_group_id));
+diesel::joinable!(llm_budgets -> organizations (organization_id));
+diesel::joinable!(llm_usage -> chats (chat_id));
+diesel::joinable!(llm_usage -> messages (message_id));
+diesel::joinable!(llm_usage -> organizations (organization_id));
+diesel::joinable!(llm_usage -> users (user_id));
 diesel::joinable!(messages -> chats (chat_id));
 diesel::joinable!(messages -> users (created_by));
 diesel::joinable!(messages_deprecated -> datasets (dataset_id));
@@ -744,9 +952,15 @@ diesel::joinable!(metric_files_to_dashboard_files -> metric_files (metric_file_i
 diesel::joinable!(metric_files_to_dashboard_files -> users (created_by));
 diesel::joinable!(metric_files_to_datasets -> datasets (dataset_id));
 diesel::joinable!(metric_files_to_datasets -> metric_files (metric_file_id));
+diesel::joinable!(model_routes -> organizations (organization_id));
 diesel::joinable!(permission_groups -> organizations (organization_id));
 diesel::joinable!(permission_groups_to_users -> permission_groups (permission_group_id));
 diesel::joinable!(permission_groups_to_users -> users (user_id));
+diesel::joinable!(query_complexity_policies -> organizations (organization_id));
+diesel::joinable!(query_quotas -> data_sources (data_source_id));
+diesel::joinable!(query_quotas -> organizations (organization_id));
+diesel::joinable!(scim_external_ids -> organizations (organization_id));
+diesel::joinable!(semantic_query_settings -> organizations (organization_id));
 diesel::joinable!(stored_values_sync_jobs -> data_sources (data_source_id));
 diesel::joinable!(teams -> organizations (organization_id));
 diesel::joinable!(teams -> users (created_by));
@@ -765,32 +979,45 @@ diesel::joinable!(users_to_organizations -> organizations (organization_id));
 diesel::allow_tables_to_appear_in_same_query!(
     api_keys,
     asset_permissions,
+    audit_logs,
     chats,
     collections,
     collections_to_assets,
     dashboard_files,
     dashboard_versions,
     dashboards,
+    data_source_cache_settings,
+    data_source_credential_rotations,
+    data_source_query_settings,
     data_sources,
+    dataset_column_policies,
     dataset_columns,
     dataset_groups,
     dataset_groups_permissions,
     dataset_permissions,
+    dataset_row_filter_policies,
     datasets,
     datasets_to_dataset_groups,
     datasets_to_permission_groups,
     entity_relationship,
+    llm_budgets,
+    llm_usage,
     messages,
     messages_deprecated,
     messages_to_files,
     metric_files,
     metric_files_to_dashboard_files,
     metric_files_to_datasets,
+    model_routes,
     organizations,
     report_files,
     permission_groups,
     permission_groups_to_identities,
     permission_groups_to_users,
+    query_complexity_policies,
+    query_quotas,
+    scim_external_ids,
+    semantic_query_settings,
     sql_evaluations,
     stored_values_sync_jobs,
     teams,
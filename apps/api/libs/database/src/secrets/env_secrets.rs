@@ -0,0 +1,90 @@
+use std::{env, path::PathBuf};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+
+use super::SecretProvider;
+
+/// Read-only secrets provided by the deployment: files in `SECRETS_DIR` named after the
+/// secret (e.g. a mounted Kubernetes secret), falling back to `BUSTER_SECRET_<NAME>`
+/// environment variables with the name upper-cased and non-alphanumerics replaced by `_`.
+pub struct EnvSecretProvider {
+    secrets_dir: Option<PathBuf>,
+}
+
+impl EnvSecretProvider {
+    pub fn from_env() -> Self {
+        Self {
+            secrets_dir: env::var("SECRETS_DIR").ok().map(PathBuf::from),
+        }
+    }
+}
+
+#[async_trait]
+impl SecretProvider for EnvSecretProvider {
+    fn name(&self) -> &'static str {
+        "env"
+    }
+
+    async fn read(&self, name: &str) -> Result<String> {
+        if let Some(secrets_dir) = &self.secrets_dir {
+            match tokio::fs::read_to_string(secrets_dir.join(name)).await {
+                Ok(value) => return Ok(value.trim_end_matches(['\n', '\r']).to_string()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+                Err(e) => return Err(anyhow!("Error reading secret file for {}: {}", name, e)),
+            }
+        }
+
+        let var = env_var_name(name);
+        env::var(&var).map_err(|_| anyhow!("Secret {} not found in {} or SECRETS_DIR", name, var))
+    }
+
+    async fn write(&self, name: &str, _value: &str) -> Result<()> {
+        Err(anyhow!(
+            "Secret {} can't be written: env secrets are managed by the deployment",
+            name
+        ))
+    }
+
+    async fn delete(&self, name: &str) -> Result<()> {
+        Err(anyhow!(
+            "Secret {} can't be deleted: env secrets are managed by the deployment",
+            name
+        ))
+    }
+
+    fn is_writable(&self) -> bool {
+        false
+    }
+}
+
+fn env_var_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("BUSTER_SECRET_{}", name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_var_name() {
+        assert_eq!(
+            env_var_name("0b1e2c3d-aaaa-bbbb-cccc-000000000001"),
+            "BUSTER_SECRET_0B1E2C3D_AAAA_BBBB_CCCC_000000000001"
+        );
+        assert_eq!(
+            env_var_name("rotation.staged"),
+            "BUSTER_SECRET_ROTATION_STAGED"
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use std::env;
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde_json::{json, Value};
+
+use super::SecretProvider;
+
+/// Secrets stored in a HashiCorp Vault KV version 2 engine, one entry per secret with the
+/// value under the `value` key.
+///
+/// Configured with `VAULT_ADDR` and `VAULT_TOKEN`, plus the optional `VAULT_NAMESPACE`,
+/// `VAULT_KV_MOUNT` (default `secret`) and `VAULT_KV_PATH_PREFIX` (default `buster`).
+pub struct HashiCorpVaultProvider {
+    addr: String,
+    token: String,
+    namespace: Option<String>,
+    mount: String,
+    path_prefix: String,
+    client: Client,
+}
+
+impl HashiCorpVaultProvider {
+    pub fn from_env() -> Result<Self> {
+        let addr = env::var("VAULT_ADDR").context("VAULT_ADDR is required for HashiCorp Vault")?;
+        let token =
+            env::var("VAULT_TOKEN").context("VAULT_TOKEN is required for HashiCorp Vault")?;
+
+        Ok(Self {
+            addr: addr.trim_end_matches('/').to_string(),
+            token,
+            namespace: env::var("VAULT_NAMESPACE").ok(),
+            mount: env::var("VAULT_KV_MOUNT").unwrap_or_else(|_| "secret".to_string()),
+            path_prefix: env::var("VAULT_KV_PATH_PREFIX").unwrap_or_else(|_| "buster".to_string()),
+            client: Client::new(),
+        })
+    }
+
+    // KV v2 keeps values under `data/` and version history under `metadata/`
+    fn url(&self, kind: &str, name: &str) -> String {
+        format!(
+            "{}/v1/{}/{}/{}/{}",
+            self.addr, self.mount, kind, self.path_prefix, name
+        )
+    }
+
+    fn authenticated(&self, request: RequestBuilder) -> RequestBuilder {
+        let request = request.header("X-Vault-Token", &self.token);
+        match &self.namespace {
+            Some(namespace) => request.header("X-Vault-Namespace", namespace),
+            None => request,
+        }
+    }
+}
+
+#[async_trait]
+impl SecretProvider for HashiCorpVaultProvider {
+    fn name(&self) -> &'static str {
+        "hashicorp"
+    }
+
+    async fn read(&self, name: &str) -> Result<String> {
+        let response = self
+            .authenticated(self.client.get(self.url("data", name)))
+            .send()
+            .await
+            .map_err(|e| anyhow!("Error reading secret from HashiCorp Vault: {}", e))?;
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(anyhow!("Secret {} not found in HashiCorp Vault", name));
+        }
+        let body: Value = response
+            .error_for_status()
+            .map_err(|e| anyhow!("Error reading secret from HashiCorp Vault: {}", e))?
+            .json()
+            .await
+            .map_err(|e| anyhow!("Invalid response from HashiCorp Vault: {}", e))?;
+
+        body["data"]["data"]["value"]
+            .as_str()
+            .map(|value| value.to_string())
+            .ok_or_else(|| anyhow!("Secret {} in HashiCorp Vault has no value", name))
+    }
+
+    async fn write(&self, name: &str, value: &str) -> Result<()> {
+        self.authenticated(self.client.post(self.url("data", name)))
+            .json(&json!({ "data": { "value": value } }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| anyhow!("Error writing secret to HashiCorp Vault: {}", e))?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, name: &str) -> Result<()> {
+        // Deleting the metadata removes every version, not just the latest
+        self.authenticated(self.client.delete(self.url("metadata", name)))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| anyhow!("Error deleting secret from HashiCorp Vault: {}", e))?;
+
+        Ok(())
+    }
+}
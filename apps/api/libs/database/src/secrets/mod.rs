@@ -0,0 +1,65 @@
+//! Backends data source credentials are stored in. The backend is picked once at startup with
+//! `SECRET_PROVIDER`: `postgres` (the Supabase vault, default), `hashicorp` or `env`.
+
+mod env_secrets;
+mod hashicorp_vault;
+mod postgres_vault;
+
+use std::env;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+
+pub use env_secrets::EnvSecretProvider;
+pub use hashicorp_vault::HashiCorpVaultProvider;
+pub use postgres_vault::PostgresVaultProvider;
+
+/// A store of named secrets. Names are unique per provider; data source credentials are
+/// stored under the data source's id.
+#[async_trait]
+pub trait SecretProvider: Send + Sync {
+    /// Short name of the backend, used in logs and errors
+    fn name(&self) -> &'static str;
+
+    async fn read(&self, name: &str) -> Result<String>;
+
+    /// Creates the secret, or replaces its value if it already exists
+    async fn write(&self, name: &str, value: &str) -> Result<()>;
+
+    async fn delete(&self, name: &str) -> Result<()>;
+
+    /// Whether `write` and `delete` are supported. Secrets in read-only providers are
+    /// managed outside the application, e.g. mounted by the orchestrator.
+    fn is_writable(&self) -> bool {
+        true
+    }
+}
+
+static SECRET_PROVIDER: Lazy<Box<dyn SecretProvider>> = Lazy::new(|| {
+    let provider = env::var("SECRET_PROVIDER").unwrap_or_else(|_| "postgres".to_string());
+    match build_provider(&provider) {
+        Ok(provider) => {
+            tracing::info!("Using the {} secret provider", provider.name());
+            provider
+        }
+        Err(e) => panic!("Invalid secret provider configuration: {}", e),
+    }
+});
+
+/// The configured secret provider
+pub fn get_secret_provider() -> &'static dyn SecretProvider {
+    &**SECRET_PROVIDER
+}
+
+fn build_provider(provider: &str) -> Result<Box<dyn SecretProvider>> {
+    match provider {
+        "postgres" => Ok(Box::new(PostgresVaultProvider)),
+        "hashicorp" => Ok(Box::new(HashiCorpVaultProvider::from_env()?)),
+        "env" => Ok(Box::new(EnvSecretProvider::from_env())),
+        other => Err(anyhow!(
+            "Unknown SECRET_PROVIDER '{}', expected postgres, hashicorp or env",
+            other
+        )),
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use diesel::{deserialize::QueryableByName, sql_types::Text};
+use diesel_async::RunQueryDsl;
+
+use super::SecretProvider;
+use crate::pool::get_pg_pool;
+
+/// Secrets stored with the Supabase vault extension in the application database
+pub struct PostgresVaultProvider;
+
+#[derive(QueryableByName)]
+struct Secret {
+    #[diesel(sql_type = Text)]
+    decrypted_secret: String,
+}
+
+#[async_trait]
+impl SecretProvider for PostgresVaultProvider {
+    fn name(&self) -> &'static str {
+        "postgres"
+    }
+
+    async fn read(&self, name: &str) -> Result<String> {
+        let mut conn = get_pg_pool()
+            .get()
+            .await
+            .map_err(|e| anyhow!("Error getting client from pool: {}", e))?;
+
+        let secret = diesel::sql_query(
+            "SELECT decrypted_secret FROM vault.decrypted_secrets WHERE name = $1 LIMIT 1",
+        )
+        .bind::<Text, _>(name)
+        .get_result::<Secret>(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Unable to read secret from database: {}", e))?;
+
+        Ok(secret.decrypted_secret)
+    }
+
+    async fn write(&self, name: &str, value: &str) -> Result<()> {
+        let mut conn = get_pg_pool()
+            .get()
+            .await
+            .map_err(|e| anyhow!("Error getting client from pool: {}", e))?;
+
+        // Update in place when the secret exists so its value changes in a single statement
+        let updated = diesel::sql_query(
+            "SELECT vault.update_secret(id, $2) FROM vault.secrets WHERE name = $1",
+        )
+        .bind::<Text, _>(name)
+        .bind::<Text, _>(value)
+        .execute(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Error updating secret via vault function: {}", e))?;
+
+        if updated == 0 {
+            diesel::sql_query("SELECT vault.create_secret($1, $2, '')")
+                .bind::<Text, _>(value)
+                .bind::<Text, _>(name)
+                .execute(&mut conn)
+                .await
+                .map_err(|e| anyhow!("Error creating secret via vault function: {}", e))?;
+        }
+
+        Ok(())
+    }
+
+    async fn delete(&self, name: &str) -> Result<()> {
+        let mut conn = get_pg_pool()
+            .get()
+            .await
+            .map_err(|e| anyhow!("Error getting client from pool: {}", e))?;
+
+        diesel::sql_query("DELETE FROM vault.secrets WHERE name = $1")
+            .bind::<Text, _>(name)
+            .execute(&mut conn)
+            .await
+            .map_err(|e| anyhow!("Error deleting secret from vault: {}", e))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+//! Column-level policies: which dataset columns a user may only see masked, or not at all.
+
+use anyhow::{Context, Result};
+use database::{
+    enums::{ColumnPolicyAction, IdentityType, UserOrganizationRole},
+    pool::get_pg_pool,
+    schema::{
+        dataset_column_policies, dataset_columns, datasets, permission_groups,
+        permission_groups_to_identities, teams_to_users, users_to_organizations,
+    },
+};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use uuid::Uuid;
+
+/// A dataset column the user can't see in full, with the most restrictive policy that applies
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestrictedColumn {
+    pub dataset_id: Uuid,
+    pub table_name: String, // The dataset's `database_name`, i.e. the table queries reference
+    pub column_name: String,
+    pub action: ColumnPolicyAction,
+}
+
+/// Returns the restricted columns of the given datasets for a user. A policy applies when it
+/// targets one of the user's permission groups (directly, through a team, or the organization's
+/// default group) or the user's role in the policy's organization.
+pub async fn get_restricted_columns(
+    user_id: &Uuid,
+    dataset_ids: &[Uuid],
+) -> Result<Vec<RestrictedColumn>> {
+    if dataset_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let mut conn = get_pg_pool().get().await.context("DB Error")?;
+
+    let user_roles: HashMap<Uuid, UserOrganizationRole> = users_to_organizations::table
+        .filter(users_to_organizations::user_id.eq(user_id))
+        .filter(users_to_organizations::deleted_at.is_null())
+        .select((
+            users_to_organizations::organization_id,
+            users_to_organizations::role,
+        ))
+        .load::<(Uuid, UserOrganizationRole)>(&mut conn)
+        .await
+        .context("Failed to fetch user organizations")?
+        .into_iter()
+        .collect();
+
+    if user_roles.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let group_ids = fetch_user_permission_group_ids(user_id, &user_roles).await?;
+
+    let policies = dataset_column_policies::table
+        .inner_join(
+            dataset_columns::table
+                .on(dataset_column_policies::dataset_column_id.eq(dataset_columns::id)),
+        )
+        .inner_join(datasets::table.on(dataset_column_policies::dataset_id.eq(datasets::id)))
+        .filter(dataset_column_policies::dataset_id.eq_any(dataset_ids))
+        .filter(
+            dataset_column_policies::permission_group_id
+                .eq_any(&group_ids)
+                .or(dataset_column_policies::role.is_not_null()),
+        )
+        .filter(dataset_column_policies::deleted_at.is_null())
+        .filter(dataset_columns::deleted_at.is_null())
+        .filter(datasets::deleted_at.is_null())
+        .select((
+            dataset_column_policies::dataset_id,
+            datasets::database_name,
+            dataset_columns::name,
+            dataset_column_policies::action,
+            dataset_column_policies::organization_id,
+            dataset_column_policies::role,
+        ))
+        .load::<(
+            Uuid,
+            String,
+            String,
+            ColumnPolicyAction,
+            Uuid,
+            Option<UserOrganizationRole>,
+        )>(&mut conn)
+        .await
+        .context("Failed to fetch dataset column policies")?;
+
+    // Most restrictive action per column
+    let mut restricted: BTreeMap<(Uuid, String), (String, ColumnPolicyAction)> = BTreeMap::new();
+    for (dataset_id, table_name, column_name, action, organization_id, role) in policies {
+        // Group policies were matched in the query; role policies need the user's role in that org
+        if let Some(role) = role {
+            if user_roles.get(&organization_id) != Some(&role) {
+                continue;
+            }
+        }
+
+        restricted
+            .entry((dataset_id, column_name))
+            .and_modify(|(_, current)| *current = (*current).max(action))
+            .or_insert((table_name, action));
+    }
+
+    Ok(restricted
+        .into_iter()
+        .map(
+            |((dataset_id, column_name), (table_name, action))| RestrictedColumn {
+                dataset_id,
+                table_name,
+                column_name,
+                action,
+            },
+        )
+        .collect())
+}
+
+// Permission groups the user belongs to directly, through a team, or as an organization member
+async fn fetch_user_permission_group_ids(
+    user_id: &Uuid,
+    user_roles: &HashMap<Uuid, UserOrganizationRole>,
+) -> Result<Vec<Uuid>> {
+    let mut conn = get_pg_pool().get().await.context("DB Error")?;
+
+    let direct_ids = permission_groups_to_identities::table
+        .inner_join(
+            permission_groups::table
+                .on(permission_groups_to_identities::permission_group_id.eq(permission_groups::id)),
+        )
+        .filter(permission_groups_to_identities::identity_id.eq(user_id))
+        .filter(permission_groups_to_identities::identity_type.eq(IdentityType::User))
+        .filter(permission_groups_to_identities::deleted_at.is_null())
+        .filter(permission_groups::deleted_at.is_null())
+        .select(permission_groups::id)
+        .load::<Uuid>(&mut conn)
+        .await
+        .context("Failed to fetch user permission groups")?;
+
+    let team_ids = permission_groups_to_identities::table
+        .inner_join(
+            permission_groups::table
+                .on(permission_groups_to_identities::permission_group_id.eq(permission_groups::id)),
+        )
+        .inner_join(
+            teams_to_users::table
+                .on(permission_groups_to_identities::identity_id.eq(teams_to_users::team_id)),
+        )
+        .filter(teams_to_users::user_id.eq(user_id))
+        .filter(teams_to_users::deleted_at.is_null())
+        .filter(permission_groups_to_identities::identity_type.eq(IdentityType::Team))
+        .filter(permission_groups_to_identities::deleted_at.is_null())
+        .filter(permission_groups::deleted_at.is_null())
+        .select(permission_groups::id)
+        .load::<Uuid>(&mut conn)
+        .await
+        .context("Failed to fetch team permission groups")?;
+
+    let organization_ids: Vec<Uuid> = user_roles.keys().copied().collect();
+    let default_group_names: Vec<String> = organization_ids
+        .iter()
+        .map(|organization_id| format!("default:{}", organization_id))
+        .collect();
+    let default_ids = permission_groups::table
+        .filter(permission_groups::name.eq_any(default_group_names))
+        .filter(permission_groups::organization_id.eq_any(organization_ids))
+        .filter(permission_groups::deleted_at.is_null())
+        .select(permission_groups::id)
+        .load::<Uuid>(&mut conn)
+        .await
+        .context("Failed to fetch default permission groups")?;
+
+    let group_ids: HashSet<Uuid> = direct_ids
+        .into_iter()
+        .chain(team_ids)
+        .chain(default_ids)
+        .collect();
+    Ok(group_ids.into_iter().collect())
+}
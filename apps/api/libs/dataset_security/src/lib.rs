@@ -1,5 +1,9 @@
 //! Library for handling dataset security and permissions.
 
+mod column_policies;
+
+pub use column_policies::{get_restricted_columns, RestrictedColumn};
+
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use database::{
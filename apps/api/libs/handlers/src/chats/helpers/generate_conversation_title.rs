@@ -1,9 +1,12 @@
 use anyhow::Result;
-use agents::LiteLlmMessage;
+use agents::{model_routes::ModelRoutes, LiteLlmMessage, LlmCallUsage};
+use database::enums::ModelRouteMode;
 use litellm::{ChatCompletionRequest, LiteLLMClient, Metadata, LiteLlmMessage as LiteLLMAgentMessage};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::llm_usage::record_message_llm_usage;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum BusterGeneratingTitleProgress {
@@ -47,6 +50,7 @@ pub async fn generate_conversation_title(
     message_id: &Uuid,
     user_id: &Uuid,
     session_id: &Uuid,
+    organization_id: &Uuid,
 ) -> Result<BusterGeneratingTitle> {
     // Format conversation messages for the prompt
     let mut formatted_messages = vec![];
@@ -69,15 +73,11 @@ pub async fn generate_conversation_title(
     // Set up LiteLLM client
     let llm_client = LiteLLMClient::new(None, None);
 
-    let model = if env::var("ENVIRONMENT").unwrap_or_else(|_| "development".to_string()) == "local" {
-        "gpt-4.1-nano".to_string()
-    } else {
-        "gemini-2.0-flash-001".to_string()
-    };
+    let model = ModelRoutes::load_for_user(user_id).await.auxiliary_model();
 
     // Create the request
     let request = ChatCompletionRequest {
-        model,
+        model: model.clone(),
         messages: vec![LiteLLMAgentMessage::User {
             id: None,
             content: prompt,
@@ -101,6 +101,25 @@ pub async fn generate_conversation_title(
         }
     };
 
+    // Titles aren't generated by the agent, so their usage is stored on its own
+    let usage =
+        LlmCallUsage::chat(model, &response.usage).in_mode(Some(ModelRouteMode::Auxiliary));
+    let recorded = record_message_llm_usage(
+        *organization_id,
+        *user_id,
+        *session_id,
+        *message_id,
+        vec![usage],
+    )
+    .await;
+    if let Err(e) = recorded {
+        tracing::warn!(
+            "Failed to record title generation LLM usage for message {}: {}",
+            message_id,
+            e
+        );
+    }
+
     // Parse LLM response
     let content = match &response.choices[0].message {
         LiteLlmMessage::Assistant {
@@ -1,9 +1,8 @@
 use agents::tools::file_tools::common::{generate_deterministic_uuid, ModifyFilesOutput};
 use dashmap::DashMap;
-use database::enums::WorkspaceSharing;
+use database::enums::{ModelRouteMode, WorkspaceSharing};
 use middleware::AuthenticatedUser;
 use std::collections::HashSet;
-use std::env;
 use std::{collections::HashMap, time::{Instant, Duration}};
 use std::sync::Arc;
 
@@ -17,7 +16,8 @@ use agents::{
         // Remove the old import
         // planning_tools::CreatePlanOutput,
     },
-    AgentExt, AgentMessage, AgentThread, BusterMultiAgent,
+    model_routes::ModelRoutes,
+    AgentExt, AgentMessage, AgentThread, BusterMultiAgent, LlmCallUsage,
 };
 
 use anyhow::{anyhow, Result};
@@ -51,6 +51,7 @@ use crate::chats::{
     streaming_parser::StreamingParser,
     utils::convert_messages_to_core_format,
 };
+use crate::llm_usage::{check_llm_budget, record_message_llm_usage, MessageLlmUsageGuard};
 use crate::messages::types::{ChatMessage, ChatUserMessage};
 
 use super::types::ChatWithMessages;
@@ -206,6 +207,7 @@ pub async fn post_chat_handler(
             return Err(anyhow!("User has no organization ID"));
         }
     };
+    check_llm_budget(user_org_id).await?;
     let (chat_id, message_id, mut chat_with_messages) =
         initialize_chat(&request, &user, user_org_id).await?;
 
@@ -353,6 +355,14 @@ pub async fn post_chat_handler(
     let is_follow_up = request.chat_id.is_some();
     // Create the agent and wrap it in Arc
     let agent = Arc::new(BusterMultiAgent::new(user.id, chat_id, is_follow_up).await?);
+    // Stores what answering the message cost when this function returns, even with an error
+    let _llm_usage_guard = MessageLlmUsageGuard::new(
+        agent.get_agent_arc().clone(),
+        user_org_id,
+        user.id,
+        chat_id,
+        message_id,
+    );
 
     // Load context if provided (combines both legacy and new asset references)
     if let Some(existing_chat_id) = request.chat_id {
@@ -403,7 +413,15 @@ pub async fn post_chat_handler(
         let user_id = user.id;
         let chat_messages = chat.messages.clone();
         tokio::spawn(async move {
-            generate_conversation_title(&chat_messages, &message_id, &user_id, &chat_id, tx).await
+            generate_conversation_title(
+                &chat_messages,
+                &message_id,
+                &user_id,
+                &chat_id,
+                &user_org_id,
+                tx,
+            )
+            .await
         })
     };
 
@@ -2700,6 +2718,7 @@ pub async fn generate_conversation_title(
     message_id: &Uuid,
     user_id: &Uuid,
     session_id: &Uuid,
+    organization_id: &Uuid,
     tx: Option<mpsc::Sender<BusterContainerResult>>,
 ) -> Result<BusterGeneratingTitle> {
     // Format conversation messages for the prompt
@@ -2723,15 +2742,11 @@ pub async fn generate_conversation_title(
     // Set up LiteLLM client
     let llm_client = LiteLLMClient::new(None, None);
 
-    let model = if env::var("ENVIRONMENT").unwrap_or_else(|_| "development".to_string()) == "local" {
-        "gpt-4.1-nano".to_string()
-    } else {
-        "gemini-2.0-flash-001".to_string()
-    };
+    let model = ModelRoutes::load_for_user(user_id).await.auxiliary_model();
 
     // Create the request
     let request = ChatCompletionRequest {
-        model,
+        model: model.clone(),
         messages: vec![LiteLLMAgentMessage::User {
             id: None,
             content: prompt,
@@ -2755,6 +2770,25 @@ pub async fn generate_conversation_title(
         }
     };
 
+    // Titles aren't generated by the agent, so their usage is stored on its own
+    let usage =
+        LlmCallUsage::chat(model, &response.usage).in_mode(Some(ModelRouteMode::Auxiliary));
+    let recorded = record_message_llm_usage(
+        *organization_id,
+        *user_id,
+        *session_id,
+        *message_id,
+        vec![usage],
+    )
+    .await;
+    if let Err(e) = recorded {
+        tracing::warn!(
+            "Failed to record title generation LLM usage for message {}: {}",
+            message_id,
+            e
+        );
+    }
+
     // Parse LLM response
     let content = match &response.choices[0].message {
         AgentMessage::Assistant {
@@ -1,7 +1,8 @@
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
 use database::{
-    enums::{AssetPermissionRole, AssetType, WorkspaceSharing},
+    enums::{AssetPermissionRole, AssetType, AuditEventType, WorkspaceSharing},
+    helpers::audit_logs::record_asset_access,
     helpers::dashboard_files::fetch_dashboard_file_with_permission,
     schema::dashboard_files::dsl,
     pool::get_pg_pool,
@@ -250,6 +251,15 @@ pub async fn update_dashboard_sharing_handler(
         }
     }
     
+    // Enabling a public link shares the dashboard with anyone who has it, so it's audited like a share
+    let public_link_details = (request.publicly_accessible == Some(true)).then(|| {
+        serde_json::json!({
+            "public_link": true,
+            "password_protected": public_password.is_some(),
+            "public_expiry_date": public_expiry_date,
+        })
+    });
+
     // Execute the update if any changes were made
     if update_needed {
         diesel::update(dsl::dashboard_files)
@@ -266,6 +276,17 @@ pub async fn update_dashboard_sharing_handler(
             .execute(&mut conn)
             .await?;
     }
+
+    if let Some(details) = public_link_details {
+        record_asset_access(
+            &user.id,
+            dashboard_id,
+            AssetType::DashboardFile,
+            AuditEventType::AssetShare,
+            details,
+        )
+        .await;
+    }
     
     info!(
         dashboard_id = %dashboard_id,
@@ -0,0 +1,129 @@
+use anyhow::{anyhow, Result};
+use chrono::{Duration, Utc};
+use diesel::ExpressionMethods;
+use diesel_async::RunQueryDsl;
+use middleware::types::AuthenticatedUser;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use database::{
+    enums::CredentialRotationStatus, models::DataSourceCredentialRotation, pool::get_pg_pool,
+    schema::data_source_credential_rotations, secrets::get_secret_provider,
+};
+
+use super::credential_rotation::{
+    get_data_source_for_admin, get_rotation, previous_secret_name, staged_secret_name,
+};
+
+const DEFAULT_ROLLBACK_WINDOW_HOURS: i64 = 24;
+const MAX_ROLLBACK_WINDOW_HOURS: i64 = 24 * 7;
+
+/// Request for swapping tested credentials in
+#[derive(Debug, Default, Deserialize)]
+pub struct CompleteCredentialRotationRequest {
+    /// How long the previous credentials can be restored for. Defaults to 24 hours.
+    pub rollback_window_hours: Option<i64>,
+}
+
+/// Makes a rotation's staged credentials the data source's active credentials.
+///
+/// Queries read the credentials on every run, so they switch over with the single write of
+/// the active secret; there's no point where neither set is in place. The replaced
+/// credentials are kept so the swap can be rolled back until the window closes.
+pub async fn complete_credential_rotation_handler(
+    user: &AuthenticatedUser,
+    data_source_id: &Uuid,
+    rotation_id: &Uuid,
+    request: CompleteCredentialRotationRequest,
+) -> Result<DataSourceCredentialRotation> {
+    get_data_source_for_admin(user, data_source_id).await?;
+
+    let rollback_window_hours = request
+        .rollback_window_hours
+        .unwrap_or(DEFAULT_ROLLBACK_WINDOW_HOURS);
+    if !(0..=MAX_ROLLBACK_WINDOW_HOURS).contains(&rollback_window_hours) {
+        return Err(anyhow!(
+            "rollback_window_hours must be between 0 and {}",
+            MAX_ROLLBACK_WINDOW_HOURS
+        ));
+    }
+
+    let rotation = get_rotation(data_source_id, rotation_id).await?;
+    if rotation.status != CredentialRotationStatus::Ready {
+        return Err(anyhow!(
+            "Only rotations whose connection test passed can be swapped in"
+        ));
+    }
+
+    let provider = get_secret_provider();
+    let active_secret_name = data_source_id.to_string();
+    let staged_credentials = provider
+        .read(&staged_secret_name(rotation_id))
+        .await
+        .map_err(|e| anyhow!("Error reading staged credentials: {}", e))?;
+    let active_credentials = provider
+        .read(&active_secret_name)
+        .await
+        .map_err(|e| anyhow!("Error reading active credentials: {}", e))?;
+
+    provider
+        .write(&previous_secret_name(rotation_id), &active_credentials)
+        .await
+        .map_err(|e| anyhow!("Error saving previous credentials: {}", e))?;
+
+    // Claiming the rotation first means a concurrent swap of the same rotation can't also
+    // overwrite the active credentials
+    let now = Utc::now();
+    let mut conn = get_pg_pool().get().await?;
+    let swapped = diesel::update(data_source_credential_rotations::table)
+        .filter(data_source_credential_rotations::id.eq(rotation_id))
+        .filter(data_source_credential_rotations::status.eq(CredentialRotationStatus::Ready))
+        .set((
+            data_source_credential_rotations::status.eq(CredentialRotationStatus::Swapped),
+            data_source_credential_rotations::swapped_at.eq(Some(now)),
+            data_source_credential_rotations::rollback_expires_at
+                .eq(Some(now + Duration::hours(rollback_window_hours))),
+            data_source_credential_rotations::updated_at.eq(now),
+        ))
+        .get_result::<DataSourceCredentialRotation>(&mut conn)
+        .await
+        .map_err(|_| anyhow!("Credential rotation is no longer ready to be swapped in"))?;
+    drop(conn);
+
+    if let Err(e) = provider
+        .write(&active_secret_name, &staged_credentials)
+        .await
+    {
+        // The active credentials weren't replaced, so the rotation can be retried
+        let mut conn = get_pg_pool().get().await?;
+        diesel::update(data_source_credential_rotations::table)
+            .filter(data_source_credential_rotations::id.eq(rotation_id))
+            .set((
+                data_source_credential_rotations::status.eq(CredentialRotationStatus::Ready),
+                data_source_credential_rotations::swapped_at.eq(None::<chrono::DateTime<Utc>>),
+                data_source_credential_rotations::rollback_expires_at
+                    .eq(None::<chrono::DateTime<Utc>>),
+                data_source_credential_rotations::updated_at.eq(Utc::now()),
+            ))
+            .execute(&mut conn)
+            .await?;
+        return Err(anyhow!("Error swapping in the new credentials: {}", e));
+    }
+
+    if let Err(e) = provider.delete(&staged_secret_name(rotation_id)).await {
+        tracing::warn!(
+            "Failed to delete staged credentials of rotation {}: {}",
+            rotation_id,
+            e
+        );
+    }
+
+    tracing::info!(
+        data_source_id = %data_source_id,
+        rotation_id = %rotation_id,
+        user_id = %user.id,
+        "Swapped in rotated data source credentials"
+    );
+
+    Ok(swapped)
+}
@@ -13,7 +13,9 @@ use database::{
     schema::data_sources,
     vault::create_secret,
 };
-use query_engine::credentials::Credential;
+use query_engine::{
+    credentials::Credential, data_source_connections::ssh_tunneling::pin_ssh_host_key,
+};
 use stored_values::schema::create_search_schema;
 
 #[derive(Deserialize)]
@@ -50,7 +52,7 @@ pub struct DatasetResponse {
 
 pub async fn create_data_source_handler(
     user: &AuthenticatedUser,
-    request: CreateDataSourceRequest,
+    mut request: CreateDataSourceRequest,
 ) -> Result<CreateDataSourceResponse> {
     // Verify user has an organization
     if user.organizations.is_empty() {
@@ -69,6 +71,17 @@ pub async fn create_data_source_handler(
         ));
     }
 
+    request
+        .credential
+        .validate()
+        .map_err(|e| anyhow!("Invalid credentials: {}", e))?;
+
+    // The jump host's key is trusted when an admin adds the data source; connections made
+    // later are rejected if it changes
+    pin_ssh_host_key(&mut request.credential)
+        .await
+        .map_err(|e| anyhow!("Error fetching SSH host key: {}", e))?;
+
     let mut conn = get_pg_pool().get().await?;
 
     // Check if data source with same name already exists in the organization
@@ -121,7 +134,7 @@ pub async fn create_data_source_handler(
     let credential_json = serde_json::to_string(&request.credential)
         .map_err(|e| anyhow!("Error serializing credentials: {}", e))?;
 
-    create_secret(&credential_json, &data_source.id.to_string(), None)
+    create_secret(&credential_json, &data_source.id.to_string())
         .await
         .map_err(|e| anyhow!("Error storing credentials in vault: {}", e))?;
 
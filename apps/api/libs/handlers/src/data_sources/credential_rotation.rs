@@ -0,0 +1,64 @@
+use anyhow::{anyhow, Result};
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use middleware::types::AuthenticatedUser;
+use uuid::Uuid;
+
+use database::{
+    enums::UserOrganizationRole,
+    models::{DataSource, DataSourceCredentialRotation},
+    pool::get_pg_pool,
+    schema::{data_source_credential_rotations, data_sources},
+};
+
+// Secret holding the credentials a rotation stages, until they're swapped in
+pub(crate) fn staged_secret_name(rotation_id: &Uuid) -> String {
+    format!("rotation-{}-staged", rotation_id)
+}
+
+// Secret holding the credentials a swap replaced, until the rollback window closes
+pub(crate) fn previous_secret_name(rotation_id: &Uuid) -> String {
+    format!("rotation-{}-previous", rotation_id)
+}
+
+/// Loads a data source of the user's organization, if the user may manage its credentials
+pub(crate) async fn get_data_source_for_admin(
+    user: &AuthenticatedUser,
+    data_source_id: &Uuid,
+) -> Result<DataSource> {
+    // Get the first organization (users can only belong to one organization currently)
+    let user_org = user
+        .organizations
+        .first()
+        .ok_or_else(|| anyhow!("User is not a member of any organization"))?;
+
+    if user_org.role != UserOrganizationRole::WorkspaceAdmin
+        && user_org.role != UserOrganizationRole::DataAdmin
+    {
+        return Err(anyhow!(
+            "User does not have appropriate permissions to rotate data source credentials"
+        ));
+    }
+
+    let mut conn = get_pg_pool().get().await?;
+    data_sources::table
+        .filter(data_sources::id.eq(data_source_id))
+        .filter(data_sources::organization_id.eq(user_org.id))
+        .filter(data_sources::deleted_at.is_null())
+        .first::<DataSource>(&mut conn)
+        .await
+        .map_err(|_| anyhow!("Data source not found or you don't have access to it"))
+}
+
+pub(crate) async fn get_rotation(
+    data_source_id: &Uuid,
+    rotation_id: &Uuid,
+) -> Result<DataSourceCredentialRotation> {
+    let mut conn = get_pg_pool().get().await?;
+    data_source_credential_rotations::table
+        .filter(data_source_credential_rotations::id.eq(rotation_id))
+        .filter(data_source_credential_rotations::data_source_id.eq(data_source_id))
+        .first::<DataSourceCredentialRotation>(&mut conn)
+        .await
+        .map_err(|_| anyhow!("Credential rotation not found"))
+}
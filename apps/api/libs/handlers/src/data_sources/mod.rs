@@ -1,10 +1,17 @@
+mod complete_credential_rotation_handler;
 mod create_data_source_handler;
+mod credential_rotation;
 mod delete_data_source_handler;
 mod get_data_source_handler;
 mod list_data_sources_handler;
+mod rollback_credential_rotation_handler;
+mod stage_credential_rotation_handler;
 mod update_data_source_handler;
 
 // Explicitly re-export the specific items from each module
+pub use complete_credential_rotation_handler::{
+    complete_credential_rotation_handler, CompleteCredentialRotationRequest,
+};
 pub use create_data_source_handler::{
     create_data_source_handler, CreateDataSourceRequest, CreateDataSourceResponse,
 };
@@ -16,6 +23,10 @@ pub use get_data_source_handler::{
 pub use list_data_sources_handler::{
     list_data_sources_handler, DataSourceListItem, ListDataSourcesRequest,
 };
+pub use rollback_credential_rotation_handler::rollback_credential_rotation_handler;
+pub use stage_credential_rotation_handler::{
+    stage_credential_rotation_handler, StageCredentialRotationRequest,
+};
 pub use update_data_source_handler::{
     update_data_source_handler, CreatedBy, DataSourceResponse as UpdateDataSourceResponse,
     UpdateDataSourceRequest,
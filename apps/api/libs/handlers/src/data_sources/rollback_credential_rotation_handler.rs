@@ -0,0 +1,101 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use middleware::types::AuthenticatedUser;
+use uuid::Uuid;
+
+use database::{
+    enums::CredentialRotationStatus, models::DataSourceCredentialRotation, pool::get_pg_pool,
+    schema::data_source_credential_rotations, secrets::get_secret_provider,
+};
+
+use super::credential_rotation::{get_data_source_for_admin, get_rotation, previous_secret_name};
+
+/// Restores the credentials a swap replaced, as long as its rollback window is still open.
+/// Only the data source's latest swap can be rolled back.
+pub async fn rollback_credential_rotation_handler(
+    user: &AuthenticatedUser,
+    data_source_id: &Uuid,
+    rotation_id: &Uuid,
+) -> Result<DataSourceCredentialRotation> {
+    get_data_source_for_admin(user, data_source_id).await?;
+
+    let rotation = get_rotation(data_source_id, rotation_id).await?;
+    let now = Utc::now();
+    match (rotation.status, rotation.rollback_expires_at) {
+        (CredentialRotationStatus::Swapped, Some(expires_at)) if expires_at > now => (),
+        (CredentialRotationStatus::Swapped, _) => {
+            return Err(anyhow!("The rollback window for this rotation has closed"))
+        }
+        _ => return Err(anyhow!("Only swapped rotations can be rolled back")),
+    }
+
+    // Rolling back an older swap would discard the credentials a later one put in place
+    let mut conn = get_pg_pool().get().await?;
+    let latest_swap_id = data_source_credential_rotations::table
+        .filter(data_source_credential_rotations::data_source_id.eq(data_source_id))
+        .filter(data_source_credential_rotations::swapped_at.is_not_null())
+        .order_by(data_source_credential_rotations::swapped_at.desc())
+        .select(data_source_credential_rotations::id)
+        .first::<Uuid>(&mut conn)
+        .await?;
+    if latest_swap_id != *rotation_id {
+        return Err(anyhow!(
+            "A later rotation has been swapped in since; roll that one back instead"
+        ));
+    }
+
+    let provider = get_secret_provider();
+    let previous_credentials = provider
+        .read(&previous_secret_name(rotation_id))
+        .await
+        .map_err(|e| anyhow!("Error reading previous credentials: {}", e))?;
+
+    let rolled_back = diesel::update(data_source_credential_rotations::table)
+        .filter(data_source_credential_rotations::id.eq(rotation_id))
+        .filter(data_source_credential_rotations::status.eq(CredentialRotationStatus::Swapped))
+        .set((
+            data_source_credential_rotations::status.eq(CredentialRotationStatus::RolledBack),
+            data_source_credential_rotations::rolled_back_at.eq(Some(now)),
+            data_source_credential_rotations::updated_at.eq(now),
+        ))
+        .get_result::<DataSourceCredentialRotation>(&mut conn)
+        .await
+        .map_err(|_| anyhow!("Credential rotation has already been rolled back"))?;
+    drop(conn);
+
+    if let Err(e) = provider
+        .write(&data_source_id.to_string(), &previous_credentials)
+        .await
+    {
+        let mut conn = get_pg_pool().get().await?;
+        diesel::update(data_source_credential_rotations::table)
+            .filter(data_source_credential_rotations::id.eq(rotation_id))
+            .set((
+                data_source_credential_rotations::status.eq(CredentialRotationStatus::Swapped),
+                data_source_credential_rotations::rolled_back_at.eq(None::<chrono::DateTime<Utc>>),
+                data_source_credential_rotations::updated_at.eq(Utc::now()),
+            ))
+            .execute(&mut conn)
+            .await?;
+        return Err(anyhow!("Error restoring the previous credentials: {}", e));
+    }
+
+    if let Err(e) = provider.delete(&previous_secret_name(rotation_id)).await {
+        tracing::warn!(
+            "Failed to delete previous credentials of rotation {}: {}",
+            rotation_id,
+            e
+        );
+    }
+
+    tracing::info!(
+        data_source_id = %data_source_id,
+        rotation_id = %rotation_id,
+        user_id = %user.id,
+        "Rolled back data source credential rotation"
+    );
+
+    Ok(rolled_back)
+}
@@ -0,0 +1,187 @@
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use middleware::types::AuthenticatedUser;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use database::{
+    enums::CredentialRotationStatus, models::DataSourceCredentialRotation, pool::get_pg_pool,
+    schema::data_source_credential_rotations, secrets::get_secret_provider,
+};
+use query_engine::{
+    credentials::Credential,
+    data_source_connections::test_data_source_connections::test_data_source_connection,
+};
+
+use super::credential_rotation::{
+    get_data_source_for_admin, previous_secret_name, staged_secret_name,
+};
+
+/// Request for staging new credentials for a data source
+#[derive(Debug, Deserialize)]
+pub struct StageCredentialRotationRequest {
+    pub credential: Credential,
+}
+
+/// Stores new credentials next to the active ones and tests a connection with them.
+///
+/// The active credentials are untouched: the rotation comes back `ready` if the connection
+/// test passed, or `failed` with the error otherwise. Staging replaces any rotation of the
+/// data source that hasn't been swapped in yet.
+pub async fn stage_credential_rotation_handler(
+    user: &AuthenticatedUser,
+    data_source_id: &Uuid,
+    request: StageCredentialRotationRequest,
+) -> Result<DataSourceCredentialRotation> {
+    let data_source = get_data_source_for_admin(user, data_source_id).await?;
+
+    let provider = get_secret_provider();
+    if !provider.is_writable() {
+        return Err(anyhow!(
+            "Credentials in the {} secret provider are managed outside the application and can't be rotated here",
+            provider.name()
+        ));
+    }
+
+    if request.credential.get_type() != data_source.type_ {
+        return Err(anyhow!(
+            "Credential type {} doesn't match the data source type {}",
+            request.credential.get_type().to_str(),
+            data_source.type_.to_str()
+        ));
+    }
+
+    cancel_pending_rotations(data_source_id).await?;
+    purge_expired_rollback_secrets(data_source_id).await;
+
+    let now = Utc::now();
+    let rotation = DataSourceCredentialRotation {
+        id: Uuid::new_v4(),
+        data_source_id: *data_source_id,
+        organization_id: data_source.organization_id,
+        status: CredentialRotationStatus::Staged,
+        test_error: None,
+        tested_at: None,
+        swapped_at: None,
+        rollback_expires_at: None,
+        rolled_back_at: None,
+        created_by: user.id,
+        created_at: now,
+        updated_at: now,
+    };
+
+    let mut conn = get_pg_pool().get().await?;
+    diesel::insert_into(data_source_credential_rotations::table)
+        .values(&rotation)
+        .execute(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Error staging credential rotation: {}", e))?;
+    drop(conn);
+
+    let credential_json = serde_json::to_string(&request.credential)
+        .map_err(|e| anyhow!("Error serializing credentials: {}", e))?;
+    provider
+        .write(&staged_secret_name(&rotation.id), &credential_json)
+        .await
+        .map_err(|e| anyhow!("Error storing staged credentials: {}", e))?;
+
+    let (status, test_error) = match test_data_source_connection(&request.credential).await {
+        Ok(()) => (CredentialRotationStatus::Ready, None),
+        Err(e) => {
+            tracing::warn!(
+                "Connection test with staged credentials failed for data source {}: {}",
+                data_source_id,
+                e
+            );
+            (CredentialRotationStatus::Failed, Some(e.to_string()))
+        }
+    };
+
+    let mut conn = get_pg_pool().get().await?;
+    let rotation = diesel::update(data_source_credential_rotations::table)
+        .filter(data_source_credential_rotations::id.eq(rotation.id))
+        .set((
+            data_source_credential_rotations::status.eq(status),
+            data_source_credential_rotations::test_error.eq(test_error),
+            data_source_credential_rotations::tested_at.eq(Some(Utc::now())),
+            data_source_credential_rotations::updated_at.eq(Utc::now()),
+        ))
+        .get_result::<DataSourceCredentialRotation>(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Error recording connection test result: {}", e))?;
+
+    Ok(rotation)
+}
+
+// Only one rotation per data source waits to be swapped in; a new one supersedes the rest
+async fn cancel_pending_rotations(data_source_id: &Uuid) -> Result<()> {
+    let mut conn = get_pg_pool().get().await?;
+    let cancelled_ids = diesel::update(data_source_credential_rotations::table)
+        .filter(data_source_credential_rotations::data_source_id.eq(data_source_id))
+        .filter(data_source_credential_rotations::status.eq_any([
+            CredentialRotationStatus::Staged,
+            CredentialRotationStatus::Ready,
+        ]))
+        .set((
+            data_source_credential_rotations::status.eq(CredentialRotationStatus::Cancelled),
+            data_source_credential_rotations::updated_at.eq(Utc::now()),
+        ))
+        .returning(data_source_credential_rotations::id)
+        .get_results::<Uuid>(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Error cancelling pending credential rotations: {}", e))?;
+    drop(conn);
+
+    for rotation_id in cancelled_ids {
+        if let Err(e) = get_secret_provider()
+            .delete(&staged_secret_name(&rotation_id))
+            .await
+        {
+            tracing::warn!(
+                "Failed to delete staged credentials of rotation {}: {}",
+                rotation_id,
+                e
+            );
+        }
+    }
+
+    Ok(())
+}
+
+// Previous credentials are only kept while they can still be rolled back to
+async fn purge_expired_rollback_secrets(data_source_id: &Uuid) {
+    let expired_ids = match get_pg_pool().get().await {
+        Ok(mut conn) => data_source_credential_rotations::table
+            .filter(data_source_credential_rotations::data_source_id.eq(data_source_id))
+            .filter(data_source_credential_rotations::status.eq(CredentialRotationStatus::Swapped))
+            .filter(data_source_credential_rotations::rollback_expires_at.lt(Utc::now()))
+            .select(data_source_credential_rotations::id)
+            .load::<Uuid>(&mut conn)
+            .await
+            .map_err(anyhow::Error::from),
+        Err(e) => Err(e.into()),
+    };
+
+    let expired_ids = match expired_ids {
+        Ok(ids) => ids,
+        Err(e) => {
+            tracing::warn!("Failed to look up expired credential rotations: {}", e);
+            return;
+        }
+    };
+
+    for rotation_id in expired_ids {
+        if let Err(e) = get_secret_provider()
+            .delete(&previous_secret_name(&rotation_id))
+            .await
+        {
+            tracing::warn!(
+                "Failed to delete previous credentials of rotation {}: {}",
+                rotation_id,
+                e
+            );
+        }
+    }
+}
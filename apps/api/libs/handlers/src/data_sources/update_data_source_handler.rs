@@ -15,7 +15,9 @@ use database::{
     schema::{data_sources, users},
     vault::{read_secret, update_secret},
 };
-use query_engine::credentials::Credential;
+use query_engine::{
+    credentials::Credential, data_source_connections::ssh_tunneling::pin_ssh_host_key,
+};
 
 /// Request for updating a data source
 #[derive(Debug, Deserialize)]
@@ -144,7 +146,7 @@ pub async fn update_data_source_handler(
             .map_err(|e| anyhow!("Failed to parse existing credentials: {}", e))?;
 
         // Create updated credential based on the type
-        let updated_credential = match &current_credential {
+        let mut updated_credential = match &current_credential {
             Credential::Postgres(creds) => {
                 let mut updated = creds.clone();
 
@@ -178,6 +180,9 @@ pub async fn update_data_source_handler(
                     updated.default_schema = Some(default_schema.to_string());
                 }
                 if let Some(jump_host) = new_credentials.get("jump_host").and_then(|v| v.as_str()) {
+                    if updated.jump_host.as_deref() != Some(jump_host) {
+                        updated.ssh_host_key_fingerprint = None;
+                    }
                     updated.jump_host = Some(jump_host.to_string());
                 }
                 if let Some(ssh_username) =
@@ -191,6 +196,12 @@ pub async fn update_data_source_handler(
                 {
                     updated.ssh_private_key = Some(ssh_private_key.to_string());
                 }
+                if let Some(ssh_host_key_fingerprint) = new_credentials
+                    .get("ssh_host_key_fingerprint")
+                    .and_then(|v| v.as_str())
+                {
+                    updated.ssh_host_key_fingerprint = Some(ssh_host_key_fingerprint.to_string());
+                }
 
                 Credential::Postgres(updated)
             }
@@ -220,6 +231,9 @@ pub async fn update_data_source_handler(
                     updated.default_database = default_database.to_string();
                 }
                 if let Some(jump_host) = new_credentials.get("jump_host").and_then(|v| v.as_str()) {
+                    if updated.jump_host.as_deref() != Some(jump_host) {
+                        updated.ssh_host_key_fingerprint = None;
+                    }
                     updated.jump_host = Some(jump_host.to_string());
                 }
                 if let Some(ssh_username) =
@@ -233,6 +247,12 @@ pub async fn update_data_source_handler(
                 {
                     updated.ssh_private_key = Some(ssh_private_key.to_string());
                 }
+                if let Some(ssh_host_key_fingerprint) = new_credentials
+                    .get("ssh_host_key_fingerprint")
+                    .and_then(|v| v.as_str())
+                {
+                    updated.ssh_host_key_fingerprint = Some(ssh_host_key_fingerprint.to_string());
+                }
 
                 Credential::MySql(updated)
             }
@@ -289,6 +309,9 @@ pub async fn update_data_source_handler(
                     updated.default_schema = Some(default_schema.to_string());
                 }
                 if let Some(jump_host) = new_credentials.get("jump_host").and_then(|v| v.as_str()) {
+                    if updated.jump_host.as_deref() != Some(jump_host) {
+                        updated.ssh_host_key_fingerprint = None;
+                    }
                     updated.jump_host = Some(jump_host.to_string());
                 }
                 if let Some(ssh_username) =
@@ -302,6 +325,12 @@ pub async fn update_data_source_handler(
                 {
                     updated.ssh_private_key = Some(ssh_private_key.to_string());
                 }
+                if let Some(ssh_host_key_fingerprint) = new_credentials
+                    .get("ssh_host_key_fingerprint")
+                    .and_then(|v| v.as_str())
+                {
+                    updated.ssh_host_key_fingerprint = Some(ssh_host_key_fingerprint.to_string());
+                }
 
                 Credential::SqlServer(updated)
             }
@@ -383,8 +412,40 @@ pub async fn update_data_source_handler(
                 if let Some(username) = new_credentials.get("username").and_then(|v| v.as_str()) {
                     updated.username = username.to_string();
                 }
+                if let Some(auth_method) = new_credentials.get("auth_method") {
+                    updated.auth_method = serde_json::from_value(auth_method.clone())
+                        .map_err(|e| anyhow!("Invalid auth_method: {}", e))?;
+                }
                 if let Some(password) = new_credentials.get("password").and_then(|v| v.as_str()) {
-                    updated.password = password.to_string();
+                    updated.password = Some(password.to_string());
+                }
+                if let Some(private_key) =
+                    new_credentials.get("private_key").and_then(|v| v.as_str())
+                {
+                    updated.private_key = Some(private_key.to_string());
+                }
+                if let Some(private_key_passphrase) = new_credentials
+                    .get("private_key_passphrase")
+                    .and_then(|v| v.as_str())
+                {
+                    updated.private_key_passphrase = Some(private_key_passphrase.to_string());
+                }
+                if let Some(oauth_client_id) =
+                    new_credentials.get("oauth_client_id").and_then(|v| v.as_str())
+                {
+                    updated.oauth_client_id = Some(oauth_client_id.to_string());
+                }
+                if let Some(oauth_client_secret) = new_credentials
+                    .get("oauth_client_secret")
+                    .and_then(|v| v.as_str())
+                {
+                    updated.oauth_client_secret = Some(oauth_client_secret.to_string());
+                }
+                if let Some(oauth_refresh_token) = new_credentials
+                    .get("oauth_refresh_token")
+                    .and_then(|v| v.as_str())
+                {
+                    updated.oauth_refresh_token = Some(oauth_refresh_token.to_string());
                 }
                 if let Some(role) = new_credentials.get("role").and_then(|v| v.as_str()) {
                     updated.role = Some(role.to_string());
@@ -402,15 +463,135 @@ pub async fn update_data_source_handler(
                     updated.default_schema = Some(default_schema.to_string());
                 }
 
+                updated
+                    .validate()
+                    .map_err(|e| anyhow!("Invalid credentials: {}", e))?;
+
                 Credential::Snowflake(updated)
             }
+            Credential::DuckDb(creds) => {
+                let mut updated = creds.clone();
+
+                if let Some(database_path) =
+                    new_credentials.get("database_path").and_then(|v| v.as_str())
+                {
+                    updated.database_path = Some(database_path.to_string());
+                }
+                if let Some(external_files) = new_credentials.get("external_files") {
+                    updated.external_files = serde_json::from_value(external_files.clone())
+                        .map_err(|e| anyhow!("Invalid external_files: {}", e))?;
+                }
+                if let Some(read_only) = new_credentials.get("read_only").and_then(|v| v.as_bool()) {
+                    updated.read_only = read_only;
+                }
+
+                updated
+                    .validate()
+                    .map_err(|e| anyhow!("Invalid credentials: {}", e))?;
+
+                Credential::DuckDb(updated)
+            }
+            Credential::Sqlite(creds) => {
+                let mut updated = creds.clone();
+
+                if let Some(database_path) =
+                    new_credentials.get("database_path").and_then(|v| v.as_str())
+                {
+                    updated.database_path = database_path.to_string();
+                }
+                if let Some(read_only) = new_credentials.get("read_only").and_then(|v| v.as_bool()) {
+                    updated.read_only = read_only;
+                }
+
+                updated
+                    .validate()
+                    .map_err(|e| anyhow!("Invalid credentials: {}", e))?;
+
+                Credential::Sqlite(updated)
+            }
+            Credential::ClickHouse(creds) => {
+                let mut updated = creds.clone();
+
+                if let Some(host) = new_credentials.get("host").and_then(|v| v.as_str()) {
+                    updated.host = host.to_string();
+                }
+                if let Some(port) = new_credentials
+                    .get("port")
+                    .and_then(|v| v.as_u64())
+                    .map(|v| v as u16)
+                {
+                    updated.port = port;
+                }
+                if let Some(username) = new_credentials.get("username").and_then(|v| v.as_str()) {
+                    updated.username = username.to_string();
+                }
+                if let Some(password) = new_credentials.get("password").and_then(|v| v.as_str()) {
+                    updated.password = password.to_string();
+                }
+                if let Some(default_database) = new_credentials
+                    .get("default_database")
+                    .and_then(|v| v.as_str())
+                {
+                    updated.default_database = default_database.to_string();
+                }
+                if let Some(secure) = new_credentials.get("secure").and_then(|v| v.as_bool()) {
+                    updated.secure = secure;
+                }
+
+                Credential::ClickHouse(updated)
+            }
+            Credential::Trino(creds) => {
+                let mut updated = creds.clone();
+
+                if let Some(host) = new_credentials.get("host").and_then(|v| v.as_str()) {
+                    updated.host = host.to_string();
+                }
+                if let Some(port) = new_credentials
+                    .get("port")
+                    .and_then(|v| v.as_u64())
+                    .map(|v| v as u16)
+                {
+                    updated.port = port;
+                }
+                if let Some(username) = new_credentials.get("username").and_then(|v| v.as_str()) {
+                    updated.username = username.to_string();
+                }
+                if let Some(password) = new_credentials.get("password").and_then(|v| v.as_str()) {
+                    updated.password = Some(password.to_string());
+                }
+                if let Some(default_catalog) = new_credentials
+                    .get("default_catalog")
+                    .and_then(|v| v.as_str())
+                {
+                    updated.default_catalog = default_catalog.to_string();
+                }
+                if let Some(default_schema) = new_credentials
+                    .get("default_schema")
+                    .and_then(|v| v.as_str())
+                {
+                    updated.default_schema = Some(default_schema.to_string());
+                }
+                if let Some(secure) = new_credentials.get("secure").and_then(|v| v.as_bool()) {
+                    updated.secure = secure;
+                }
+                if let Some(presto) = new_credentials.get("presto").and_then(|v| v.as_bool()) {
+                    updated.presto = presto;
+                }
+
+                Credential::Trino(updated)
+            }
         };
 
+        // A new jump host's key is pinned like it is when the data source is created
+        pin_ssh_host_key(&mut updated_credential)
+            .await
+            .map_err(|e| anyhow!("Error fetching SSH host key: {}", e))?;
+
         // Update the secret
         let updated_secret_json = serde_json::to_string(&updated_credential)
             .map_err(|e| anyhow!("Failed to serialize updated credentials: {}", e))?;
 
-        update_secret(data_source_id, &updated_secret_json, &data_source.name, None)
+        update_secret(data_source_id, &updated_secret_json)
             .await
             .map_err(|e| anyhow!("Error updating credentials in vault: {}", e))?;
     }
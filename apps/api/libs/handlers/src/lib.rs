@@ -4,10 +4,13 @@ pub mod dashboards;
 pub mod data_sources;
 pub mod datasets;
 pub mod favorites;
+pub mod llm_usage;
 pub mod logs;
 pub mod messages;
 pub mod metrics;
 pub mod organizations;
+pub mod query_settings;
+pub mod scim;
 pub mod search;
 pub mod users;
 pub mod utils;
@@ -0,0 +1,102 @@
+use anyhow::Result;
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use database::{
+    pool::get_pg_pool,
+    schema::{llm_budgets, llm_usage},
+};
+use diesel::{dsl::sql, sql_types::BigInt, ExpressionMethods, OptionalExtension, QueryDsl};
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
+
+use super::{types::LlmBudgetStatus, LlmUsageError};
+
+pub(crate) fn micros_to_usd(micros: i64) -> f64 {
+    micros as f64 / 1_000_000.0
+}
+
+pub(crate) fn usd_to_micros(usd: f64) -> i64 {
+    (usd * 1_000_000.0).round() as i64
+}
+
+/// Midnight UTC on the first day of the month `now` falls in
+pub fn month_start(now: DateTime<Utc>) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+        .single()
+        .unwrap_or(now)
+}
+
+/// The organization's monthly budget and how much of it has been used, if it has a budget
+pub async fn get_llm_budget_status(organization_id: Uuid) -> Result<Option<LlmBudgetStatus>> {
+    let mut conn = get_pg_pool().get().await?;
+
+    let budget_micros = llm_budgets::table
+        .filter(llm_budgets::organization_id.eq(organization_id))
+        .select(llm_budgets::monthly_budget_micros)
+        .first::<i64>(&mut conn)
+        .await
+        .optional()?;
+
+    let Some(budget_micros) = budget_micros else {
+        return Ok(None);
+    };
+
+    let month_to_date_micros = llm_usage::table
+        .filter(llm_usage::organization_id.eq(organization_id))
+        .filter(llm_usage::created_at.ge(month_start(Utc::now())))
+        .select(sql::<BigInt>("COALESCE(SUM(cost_micros), 0)::BIGINT"))
+        .first::<i64>(&mut conn)
+        .await?;
+
+    Ok(Some(LlmBudgetStatus {
+        monthly_budget_usd: micros_to_usd(budget_micros),
+        month_to_date_usd: micros_to_usd(month_to_date_micros),
+        exceeded: month_to_date_micros >= budget_micros,
+    }))
+}
+
+/// Fails with [`LlmUsageError::BudgetExceeded`] once the organization's usage this month has
+/// reached its budget. Runs already in progress are allowed to finish, so usage can end up
+/// slightly over budget.
+pub async fn check_llm_budget(organization_id: Uuid) -> Result<()> {
+    match get_llm_budget_status(organization_id).await? {
+        Some(status) if status.exceeded => Err(LlmUsageError::BudgetExceeded {
+            monthly_budget_usd: status.monthly_budget_usd,
+            month_to_date_usd: status.month_to_date_usd,
+        }
+        .into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_month_start() {
+        let now = Utc.with_ymd_and_hms(2025, 5, 19, 13, 45, 12).unwrap();
+        assert_eq!(
+            month_start(now),
+            Utc.with_ymd_and_hms(2025, 5, 1, 0, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_usd_micros_round_trip() {
+        assert_eq!(usd_to_micros(12.5), 12_500_000);
+        assert_eq!(usd_to_micros(0.0000014), 1);
+        assert_eq!(micros_to_usd(3_300), 0.0033);
+    }
+
+    #[test]
+    fn test_budget_exceeded_message() {
+        let message = LlmUsageError::BudgetExceeded {
+            monthly_budget_usd: 100.0,
+            month_to_date_usd: 100.4,
+        }
+        .to_string();
+
+        assert!(message.starts_with("LLM budget exceeded"));
+        assert!(message.contains("$100.40 of the organization's $100.00"));
+    }
+}
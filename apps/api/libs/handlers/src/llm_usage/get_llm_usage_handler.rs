@@ -0,0 +1,179 @@
+use anyhow::Result;
+use chrono::Utc;
+use database::pool::get_pg_pool;
+use diesel::sql_types::{BigInt, Nullable, Text, Timestamptz, Uuid as SqlUuid};
+use diesel_async::RunQueryDsl;
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+use super::{
+    budget::{get_llm_budget_status, micros_to_usd, month_start},
+    require_workspace_admin,
+    types::{LlmUsageGroup, LlmUsageGroupBy, LlmUsageQuery, LlmUsageResponse, LlmUsageTotals},
+    LlmUsageError,
+};
+
+#[derive(diesel::QueryableByName, Debug)]
+struct LlmUsageRow {
+    #[diesel(sql_type = Nullable<Text>)]
+    key: Option<String>,
+    #[diesel(sql_type = Nullable<Text>)]
+    name: Option<String>,
+    #[diesel(sql_type = BigInt)]
+    calls: i64,
+    #[diesel(sql_type = BigInt)]
+    prompt_tokens: i64,
+    #[diesel(sql_type = BigInt)]
+    completion_tokens: i64,
+    #[diesel(sql_type = BigInt)]
+    reasoning_tokens: i64,
+    #[diesel(sql_type = BigInt)]
+    total_tokens: i64,
+    #[diesel(sql_type = BigInt)]
+    cost_micros: i64,
+}
+
+impl LlmUsageRow {
+    fn totals(&self) -> LlmUsageTotals {
+        LlmUsageTotals {
+            calls: self.calls,
+            prompt_tokens: self.prompt_tokens,
+            completion_tokens: self.completion_tokens,
+            reasoning_tokens: self.reasoning_tokens,
+            total_tokens: self.total_tokens,
+            cost_usd: micros_to_usd(self.cost_micros),
+        }
+    }
+}
+
+const SUMS: &str = "COUNT(*)::BIGINT AS calls,
+    COALESCE(SUM(l.prompt_tokens), 0)::BIGINT AS prompt_tokens,
+    COALESCE(SUM(l.completion_tokens), 0)::BIGINT AS completion_tokens,
+    COALESCE(SUM(l.reasoning_tokens), 0)::BIGINT AS reasoning_tokens,
+    COALESCE(SUM(l.total_tokens), 0)::BIGINT AS total_tokens,
+    COALESCE(SUM(l.cost_micros), 0)::BIGINT AS cost_micros";
+
+/// The key and name columns and the joins needed to group usage. Users on several teams
+/// count toward each of them.
+fn grouping(group_by: LlmUsageGroupBy) -> (&'static str, &'static str, &'static str) {
+    match group_by {
+        LlmUsageGroupBy::User => (
+            "l.user_id::TEXT",
+            "COALESCE(u.name, u.email)",
+            "LEFT JOIN users u ON u.id = l.user_id",
+        ),
+        LlmUsageGroupBy::Team => (
+            "t.id::TEXT",
+            "t.name",
+            "LEFT JOIN (
+                SELECT tu.user_id, teams.id, teams.name
+                FROM teams_to_users tu
+                JOIN teams ON teams.id = tu.team_id
+                WHERE teams.organization_id = $1
+                    AND teams.deleted_at IS NULL
+                    AND tu.deleted_at IS NULL
+            ) t ON t.user_id = l.user_id",
+        ),
+        LlmUsageGroupBy::Chat => (
+            "l.chat_id::TEXT",
+            "c.title",
+            "LEFT JOIN chats c ON c.id = l.chat_id",
+        ),
+        LlmUsageGroupBy::Model => ("l.model", "l.model", ""),
+        LlmUsageGroupBy::Mode => ("l.mode", "l.mode", ""),
+    }
+}
+
+fn grouped_usage_sql(group_by: LlmUsageGroupBy) -> String {
+    let (key, name, joins) = grouping(group_by);
+
+    format!(
+        "SELECT {key} AS key, MAX({name}) AS name, {SUMS}
+        FROM llm_usage l
+        {joins}
+        WHERE l.organization_id = $1 AND l.created_at >= $2 AND l.created_at < $3
+        GROUP BY {key}
+        ORDER BY cost_micros DESC, key"
+    )
+}
+
+fn total_usage_sql() -> String {
+    format!(
+        "SELECT NULL::TEXT AS key, NULL::TEXT AS name, {SUMS}
+        FROM llm_usage l
+        WHERE l.organization_id = $1 AND l.created_at >= $2 AND l.created_at < $3"
+    )
+}
+
+/// Token usage and cost of an organization's LLM calls over a time range, grouped by user,
+/// team, chat, model or agent mode. Only workspace admins can see it.
+pub async fn get_llm_usage_handler(
+    user: &AuthenticatedUser,
+    organization_id: Uuid,
+    query: LlmUsageQuery,
+) -> Result<LlmUsageResponse> {
+    require_workspace_admin(user, organization_id)?;
+
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or_else(|| month_start(to));
+    if from >= to {
+        return Err(LlmUsageError::Invalid("Invalid time range: from must be before to").into());
+    }
+
+    let mut conn = get_pg_pool().get().await?;
+
+    let totals = diesel::sql_query(total_usage_sql())
+        .bind::<SqlUuid, _>(organization_id)
+        .bind::<Timestamptz, _>(from)
+        .bind::<Timestamptz, _>(to)
+        .get_result::<LlmUsageRow>(&mut conn)
+        .await?;
+
+    let groups = diesel::sql_query(grouped_usage_sql(query.group_by))
+        .bind::<SqlUuid, _>(organization_id)
+        .bind::<Timestamptz, _>(from)
+        .bind::<Timestamptz, _>(to)
+        .load::<LlmUsageRow>(&mut conn)
+        .await?
+        .into_iter()
+        .map(|row| LlmUsageGroup {
+            totals: row.totals(),
+            key: row.key,
+            name: row.name,
+        })
+        .collect();
+
+    Ok(LlmUsageResponse {
+        from,
+        to,
+        group_by: query.group_by,
+        totals: totals.totals(),
+        groups,
+        budget: get_llm_budget_status(organization_id).await?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grouped_usage_sql_only_joins_what_the_grouping_needs() {
+        let by_model = grouped_usage_sql(LlmUsageGroupBy::Model);
+        assert!(by_model.contains("GROUP BY l.model"));
+        assert!(!by_model.contains("JOIN"));
+
+        let by_team = grouped_usage_sql(LlmUsageGroupBy::Team);
+        assert!(by_team.contains("GROUP BY t.id::TEXT"));
+        assert!(by_team.contains("teams.organization_id = $1"));
+    }
+
+    #[test]
+    fn test_group_by_defaults_to_mode() {
+        let query: LlmUsageQuery = serde_json::from_str("{}").unwrap();
+        assert_eq!(query.group_by, LlmUsageGroupBy::Mode);
+
+        let query: LlmUsageQuery = serde_json::from_str(r#"{"group_by": "team"}"#).unwrap();
+        assert_eq!(query.group_by, LlmUsageGroupBy::Team);
+    }
+}
@@ -0,0 +1,50 @@
+pub mod budget;
+pub mod get_llm_usage_handler;
+pub mod record_llm_usage;
+pub mod types;
+pub mod update_llm_budget_handler;
+
+pub use budget::*;
+pub use get_llm_usage_handler::*;
+pub use record_llm_usage::*;
+pub use update_llm_budget_handler::*;
+
+use database::enums::UserOrganizationRole;
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+/// Why an LLM usage request or a chat was refused. Anything else that goes wrong is returned
+/// as a plain `anyhow::Error`.
+#[derive(Debug, thiserror::Error)]
+pub enum LlmUsageError {
+    #[error("User is not a member of this organization")]
+    NotMember,
+    #[error("User is not a workspace admin")]
+    NotWorkspaceAdmin,
+    #[error("{0}")]
+    Invalid(&'static str),
+    /// The organization's usage this month has reached its budget
+    #[error("LLM budget exceeded: ${month_to_date_usd:.2} of the organization's ${monthly_budget_usd:.2} monthly budget has been used")]
+    BudgetExceeded {
+        monthly_budget_usd: f64,
+        month_to_date_usd: f64,
+    },
+}
+
+/// LLM usage and budgets can only be seen and changed by the organization's workspace admins
+fn require_workspace_admin(
+    user: &AuthenticatedUser,
+    organization_id: Uuid,
+) -> Result<(), LlmUsageError> {
+    let user_org = user
+        .organizations
+        .iter()
+        .find(|org| org.id == organization_id)
+        .ok_or(LlmUsageError::NotMember)?;
+
+    if user_org.role != UserOrganizationRole::WorkspaceAdmin {
+        return Err(LlmUsageError::NotWorkspaceAdmin);
+    }
+
+    Ok(())
+}
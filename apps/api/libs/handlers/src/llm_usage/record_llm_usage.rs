@@ -0,0 +1,112 @@
+use agents::{Agent, LlmCallUsage};
+use anyhow::Result;
+use chrono::Utc;
+use database::{models::LlmUsage, pool::get_pg_pool, schema::llm_usage};
+use diesel::insert_into;
+use diesel_async::RunQueryDsl;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Stores the usage of the agent's LLM calls when answering a message ends, however it ends:
+/// with a response, with an error returned partway through, or with the request cancelled.
+/// Failing to store it doesn't fail the message.
+pub struct MessageLlmUsageGuard {
+    agent: Arc<Agent>,
+    organization_id: Uuid,
+    user_id: Uuid,
+    chat_id: Uuid,
+    message_id: Uuid,
+}
+
+impl MessageLlmUsageGuard {
+    pub fn new(
+        agent: Arc<Agent>,
+        organization_id: Uuid,
+        user_id: Uuid,
+        chat_id: Uuid,
+        message_id: Uuid,
+    ) -> Self {
+        Self {
+            agent,
+            organization_id,
+            user_id,
+            chat_id,
+            message_id,
+        }
+    }
+}
+
+impl Drop for MessageLlmUsageGuard {
+    fn drop(&mut self) {
+        let Ok(runtime) = tokio::runtime::Handle::try_current() else {
+            tracing::warn!(
+                "No runtime to record LLM usage for message {}",
+                self.message_id
+            );
+            return;
+        };
+
+        let agent = self.agent.clone();
+        let (organization_id, user_id, chat_id, message_id) = (
+            self.organization_id,
+            self.user_id,
+            self.chat_id,
+            self.message_id,
+        );
+        runtime.spawn(async move {
+            let usage = agent.take_usage().await;
+            let recorded =
+                record_message_llm_usage(organization_id, user_id, chat_id, message_id, usage)
+                    .await;
+            if let Err(e) = recorded {
+                tracing::warn!(
+                    "Failed to record LLM usage for message {}: {}",
+                    message_id,
+                    e
+                );
+            }
+        });
+    }
+}
+
+/// Stores the usage of the LLM calls made while answering a message
+pub async fn record_message_llm_usage(
+    organization_id: Uuid,
+    user_id: Uuid,
+    chat_id: Uuid,
+    message_id: Uuid,
+    usage: Vec<LlmCallUsage>,
+) -> Result<()> {
+    if usage.is_empty() {
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    let rows: Vec<LlmUsage> = usage
+        .into_iter()
+        .map(|call| LlmUsage {
+            id: Uuid::new_v4(),
+            organization_id,
+            user_id,
+            chat_id: Some(chat_id),
+            message_id: Some(message_id),
+            cost_micros: call.cost_micros(),
+            model: call.model,
+            mode: call.mode,
+            tool_name: call.tool_name,
+            prompt_tokens: call.prompt_tokens,
+            completion_tokens: call.completion_tokens,
+            reasoning_tokens: call.reasoning_tokens,
+            total_tokens: call.total_tokens,
+            created_at: now,
+        })
+        .collect();
+
+    let mut conn = get_pg_pool().get().await?;
+    insert_into(llm_usage::table)
+        .values(&rows)
+        .execute(&mut conn)
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,67 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LlmUsageGroupBy {
+    User,
+    Team,
+    Chat,
+    Model,
+    #[default]
+    Mode,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct LlmUsageQuery {
+    /// Defaults to the start of the current month
+    pub from: Option<DateTime<Utc>>,
+    /// Defaults to now
+    pub to: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub group_by: LlmUsageGroupBy,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LlmUsageTotals {
+    pub calls: i64,
+    pub prompt_tokens: i64,
+    pub completion_tokens: i64,
+    pub reasoning_tokens: i64,
+    pub total_tokens: i64,
+    pub cost_usd: f64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LlmUsageGroup {
+    /// The id of the user, team or chat, or the model or mode name. None for usage that
+    /// doesn't belong to any, e.g. the usage of users who aren't on a team.
+    pub key: Option<String>,
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub totals: LlmUsageTotals,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LlmBudgetStatus {
+    pub monthly_budget_usd: f64,
+    /// Cost of the usage since the start of the current month
+    pub month_to_date_usd: f64,
+    pub exceeded: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LlmUsageResponse {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub group_by: LlmUsageGroupBy,
+    pub totals: LlmUsageTotals,
+    pub groups: Vec<LlmUsageGroup>,
+    pub budget: Option<LlmBudgetStatus>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct UpdateLlmBudgetRequest {
+    /// None removes the budget
+    pub monthly_budget_usd: Option<f64>,
+}
@@ -0,0 +1,62 @@
+use anyhow::Result;
+use chrono::Utc;
+use database::{models::LlmBudget, pool::get_pg_pool, schema::llm_budgets};
+use diesel::{insert_into, upsert::excluded, ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+use super::{
+    budget::{get_llm_budget_status, usd_to_micros},
+    require_workspace_admin,
+    types::{LlmBudgetStatus, UpdateLlmBudgetRequest},
+    LlmUsageError,
+};
+
+/// Sets or removes an organization's monthly LLM budget. Only workspace admins can change it.
+pub async fn update_llm_budget_handler(
+    user: &AuthenticatedUser,
+    organization_id: Uuid,
+    request: UpdateLlmBudgetRequest,
+) -> Result<Option<LlmBudgetStatus>> {
+    require_workspace_admin(user, organization_id)?;
+
+    let mut conn = get_pg_pool().get().await?;
+
+    match request.monthly_budget_usd {
+        Some(budget_usd) => {
+            let monthly_budget_micros = usd_to_micros(budget_usd);
+            if !budget_usd.is_finite() || monthly_budget_micros <= 0 {
+                return Err(
+                    LlmUsageError::Invalid("Invalid budget: must be a positive amount").into(),
+                );
+            }
+
+            let now = Utc::now();
+            insert_into(llm_budgets::table)
+                .values(&LlmBudget {
+                    organization_id,
+                    monthly_budget_micros,
+                    created_at: now,
+                    updated_at: now,
+                })
+                .on_conflict(llm_budgets::organization_id)
+                .do_update()
+                .set((
+                    llm_budgets::monthly_budget_micros
+                        .eq(excluded(llm_budgets::monthly_budget_micros)),
+                    llm_budgets::updated_at.eq(now),
+                ))
+                .execute(&mut conn)
+                .await?;
+        }
+        None => {
+            diesel::delete(llm_budgets::table)
+                .filter(llm_budgets::organization_id.eq(organization_id))
+                .execute(&mut conn)
+                .await?;
+        }
+    }
+
+    get_llm_budget_status(organization_id).await
+}
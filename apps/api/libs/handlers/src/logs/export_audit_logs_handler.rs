@@ -0,0 +1,77 @@
+use database::models::AuditLog;
+use database::pool::get_pg_pool;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
+
+use crate::utils::csv::csv_row;
+
+use super::list_audit_logs_handler::{filtered_audit_logs, AuditLogFilters};
+
+// Larger exports should be split up with the `from`/`to` filters
+const MAX_EXPORTED_ROWS: i64 = 100_000;
+
+const CSV_HEADER: [&str; 15] = [
+    "id",
+    "created_at",
+    "organization_id",
+    "user_id",
+    "event_type",
+    "surface",
+    "data_source_id",
+    "query_text",
+    "query_hash",
+    "row_count",
+    "duration_ms",
+    "error",
+    "asset_id",
+    "asset_type",
+    "details",
+];
+
+/// Export an organization's audit log as CSV, newest first
+pub async fn export_audit_logs_handler(
+    filters: AuditLogFilters,
+    organization_id: Uuid,
+) -> Result<String, anyhow::Error> {
+    let mut conn = get_pg_pool().get().await?;
+
+    let audit_logs = filtered_audit_logs(organization_id, &filters)
+        .limit(MAX_EXPORTED_ROWS)
+        .select(AuditLog::as_select())
+        .load::<AuditLog>(&mut conn)
+        .await?;
+
+    Ok(audit_logs_to_csv(&audit_logs))
+}
+
+fn audit_logs_to_csv(audit_logs: &[AuditLog]) -> String {
+    let mut csv = csv_row(CSV_HEADER.iter().map(|field| field.to_string()));
+    for audit_log in audit_logs {
+        csv.push_str(&csv_row([
+            audit_log.id.to_string(),
+            audit_log.created_at.to_rfc3339(),
+            audit_log.organization_id.to_string(),
+            optional(audit_log.user_id),
+            audit_log.event_type.to_str().to_string(),
+            audit_log.surface.to_str().to_string(),
+            optional(audit_log.data_source_id),
+            audit_log.query_text.clone().unwrap_or_default(),
+            audit_log.query_hash.clone().unwrap_or_default(),
+            optional(audit_log.row_count),
+            optional(audit_log.duration_ms),
+            audit_log.error.clone().unwrap_or_default(),
+            optional(audit_log.asset_id),
+            audit_log
+                .asset_type
+                .map(|asset_type| asset_type.to_string().to_string())
+                .unwrap_or_default(),
+            audit_log.details.to_string(),
+        ]));
+    }
+    csv
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
@@ -0,0 +1,103 @@
+use chrono::{DateTime, Utc};
+use database::enums::{AuditEventType, AuditSurface};
+use database::models::AuditLog;
+use database::pool::get_pg_pool;
+use database::schema::audit_logs;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::PaginationInfo;
+
+/// Filters shared by the audit log listing and export. Every filter is optional.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuditLogFilters {
+    pub user_id: Option<Uuid>,
+    pub event_type: Option<AuditEventType>,
+    pub surface: Option<AuditSurface>,
+    pub data_source_id: Option<Uuid>,
+    pub asset_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>, // Inclusive
+    pub to: Option<DateTime<Utc>>,   // Exclusive
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListAuditLogsRequest {
+    pub filters: AuditLogFilters,
+    pub page: Option<i32>,
+    pub page_size: i32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListAuditLogsResponse {
+    pub items: Vec<AuditLog>,
+    pub pagination: PaginationInfo,
+}
+
+/// List an organization's audit log, newest first, with pagination support
+pub async fn list_audit_logs_handler(
+    request: ListAuditLogsRequest,
+    organization_id: Uuid,
+) -> Result<ListAuditLogsResponse, anyhow::Error> {
+    let mut conn = get_pg_pool().get().await?;
+
+    // Calculate offset based on page number
+    let page = request.page.unwrap_or(1).max(1);
+    let offset = (page - 1) * request.page_size;
+
+    let results = filtered_audit_logs(organization_id, &request.filters)
+        .offset(offset as i64)
+        .limit((request.page_size + 1) as i64)
+        .select(AuditLog::as_select())
+        .load::<AuditLog>(&mut conn)
+        .await?;
+
+    let has_more = results.len() > request.page_size as usize;
+    let items: Vec<AuditLog> = results
+        .into_iter()
+        .take(request.page_size as usize)
+        .collect();
+
+    let pagination = PaginationInfo {
+        has_more,
+        next_page: if has_more { Some(page + 1) } else { None },
+        total_items: items.len() as i32,
+    };
+
+    Ok(ListAuditLogsResponse { items, pagination })
+}
+
+pub(crate) fn filtered_audit_logs(
+    organization_id: Uuid,
+    filters: &AuditLogFilters,
+) -> audit_logs::BoxedQuery<'static, Pg> {
+    let mut query = audit_logs::table
+        .filter(audit_logs::organization_id.eq(organization_id))
+        .into_boxed();
+
+    if let Some(user_id) = filters.user_id {
+        query = query.filter(audit_logs::user_id.eq(user_id));
+    }
+    if let Some(event_type) = filters.event_type {
+        query = query.filter(audit_logs::event_type.eq(event_type));
+    }
+    if let Some(surface) = filters.surface {
+        query = query.filter(audit_logs::surface.eq(surface));
+    }
+    if let Some(data_source_id) = filters.data_source_id {
+        query = query.filter(audit_logs::data_source_id.eq(data_source_id));
+    }
+    if let Some(asset_id) = filters.asset_id {
+        query = query.filter(audit_logs::asset_id.eq(asset_id));
+    }
+    if let Some(from) = filters.from {
+        query = query.filter(audit_logs::created_at.ge(from));
+    }
+    if let Some(to) = filters.to {
+        query = query.filter(audit_logs::created_at.lt(to));
+    }
+
+    query.order_by((audit_logs::created_at.desc(), audit_logs::id.desc()))
+}
@@ -1,3 +1,7 @@
+pub mod export_audit_logs_handler;
+pub mod list_audit_logs_handler;
 pub mod list_logs_handler;
 
+pub use export_audit_logs_handler::*;
+pub use list_audit_logs_handler::*;
 pub use list_logs_handler::*;
@@ -0,0 +1,283 @@
+use std::pin::Pin;
+use std::time::Instant;
+
+use anyhow::{anyhow, Result};
+use database::{
+    enums::{AssetType, AuditSurface},
+    pool::get_pg_pool,
+    schema::metric_files,
+    types::MetricYml,
+};
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use futures::{Stream, StreamExt};
+use indexmap::IndexMap;
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+use query_engine::data_source_query_routes::column_level_security::get_column_masks;
+use query_engine::data_source_query_routes::query_audit::{
+    audit_streamed_query, QueryAuditContext,
+};
+use query_engine::data_source_query_routes::query_cancellation::CancellationToken;
+use query_engine::data_source_query_routes::query_stream::{query_engine_stream, QueryStream};
+use query_engine::data_source_query_routes::row_level_security::get_row_filters;
+use query_engine::data_source_query_routes::semantic_query::apply_semantic_query_policy;
+use query_engine::data_types::DataType;
+
+use crate::utils::csv::{csv_field, csv_quote, csv_row};
+
+use super::get_metric_data_handler::get_metric_for_data;
+
+// Rows read from the data source and written out at a time
+const EXPORT_BATCH_SIZE: usize = 5_000;
+
+/// CSV text of an export, one chunk per batch of rows
+pub type CsvStream = Pin<Box<dyn Stream<Item = Result<String>> + Send>>;
+
+/// A metric's full result as a CSV download
+pub struct MetricDataExport {
+    pub file_name: String,
+    pub csv: CsvStream,
+}
+
+/// Exports every row of a metric's query as CSV. Rows are streamed from the data source and
+/// written out batch by batch, so large exports are never held in memory. The viewer's row
+/// filters, column policies and semantic query policy apply as in `get_metric_data_handler`.
+pub async fn export_metric_data_handler(
+    metric_id: Uuid,
+    user: AuthenticatedUser,
+    version_number: Option<i32>,
+    password: Option<String>,
+) -> Result<MetricDataExport> {
+    let metric = get_metric_for_data(&metric_id, &user, version_number, password).await?;
+
+    let metric_yml: MetricYml = serde_yaml::from_str(&metric.file)
+        .map_err(|e| anyhow!("Failed to parse metric definition: {}", e))?;
+    let data_source_id = metric.data_source_id;
+
+    let mut conn = get_pg_pool().get().await?;
+    let metric_organization_id = metric_files::table
+        .filter(metric_files::id.eq(metric_id))
+        .select(metric_files::organization_id)
+        .first::<Uuid>(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Error retrieving metric organization: {}", e))?;
+    drop(conn);
+
+    // Viewers outside the metric's organization can only have reached it through a public link
+    let surface = if user
+        .organizations
+        .iter()
+        .any(|org| org.id == metric_organization_id)
+    {
+        AuditSurface::MetricData
+    } else {
+        AuditSurface::PublicShare
+    };
+    let audit_context =
+        QueryAuditContext::new(user.id, surface).with_asset(metric_id, AssetType::MetricFile);
+
+    let row_filters = get_row_filters(&user.id, &data_source_id)
+        .await
+        .map_err(|e| anyhow!("Error resolving row filters: {}", e))?;
+    let column_masks = get_column_masks(&user.id, &data_source_id)
+        .await
+        .map_err(|e| anyhow!("Error resolving column policies: {}", e))?;
+    let sql = apply_semantic_query_policy(&user.id, &data_source_id, &metric_yml.sql)
+        .await
+        .map_err(|e| anyhow!("Error applying semantic query policy: {}", e))?;
+
+    let started_at = Instant::now();
+    let rows = match query_engine_stream(
+        &data_source_id,
+        &sql,
+        EXPORT_BATCH_SIZE,
+        None,
+        &row_filters,
+        &column_masks,
+        &CancellationToken::new(),
+    )
+    .await
+    {
+        Ok(rows) => rows,
+        Err(e) => {
+            audit_streamed_query(
+                &audit_context,
+                &data_source_id,
+                &sql,
+                started_at,
+                None,
+                Some(e.to_string()),
+            )
+            .await;
+            return Err(e.context("Error executing metric query"));
+        }
+    };
+
+    for warning in &rows.warnings {
+        tracing::warn!("Exporting data for metric {}: {}", metric_id, warning);
+    }
+
+    let audit = ExportAudit {
+        context: audit_context,
+        data_source_id,
+        sql,
+        started_at,
+        row_count: 0,
+        recorded: false,
+    };
+
+    Ok(MetricDataExport {
+        file_name: export_file_name(&metric.name),
+        csv: csv_stream(rows, audit),
+    })
+}
+
+fn csv_stream(rows: QueryStream, audit: ExportAudit) -> CsvStream {
+    let stream = futures::stream::unfold(Some((rows, audit, false)), |state| async move {
+        let (mut rows, mut audit, header_written) = state?;
+
+        match rows.next().await {
+            Some(Ok(batch)) => {
+                audit.row_count = rows.row_count();
+
+                let mut csv = String::new();
+                // Column names come from the first row, so an empty result exports as an empty file
+                if !header_written {
+                    if let Some(first_row) = batch.first() {
+                        csv.push_str(&csv_row(first_row.keys().cloned()));
+                    }
+                }
+                for row in &batch {
+                    csv.push_str(&csv_record(row));
+                }
+
+                let header_written = header_written || !batch.is_empty();
+                Some((Ok(csv), Some((rows, audit, header_written))))
+            }
+            Some(Err(e)) => {
+                audit.record(Some(e.to_string())).await;
+                Some((Err(e.context("Error exporting metric data")), None))
+            }
+            None => {
+                audit.record(None).await;
+                None
+            }
+        }
+    });
+
+    Box::pin(stream)
+}
+
+fn csv_record(row: &IndexMap<String, DataType>) -> String {
+    let mut record = row.values().map(csv_value).collect::<Vec<_>>().join(",");
+    record.push_str("\r\n");
+    record
+}
+
+// Only text can be read as a formula; numbers keep their sign
+fn csv_value(value: &DataType) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::Null) | Err(_) => String::new(),
+        Ok(serde_json::Value::String(text)) => csv_field(&text),
+        Ok(other) => csv_quote(&other.to_string()),
+    }
+}
+
+fn export_file_name(metric_name: &str) -> String {
+    let name: String = metric_name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let name = name.trim_matches('_');
+
+    if name.is_empty() {
+        "metric_data.csv".to_string()
+    } else {
+        format!("{}.csv", name)
+    }
+}
+
+// Records the export in the audit log once it ends, including exports the client abandoned
+struct ExportAudit {
+    context: QueryAuditContext,
+    data_source_id: Uuid,
+    sql: String,
+    started_at: Instant,
+    row_count: i64,
+    recorded: bool,
+}
+
+impl ExportAudit {
+    async fn record(&mut self, error: Option<String>) {
+        self.recorded = true;
+        audit_streamed_query(
+            &self.context,
+            &self.data_source_id,
+            &self.sql,
+            self.started_at,
+            Some(self.row_count),
+            error,
+        )
+        .await;
+    }
+}
+
+impl Drop for ExportAudit {
+    fn drop(&mut self) {
+        if self.recorded {
+            return;
+        }
+
+        let context = self.context;
+        let data_source_id = self.data_source_id;
+        let sql = std::mem::take(&mut self.sql);
+        let started_at = self.started_at;
+        let row_count = self.row_count;
+        tokio::spawn(async move {
+            audit_streamed_query(
+                &context,
+                &data_source_id,
+                &sql,
+                started_at,
+                Some(row_count),
+                Some("Export was cancelled before it finished".to_string()),
+            )
+            .await;
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_record_formats_values() {
+        let row = IndexMap::from([
+            ("id".to_string(), DataType::Int8(Some(-3))),
+            (
+                "name".to_string(),
+                DataType::Text(Some("=SUM(A1), b".to_string())),
+            ),
+            ("amount".to_string(), DataType::Float8(None)),
+            ("note".to_string(), DataType::Null),
+        ]);
+
+        assert_eq!(csv_record(&row), "-3,\"'=SUM(A1), b\",,\r\n");
+    }
+
+    #[test]
+    fn test_export_file_name() {
+        assert_eq!(export_file_name("Revenue by month"), "Revenue_by_month.csv");
+        assert_eq!(export_file_name("../../etc"), "etc.csv");
+        assert_eq!(export_file_name("\"\""), "metric_data.csv");
+    }
+}
@@ -1,10 +1,11 @@
 use anyhow::{anyhow, Result};
 use database::{
+    enums::{AssetType, AuditSurface},
     pool::get_pg_pool,
-    schema::metric_files,
+    schema::{dataset_column_policies, dataset_row_filter_policies, datasets, metric_files},
     types::{data_metadata::DataMetadata, MetricYml},
 };
-use diesel::{ExpressionMethods, QueryDsl};
+use diesel::{dsl::exists, BoolExpressionMethods, ExpressionMethods, QueryDsl};
 use diesel_async::RunQueryDsl;
 use indexmap::IndexMap;
 use middleware::AuthenticatedUser;
@@ -12,6 +13,12 @@ use serde::{Deserialize, Serialize};
 use sharing::asset_access_checks::check_metric_collection_access;
 use uuid::Uuid;
 
+use query_engine::data_source_query_routes::column_level_security::get_column_masks;
+use query_engine::data_source_query_routes::query_audit::{audit_query, QueryAuditContext};
+use query_engine::data_source_query_routes::query_cache::{query_engine_cached, QueryCacheContext};
+use query_engine::data_source_query_routes::query_cancellation::CancellationToken;
+use query_engine::data_source_query_routes::row_level_security::get_row_filters;
+use query_engine::data_source_query_routes::semantic_query::apply_semantic_query_policy;
 use query_engine::data_types::DataType;
 
 use crate::metrics::{get_metric_for_dashboard_handler, get_metric_handler, BusterMetric};
@@ -23,6 +30,8 @@ pub struct GetMetricDataRequest {
     pub version_number: Option<i32>,
     pub limit: Option<i64>,
     pub password: Option<String>,
+    /// `next_cursor` from a previous response, to fetch the following page
+    pub cursor: Option<String>,
 }
 
 /// Structure for the metric data response
@@ -32,6 +41,12 @@ pub struct MetricDataResponse {
     pub data: Vec<IndexMap<String, DataType>>,
     pub data_metadata: DataMetadata,
     pub has_more_records: bool,
+    /// Cursor for the next page. Only queries with an ORDER BY can be paged past the first page.
+    pub next_cursor: Option<String>,
+    /// True when the data was served from the query result cache
+    pub cache_hit: bool,
+    /// Complexity policy and row limit warnings raised while running the query
+    pub warnings: Vec<String>,
 }
 
 /// Handler to retrieve both the metric definition and its associated data
@@ -45,12 +60,278 @@ pub async fn get_metric_data_handler(
         user.id
     );
 
-    // --- Step 1: Try retrieving metric with standard permission checks ---
-    let metric_result = get_metric_handler(
+    let metric = get_metric_for_data(
         &request.metric_id,
         &user,
         request.version_number,
-        request.password.clone(), // Clone password for potential reuse/logging
+        request.password.clone(),
+    )
+    .await?;
+
+    // --- Step 5: Proceed with data fetching using the obtained metric definition ---
+    tracing::debug!("Parsing metric definition from YAML to get SQL.");
+    // Parse the metric definition from YAML to get SQL
+    let metric_yml: MetricYml = match serde_yaml::from_str(&metric.file) {
+        Ok(yml) => yml,
+        Err(parse_err) => {
+            tracing::error!("Failed to parse metric YAML: {}", parse_err);
+            return Err(anyhow!("Failed to parse metric definition: {}", parse_err));
+        }
+    };
+    let sql = metric_yml.sql;
+
+    // --- USE DIRECT DATA SOURCE ID ---
+    let data_source_id = metric.data_source_id; // Already a Uuid
+    tracing::debug!(metric_id = %request.metric_id, data_source_id = %data_source_id, "Using direct data source ID from metric");
+
+    tracing::info!(
+        "Querying data for metric {}. Data source: {}, Limit: {:?}", // Removed dataset name as we don't fetch it anymore
+        request.metric_id,
+        data_source_id, // Use the direct ID
+        request.limit
+    );
+
+    // Determine the actual query limit - we query for one extra record to check if there are more
+    let display_limit = request.limit.unwrap_or(5000).min(5000);
+    let query_limit = display_limit + 1;
+
+    // Try to get cached metadata first
+    let mut conn_meta = get_pg_pool().get().await?;
+    let (cached_metadata, metric_organization_id) = metric_files::table
+        .filter(metric_files::id.eq(request.metric_id))
+        .select((metric_files::data_metadata, metric_files::organization_id))
+        .first::<(Option<DataMetadata>, Uuid)>(&mut conn_meta)
+        .await
+        .map_err(|e| anyhow!("Error retrieving cached metadata: {}", e))?;
+    tracing::debug!("Cached metadata found: {}", cached_metadata.is_some());
+
+    // Viewers outside the metric's organization can only have reached it through a public link
+    let surface = if user
+        .organizations
+        .iter()
+        .any(|org| org.id == metric_organization_id)
+    {
+        AuditSurface::MetricData
+    } else {
+        AuditSurface::PublicShare
+    };
+    let audit_context =
+        QueryAuditContext::new(user.id, surface).with_asset(request.metric_id, AssetType::MetricFile);
+
+    // The viewer only sees the rows their row filter policies allow and the columns their
+    // column policies leave visible
+    let row_filters = get_row_filters(&user.id, &data_source_id)
+        .await
+        .map_err(|e| anyhow!("Error resolving row filters: {}", e))?;
+    let column_masks = get_column_masks(&user.id, &data_source_id)
+        .await
+        .map_err(|e| anyhow!("Error resolving column policies: {}", e))?;
+    let restricted = !row_filters.is_empty() || !column_masks.is_empty();
+
+    // Viewers their organization limits to semantic queries only see metrics written as one
+    let sql = apply_semantic_query_policy(&user.id, &data_source_id, &sql)
+        .await
+        .map_err(|e| anyhow!("Error applying semantic query policy: {}", e))?;
+
+    // Stops the warehouse query if the client goes away before it finishes
+    let cancellation = CancellationToken::new();
+    let _cancel_on_drop = cancellation.clone().drop_guard();
+
+    // Execute the query to get the metric data. Follow-up pages resume from the cursor.
+    let (data, query_metadata, has_more_records, next_cursor, cache_hit, warnings) = if let Some(cursor) = request.cursor.as_deref() {
+        let page = match audit_query(
+            &audit_context,
+            &data_source_id,
+            &sql,
+            query_engine::data_source_query_routes::query_pagination::query_engine_page(
+                &data_source_id,
+                &sql,
+                display_limit,
+                Some(cursor),
+                &row_filters,
+                &column_masks,
+                &cancellation,
+            ),
+        )
+        .await
+        {
+            Ok(page) => page,
+            Err(e) => {
+                tracing::error!(
+                    "Error executing metric query page for metric {}: {}",
+                    request.metric_id,
+                    e
+                );
+                return Err(e.context("Error executing metric query"));
+            }
+        };
+
+        (
+            page.data,
+            page.metadata,
+            page.next_cursor.is_some(),
+            page.next_cursor,
+            false,
+            page.warnings,
+        )
+    } else {
+        let cache_context = QueryCacheContext {
+            metric_id: Some(request.metric_id),
+            row_filters,
+            column_masks,
+        };
+        let (query_result, cache_hit) = match audit_query(
+            &audit_context,
+            &data_source_id,
+            &sql,
+            query_engine_cached(
+                &data_source_id, // Use the direct ID
+                &sql,
+                Some(query_limit),
+                &cache_context,
+                &cancellation,
+            ),
+        )
+        .await
+        {
+            Ok((result, cache_hit)) => {
+                tracing::info!(
+                    "Successfully executed metric query. Rows returned: {}, cache hit: {}",
+                    result.data.len(),
+                    cache_hit
+                );
+                (result, cache_hit)
+            }
+            Err(e) => {
+                tracing::error!(
+                    "Error executing metric query for metric {}: {}",
+                    request.metric_id,
+                    e
+                );
+                return Err(e.context("Error executing metric query"));
+            }
+        };
+
+        // Check if we have more records than the display limit
+        let has_more_records = query_result.data.len() > display_limit as usize;
+
+        // Truncate to display limit if we got more
+        let mut data = query_result.data;
+        if has_more_records {
+            data.truncate(display_limit as usize);
+        }
+
+        // Only ordered queries can be paged further; for the rest the first page is all there is
+        let next_cursor = if has_more_records {
+            query_engine::data_source_query_routes::query_pagination::next_page_cursor(
+                &data_source_id,
+                &sql,
+                display_limit as u64,
+            )
+            .await?
+        } else {
+            None
+        };
+
+        (
+            data,
+            query_result.metadata,
+            has_more_records,
+            next_cursor,
+            cache_hit,
+            query_result.warnings,
+        )
+    };
+
+    // Determine which metadata to use. Cached metadata is computed for whoever last saved the
+    // metric, under their own row filters and column masks, so it's only reused when no policy
+    // on the data source could make it differ between viewers.
+    let cached_metadata = match cached_metadata {
+        Some(metadata) if !restricted && !has_data_policies(&data_source_id).await? => {
+            Some(metadata)
+        }
+        _ => None,
+    };
+    let final_metadata = if let Some(metadata) = cached_metadata {
+        tracing::debug!(
+            "Using cached metadata. Cached rows: {}, Query rows: {}",
+            metadata.row_count,
+            data.len()
+        );
+        // Use cached metadata but update row count if it differs significantly or if cached count is 0
+        // (We update if different because the cache might be stale regarding row count)
+        if metadata.row_count != data.len() as i64 {
+            tracing::debug!("Row count changed. Updating metadata row count.");
+            let mut updated_metadata = metadata.clone();
+            updated_metadata.row_count = data.len() as i64;
+            // Potentially update updated_at? For now, just row count.
+            updated_metadata
+        } else {
+            metadata
+        }
+    } else {
+        tracing::debug!("No cached metadata found. Using metadata from query result.");
+        // No cached metadata, use the one from query_result
+        let mut metadata = query_metadata;
+        // Update row count to match the actual data we're returning
+        metadata.row_count = data.len() as i64;
+        metadata
+    };
+
+    // Construct and return the response
+    tracing::info!(
+        "Successfully retrieved data for metric {}. Returning response with has_more_records: {}",
+        request.metric_id,
+        has_more_records
+    );
+    Ok(MetricDataResponse {
+        metric_id: request.metric_id,
+        data,
+        data_metadata: final_metadata,
+        has_more_records,
+        next_cursor,
+        cache_hit,
+        warnings,
+    })
+}
+
+// True when any row filter or column policy is set on the data source's datasets
+async fn has_data_policies(data_source_id: &Uuid) -> Result<bool> {
+    let mut conn = get_pg_pool().get().await?;
+
+    let row_policies = dataset_row_filter_policies::table
+        .inner_join(datasets::table)
+        .filter(datasets::data_source_id.eq(data_source_id))
+        .filter(datasets::deleted_at.is_null())
+        .filter(dataset_row_filter_policies::deleted_at.is_null())
+        .select(dataset_row_filter_policies::id);
+    let column_policies = dataset_column_policies::table
+        .inner_join(datasets::table)
+        .filter(datasets::data_source_id.eq(data_source_id))
+        .filter(datasets::deleted_at.is_null())
+        .filter(dataset_column_policies::deleted_at.is_null())
+        .select(dataset_column_policies::id);
+
+    diesel::select(exists(row_policies).or(exists(column_policies)))
+        .get_result::<bool>(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Error checking data source policies: {}", e))
+}
+
+/// Fetches the metric whose data is requested. Viewers without direct access to the metric can
+/// still read it through a dashboard, chat or collection that contains it.
+pub(crate) async fn get_metric_for_data(
+    metric_id: &Uuid,
+    user: &AuthenticatedUser,
+    version_number: Option<i32>,
+    password: Option<String>,
+) -> Result<BusterMetric> {
+    // --- Step 1: Try retrieving metric with standard permission checks ---
+    let metric_result = get_metric_handler(
+        metric_id,
+        user,
+        version_number,
+        password.clone(), // Clone password for potential reuse/logging
     )
     .await;
 
@@ -73,7 +354,7 @@ pub async fn get_metric_data_handler(
                 );
 
                 // Check if user has access to ANY dashboard containing this metric (including public dashboards)
-                let has_dashboard_access = sharing::check_metric_dashboard_access(&request.metric_id, &user.id, &user.organizations)
+                let has_dashboard_access = sharing::check_metric_dashboard_access(metric_id, &user.id, &user.organizations)
                     .await
                     .unwrap_or(false);
 
@@ -81,10 +362,10 @@ pub async fn get_metric_data_handler(
                     // User has access to a dashboard containing this metric
                     tracing::info!("Found associated dashboard with user access. Fetching metric with dashboard context.");
                     match get_metric_for_dashboard_handler(
-                        &request.metric_id,
-                        &user,
-                        request.version_number,
-                        request.password.clone(),
+                        metric_id,
+                        user,
+                        version_number,
+                        password.clone(),
                     )
                     .await
                     {
@@ -103,7 +384,7 @@ pub async fn get_metric_data_handler(
                 } else {
                     // No dashboard access, check if user has access via a chat
                     tracing::info!("No dashboard association found. Checking chat access.");
-                    let has_chat_access = sharing::check_metric_chat_access(&request.metric_id, &user.id, &user.organizations)
+                    let has_chat_access = sharing::check_metric_chat_access(metric_id, &user.id, &user.organizations)
                         .await
                         .unwrap_or(false);
 
@@ -111,10 +392,10 @@ pub async fn get_metric_data_handler(
                         // User has access to a chat containing this metric
                         tracing::info!("Found associated chat with user access. Fetching metric with chat context.");
                         match get_metric_for_dashboard_handler(
-                            &request.metric_id,
-                            &user,
-                            request.version_number,
-                            request.password.clone(),
+                            metric_id,
+                            user,
+                            version_number,
+                            password.clone(),
                         )
                         .await
                         {
@@ -133,7 +414,7 @@ pub async fn get_metric_data_handler(
                     } else {
                         // No chat access, check if user has access via a collection
                         tracing::info!("No chat association found. Checking collection access.");
-                        let has_collection_access = check_metric_collection_access(&request.metric_id, &user.id, &user.organizations)
+                        let has_collection_access = check_metric_collection_access(metric_id, &user.id, &user.organizations)
                             .await
                             .unwrap_or(false);
 
@@ -141,10 +422,10 @@ pub async fn get_metric_data_handler(
                             // User has access to a collection containing this metric
                             tracing::info!("Found associated collection with user access. Fetching metric with collection context.");
                             match get_metric_for_dashboard_handler(
-                                &request.metric_id,
-                                &user,
-                                request.version_number,
-                                request.password.clone(),
+                                metric_id,
+                                user,
+                                version_number,
+                                password.clone(),
                             )
                             .await
                             {
@@ -175,114 +456,5 @@ pub async fn get_metric_data_handler(
         }
     };
 
-    // --- Step 5: Proceed with data fetching using the obtained metric definition ---
-    tracing::debug!("Parsing metric definition from YAML to get SQL.");
-    // Parse the metric definition from YAML to get SQL
-    let metric_yml: MetricYml = match serde_yaml::from_str(&metric.file) {
-        Ok(yml) => yml,
-        Err(parse_err) => {
-            tracing::error!("Failed to parse metric YAML: {}", parse_err);
-            return Err(anyhow!("Failed to parse metric definition: {}", parse_err));
-        }
-    };
-    let sql = metric_yml.sql;
-
-    // --- USE DIRECT DATA SOURCE ID ---
-    let data_source_id = metric.data_source_id; // Already a Uuid
-    tracing::debug!(metric_id = %request.metric_id, data_source_id = %data_source_id, "Using direct data source ID from metric");
-
-    tracing::info!(
-        "Querying data for metric {}. Data source: {}, Limit: {:?}", // Removed dataset name as we don't fetch it anymore
-        request.metric_id,
-        data_source_id, // Use the direct ID
-        request.limit
-    );
-
-    // Determine the actual query limit - we query for one extra record to check if there are more
-    let display_limit = request.limit.unwrap_or(5000).min(5000);
-    let query_limit = display_limit + 1;
-
-    // Try to get cached metadata first
-    let mut conn_meta = get_pg_pool().get().await?;
-    let cached_metadata = metric_files::table
-        .filter(metric_files::id.eq(request.metric_id))
-        .select(metric_files::data_metadata)
-        .first::<Option<DataMetadata>>(&mut conn_meta)
-        .await
-        .map_err(|e| anyhow!("Error retrieving cached metadata: {}", e))?;
-    tracing::debug!("Cached metadata found: {}", cached_metadata.is_some());
-
-    // Execute the query to get the metric data
-    let query_result = match query_engine::data_source_query_routes::query_engine::query_engine(
-        &data_source_id, // Use the direct ID
-        &sql,
-        Some(query_limit),
-    )
-    .await
-    {
-        Ok(result) => {
-            tracing::info!(
-                "Successfully executed metric query. Rows returned: {}",
-                result.data.len()
-            );
-            result
-        }
-        Err(e) => {
-            tracing::error!(
-                "Error executing metric query for metric {}: {}",
-                request.metric_id,
-                e
-            );
-            return Err(anyhow!("Error executing metric query: {}", e));
-        }
-    };
-
-    // Check if we have more records than the display limit
-    let has_more_records = query_result.data.len() > display_limit as usize;
-    
-    // Truncate to display limit if we got more
-    let mut data = query_result.data;
-    if has_more_records {
-        data.truncate(display_limit as usize);
-    }
-
-    // Determine which metadata to use
-    let final_metadata = if let Some(metadata) = cached_metadata {
-        tracing::debug!(
-            "Using cached metadata. Cached rows: {}, Query rows: {}",
-            metadata.row_count,
-            data.len()
-        );
-        // Use cached metadata but update row count if it differs significantly or if cached count is 0
-        // (We update if different because the cache might be stale regarding row count)
-        if metadata.row_count != data.len() as i64 {
-            tracing::debug!("Row count changed. Updating metadata row count.");
-            let mut updated_metadata = metadata.clone();
-            updated_metadata.row_count = data.len() as i64;
-            // Potentially update updated_at? For now, just row count.
-            updated_metadata
-        } else {
-            metadata
-        }
-    } else {
-        tracing::debug!("No cached metadata found. Using metadata from query result.");
-        // No cached metadata, use the one from query_result
-        let mut metadata = query_result.metadata.clone();
-        // Update row count to match the actual data we're returning
-        metadata.row_count = data.len() as i64;
-        metadata
-    };
-
-    // Construct and return the response
-    tracing::info!(
-        "Successfully retrieved data for metric {}. Returning response with has_more_records: {}",
-        request.metric_id,
-        has_more_records
-    );
-    Ok(MetricDataResponse {
-        metric_id: request.metric_id,
-        data,
-        data_metadata: final_metadata,
-        has_more_records,
-    })
+    Ok(metric)
 }
@@ -1,6 +1,7 @@
 pub mod bulk_update_metrics_handler;
 pub mod color_palette_helpers;
 pub mod delete_metric_handler;
+pub mod export_metric_data_handler;
 pub mod get_metric_data_handler;
 pub mod get_metric_handler;
 pub mod list_metrics_handler;
@@ -1,7 +1,8 @@
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Utc};
 use database::{
-    enums::{AssetPermissionRole, AssetType, WorkspaceSharing},
+    enums::{AssetPermissionRole, AssetType, AuditEventType, WorkspaceSharing},
+    helpers::audit_logs::record_asset_access,
     helpers::metric_files::fetch_metric_file_with_permissions,
     pool::get_pg_pool,
     schema::metric_files::dsl,
@@ -242,6 +243,15 @@ pub async fn update_metric_sharing_handler(
         }
     }
     
+    // Enabling a public link shares the metric with anyone who has it, so it's audited like a share
+    let public_link_details = (request.publicly_accessible == Some(true)).then(|| {
+        serde_json::json!({
+            "public_link": true,
+            "password_protected": public_password.is_some(),
+            "public_expiry_date": public_expiry_date,
+        })
+    });
+
     // Execute the update if any changes were made
     if update_needed {
         diesel::update(dsl::metric_files)
@@ -259,6 +269,17 @@ pub async fn update_metric_sharing_handler(
             .await?;
     }
 
+    if let Some(details) = public_link_details {
+        record_asset_access(
+            &user.id,
+            metric_id,
+            AssetType::MetricFile,
+            AuditEventType::AssetShare,
+            details,
+        )
+        .await;
+    }
+
     Ok(())
 }
 
@@ -1,7 +1,10 @@
 use anyhow::{anyhow, bail, Result};
 use chrono::{DateTime, Utc};
 use database::{
-    enums::{AssetPermissionRole, AssetType, DataSourceType, IdentityType, Verification, WorkspaceSharing},
+    enums::{
+        AssetPermissionRole, AssetType, AuditSurface, DataSourceType, IdentityType, Verification,
+        WorkspaceSharing,
+    },
     helpers::metric_files::fetch_metric_file_with_permissions,
     models::{Dataset, MetricFile, MetricFileToDataset},
     pool::get_pg_pool,
@@ -16,7 +19,12 @@ use diesel::{insert_into, AsChangeset, ExpressionMethods, QueryDsl};
 use diesel_async::RunQueryDsl;
 use indexmap;
 use middleware::AuthenticatedUser;
-use query_engine::data_source_query_routes::query_engine::query_engine;
+use query_engine::data_source_query_routes::{
+    query_audit::{audit_query, QueryAuditContext},
+    query_cache::invalidate_metric_cache,
+    query_cancellation::CancellationToken,
+    query_engine::query_engine_for_user,
+};
 use serde_json::Value;
 use sharing::check_permission_access;
 use sql_analyzer::{analyze_query, types::TableKind};
@@ -242,7 +250,25 @@ pub async fn update_metric_handler(
             }
 
             // 4. Execute Query for Metadata (using the same data_source_id)
-            match query_engine(&ds_id, &final_content.sql, Some(100)).await {
+            let audit_context = QueryAuditContext::new(user.id, AuditSurface::Api)
+                .with_asset(*metric_id, AssetType::MetricFile);
+            // Stops the query if the request is dropped
+            let cancellation = CancellationToken::new();
+            let _cancel_on_drop = cancellation.clone().drop_guard();
+            match audit_query(
+                &audit_context,
+                &ds_id,
+                &final_content.sql,
+                query_engine_for_user(
+                    &user.id,
+                    &ds_id,
+                    &final_content.sql,
+                    Some(100),
+                    &cancellation,
+                ),
+            )
+            .await
+            {
                 Ok(query_result) => {
                     data_metadata = Some(query_result.metadata.clone());
                     // Update column formats based on new metadata
@@ -349,6 +375,11 @@ pub async fn update_metric_handler(
         .await
         .map_err(|e| anyhow!("Failed to update metric file record: {}", e))?;
 
+    // Results cached for the previous definition must not be served for the new one
+    if let Err(e) = invalidate_metric_cache(metric_id).await {
+        tracing::warn!("Failed to invalidate query cache for metric {}: {}", metric_id, e);
+    }
+
     // --- Update Dataset Associations for the NEW/UPDATED version ---
     let now = Utc::now();
     let new_associations: Vec<MetricFileToDataset> = validated_dataset_ids
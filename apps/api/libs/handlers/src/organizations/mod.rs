@@ -1,6 +1,8 @@
+pub mod model_routes_handler;
 pub mod types;
 pub mod update_organization_handler;
 pub mod post_organization_handler;
 
+pub use model_routes_handler::*;
 pub use update_organization_handler::*;
 pub use post_organization_handler::*;
\ No newline at end of file
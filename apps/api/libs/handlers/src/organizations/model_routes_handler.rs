@@ -0,0 +1,163 @@
+use anyhow::Result;
+use chrono::Utc;
+use database::{models::ModelRoute, pool::get_pg_pool, schema::model_routes};
+use diesel::{insert_into, ExpressionMethods, QueryDsl};
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+use crate::{
+    organizations::types::ModelRouteBody,
+    query_settings::{require_workspace_admin, QuerySettingsError},
+};
+
+/// The models the organization routes its agent modes to. Modes without a route use the
+/// `default` route, or their own model if there is none.
+pub async fn get_model_routes_handler(
+    user: &AuthenticatedUser,
+    organization_id: Uuid,
+) -> Result<Vec<ModelRouteBody>> {
+    require_workspace_admin(user, organization_id)?;
+
+    let mut conn = get_pg_pool().get().await?;
+
+    let routes = model_routes::table
+        .filter(model_routes::organization_id.eq(organization_id))
+        .order(model_routes::mode.asc())
+        .load::<ModelRoute>(&mut conn)
+        .await?;
+
+    Ok(routes.into_iter().map(ModelRouteBody::from).collect())
+}
+
+/// Replaces the organization's model routes. Modes left out of `routes` go back to the
+/// default route. Only workspace admins can change them.
+pub async fn update_model_routes_handler(
+    user: &AuthenticatedUser,
+    organization_id: Uuid,
+    routes: Vec<ModelRouteBody>,
+) -> Result<Vec<ModelRouteBody>> {
+    require_workspace_admin(user, organization_id)?;
+
+    let routes = validate_routes(routes)?;
+
+    let mut conn = get_pg_pool().get().await?;
+
+    let now = Utc::now();
+    let rows: Vec<ModelRoute> = routes
+        .iter()
+        .map(|route| ModelRoute {
+            id: Uuid::new_v4(),
+            organization_id,
+            mode: route.mode,
+            models: route.models.clone(),
+            created_at: now,
+            updated_at: now,
+        })
+        .collect();
+
+    conn.transaction::<_, anyhow::Error, _>(|conn| {
+        async move {
+            diesel::delete(model_routes::table)
+                .filter(model_routes::organization_id.eq(organization_id))
+                .execute(conn)
+                .await?;
+
+            if !rows.is_empty() {
+                insert_into(model_routes::table)
+                    .values(&rows)
+                    .execute(conn)
+                    .await?;
+            }
+
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await?;
+
+    Ok(routes)
+}
+
+/// Trims model names and drops blank and repeated ones. Each mode can only be routed once and
+/// needs at least one model.
+fn validate_routes(routes: Vec<ModelRouteBody>) -> Result<Vec<ModelRouteBody>, QuerySettingsError> {
+    let mut validated: Vec<ModelRouteBody> = Vec::with_capacity(routes.len());
+
+    for route in routes {
+        if validated.iter().any(|other| other.mode == route.mode) {
+            return Err(QuerySettingsError::Invalid(format!(
+                "the {:?} mode is routed more than once",
+                route.mode
+            )));
+        }
+
+        let mut models: Vec<String> = Vec::with_capacity(route.models.len());
+        for model in route.models {
+            let model = model.trim();
+            if !model.is_empty() && !models.iter().any(|other| other == model) {
+                models.push(model.to_string());
+            }
+        }
+        if models.is_empty() {
+            return Err(QuerySettingsError::Invalid(format!(
+                "the {:?} mode needs at least one model",
+                route.mode
+            )));
+        }
+
+        validated.push(ModelRouteBody {
+            mode: route.mode,
+            models,
+        });
+    }
+
+    Ok(validated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use database::enums::ModelRouteMode;
+
+    fn route(mode: ModelRouteMode, models: &[&str]) -> ModelRouteBody {
+        ModelRouteBody {
+            mode,
+            models: models.iter().map(|model| model.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_validate_routes_cleans_models() {
+        let validated = validate_routes(vec![route(
+            ModelRouteMode::Default,
+            &[
+                " azure/gpt-4.1 ",
+                "",
+                "azure/gpt-4.1",
+                "bedrock/anthropic.claude-3-7-sonnet",
+            ],
+        )])
+        .unwrap();
+
+        assert_eq!(
+            validated,
+            vec![route(
+                ModelRouteMode::Default,
+                &["azure/gpt-4.1", "bedrock/anthropic.claude-3-7-sonnet"]
+            )]
+        );
+    }
+
+    #[test]
+    fn test_validate_routes_rejects_empty_and_repeated_modes() {
+        let result = validate_routes(vec![route(ModelRouteMode::Review, &[" "])]);
+        assert!(matches!(result, Err(QuerySettingsError::Invalid(_))));
+
+        let result = validate_routes(vec![
+            route(ModelRouteMode::Auxiliary, &["azure/gpt-4.1-nano"]),
+            route(ModelRouteMode::Auxiliary, &["azure/gpt-4.1-mini"]),
+        ]);
+        assert!(matches!(result, Err(QuerySettingsError::Invalid(_))));
+    }
+}
@@ -1,3 +1,4 @@
+use database::{enums::ModelRouteMode, models::ModelRoute};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -10,4 +11,19 @@ pub struct UpdateOrganizationRequest {
 pub struct OrganizationResponse {
     pub id: Uuid,
     pub name: String,
-}
\ No newline at end of file
+}
+/// The models an organization routes an agent mode to, in order of preference
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ModelRouteBody {
+    pub mode: ModelRouteMode,
+    pub models: Vec<String>,
+}
+
+impl From<ModelRoute> for ModelRouteBody {
+    fn from(route: ModelRoute) -> Self {
+        Self {
+            mode: route.mode,
+            models: route.models,
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use anyhow::Result;
+use chrono::Utc;
+use database::{
+    models::DataSourceCacheSettings, pool::get_pg_pool, schema::data_source_cache_settings,
+};
+use diesel::{insert_into, upsert::excluded, ExpressionMethods, OptionalExtension, QueryDsl};
+use diesel_async::RunQueryDsl;
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+use super::{require_data_source_admin, types::DataSourceCacheSettingsBody, QuerySettingsError};
+
+// Cached results are served without re-running the query, so they can't be allowed to go
+// more than a day stale
+const MAX_CACHE_TTL_SECONDS: i32 = 24 * 60 * 60;
+
+/// The data source's result cache TTL
+pub async fn get_data_source_cache_settings_handler(
+    user: &AuthenticatedUser,
+    data_source_id: Uuid,
+) -> Result<DataSourceCacheSettingsBody> {
+    require_data_source_admin(user, data_source_id).await?;
+
+    let mut conn = get_pg_pool().get().await?;
+
+    let ttl_seconds = data_source_cache_settings::table
+        .filter(data_source_cache_settings::data_source_id.eq(data_source_id))
+        .select(data_source_cache_settings::ttl_seconds)
+        .first::<i32>(&mut conn)
+        .await
+        .optional()?;
+
+    Ok(DataSourceCacheSettingsBody {
+        ttl_seconds: ttl_seconds.unwrap_or(0),
+    })
+}
+
+/// Sets how long the data source's query results are cached. A TTL of 0 turns caching off.
+pub async fn update_data_source_cache_settings_handler(
+    user: &AuthenticatedUser,
+    data_source_id: Uuid,
+    settings: DataSourceCacheSettingsBody,
+) -> Result<DataSourceCacheSettingsBody> {
+    require_data_source_admin(user, data_source_id).await?;
+
+    if !(0..=MAX_CACHE_TTL_SECONDS).contains(&settings.ttl_seconds) {
+        return Err(QuerySettingsError::Invalid(format!(
+            "ttl_seconds must be between 0 and {}",
+            MAX_CACHE_TTL_SECONDS
+        ))
+        .into());
+    }
+
+    let mut conn = get_pg_pool().get().await?;
+
+    let now = Utc::now();
+    let saved = insert_into(data_source_cache_settings::table)
+        .values(&DataSourceCacheSettings {
+            data_source_id,
+            ttl_seconds: settings.ttl_seconds,
+            created_at: now,
+            updated_at: now,
+        })
+        .on_conflict(data_source_cache_settings::data_source_id)
+        .do_update()
+        .set((
+            data_source_cache_settings::ttl_seconds
+                .eq(excluded(data_source_cache_settings::ttl_seconds)),
+            data_source_cache_settings::updated_at.eq(now),
+        ))
+        .get_result::<DataSourceCacheSettings>(&mut conn)
+        .await?;
+
+    Ok(DataSourceCacheSettingsBody {
+        ttl_seconds: saved.ttl_seconds,
+    })
+}
@@ -0,0 +1,160 @@
+use anyhow::Result;
+use chrono::Utc;
+use database::{
+    models::QueryComplexityPolicy, pool::get_pg_pool, schema::query_complexity_policies,
+};
+use diesel::{insert_into, upsert::excluded, ExpressionMethods, OptionalExtension, QueryDsl};
+use diesel_async::RunQueryDsl;
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+use super::{require_workspace_admin, types::QueryComplexityPolicySettings, QuerySettingsError};
+
+/// The organization's query complexity policy, if it has one
+pub async fn get_query_complexity_policy_handler(
+    user: &AuthenticatedUser,
+    organization_id: Uuid,
+) -> Result<Option<QueryComplexityPolicySettings>> {
+    require_workspace_admin(user, organization_id)?;
+
+    let mut conn = get_pg_pool().get().await?;
+
+    let policy = query_complexity_policies::table
+        .filter(query_complexity_policies::organization_id.eq(organization_id))
+        .first::<QueryComplexityPolicy>(&mut conn)
+        .await
+        .optional()?;
+
+    Ok(policy.map(QueryComplexityPolicySettings::from))
+}
+
+/// Sets the organization's query complexity policy, or removes it when `settings` is None.
+/// Only workspace admins can change it.
+pub async fn update_query_complexity_policy_handler(
+    user: &AuthenticatedUser,
+    organization_id: Uuid,
+    settings: Option<QueryComplexityPolicySettings>,
+) -> Result<Option<QueryComplexityPolicySettings>> {
+    require_workspace_admin(user, organization_id)?;
+
+    let mut conn = get_pg_pool().get().await?;
+
+    let Some(settings) = settings else {
+        diesel::delete(query_complexity_policies::table)
+            .filter(query_complexity_policies::organization_id.eq(organization_id))
+            .execute(&mut conn)
+            .await?;
+        return Ok(None);
+    };
+
+    let settings = validate_policy(settings)?;
+
+    let now = Utc::now();
+    let policy = insert_into(query_complexity_policies::table)
+        .values(&QueryComplexityPolicy {
+            organization_id,
+            max_score: settings.max_score,
+            max_joins: settings.max_joins,
+            max_subquery_depth: settings.max_subquery_depth,
+            reject_cartesian_products: settings.reject_cartesian_products,
+            large_tables: settings.large_tables,
+            enforcement: settings.enforcement,
+            created_at: now,
+            updated_at: now,
+        })
+        .on_conflict(query_complexity_policies::organization_id)
+        .do_update()
+        .set((
+            query_complexity_policies::max_score.eq(excluded(query_complexity_policies::max_score)),
+            query_complexity_policies::max_joins.eq(excluded(query_complexity_policies::max_joins)),
+            query_complexity_policies::max_subquery_depth
+                .eq(excluded(query_complexity_policies::max_subquery_depth)),
+            query_complexity_policies::reject_cartesian_products.eq(excluded(
+                query_complexity_policies::reject_cartesian_products,
+            )),
+            query_complexity_policies::large_tables
+                .eq(excluded(query_complexity_policies::large_tables)),
+            query_complexity_policies::enforcement
+                .eq(excluded(query_complexity_policies::enforcement)),
+            query_complexity_policies::updated_at.eq(now),
+        ))
+        .get_result::<QueryComplexityPolicy>(&mut conn)
+        .await?;
+
+    Ok(Some(policy.into()))
+}
+
+fn validate_policy(
+    mut settings: QueryComplexityPolicySettings,
+) -> Result<QueryComplexityPolicySettings, QuerySettingsError> {
+    let limits = [
+        ("max_score", settings.max_score),
+        ("max_joins", settings.max_joins),
+        ("max_subquery_depth", settings.max_subquery_depth),
+    ];
+    for (name, limit) in limits {
+        if limit.is_some_and(|limit| limit < 0) {
+            return Err(QuerySettingsError::Invalid(format!(
+                "{} can't be negative",
+                name
+            )));
+        }
+    }
+
+    settings.large_tables = settings
+        .large_tables
+        .into_iter()
+        .map(|table| table.trim().to_string())
+        .filter(|table| !table.is_empty())
+        .collect();
+    settings.large_tables.sort();
+    settings.large_tables.dedup();
+
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use database::enums::QueryPolicyEnforcement;
+
+    fn settings() -> QueryComplexityPolicySettings {
+        QueryComplexityPolicySettings {
+            max_score: Some(50),
+            max_joins: None,
+            max_subquery_depth: Some(0),
+            reject_cartesian_products: true,
+            large_tables: vec![],
+            enforcement: QueryPolicyEnforcement::Reject,
+        }
+    }
+
+    #[test]
+    fn test_validate_policy_rejects_negative_limits() {
+        let result = validate_policy(QueryComplexityPolicySettings {
+            max_joins: Some(-1),
+            ..settings()
+        });
+
+        assert!(
+            matches!(result, Err(QuerySettingsError::Invalid(message)) if message.contains("max_joins"))
+        );
+    }
+
+    #[test]
+    fn test_validate_policy_cleans_large_tables() {
+        let validated = validate_policy(QueryComplexityPolicySettings {
+            large_tables: vec![
+                " analytics.events ".to_string(),
+                "".to_string(),
+                "analytics.events".to_string(),
+                "orders".to_string(),
+            ],
+            ..settings()
+        })
+        .unwrap();
+
+        assert_eq!(validated.large_tables, vec!["analytics.events", "orders"]);
+        assert_eq!(validated.max_subquery_depth, Some(0));
+    }
+}
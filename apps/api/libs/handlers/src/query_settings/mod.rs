@@ -0,0 +1,104 @@
+pub mod cache_settings;
+pub mod complexity_policy;
+pub mod query_quotas;
+pub mod semantic_settings;
+pub mod statement_timeout;
+pub mod types;
+
+pub use cache_settings::*;
+pub use complexity_policy::*;
+pub use query_quotas::*;
+pub use semantic_settings::*;
+pub use statement_timeout::*;
+
+use anyhow::Result;
+use database::{enums::UserOrganizationRole, pool::get_pg_pool, schema::data_sources};
+use diesel::{ExpressionMethods, OptionalExtension, QueryDsl};
+use diesel_async::RunQueryDsl;
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+/// Why a query settings request was refused. Anything else that goes wrong is returned as a
+/// plain `anyhow::Error`.
+#[derive(Debug, thiserror::Error)]
+pub enum QuerySettingsError {
+    #[error("User is not a member of this organization")]
+    NotMember,
+    #[error("User is not a workspace admin")]
+    NotWorkspaceAdmin,
+    #[error("User is not a workspace or data admin")]
+    NotDataAdmin,
+    #[error("Data source not found")]
+    DataSourceNotFound,
+    #[error("Invalid settings: {0}")]
+    Invalid(String),
+}
+
+/// Query settings can only be read and changed by the organization's workspace admins
+pub(crate) fn require_workspace_admin(
+    user: &AuthenticatedUser,
+    organization_id: Uuid,
+) -> Result<(), QuerySettingsError> {
+    let user_org = user
+        .organizations
+        .iter()
+        .find(|org| org.id == organization_id)
+        .ok_or(QuerySettingsError::NotMember)?;
+
+    if user_org.role != UserOrganizationRole::WorkspaceAdmin {
+        return Err(QuerySettingsError::NotWorkspaceAdmin);
+    }
+
+    Ok(())
+}
+
+/// Data source settings can be changed by workspace and data admins of the organization that
+/// owns the data source. Data sources in other organizations are reported as not found.
+pub(crate) async fn require_data_source_admin(
+    user: &AuthenticatedUser,
+    data_source_id: Uuid,
+) -> Result<()> {
+    match data_source_role(user, data_source_id).await? {
+        (_, UserOrganizationRole::WorkspaceAdmin | UserOrganizationRole::DataAdmin) => Ok(()),
+        _ => Err(QuerySettingsError::NotDataAdmin.into()),
+    }
+}
+
+/// Like `require_workspace_admin`, for the organization that owns the data source. Returns the
+/// organization's id.
+pub(crate) async fn require_data_source_workspace_admin(
+    user: &AuthenticatedUser,
+    data_source_id: Uuid,
+) -> Result<Uuid> {
+    match data_source_role(user, data_source_id).await? {
+        (organization_id, UserOrganizationRole::WorkspaceAdmin) => Ok(organization_id),
+        _ => Err(QuerySettingsError::NotWorkspaceAdmin.into()),
+    }
+}
+
+// The organization that owns the data source and the user's role in it
+async fn data_source_role(
+    user: &AuthenticatedUser,
+    data_source_id: Uuid,
+) -> Result<(Uuid, UserOrganizationRole)> {
+    let mut conn = get_pg_pool().get().await?;
+
+    let organization_id = data_sources::table
+        .filter(data_sources::id.eq(data_source_id))
+        .filter(data_sources::deleted_at.is_null())
+        .select(data_sources::organization_id)
+        .first::<Uuid>(&mut conn)
+        .await
+        .optional()?;
+
+    let user_org = organization_id.and_then(|organization_id| {
+        user.organizations
+            .iter()
+            .find(|org| org.id == organization_id)
+    });
+
+    match user_org {
+        Some(org) => Ok((org.id, org.role)),
+        None => Err(QuerySettingsError::DataSourceNotFound.into()),
+    }
+}
@@ -0,0 +1,209 @@
+use anyhow::Result;
+use chrono::Utc;
+use database::{models::QueryQuota, pool::get_pg_pool, schema::query_quotas};
+use diesel::{insert_into, ExpressionMethods, OptionalExtension, QueryDsl, SelectableHelper};
+use diesel_async::{scoped_futures::ScopedFutureExt, AsyncConnection, RunQueryDsl};
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+use super::{
+    require_data_source_workspace_admin, require_workspace_admin, types::QueryQuotaBody,
+    QuerySettingsError,
+};
+
+/// The organization-wide query quota, if it has one
+pub async fn get_organization_query_quota_handler(
+    user: &AuthenticatedUser,
+    organization_id: Uuid,
+) -> Result<Option<QueryQuotaBody>> {
+    require_workspace_admin(user, organization_id)?;
+
+    load_quota(organization_id, None).await
+}
+
+/// Sets the quota that applies to all of the organization's queries, or removes it when
+/// `quota` is None. Only workspace admins can change it.
+pub async fn update_organization_query_quota_handler(
+    user: &AuthenticatedUser,
+    organization_id: Uuid,
+    quota: Option<QueryQuotaBody>,
+) -> Result<Option<QueryQuotaBody>> {
+    require_workspace_admin(user, organization_id)?;
+
+    save_quota(organization_id, None, quota).await
+}
+
+/// The data source's query quota, if it has one. It applies on top of the organization's.
+pub async fn get_data_source_query_quota_handler(
+    user: &AuthenticatedUser,
+    data_source_id: Uuid,
+) -> Result<Option<QueryQuotaBody>> {
+    let organization_id = require_data_source_workspace_admin(user, data_source_id).await?;
+
+    load_quota(organization_id, Some(data_source_id)).await
+}
+
+/// Sets the quota that applies to the data source's queries on top of the organization's, or
+/// removes it when `quota` is None. Only workspace admins of the data source's organization
+/// can change it.
+pub async fn update_data_source_query_quota_handler(
+    user: &AuthenticatedUser,
+    data_source_id: Uuid,
+    quota: Option<QueryQuotaBody>,
+) -> Result<Option<QueryQuotaBody>> {
+    let organization_id = require_data_source_workspace_admin(user, data_source_id).await?;
+
+    save_quota(organization_id, Some(data_source_id), quota).await
+}
+
+async fn load_quota(
+    organization_id: Uuid,
+    data_source_id: Option<Uuid>,
+) -> Result<Option<QueryQuotaBody>> {
+    let mut conn = get_pg_pool().get().await?;
+
+    let query = query_quotas::table
+        .filter(query_quotas::organization_id.eq(organization_id))
+        .into_boxed();
+    let query = match data_source_id {
+        Some(data_source_id) => query.filter(query_quotas::data_source_id.eq(data_source_id)),
+        None => query.filter(query_quotas::data_source_id.is_null()),
+    };
+
+    let quota = query
+        .select(QueryQuota::as_select())
+        .first::<QueryQuota>(&mut conn)
+        .await
+        .optional()?;
+
+    Ok(quota.map(QueryQuotaBody::from))
+}
+
+// Replaces the quota of the scope. The unique indexes on the table are partial, so the old
+// row is deleted rather than upserted.
+async fn save_quota(
+    organization_id: Uuid,
+    data_source_id: Option<Uuid>,
+    quota: Option<QueryQuotaBody>,
+) -> Result<Option<QueryQuotaBody>> {
+    let quota = quota.map(validate_quota).transpose()?;
+
+    let mut conn = get_pg_pool().get().await?;
+
+    let now = Utc::now();
+    let row = quota.map(|quota| QueryQuota {
+        id: Uuid::new_v4(),
+        organization_id,
+        data_source_id,
+        max_concurrent_queries: quota.max_concurrent_queries,
+        max_queries_per_minute: quota.max_queries_per_minute,
+        max_rows_returned: quota.max_rows_returned,
+        max_bytes_scanned: quota.max_bytes_scanned,
+        created_at: now,
+        updated_at: now,
+    });
+
+    let saved = conn
+        .transaction::<_, anyhow::Error, _>(|conn| {
+            async move {
+                let existing = diesel::delete(query_quotas::table)
+                    .filter(query_quotas::organization_id.eq(organization_id))
+                    .into_boxed();
+                let existing = match data_source_id {
+                    Some(data_source_id) => {
+                        existing.filter(query_quotas::data_source_id.eq(data_source_id))
+                    }
+                    None => existing.filter(query_quotas::data_source_id.is_null()),
+                };
+                existing.execute(conn).await?;
+
+                let Some(row) = row else {
+                    return Ok(None);
+                };
+                let saved = insert_into(query_quotas::table)
+                    .values(&row)
+                    .get_result::<QueryQuota>(conn)
+                    .await?;
+
+                Ok(Some(saved))
+            }
+            .scope_boxed()
+        })
+        .await?;
+
+    Ok(saved.map(QueryQuotaBody::from))
+}
+
+// Limits must be positive: a zero limit would block every query, so it's left unset instead
+fn validate_quota(quota: QueryQuotaBody) -> Result<QueryQuotaBody, QuerySettingsError> {
+    let limits = [
+        (
+            "max_concurrent_queries",
+            quota.max_concurrent_queries.map(i64::from),
+        ),
+        (
+            "max_queries_per_minute",
+            quota.max_queries_per_minute.map(i64::from),
+        ),
+        ("max_rows_returned", quota.max_rows_returned),
+        ("max_bytes_scanned", quota.max_bytes_scanned),
+    ];
+    for (name, limit) in limits {
+        if limit.is_some_and(|limit| limit <= 0) {
+            return Err(QuerySettingsError::Invalid(format!(
+                "{} must be greater than 0",
+                name
+            )));
+        }
+    }
+
+    Ok(quota)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quota() -> QueryQuotaBody {
+        QueryQuotaBody {
+            max_concurrent_queries: Some(5),
+            max_queries_per_minute: None,
+            max_rows_returned: Some(10_000),
+            max_bytes_scanned: None,
+        }
+    }
+
+    #[test]
+    fn test_validate_quota_accepts_positive_and_unset_limits() {
+        assert_eq!(validate_quota(quota()).unwrap(), quota());
+    }
+
+    #[test]
+    fn test_validate_quota_rejects_limits_below_one() {
+        for invalid in [
+            QueryQuotaBody {
+                max_concurrent_queries: Some(0),
+                ..quota()
+            },
+            QueryQuotaBody {
+                max_queries_per_minute: Some(-1),
+                ..quota()
+            },
+            QueryQuotaBody {
+                max_rows_returned: Some(0),
+                ..quota()
+            },
+            QueryQuotaBody {
+                max_bytes_scanned: Some(-10),
+                ..quota()
+            },
+        ] {
+            let result = validate_quota(invalid.clone());
+            assert!(
+                matches!(result, Err(QuerySettingsError::Invalid(_))),
+                "{:?}",
+                invalid
+            );
+        }
+    }
+}
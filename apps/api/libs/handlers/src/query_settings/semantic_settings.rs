@@ -0,0 +1,73 @@
+use anyhow::Result;
+use chrono::Utc;
+use database::{
+    enums::SemanticValidationMode, models::SemanticQuerySettings, pool::get_pg_pool,
+    schema::semantic_query_settings,
+};
+use diesel::{insert_into, upsert::excluded, ExpressionMethods, OptionalExtension, QueryDsl};
+use diesel_async::RunQueryDsl;
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+use super::{require_workspace_admin, types::SemanticQuerySettingsBody};
+
+/// The organization's semantic query settings. Organizations that never configured them
+/// validate in flexible mode and don't restrict anyone to semantic queries.
+pub async fn get_semantic_query_settings_handler(
+    user: &AuthenticatedUser,
+    organization_id: Uuid,
+) -> Result<SemanticQuerySettingsBody> {
+    require_workspace_admin(user, organization_id)?;
+
+    let mut conn = get_pg_pool().get().await?;
+
+    let settings = semantic_query_settings::table
+        .filter(semantic_query_settings::organization_id.eq(organization_id))
+        .first::<SemanticQuerySettings>(&mut conn)
+        .await
+        .optional()?;
+
+    Ok(settings.map_or(
+        SemanticQuerySettingsBody {
+            validation_mode: SemanticValidationMode::Flexible,
+            strict_for_restricted_queriers: false,
+        },
+        SemanticQuerySettingsBody::from,
+    ))
+}
+
+/// Sets how the organization's semantic queries are validated. Only workspace admins can
+/// change it.
+pub async fn update_semantic_query_settings_handler(
+    user: &AuthenticatedUser,
+    organization_id: Uuid,
+    settings: SemanticQuerySettingsBody,
+) -> Result<SemanticQuerySettingsBody> {
+    require_workspace_admin(user, organization_id)?;
+
+    let mut conn = get_pg_pool().get().await?;
+
+    let now = Utc::now();
+    let settings = insert_into(semantic_query_settings::table)
+        .values(&SemanticQuerySettings {
+            organization_id,
+            validation_mode: settings.validation_mode,
+            strict_for_restricted_queriers: settings.strict_for_restricted_queriers,
+            created_at: now,
+            updated_at: now,
+        })
+        .on_conflict(semantic_query_settings::organization_id)
+        .do_update()
+        .set((
+            semantic_query_settings::validation_mode
+                .eq(excluded(semantic_query_settings::validation_mode)),
+            semantic_query_settings::strict_for_restricted_queriers.eq(excluded(
+                semantic_query_settings::strict_for_restricted_queriers,
+            )),
+            semantic_query_settings::updated_at.eq(now),
+        ))
+        .get_result::<SemanticQuerySettings>(&mut conn)
+        .await?;
+
+    Ok(settings.into())
+}
@@ -0,0 +1,74 @@
+use anyhow::Result;
+use chrono::Utc;
+use database::{
+    models::DataSourceQuerySettings, pool::get_pg_pool, schema::data_source_query_settings,
+};
+use diesel::{insert_into, upsert::excluded, ExpressionMethods, OptionalExtension, QueryDsl};
+use diesel_async::RunQueryDsl;
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+use super::{require_data_source_admin, types::DataSourceQuerySettingsBody, QuerySettingsError};
+
+/// The data source's statement timeout
+pub async fn get_data_source_query_settings_handler(
+    user: &AuthenticatedUser,
+    data_source_id: Uuid,
+) -> Result<DataSourceQuerySettingsBody> {
+    require_data_source_admin(user, data_source_id).await?;
+
+    let mut conn = get_pg_pool().get().await?;
+
+    let statement_timeout_seconds = data_source_query_settings::table
+        .filter(data_source_query_settings::data_source_id.eq(data_source_id))
+        .select(data_source_query_settings::statement_timeout_seconds)
+        .first::<i32>(&mut conn)
+        .await
+        .optional()?;
+
+    Ok(DataSourceQuerySettingsBody {
+        statement_timeout_seconds: statement_timeout_seconds.unwrap_or(0),
+    })
+}
+
+/// Sets how long a single query on the data source may run before it is cancelled on the
+/// warehouse. A timeout of 0 removes the limit.
+pub async fn update_data_source_query_settings_handler(
+    user: &AuthenticatedUser,
+    data_source_id: Uuid,
+    settings: DataSourceQuerySettingsBody,
+) -> Result<DataSourceQuerySettingsBody> {
+    require_data_source_admin(user, data_source_id).await?;
+
+    if settings.statement_timeout_seconds < 0 {
+        return Err(QuerySettingsError::Invalid(
+            "statement_timeout_seconds can't be negative".to_string(),
+        )
+        .into());
+    }
+
+    let mut conn = get_pg_pool().get().await?;
+
+    let now = Utc::now();
+    let saved = insert_into(data_source_query_settings::table)
+        .values(&DataSourceQuerySettings {
+            data_source_id,
+            statement_timeout_seconds: settings.statement_timeout_seconds,
+            created_at: now,
+            updated_at: now,
+        })
+        .on_conflict(data_source_query_settings::data_source_id)
+        .do_update()
+        .set((
+            data_source_query_settings::statement_timeout_seconds.eq(excluded(
+                data_source_query_settings::statement_timeout_seconds,
+            )),
+            data_source_query_settings::updated_at.eq(now),
+        ))
+        .get_result::<DataSourceQuerySettings>(&mut conn)
+        .await?;
+
+    Ok(DataSourceQuerySettingsBody {
+        statement_timeout_seconds: saved.statement_timeout_seconds,
+    })
+}
@@ -0,0 +1,85 @@
+use database::{
+    enums::{QueryPolicyEnforcement, SemanticValidationMode},
+    models::{QueryComplexityPolicy, QueryQuota, SemanticQuerySettings},
+};
+use serde::{Deserialize, Serialize};
+
+/// An organization's query complexity policy. Limits left unset aren't enforced.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct QueryComplexityPolicySettings {
+    pub max_score: Option<i32>,
+    pub max_joins: Option<i32>,
+    pub max_subquery_depth: Option<i32>,
+    #[serde(default)]
+    pub reject_cartesian_products: bool,
+    /// Tables that must not be scanned without a WHERE clause
+    #[serde(default)]
+    pub large_tables: Vec<String>,
+    pub enforcement: QueryPolicyEnforcement,
+}
+
+impl From<QueryComplexityPolicy> for QueryComplexityPolicySettings {
+    fn from(policy: QueryComplexityPolicy) -> Self {
+        Self {
+            max_score: policy.max_score,
+            max_joins: policy.max_joins,
+            max_subquery_depth: policy.max_subquery_depth,
+            reject_cartesian_products: policy.reject_cartesian_products,
+            large_tables: policy.large_tables,
+            enforcement: policy.enforcement,
+        }
+    }
+}
+
+/// Limits on how hard an organization's or a data source's queries may hit the warehouse.
+/// Limits left unset aren't enforced.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct QueryQuotaBody {
+    pub max_concurrent_queries: Option<i32>,
+    pub max_queries_per_minute: Option<i32>,
+    pub max_rows_returned: Option<i64>,
+    /// Only enforced by warehouses that report bytes scanned before a query runs
+    pub max_bytes_scanned: Option<i64>,
+}
+
+impl From<QueryQuota> for QueryQuotaBody {
+    fn from(quota: QueryQuota) -> Self {
+        Self {
+            max_concurrent_queries: quota.max_concurrent_queries,
+            max_queries_per_minute: quota.max_queries_per_minute,
+            max_rows_returned: quota.max_rows_returned,
+            max_bytes_scanned: quota.max_bytes_scanned,
+        }
+    }
+}
+
+/// How long query results from a data source are cached. 0 turns caching off, which is the
+/// default for data sources that were never configured.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DataSourceCacheSettingsBody {
+    pub ttl_seconds: i32,
+}
+
+/// Longest a single query on a data source may run. 0 means no limit.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct DataSourceQuerySettingsBody {
+    pub statement_timeout_seconds: i32,
+}
+
+/// How an organization's SQL written against the semantic layer is validated
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SemanticQuerySettingsBody {
+    pub validation_mode: SemanticValidationMode,
+    /// Restricted queriers may only run semantic queries, validated in strict mode
+    #[serde(default)]
+    pub strict_for_restricted_queriers: bool,
+}
+
+impl From<SemanticQuerySettings> for SemanticQuerySettingsBody {
+    fn from(settings: SemanticQuerySettings) -> Self {
+        Self {
+            validation_mode: settings.validation_mode,
+            strict_for_restricted_queriers: settings.strict_for_restricted_queriers,
+        }
+    }
+}
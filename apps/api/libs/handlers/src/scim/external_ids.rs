@@ -0,0 +1,106 @@
+use anyhow::Result;
+use chrono::Utc;
+use database::{enums::ScimResourceType, models::ScimExternalId, schema::scim_external_ids};
+use diesel::{upsert::excluded, ExpressionMethods, OptionalExtension, QueryDsl};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// Stores the identity provider's id for a resource, or forgets it when `external_id` is None
+pub(crate) async fn set_external_id(
+    conn: &mut AsyncPgConnection,
+    organization_id: Uuid,
+    resource_type: ScimResourceType,
+    resource_id: Uuid,
+    external_id: Option<&str>,
+) -> Result<()> {
+    let external_id = match external_id {
+        Some(external_id) => external_id,
+        None => {
+            diesel::delete(scim_external_ids::table)
+                .filter(scim_external_ids::organization_id.eq(organization_id))
+                .filter(scim_external_ids::resource_type.eq(resource_type))
+                .filter(scim_external_ids::resource_id.eq(resource_id))
+                .execute(conn)
+                .await?;
+            return Ok(());
+        }
+    };
+
+    let now = Utc::now();
+    diesel::insert_into(scim_external_ids::table)
+        .values(&ScimExternalId {
+            organization_id,
+            resource_type,
+            resource_id,
+            external_id: external_id.to_string(),
+            created_at: now,
+            updated_at: now,
+        })
+        .on_conflict((
+            scim_external_ids::organization_id,
+            scim_external_ids::resource_type,
+            scim_external_ids::resource_id,
+        ))
+        .do_update()
+        .set((
+            scim_external_ids::external_id.eq(excluded(scim_external_ids::external_id)),
+            scim_external_ids::updated_at.eq(now),
+        ))
+        .execute(conn)
+        .await?;
+
+    Ok(())
+}
+
+pub(crate) async fn get_external_id(
+    conn: &mut AsyncPgConnection,
+    organization_id: Uuid,
+    resource_type: ScimResourceType,
+    resource_id: Uuid,
+) -> Result<Option<String>> {
+    Ok(scim_external_ids::table
+        .filter(scim_external_ids::organization_id.eq(organization_id))
+        .filter(scim_external_ids::resource_type.eq(resource_type))
+        .filter(scim_external_ids::resource_id.eq(resource_id))
+        .select(scim_external_ids::external_id)
+        .first::<String>(conn)
+        .await
+        .optional()?)
+}
+
+pub(crate) async fn get_external_ids(
+    conn: &mut AsyncPgConnection,
+    organization_id: Uuid,
+    resource_type: ScimResourceType,
+    resource_ids: &[Uuid],
+) -> Result<HashMap<Uuid, String>> {
+    let external_ids = scim_external_ids::table
+        .filter(scim_external_ids::organization_id.eq(organization_id))
+        .filter(scim_external_ids::resource_type.eq(resource_type))
+        .filter(scim_external_ids::resource_id.eq_any(resource_ids))
+        .select((
+            scim_external_ids::resource_id,
+            scim_external_ids::external_id,
+        ))
+        .load::<(Uuid, String)>(conn)
+        .await?;
+
+    Ok(external_ids.into_iter().collect())
+}
+
+/// Resources with the identity provider's id, for `externalId eq` filters
+pub(crate) async fn find_by_external_id(
+    conn: &mut AsyncPgConnection,
+    organization_id: Uuid,
+    resource_type: ScimResourceType,
+    external_id: &str,
+) -> Result<Vec<Uuid>> {
+    Ok(scim_external_ids::table
+        .filter(scim_external_ids::organization_id.eq(organization_id))
+        .filter(scim_external_ids::resource_type.eq(resource_type))
+        .filter(scim_external_ids::external_id.eq(external_id))
+        .select(scim_external_ids::resource_id)
+        .load::<Uuid>(conn)
+        .await?)
+}
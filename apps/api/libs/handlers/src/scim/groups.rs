@@ -0,0 +1,815 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use database::{
+    enums::{IdentityType, ScimResourceType, SharingSetting, TeamToUserRole},
+    models::{Team, TeamToUser},
+    pool::get_pg_pool,
+    schema::{
+        asset_permissions, permission_groups_to_identities, teams, teams_to_users, users,
+        users_to_organizations,
+    },
+};
+use diesel::{ExpressionMethods, OptionalExtension, PgTextExpressionMethods, QueryDsl};
+use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use middleware::AuthenticatedUser;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use super::{
+    external_ids::{find_by_external_id, get_external_id, get_external_ids, set_external_id},
+    scim_organization_id,
+    types::{
+        escape_like, patch_string, ScimError, ScimFilter, ScimGroup, ScimListRequest,
+        ScimListResponse, ScimMember, ScimMeta, ScimPatchOperation, ScimPatchRequest, GROUP_SCHEMA,
+    },
+};
+
+/// The parts of a team the identity provider manages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ScimGroupState {
+    pub display_name: String,
+    pub external_id: Option<String>,
+    pub members: Vec<Uuid>,
+}
+
+impl ScimGroupState {
+    fn from_request(request: &ScimGroup) -> Result<Self, ScimError> {
+        let mut members = Vec::new();
+        for member in &request.members {
+            if !members.contains(&member.value) {
+                members.push(member.value);
+            }
+        }
+
+        Ok(ScimGroupState {
+            display_name: validate_display_name(&request.display_name)?,
+            external_id: request.external_id.clone(),
+            members,
+        })
+    }
+}
+
+/// Lists the organization's teams. Supports `displayName eq` and `externalId eq` filters.
+pub async fn list_scim_groups(
+    user: &AuthenticatedUser,
+    request: ScimListRequest,
+) -> Result<ScimListResponse<ScimGroup>> {
+    let organization_id = scim_organization_id(user)?;
+    let filter = request
+        .filter
+        .as_deref()
+        .map(ScimFilter::parse)
+        .transpose()?;
+    let (offset, limit) = request.page();
+
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .context("Failed to get database connection")?;
+
+    let mut query = teams::table
+        .filter(teams::organization_id.eq(organization_id))
+        .filter(teams::deleted_at.is_null())
+        .into_boxed();
+    let mut count_query = teams::table
+        .filter(teams::organization_id.eq(organization_id))
+        .filter(teams::deleted_at.is_null())
+        .into_boxed();
+
+    match &filter {
+        Some(filter) if filter.is_on("displayName") => {
+            let name_pattern = escape_like(&filter.value);
+            query = query.filter(teams::name.ilike(name_pattern.clone()));
+            count_query = count_query.filter(teams::name.ilike(name_pattern));
+        }
+        Some(filter) if filter.is_on("externalId") => {
+            let team_ids = find_by_external_id(
+                &mut conn,
+                organization_id,
+                ScimResourceType::Group,
+                &filter.value,
+            )
+            .await?;
+            query = query.filter(teams::id.eq_any(team_ids.clone()));
+            count_query = count_query.filter(teams::id.eq_any(team_ids));
+        }
+        Some(filter) => {
+            return Err(ScimError::bad_request(
+                "invalidFilter",
+                format!("Filtering groups by {} is not supported", filter.attribute),
+            )
+            .into())
+        }
+        None => {}
+    }
+
+    let total_results = count_query
+        .count()
+        .get_result::<i64>(&mut conn)
+        .await
+        .context("Failed to count teams")?;
+
+    let teams = query
+        .order((teams::created_at.asc(), teams::id.asc()))
+        .offset(offset)
+        .limit(limit)
+        .load::<Team>(&mut conn)
+        .await
+        .context("Failed to load teams")?;
+
+    let team_ids: Vec<Uuid> = teams.iter().map(|team| team.id).collect();
+    let mut members = load_members(&mut conn, &team_ids).await?;
+    let external_ids = get_external_ids(
+        &mut conn,
+        organization_id,
+        ScimResourceType::Group,
+        &team_ids,
+    )
+    .await?;
+
+    let resources = teams
+        .iter()
+        .map(|team| {
+            to_scim_group(
+                team,
+                members.remove(&team.id).unwrap_or_default(),
+                external_ids.get(&team.id).cloned(),
+            )
+        })
+        .collect();
+
+    Ok(ScimListResponse::new(resources, total_results, offset + 1))
+}
+
+pub async fn get_scim_group(user: &AuthenticatedUser, team_id: Uuid) -> Result<ScimGroup> {
+    let organization_id = scim_organization_id(user)?;
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .context("Failed to get database connection")?;
+
+    load_scim_group(&mut conn, organization_id, team_id).await
+}
+
+pub async fn create_scim_group(user: &AuthenticatedUser, request: ScimGroup) -> Result<ScimGroup> {
+    let organization_id = scim_organization_id(user)?;
+    let state = ScimGroupState::from_request(&request)?;
+
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .context("Failed to get database connection")?;
+
+    check_name_available(&mut conn, organization_id, &state.display_name, None).await?;
+    check_members(&mut conn, organization_id, &state.members).await?;
+
+    let now = Utc::now();
+    let team = Team {
+        id: Uuid::new_v4(),
+        name: state.display_name.clone(),
+        organization_id,
+        sharing_setting: SharingSetting::None,
+        edit_sql: false,
+        upload_csv: false,
+        export_assets: false,
+        email_slack_enabled: false,
+        created_by: user.id,
+        created_at: now,
+        updated_at: now,
+        deleted_at: None,
+    };
+
+    diesel::insert_into(teams::table)
+        .values(&team)
+        .execute(&mut conn)
+        .await
+        .context("Failed to insert team")?;
+
+    add_members(&mut conn, team.id, &state.members).await?;
+    set_external_id(
+        &mut conn,
+        organization_id,
+        ScimResourceType::Group,
+        team.id,
+        state.external_id.as_deref(),
+    )
+    .await?;
+
+    tracing::info!(
+        team_id = %team.id,
+        organization_id = %organization_id,
+        "Provisioned team over SCIM"
+    );
+
+    load_scim_group(&mut conn, organization_id, team.id).await
+}
+
+/// Replaces the team's name and members with the identity provider's
+pub async fn replace_scim_group(
+    user: &AuthenticatedUser,
+    team_id: Uuid,
+    request: ScimGroup,
+) -> Result<ScimGroup> {
+    let organization_id = scim_organization_id(user)?;
+    let new_state = ScimGroupState::from_request(&request)?;
+
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .context("Failed to get database connection")?;
+
+    let current_state = load_group_state(&mut conn, organization_id, team_id).await?;
+    save_group_state(
+        &mut conn,
+        organization_id,
+        team_id,
+        &current_state,
+        &new_state,
+    )
+    .await?;
+
+    load_scim_group(&mut conn, organization_id, team_id).await
+}
+
+pub async fn patch_scim_group(
+    user: &AuthenticatedUser,
+    team_id: Uuid,
+    request: ScimPatchRequest,
+) -> Result<ScimGroup> {
+    let organization_id = scim_organization_id(user)?;
+
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .context("Failed to get database connection")?;
+
+    let current_state = load_group_state(&mut conn, organization_id, team_id).await?;
+    let mut new_state = current_state.clone();
+    apply_group_patch(&mut new_state, &request.operations)?;
+    save_group_state(
+        &mut conn,
+        organization_id,
+        team_id,
+        &current_state,
+        &new_state,
+    )
+    .await?;
+
+    load_scim_group(&mut conn, organization_id, team_id).await
+}
+
+/// Soft deletes the team, its memberships, and the permission groups and asset permissions
+/// granted to it
+pub async fn delete_scim_group(user: &AuthenticatedUser, team_id: Uuid) -> Result<()> {
+    let organization_id = scim_organization_id(user)?;
+
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .context("Failed to get database connection")?;
+
+    // Checks the team exists in the organization
+    load_team(&mut conn, organization_id, team_id).await?;
+
+    let now = Utc::now();
+
+    diesel::update(teams::table)
+        .filter(teams::id.eq(team_id))
+        .set((teams::deleted_at.eq(Some(now)), teams::updated_at.eq(now)))
+        .execute(&mut conn)
+        .await
+        .context("Failed to delete team")?;
+
+    diesel::update(teams_to_users::table)
+        .filter(teams_to_users::team_id.eq(team_id))
+        .filter(teams_to_users::deleted_at.is_null())
+        .set((
+            teams_to_users::deleted_at.eq(Some(now)),
+            teams_to_users::updated_at.eq(now),
+        ))
+        .execute(&mut conn)
+        .await
+        .context("Failed to remove team members")?;
+
+    diesel::update(permission_groups_to_identities::table)
+        .filter(permission_groups_to_identities::identity_id.eq(team_id))
+        .filter(permission_groups_to_identities::identity_type.eq(IdentityType::Team))
+        .filter(permission_groups_to_identities::deleted_at.is_null())
+        .set((
+            permission_groups_to_identities::deleted_at.eq(Some(now)),
+            permission_groups_to_identities::updated_at.eq(now),
+            permission_groups_to_identities::updated_by.eq(user.id),
+        ))
+        .execute(&mut conn)
+        .await
+        .context("Failed to remove team from permission groups")?;
+
+    diesel::update(asset_permissions::table)
+        .filter(asset_permissions::identity_id.eq(team_id))
+        .filter(asset_permissions::identity_type.eq(IdentityType::Team))
+        .filter(asset_permissions::deleted_at.is_null())
+        .set((
+            asset_permissions::deleted_at.eq(Some(now)),
+            asset_permissions::updated_at.eq(now),
+            asset_permissions::updated_by.eq(user.id),
+        ))
+        .execute(&mut conn)
+        .await
+        .context("Failed to revoke team asset permissions")?;
+
+    set_external_id(
+        &mut conn,
+        organization_id,
+        ScimResourceType::Group,
+        team_id,
+        None,
+    )
+    .await?;
+
+    tracing::info!(
+        team_id = %team_id,
+        organization_id = %organization_id,
+        "Deprovisioned team over SCIM"
+    );
+
+    Ok(())
+}
+
+/// Applies PATCH operations. Operations on attributes Buster doesn't store are ignored.
+pub(crate) fn apply_group_patch(
+    state: &mut ScimGroupState,
+    operations: &[ScimPatchOperation],
+) -> Result<(), ScimError> {
+    for operation in operations {
+        let op = operation.op.to_ascii_lowercase();
+        let path = operation.path.as_deref().map(str::trim);
+
+        match (op.as_str(), path, &operation.value) {
+            ("add", Some(path), Some(value)) if path.eq_ignore_ascii_case("members") => {
+                for member in parse_members(value)? {
+                    if !state.members.contains(&member) {
+                        state.members.push(member);
+                    }
+                }
+            }
+            ("replace", Some(path), Some(value)) if path.eq_ignore_ascii_case("members") => {
+                state.members = parse_members(value)?;
+            }
+            ("remove", Some(path), value) if path.eq_ignore_ascii_case("members") => {
+                match value {
+                    // Removing the attribute without a value removes every member
+                    None | Some(Value::Null) => state.members.clear(),
+                    Some(value) => {
+                        let removed = parse_members(value)?;
+                        state.members.retain(|member| !removed.contains(member));
+                    }
+                }
+            }
+            ("remove", Some(path), _) if member_filter(path).is_some() => {
+                let removed = member_filter(path).flatten().ok_or_else(|| {
+                    ScimError::bad_request("invalidPath", format!("Unsupported path: {}", path))
+                })?;
+                state.members.retain(|member| *member != removed);
+            }
+            ("add" | "replace", Some(path), Some(value)) => {
+                apply_group_attribute(state, path, value)?;
+            }
+            ("remove", Some(path), _) => apply_group_attribute(state, path, &Value::Null)?,
+            ("add" | "replace", None, Some(Value::Object(attributes))) => {
+                for (path, value) in attributes {
+                    if path.eq_ignore_ascii_case("members") {
+                        state.members = parse_members(value)?;
+                    } else {
+                        apply_group_attribute(state, path, value)?;
+                    }
+                }
+            }
+            ("add" | "replace" | "remove", _, _) => {
+                return Err(ScimError::bad_request(
+                    "invalidSyntax",
+                    format!(
+                        "PATCH operation {} is missing a path or value",
+                        operation.op
+                    ),
+                ))
+            }
+            _ => {
+                return Err(ScimError::bad_request(
+                    "invalidSyntax",
+                    format!("Unsupported PATCH operation: {}", operation.op),
+                ))
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn apply_group_attribute(
+    state: &mut ScimGroupState,
+    path: &str,
+    value: &Value,
+) -> Result<(), ScimError> {
+    match path.to_ascii_lowercase().as_str() {
+        "displayname" => {
+            let display_name = patch_string(value)?.ok_or_else(|| {
+                ScimError::bad_request("mutability", "displayName can't be removed")
+            })?;
+            state.display_name = validate_display_name(&display_name)?;
+        }
+        "externalid" => state.external_id = patch_string(value)?,
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Parses a `members[value eq "<id>"]` path. Returns Some(None) for member filters on other
+/// attributes or with an invalid id.
+fn member_filter(path: &str) -> Option<Option<Uuid>> {
+    let filter = path
+        .get(..8)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("members["))
+        .and_then(|_| path[8..].strip_suffix(']'))?;
+
+    Some(
+        ScimFilter::parse(filter)
+            .ok()
+            .filter(|filter| filter.is_on("value"))
+            .and_then(|filter| Uuid::parse_str(&filter.value).ok()),
+    )
+}
+
+fn parse_members(value: &Value) -> Result<Vec<Uuid>, ScimError> {
+    let members: Vec<ScimMember> = match value {
+        Value::Array(_) => serde_json::from_value(value.clone()),
+        _ => serde_json::from_value(value.clone()).map(|member| vec![member]),
+    }
+    .map_err(|e| ScimError::bad_request("invalidValue", format!("Invalid members: {}", e)))?;
+
+    let mut member_ids = Vec::new();
+    for member in members {
+        if !member_ids.contains(&member.value) {
+            member_ids.push(member.value);
+        }
+    }
+    Ok(member_ids)
+}
+
+fn validate_display_name(display_name: &str) -> Result<String, ScimError> {
+    let display_name = display_name.trim();
+    if display_name.is_empty() {
+        return Err(ScimError::bad_request(
+            "invalidValue",
+            "displayName can't be empty",
+        ));
+    }
+    Ok(display_name.to_string())
+}
+
+async fn save_group_state(
+    conn: &mut AsyncPgConnection,
+    organization_id: Uuid,
+    team_id: Uuid,
+    current: &ScimGroupState,
+    new: &ScimGroupState,
+) -> Result<()> {
+    let now = Utc::now();
+
+    if new.display_name != current.display_name {
+        check_name_available(conn, organization_id, &new.display_name, Some(team_id)).await?;
+        diesel::update(teams::table)
+            .filter(teams::id.eq(team_id))
+            .set((teams::name.eq(&new.display_name), teams::updated_at.eq(now)))
+            .execute(conn)
+            .await
+            .context("Failed to update team")?;
+    }
+
+    if new.external_id != current.external_id {
+        set_external_id(
+            conn,
+            organization_id,
+            ScimResourceType::Group,
+            team_id,
+            new.external_id.as_deref(),
+        )
+        .await?;
+    }
+
+    let added: Vec<Uuid> = new
+        .members
+        .iter()
+        .filter(|member| !current.members.contains(member))
+        .copied()
+        .collect();
+    let removed: Vec<Uuid> = current
+        .members
+        .iter()
+        .filter(|member| !new.members.contains(member))
+        .copied()
+        .collect();
+
+    check_members(conn, organization_id, &added).await?;
+    add_members(conn, team_id, &added).await?;
+
+    if !removed.is_empty() {
+        diesel::update(teams_to_users::table)
+            .filter(teams_to_users::team_id.eq(team_id))
+            .filter(teams_to_users::user_id.eq_any(&removed))
+            .filter(teams_to_users::deleted_at.is_null())
+            .set((
+                teams_to_users::deleted_at.eq(Some(now)),
+                teams_to_users::updated_at.eq(now),
+            ))
+            .execute(conn)
+            .await
+            .context("Failed to remove team members")?;
+    }
+
+    Ok(())
+}
+
+/// Adds members to the team, restoring memberships that were removed
+async fn add_members(conn: &mut AsyncPgConnection, team_id: Uuid, user_ids: &[Uuid]) -> Result<()> {
+    if user_ids.is_empty() {
+        return Ok(());
+    }
+
+    let now = Utc::now();
+    let memberships: Vec<TeamToUser> = user_ids
+        .iter()
+        .map(|user_id| TeamToUser {
+            team_id,
+            user_id: *user_id,
+            role: TeamToUserRole::Member,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+        })
+        .collect();
+
+    diesel::insert_into(teams_to_users::table)
+        .values(&memberships)
+        .on_conflict((teams_to_users::team_id, teams_to_users::user_id))
+        .do_update()
+        .set((
+            teams_to_users::deleted_at.eq(None::<DateTime<Utc>>),
+            teams_to_users::updated_at.eq(now),
+        ))
+        .execute(conn)
+        .await
+        .context("Failed to add team members")?;
+
+    Ok(())
+}
+
+/// Teams can only have active members of the organization
+async fn check_members(
+    conn: &mut AsyncPgConnection,
+    organization_id: Uuid,
+    user_ids: &[Uuid],
+) -> Result<()> {
+    if user_ids.is_empty() {
+        return Ok(());
+    }
+
+    let active_members: HashSet<Uuid> = users_to_organizations::table
+        .filter(users_to_organizations::organization_id.eq(organization_id))
+        .filter(users_to_organizations::user_id.eq_any(user_ids))
+        .filter(users_to_organizations::deleted_at.is_null())
+        .select(users_to_organizations::user_id)
+        .load::<Uuid>(conn)
+        .await
+        .context("Failed to query organization members")?
+        .into_iter()
+        .collect();
+
+    match user_ids.iter().find(|id| !active_members.contains(id)) {
+        Some(user_id) => Err(ScimError::bad_request(
+            "invalidValue",
+            format!(
+                "User {} is not an active member of the organization",
+                user_id
+            ),
+        )
+        .into()),
+        None => Ok(()),
+    }
+}
+
+async fn check_name_available(
+    conn: &mut AsyncPgConnection,
+    organization_id: Uuid,
+    name: &str,
+    team_id: Option<Uuid>,
+) -> Result<()> {
+    let mut query = teams::table
+        .filter(teams::organization_id.eq(organization_id))
+        .filter(teams::name.ilike(escape_like(name)))
+        .filter(teams::deleted_at.is_null())
+        .select(teams::id)
+        .into_boxed();
+    if let Some(team_id) = team_id {
+        query = query.filter(teams::id.ne(team_id));
+    }
+
+    let existing = query
+        .first::<Uuid>(conn)
+        .await
+        .optional()
+        .context("Failed to query for existing team")?;
+
+    match existing {
+        Some(_) => Err(ScimError::conflict(format!("A team named {} already exists", name)).into()),
+        None => Ok(()),
+    }
+}
+
+async fn load_team(
+    conn: &mut AsyncPgConnection,
+    organization_id: Uuid,
+    team_id: Uuid,
+) -> Result<Team> {
+    teams::table
+        .filter(teams::id.eq(team_id))
+        .filter(teams::organization_id.eq(organization_id))
+        .filter(teams::deleted_at.is_null())
+        .first::<Team>(conn)
+        .await
+        .optional()
+        .context("Failed to load team")?
+        .ok_or_else(|| ScimError::not_found(format!("Group {} not found", team_id)).into())
+}
+
+/// Active members of each team, with their emails
+async fn load_members(
+    conn: &mut AsyncPgConnection,
+    team_ids: &[Uuid],
+) -> Result<HashMap<Uuid, Vec<ScimMember>>> {
+    let rows = teams_to_users::table
+        .inner_join(users::table)
+        .filter(teams_to_users::team_id.eq_any(team_ids))
+        .filter(teams_to_users::deleted_at.is_null())
+        .order((teams_to_users::created_at.asc(), users::id.asc()))
+        .select((teams_to_users::team_id, users::id, users::email))
+        .load::<(Uuid, Uuid, String)>(conn)
+        .await
+        .context("Failed to load team members")?;
+
+    let mut members: HashMap<Uuid, Vec<ScimMember>> = HashMap::new();
+    for (team_id, user_id, email) in rows {
+        members.entry(team_id).or_default().push(ScimMember {
+            value: user_id,
+            display: Some(email),
+        });
+    }
+    Ok(members)
+}
+
+async fn load_scim_group(
+    conn: &mut AsyncPgConnection,
+    organization_id: Uuid,
+    team_id: Uuid,
+) -> Result<ScimGroup> {
+    let team = load_team(conn, organization_id, team_id).await?;
+    let members = load_members(conn, &[team_id])
+        .await?
+        .remove(&team_id)
+        .unwrap_or_default();
+    let external_id =
+        get_external_id(conn, organization_id, ScimResourceType::Group, team_id).await?;
+
+    Ok(to_scim_group(&team, members, external_id))
+}
+
+async fn load_group_state(
+    conn: &mut AsyncPgConnection,
+    organization_id: Uuid,
+    team_id: Uuid,
+) -> Result<ScimGroupState> {
+    let group = load_scim_group(conn, organization_id, team_id).await?;
+    Ok(ScimGroupState {
+        display_name: group.display_name,
+        external_id: group.external_id,
+        members: group
+            .members
+            .into_iter()
+            .map(|member| member.value)
+            .collect(),
+    })
+}
+
+fn to_scim_group(team: &Team, members: Vec<ScimMember>, external_id: Option<String>) -> ScimGroup {
+    ScimGroup {
+        schemas: vec![GROUP_SCHEMA.to_string()],
+        id: Some(team.id),
+        external_id,
+        display_name: team.name.clone(),
+        members,
+        meta: Some(ScimMeta {
+            resource_type: "Group".to_string(),
+            created: team.created_at,
+            last_modified: team.updated_at,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const ADA: &str = "6c3f0b9e-4a52-4a8e-9d0b-0d7f1c2e3a41";
+    const GRACE: &str = "1b2c3d4e-5f60-4718-8a9b-0c1d2e3f4a5b";
+
+    fn id(value: &str) -> Uuid {
+        Uuid::parse_str(value).unwrap()
+    }
+
+    fn operation(op: &str, path: Option<&str>, value: Option<Value>) -> ScimPatchOperation {
+        ScimPatchOperation {
+            op: op.to_string(),
+            path: path.map(str::to_string),
+            value,
+        }
+    }
+
+    fn state() -> ScimGroupState {
+        ScimGroupState {
+            display_name: "Analysts".to_string(),
+            external_id: None,
+            members: vec![id(ADA)],
+        }
+    }
+
+    #[test]
+    fn test_patch_adds_and_removes_members() {
+        let mut state = state();
+        apply_group_patch(
+            &mut state,
+            &[
+                operation(
+                    "Add",
+                    Some("members"),
+                    Some(json!([{"value": GRACE}, {"value": ADA}])),
+                ),
+                operation(
+                    "Remove",
+                    Some(&format!("members[value eq \"{}\"]", ADA)),
+                    None,
+                ),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(state.members, vec![id(GRACE)]);
+    }
+
+    #[test]
+    fn test_patch_remove_members_without_value_clears_them() {
+        let mut state = state();
+        apply_group_patch(&mut state, &[operation("remove", Some("members"), None)]).unwrap();
+        assert!(state.members.is_empty());
+    }
+
+    #[test]
+    fn test_patch_without_path_replaces_name_and_members() {
+        let mut state = state();
+        apply_group_patch(
+            &mut state,
+            &[operation(
+                "replace",
+                None,
+                Some(json!({"displayName": "Data team", "members": [{"value": GRACE}]})),
+            )],
+        )
+        .unwrap();
+
+        assert_eq!(state.display_name, "Data team");
+        assert_eq!(state.members, vec![id(GRACE)]);
+    }
+
+    #[test]
+    fn test_patch_rejects_invalid_member_ids() {
+        let mut state = state();
+        let error = apply_group_patch(
+            &mut state,
+            &[operation(
+                "add",
+                Some("members"),
+                Some(json!([{"value": "ada"}])),
+            )],
+        )
+        .unwrap_err();
+        assert_eq!(error.scim_type, Some("invalidValue"));
+
+        let error = apply_group_patch(
+            &mut state,
+            &[operation("remove", Some("members[value eq \"ada\"]"), None)],
+        )
+        .unwrap_err();
+        assert_eq!(error.scim_type, Some("invalidPath"));
+    }
+}
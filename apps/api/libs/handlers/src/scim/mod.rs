@@ -0,0 +1,30 @@
+//! SCIM 2.0 provisioning. The organization's identity provider creates, updates and
+//! deprovisions users and teams here, authenticating with an API key that has the
+//! `scim:provision` scope.
+
+mod external_ids;
+mod groups;
+mod types;
+mod users;
+
+pub use groups::*;
+pub use types::*;
+pub use users::*;
+
+use anyhow::Result;
+use database::enums::UserOrganizationRole;
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+/// The organization the identity provider provisions. Only workspace admins' keys may
+/// provision.
+pub(crate) fn scim_organization_id(user: &AuthenticatedUser) -> Result<Uuid> {
+    match user.organizations.first() {
+        Some(org) if org.role == UserOrganizationRole::WorkspaceAdmin => Ok(org.id),
+        Some(_) => Err(ScimError::forbidden(
+            "SCIM provisioning requires an API key of a workspace admin",
+        )
+        .into()),
+        None => Err(ScimError::forbidden("User is not a member of an organization").into()),
+    }
+}
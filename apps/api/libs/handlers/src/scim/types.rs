@@ -0,0 +1,347 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+pub const USER_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+pub const GROUP_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:Group";
+pub const LIST_RESPONSE_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
+pub const ERROR_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:Error";
+
+/// A user as SCIM represents it. `userName` is the user's email.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimUser {
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+    pub user_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<ScimName>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub emails: Vec<ScimEmail>,
+    #[serde(default = "default_active")]
+    pub active: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<ScimMeta>,
+}
+
+fn default_active() -> bool {
+    true
+}
+
+impl ScimUser {
+    /// The name to store for the user: the display name, or else the name parts the identity
+    /// provider sent
+    pub fn full_name(&self) -> Option<String> {
+        if let Some(display_name) = self.display_name.as_ref().filter(|n| !n.trim().is_empty()) {
+            return Some(display_name.trim().to_string());
+        }
+        self.name.as_ref().and_then(ScimName::full_name)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimName {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub formatted: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub given_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub family_name: Option<String>,
+}
+
+impl ScimName {
+    pub fn full_name(&self) -> Option<String> {
+        if let Some(formatted) = self.formatted.as_ref().filter(|n| !n.trim().is_empty()) {
+            return Some(formatted.trim().to_string());
+        }
+
+        let parts: Vec<&str> = [&self.given_name, &self.family_name]
+            .into_iter()
+            .flatten()
+            .map(|part| part.trim())
+            .filter(|part| !part.is_empty())
+            .collect();
+        (!parts.is_empty()).then(|| parts.join(" "))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScimEmail {
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub primary: Option<bool>,
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    pub type_: Option<String>,
+}
+
+/// A team as SCIM represents it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimGroup {
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_id: Option<String>,
+    pub display_name: String,
+    #[serde(default)]
+    pub members: Vec<ScimMember>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta: Option<ScimMeta>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScimMember {
+    pub value: Uuid,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimMeta {
+    pub resource_type: String,
+    pub created: DateTime<Utc>,
+    pub last_modified: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimListResponse<T> {
+    pub schemas: Vec<String>,
+    pub total_results: i64,
+    pub start_index: i64,
+    pub items_per_page: i64,
+    #[serde(rename = "Resources")]
+    pub resources: Vec<T>,
+}
+
+impl<T> ScimListResponse<T> {
+    pub fn new(resources: Vec<T>, total_results: i64, start_index: i64) -> Self {
+        ScimListResponse {
+            schemas: vec![LIST_RESPONSE_SCHEMA.to_string()],
+            total_results,
+            start_index,
+            items_per_page: resources.len() as i64,
+            resources,
+        }
+    }
+}
+
+/// Query parameters of a list request. `startIndex` is 1-based.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScimListRequest {
+    pub filter: Option<String>,
+    pub start_index: Option<i64>,
+    pub count: Option<i64>,
+}
+
+impl ScimListRequest {
+    const DEFAULT_COUNT: i64 = 100;
+    const MAX_COUNT: i64 = 1000;
+
+    /// Rows to skip and rows to return
+    pub fn page(&self) -> (i64, i64) {
+        let start_index = self.start_index.unwrap_or(1).max(1);
+        let count = self
+            .count
+            .unwrap_or(Self::DEFAULT_COUNT)
+            .clamp(0, Self::MAX_COUNT);
+        (start_index - 1, count)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScimPatchRequest {
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    #[serde(rename = "Operations")]
+    pub operations: Vec<ScimPatchOperation>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScimPatchOperation {
+    pub op: String, // Identity providers differ in case, e.g. "replace" and "Replace"
+    #[serde(default)]
+    pub path: Option<String>,
+    #[serde(default)]
+    pub value: Option<Value>,
+}
+
+/// A request the SCIM endpoints reject, reported to the identity provider in SCIM's error
+/// format. Handlers return it inside `anyhow::Error`; anything else is an internal error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScimError {
+    pub status: u16,
+    pub scim_type: Option<&'static str>,
+    pub detail: String,
+}
+
+impl ScimError {
+    pub fn bad_request(scim_type: &'static str, detail: impl Into<String>) -> Self {
+        ScimError {
+            status: 400,
+            scim_type: Some(scim_type),
+            detail: detail.into(),
+        }
+    }
+
+    pub fn forbidden(detail: impl Into<String>) -> Self {
+        ScimError {
+            status: 403,
+            scim_type: None,
+            detail: detail.into(),
+        }
+    }
+
+    pub fn not_found(detail: impl Into<String>) -> Self {
+        ScimError {
+            status: 404,
+            scim_type: None,
+            detail: detail.into(),
+        }
+    }
+
+    pub fn conflict(detail: impl Into<String>) -> Self {
+        ScimError {
+            status: 409,
+            scim_type: Some("uniqueness"),
+            detail: detail.into(),
+        }
+    }
+
+    pub fn body(&self) -> Value {
+        let mut body = json!({
+            "schemas": [ERROR_SCHEMA],
+            "status": self.status.to_string(),
+            "detail": self.detail,
+        });
+        if let Some(scim_type) = self.scim_type {
+            body["scimType"] = json!(scim_type);
+        }
+        body
+    }
+}
+
+impl fmt::Display for ScimError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.detail)
+    }
+}
+
+impl std::error::Error for ScimError {}
+
+/// A list filter. Only `eq` comparisons on a single attribute are supported, which is what
+/// identity providers send to look up a resource before creating it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScimFilter {
+    pub attribute: String,
+    pub value: String,
+}
+
+impl ScimFilter {
+    pub fn parse(filter: &str) -> Result<Self, ScimError> {
+        let invalid =
+            || ScimError::bad_request("invalidFilter", format!("Unsupported filter: {}", filter));
+
+        let filter = filter.trim();
+        let (attribute, rest) = filter.split_once(char::is_whitespace).ok_or_else(invalid)?;
+        let (operator, value) = rest
+            .trim_start()
+            .split_once(char::is_whitespace)
+            .ok_or_else(invalid)?;
+        if !operator.eq_ignore_ascii_case("eq") {
+            return Err(invalid());
+        }
+
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .ok_or_else(invalid)?;
+
+        Ok(ScimFilter {
+            attribute: attribute.to_string(),
+            value: value.replace("\\\"", "\""),
+        })
+    }
+
+    /// Whether the filter is on the attribute, ignoring case as SCIM attribute names do
+    pub fn is_on(&self, attribute: &str) -> bool {
+        self.attribute.eq_ignore_ascii_case(attribute)
+    }
+}
+
+/// Escapes LIKE wildcards so the value only matches itself
+pub(crate) fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Reads a PATCH value as a bool. Some identity providers send "True" and "False" strings.
+pub(crate) fn patch_bool(value: &Value) -> Result<bool, ScimError> {
+    match value {
+        Value::Bool(value) => Ok(*value),
+        Value::String(value) if value.eq_ignore_ascii_case("true") => Ok(true),
+        Value::String(value) if value.eq_ignore_ascii_case("false") => Ok(false),
+        _ => Err(ScimError::bad_request(
+            "invalidValue",
+            format!("Expected a boolean, got {}", value),
+        )),
+    }
+}
+
+/// Reads a PATCH value as a string, or None for null
+pub(crate) fn patch_string(value: &Value) -> Result<Option<String>, ScimError> {
+    match value {
+        Value::String(value) => Ok(Some(value.clone())),
+        Value::Null => Ok(None),
+        _ => Err(ScimError::bad_request(
+            "invalidValue",
+            format!("Expected a string, got {}", value),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_eq_filter() {
+        let filter = ScimFilter::parse("userName eq \"ada@example.com\"").unwrap();
+        assert!(filter.is_on("username"));
+        assert_eq!(filter.value, "ada@example.com");
+
+        let filter = ScimFilter::parse(r#"displayName EQ "The \"A\" team""#).unwrap();
+        assert!(filter.is_on("displayName"));
+        assert_eq!(filter.value, "The \"A\" team");
+    }
+
+    #[test]
+    fn test_parse_rejects_unsupported_filters() {
+        for filter in [
+            "userName co \"ada\"",
+            "userName eq ada",
+            "userName",
+            "userName eq \"ada\" and active eq true",
+        ] {
+            let error = ScimFilter::parse(filter).unwrap_err();
+            assert_eq!(error.scim_type, Some("invalidFilter"), "{}", filter);
+        }
+    }
+}
@@ -0,0 +1,1024 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use database::{
+    enums::{
+        AssetType, IdentityType, ScimResourceType, SharingSetting, UserOrganizationRole,
+        UserOrganizationStatus,
+    },
+    models::{User, UserToOrganization},
+    pool::get_pg_pool,
+    schema::{
+        api_keys, asset_permissions, chats, collections, dashboard_files, dashboards, metric_files,
+        organizations, permission_groups, permission_groups_to_identities, report_files, teams,
+        teams_to_users, threads_deprecated, users, users_to_organizations,
+    },
+};
+use diesel::{ExpressionMethods, OptionalExtension, PgTextExpressionMethods, QueryDsl};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use middleware::AuthenticatedUser;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use super::{
+    external_ids::{find_by_external_id, get_external_id, get_external_ids, set_external_id},
+    scim_organization_id,
+    types::{
+        escape_like, patch_bool, patch_string, ScimEmail, ScimError, ScimFilter, ScimListRequest,
+        ScimListResponse, ScimMeta, ScimName, ScimPatchOperation, ScimPatchRequest, ScimUser,
+        USER_SCHEMA,
+    },
+};
+
+/// When a user joined the organization and, if deprovisioned, when they left
+type Membership = (DateTime<Utc>, DateTime<Utc>, Option<DateTime<Utc>>);
+
+/// The parts of a user the identity provider manages
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ScimUserState {
+    pub user_name: String,
+    pub name: Option<String>,
+    pub external_id: Option<String>,
+    pub active: bool,
+}
+
+impl ScimUserState {
+    fn from_request(request: &ScimUser) -> Result<Self, ScimError> {
+        Ok(ScimUserState {
+            user_name: validate_user_name(&request.user_name)?,
+            name: request.full_name(),
+            external_id: request.external_id.clone(),
+            active: request.active,
+        })
+    }
+}
+
+/// Lists the organization's users, including deprovisioned ones. Supports `userName eq` and
+/// `externalId eq` filters.
+pub async fn list_scim_users(
+    user: &AuthenticatedUser,
+    request: ScimListRequest,
+) -> Result<ScimListResponse<ScimUser>> {
+    let organization_id = scim_organization_id(user)?;
+    let filter = request
+        .filter
+        .as_deref()
+        .map(ScimFilter::parse)
+        .transpose()?;
+    let (offset, limit) = request.page();
+
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .context("Failed to get database connection")?;
+
+    let mut query = users_to_organizations::table
+        .filter(users_to_organizations::organization_id.eq(organization_id))
+        .into_boxed();
+    let mut count_query = users_to_organizations::table
+        .filter(users_to_organizations::organization_id.eq(organization_id))
+        .into_boxed();
+
+    match &filter {
+        Some(filter) if filter.is_on("userName") => {
+            let email_pattern = escape_like(&filter.value);
+            query = query.filter(
+                users_to_organizations::user_id.eq_any(
+                    users::table
+                        .filter(users::email.ilike(email_pattern.clone()))
+                        .select(users::id),
+                ),
+            );
+            count_query = count_query.filter(
+                users_to_organizations::user_id.eq_any(
+                    users::table
+                        .filter(users::email.ilike(email_pattern))
+                        .select(users::id),
+                ),
+            );
+        }
+        Some(filter) if filter.is_on("externalId") => {
+            let user_ids = find_by_external_id(
+                &mut conn,
+                organization_id,
+                ScimResourceType::User,
+                &filter.value,
+            )
+            .await?;
+            query = query.filter(users_to_organizations::user_id.eq_any(user_ids.clone()));
+            count_query = count_query.filter(users_to_organizations::user_id.eq_any(user_ids));
+        }
+        Some(filter) => {
+            return Err(ScimError::bad_request(
+                "invalidFilter",
+                format!("Filtering users by {} is not supported", filter.attribute),
+            )
+            .into())
+        }
+        None => {}
+    }
+
+    let total_results = count_query
+        .count()
+        .get_result::<i64>(&mut conn)
+        .await
+        .context("Failed to count users")?;
+
+    let memberships = query
+        .order((
+            users_to_organizations::created_at.asc(),
+            users_to_organizations::user_id.asc(),
+        ))
+        .offset(offset)
+        .limit(limit)
+        .select((
+            users_to_organizations::user_id,
+            users_to_organizations::created_at,
+            users_to_organizations::updated_at,
+            users_to_organizations::deleted_at,
+        ))
+        .load::<(Uuid, DateTime<Utc>, DateTime<Utc>, Option<DateTime<Utc>>)>(&mut conn)
+        .await
+        .context("Failed to load users")?;
+
+    let user_ids: Vec<Uuid> = memberships.iter().map(|m| m.0).collect();
+    let users = users::table
+        .filter(users::id.eq_any(&user_ids))
+        .select(User::as_select())
+        .load::<User>(&mut conn)
+        .await
+        .context("Failed to load users")?;
+    let external_ids = get_external_ids(
+        &mut conn,
+        organization_id,
+        ScimResourceType::User,
+        &user_ids,
+    )
+    .await?;
+
+    let resources = memberships
+        .into_iter()
+        .filter_map(|(user_id, created_at, updated_at, deleted_at)| {
+            let user = users.iter().find(|u| u.id == user_id)?;
+            Some(to_scim_user(
+                user,
+                (created_at, updated_at, deleted_at),
+                external_ids.get(&user_id).cloned(),
+            ))
+        })
+        .collect();
+
+    Ok(ScimListResponse::new(resources, total_results, offset + 1))
+}
+
+pub async fn get_scim_user(user: &AuthenticatedUser, user_id: Uuid) -> Result<ScimUser> {
+    let organization_id = scim_organization_id(user)?;
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .context("Failed to get database connection")?;
+
+    load_scim_user(&mut conn, organization_id, user_id).await
+}
+
+/// Adds a user to the organization, creating them if they have never signed in. Users
+/// provisioned after being deprovisioned get their membership back.
+pub async fn create_scim_user(user: &AuthenticatedUser, request: ScimUser) -> Result<ScimUser> {
+    let organization_id = scim_organization_id(user)?;
+    let state = ScimUserState::from_request(&request)?;
+
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .context("Failed to get database connection")?;
+
+    let default_role = organizations::table
+        .filter(organizations::id.eq(organization_id))
+        .select(organizations::default_role)
+        .first::<UserOrganizationRole>(&mut conn)
+        .await
+        .context("Failed to find organization")?;
+
+    let now = Utc::now();
+    let existing_user = users::table
+        .filter(users::email.ilike(escape_like(&state.user_name)))
+        .select(User::as_select())
+        .first::<User>(&mut conn)
+        .await
+        .optional()
+        .context("Failed to query for existing user")?;
+
+    let user_id = match existing_user {
+        Some(existing_user) => {
+            // The user record is shared by every organization the user belongs to, so only
+            // an organization that has the user to itself may rename them.
+            let in_other_organizations =
+                in_other_organizations(&mut conn, organization_id, existing_user.id).await?;
+            if !in_other_organizations && state.name.is_some() && state.name != existing_user.name {
+                diesel::update(users::table)
+                    .filter(users::id.eq(existing_user.id))
+                    .set((users::name.eq(&state.name), users::updated_at.eq(now)))
+                    .execute(&mut conn)
+                    .await
+                    .context("Failed to update user")?;
+            }
+            existing_user.id
+        }
+        None => {
+            let new_user_id = Uuid::new_v4();
+            let new_user = User {
+                id: new_user_id,
+                email: state.user_name.clone(),
+                name: state.name.clone(),
+                config: json!({}),
+                created_at: now,
+                updated_at: now,
+                attributes: json!({
+                    "user_id": new_user_id.to_string(),
+                    "user_email": state.user_name,
+                    "organization_id": organization_id.to_string(),
+                    "organization_role": format!("{:?}", default_role)
+                }),
+                avatar_url: None,
+            };
+
+            diesel::insert_into(users::table)
+                .values(&new_user)
+                .execute(&mut conn)
+                .await
+                .context("Failed to insert user")?;
+            new_user_id
+        }
+    };
+
+    let membership = users_to_organizations::table
+        .filter(users_to_organizations::user_id.eq(user_id))
+        .filter(users_to_organizations::organization_id.eq(organization_id))
+        .select(users_to_organizations::deleted_at)
+        .first::<Option<DateTime<Utc>>>(&mut conn)
+        .await
+        .optional()
+        .context("Failed to query organization membership")?;
+
+    match membership {
+        Some(None) => {
+            return Err(ScimError::conflict(format!(
+                "User {} is already a member of the organization",
+                state.user_name
+            ))
+            .into());
+        }
+        Some(Some(_)) => {
+            if state.active {
+                reactivate_membership(&mut conn, organization_id, user_id, user.id).await?;
+            }
+        }
+        None => {
+            diesel::insert_into(users_to_organizations::table)
+                .values(&UserToOrganization {
+                    user_id,
+                    organization_id,
+                    role: default_role,
+                    sharing_setting: SharingSetting::None,
+                    edit_sql: false,
+                    upload_csv: false,
+                    export_assets: false,
+                    email_slack_enabled: false,
+                    created_at: now,
+                    updated_at: now,
+                    deleted_at: (!state.active).then_some(now),
+                    created_by: user.id,
+                    updated_by: user.id,
+                    deleted_by: (!state.active).then_some(user.id),
+                    status: if state.active {
+                        UserOrganizationStatus::Active
+                    } else {
+                        UserOrganizationStatus::Inactive
+                    },
+                })
+                .execute(&mut conn)
+                .await
+                .context("Failed to add user to organization")?;
+        }
+    }
+
+    set_external_id(
+        &mut conn,
+        organization_id,
+        ScimResourceType::User,
+        user_id,
+        state.external_id.as_deref(),
+    )
+    .await?;
+
+    tracing::info!(
+        user_id = %user_id,
+        organization_id = %organization_id,
+        "Provisioned user over SCIM"
+    );
+
+    load_scim_user(&mut conn, organization_id, user_id).await
+}
+
+/// Replaces the user's attributes with the identity provider's. Setting `active` to false
+/// deprovisions the user.
+pub async fn replace_scim_user(
+    user: &AuthenticatedUser,
+    user_id: Uuid,
+    request: ScimUser,
+) -> Result<ScimUser> {
+    let organization_id = scim_organization_id(user)?;
+    let new_state = ScimUserState::from_request(&request)?;
+
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .context("Failed to get database connection")?;
+
+    let current_state = load_user_state(&mut conn, organization_id, user_id).await?;
+    save_user_state(
+        &mut conn,
+        organization_id,
+        user_id,
+        user.id,
+        &current_state,
+        &new_state,
+    )
+    .await?;
+
+    load_scim_user(&mut conn, organization_id, user_id).await
+}
+
+pub async fn patch_scim_user(
+    user: &AuthenticatedUser,
+    user_id: Uuid,
+    request: ScimPatchRequest,
+) -> Result<ScimUser> {
+    let organization_id = scim_organization_id(user)?;
+
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .context("Failed to get database connection")?;
+
+    let current_state = load_user_state(&mut conn, organization_id, user_id).await?;
+    let mut new_state = current_state.clone();
+    apply_user_patch(&mut new_state, &request.operations)?;
+    save_user_state(
+        &mut conn,
+        organization_id,
+        user_id,
+        user.id,
+        &current_state,
+        &new_state,
+    )
+    .await?;
+
+    load_scim_user(&mut conn, organization_id, user_id).await
+}
+
+/// Deprovisions the user. The user record is kept since they may own assets.
+pub async fn delete_scim_user(user: &AuthenticatedUser, user_id: Uuid) -> Result<()> {
+    let organization_id = scim_organization_id(user)?;
+
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .context("Failed to get database connection")?;
+
+    let state = load_user_state(&mut conn, organization_id, user_id).await?;
+    if state.active {
+        deprovision_user(&mut conn, organization_id, user_id, user.id).await?;
+    }
+    set_external_id(
+        &mut conn,
+        organization_id,
+        ScimResourceType::User,
+        user_id,
+        None,
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Applies PATCH operations. Operations on attributes Buster doesn't store are ignored, as
+/// identity providers send them regardless.
+pub(crate) fn apply_user_patch(
+    state: &mut ScimUserState,
+    operations: &[ScimPatchOperation],
+) -> Result<(), ScimError> {
+    for operation in operations {
+        let op = operation.op.to_ascii_lowercase();
+        let remove = match op.as_str() {
+            "add" | "replace" => false,
+            "remove" => true,
+            _ => {
+                return Err(ScimError::bad_request(
+                    "invalidSyntax",
+                    format!("Unsupported PATCH operation: {}", operation.op),
+                ))
+            }
+        };
+
+        match (&operation.path, &operation.value) {
+            (Some(path), _) if remove => apply_user_attribute(state, path, &Value::Null)?,
+            (Some(path), Some(value)) => apply_user_attribute(state, path, value)?,
+            (None, Some(Value::Object(attributes))) if !remove => {
+                for (path, value) in attributes {
+                    apply_user_attribute(state, path, value)?;
+                }
+            }
+            _ => {
+                return Err(ScimError::bad_request(
+                    "invalidSyntax",
+                    format!(
+                        "PATCH operation {} is missing a path or value",
+                        operation.op
+                    ),
+                ))
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn apply_user_attribute(
+    state: &mut ScimUserState,
+    path: &str,
+    value: &Value,
+) -> Result<(), ScimError> {
+    match path.to_ascii_lowercase().as_str() {
+        "active" => state.active = patch_bool(value)?,
+        "username" => {
+            let user_name = patch_string(value)?
+                .ok_or_else(|| ScimError::bad_request("mutability", "userName can't be removed"))?;
+            state.user_name = validate_user_name(&user_name)?;
+        }
+        "displayname" | "name.formatted" => state.name = patch_string(value)?,
+        "name" => {
+            state.name = match value {
+                Value::Null => None,
+                value => serde_json::from_value::<ScimName>(value.clone())
+                    .map_err(|e| ScimError::bad_request("invalidValue", e.to_string()))?
+                    .full_name(),
+            }
+        }
+        "externalid" => state.external_id = patch_string(value)?,
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Users sign in with their email, so the identity provider's userName has to be one
+fn validate_user_name(user_name: &str) -> Result<String, ScimError> {
+    let user_name = user_name.trim();
+    if user_name.is_empty() || !user_name.contains('@') {
+        return Err(ScimError::bad_request(
+            "invalidValue",
+            "userName must be the user's email address",
+        ));
+    }
+    Ok(user_name.to_string())
+}
+
+async fn save_user_state(
+    conn: &mut AsyncPgConnection,
+    organization_id: Uuid,
+    user_id: Uuid,
+    actor_id: Uuid,
+    current: &ScimUserState,
+    new: &ScimUserState,
+) -> Result<()> {
+    let now = Utc::now();
+
+    if !new.user_name.eq_ignore_ascii_case(&current.user_name) {
+        let email_taken = users::table
+            .filter(users::email.ilike(escape_like(&new.user_name)))
+            .filter(users::id.ne(user_id))
+            .select(users::id)
+            .first::<Uuid>(conn)
+            .await
+            .optional()
+            .context("Failed to query for existing user")?
+            .is_some();
+        if email_taken {
+            return Err(ScimError::conflict(format!(
+                "Another user already has the email {}",
+                new.user_name
+            ))
+            .into());
+        }
+    }
+
+    let record_changed = new.user_name != current.user_name || new.name != current.name;
+    let shared = record_changed && in_other_organizations(conn, organization_id, user_id).await?;
+    if let Some((email, name)) = user_record_update(current, new, shared)? {
+        diesel::update(users::table)
+            .filter(users::id.eq(user_id))
+            .set((
+                users::email.eq(email),
+                users::name.eq(name),
+                users::updated_at.eq(now),
+            ))
+            .execute(conn)
+            .await
+            .context("Failed to update user")?;
+    }
+
+    if new.external_id != current.external_id {
+        set_external_id(
+            conn,
+            organization_id,
+            ScimResourceType::User,
+            user_id,
+            new.external_id.as_deref(),
+        )
+        .await?;
+    }
+
+    match (current.active, new.active) {
+        (true, false) => deprovision_user(conn, organization_id, user_id, actor_id).await?,
+        (false, true) => reactivate_membership(conn, organization_id, user_id, actor_id).await?,
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// The email and name to write to the user record, if they change. The record is shared by
+/// every organization the user belongs to, so when `shared` the email can't be changed and
+/// the name is left as it is, as when an existing user is provisioned.
+fn user_record_update<'a>(
+    current: &ScimUserState,
+    new: &'a ScimUserState,
+    shared: bool,
+) -> Result<Option<(&'a str, &'a Option<String>)>, ScimError> {
+    if new.user_name == current.user_name && new.name == current.name {
+        return Ok(None);
+    }
+    if !shared {
+        return Ok(Some((&new.user_name, &new.name)));
+    }
+    if !new.user_name.eq_ignore_ascii_case(&current.user_name) {
+        return Err(ScimError::bad_request(
+            "mutability",
+            "userName can't be changed for a user who belongs to other organizations",
+        ));
+    }
+    Ok(None)
+}
+
+// Whether the user is an active member of any organization besides this one
+async fn in_other_organizations(
+    conn: &mut AsyncPgConnection,
+    organization_id: Uuid,
+    user_id: Uuid,
+) -> Result<bool> {
+    let memberships = users_to_organizations::table
+        .filter(users_to_organizations::user_id.eq(user_id))
+        .filter(users_to_organizations::organization_id.ne(organization_id))
+        .filter(users_to_organizations::deleted_at.is_null())
+        .count()
+        .get_result::<i64>(conn)
+        .await
+        .context("Failed to query organization memberships")?;
+
+    Ok(memberships > 0)
+}
+
+/// Removes the user's access to the organization: their membership, team memberships,
+/// permission group memberships, permissions on the organization's assets and API keys are
+/// soft deleted in a single transaction.
+async fn deprovision_user(
+    conn: &mut AsyncPgConnection,
+    organization_id: Uuid,
+    user_id: Uuid,
+    actor_id: Uuid,
+) -> Result<()> {
+    conn.transaction::<_, anyhow::Error, _>(|conn| {
+        async move {
+            let now = Utc::now();
+
+            diesel::update(users_to_organizations::table)
+                .filter(users_to_organizations::user_id.eq(user_id))
+                .filter(users_to_organizations::organization_id.eq(organization_id))
+                .set((
+                    users_to_organizations::deleted_at.eq(Some(now)),
+                    users_to_organizations::deleted_by.eq(Some(actor_id)),
+                    users_to_organizations::status.eq(UserOrganizationStatus::Inactive),
+                    users_to_organizations::updated_at.eq(now),
+                    users_to_organizations::updated_by.eq(actor_id),
+                ))
+                .execute(conn)
+                .await
+                .context("Failed to remove user from organization")?;
+
+            diesel::update(teams_to_users::table)
+                .filter(teams_to_users::user_id.eq(user_id))
+                .filter(
+                    teams_to_users::team_id.eq_any(
+                        teams::table
+                            .filter(teams::organization_id.eq(organization_id))
+                            .select(teams::id),
+                    ),
+                )
+                .filter(teams_to_users::deleted_at.is_null())
+                .set((
+                    teams_to_users::deleted_at.eq(Some(now)),
+                    teams_to_users::updated_at.eq(now),
+                ))
+                .execute(conn)
+                .await
+                .context("Failed to remove user from teams")?;
+
+            diesel::update(permission_groups_to_identities::table)
+                .filter(permission_groups_to_identities::identity_id.eq(user_id))
+                .filter(permission_groups_to_identities::identity_type.eq(IdentityType::User))
+                .filter(
+                    permission_groups_to_identities::permission_group_id.eq_any(
+                        permission_groups::table
+                            .filter(permission_groups::organization_id.eq(organization_id))
+                            .select(permission_groups::id),
+                    ),
+                )
+                .filter(permission_groups_to_identities::deleted_at.is_null())
+                .set((
+                    permission_groups_to_identities::deleted_at.eq(Some(now)),
+                    permission_groups_to_identities::updated_at.eq(now),
+                    permission_groups_to_identities::updated_by.eq(actor_id),
+                ))
+                .execute(conn)
+                .await
+                .context("Failed to remove user from permission groups")?;
+
+            revoke_asset_permissions(conn, organization_id, user_id, actor_id).await?;
+
+            diesel::update(api_keys::table)
+                .filter(api_keys::owner_id.eq(user_id))
+                .filter(api_keys::organization_id.eq(organization_id))
+                .filter(api_keys::deleted_at.is_null())
+                .set((
+                    api_keys::deleted_at.eq(Some(now)),
+                    api_keys::updated_at.eq(now),
+                ))
+                .execute(conn)
+                .await
+                .context("Failed to revoke API keys")?;
+
+            Ok(())
+        }
+        .scope_boxed()
+    })
+    .await?;
+
+    tracing::info!(
+        user_id = %user_id,
+        organization_id = %organization_id,
+        "Deprovisioned user over SCIM"
+    );
+
+    Ok(())
+}
+
+/// Revokes the user's permissions on assets that belong to the organization. Asset
+/// permissions don't record an organization, so each grant is checked against the asset.
+async fn revoke_asset_permissions(
+    conn: &mut AsyncPgConnection,
+    organization_id: Uuid,
+    user_id: Uuid,
+    actor_id: Uuid,
+) -> Result<()> {
+    let grants = asset_permissions::table
+        .filter(asset_permissions::identity_id.eq(user_id))
+        .filter(asset_permissions::identity_type.eq(IdentityType::User))
+        .filter(asset_permissions::deleted_at.is_null())
+        .select((asset_permissions::asset_id, asset_permissions::asset_type))
+        .load::<(Uuid, AssetType)>(conn)
+        .await
+        .context("Failed to load asset permissions")?;
+
+    let mut asset_types: Vec<AssetType> = Vec::new();
+    for (_, asset_type) in &grants {
+        if !asset_types.contains(asset_type) {
+            asset_types.push(*asset_type);
+        }
+    }
+
+    let now = Utc::now();
+    for asset_type in asset_types {
+        let asset_ids: Vec<Uuid> = grants
+            .iter()
+            .filter(|(_, t)| *t == asset_type)
+            .map(|(id, _)| *id)
+            .collect();
+        let organization_asset_ids =
+            organization_asset_ids(conn, organization_id, asset_type, &asset_ids).await?;
+        if organization_asset_ids.is_empty() {
+            continue;
+        }
+
+        diesel::update(asset_permissions::table)
+            .filter(asset_permissions::identity_id.eq(user_id))
+            .filter(asset_permissions::identity_type.eq(IdentityType::User))
+            .filter(asset_permissions::asset_type.eq(asset_type))
+            .filter(asset_permissions::asset_id.eq_any(&organization_asset_ids))
+            .filter(asset_permissions::deleted_at.is_null())
+            .set((
+                asset_permissions::deleted_at.eq(Some(now)),
+                asset_permissions::updated_at.eq(now),
+                asset_permissions::updated_by.eq(actor_id),
+            ))
+            .execute(conn)
+            .await
+            .context("Failed to revoke asset permissions")?;
+    }
+
+    Ok(())
+}
+
+/// The subset of `asset_ids` that belong to the organization
+async fn organization_asset_ids(
+    conn: &mut AsyncPgConnection,
+    organization_id: Uuid,
+    asset_type: AssetType,
+    asset_ids: &[Uuid],
+) -> Result<Vec<Uuid>> {
+    let ids = match asset_type {
+        AssetType::Dashboard => {
+            dashboards::table
+                .filter(dashboards::id.eq_any(asset_ids))
+                .filter(dashboards::organization_id.eq(organization_id))
+                .select(dashboards::id)
+                .load::<Uuid>(conn)
+                .await
+        }
+        AssetType::Thread => {
+            threads_deprecated::table
+                .filter(threads_deprecated::id.eq_any(asset_ids))
+                .filter(threads_deprecated::organization_id.eq(organization_id))
+                .select(threads_deprecated::id)
+                .load::<Uuid>(conn)
+                .await
+        }
+        AssetType::Collection => {
+            collections::table
+                .filter(collections::id.eq_any(asset_ids))
+                .filter(collections::organization_id.eq(organization_id))
+                .select(collections::id)
+                .load::<Uuid>(conn)
+                .await
+        }
+        AssetType::Chat => {
+            chats::table
+                .filter(chats::id.eq_any(asset_ids))
+                .filter(chats::organization_id.eq(organization_id))
+                .select(chats::id)
+                .load::<Uuid>(conn)
+                .await
+        }
+        AssetType::MetricFile => {
+            metric_files::table
+                .filter(metric_files::id.eq_any(asset_ids))
+                .filter(metric_files::organization_id.eq(organization_id))
+                .select(metric_files::id)
+                .load::<Uuid>(conn)
+                .await
+        }
+        AssetType::DashboardFile => {
+            dashboard_files::table
+                .filter(dashboard_files::id.eq_any(asset_ids))
+                .filter(dashboard_files::organization_id.eq(organization_id))
+                .select(dashboard_files::id)
+                .load::<Uuid>(conn)
+                .await
+        }
+        AssetType::ReportFile => {
+            report_files::table
+                .filter(report_files::id.eq_any(asset_ids))
+                .filter(report_files::organization_id.eq(organization_id))
+                .select(report_files::id)
+                .load::<Uuid>(conn)
+                .await
+        }
+    };
+
+    ids.context("Failed to load the organization's assets")
+}
+
+/// Restores a deprovisioned user's membership. Teams and permissions aren't restored; the
+/// identity provider re-adds the user to their groups.
+async fn reactivate_membership(
+    conn: &mut AsyncPgConnection,
+    organization_id: Uuid,
+    user_id: Uuid,
+    actor_id: Uuid,
+) -> Result<()> {
+    diesel::update(users_to_organizations::table)
+        .filter(users_to_organizations::user_id.eq(user_id))
+        .filter(users_to_organizations::organization_id.eq(organization_id))
+        .set((
+            users_to_organizations::deleted_at.eq(None::<DateTime<Utc>>),
+            users_to_organizations::deleted_by.eq(None::<Uuid>),
+            users_to_organizations::status.eq(UserOrganizationStatus::Active),
+            users_to_organizations::updated_at.eq(Utc::now()),
+            users_to_organizations::updated_by.eq(actor_id),
+        ))
+        .execute(conn)
+        .await
+        .context("Failed to restore organization membership")?;
+
+    Ok(())
+}
+
+async fn load_user(
+    conn: &mut AsyncPgConnection,
+    organization_id: Uuid,
+    user_id: Uuid,
+) -> Result<(User, Membership, Option<String>)> {
+    let membership = users_to_organizations::table
+        .filter(users_to_organizations::user_id.eq(user_id))
+        .filter(users_to_organizations::organization_id.eq(organization_id))
+        .select((
+            users_to_organizations::created_at,
+            users_to_organizations::updated_at,
+            users_to_organizations::deleted_at,
+        ))
+        .first::<Membership>(conn)
+        .await
+        .optional()
+        .context("Failed to query organization membership")?
+        .ok_or_else(|| ScimError::not_found(format!("User {} not found", user_id)))?;
+
+    let user = users::table
+        .filter(users::id.eq(user_id))
+        .select(User::as_select())
+        .first::<User>(conn)
+        .await
+        .context("Failed to load user")?;
+
+    let external_id =
+        get_external_id(conn, organization_id, ScimResourceType::User, user_id).await?;
+
+    Ok((user, membership, external_id))
+}
+
+async fn load_scim_user(
+    conn: &mut AsyncPgConnection,
+    organization_id: Uuid,
+    user_id: Uuid,
+) -> Result<ScimUser> {
+    let (user, membership, external_id) = load_user(conn, organization_id, user_id).await?;
+    Ok(to_scim_user(&user, membership, external_id))
+}
+
+async fn load_user_state(
+    conn: &mut AsyncPgConnection,
+    organization_id: Uuid,
+    user_id: Uuid,
+) -> Result<ScimUserState> {
+    let (user, (_, _, deleted_at), external_id) = load_user(conn, organization_id, user_id).await?;
+    Ok(ScimUserState {
+        user_name: user.email,
+        name: user.name,
+        external_id,
+        active: deleted_at.is_none(),
+    })
+}
+
+fn to_scim_user(user: &User, membership: Membership, external_id: Option<String>) -> ScimUser {
+    let (created_at, updated_at, deleted_at) = membership;
+
+    ScimUser {
+        schemas: vec![USER_SCHEMA.to_string()],
+        id: Some(user.id),
+        external_id,
+        user_name: user.email.clone(),
+        name: user.name.as_ref().map(|name| ScimName {
+            formatted: Some(name.clone()),
+            given_name: None,
+            family_name: None,
+        }),
+        display_name: user.name.clone(),
+        emails: vec![ScimEmail {
+            value: user.email.clone(),
+            primary: Some(true),
+            type_: Some("work".to_string()),
+        }],
+        active: deleted_at.is_none(),
+        meta: Some(ScimMeta {
+            resource_type: "User".to_string(),
+            created: created_at,
+            last_modified: updated_at.max(user.updated_at),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> ScimUserState {
+        ScimUserState {
+            user_name: "ada@example.com".to_string(),
+            name: Some("Ada Lovelace".to_string()),
+            external_id: Some("00u1".to_string()),
+            active: true,
+        }
+    }
+
+    fn operation(op: &str, path: Option<&str>, value: Option<Value>) -> ScimPatchOperation {
+        ScimPatchOperation {
+            op: op.to_string(),
+            path: path.map(str::to_string),
+            value,
+        }
+    }
+
+    #[test]
+    fn test_patch_deactivates_with_string_bool() {
+        let mut state = state();
+        apply_user_patch(
+            &mut state,
+            &[operation("Replace", Some("active"), Some(json!("False")))],
+        )
+        .unwrap();
+        assert!(!state.active);
+    }
+
+    #[test]
+    fn test_patch_without_path_sets_each_attribute() {
+        let mut state = state();
+        apply_user_patch(
+            &mut state,
+            &[operation(
+                "replace",
+                None,
+                Some(json!({
+                    "active": false,
+                    "userName": "ada@lovelace.dev",
+                    "name": {"givenName": "Ada", "familyName": "King"},
+                    "title": "Countess"
+                })),
+            )],
+        )
+        .unwrap();
+
+        assert_eq!(
+            state,
+            ScimUserState {
+                user_name: "ada@lovelace.dev".to_string(),
+                name: Some("Ada King".to_string()),
+                external_id: Some("00u1".to_string()),
+                active: false,
+            }
+        );
+    }
+
+    #[test]
+    fn test_patch_remove_clears_optional_attributes() {
+        let mut state = state();
+        apply_user_patch(&mut state, &[operation("remove", Some("externalId"), None)]).unwrap();
+        assert_eq!(state.external_id, None);
+
+        let error = apply_user_patch(&mut state, &[operation("remove", Some("userName"), None)])
+            .unwrap_err();
+        assert_eq!(error.status, 400);
+    }
+
+    #[test]
+    fn test_patch_rejects_non_email_user_name() {
+        let mut state = state();
+        let error = apply_user_patch(
+            &mut state,
+            &[operation("replace", Some("userName"), Some(json!("ada")))],
+        )
+        .unwrap_err();
+        assert_eq!(error.scim_type, Some("invalidValue"));
+    }
+
+    #[test]
+    fn test_shared_user_record_keeps_email_and_name() {
+        let current = state();
+        let renamed = ScimUserState {
+            name: Some("Ada King".to_string()),
+            ..state()
+        };
+        let new_email = ScimUserState {
+            user_name: "ada@lovelace.dev".to_string(),
+            ..state()
+        };
+
+        assert_eq!(
+            user_record_update(&current, &renamed, false).unwrap(),
+            Some(("ada@example.com", &Some("Ada King".to_string())))
+        );
+        assert_eq!(user_record_update(&current, &renamed, true).unwrap(), None);
+
+        let error = user_record_update(&current, &new_email, true).unwrap_err();
+        assert_eq!(error.scim_type, Some("mutability"));
+    }
+}
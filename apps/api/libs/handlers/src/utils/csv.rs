@@ -0,0 +1,48 @@
+/// Formats one CSV record, terminated with CRLF
+pub fn csv_row(fields: impl IntoIterator<Item = String>) -> String {
+    let mut row = fields
+        .into_iter()
+        .map(|field| csv_field(&field))
+        .collect::<Vec<_>>()
+        .join(",");
+    row.push_str("\r\n");
+    row
+}
+
+/// Escapes a text field. Fields starting with a formula character are prefixed with a quote so
+/// spreadsheets don't evaluate query text as a formula.
+pub fn csv_field(field: &str) -> String {
+    if field.starts_with(['=', '+', '-', '@']) {
+        csv_quote(&format!("'{}", field))
+    } else {
+        csv_quote(field)
+    }
+}
+
+/// Quotes fields containing delimiters, quotes or line breaks, doubling embedded quotes
+/// (RFC 4180). Use `csv_field` for text that could be read as a formula.
+pub fn csv_quote(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field_escaping() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(
+            csv_field("SELECT \"name\"\nFROM users"),
+            "\"SELECT \"\"name\"\"\nFROM users\""
+        );
+        assert_eq!(csv_field("=HYPERLINK(\"x\")"), "\"'=HYPERLINK(\"\"x\"\")\"");
+        assert_eq!(csv_row(["a".to_string(), String::new()]), "a,\r\n");
+        assert_eq!(csv_quote("-12.5"), "-12.5");
+    }
+}
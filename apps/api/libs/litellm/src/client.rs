@@ -6,6 +6,10 @@ use tokio::sync::mpsc;
 use once_cell::sync::Lazy;
 use tracing;
 
+use super::providers::{
+    Anthropic, Decoded, Gemini, OpenAiCompatible, Providers, SseEvent, SseParser, StreamDecoder,
+};
+use super::replay::{FixtureKind, FixtureRequest, TrafficFixtures, TrafficMode};
 use super::types::*;
 
 // Debug flag controlled by environment variable
@@ -19,6 +23,9 @@ static DEBUG_ENABLED: Lazy<bool> = Lazy::new(|| {
 pub struct LiteLLMClient {
     client: Client,
     pub(crate) base_url: String,
+    api_key: String,
+    providers: Providers,
+    fixtures: Option<TrafficFixtures>,
 }
 
 impl LiteLLMClient {
@@ -30,6 +37,13 @@ impl LiteLLMClient {
     }
     
     pub fn new(api_key: Option<String>, base_url: Option<String>) -> Self {
+        // Misconfigured traffic settings shouldn't take the client down; traffic goes live
+        let fixtures = TrafficFixtures::from_env().unwrap_or_else(|e| {
+            tracing::error!("Ignoring LLM traffic settings: {}", e);
+            None
+        });
+        let replaying = matches!(&fixtures, Some(f) if f.mode() == TrafficMode::Replay);
+
         // Check for API key - when using LiteLLM with a config file, the API key is typically
         // already in the config file, so we just need a dummy value here for the client
         let api_key = api_key
@@ -40,7 +54,12 @@ impl LiteLLMClient {
                 if env::var("LITELLM_CONFIG_PATH").is_ok() {
                     Self::debug_log("Using LiteLLM config from environment");
                     "dummy-key-not-used".to_string()
+                } else if replaying {
+                    // Replayed traffic never reaches the LLM
+                    "dummy-key-not-used".to_string()
                 } else {
+                    // Every model without a provider prefix goes to the OpenAI-compatible
+                    // endpoint, so it needs a key even when native providers are configured
                     panic!("LLM_API_KEY must be provided either through parameter, environment variable, or LITELLM_CONFIG_PATH must be set");
                 }
             });
@@ -49,11 +68,8 @@ impl LiteLLMClient {
             .or_else(|| env::var("LLM_BASE_URL").ok())
             .unwrap_or_else(|| "http://localhost:8000".to_string());
 
+        // Each provider authenticates its own requests
         let mut headers = header::HeaderMap::new();
-        headers.insert(
-            "Authorization",
-            header::HeaderValue::from_str(&format!("Bearer {}", api_key)).unwrap(),
-        );
         headers.insert(
             "Content-Type",
             header::HeaderValue::from_static("application/json"),
@@ -68,9 +84,47 @@ impl LiteLLMClient {
             .build()
             .expect("Failed to create HTTP client");
 
+        let providers =
+            Providers::from_env(OpenAiCompatible::new(api_key.clone(), base_url.clone()));
+
         Self {
             client,
             base_url,
+            api_key,
+            providers,
+            fixtures,
+        }
+    }
+
+    /// Sends `anthropic/` models straight to the Anthropic API instead of the one configured
+    /// by `ANTHROPIC_API_KEY` and `ANTHROPIC_BASE_URL`
+    pub fn with_anthropic(mut self, api_key: String, base_url: Option<String>) -> Self {
+        self.providers.anthropic = Some(Anthropic::new(api_key, base_url));
+        self
+    }
+
+    /// Sends `gemini/` models straight to the Gemini API instead of the one configured by
+    /// `GEMINI_API_KEY` and `GEMINI_BASE_URL`
+    pub fn with_gemini(mut self, api_key: String, base_url: Option<String>) -> Self {
+        self.providers.gemini = Some(Gemini::new(api_key, base_url));
+        self
+    }
+
+    /// Records responses to, or replays them from, the given fixtures instead of the ones
+    /// configured by `LLM_TRAFFIC_MODE` and `LLM_FIXTURES_DIR`
+    pub fn with_fixtures(mut self, fixtures: TrafficFixtures) -> Self {
+        self.fixtures = Some(fixtures);
+        self
+    }
+
+    fn fixture_for<T: serde::Serialize>(
+        &self,
+        kind: FixtureKind,
+        request: &T,
+    ) -> Result<Option<(TrafficMode, FixtureRequest)>> {
+        match &self.fixtures {
+            Some(fixtures) => Ok(Some((fixtures.mode(), fixtures.fixture_for(kind, request)?))),
+            None => Ok(None),
         }
     }
 
@@ -78,9 +132,22 @@ impl LiteLLMClient {
         &self,
         request: ChatCompletionRequest,
     ) -> Result<ChatCompletionResponse> {
-        let url = format!("{}/chat/completions", self.base_url);
+        let fixture = self.fixture_for(FixtureKind::ChatCompletion, &request)?;
+        if let Some((TrafficMode::Replay, fixture)) = &fixture {
+            return fixture.load();
+        }
+
+        let (provider, model) = self.providers.route(&request.model)?;
+        let provider_request = ChatCompletionRequest {
+            model: model.to_string(),
+            ..request.clone()
+        };
 
-        Self::debug_log(&format!("Sending chat completion request to URL: {}", url));
+        Self::debug_log(&format!(
+            "Sending chat completion request for {} to {}",
+            request.model,
+            provider.name()
+        ));
         if *DEBUG_ENABLED {
             Self::debug_log(&format!(
                 "Request payload: {}",
@@ -88,10 +155,8 @@ impl LiteLLMClient {
             ));
         }
 
-        let response = self
-            .client
-            .post(&url)
-            .json(&request)
+        let response = provider
+            .chat_request(&self.client, &provider_request, false)?
             .send()
             .await
             .map_err(|e| {
@@ -109,14 +174,16 @@ impl LiteLLMClient {
         }
 
         // Parse the response text into the expected type
-        let response: ChatCompletionResponse = serde_json::from_str(&response_text).map_err(|e| {
-            tracing::error!(
-                "Failed to parse chat completion response. Text: {}, Error: {:?}",
-                response_text,
+        let response = provider
+            .parse_response(&provider_request, &response_text)
+            .map_err(|e| {
+                tracing::error!(
+                    "Failed to parse chat completion response. Text: {}, Error: {:?}",
+                    response_text,
+                    e
+                );
                 e
-            );
-            anyhow::Error::from(e)
-        })?;
+            })?;
 
         // Log tool calls if present and debug is enabled
         if *DEBUG_ENABLED {
@@ -139,6 +206,10 @@ impl LiteLLMClient {
             ));
         }
 
+        if let Some((_, fixture)) = fixture {
+            fixture.save(&response)?;
+        }
+
         Ok(response)
     }
 
@@ -146,11 +217,62 @@ impl LiteLLMClient {
         &self,
         request: ChatCompletionRequest,
     ) -> Result<mpsc::Receiver<Result<ChatCompletionChunk>>> {
-        let url = format!("{}/chat/completions", self.base_url);
+        let (mode, fixture) = match self.fixture_for(FixtureKind::ChatCompletionStream, &request)? {
+            Some(fixture) => fixture,
+            None => return self.send_stream_chat_completion(request).await,
+        };
+
+        let (tx, rx) = mpsc::channel(100);
+        match mode {
+            TrafficMode::Replay => {
+                let chunks: Vec<ChatCompletionChunk> = fixture.load()?;
+                tokio::spawn(async move {
+                    for chunk in chunks {
+                        if tx.send(Ok(chunk)).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            TrafficMode::Record => {
+                let mut stream = self.send_stream_chat_completion(request).await?;
+                tokio::spawn(async move {
+                    let mut chunks = Vec::new();
+                    let mut failed = false;
+                    while let Some(chunk) = stream.recv().await {
+                        match &chunk {
+                            Ok(chunk) => chunks.push(chunk.clone()),
+                            Err(_) => failed = true,
+                        }
+                        let _ = tx.send(chunk).await;
+                    }
+                    // A stream that errored part way through isn't worth replaying
+                    if !failed {
+                        if let Err(e) = fixture.save(&chunks) {
+                            tracing::error!("Failed to record chat completion stream: {:?}", e);
+                        }
+                    }
+                });
+            }
+        }
+
+        Ok(rx)
+    }
+
+    async fn send_stream_chat_completion(
+        &self,
+        request: ChatCompletionRequest,
+    ) -> Result<mpsc::Receiver<Result<ChatCompletionChunk>>> {
+        let (provider, model) = self.providers.route(&request.model)?;
+        let provider_request = ChatCompletionRequest {
+            model: model.to_string(),
+            ..request.clone()
+        };
 
         Self::debug_log(&format!(
-            "Starting stream chat completion request to URL: {}",
-            url
+            "Starting stream chat completion request for {} to {}",
+            request.model,
+            provider.name()
         ));
         if *DEBUG_ENABLED {
             Self::debug_log(&format!(
@@ -159,26 +281,39 @@ impl LiteLLMClient {
             ));
         }
 
-        let mut stream = self
-            .client
-            .post(&url)
-            .json(&ChatCompletionRequest {
-                stream: Some(true),
-                ..request
-            })
+        let response = provider
+            .chat_request(&self.client, &provider_request, true)?
             .send()
             .await
             .map_err(|e| {
                 tracing::error!("Failed to send stream chat completion request: {:?}", e);
                 anyhow::Error::from(e)
-            })?
-            .bytes_stream();
+            })?;
+
+        // Errors come back as a plain JSON body rather than as events
+        let status = response.status();
+        if !status.is_success() {
+            let response_text = response.text().await.unwrap_or_default();
+            tracing::error!(
+                "Stream chat completion request failed with status {}: {}",
+                status,
+                response_text
+            );
+            return Err(anyhow::anyhow!(
+                "Chat completion request failed with status {}: {}",
+                status,
+                response_text
+            ));
+        }
+
+        let mut stream = response.bytes_stream();
+        let mut decoder = provider.stream_decoder(&provider_request);
 
         let (tx, rx) = mpsc::channel(100);
         let debug_enabled = *DEBUG_ENABLED; // Capture for the async block
 
         tokio::spawn(async move {
-            let mut buffer = String::new();
+            let mut parser = SseParser::new();
             if debug_enabled {
                 Self::debug_log("Stream processing started");
             }
@@ -186,73 +321,22 @@ impl LiteLLMClient {
             while let Some(chunk_result) = stream.next().await {
                 match chunk_result {
                     Ok(chunk) => {
-                        let chunk_str = String::from_utf8_lossy(&chunk);
                         if debug_enabled {
-                            Self::debug_log(&format!("Raw response payload: {}", chunk_str));
+                            Self::debug_log(&format!(
+                                "Raw response payload: {}",
+                                String::from_utf8_lossy(&chunk)
+                            ));
                         }
-                        buffer.push_str(&chunk_str);
 
-                        while let Some(pos) = buffer.find("\n\n") {
-                            let line = buffer[..pos].trim().to_string();
-                            buffer = buffer[pos + 2..].to_string();
-
-                            if let Some(data) = line.strip_prefix("data: ") {
+                        for event in parser.push(&chunk) {
+                            if Self::decode_event(decoder.as_mut(), &event, &tx, debug_enabled) {
                                 if debug_enabled {
-                                    Self::debug_log(&format!("Processing stream data: {}", data));
-                                }
-                                if data == "[DONE]" {
-                                    if debug_enabled {
-                                        Self::debug_log("Stream completed with [DONE] signal");
-                                    }
-                                    return;
+                                    Self::debug_log("Stream completed with done signal");
                                 }
-
-                                match serde_json::from_str::<ChatCompletionChunk>(data) {
-                                    Ok(response) => {
-                                        // Log tool calls if present and debug is enabled
-                                        if debug_enabled {
-                                            if let Some(tool_calls) = &response.choices[0].delta.tool_calls
-                                            {
-                                                Self::debug_log("Tool calls in stream chunk:");
-                                                for tool_call in tool_calls {
-                                                    if let (Some(id), Some(function)) =
-                                                        (tool_call.id.clone(), tool_call.function.clone())
-                                                    {
-                                                        Self::debug_log(&format!("Tool Call ID: {}", id));
-                                                        if let Some(name) = function.name {
-                                                            Self::debug_log(&format!("Tool Name: {}", name));
-                                                        }
-                                                        if let Some(arguments) = function.arguments {
-                                                            Self::debug_log(&format!(
-                                                                "Tool Arguments: {}",
-                                                                arguments
-                                                            ));
-                                                        }
-                                                    }
-                                                }
-                                            }
-                                            Self::debug_log(&format!("Parsed stream chunk: {:?}", response));
-                                        }
-                                        
-                                        // Use try_send instead of send to avoid blocking
-                                        if tx.try_send(Ok(response)).is_err() {
-                                            // If the channel is full, log it but continue processing
-                                            if debug_enabled {
-                                                Self::debug_log("Warning: Channel full, receiver not keeping up");
-                                            }
-                                        }
-                                    }
-                                    Err(e) => {
-                                        if debug_enabled {
-                                            Self::debug_log(&format!("Error in stream processing: {:?}", e));
-                                        }
-                                        tracing::error!("Error receiving chunk from stream: {:?}", e);
-                                        // Use try_send to avoid blocking
-                                        let _ = tx.try_send(Err(anyhow::Error::from(e)));
-                                    }
+                                for chunk in decoder.finish() {
+                                    Self::send_chunk(&tx, chunk, debug_enabled);
                                 }
-                            } else if !line.is_empty() {
-                                tracing::warn!("Received unexpected line in stream: {}", line);
+                                return;
                             }
                         }
                     }
@@ -266,6 +350,14 @@ impl LiteLLMClient {
                     }
                 }
             }
+
+            // Some providers just close the connection once they're done
+            if let Some(event) = parser.finish() {
+                Self::decode_event(decoder.as_mut(), &event, &tx, debug_enabled);
+            }
+            for chunk in decoder.finish() {
+                Self::send_chunk(&tx, chunk, debug_enabled);
+            }
             if debug_enabled {
                 Self::debug_log("Stream processing completed");
             }
@@ -277,10 +369,85 @@ impl LiteLLMClient {
         Ok(rx)
     }
 
+    /// Sends the chunks decoded from one stream event and returns whether the stream is done
+    fn decode_event(
+        decoder: &mut dyn StreamDecoder,
+        event: &SseEvent,
+        tx: &mpsc::Sender<Result<ChatCompletionChunk>>,
+        debug_enabled: bool,
+    ) -> bool {
+        if debug_enabled {
+            Self::debug_log(&format!("Processing stream data: {}", event.data));
+        }
+
+        match decoder.decode(event) {
+            Ok(Decoded::Chunks(chunks)) => {
+                for chunk in chunks {
+                    Self::send_chunk(tx, chunk, debug_enabled);
+                }
+                false
+            }
+            Ok(Decoded::Done) => true,
+            Err(e) => {
+                if debug_enabled {
+                    Self::debug_log(&format!("Error in stream processing: {:?}", e));
+                }
+                tracing::error!("Error receiving chunk from stream: {:?}", e);
+                // Use try_send to avoid blocking
+                let _ = tx.try_send(Err(e));
+                false
+            }
+        }
+    }
+
+    fn send_chunk(
+        tx: &mpsc::Sender<Result<ChatCompletionChunk>>,
+        chunk: ChatCompletionChunk,
+        debug_enabled: bool,
+    ) {
+        // Log tool calls if present and debug is enabled
+        if debug_enabled {
+            if let Some(tool_calls) = chunk
+                .choices
+                .first()
+                .and_then(|choice| choice.delta.tool_calls.as_ref())
+            {
+                Self::debug_log("Tool calls in stream chunk:");
+                for tool_call in tool_calls {
+                    if let (Some(id), Some(function)) =
+                        (tool_call.id.clone(), tool_call.function.clone())
+                    {
+                        Self::debug_log(&format!("Tool Call ID: {}", id));
+                        if let Some(name) = function.name {
+                            Self::debug_log(&format!("Tool Name: {}", name));
+                        }
+                        if let Some(arguments) = function.arguments {
+                            Self::debug_log(&format!("Tool Arguments: {}", arguments));
+                        }
+                    }
+                }
+            }
+            Self::debug_log(&format!("Parsed stream chunk: {:?}", chunk));
+        }
+
+        // Use try_send instead of send to avoid blocking
+        if tx.try_send(Ok(chunk)).is_err() {
+            // If the channel is full, log it but continue processing
+            if debug_enabled {
+                Self::debug_log("Warning: Channel full, receiver not keeping up");
+            }
+        }
+    }
+
     pub async fn generate_embeddings(
         &self,
         request: EmbeddingRequest,
     ) -> Result<EmbeddingResponse> {
+        let fixture = self.fixture_for(FixtureKind::Embeddings, &request)?;
+        if let Some((TrafficMode::Replay, fixture)) = &fixture {
+            return fixture.load();
+        }
+
         let url = format!("{}/embeddings", self.base_url);
 
         Self::debug_log(&format!("Sending embedding request to URL: {}", url));
@@ -294,6 +461,7 @@ impl LiteLLMClient {
         let response = self
             .client
             .post(&url)
+            .bearer_auth(&self.api_key)
             .json(&request)
             .send()
             .await?;
@@ -330,6 +498,10 @@ impl LiteLLMClient {
             ));
         }
 
+        if let Some((_, fixture)) = fixture {
+            fixture.save(&response)?;
+        }
+
         Ok(response)
     }
 }
@@ -462,6 +634,9 @@ mod tests {
 
         let mut request = create_test_request();
         request.stream = Some(true);
+        request.stream_options = Some(StreamOptions {
+            include_usage: true,
+        });
         let request_body = serde_json::to_string(&request).unwrap();
 
         let mock = server.mock("POST", "/chat/completions")
@@ -502,6 +677,80 @@ mod tests {
         mock.assert();
     }
 
+    #[tokio::test]
+    async fn test_stream_chat_completion_record_and_replay() {
+        let mut server = mockito::Server::new_async().await;
+        let fixtures_dir =
+            env::temp_dir().join(format!("litellm-stream-fixtures-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&fixtures_dir);
+
+        let mock = server.mock("POST", "/chat/completions")
+            .with_status(200)
+            .with_header("content-type", "text/event-stream")
+            .with_body(
+                "data: {\"id\":\"1\",\"object\":\"chat.completion.chunk\",\"created\":1234567890,\"model\":\"gpt-4\",\"system_fingerprint\":\"fp_44709d6fcb\",\"choices\":[{\"index\":0,\"delta\":{\"role\":\"assistant\",\"content\":\"Hello\"},\"finish_reason\":null}]}\n\n\
+                 data: {\"id\":\"1\",\"object\":\"chat.completion.chunk\",\"created\":1234567890,\"model\":\"gpt-4\",\"system_fingerprint\":\"fp_44709d6fcb\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\" world\"},\"finish_reason\":null}]}\n\n\
+                 data: [DONE]\n\n"
+            )
+            .expect(1)
+            .create();
+
+        let recording = LiteLLMClient::new(Some("test-key".to_string()), Some(server.url()))
+            .with_fixtures(TrafficFixtures::record(&fixtures_dir));
+        let mut stream = recording
+            .stream_chat_completion(create_test_request())
+            .await
+            .unwrap();
+        let mut recorded = Vec::new();
+        while let Some(chunk) = stream.recv().await {
+            recorded.push(chunk.unwrap());
+        }
+
+        // Nothing listens at this address, so every chunk has to come from the fixture
+        let replaying = LiteLLMClient::new(
+            Some("test-key".to_string()),
+            Some("http://127.0.0.1:9".to_string()),
+        )
+        .with_fixtures(TrafficFixtures::replay(&fixtures_dir));
+        let mut stream = replaying
+            .stream_chat_completion(create_test_request())
+            .await
+            .unwrap();
+        let mut replayed = Vec::new();
+        while let Some(chunk) = stream.recv().await {
+            replayed.push(chunk.unwrap());
+        }
+
+        assert_eq!(recorded.len(), 2);
+        assert_eq!(
+            serde_json::to_value(&recorded).unwrap(),
+            serde_json::to_value(&replayed).unwrap()
+        );
+        mock.assert();
+
+        let _ = std::fs::remove_dir_all(&fixtures_dir);
+    }
+
+    #[tokio::test]
+    async fn test_chat_completion_replays_committed_fixture() {
+        // Recorded from the response mocked in test_chat_completion_success. If a change to
+        // request normalization moves the fixture's hash, committed fixtures stop replaying.
+        let fixtures_dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures");
+        let client = LiteLLMClient::new(
+            Some("test-key".to_string()),
+            Some("http://127.0.0.1:9".to_string()),
+        )
+        .with_fixtures(TrafficFixtures::replay(fixtures_dir));
+
+        let response = client.chat_completion(create_test_request()).await.unwrap();
+        assert_eq!(response.id, "test-id");
+        if let AgentMessage::Assistant { content, .. } = response.choices[0].message.clone() {
+            assert_eq!(content.unwrap(), "Hello there!");
+        } else {
+            panic!("Expected assistant message");
+        }
+    }
+
     #[tokio::test]
     async fn test_tool_call_completion() {
         let mut server = mockito::Server::new_async().await;
@@ -578,6 +827,59 @@ mod tests {
         mock.assert();
     }
 
+    #[tokio::test]
+    async fn test_chat_completion_routed_to_anthropic() {
+        let mut server = mockito::Server::new_async().await;
+
+        let mock = server
+            .mock("POST", "/v1/messages")
+            .match_header("x-api-key", "anthropic-key")
+            .match_header("anthropic-version", "2023-06-01")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "model": "claude-sonnet-4-0",
+                "messages": [{"role": "user", "content": [{"type": "text", "text": "Hello"}]}]
+            })))
+            .with_status(200)
+            .with_header("content-type", "application/json")
+            .with_body(
+                r#"{
+                "id": "msg_1",
+                "type": "message",
+                "role": "assistant",
+                "model": "claude-sonnet-4-0",
+                "content": [{"type": "text", "text": "Hello there!"}],
+                "stop_reason": "end_turn",
+                "usage": {"input_tokens": 10, "output_tokens": 20}
+            }"#,
+            )
+            .create();
+
+        // Nothing listens at the proxy address, so the call has to go to Anthropic
+        let client = LiteLLMClient::new(
+            Some("test-key".to_string()),
+            Some("http://127.0.0.1:9".to_string()),
+        )
+        .with_anthropic("anthropic-key".to_string(), Some(server.url()));
+
+        let response = client
+            .chat_completion(ChatCompletionRequest {
+                model: "anthropic/claude-sonnet-4-0".to_string(),
+                ..create_test_request()
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(response.id, "msg_1");
+        assert_eq!(
+            response.choices[0].message.get_content().as_deref(),
+            Some("Hello there!")
+        );
+        assert_eq!(response.choices[0].finish_reason.as_deref(), Some("stop"));
+        assert_eq!(response.usage.total_tokens, 30);
+
+        mock.assert();
+    }
+
     #[test]
     fn test_client_initialization_with_env_vars() {
         let test_api_key = "test-env-key";
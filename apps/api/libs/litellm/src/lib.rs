@@ -1,5 +1,9 @@
 mod client;
+mod providers;
+mod replay;
 mod types;
 
 pub use client::*;
-pub use types::{AgentMessage, ChatCompletionChunk, ChatCompletionRequest, ChatCompletionResponse, Metadata, MessageProgress, Tool, ToolCall, ToolChoice, ResponseFormat, EmbeddingRequest, EmbeddingResponse, EmbeddingData, EmbeddingUsage, DeltaToolCall, FunctionCall}; 
\ No newline at end of file
+pub use providers::{Decoded, Provider, SseEvent, StreamDecoder};
+pub use replay::{take_missing_fixtures, TrafficFixtures, TrafficMode};
+pub use types::{AgentMessage, ChatCompletionChunk, ChatCompletionRequest, ChatCompletionResponse, Metadata, MessageProgress, Tool, ToolCall, ToolChoice, ResponseFormat, StreamOptions, Usage, EmbeddingRequest, EmbeddingResponse, EmbeddingData, EmbeddingUsage, Delta, DeltaToolCall, DeltaFunctionCall, FunctionCall}; 
\ No newline at end of file
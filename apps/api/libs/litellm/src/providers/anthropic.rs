@@ -0,0 +1,722 @@
+//! Anthropic Messages API (`POST /v1/messages`).
+//!
+//! Thinking is requested when the request sets `reasoning_effort`, but only at the start of
+//! an assistant turn and with `tool_choice` auto. Anthropic requires the thinking blocks of a
+//! turn to be sent back while its tool calls are resolved, and `AgentMessage` has nowhere to
+//! keep them, so the rest of the turn runs without thinking. Forced tool use can't be
+//! combined with thinking at all.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use super::{
+    chunk, parse_arguments, reasoning_budget, unix_now, usage_chunk, Decoded, Provider, SseEvent,
+    StreamDecoder,
+};
+use crate::types::{
+    AgentMessage, ChatCompletionChunk, ChatCompletionRequest, ChatCompletionResponse, Choice,
+    Delta, DeltaFunctionCall, DeltaToolCall, FunctionCall, MessageProgress, ToolCall, ToolChoice,
+    Usage,
+};
+
+const DEFAULT_BASE_URL: &str = "https://api.anthropic.com";
+const API_VERSION: &str = "2023-06-01";
+/// Anthropic requires `max_tokens`
+const DEFAULT_MAX_TOKENS: u32 = 8192;
+
+#[derive(Debug, Clone)]
+pub struct Anthropic {
+    api_key: String,
+    base_url: String,
+}
+
+impl Anthropic {
+    pub fn new(api_key: String, base_url: Option<String>) -> Self {
+        Self {
+            api_key,
+            base_url: base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+        }
+    }
+}
+
+impl Provider for Anthropic {
+    fn name(&self) -> &'static str {
+        "anthropic"
+    }
+
+    fn chat_request(
+        &self,
+        client: &Client,
+        request: &ChatCompletionRequest,
+        stream: bool,
+    ) -> Result<RequestBuilder> {
+        Ok(client
+            .post(format!("{}/v1/messages", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION)
+            .json(&request_body(request, stream)))
+    }
+
+    fn parse_response(
+        &self,
+        request: &ChatCompletionRequest,
+        body: &str,
+    ) -> Result<ChatCompletionResponse> {
+        let message: MessageResponse = serde_json::from_str(body)
+            .map_err(|_| anyhow!("Anthropic request failed: {}", error_message(body)))?;
+
+        let mut content = String::new();
+        let mut tool_calls = Vec::new();
+        for block in message.content {
+            match block {
+                ContentBlock::Text { text } => content.push_str(&text),
+                ContentBlock::ToolUse { id, name, input } => tool_calls.push(ToolCall {
+                    id,
+                    call_type: "function".to_string(),
+                    function: FunctionCall {
+                        name,
+                        arguments: input.to_string(),
+                    },
+                    code_interpreter: None,
+                    retrieval: None,
+                }),
+                ContentBlock::Other => {}
+            }
+        }
+
+        Ok(ChatCompletionResponse {
+            id: message.id,
+            object: "chat.completion".to_string(),
+            created: unix_now(),
+            model: request.model.clone(),
+            system_fingerprint: None,
+            choices: vec![Choice {
+                index: 0,
+                message: AgentMessage::assistant(
+                    None,
+                    (!content.is_empty()).then_some(content),
+                    (!tool_calls.is_empty()).then_some(tool_calls),
+                    MessageProgress::Complete,
+                    None,
+                    None,
+                ),
+                delta: None,
+                logprobs: None,
+                finish_reason: message.stop_reason.map(|reason| finish_reason(&reason)),
+            }],
+            service_tier: None,
+            usage: message.usage.into_usage(),
+        })
+    }
+
+    fn stream_decoder(&self, request: &ChatCompletionRequest) -> Box<dyn StreamDecoder> {
+        Box::new(AnthropicDecoder {
+            model: request.model.clone(),
+            include_usage: request
+                .stream_options
+                .as_ref()
+                .is_none_or(|options| options.include_usage),
+            created: unix_now(),
+            ..Default::default()
+        })
+    }
+}
+
+fn request_body(request: &ChatCompletionRequest, stream: bool) -> Value {
+    let mut system = Vec::new();
+    let mut messages: Vec<(&str, Vec<Value>)> = Vec::new();
+
+    for message in &request.messages {
+        let (role, blocks) = match message {
+            AgentMessage::Developer { content, .. } => {
+                system.push(content.as_str());
+                continue;
+            }
+            AgentMessage::User { content, .. } => {
+                ("user", vec![json!({"type": "text", "text": content})])
+            }
+            AgentMessage::Assistant {
+                content,
+                tool_calls,
+                ..
+            } => {
+                let mut blocks = Vec::new();
+                if let Some(text) = content.as_ref().filter(|text| !text.is_empty()) {
+                    blocks.push(json!({"type": "text", "text": text}));
+                }
+                for call in tool_calls.iter().flatten() {
+                    blocks.push(json!({
+                        "type": "tool_use",
+                        "id": call.id,
+                        "name": call.function.name,
+                        "input": parse_arguments(&call.function.arguments),
+                    }));
+                }
+                ("assistant", blocks)
+            }
+            AgentMessage::Tool {
+                content,
+                tool_call_id,
+                ..
+            } => (
+                "user",
+                vec![json!({
+                    "type": "tool_result",
+                    "tool_use_id": tool_call_id,
+                    "content": content,
+                })],
+            ),
+            AgentMessage::Done => continue,
+        };
+
+        if blocks.is_empty() {
+            continue;
+        }
+        // Tool results follow each other, and turns must alternate
+        match messages.last_mut() {
+            Some((last_role, last_blocks)) if *last_role == role => last_blocks.extend(blocks),
+            _ => messages.push((role, blocks)),
+        }
+    }
+
+    let mut max_tokens = request.max_completion_tokens.unwrap_or(DEFAULT_MAX_TOKENS);
+    let mut body = Map::new();
+    body.insert("model".to_string(), json!(request.model));
+    body.insert(
+        "messages".to_string(),
+        messages
+            .into_iter()
+            .map(|(role, content)| json!({"role": role, "content": content}))
+            .collect(),
+    );
+    if !system.is_empty() {
+        body.insert("system".to_string(), json!(system.join("\n\n")));
+    }
+
+    if let Some(tools) = request.tools.as_ref().filter(|tools| !tools.is_empty()) {
+        let tools: Vec<Value> = tools
+            .iter()
+            .map(|tool| {
+                json!({
+                    "name": tool.function["name"],
+                    "description": tool.function["description"].as_str().unwrap_or_default(),
+                    "input_schema": tool
+                        .function
+                        .get("parameters")
+                        .cloned()
+                        .unwrap_or_else(|| json!({"type": "object", "properties": {}})),
+                })
+            })
+            .collect();
+        body.insert("tools".to_string(), json!(tools));
+
+        let mut tool_choice = match &request.tool_choice {
+            Some(ToolChoice::None) => json!({"type": "none"}),
+            Some(ToolChoice::Required) => json!({"type": "any"}),
+            Some(ToolChoice::Function { function, .. }) => {
+                json!({"type": "tool", "name": function.name})
+            }
+            Some(ToolChoice::Auto) | None => json!({"type": "auto"}),
+        };
+        if request.parallel_tool_calls == Some(false) {
+            tool_choice["disable_parallel_tool_use"] = json!(true);
+        }
+        body.insert("tool_choice".to_string(), tool_choice);
+    }
+
+    let thinking_budget = request
+        .reasoning_effort
+        .as_deref()
+        .and_then(reasoning_budget)
+        .filter(|_| can_think(request));
+    match thinking_budget {
+        Some(budget) => {
+            // Thinking counts against max_tokens, so the answer keeps its own allowance.
+            // Temperature and top_p can't be changed while thinking.
+            max_tokens += budget;
+            body.insert(
+                "thinking".to_string(),
+                json!({"type": "enabled", "budget_tokens": budget}),
+            );
+        }
+        None => {
+            if let Some(temperature) = request.temperature {
+                body.insert("temperature".to_string(), json!(temperature));
+            }
+            if let Some(top_p) = request.top_p {
+                body.insert("top_p".to_string(), json!(top_p));
+            }
+        }
+    }
+    body.insert("max_tokens".to_string(), json!(max_tokens));
+
+    if let Some(stop) = &request.stop {
+        body.insert("stop_sequences".to_string(), json!(stop));
+    }
+    if stream {
+        body.insert("stream".to_string(), json!(true));
+    }
+
+    Value::Object(body)
+}
+
+fn can_think(request: &ChatCompletionRequest) -> bool {
+    let starts_turn = matches!(
+        request
+            .messages
+            .iter()
+            .rev()
+            .find(|message| !matches!(message, AgentMessage::Developer { .. })),
+        Some(AgentMessage::User { .. })
+    );
+    let auto_tool_choice = matches!(
+        request.tool_choice,
+        None | Some(ToolChoice::Auto) | Some(ToolChoice::None)
+    );
+
+    starts_turn && auto_tool_choice
+}
+
+fn finish_reason(stop_reason: &str) -> String {
+    match stop_reason {
+        "tool_use" => "tool_calls",
+        "max_tokens" => "length",
+        "refusal" => "content_filter",
+        _ => "stop",
+    }
+    .to_string()
+}
+
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|value| value["error"]["message"].as_str().map(String::from))
+        .unwrap_or_else(|| body.to_string())
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageResponse {
+    id: String,
+    content: Vec<ContentBlock>,
+    stop_reason: Option<String>,
+    usage: AnthropicUsage,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text {
+        text: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        input: Value,
+    },
+    /// `thinking` and `redacted_thinking`, which aren't part of the message
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct AnthropicUsage {
+    #[serde(default)]
+    input_tokens: i32,
+    #[serde(default)]
+    cache_creation_input_tokens: Option<i32>,
+    #[serde(default)]
+    cache_read_input_tokens: Option<i32>,
+    #[serde(default)]
+    output_tokens: i32,
+}
+
+impl AnthropicUsage {
+    fn prompt_tokens(&self) -> i32 {
+        self.input_tokens
+            + self.cache_creation_input_tokens.unwrap_or_default()
+            + self.cache_read_input_tokens.unwrap_or_default()
+    }
+
+    fn into_usage(self) -> Usage {
+        let prompt_tokens = self.prompt_tokens();
+        Usage {
+            prompt_tokens,
+            completion_tokens: self.output_tokens,
+            total_tokens: prompt_tokens + self.output_tokens,
+            completion_tokens_details: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamEvent {
+    MessageStart {
+        message: StartedMessage,
+    },
+    ContentBlockStart {
+        index: usize,
+        content_block: ContentBlock,
+    },
+    ContentBlockDelta {
+        index: usize,
+        delta: BlockDelta,
+    },
+    MessageDelta {
+        delta: MessageDelta,
+        #[serde(default)]
+        usage: Option<AnthropicUsage>,
+    },
+    MessageStop,
+    Error {
+        error: Value,
+    },
+    /// `ping` and `content_block_stop`
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct StartedMessage {
+    id: String,
+    #[serde(default)]
+    usage: AnthropicUsage,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BlockDelta {
+    TextDelta {
+        text: String,
+    },
+    InputJsonDelta {
+        partial_json: String,
+    },
+    ThinkingDelta {
+        thinking: String,
+    },
+    /// `signature_delta`, which is only needed to send thinking back
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageDelta {
+    stop_reason: Option<String>,
+}
+
+#[derive(Default)]
+struct AnthropicDecoder {
+    id: String,
+    model: String,
+    created: i64,
+    include_usage: bool,
+    /// Tool call ids by content block index. Every tool call delta carries its call's id
+    /// because the agent matches deltas to calls by id.
+    tool_call_ids: HashMap<usize, String>,
+    usage: AnthropicUsage,
+}
+
+impl AnthropicDecoder {
+    fn chunk(&self, delta: Delta, finish_reason: Option<String>) -> ChatCompletionChunk {
+        chunk(&self.id, &self.model, self.created, delta, finish_reason)
+    }
+
+    fn tool_call_delta(id: &str, name: Option<String>, arguments: String) -> Delta {
+        Delta {
+            tool_calls: Some(vec![DeltaToolCall {
+                id: Some(id.to_string()),
+                call_type: Some("function".to_string()),
+                function: Some(DeltaFunctionCall {
+                    name,
+                    arguments: Some(arguments),
+                }),
+                code_interpreter: None,
+                retrieval: None,
+            }]),
+            ..Default::default()
+        }
+    }
+}
+
+impl StreamDecoder for AnthropicDecoder {
+    fn decode(&mut self, event: &SseEvent) -> Result<Decoded> {
+        let chunk = match serde_json::from_str::<StreamEvent>(&event.data)? {
+            StreamEvent::MessageStart { message } => {
+                self.id = message.id;
+                self.usage = message.usage;
+                self.chunk(
+                    Delta {
+                        role: Some("assistant".to_string()),
+                        content: Some(String::new()),
+                        ..Default::default()
+                    },
+                    None,
+                )
+            }
+            StreamEvent::ContentBlockStart {
+                index,
+                content_block: ContentBlock::ToolUse { id, name, .. },
+            } => {
+                let delta = Self::tool_call_delta(&id, Some(name), String::new());
+                self.tool_call_ids.insert(index, id);
+                self.chunk(delta, None)
+            }
+            StreamEvent::ContentBlockStart { .. } => return Ok(Decoded::Chunks(Vec::new())),
+            StreamEvent::ContentBlockDelta { index, delta } => match delta {
+                BlockDelta::TextDelta { text } => self.chunk(
+                    Delta {
+                        content: Some(text),
+                        ..Default::default()
+                    },
+                    None,
+                ),
+                BlockDelta::ThinkingDelta { thinking } => self.chunk(
+                    Delta {
+                        reasoning_content: Some(thinking),
+                        ..Default::default()
+                    },
+                    None,
+                ),
+                BlockDelta::InputJsonDelta { partial_json } => {
+                    let id = self
+                        .tool_call_ids
+                        .get(&index)
+                        .ok_or_else(|| anyhow!("Tool input for unknown content block {}", index))?;
+                    self.chunk(Self::tool_call_delta(id, None, partial_json), None)
+                }
+                BlockDelta::Other => return Ok(Decoded::Chunks(Vec::new())),
+            },
+            StreamEvent::MessageDelta { delta, usage } => {
+                if let Some(usage) = usage {
+                    self.usage.output_tokens = usage.output_tokens;
+                }
+                match delta.stop_reason {
+                    Some(reason) => self.chunk(Delta::default(), Some(finish_reason(&reason))),
+                    None => return Ok(Decoded::Chunks(Vec::new())),
+                }
+            }
+            StreamEvent::MessageStop => return Ok(Decoded::Done),
+            StreamEvent::Error { error } => {
+                return Err(anyhow!(
+                    "Anthropic stream failed: {}",
+                    error["message"].as_str().unwrap_or("unknown error")
+                ))
+            }
+            StreamEvent::Other => return Ok(Decoded::Chunks(Vec::new())),
+        };
+
+        Ok(Decoded::Chunks(vec![chunk]))
+    }
+
+    fn finish(&mut self) -> Vec<ChatCompletionChunk> {
+        if !self.include_usage || self.id.is_empty() {
+            return Vec::new();
+        }
+        let usage = std::mem::take(&mut self.usage).into_usage();
+        vec![usage_chunk(&self.id, &self.model, self.created, usage)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::Tool;
+
+    fn request(messages: Vec<AgentMessage>) -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            model: "claude-sonnet-4-0".to_string(),
+            messages,
+            tools: Some(vec![Tool {
+                tool_type: "function".to_string(),
+                function: json!({
+                    "name": "search",
+                    "description": "Searches the catalog",
+                    "parameters": {"type": "object", "properties": {"q": {"type": "string"}}},
+                }),
+            }]),
+            tool_choice: Some(ToolChoice::Auto),
+            reasoning_effort: Some("low".to_string()),
+            temperature: Some(0.0),
+            ..Default::default()
+        }
+    }
+
+    fn tool_call(id: &str, arguments: &str) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            call_type: "function".to_string(),
+            function: FunctionCall {
+                name: "search".to_string(),
+                arguments: arguments.to_string(),
+            },
+            code_interpreter: None,
+            retrieval: None,
+        }
+    }
+
+    fn event(data: Value) -> SseEvent {
+        SseEvent {
+            event: data["type"].as_str().map(String::from),
+            data: data.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_request_body_at_the_start_of_a_turn() {
+        let body = request_body(
+            &request(vec![
+                AgentMessage::developer("Be brief."),
+                AgentMessage::user("How many orders?"),
+            ]),
+            true,
+        );
+
+        assert_eq!(body["system"], "Be brief.");
+        assert_eq!(
+            body["messages"],
+            json!([{"role": "user", "content": [{"type": "text", "text": "How many orders?"}]}])
+        );
+        assert_eq!(body["tools"][0]["name"], "search");
+        assert_eq!(body["tools"][0]["input_schema"]["properties"]["q"]["type"], "string");
+        assert_eq!(body["tool_choice"], json!({"type": "auto"}));
+        assert_eq!(body["thinking"], json!({"type": "enabled", "budget_tokens": 1024}));
+        assert_eq!(body["max_tokens"], DEFAULT_MAX_TOKENS + 1024);
+        assert!(body.get("temperature").is_none());
+        assert_eq!(body["stream"], true);
+    }
+
+    #[test]
+    fn test_request_body_while_resolving_tool_calls() {
+        let body = request_body(
+            &request(vec![
+                AgentMessage::user("How many orders?"),
+                AgentMessage::assistant(
+                    None,
+                    None,
+                    Some(vec![tool_call("call_1", r#"{"q":"orders"}"#), tool_call("call_2", "")]),
+                    MessageProgress::Complete,
+                    None,
+                    None,
+                ),
+                AgentMessage::tool(None, "orders", "call_1", None, MessageProgress::Complete),
+                AgentMessage::tool(None, "nothing", "call_2", None, MessageProgress::Complete),
+            ]),
+            false,
+        );
+
+        let messages = body["messages"].as_array().unwrap();
+        assert_eq!(messages.len(), 3);
+        assert_eq!(messages[1]["content"][0]["input"], json!({"q": "orders"}));
+        assert_eq!(messages[1]["content"][1]["input"], json!({}));
+        // Both tool results go back in one user turn
+        assert_eq!(messages[2]["role"], "user");
+        assert_eq!(messages[2]["content"][1]["tool_use_id"], "call_2");
+
+        assert!(body.get("thinking").is_none());
+        assert_eq!(body["temperature"], 0.0);
+        assert_eq!(body["max_tokens"], DEFAULT_MAX_TOKENS);
+        assert!(body.get("stream").is_none());
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let body = json!({
+            "id": "msg_1",
+            "type": "message",
+            "role": "assistant",
+            "content": [
+                {"type": "thinking", "thinking": "Look it up.", "signature": "sig"},
+                {"type": "text", "text": "Searching."},
+                {"type": "tool_use", "id": "toolu_1", "name": "search", "input": {"q": "orders"}}
+            ],
+            "stop_reason": "tool_use",
+            "usage": {"input_tokens": 10, "cache_read_input_tokens": 5, "output_tokens": 7}
+        });
+
+        let response = Anthropic::new("key".to_string(), None)
+            .parse_response(&request(vec![]), &body.to_string())
+            .unwrap();
+
+        let choice = &response.choices[0];
+        assert_eq!(choice.finish_reason.as_deref(), Some("tool_calls"));
+        assert_eq!(choice.message.get_content().as_deref(), Some("Searching."));
+        let tool_calls = choice.message.get_tool_calls().unwrap();
+        assert_eq!(tool_calls[0].id, "toolu_1");
+        assert_eq!(tool_calls[0].function.arguments, r#"{"q":"orders"}"#);
+        assert_eq!(response.usage.prompt_tokens, 15);
+        assert_eq!(response.usage.total_tokens, 22);
+    }
+
+    #[test]
+    fn test_parse_error_response() {
+        let body = r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#;
+        let err = Anthropic::new("key".to_string(), None)
+            .parse_response(&request(vec![]), body)
+            .unwrap_err();
+
+        assert_eq!(err.to_string(), "Anthropic request failed: Overloaded");
+    }
+
+    #[test]
+    fn test_stream_decoder() {
+        let mut decoder = Anthropic::new("key".to_string(), None).stream_decoder(&request(vec![]));
+        let events = [
+            json!({"type": "message_start", "message": {"id": "msg_1", "usage": {"input_tokens": 12, "output_tokens": 1}}}),
+            json!({"type": "content_block_start", "index": 0, "content_block": {"type": "thinking", "thinking": ""}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "thinking_delta", "thinking": "Search first."}}),
+            json!({"type": "content_block_delta", "index": 0, "delta": {"type": "signature_delta", "signature": "sig"}}),
+            json!({"type": "content_block_stop", "index": 0}),
+            json!({"type": "content_block_start", "index": 1, "content_block": {"type": "tool_use", "id": "toolu_1", "name": "search", "input": {}}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "{\"q\": "}}),
+            json!({"type": "content_block_delta", "index": 1, "delta": {"type": "input_json_delta", "partial_json": "\"orders\"}"}}),
+            json!({"type": "ping"}),
+            json!({"type": "message_delta", "delta": {"stop_reason": "tool_use"}, "usage": {"output_tokens": 30}}),
+        ];
+
+        let mut chunks = Vec::new();
+        for data in events {
+            match decoder.decode(&event(data)).unwrap() {
+                Decoded::Chunks(decoded) => chunks.extend(decoded),
+                Decoded::Done => panic!("Stream ended early"),
+            }
+        }
+        assert!(matches!(
+            decoder.decode(&event(json!({"type": "message_stop"}))).unwrap(),
+            Decoded::Done
+        ));
+        chunks.extend(decoder.finish());
+
+        let deltas: Vec<&Delta> = chunks
+            .iter()
+            .filter_map(|chunk| chunk.choices.first().map(|choice| &choice.delta))
+            .collect();
+        assert_eq!(deltas[0].role.as_deref(), Some("assistant"));
+        assert_eq!(deltas[1].reasoning_content.as_deref(), Some("Search first."));
+
+        let tool_deltas: Vec<&DeltaToolCall> = deltas
+            .iter()
+            .filter_map(|delta| delta.tool_calls.as_ref())
+            .flatten()
+            .collect();
+        assert_eq!(tool_deltas.len(), 3);
+        assert!(tool_deltas.iter().all(|d| d.id.as_deref() == Some("toolu_1")));
+        let arguments: String = tool_deltas
+            .iter()
+            .filter_map(|d| d.function.as_ref()?.arguments.clone())
+            .collect();
+        assert_eq!(arguments, r#"{"q": "orders"}"#);
+
+        let finished = &chunks[chunks.len() - 2];
+        assert_eq!(finished.choices[0].finish_reason.as_deref(), Some("tool_calls"));
+
+        let usage = chunks.last().unwrap().usage.as_ref().unwrap();
+        assert_eq!(usage.prompt_tokens, 12);
+        assert_eq!(usage.completion_tokens, 30);
+        assert!(chunks.iter().all(|chunk| chunk.id == "msg_1"));
+    }
+}
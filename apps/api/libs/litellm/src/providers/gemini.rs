@@ -0,0 +1,763 @@
+//! Gemini API (`generateContent` and `streamGenerateContent`).
+//!
+//! Gemini sends each function call whole rather than streaming its arguments, and doesn't
+//! always give calls an id, so the decoder makes one up for calls without.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Result};
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use super::{
+    chunk, reasoning_budget, unix_now, usage_chunk, Decoded, Provider, SseEvent, StreamDecoder,
+};
+use crate::types::{
+    AgentMessage, ChatCompletionChunk, ChatCompletionRequest, ChatCompletionResponse, Choice,
+    CompletionTokensDetails, Delta, DeltaFunctionCall, DeltaToolCall, FunctionCall,
+    MessageProgress, ToolCall, ToolChoice, Usage,
+};
+
+const DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com";
+
+/// Schema keywords Gemini rejects in function parameters
+const UNSUPPORTED_SCHEMA_KEYS: &[&str] = &["additionalProperties", "$schema", "strict"];
+
+#[derive(Debug, Clone)]
+pub struct Gemini {
+    api_key: String,
+    base_url: String,
+}
+
+impl Gemini {
+    pub fn new(api_key: String, base_url: Option<String>) -> Self {
+        Self {
+            api_key,
+            base_url: base_url.unwrap_or_else(|| DEFAULT_BASE_URL.to_string()),
+        }
+    }
+}
+
+impl Provider for Gemini {
+    fn name(&self) -> &'static str {
+        "gemini"
+    }
+
+    fn chat_request(
+        &self,
+        client: &Client,
+        request: &ChatCompletionRequest,
+        stream: bool,
+    ) -> Result<RequestBuilder> {
+        let url = if stream {
+            format!(
+                "{}/v1beta/models/{}:streamGenerateContent?alt=sse",
+                self.base_url, request.model
+            )
+        } else {
+            format!("{}/v1beta/models/{}:generateContent", self.base_url, request.model)
+        };
+
+        Ok(client
+            .post(url)
+            .header("x-goog-api-key", &self.api_key)
+            .json(&request_body(request)))
+    }
+
+    fn parse_response(
+        &self,
+        request: &ChatCompletionRequest,
+        body: &str,
+    ) -> Result<ChatCompletionResponse> {
+        let response = parse_generate_response(body)?;
+        let created = unix_now();
+        let id = response.response_id.clone().unwrap_or_default();
+
+        let mut content = String::new();
+        let mut tool_calls = Vec::new();
+        let candidate = response.candidates.into_iter().next().unwrap_or_default();
+        for part in candidate.content.parts {
+            if let Some(text) = part.text.filter(|_| !part.thought) {
+                content.push_str(&text);
+            }
+            if let Some(call) = part.function_call {
+                let id = call
+                    .id
+                    .unwrap_or_else(|| tool_call_id(&id, created, tool_calls.len()));
+                tool_calls.push(ToolCall {
+                    id,
+                    call_type: "function".to_string(),
+                    function: FunctionCall {
+                        name: call.name,
+                        arguments: call.args.to_string(),
+                    },
+                    code_interpreter: None,
+                    retrieval: None,
+                });
+            }
+        }
+
+        let finish_reason = candidate
+            .finish_reason
+            .map(|reason| finish_reason(&reason, !tool_calls.is_empty()));
+
+        Ok(ChatCompletionResponse {
+            id,
+            object: "chat.completion".to_string(),
+            created,
+            model: request.model.clone(),
+            system_fingerprint: None,
+            choices: vec![Choice {
+                index: 0,
+                message: AgentMessage::assistant(
+                    None,
+                    (!content.is_empty()).then_some(content),
+                    (!tool_calls.is_empty()).then_some(tool_calls),
+                    MessageProgress::Complete,
+                    None,
+                    None,
+                ),
+                delta: None,
+                logprobs: None,
+                finish_reason,
+            }],
+            service_tier: None,
+            usage: response.usage_metadata.unwrap_or_default().into_usage(),
+        })
+    }
+
+    fn stream_decoder(&self, request: &ChatCompletionRequest) -> Box<dyn StreamDecoder> {
+        Box::new(GeminiDecoder {
+            model: request.model.clone(),
+            include_usage: request
+                .stream_options
+                .as_ref()
+                .is_none_or(|options| options.include_usage),
+            created: unix_now(),
+            ..Default::default()
+        })
+    }
+}
+
+fn request_body(request: &ChatCompletionRequest) -> Value {
+    // Gemini names function responses rather than matching them to calls by id
+    let tool_names: HashMap<&str, &str> = request
+        .messages
+        .iter()
+        .filter_map(|message| match message {
+            AgentMessage::Assistant {
+                tool_calls: Some(calls),
+                ..
+            } => Some(calls),
+            _ => None,
+        })
+        .flatten()
+        .map(|call| (call.id.as_str(), call.function.name.as_str()))
+        .collect();
+
+    let mut system = Vec::new();
+    let mut contents: Vec<(&str, Vec<Value>)> = Vec::new();
+
+    for message in &request.messages {
+        let (role, parts) = match message {
+            AgentMessage::Developer { content, .. } => {
+                system.push(content.as_str());
+                continue;
+            }
+            AgentMessage::User { content, .. } => ("user", vec![json!({"text": content})]),
+            AgentMessage::Assistant {
+                content,
+                tool_calls,
+                ..
+            } => {
+                let mut parts = Vec::new();
+                if let Some(text) = content.as_ref().filter(|text| !text.is_empty()) {
+                    parts.push(json!({"text": text}));
+                }
+                for call in tool_calls.iter().flatten() {
+                    parts.push(json!({
+                        "functionCall": {
+                            "name": call.function.name,
+                            "args": super::parse_arguments(&call.function.arguments),
+                        }
+                    }));
+                }
+                ("model", parts)
+            }
+            AgentMessage::Tool {
+                content,
+                tool_call_id,
+                name,
+                ..
+            } => {
+                let name = name
+                    .as_deref()
+                    .or_else(|| tool_names.get(tool_call_id.as_str()).copied())
+                    .unwrap_or_default();
+                // The response has to be an object
+                let response = match serde_json::from_str::<Value>(content) {
+                    Ok(value @ Value::Object(_)) => value,
+                    Ok(value) => json!({"content": value}),
+                    Err(_) => json!({"content": content}),
+                };
+                (
+                    "user",
+                    vec![json!({"functionResponse": {"name": name, "response": response}})],
+                )
+            }
+            AgentMessage::Done => continue,
+        };
+
+        if parts.is_empty() {
+            continue;
+        }
+        match contents.last_mut() {
+            Some((last_role, last_parts)) if *last_role == role => last_parts.extend(parts),
+            _ => contents.push((role, parts)),
+        }
+    }
+
+    let mut body = Map::new();
+    body.insert(
+        "contents".to_string(),
+        contents
+            .into_iter()
+            .map(|(role, parts)| json!({"role": role, "parts": parts}))
+            .collect(),
+    );
+    if !system.is_empty() {
+        body.insert(
+            "systemInstruction".to_string(),
+            json!({"parts": [{"text": system.join("\n\n")}]}),
+        );
+    }
+
+    if let Some(tools) = request.tools.as_ref().filter(|tools| !tools.is_empty()) {
+        let declarations: Vec<Value> = tools
+            .iter()
+            .map(|tool| {
+                let mut declaration = json!({
+                    "name": tool.function["name"],
+                    "description": tool.function["description"].as_str().unwrap_or_default(),
+                });
+                // Gemini rejects object schemas without properties, so tools without
+                // parameters leave them out
+                let parameters = &tool.function["parameters"];
+                if parameters["properties"]
+                    .as_object()
+                    .is_some_and(|properties| !properties.is_empty())
+                {
+                    declaration["parameters"] = clean_schema(parameters);
+                }
+                declaration
+            })
+            .collect();
+        body.insert(
+            "tools".to_string(),
+            json!([{"functionDeclarations": declarations}]),
+        );
+
+        let function_calling = match &request.tool_choice {
+            Some(ToolChoice::None) => json!({"mode": "NONE"}),
+            Some(ToolChoice::Required) => json!({"mode": "ANY"}),
+            Some(ToolChoice::Function { function, .. }) => {
+                json!({"mode": "ANY", "allowedFunctionNames": [function.name]})
+            }
+            Some(ToolChoice::Auto) | None => json!({"mode": "AUTO"}),
+        };
+        body.insert(
+            "toolConfig".to_string(),
+            json!({"functionCallingConfig": function_calling}),
+        );
+    }
+
+    let mut config = Map::new();
+    if let Some(temperature) = request.temperature {
+        config.insert("temperature".to_string(), json!(temperature));
+    }
+    if let Some(top_p) = request.top_p {
+        config.insert("topP".to_string(), json!(top_p));
+    }
+    if let Some(max_tokens) = request.max_completion_tokens {
+        config.insert("maxOutputTokens".to_string(), json!(max_tokens));
+    }
+    if let Some(stop) = &request.stop {
+        config.insert("stopSequences".to_string(), json!(stop));
+    }
+    if request
+        .response_format
+        .as_ref()
+        .is_some_and(|format| format.type_.starts_with("json"))
+    {
+        config.insert("responseMimeType".to_string(), json!("application/json"));
+    }
+    if let Some(budget) = request
+        .reasoning_effort
+        .as_deref()
+        .and_then(reasoning_budget)
+        .filter(|_| supports_thinking(&request.model))
+    {
+        config.insert(
+            "thinkingConfig".to_string(),
+            json!({"thinkingBudget": budget, "includeThoughts": true}),
+        );
+    }
+    if !config.is_empty() {
+        body.insert("generationConfig".to_string(), Value::Object(config));
+    }
+
+    Value::Object(body)
+}
+
+/// Models before 2.5 reject a thinking config
+fn supports_thinking(model: &str) -> bool {
+    !(model.starts_with("gemini-1") || model.starts_with("gemini-2.0"))
+}
+
+/// Rewrites a JSON schema into the subset Gemini accepts
+fn clean_schema(schema: &Value) -> Value {
+    match schema {
+        Value::Object(map) => {
+            let mut cleaned = Map::new();
+            for (key, value) in map {
+                if UNSUPPORTED_SCHEMA_KEYS.contains(&key.as_str()) {
+                    continue;
+                }
+                // Nullable types are written `["string", "null"]` for OpenAI
+                if let ("type", Value::Array(types)) = (key.as_str(), value) {
+                    let mut types = types.iter().filter(|t| t.as_str() != Some("null"));
+                    if let Some(first) = types.next() {
+                        cleaned.insert(key.clone(), first.clone());
+                    }
+                    cleaned.insert("nullable".to_string(), json!(true));
+                    continue;
+                }
+                cleaned.insert(key.clone(), clean_schema(value));
+            }
+            Value::Object(cleaned)
+        }
+        Value::Array(items) => Value::Array(items.iter().map(clean_schema).collect()),
+        other => other.clone(),
+    }
+}
+
+fn finish_reason(reason: &str, called_tools: bool) -> String {
+    match reason {
+        "STOP" if called_tools => "tool_calls",
+        "MAX_TOKENS" => "length",
+        "SAFETY" | "RECITATION" | "BLOCKLIST" | "PROHIBITED_CONTENT" | "SPII" => "content_filter",
+        _ => "stop",
+    }
+    .to_string()
+}
+
+fn tool_call_id(response_id: &str, created: i64, index: usize) -> String {
+    if response_id.is_empty() {
+        format!("call_{}_{}", created, index)
+    } else {
+        format!("call_{}_{}", response_id, index)
+    }
+}
+
+fn parse_generate_response(body: &str) -> Result<GenerateContentResponse> {
+    let value: Value = serde_json::from_str(body)
+        .map_err(|_| anyhow!("Gemini request failed: {}", body))?;
+    if let Some(error) = value.get("error") {
+        return Err(anyhow!(
+            "Gemini request failed: {}",
+            error["message"].as_str().unwrap_or("unknown error")
+        ));
+    }
+    Ok(serde_json::from_value(value)?)
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateContentResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+    usage_metadata: Option<UsageMetadata>,
+    response_id: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Candidate {
+    #[serde(default)]
+    content: Content,
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Content {
+    #[serde(default)]
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Part {
+    text: Option<String>,
+    /// Set on the parts that carry the model's thinking
+    #[serde(default)]
+    thought: bool,
+    function_call: Option<GeminiFunctionCall>,
+}
+
+#[derive(Debug, Deserialize)]
+struct GeminiFunctionCall {
+    id: Option<String>,
+    name: String,
+    #[serde(default)]
+    args: Value,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UsageMetadata {
+    #[serde(default)]
+    prompt_token_count: i32,
+    #[serde(default)]
+    candidates_token_count: i32,
+    #[serde(default)]
+    thoughts_token_count: i32,
+    #[serde(default)]
+    total_token_count: i32,
+}
+
+impl UsageMetadata {
+    fn into_usage(self) -> Usage {
+        let completion_tokens = self.candidates_token_count + self.thoughts_token_count;
+        Usage {
+            prompt_tokens: self.prompt_token_count,
+            completion_tokens,
+            total_tokens: self
+                .total_token_count
+                .max(self.prompt_token_count + completion_tokens),
+            completion_tokens_details: (self.thoughts_token_count > 0).then_some(
+                CompletionTokensDetails {
+                    reasoning_tokens: self.thoughts_token_count,
+                    accepted_prediction_tokens: 0,
+                    rejected_prediction_tokens: 0,
+                },
+            ),
+        }
+    }
+}
+
+#[derive(Default)]
+struct GeminiDecoder {
+    id: String,
+    model: String,
+    created: i64,
+    include_usage: bool,
+    started: bool,
+    tool_calls: usize,
+    /// Each event carries the usage so far, so only the last one counts
+    usage: Option<UsageMetadata>,
+}
+
+impl GeminiDecoder {
+    fn chunk(&self, delta: Delta, finish_reason: Option<String>) -> ChatCompletionChunk {
+        chunk(&self.id, &self.model, self.created, delta, finish_reason)
+    }
+}
+
+impl StreamDecoder for GeminiDecoder {
+    fn decode(&mut self, event: &SseEvent) -> Result<Decoded> {
+        let response = parse_generate_response(&event.data)?;
+        let mut chunks = Vec::new();
+
+        if !self.started {
+            self.started = true;
+            self.id = response.response_id.clone().unwrap_or_default();
+            chunks.push(self.chunk(
+                Delta {
+                    role: Some("assistant".to_string()),
+                    content: Some(String::new()),
+                    ..Default::default()
+                },
+                None,
+            ));
+        }
+        if response.usage_metadata.is_some() {
+            self.usage = response.usage_metadata;
+        }
+
+        let Some(candidate) = response.candidates.into_iter().next() else {
+            return Ok(Decoded::Chunks(chunks));
+        };
+
+        for part in candidate.content.parts {
+            if let Some(text) = part.text {
+                let delta = if part.thought {
+                    Delta {
+                        reasoning_content: Some(text),
+                        ..Default::default()
+                    }
+                } else {
+                    Delta {
+                        content: Some(text),
+                        ..Default::default()
+                    }
+                };
+                chunks.push(self.chunk(delta, None));
+            }
+            if let Some(call) = part.function_call {
+                let id = call
+                    .id
+                    .unwrap_or_else(|| tool_call_id(&self.id, self.created, self.tool_calls));
+                self.tool_calls += 1;
+                chunks.push(self.chunk(
+                    Delta {
+                        tool_calls: Some(vec![DeltaToolCall {
+                            id: Some(id),
+                            call_type: Some("function".to_string()),
+                            function: Some(DeltaFunctionCall {
+                                name: Some(call.name),
+                                arguments: Some(call.args.to_string()),
+                            }),
+                            code_interpreter: None,
+                            retrieval: None,
+                        }]),
+                        ..Default::default()
+                    },
+                    None,
+                ));
+            }
+        }
+
+        if let Some(reason) = candidate.finish_reason {
+            chunks.push(self.chunk(
+                Delta::default(),
+                Some(finish_reason(&reason, self.tool_calls > 0)),
+            ));
+        }
+
+        Ok(Decoded::Chunks(chunks))
+    }
+
+    fn finish(&mut self) -> Vec<ChatCompletionChunk> {
+        match self.usage.take().filter(|_| self.include_usage) {
+            Some(usage) => vec![usage_chunk(
+                &self.id,
+                &self.model,
+                self.created,
+                usage.into_usage(),
+            )],
+            None => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{ResponseFormat, Tool};
+
+    fn request(messages: Vec<AgentMessage>) -> ChatCompletionRequest {
+        ChatCompletionRequest {
+            model: "gemini-2.5-pro".to_string(),
+            messages,
+            tools: Some(vec![
+                Tool {
+                    tool_type: "function".to_string(),
+                    function: json!({
+                        "name": "search",
+                        "description": "Searches the catalog",
+                        "strict": true,
+                        "parameters": {
+                            "type": "object",
+                            "additionalProperties": false,
+                            "properties": {
+                                "q": {"type": "string"},
+                                "limit": {"type": ["integer", "null"]}
+                            },
+                            "required": ["q"]
+                        },
+                    }),
+                },
+                Tool {
+                    tool_type: "function".to_string(),
+                    function: json!({
+                        "name": "done",
+                        "description": "Finishes the analysis",
+                        "parameters": {"type": "object", "properties": {}},
+                    }),
+                },
+            ]),
+            tool_choice: Some(ToolChoice::Required),
+            reasoning_effort: Some("medium".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_request_body() {
+        let body = request_body(&request(vec![
+            AgentMessage::developer("Be brief."),
+            AgentMessage::user("How many orders?"),
+            AgentMessage::assistant(
+                None,
+                Some("Searching.".to_string()),
+                Some(vec![ToolCall {
+                    id: "call_1".to_string(),
+                    call_type: "function".to_string(),
+                    function: FunctionCall {
+                        name: "search".to_string(),
+                        arguments: r#"{"q":"orders"}"#.to_string(),
+                    },
+                    code_interpreter: None,
+                    retrieval: None,
+                }]),
+                MessageProgress::Complete,
+                None,
+                None,
+            ),
+            AgentMessage::tool(None, "[1, 2]", "call_1", None, MessageProgress::Complete),
+        ]));
+
+        assert_eq!(body["systemInstruction"]["parts"][0]["text"], "Be brief.");
+
+        let contents = body["contents"].as_array().unwrap();
+        assert_eq!(contents.len(), 3);
+        assert_eq!(contents[1]["role"], "model");
+        assert_eq!(contents[1]["parts"][1]["functionCall"]["args"], json!({"q": "orders"}));
+        assert_eq!(
+            contents[2]["parts"][0]["functionResponse"],
+            json!({"name": "search", "response": {"content": [1, 2]}})
+        );
+
+        let declarations = &body["tools"][0]["functionDeclarations"];
+        assert_eq!(
+            declarations[0]["parameters"],
+            json!({
+                "type": "object",
+                "properties": {
+                    "q": {"type": "string"},
+                    "limit": {"type": "integer", "nullable": true}
+                },
+                "required": ["q"]
+            })
+        );
+        assert!(declarations[1].get("parameters").is_none());
+
+        assert_eq!(body["toolConfig"]["functionCallingConfig"]["mode"], "ANY");
+        assert_eq!(
+            body["generationConfig"]["thinkingConfig"],
+            json!({"thinkingBudget": 4096, "includeThoughts": true})
+        );
+    }
+
+    #[test]
+    fn test_request_body_for_older_models() {
+        let body = request_body(&ChatCompletionRequest {
+            model: "gemini-2.0-flash-001".to_string(),
+            response_format: Some(ResponseFormat {
+                type_: "json_object".to_string(),
+                json_schema: None,
+            }),
+            ..request(vec![AgentMessage::user("List the todos")])
+        });
+
+        assert_eq!(body["generationConfig"]["responseMimeType"], "application/json");
+        assert!(body["generationConfig"].get("thinkingConfig").is_none());
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let body = json!({
+            "candidates": [{
+                "content": {"role": "model", "parts": [
+                    {"text": "Look it up.", "thought": true},
+                    {"text": "Searching."},
+                    {"functionCall": {"name": "search", "args": {"q": "orders"}}}
+                ]},
+                "finishReason": "STOP"
+            }],
+            "usageMetadata": {
+                "promptTokenCount": 20,
+                "candidatesTokenCount": 8,
+                "thoughtsTokenCount": 12,
+                "totalTokenCount": 40
+            },
+            "responseId": "resp_1"
+        });
+
+        let response = Gemini::new("key".to_string(), None)
+            .parse_response(&request(vec![]), &body.to_string())
+            .unwrap();
+
+        let choice = &response.choices[0];
+        assert_eq!(choice.finish_reason.as_deref(), Some("tool_calls"));
+        assert_eq!(choice.message.get_content().as_deref(), Some("Searching."));
+        let tool_calls = choice.message.get_tool_calls().unwrap();
+        assert_eq!(tool_calls[0].id, "call_resp_1_0");
+        assert_eq!(tool_calls[0].function.arguments, r#"{"q":"orders"}"#);
+        assert_eq!(response.usage.completion_tokens, 20);
+        assert_eq!(
+            response.usage.completion_tokens_details.unwrap().reasoning_tokens,
+            12
+        );
+    }
+
+    #[test]
+    fn test_parse_error_response() {
+        let body = r#"{"error": {"code": 400, "message": "API key not valid.", "status": "INVALID_ARGUMENT"}}"#;
+        let err = Gemini::new("key".to_string(), None)
+            .parse_response(&request(vec![]), body)
+            .unwrap_err();
+
+        assert_eq!(err.to_string(), "Gemini request failed: API key not valid.");
+    }
+
+    #[test]
+    fn test_stream_decoder() {
+        let mut decoder = Gemini::new("key".to_string(), None).stream_decoder(&request(vec![]));
+        let events = [
+            json!({
+                "candidates": [{"content": {"role": "model", "parts": [{"text": "Search first.", "thought": true}]}}],
+                "usageMetadata": {"promptTokenCount": 20},
+                "responseId": "resp_1"
+            }),
+            json!({
+                "candidates": [{"content": {"role": "model", "parts": [
+                    {"functionCall": {"name": "search", "args": {"q": "orders"}}},
+                    {"functionCall": {"name": "search", "args": {"q": "revenue"}}}
+                ]}, "finishReason": "STOP"}],
+                "usageMetadata": {"promptTokenCount": 20, "candidatesTokenCount": 10, "totalTokenCount": 30},
+                "responseId": "resp_1"
+            }),
+        ];
+
+        let mut chunks = Vec::new();
+        for data in events {
+            let event = SseEvent {
+                event: None,
+                data: data.to_string(),
+            };
+            match decoder.decode(&event).unwrap() {
+                Decoded::Chunks(decoded) => chunks.extend(decoded),
+                Decoded::Done => panic!("Gemini streams end when the connection closes"),
+            }
+        }
+        chunks.extend(decoder.finish());
+
+        assert_eq!(chunks.len(), 6);
+        assert_eq!(chunks[0].choices[0].delta.role.as_deref(), Some("assistant"));
+        assert_eq!(
+            chunks[1].choices[0].delta.reasoning_content.as_deref(),
+            Some("Search first.")
+        );
+
+        let ids: Vec<String> = chunks[2..4]
+            .iter()
+            .map(|chunk| chunk.choices[0].delta.tool_calls.as_ref().unwrap()[0].id.clone().unwrap())
+            .collect();
+        assert_eq!(ids, vec!["call_resp_1_0", "call_resp_1_1"]);
+
+        assert_eq!(chunks[4].choices[0].finish_reason.as_deref(), Some("tool_calls"));
+        assert_eq!(chunks[5].usage.as_ref().unwrap().total_tokens, 30);
+    }
+}
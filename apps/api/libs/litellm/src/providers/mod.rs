@@ -0,0 +1,213 @@
+//! Adapters for the LLM APIs the client can talk to.
+//!
+//! Requests and responses always use the OpenAI chat completion types in `types`. Each
+//! provider translates them to and from its own API, so the agent doesn't care whether a
+//! model is served by an OpenAI-compatible proxy or called directly.
+//!
+//! A model is only sent to a native provider when its name is prefixed with the provider, as
+//! in `anthropic/claude-sonnet-4-0` or `gemini/gemini-2.5-pro`, and the provider's API key
+//! (`ANTHROPIC_API_KEY`, `GEMINI_API_KEY`) is set. Every other model, including unprefixed
+//! `claude-*` and `gemini-*` models, goes to the OpenAI-compatible endpoint at `LLM_BASE_URL`.
+
+use std::{
+    env,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+use reqwest::{Client, RequestBuilder};
+use serde_json::Value;
+
+use crate::types::{
+    ChatCompletionChunk, ChatCompletionRequest, ChatCompletionResponse, Delta, StreamChoice, Usage,
+};
+
+mod anthropic;
+mod gemini;
+mod openai;
+mod sse;
+
+pub use anthropic::Anthropic;
+pub use gemini::Gemini;
+pub use openai::OpenAiCompatible;
+pub use sse::{SseEvent, SseParser};
+
+/// Translates chat completions to and from one provider's API
+pub trait Provider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Builds the HTTP request for a chat completion. `request.model` is the provider's own
+    /// name for the model, without any routing prefix.
+    fn chat_request(
+        &self,
+        client: &Client,
+        request: &ChatCompletionRequest,
+        stream: bool,
+    ) -> Result<RequestBuilder>;
+
+    /// Parses the body of a non-streamed response
+    fn parse_response(&self, request: &ChatCompletionRequest, body: &str)
+        -> Result<ChatCompletionResponse>;
+
+    /// A decoder for the events of a streamed response
+    fn stream_decoder(&self, request: &ChatCompletionRequest) -> Box<dyn StreamDecoder>;
+}
+
+/// What a stream decoder made of one event
+#[derive(Debug)]
+pub enum Decoded {
+    Chunks(Vec<ChatCompletionChunk>),
+    /// The provider signalled the end of the stream
+    Done,
+}
+
+/// Turns a provider's stream events into chat completion chunks
+pub trait StreamDecoder: Send {
+    fn decode(&mut self, event: &SseEvent) -> Result<Decoded>;
+
+    /// Chunks to send once the stream has ended, such as the usage of the call
+    fn finish(&mut self) -> Vec<ChatCompletionChunk> {
+        Vec::new()
+    }
+}
+
+/// The providers a client can route models to
+#[derive(Clone)]
+pub(crate) struct Providers {
+    pub(crate) openai: OpenAiCompatible,
+    pub(crate) anthropic: Option<Anthropic>,
+    pub(crate) gemini: Option<Gemini>,
+}
+
+impl Providers {
+    pub(crate) fn from_env(openai: OpenAiCompatible) -> Self {
+        Self {
+            openai,
+            anthropic: env::var("ANTHROPIC_API_KEY")
+                .ok()
+                .map(|key| Anthropic::new(key, env::var("ANTHROPIC_BASE_URL").ok())),
+            gemini: env::var("GEMINI_API_KEY")
+                .ok()
+                .map(|key| Gemini::new(key, env::var("GEMINI_BASE_URL").ok())),
+        }
+    }
+
+    /// The provider for a model and the provider's name for it
+    pub(crate) fn route<'a>(&self, model: &'a str) -> Result<(&dyn Provider, &'a str)> {
+        if let Some(name) = model.strip_prefix("anthropic/") {
+            let provider = self
+                .anthropic
+                .as_ref()
+                .ok_or_else(|| anyhow!("ANTHROPIC_API_KEY must be set to use {}", model))?;
+            return Ok((provider, name));
+        }
+        if let Some(name) = model.strip_prefix("gemini/") {
+            let provider = self
+                .gemini
+                .as_ref()
+                .ok_or_else(|| anyhow!("GEMINI_API_KEY must be set to use {}", model))?;
+            return Ok((provider, name));
+        }
+
+        Ok((&self.openai, model))
+    }
+}
+
+pub(crate) fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
+
+pub(crate) fn chunk(
+    id: &str,
+    model: &str,
+    created: i64,
+    delta: Delta,
+    finish_reason: Option<String>,
+) -> ChatCompletionChunk {
+    ChatCompletionChunk {
+        id: id.to_string(),
+        object: "chat.completion.chunk".to_string(),
+        created,
+        model: model.to_string(),
+        system_fingerprint: None,
+        choices: vec![StreamChoice {
+            index: 0,
+            delta,
+            logprobs: None,
+            finish_reason,
+        }],
+        usage: None,
+    }
+}
+
+/// The last chunk of a stream, which carries the usage of the call and no choices
+pub(crate) fn usage_chunk(id: &str, model: &str, created: i64, usage: Usage) -> ChatCompletionChunk {
+    ChatCompletionChunk {
+        choices: Vec::new(),
+        usage: Some(usage),
+        ..chunk(id, model, created, Delta::default(), None)
+    }
+}
+
+/// Tool call arguments as a JSON object. Models occasionally send an empty string for tools
+/// without parameters.
+pub(crate) fn parse_arguments(arguments: &str) -> Value {
+    match serde_json::from_str::<Value>(arguments) {
+        Ok(value @ Value::Object(_)) => value,
+        _ => Value::Object(Default::default()),
+    }
+}
+
+/// Thinking budget in tokens for an OpenAI `reasoning_effort`
+pub(crate) fn reasoning_budget(effort: &str) -> Option<u32> {
+    match effort {
+        "low" => Some(1024),
+        "medium" => Some(4096),
+        "high" => Some(16384),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn providers() -> Providers {
+        Providers {
+            openai: OpenAiCompatible::new("proxy-key".to_string(), "http://proxy".to_string()),
+            anthropic: Some(Anthropic::new("anthropic-key".to_string(), None)),
+            gemini: None,
+        }
+    }
+
+    #[test]
+    fn test_route() {
+        let providers = providers();
+
+        let (provider, model) = providers.route("anthropic/claude-3-7-sonnet-latest").unwrap();
+        assert_eq!((provider.name(), model), ("anthropic", "claude-3-7-sonnet-latest"));
+
+        // Native routing is opt-in, so unprefixed models stay on the proxy even with a key set
+        let (provider, model) = providers.route("claude-sonnet-4-0").unwrap();
+        assert_eq!((provider.name(), model), ("openai", "claude-sonnet-4-0"));
+
+        let (provider, model) = providers.route("gemini-2.0-flash-001").unwrap();
+        assert_eq!((provider.name(), model), ("openai", "gemini-2.0-flash-001"));
+
+        let (provider, model) = providers.route("bedrock/anthropic.claude-3-7-sonnet").unwrap();
+        assert_eq!((provider.name(), model), ("openai", "bedrock/anthropic.claude-3-7-sonnet"));
+
+        let err = providers.route("gemini/gemini-2.5-pro").err().unwrap();
+        assert!(err.to_string().contains("GEMINI_API_KEY"));
+    }
+
+    #[test]
+    fn test_parse_arguments() {
+        assert_eq!(parse_arguments(r#"{"a": 1}"#), serde_json::json!({"a": 1}));
+        assert_eq!(parse_arguments(""), serde_json::json!({}));
+        assert_eq!(parse_arguments("[1]"), serde_json::json!({}));
+    }
+}
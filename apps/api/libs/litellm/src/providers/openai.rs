@@ -0,0 +1,75 @@
+use anyhow::Result;
+use reqwest::{Client, RequestBuilder};
+
+use super::{Decoded, Provider, SseEvent, StreamDecoder};
+use crate::types::{
+    ChatCompletionChunk, ChatCompletionRequest, ChatCompletionResponse, StreamOptions,
+};
+
+/// An OpenAI-compatible `/chat/completions` endpoint, such as a LiteLLM proxy
+#[derive(Debug, Clone)]
+pub struct OpenAiCompatible {
+    api_key: String,
+    base_url: String,
+}
+
+impl OpenAiCompatible {
+    pub fn new(api_key: String, base_url: String) -> Self {
+        Self { api_key, base_url }
+    }
+}
+
+impl Provider for OpenAiCompatible {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    fn chat_request(
+        &self,
+        client: &Client,
+        request: &ChatCompletionRequest,
+        stream: bool,
+    ) -> Result<RequestBuilder> {
+        let url = format!("{}/chat/completions", self.base_url);
+        let builder = client.post(url).bearer_auth(&self.api_key);
+
+        if !stream {
+            return Ok(builder.json(request));
+        }
+
+        Ok(builder.json(&ChatCompletionRequest {
+            stream: Some(true),
+            // Ask for the usage of the call in the last chunk unless the caller chose
+            stream_options: request
+                .stream_options
+                .clone()
+                .or(Some(StreamOptions { include_usage: true })),
+            ..request.clone()
+        }))
+    }
+
+    fn parse_response(
+        &self,
+        _request: &ChatCompletionRequest,
+        body: &str,
+    ) -> Result<ChatCompletionResponse> {
+        Ok(serde_json::from_str(body)?)
+    }
+
+    fn stream_decoder(&self, _request: &ChatCompletionRequest) -> Box<dyn StreamDecoder> {
+        Box::new(OpenAiDecoder)
+    }
+}
+
+struct OpenAiDecoder;
+
+impl StreamDecoder for OpenAiDecoder {
+    fn decode(&mut self, event: &SseEvent) -> Result<Decoded> {
+        if event.data == "[DONE]" {
+            return Ok(Decoded::Done);
+        }
+
+        let chunk: ChatCompletionChunk = serde_json::from_str(&event.data)?;
+        Ok(Decoded::Chunks(vec![chunk]))
+    }
+}
@@ -0,0 +1,116 @@
+/// One server-sent event
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SseEvent {
+    /// The `event:` field, which Anthropic uses to name the event type
+    pub event: Option<String>,
+    /// The `data:` lines, joined with newlines
+    pub data: String,
+}
+
+/// Splits a server-sent event stream into events as its bytes arrive. Works on bytes so a
+/// multi-byte character split across network chunks isn't mangled.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buffer: Vec<u8>,
+}
+
+impl SseParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the next bytes of the stream and returns the events they complete
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<SseEvent> {
+        // Gemini separates events with CRLF, which JSON payloads never contain unescaped
+        self.buffer.extend(bytes.iter().filter(|&&b| b != b'\r'));
+
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.windows(2).position(|w| w == b"\n\n") {
+            let block: Vec<u8> = self.buffer.drain(..pos + 2).collect();
+            if let Some(event) = parse_event(&String::from_utf8_lossy(&block[..pos])) {
+                events.push(event);
+            }
+        }
+        events
+    }
+
+    /// The event left in the buffer when the stream ended without a trailing blank line
+    pub fn finish(&mut self) -> Option<SseEvent> {
+        let block = std::mem::take(&mut self.buffer);
+        parse_event(&String::from_utf8_lossy(&block))
+    }
+}
+
+fn parse_event(block: &str) -> Option<SseEvent> {
+    let mut event = SseEvent::default();
+    let mut data_lines = Vec::new();
+
+    for line in block.lines() {
+        // Lines starting with a colon are comments, used as keep-alives
+        if line.is_empty() || line.starts_with(':') {
+            continue;
+        }
+        let (field, value) = line.split_once(':').unwrap_or((line, ""));
+        let value = value.strip_prefix(' ').unwrap_or(value);
+        match field {
+            "event" => event.event = Some(value.to_string()),
+            "data" => data_lines.push(value),
+            _ => tracing::debug!("Ignoring unexpected line in stream: {}", line),
+        }
+    }
+
+    if data_lines.is_empty() {
+        return None;
+    }
+    event.data = data_lines.join("\n");
+    Some(event)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_split_across_chunks() {
+        let mut parser = SseParser::new();
+
+        assert!(parser.push(b"event: message_start\ndata: {\"a\":").is_empty());
+        let events = parser.push(b"1}\n\n: ping\n\ndata: [DONE]\n\n");
+
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: Some("message_start".to_string()),
+                    data: "{\"a\":1}".to_string(),
+                },
+                SseEvent {
+                    event: None,
+                    data: "[DONE]".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_crlf_and_split_characters() {
+        let mut parser = SseParser::new();
+        let text = "data: {\"text\":\"caf\u{e9}\"}\r\n\r\n".as_bytes();
+
+        // Split in the middle of the two-byte "é"
+        let split = text.iter().position(|&b| b == 0xc3).unwrap() + 1;
+        assert!(parser.push(&text[..split]).is_empty());
+        let events = parser.push(&text[split..]);
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "{\"text\":\"caf\u{e9}\"}");
+    }
+
+    #[test]
+    fn test_finish_returns_the_unterminated_event() {
+        let mut parser = SseParser::new();
+        assert!(parser.push(b"data: {}").is_empty());
+        assert_eq!(parser.finish().map(|e| e.data), Some("{}".to_string()));
+        assert_eq!(parser.finish(), None);
+    }
+}
@@ -0,0 +1,405 @@
+//! Record and replay of LLM traffic, so agent runs can be tested without a live LLM.
+//!
+//! With `LLM_TRAFFIC_MODE=record` the client sends requests as usual and also writes each
+//! response to a fixture file in `LLM_FIXTURES_DIR`. With `LLM_TRAFFIC_MODE=replay` it answers
+//! from those files and never makes a request.
+//!
+//! Fixtures are looked up by a hash of the request. Before hashing, the parts of a request
+//! that change from run to run without changing what the agent does are normalized away:
+//! system prompts (they embed today's date), request metadata, tool schemas (only tool names
+//! are kept), timestamps, and UUIDs. UUIDs are numbered in order of appearance, and the ones a
+//! recorded response repeats from its request are swapped for the replaying run's UUIDs, so
+//! ids the agent created in one run can be referenced in the next.
+
+use anyhow::{anyhow, Context, Result};
+use once_cell::sync::Lazy;
+use regex::{Captures, Regex};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+static UUID_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"[0-9a-fA-F]{8}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{4}-[0-9a-fA-F]{12}")
+        .unwrap()
+});
+static TIMESTAMP_PATTERN: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\d{4}-\d{2}-\d{2}[T ]\d{2}:\d{2}:\d{2}(\.\d+)?(Z|[+-]\d{2}:?\d{2})?").unwrap()
+});
+static ID_PLACEHOLDER_PATTERN: Lazy<Regex> = Lazy::new(|| Regex::new(r"<id:(\d+)>").unwrap());
+
+// Fixtures replays looked for and didn't find. Some callers treat LLM errors as empty results,
+// so a missing fixture can't be left to surface as a failed request.
+static MISSING_FIXTURES: Lazy<Mutex<Vec<PathBuf>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Takes the fixtures replays have failed to find since the last call
+pub fn take_missing_fixtures() -> Vec<PathBuf> {
+    std::mem::take(&mut *MISSING_FIXTURES.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrafficMode {
+    /// Send requests to the LLM and write the responses to fixtures
+    Record,
+    /// Answer requests from fixtures
+    Replay,
+}
+
+/// Where a client records LLM traffic to or replays it from
+#[derive(Debug, Clone)]
+pub struct TrafficFixtures {
+    mode: TrafficMode,
+    dir: PathBuf,
+}
+
+impl TrafficFixtures {
+    pub fn record(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            mode: TrafficMode::Record,
+            dir: dir.into(),
+        }
+    }
+
+    pub fn replay(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            mode: TrafficMode::Replay,
+            dir: dir.into(),
+        }
+    }
+
+    /// Reads `LLM_TRAFFIC_MODE` and `LLM_FIXTURES_DIR`. Returns None when traffic should go to
+    /// the LLM as usual, and an error when the variables are set to something unusable.
+    pub fn from_env() -> Result<Option<Self>> {
+        let mode = match env::var("LLM_TRAFFIC_MODE") {
+            Ok(mode) => mode.to_lowercase(),
+            Err(_) => return Ok(None),
+        };
+        if mode.is_empty() || mode == "live" {
+            return Ok(None);
+        }
+
+        let dir = env::var("LLM_FIXTURES_DIR").map_err(|_| {
+            anyhow!(
+                "LLM_FIXTURES_DIR must be set when LLM_TRAFFIC_MODE is {}",
+                mode
+            )
+        })?;
+
+        match mode.as_str() {
+            "record" => Ok(Some(Self::record(dir))),
+            "replay" => Ok(Some(Self::replay(dir))),
+            _ => Err(anyhow!(
+                "LLM_TRAFFIC_MODE must be record, replay or live, got {}",
+                mode
+            )),
+        }
+    }
+
+    pub fn mode(&self) -> TrafficMode {
+        self.mode
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub(crate) fn fixture_for<T: Serialize>(
+        &self,
+        kind: FixtureKind,
+        request: &T,
+    ) -> Result<FixtureRequest> {
+        let (request, ids) = normalize_request(serde_json::to_value(request)?);
+
+        let mut hasher = Sha256::new();
+        hasher.update(kind.as_str().as_bytes());
+        hasher.update(serde_json::to_string(&request)?.as_bytes());
+        let hash: String = hasher
+            .finalize()
+            .iter()
+            .take(8)
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        Ok(FixtureRequest {
+            path: self.dir.join(format!("{}-{}.json", kind.as_str(), hash)),
+            kind,
+            request,
+            ids,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum FixtureKind {
+    ChatCompletion,
+    ChatCompletionStream,
+    Embeddings,
+}
+
+impl FixtureKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            FixtureKind::ChatCompletion => "chat_completion",
+            FixtureKind::ChatCompletionStream => "chat_completion_stream",
+            FixtureKind::Embeddings => "embeddings",
+        }
+    }
+}
+
+/// A recorded exchange. The normalized request is kept so fixtures can be reviewed.
+#[derive(Debug, Serialize, Deserialize)]
+struct Fixture {
+    kind: FixtureKind,
+    request: Value,
+    response: Value,
+}
+
+/// The fixture a request is recorded to or replayed from
+#[derive(Debug)]
+pub(crate) struct FixtureRequest {
+    path: PathBuf,
+    kind: FixtureKind,
+    request: Value,
+    /// The request's UUIDs, in the order they were numbered
+    ids: Vec<String>,
+}
+
+impl FixtureRequest {
+    pub(crate) fn load<R: DeserializeOwned>(&self) -> Result<R> {
+        let contents = fs::read_to_string(&self.path).with_context(|| {
+            MISSING_FIXTURES
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push(self.path.clone());
+            format!(
+                "No recorded LLM response for this request: {} is missing. Record it with LLM_TRAFFIC_MODE=record. Request: {}",
+                self.path.display(),
+                self.request
+            )
+        })?;
+        let fixture: Fixture = serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid LLM fixture {}", self.path.display()))?;
+
+        let response = map_strings(fixture.response, &mut |value| {
+            ID_PLACEHOLDER_PATTERN
+                .replace_all(value, |caps: &Captures| {
+                    caps[1]
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|index| self.ids.get(index))
+                        .cloned()
+                        .unwrap_or_else(|| caps[0].to_string())
+                })
+                .into_owned()
+        });
+
+        serde_json::from_value(response)
+            .map_err(|e| anyhow!("Invalid LLM fixture {}: {}", self.path.display(), e))
+    }
+
+    pub(crate) fn save<R: Serialize>(&self, response: &R) -> Result<()> {
+        let response = map_strings(serde_json::to_value(response)?, &mut |value| {
+            let mut value = value.to_string();
+            for (index, id) in self.ids.iter().enumerate() {
+                value = value.replace(id.as_str(), &format!("<id:{}>", index));
+            }
+            value
+        });
+
+        let fixture = Fixture {
+            kind: self.kind,
+            request: self.request.clone(),
+            response,
+        };
+
+        fs::create_dir_all(self.path.parent().unwrap_or(Path::new(".")))?;
+        fs::write(&self.path, serde_json::to_string_pretty(&fixture)?)
+            .with_context(|| format!("Failed to write LLM fixture {}", self.path.display()))?;
+
+        Ok(())
+    }
+}
+
+/// Strips the parts of a request that vary between runs and numbers its UUIDs. Object keys
+/// are sorted so the hash doesn't depend on field order.
+fn normalize_request(mut request: Value) -> (Value, Vec<String>) {
+    if let Value::Object(fields) = &mut request {
+        for key in ["metadata", "stream", "user"] {
+            fields.remove(key);
+        }
+
+        if let Some(Value::Array(messages)) = fields.get_mut("messages") {
+            messages.retain(|message| {
+                !matches!(
+                    message.get("role").and_then(Value::as_str),
+                    Some("developer") | Some("system")
+                )
+            });
+        }
+
+        if let Some(Value::Array(tools)) = fields.get_mut("tools") {
+            for tool in tools.iter_mut() {
+                *tool = tool
+                    .pointer("/function/name")
+                    .cloned()
+                    .unwrap_or(Value::Null);
+            }
+        }
+    }
+
+    let mut ids = Vec::new();
+    let request = canonicalize(request, &mut ids);
+    (request, ids)
+}
+
+fn canonicalize(value: Value, ids: &mut Vec<String>) -> Value {
+    match value {
+        Value::Object(fields) => {
+            let mut fields: Vec<(String, Value)> = fields.into_iter().collect();
+            fields.sort_by(|a, b| a.0.cmp(&b.0));
+            Value::Object(
+                fields
+                    .into_iter()
+                    .map(|(key, value)| (key, canonicalize(value, ids)))
+                    .collect(),
+            )
+        }
+        Value::Array(values) => Value::Array(
+            values
+                .into_iter()
+                .map(|value| canonicalize(value, ids))
+                .collect(),
+        ),
+        Value::String(value) => {
+            let value = UUID_PATTERN.replace_all(&value, |caps: &Captures| {
+                let id = caps[0].to_lowercase();
+                let index = match ids.iter().position(|known| *known == id) {
+                    Some(index) => index,
+                    None => {
+                        ids.push(id);
+                        ids.len() - 1
+                    }
+                };
+                format!("<id:{}>", index)
+            });
+            Value::String(
+                TIMESTAMP_PATTERN
+                    .replace_all(&value, "<timestamp>")
+                    .into_owned(),
+            )
+        }
+        value => value,
+    }
+}
+
+fn map_strings(value: Value, f: &mut impl FnMut(&str) -> String) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key, map_strings(value, f)))
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(
+            values
+                .into_iter()
+                .map(|value| map_strings(value, f))
+                .collect(),
+        ),
+        Value::String(value) => Value::String(f(&value)),
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn fixtures_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("litellm-fixtures-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn request(today: &str, metric_id: &str) -> Value {
+        json!({
+            "model": "o4-mini",
+            "messages": [
+                {"role": "developer", "content": format!("Today is {}", today)},
+                {"role": "user", "content": "Chart revenue by month"},
+                {"role": "tool", "tool_call_id": "call_1", "content": format!("Created metric {} at {}T10:00:00Z", metric_id, today)}
+            ],
+            "tools": [{"type": "function", "function": {"name": "create_metrics", "description": "Creates metrics"}}],
+            "metadata": {"trace_id": "9a3c1a52-8d0e-4a3e-9f3f-3d2b3c1e0f11"}
+        })
+    }
+
+    #[test]
+    fn test_runs_on_different_days_share_fixtures() {
+        let fixtures = TrafficFixtures::replay(fixtures_dir("hash"));
+        let first = fixtures
+            .fixture_for(
+                FixtureKind::ChatCompletionStream,
+                &request("2025-05-01", "6c3f0b9e-4a52-4a8e-9d0b-0d7f1c2e3a41"),
+            )
+            .unwrap();
+        let second = fixtures
+            .fixture_for(
+                FixtureKind::ChatCompletionStream,
+                &request("2025-05-02", "1b2c3d4e-5f60-4718-8a9b-0c1d2e3f4a5b"),
+            )
+            .unwrap();
+
+        assert_eq!(first.path, second.path);
+        assert_eq!(first.request["tools"], json!(["create_metrics"]));
+        assert!(first.request.get("metadata").is_none());
+    }
+
+    #[test]
+    fn test_replay_substitutes_the_replaying_runs_ids() {
+        let dir = fixtures_dir("ids");
+        let recorded_id = "6c3f0b9e-4a52-4a8e-9d0b-0d7f1c2e3a41";
+        let replayed_id = "1b2c3d4e-5f60-4718-8a9b-0c1d2e3f4a5b";
+
+        TrafficFixtures::record(&dir)
+            .fixture_for(
+                FixtureKind::ChatCompletion,
+                &request("2025-05-01", recorded_id),
+            )
+            .unwrap()
+            .save(&json!({"content": format!("Updated metric {}", recorded_id)}))
+            .unwrap();
+
+        let response: Value = TrafficFixtures::replay(&dir)
+            .fixture_for(
+                FixtureKind::ChatCompletion,
+                &request("2025-05-02", replayed_id),
+            )
+            .unwrap()
+            .load()
+            .unwrap();
+        assert_eq!(
+            response,
+            json!({"content": format!("Updated metric {}", replayed_id)})
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_missing_fixture_names_the_file() {
+        let fixture = TrafficFixtures::replay(fixtures_dir("missing"))
+            .fixture_for(FixtureKind::Embeddings, &json!({"input": ["revenue"]}))
+            .unwrap();
+        let error = fixture.load::<Value>().unwrap_err();
+
+        assert!(error.to_string().contains("embeddings-"));
+        assert!(take_missing_fixtures().contains(&fixture.path));
+    }
+}
@@ -40,6 +40,8 @@ pub struct ChatCompletionRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub stream: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub temperature: Option<f32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub top_p: Option<f32>,
@@ -55,6 +57,12 @@ pub struct ChatCompletionRequest {
     pub metadata: Option<Metadata>,
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StreamOptions {
+    /// Ask for a final chunk, with no choices, that carries the usage of the whole call
+    pub include_usage: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Metadata {
     pub generation_name: String,
@@ -81,6 +89,7 @@ impl Default for ChatCompletionRequest {
             seed: None,
             stop: None,
             stream: None,
+            stream_options: None,
             temperature: None,
             top_p: None,
             tools: None,
@@ -434,6 +443,9 @@ pub struct ChatCompletionChunk {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub system_fingerprint: Option<String>,
     pub choices: Vec<StreamChoice>,
+    /// Only set on the last chunk, when usage was asked for through `stream_options`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -445,12 +457,15 @@ pub struct StreamChoice {
     pub finish_reason: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Delta {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub role: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub content: Option<String>,
+    /// The model's thinking, from providers that stream it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_content: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub function_call: Option<DeltaFunctionCall>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -829,12 +844,14 @@ mod tests {
                 delta: Delta {
                     role: Some("assistant".to_string()),
                     content: Some("".to_string()),
+                    reasoning_content: None,
                     function_call: None,
                     tool_calls: None,
                 },
                 logprobs: None,
                 finish_reason: None,
             }],
+            usage: None,
         };
 
         // Test content chunk
@@ -849,12 +866,14 @@ mod tests {
                 delta: Delta {
                     role: None,
                     content: Some("Hello".to_string()),
+                    reasoning_content: None,
                     function_call: None,
                     tool_calls: None,
                 },
                 logprobs: None,
                 finish_reason: None,
             }],
+            usage: None,
         };
 
         // Test final chunk
@@ -869,12 +888,14 @@ mod tests {
                 delta: Delta {
                     role: None,
                     content: None,
+                    reasoning_content: None,
                     function_call: None,
                     tool_calls: None,
                 },
                 logprobs: None,
                 finish_reason: Some("stop".to_string()),
             }],
+            usage: None,
         };
 
         // Test serialization/deserialization of all chunks
@@ -927,6 +948,31 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_usage_chunk() {
+        // Sent after the final chunk when the request sets stream_options.include_usage
+        let json = r#"{
+            "id": "chatcmpl-123",
+            "object": "chat.completion.chunk",
+            "created": 1694268190,
+            "model": "gpt-4o-mini",
+            "choices": [],
+            "usage": {
+                "prompt_tokens": 9,
+                "completion_tokens": 12,
+                "total_tokens": 21
+            }
+        }"#;
+
+        let chunk: ChatCompletionChunk = serde_json::from_str(json).unwrap();
+
+        assert!(chunk.choices.is_empty());
+        let usage = chunk.usage.unwrap();
+        assert_eq!(usage.prompt_tokens, 9);
+        assert_eq!(usage.completion_tokens, 12);
+        assert_eq!(usage.total_tokens, 21);
+    }
+
     #[tokio::test]
     async fn test_chat_completion_function_calling() {
         // Test request with function tool
@@ -0,0 +1,159 @@
+use anyhow::{anyhow, Result};
+use axum::http::Method;
+use chrono::{Duration, Utc};
+use database::{pool::get_pg_pool, pool::get_redis_pool, schema::api_keys};
+use diesel::{BoolExpressionMethods, ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Prefix the REST routes are mounted under
+const API_PREFIX: &str = "/api/v1";
+
+/// What a scoped API key is allowed to do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApiKeyScope {
+    /// Read metrics and their data
+    #[serde(rename = "metrics:read")]
+    MetricsRead,
+    /// Run SQL on the key's data sources
+    #[serde(rename = "sql:run")]
+    SqlRun,
+    /// Deploy datasets
+    #[serde(rename = "datasets:deploy")]
+    DatasetsDeploy,
+    /// Provision users and groups over SCIM. Only honoured for keys of workspace admins.
+    #[serde(rename = "scim:provision")]
+    ScimProvision,
+}
+
+impl ApiKeyScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiKeyScope::MetricsRead => "metrics:read",
+            ApiKeyScope::SqlRun => "sql:run",
+            ApiKeyScope::DatasetsDeploy => "datasets:deploy",
+            ApiKeyScope::ScimProvision => "scim:provision",
+        }
+    }
+
+    /// Whether the scope covers a request. `path` is relative to the API prefix.
+    fn allows(&self, method: &Method, path: &str) -> bool {
+        let segments: Vec<&str> = path.split('/').filter(|s| !s.is_empty()).collect();
+        match self {
+            ApiKeyScope::MetricsRead => {
+                method == Method::GET && segments.first() == Some(&"metric_files")
+            }
+            ApiKeyScope::SqlRun => method == Method::POST && segments == ["sql", "run"],
+            ApiKeyScope::DatasetsDeploy => {
+                method == Method::POST && segments == ["datasets", "deploy"]
+            }
+            ApiKeyScope::ScimProvision => segments.first() == Some(&"scim"),
+        }
+    }
+}
+
+impl FromStr for ApiKeyScope {
+    type Err = anyhow::Error;
+
+    fn from_str(scope: &str) -> Result<Self> {
+        match scope {
+            "metrics:read" => Ok(ApiKeyScope::MetricsRead),
+            "sql:run" => Ok(ApiKeyScope::SqlRun),
+            "datasets:deploy" => Ok(ApiKeyScope::DatasetsDeploy),
+            "scim:provision" => Ok(ApiKeyScope::ScimProvision),
+            _ => Err(anyhow!("Unknown API key scope: {}", scope)),
+        }
+    }
+}
+
+/// The API key a request authenticated with. Added to the request extensions next to the
+/// `AuthenticatedUser`; handlers that touch data sources check `allows_data_source`.
+#[derive(Debug, Clone)]
+pub struct ApiKeyAccess {
+    pub id: Uuid,
+    /// None for keys with their owner's full access
+    pub scopes: Option<Vec<ApiKeyScope>>,
+    pub data_source_ids: Vec<Uuid>,
+    pub rate_limit_per_minute: Option<i32>,
+}
+
+impl ApiKeyAccess {
+    /// Whether the key may make the request. `path` is the full request path.
+    pub fn allows_request(&self, method: &Method, path: &str) -> bool {
+        let scopes = match &self.scopes {
+            Some(scopes) => scopes,
+            None => return true,
+        };
+
+        let path = path.strip_prefix(API_PREFIX).unwrap_or(path);
+        scopes.iter().any(|scope| scope.allows(method, path))
+    }
+
+    /// Whether the key may run SQL on the data source
+    pub fn allows_data_source(&self, data_source_id: &Uuid) -> bool {
+        self.scopes.is_none() || self.data_source_ids.contains(data_source_id)
+    }
+}
+
+/// Counts the request against the key's per-minute limit. Returns false once the limit is
+/// used up. Requests go through if Redis can't be reached.
+pub(crate) async fn check_rate_limit(access: &ApiKeyAccess) -> bool {
+    let limit = match access.rate_limit_per_minute {
+        Some(limit) => limit,
+        None => return true,
+    };
+
+    let minute = Utc::now().timestamp() / 60;
+    let key = format!("api_key_rate:{}:{}", access.id, minute);
+
+    let count: Result<i64> = async {
+        let mut redis_conn = get_redis_pool()
+            .get()
+            .await
+            .map_err(|e| anyhow!("Error getting redis connection: {}", e))?;
+        let count: i64 = redis_conn.incr(&key, 1).await?;
+        if count == 1 {
+            redis_conn.expire::<_, ()>(&key, 60).await?;
+        }
+        Ok(count)
+    }
+    .await;
+
+    match count {
+        Ok(count) => count <= limit as i64,
+        Err(e) => {
+            tracing::warn!("Failed to check API key rate limit: {}", e);
+            true
+        }
+    }
+}
+
+/// Records that the key was used. Writes at most once a minute per key so busy keys don't
+/// turn every request into a write.
+pub(crate) fn record_api_key_use(api_key_id: Uuid) {
+    tokio::spawn(async move {
+        let now = Utc::now();
+        let result: Result<()> = async {
+            let mut conn = get_pg_pool().get().await?;
+            diesel::update(api_keys::table)
+                .filter(api_keys::id.eq(api_key_id))
+                .filter(
+                    api_keys::last_used_at
+                        .is_null()
+                        .or(api_keys::last_used_at.lt(now - Duration::minutes(1))),
+                )
+                .set(api_keys::last_used_at.eq(Some(now)))
+                .execute(&mut conn)
+                .await?;
+            Ok(())
+        }
+        .await;
+
+        if let Err(e) = result {
+            tracing::warn!("Failed to record use of API key {}: {}", api_key_id, e);
+        }
+    });
+}
@@ -1,11 +1,17 @@
 use anyhow::{anyhow, Result};
-use axum::{extract::Request, http::StatusCode, middleware::Next, response::Response};
+use axum::{
+    extract::{OriginalUri, Request},
+    http::StatusCode,
+    middleware::Next,
+    response::Response,
+};
+use chrono::Utc;
 use database::{
-    models::User,
+    models::{ApiKey, User},
     pool::get_pg_pool,
     schema::{api_keys, teams_to_users, users, users_to_organizations},
 };
-use diesel::{ExpressionMethods, JoinOnDsl, QueryDsl};
+use diesel::{BoolExpressionMethods, ExpressionMethods, JoinOnDsl, QueryDsl};
 use diesel_async::RunQueryDsl;
 use futures::try_join;
 use jsonwebtoken::{decode, Algorithm, DecodingKey, Validation};
@@ -14,6 +20,7 @@ use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, env};
 use uuid::Uuid;
 
+use crate::api_keys::{check_rate_limit, record_api_key_use, ApiKeyAccess};
 use crate::types::{AuthenticatedUser, OrganizationMembership, TeamMembership};
 
 lazy_static! {
@@ -84,7 +91,7 @@ pub async fn auth(mut req: Request, next: Next) -> Result<Response, StatusCode>
         }
     };
 
-    let user = match authorize_current_user(&token).await {
+    let (user, api_key) = match authorize_current_user(&token).await {
         Ok(Some(user)) => user,
         Ok(None) => return Err(StatusCode::UNAUTHORIZED),
         Err(e) => {
@@ -93,6 +100,29 @@ pub async fn auth(mut req: Request, next: Next) -> Result<Response, StatusCode>
         }
     };
 
+    // Scoped keys only reach the routes their scopes cover
+    if let Some(api_key) = &api_key {
+        let path = req
+            .extensions()
+            .get::<OriginalUri>()
+            .map(|uri| uri.0.path().to_string())
+            .unwrap_or_else(|| req.uri().path().to_string());
+
+        if !api_key.allows_request(req.method(), &path) {
+            tracing::warn!(
+                api_key_id = %api_key.id,
+                "API key scopes don't allow {} {}", req.method(), path
+            );
+            return Err(StatusCode::FORBIDDEN);
+        }
+
+        if !check_rate_limit(api_key).await {
+            return Err(StatusCode::TOO_MANY_REQUESTS);
+        }
+
+        record_api_key_use(api_key.id);
+    }
+
     // --- Payment Required Check START ---
     if env::var("ENVIRONMENT").unwrap_or_default() == "production" {
         if let Some(org_membership) = user.organizations.get(0) {
@@ -144,10 +174,16 @@ pub async fn auth(mut req: Request, next: Next) -> Result<Response, StatusCode>
     // --- Payment Required Check END ---
 
     req.extensions_mut().insert(user);
+    if let Some(api_key) = api_key {
+        req.extensions_mut().insert(api_key);
+    }
     Ok(next.run(req).await)
 }
 
-async fn authorize_current_user(token: &str) -> Result<Option<AuthenticatedUser>> {
+/// Returns the token's user, and the key's access if the token is an API key
+async fn authorize_current_user(
+    token: &str,
+) -> Result<Option<(AuthenticatedUser, Option<ApiKeyAccess>)>> {
     let mut validation = Validation::new(Algorithm::HS256);
     validation.set_audience(&["authenticated", "api"]);
 
@@ -163,8 +199,12 @@ async fn authorize_current_user(token: &str) -> Result<Option<AuthenticatedUser>
     };
 
     let user = match token_data.aud.contains("api") {
-        true => find_user_by_api_key(token).await,
-        false => find_user_by_id(&Uuid::parse_str(&token_data.sub).unwrap()).await,
+        true => find_user_by_api_key(token)
+            .await
+            .map(|user| user.map(|(user, api_key)| (user, Some(api_key)))),
+        false => find_user_by_id(&Uuid::parse_str(&token_data.sub).unwrap())
+            .await
+            .map(|user| user.map(|user| (user, None))),
     };
 
     let user = match user {
@@ -262,7 +302,7 @@ async fn find_user_by_id(id: &Uuid) -> Result<Option<AuthenticatedUser>> {
     }))
 }
 
-async fn find_user_by_api_key(token: &str) -> Result<Option<AuthenticatedUser>> {
+async fn find_user_by_api_key(token: &str) -> Result<Option<(AuthenticatedUser, ApiKeyAccess)>> {
     let pg_pool = get_pg_pool();
     let token = token.to_string(); // Clone the token for move into tasks
 
@@ -273,14 +313,19 @@ async fn find_user_by_api_key(token: &str) -> Result<Option<AuthenticatedUser>>
             .inner_join(api_keys::table.on(users::id.eq(api_keys::owner_id)))
             .filter(api_keys::key.eq(token))
             .filter(api_keys::deleted_at.is_null())
-            .select(users::all_columns)
-            .first::<User>(&mut conn)
+            .filter(
+                api_keys::expires_at
+                    .is_null()
+                    .or(api_keys::expires_at.gt(Utc::now())),
+            )
+            .select((users::all_columns, api_keys::all_columns))
+            .first::<(User, ApiKey)>(&mut conn)
             .await
             .map_err(|e| anyhow!("Error querying user: {}", e))
     });
 
     // Get user first since we need the ID for the other queries
-    let user = user_task
+    let (user, api_key) = user_task
         .await
         .map_err(|e| anyhow!("User task failed: {}", e))??;
 
@@ -337,16 +382,31 @@ async fn find_user_by_api_key(token: &str) -> Result<Option<AuthenticatedUser>>
         .map(|(id, role)| TeamMembership { id, role })
         .collect();
 
-    Ok(Some(AuthenticatedUser {
-        id: user.id,
-        email: user.email,
-        name: user.name,
-        config: user.config,
-        created_at: user.created_at,
-        updated_at: user.updated_at,
-        attributes: user.attributes,
-        avatar_url: user.avatar_url,
-        organizations,
-        teams,
-    }))
+    let access = ApiKeyAccess {
+        id: api_key.id,
+        scopes: api_key.scopes.map(|scopes| {
+            scopes
+                .iter()
+                .filter_map(|scope| scope.parse().ok())
+                .collect()
+        }),
+        data_source_ids: api_key.data_source_ids,
+        rate_limit_per_minute: api_key.rate_limit_per_minute,
+    };
+
+    Ok(Some((
+        AuthenticatedUser {
+            id: user.id,
+            email: user.email,
+            name: user.name,
+            config: user.config,
+            created_at: user.created_at,
+            updated_at: user.updated_at,
+            attributes: user.attributes,
+            avatar_url: user.avatar_url,
+            organizations,
+            teams,
+        },
+        access,
+    )))
 }
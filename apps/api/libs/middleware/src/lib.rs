@@ -3,12 +3,14 @@
 //! This library provides common middleware components for the Buster web server,
 //! including authentication and CORS handling.
 
+pub mod api_keys;
 pub mod auth;
 pub mod cors;
 pub mod types;
 pub mod error;
 
 // Re-export commonly used types
+pub use api_keys::{ApiKeyAccess, ApiKeyScope};
 pub use auth::auth;
 pub use cors::cors;
 pub use error::{
@@ -1,4 +1,9 @@
+use std::path::{Component, Path, PathBuf};
+
 use anyhow::{anyhow, Result};
+use pkcs8::{
+    der::pem::LineEnding, der::zeroize::Zeroizing, EncryptedPrivateKeyInfo, SecretDocument,
+};
 use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 use uuid::Uuid;
@@ -16,6 +21,10 @@ pub enum Credential {
     Redshift(RedshiftCredentials),
     Databricks(DatabricksCredentials),
     Snowflake(SnowflakeCredentials),
+    DuckDb(DuckDbCredentials),
+    Sqlite(SqliteCredentials),
+    ClickHouse(ClickHouseCredentials),
+    Trino(TrinoCredentials),
 }
 
 /// Custom deserializer that handles both string and JSON object formats for credentials
@@ -78,6 +87,7 @@ pub struct MariadbCredentials {
     pub jump_host: Option<String>,
     pub ssh_username: Option<String>,
     pub ssh_private_key: Option<String>,
+    pub ssh_host_key_fingerprint: Option<String>,
     pub default_database: String,
 }
 
@@ -92,6 +102,7 @@ pub struct MySqlCredentials {
     pub jump_host: Option<String>,
     pub ssh_username: Option<String>,
     pub ssh_private_key: Option<String>,
+    pub ssh_host_key_fingerprint: Option<String>,
     pub default_database: String,
 }
 
@@ -106,6 +117,7 @@ pub struct PostgresCredentials {
     pub jump_host: Option<String>,
     pub ssh_username: Option<String>,
     pub ssh_private_key: Option<String>,
+    pub ssh_host_key_fingerprint: Option<String>,
     #[serde(alias = "database")]
     pub default_database: String,
     pub default_schema: Option<String>,
@@ -130,13 +142,127 @@ pub struct SnowflakeCredentials {
     pub account_id: String,
     pub warehouse_id: String,
     pub username: String,
-    pub password: String,
+    /// Credentials saved before other auth methods were supported are password credentials
+    #[serde(default)]
+    pub auth_method: SnowflakeAuthMethod,
+    pub password: Option<String>,
+    /// PKCS#8 PEM private key whose public key is set on the Snowflake user (`RSA_PUBLIC_KEY`)
+    pub private_key: Option<String>,
+    /// Only needed when the private key is an `ENCRYPTED PRIVATE KEY`
+    pub private_key_passphrase: Option<String>,
+    /// Client of the Snowflake security integration the refresh token was issued to
+    pub oauth_client_id: Option<String>,
+    pub oauth_client_secret: Option<String>,
+    /// Exchanged for a short-lived access token each time a connection is opened
+    pub oauth_refresh_token: Option<String>,
     pub role: Option<String>,
     #[serde(alias = "database")]
     pub default_database: String,
     pub default_schema: Option<String>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SnowflakeAuthMethod {
+    #[default]
+    Password,
+    KeyPair,
+    #[serde(rename = "oauth")]
+    OAuth,
+}
+
+impl SnowflakeCredentials {
+    /// Checks the fields the auth method needs are set. Key pairs are also decrypted, so a
+    /// wrong passphrase is caught before the credentials are saved.
+    pub fn validate(&self) -> Result<()> {
+        self.validate_account_id()?;
+
+        match self.auth_method {
+            SnowflakeAuthMethod::Password => {
+                if self.password.as_deref().unwrap_or_default().is_empty() {
+                    return Err(anyhow!("password is required for Snowflake password auth"));
+                }
+            }
+            SnowflakeAuthMethod::KeyPair => {
+                self.private_key_pem()?;
+            }
+            SnowflakeAuthMethod::OAuth => {
+                let fields = [
+                    ("oauth_client_id", &self.oauth_client_id),
+                    ("oauth_client_secret", &self.oauth_client_secret),
+                    ("oauth_refresh_token", &self.oauth_refresh_token),
+                ];
+                for (name, value) in fields {
+                    if value.as_deref().unwrap_or_default().is_empty() {
+                        return Err(anyhow!("{} is required for Snowflake OAuth auth", name));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The account identifier becomes part of the Snowflake host name, so it may only contain
+    /// letters, digits, `_`, `.` and `-`
+    pub fn validate_account_id(&self) -> Result<()> {
+        let valid = !self.account_id.is_empty()
+            && self
+                .account_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'));
+        if !valid {
+            return Err(anyhow!(
+                "account_id may only contain letters, digits, '_', '.' and '-'"
+            ));
+        }
+        Ok(())
+    }
+
+    /// The private key as an unencrypted PKCS#8 PEM, decrypting it with the passphrase if needed
+    pub fn private_key_pem(&self) -> Result<Zeroizing<String>> {
+        let private_key = match self.private_key.as_deref().map(str::trim) {
+            Some(private_key) if !private_key.is_empty() => private_key,
+            _ => {
+                return Err(anyhow!(
+                    "private_key is required for Snowflake key pair auth"
+                ))
+            }
+        };
+
+        let (label, document) = SecretDocument::from_pem(private_key)
+            .map_err(|e| anyhow!("private_key is not a valid PEM key: {}", e))?;
+
+        match label {
+            "PRIVATE KEY" => Ok(Zeroizing::new(private_key.to_string())),
+            "ENCRYPTED PRIVATE KEY" => {
+                let passphrase = match self.private_key_passphrase.as_deref() {
+                    Some(passphrase) if !passphrase.is_empty() => passphrase,
+                    _ => {
+                        return Err(anyhow!(
+                            "private_key is encrypted but no private_key_passphrase was given"
+                        ))
+                    }
+                };
+
+                let encrypted = EncryptedPrivateKeyInfo::try_from(document.as_bytes())
+                    .map_err(|e| anyhow!("private_key is not a valid encrypted PKCS#8 key: {}", e))?;
+                let decrypted = encrypted.decrypt(passphrase).map_err(|_| {
+                    anyhow!("private_key could not be decrypted with private_key_passphrase")
+                })?;
+
+                decrypted
+                    .to_pem("PRIVATE KEY", LineEnding::LF)
+                    .map_err(|e| anyhow!("Error encoding decrypted private key: {}", e))
+            }
+            other => Err(anyhow!(
+                "private_key must be a PKCS#8 key (BEGIN PRIVATE KEY or BEGIN ENCRYPTED PRIVATE KEY), got {}",
+                other
+            )),
+        }
+    }
+}
+
 // can get rid of schemas and database id
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -148,12 +274,172 @@ pub struct SqlServerCredentials {
     pub jump_host: Option<String>,
     pub ssh_username: Option<String>,
     pub ssh_private_key: Option<String>,
+    pub ssh_host_key_fingerprint: Option<String>,
     pub default_database: String,
     pub default_schema: Option<String>,
 }
 
 // can get rid of schemas and
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DuckDbCredentials {
+    /// Path to a DuckDB database file. When omitted, an in-memory database is used.
+    pub database_path: Option<String>,
+    /// Parquet/CSV files exposed as views, e.g. local extracts queried without a database file.
+    #[serde(default)]
+    pub external_files: Vec<DuckDbExternalFile>,
+    /// Kept so stored credentials still deserialize; file sources are always opened read-only.
+    #[serde(default = "default_read_only")]
+    pub read_only: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DuckDbExternalFile {
+    pub view_name: String,
+    pub path: String,
+    /// `parquet` or `csv`; inferred from the file extension when omitted.
+    pub format: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SqliteCredentials {
+    #[serde(alias = "database")]
+    pub database_path: String,
+    /// Kept so stored credentials still deserialize; file sources are always opened read-only.
+    #[serde(default = "default_read_only")]
+    pub read_only: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClickHouseCredentials {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: String,
+    #[serde(alias = "database")]
+    pub default_database: String,
+    /// Connect over HTTPS (ClickHouse Cloud and most managed deployments).
+    #[serde(default)]
+    pub secure: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrinoCredentials {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub password: Option<String>,
+    #[serde(alias = "catalog")]
+    pub default_catalog: String,
+    pub default_schema: Option<String>,
+    #[serde(default)]
+    pub secure: bool,
+    /// Send `X-Presto-*` headers instead of `X-Trino-*` for PrestoDB clusters.
+    #[serde(default)]
+    pub presto: bool,
+}
+
+fn default_read_only() -> bool {
+    true
+}
+
+impl DuckDbCredentials {
+    /// Checks the database and every external file resolve inside the data root
+    pub fn validate(&self) -> Result<()> {
+        if let Some(path) = &self.database_path {
+            resolve_data_file(path)?;
+        }
+        for file in &self.external_files {
+            resolve_data_file(&file.path)?;
+        }
+        Ok(())
+    }
+}
+
+impl SqliteCredentials {
+    /// Checks the database file resolves inside the data root
+    pub fn validate(&self) -> Result<()> {
+        resolve_data_file(&self.database_path)?;
+        Ok(())
+    }
+}
+
+/// Directory DuckDB and SQLite data sources may read from. File sources are disabled when unset.
+pub const FILE_DATA_SOURCES_ROOT_ENV: &str = "FILE_DATA_SOURCES_ROOT";
+
+/// Resolves a DuckDB/SQLite file path against `FILE_DATA_SOURCES_ROOT`, so credentials can't
+/// point at arbitrary files on the server (`/proc/self/environ`, `../../etc/passwd`, ...).
+pub fn resolve_data_file(path: &str) -> Result<PathBuf> {
+    let root = match std::env::var(FILE_DATA_SOURCES_ROOT_ENV) {
+        Ok(root) if !root.trim().is_empty() => root,
+        _ => {
+            return Err(anyhow!(
+                "File data sources are disabled; set {} to the directory they may read from",
+                FILE_DATA_SOURCES_ROOT_ENV
+            ))
+        }
+    };
+    resolve_data_file_in(Path::new(&root), path)
+}
+
+/// Relative paths are taken from `root`, absolute ones must already be under it. `..` and
+/// symlinks are rejected outright rather than resolved, and the file must exist.
+fn resolve_data_file_in(configured_root: &Path, path: &str) -> Result<PathBuf> {
+    let root = configured_root.canonicalize().map_err(|e| {
+        anyhow!(
+            "Data source root {} is not accessible: {}",
+            configured_root.display(),
+            e
+        )
+    })?;
+
+    let requested = Path::new(path);
+    if requested
+        .components()
+        .any(|component| matches!(component, Component::ParentDir))
+    {
+        return Err(anyhow!("Data file path {} may not contain '..'", path));
+    }
+
+    let relative = if requested.is_absolute() {
+        requested
+            .strip_prefix(&root)
+            .or_else(|_| requested.strip_prefix(configured_root))
+            .map_err(|_| anyhow!("Data file {} is outside the data root", path))?
+    } else {
+        requested
+    };
+
+    let mut resolved = root.clone();
+    for component in relative.components() {
+        match component {
+            Component::Normal(part) => resolved.push(part),
+            Component::CurDir => continue,
+            _ => return Err(anyhow!("Data file path {} is not valid", path)),
+        }
+
+        let metadata = std::fs::symlink_metadata(&resolved)
+            .map_err(|_| anyhow!("Data file {} does not exist", path))?;
+        if metadata.file_type().is_symlink() {
+            return Err(anyhow!("Data file path {} goes through a symlink", path));
+        }
+    }
+
+    if !resolved.is_file() {
+        return Err(anyhow!("Data file {} is not a file", path));
+    }
+
+    // Belt and braces: the walk above already refuses anything that could leave the root
+    let canonical = resolved
+        .canonicalize()
+        .map_err(|e| anyhow!("Data file {} is not accessible: {}", path, e))?;
+    if !canonical.starts_with(&root) {
+        return Err(anyhow!("Data file {} is outside the data root", path));
+    }
+
+    Ok(canonical)
+}
+
 impl Credential {
     pub fn get_type_string(&self) -> String {
         match self {
@@ -164,6 +450,20 @@ impl Credential {
             Credential::Redshift(_) => "redshift".to_string(),
             Credential::Databricks(_) => "databricks".to_string(),
             Credential::Snowflake(_) => "snowflake".to_string(),
+            Credential::DuckDb(_) => "duckdb".to_string(),
+            Credential::Sqlite(_) => "sqlite".to_string(),
+            Credential::ClickHouse(_) => "clickhouse".to_string(),
+            Credential::Trino(_) => "trino".to_string(),
+        }
+    }
+
+    /// Checks credentials beyond what deserializing them does, before they're saved or used
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Credential::Snowflake(credential) => credential.validate(),
+            Credential::DuckDb(credential) => credential.validate(),
+            Credential::Sqlite(credential) => credential.validate(),
+            _ => Ok(()),
         }
     }
 
@@ -176,6 +476,10 @@ impl Credential {
             Credential::Redshift(_) => DataSourceType::Redshift,
             Credential::Databricks(_) => DataSourceType::Databricks,
             Credential::Snowflake(_) => DataSourceType::Snowflake,
+            Credential::DuckDb(_) => DataSourceType::DuckDb,
+            Credential::Sqlite(_) => DataSourceType::Sqlite,
+            Credential::ClickHouse(_) => DataSourceType::ClickHouse,
+            Credential::Trino(_) => DataSourceType::Trino,
         }
     }
 }
@@ -265,7 +569,18 @@ pub async fn get_data_source_credentials(
             match serde_json::from_str::<SnowflakeCredentials>(&secret_string) {
                 Ok(mut credential) => {
                     if redact_secret {
-                        credential.password = "[REDACTED]".to_string();
+                        credential.password = credential.password.map(|_| "[REDACTED]".to_string());
+                        credential.private_key =
+                            credential.private_key.map(|_| "[REDACTED]".to_string());
+                        credential.private_key_passphrase = credential
+                            .private_key_passphrase
+                            .map(|_| "[REDACTED]".to_string());
+                        credential.oauth_client_secret = credential
+                            .oauth_client_secret
+                            .map(|_| "[REDACTED]".to_string());
+                        credential.oauth_refresh_token = credential
+                            .oauth_refresh_token
+                            .map(|_| "[REDACTED]".to_string());
                     }
                     Credential::Snowflake(credential)
                 }
@@ -298,6 +613,111 @@ pub async fn get_data_source_credentials(
                 Err(e) => return Err(anyhow!("Error deserializing Supabase secret: {:?}", e)),
             }
         }
+        // Embedded databases only hold file paths, so there is nothing to redact
+        DataSourceType::DuckDb => match serde_json::from_str::<DuckDbCredentials>(&secret_string) {
+            Ok(credential) => Credential::DuckDb(credential),
+            Err(e) => return Err(anyhow!("Error deserializing DuckDB secret: {:?}", e)),
+        },
+        DataSourceType::Sqlite => match serde_json::from_str::<SqliteCredentials>(&secret_string) {
+            Ok(credential) => Credential::Sqlite(credential),
+            Err(e) => return Err(anyhow!("Error deserializing SQLite secret: {:?}", e)),
+        },
+        DataSourceType::ClickHouse => {
+            match serde_json::from_str::<ClickHouseCredentials>(&secret_string) {
+                Ok(mut credential) => {
+                    if redact_secret {
+                        credential.password = "[REDACTED]".to_string();
+                    }
+                    Credential::ClickHouse(credential)
+                }
+                Err(e) => return Err(anyhow!("Error deserializing ClickHouse secret: {:?}", e)),
+            }
+        }
+        DataSourceType::Trino => match serde_json::from_str::<TrinoCredentials>(&secret_string) {
+            Ok(mut credential) => {
+                if redact_secret {
+                    credential.password = credential.password.map(|_| "[REDACTED]".to_string());
+                }
+                Credential::Trino(credential)
+            }
+            Err(e) => return Err(anyhow!("Error deserializing Trino secret: {:?}", e)),
+        },
     };
     Ok(credential)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+
+    fn data_root() -> PathBuf {
+        let root = std::env::temp_dir().join(format!("file_data_sources_{}", Uuid::new_v4()));
+        fs::create_dir_all(root.join("extracts")).unwrap();
+        fs::write(root.join("extracts/orders.csv"), "id\n1\n").unwrap();
+        root
+    }
+
+    #[test]
+    fn test_resolve_data_file_accepts_relative_and_absolute_paths_in_root() {
+        let root = data_root();
+        let expected = root.join("extracts/orders.csv").canonicalize().unwrap();
+
+        assert_eq!(
+            resolve_data_file_in(&root, "extracts/orders.csv").unwrap(),
+            expected
+        );
+        assert_eq!(
+            resolve_data_file_in(&root, expected.to_str().unwrap()).unwrap(),
+            expected
+        );
+    }
+
+    #[test]
+    fn test_resolve_data_file_rejects_paths_leaving_root() {
+        let root = data_root();
+
+        assert!(resolve_data_file_in(&root, "../orders.csv").is_err());
+        assert!(resolve_data_file_in(&root, "extracts/../../etc/passwd").is_err());
+        assert!(resolve_data_file_in(&root, "/proc/self/environ").is_err());
+        assert!(resolve_data_file_in(&root, "/etc/passwd").is_err());
+    }
+
+    #[test]
+    fn test_resolve_data_file_rejects_missing_files_and_directories() {
+        let root = data_root();
+
+        assert!(resolve_data_file_in(&root, "extracts/missing.csv").is_err());
+        assert!(resolve_data_file_in(&root, "extracts").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_data_file_rejects_symlinks() {
+        let root = data_root();
+        std::os::unix::fs::symlink("/etc/passwd", root.join("passwd.csv")).unwrap();
+        std::os::unix::fs::symlink(root.join("extracts"), root.join("linked")).unwrap();
+
+        assert!(resolve_data_file_in(&root, "passwd.csv").is_err());
+        assert!(resolve_data_file_in(&root, "linked/orders.csv").is_err());
+    }
+
+    #[test]
+    fn test_snowflake_account_id_validation() {
+        let mut credentials: SnowflakeCredentials = serde_json::from_value(serde_json::json!({
+            "account_id": "xy12345.us-east-1",
+            "warehouse_id": "COMPUTE_WH",
+            "username": "analyst",
+            "password": "secret",
+            "default_database": "ANALYTICS",
+        }))
+        .unwrap();
+        assert!(credentials.validate().is_ok());
+
+        for account_id in ["", "evil.com/x?", "acct@attacker.example", "acct#frag"] {
+            credentials.account_id = account_id.to_string();
+            assert!(credentials.validate().is_err(), "{}", account_id);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::{
+    credentials::ClickHouseCredentials,
+    data_source_query_routes::query_cancellation::{query_cancelled, CancellationToken},
+};
+
+pub async fn get_clickhouse_client(credentials: &ClickHouseCredentials) -> Result<ClickHouse> {
+    let clickhouse_client = ClickHouse::new(credentials);
+
+    Ok(clickhouse_client)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClickHouseColumn {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_name: String,
+}
+
+/// Response body of the HTTP interface for `FORMAT JSONCompact`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ClickHouseResponse {
+    pub meta: Vec<ClickHouseColumn>,
+    pub data: Vec<Vec<Value>>,
+    pub rows: Option<i64>,
+}
+
+#[derive(Clone)]
+pub struct ClickHouse {
+    pub base_url: String,
+    pub username: String,
+    pub password: String,
+    pub database: String,
+}
+
+impl ClickHouse {
+    pub fn new(credentials: &ClickHouseCredentials) -> Self {
+        let scheme = if credentials.secure { "https" } else { "http" };
+
+        ClickHouse {
+            base_url: format!("{}://{}:{}", scheme, credentials.host, credentials.port),
+            username: credentials.username.clone(),
+            password: credentials.password.clone(),
+            database: credentials.default_database.clone(),
+        }
+    }
+
+    /// Runs a statement over the HTTP interface. When `max_rows` is set the server stops
+    /// producing rows once it is reached instead of erroring. If `cancellation` fires first
+    /// the query is killed on the server.
+    pub async fn query(
+        &self,
+        statement: String,
+        max_rows: Option<usize>,
+        cancellation: &CancellationToken,
+    ) -> Result<ClickHouseResponse> {
+        let client = reqwest::Client::new();
+        let query_id = Uuid::new_v4().to_string();
+
+        let mut params = vec![
+            ("database", self.database.clone()),
+            ("default_format", "JSONCompact".to_string()),
+            ("output_format_json_quote_64bit_integers", "0".to_string()),
+            ("readonly", "2".to_string()),
+            ("query_id", query_id.clone()),
+        ];
+        if let Some(max_rows) = max_rows {
+            params.push(("max_result_rows", max_rows.to_string()));
+            params.push(("result_overflow_mode", "break".to_string()));
+        }
+
+        let request = client
+            .post(&self.base_url)
+            .query(&params)
+            .header("X-ClickHouse-User", &self.username)
+            .header("X-ClickHouse-Key", &self.password)
+            .timeout(Duration::from_secs(300))
+            .body(statement);
+
+        tokio::select! {
+            response = Self::send(request) => response,
+            _ = cancellation.cancelled() => {
+                self.kill_query(&client, &query_id).await;
+                Err(query_cancelled())
+            }
+        }
+    }
+
+    async fn kill_query(&self, client: &reqwest::Client, query_id: &str) {
+        let result = client
+            .post(&self.base_url)
+            .header("X-ClickHouse-User", &self.username)
+            .header("X-ClickHouse-Key", &self.password)
+            .timeout(Duration::from_secs(30))
+            .body(format!("KILL QUERY WHERE query_id = '{}' ASYNC", query_id))
+            .send()
+            .await;
+
+        match result {
+            Ok(response) if response.status().is_success() => (),
+            Ok(response) => {
+                tracing::warn!("Failed to kill ClickHouse query {}: {}", query_id, response.status())
+            }
+            Err(e) => tracing::warn!("Failed to kill ClickHouse query {}: {}", query_id, e),
+        }
+    }
+
+    async fn send(request: reqwest::RequestBuilder) -> Result<ClickHouseResponse> {
+        let response = match request.send().await {
+            Ok(res) => res,
+            Err(e) => return Err(anyhow!(e.to_string())),
+        };
+
+        // Errors come back as plain text with a non-2xx status
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("ClickHouse returned {}: {}", status, body.trim()));
+        }
+
+        let response: ClickHouseResponse = match response.json().await {
+            Ok(res) => res,
+            Err(e) => return Err(anyhow!(e.to_string())),
+        };
+
+        Ok(response)
+    }
+}
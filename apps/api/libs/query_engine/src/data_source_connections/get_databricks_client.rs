@@ -2,7 +2,10 @@ use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 
-use crate::credentials::DatabricksCredentials;
+use crate::{
+    credentials::DatabricksCredentials,
+    data_source_query_routes::query_cancellation::{query_cancelled, CancellationToken},
+};
 
 pub async fn get_databricks_client(credentials: &DatabricksCredentials) -> Result<Databricks> {
     let databricks_client = Databricks::new(credentials).await;
@@ -43,11 +46,19 @@ pub struct DatabricksQuery {
     pub warehouse_id: String,
     pub catalog: String,
     pub statement: String,
+    pub wait_timeout: String,
+    pub on_wait_timeout: String,
+}
+
+#[derive(Serialize, Debug, Deserialize, Clone)]
+pub struct StatusError {
+    pub message: Option<String>,
 }
 
 #[derive(Serialize, Debug, Deserialize, Clone)]
 pub struct Status {
     pub state: String,
+    pub error: Option<StatusError>,
 }
 
 #[derive(Serialize, Debug, Deserialize, Clone)]
@@ -73,8 +84,8 @@ pub struct Manifest {
 pub struct QueryResponse {
     pub statement_id: String,
     pub status: Status,
-    pub manifest: Manifest,
-    pub result: DatabricksResult,
+    pub manifest: Option<Manifest>, // Only present once the statement has succeeded
+    pub result: Option<DatabricksResult>,
 }
 
 impl Databricks {
@@ -87,30 +98,77 @@ impl Databricks {
         }
     }
 
-    pub async fn query(self, statement: String) -> Result<QueryResponse> {
+    /// Runs a statement and polls until it finishes. If `cancellation` fires first the
+    /// statement is cancelled on the warehouse.
+    pub async fn query(
+        self,
+        statement: String,
+        cancellation: &CancellationToken,
+    ) -> Result<QueryResponse> {
         let client = reqwest::Client::new();
+        let statements_url = format!("https://{host}/api/2.0/sql/statements", host = self.host);
 
         let databricks_query = DatabricksQuery {
-            warehouse_id: self.warehouse_id,
-            catalog: self.catalog_name,
+            warehouse_id: self.warehouse_id.clone(),
+            catalog: self.catalog_name.clone(),
             statement,
+            // Return after 10s with a statement id instead of blocking until completion
+            wait_timeout: "10s".to_string(),
+            on_wait_timeout: "CONTINUE".to_string(),
         };
 
-        let query_result = match client
-            .post(format!(
-                "https://{host}/api/2.0/sql/statements/",
-                host = self.host
-            ))
-            .headers({
-                let mut headers = reqwest::header::HeaderMap::new();
-                headers.insert(
-                    reqwest::header::AUTHORIZATION,
-                    format!("Bearer {}", self.api_key).parse().unwrap(),
-                );
-                headers
-            })
+        let mut response = self
+            .send(client.post(format!("{}/", statements_url)).json(&databricks_query))
+            .await?;
+
+        loop {
+            match response.status.state.as_str() {
+                "PENDING" | "RUNNING" => {}
+                "SUCCEEDED" => return Ok(response),
+                state => {
+                    let message = response
+                        .status
+                        .error
+                        .and_then(|error| error.message)
+                        .unwrap_or_default();
+                    return Err(anyhow!(
+                        "Databricks statement {} {}: {}",
+                        response.statement_id,
+                        state.to_lowercase(),
+                        message
+                    ));
+                }
+            }
+
+            tokio::select! {
+                biased;
+                _ = cancellation.cancelled() => {
+                    let cancel_url = format!("{}/{}/cancel", statements_url, response.statement_id);
+                    if let Err(e) = self.authorize(client.post(cancel_url)).send().await {
+                        tracing::warn!("Failed to cancel Databricks statement {}: {}", response.statement_id, e);
+                    }
+                    return Err(query_cancelled());
+                }
+                _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+            }
+
+            response = self
+                .send(client.get(format!("{}/{}", statements_url, response.statement_id)))
+                .await?;
+        }
+    }
+
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        request.header(
+            reqwest::header::AUTHORIZATION,
+            format!("Bearer {}", self.api_key),
+        )
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<QueryResponse> {
+        let query_result = match self
+            .authorize(request)
             .timeout(Duration::from_secs(300))
-            .json(&databricks_query)
             .send()
             .await
         {
@@ -0,0 +1,93 @@
+use anyhow::{anyhow, Error};
+use duckdb::{AccessMode, Config, Connection};
+
+use crate::credentials::{resolve_data_file, DuckDbCredentials, DuckDbExternalFile};
+
+pub async fn get_duckdb_connection(credentials: &DuckDbCredentials) -> Result<Connection, Error> {
+    let credentials = credentials.clone();
+
+    // DuckDB's API is synchronous, so open the database off the async runtime
+    tokio::task::spawn_blocking(move || open_duckdb_connection(&credentials))
+        .await
+        .map_err(|e| anyhow!("DuckDB connection task failed: {}", e))?
+}
+
+fn open_duckdb_connection(credentials: &DuckDbCredentials) -> Result<Connection, Error> {
+    // Paths are re-checked on every open, so credentials saved before the data root existed
+    // can't read outside it either
+    let connection = match &credentials.database_path {
+        Some(path) => {
+            let path = resolve_data_file(path)?;
+            let config = Config::default().access_mode(AccessMode::ReadOnly)?;
+
+            Connection::open_with_flags(&path, config).map_err(|e| {
+                tracing::error!("Error opening DuckDB database {}: {}", path.display(), e);
+                anyhow!(e)
+            })?
+        }
+        None => Connection::open_in_memory().map_err(|e| {
+            tracing::error!("Error opening in-memory DuckDB database: {}", e);
+            anyhow!(e)
+        })?,
+    };
+
+    let mut allowed_paths = Vec::with_capacity(credentials.external_files.len());
+    for file in &credentials.external_files {
+        let path = resolve_data_file(&file.path)?;
+        let path = path
+            .to_str()
+            .ok_or_else(|| anyhow!("Data file path {} is not valid UTF-8", file.path))?;
+
+        connection
+            .execute_batch(&create_view_sql(file, path)?)
+            .map_err(|e| {
+                anyhow!(
+                    "Error registering {} as view {}: {}",
+                    file.path,
+                    file.view_name,
+                    e
+                )
+            })?;
+        allowed_paths.push(quote_literal(path));
+    }
+
+    // Queries may only read the configured files; `read_csv('/etc/passwd')` and friends are blocked
+    connection
+        .execute_batch(&format!(
+            "SET allowed_paths = [{}]; SET enable_external_access = false; SET lock_configuration = true;",
+            allowed_paths.join(", ")
+        ))
+        .map_err(|e| anyhow!("Error restricting DuckDB file access: {}", e))?;
+
+    Ok(connection)
+}
+
+fn create_view_sql(file: &DuckDbExternalFile, path: &str) -> Result<String, Error> {
+    let format = match &file.format {
+        Some(format) => format.to_lowercase(),
+        None => file
+            .path
+            .rsplit('.')
+            .next()
+            .unwrap_or_default()
+            .to_lowercase(),
+    };
+
+    let reader = match format.as_str() {
+        "parquet" => "read_parquet",
+        "csv" | "tsv" => "read_csv_auto",
+        other => return Err(anyhow!("Unsupported DuckDB file format: {}", other)),
+    };
+
+    // Temporary views live outside the database file, so they work on read-only databases
+    Ok(format!(
+        "CREATE OR REPLACE TEMP VIEW \"{}\" AS SELECT * FROM {}({})",
+        file.view_name.replace('"', "\"\""),
+        reader,
+        quote_literal(path)
+    ))
+}
+
+fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
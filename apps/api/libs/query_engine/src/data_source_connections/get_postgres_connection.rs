@@ -1,52 +1,28 @@
-use std::{borrow::Cow, time::Duration};
+use std::{borrow::Cow, sync::Arc, time::Duration};
 
 use anyhow::{anyhow, Result};
 use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
-use std::process::Child;
-use tempfile::NamedTempFile;
 use url::form_urlencoded::byte_serialize;
 
 use crate::credentials::PostgresCredentials;
 
-use super::ssh_tunneling::establish_ssh_tunnel;
+use super::ssh_tunneling::{get_ssh_tunnel, SshTunnel, SshTunnelConfig};
 
 pub async fn get_postgres_connection(
     credentials: &PostgresCredentials,
-) -> Result<(
-    Pool<Postgres>,
-    Option<std::process::Child>,
-    Option<Vec<NamedTempFile>>,
-)> {
-    let mut parent_ssh_tunnel: Option<Child> = None;
-    let mut parent_temp_files: Option<Vec<NamedTempFile>> = None;
-    let mut parent_local_port: Option<u16> = None;
-
-    if let (Some(jump_host), Some(ssh_private_key), Some(ssh_username)) = (
-        credentials.jump_host.clone(),
-        credentials.ssh_private_key.clone(),
-        credentials.ssh_username.clone(),
+) -> Result<(Pool<Postgres>, Option<Arc<SshTunnel>>)> {
+    let ssh_tunnel = match SshTunnelConfig::from_credentials(
+        &credentials.jump_host,
+        &credentials.ssh_username,
+        &credentials.ssh_private_key,
+        &credentials.ssh_host_key_fingerprint,
+        &credentials.host,
+        credentials.port,
     ) {
-        let (ssh_tunnel, local_port, temp_files) = match establish_ssh_tunnel(
-            ssh_private_key,
-            jump_host,
-            ssh_username,
-            credentials.host.clone(),
-            credentials.port.to_string().clone(),
-        ) {
-            Ok((ssh_tunnel, local_port, temp_files)) => (Some(ssh_tunnel), local_port, temp_files),
-            Err(e) => {
-                tracing::error!(
-                    "There was an issue while establishing the ssh tunnel: {}",
-                    e
-                );
-                return Err(anyhow!(e));
-            }
-        };
-
-        parent_local_port = Some(local_port);
-        parent_ssh_tunnel = ssh_tunnel;
-        parent_temp_files = Some(temp_files);
-    }
+        Some(config) => Some(get_ssh_tunnel(config).await?),
+        None => None,
+    };
+    let parent_local_port = ssh_tunnel.as_ref().map(|tunnel| tunnel.local_port());
 
     let connection_string;
 
@@ -82,7 +58,7 @@ pub async fn get_postgres_connection(
         }
     };
 
-    Ok((pg_pool, parent_ssh_tunnel, parent_temp_files))
+    Ok((pg_pool, ssh_tunnel))
 }
 
 fn url_encode(input: &str) -> Cow<str> {
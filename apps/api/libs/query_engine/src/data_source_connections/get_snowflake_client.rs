@@ -1,27 +1,329 @@
-use anyhow::{anyhow, Error};
+use anyhow::{anyhow, Error, Result};
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
 use snowflake_api::SnowflakeApi;
+use std::time::Duration;
+use uuid::Uuid;
 
-use crate::credentials::SnowflakeCredentials;
+use crate::{
+    credentials::{SnowflakeAuthMethod, SnowflakeCredentials},
+    data_source_query_routes::query_cancellation::{query_cancelled, CancellationToken},
+};
+
+/// A Snowflake connection. The connector protocol used by `snowflake_api` only supports
+/// password and key pair logins, so OAuth connections go through the SQL API instead, with an
+/// access token exchanged for the stored refresh token when the connection is opened.
+pub enum SnowflakeClient {
+    Connector(SnowflakeApi),
+    SqlApi(SnowflakeSqlApi),
+}
 
 // TODO: make sure that can handle database attached to  datasets or other option
 pub async fn get_snowflake_client(
     credentials: &SnowflakeCredentials,
-) -> Result<SnowflakeApi, Error> {
-    let snowflake_client = match SnowflakeApi::with_password_auth(
-        &credentials.account_id,
-        Some(&credentials.warehouse_id),
-        Some(&credentials.default_database),
-        None,
-        &credentials.username,
-        credentials.role.as_deref(),
-        &credentials.password,
-    ) {
-        Ok(snowflake) => snowflake,
+) -> Result<SnowflakeClient, Error> {
+    credentials.validate_account_id()?;
+
+    let snowflake_client = match credentials.auth_method {
+        SnowflakeAuthMethod::Password => {
+            let password = credentials
+                .password
+                .as_deref()
+                .ok_or_else(|| anyhow!("password is required for Snowflake password auth"))?;
+
+            SnowflakeApi::with_password_auth(
+                &credentials.account_id,
+                Some(&credentials.warehouse_id),
+                Some(&credentials.default_database),
+                None,
+                &credentials.username,
+                credentials.role.as_deref(),
+                password,
+            )
+        }
+        SnowflakeAuthMethod::KeyPair => {
+            let private_key = credentials.private_key_pem()?;
+
+            SnowflakeApi::with_certificate_auth(
+                &credentials.account_id,
+                Some(&credentials.warehouse_id),
+                Some(&credentials.default_database),
+                None,
+                &credentials.username,
+                credentials.role.as_deref(),
+                &private_key,
+            )
+        }
+        SnowflakeAuthMethod::OAuth => {
+            return Ok(SnowflakeClient::SqlApi(
+                SnowflakeSqlApi::connect(credentials).await?,
+            ));
+        }
+    };
+
+    match snowflake_client {
+        Ok(snowflake) => Ok(SnowflakeClient::Connector(snowflake)),
         Err(e) => {
             tracing::error!("Error creating SnowflakeApi: {}", e);
-            return Err(anyhow!(e));
+            Err(anyhow!(e))
         }
-    };
+    }
+}
+
+// An endpoint on the account's Snowflake host. The account id is checked to be the whole
+// host label, so a crafted id can't point requests carrying credentials at another host.
+fn snowflake_url(account_id: &str, path: &str) -> Result<Url> {
+    let host = format!("{}.snowflakecomputing.com", account_id);
+    let url = Url::parse(&format!("https://{}/{}", host, path))
+        .map_err(|e| anyhow!("Invalid Snowflake account_id: {}", e))?;
 
-    Ok(snowflake_client)
+    if url.host_str() != Some(host.to_lowercase().as_str()) {
+        return Err(anyhow!("Invalid Snowflake account_id: {}", account_id));
+    }
+    Ok(url)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SqlApiColumn {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_: String,
+    pub scale: Option<i64>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SqlApiResultSetMetaData {
+    pub row_type: Vec<SqlApiColumn>,
+    #[serde(default)]
+    pub partition_info: Vec<serde_json::Value>,
+}
+
+/// A SQL API response. Statement status responses only carry the code, message and handle;
+/// later result partitions only carry `data`.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SqlApiResponse {
+    pub code: Option<String>,
+    pub message: Option<String>,
+    pub statement_handle: Option<String>,
+    pub result_set_meta_data: Option<SqlApiResultSetMetaData>,
+    #[serde(default)]
+    pub data: Vec<Vec<Option<String>>>,
+}
+
+#[derive(Serialize, Debug)]
+struct SqlApiStatement<'a> {
+    statement: &'a str,
+    warehouse: &'a str,
+    database: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    schema: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<&'a str>,
+}
+
+/// Rows of a finished statement. Values are Snowflake's JSON encoding of each column type.
+#[derive(Debug, Clone)]
+pub struct SqlApiResult {
+    pub columns: Vec<SqlApiColumn>,
+    pub rows: Vec<Vec<Option<String>>>,
+}
+
+// Response of Snowflake's OAuth token endpoint
+#[derive(Deserialize)]
+struct OAuthTokenResponse {
+    access_token: String,
+}
+
+#[derive(Clone)]
+pub struct SnowflakeSqlApi {
+    pub account_id: String,
+    /// Short-lived (10 minute) access token; a new one is fetched for every connection
+    pub access_token: String,
+    pub warehouse: String,
+    pub database: String,
+    pub schema: Option<String>,
+    pub role: Option<String>,
+}
+
+impl SnowflakeSqlApi {
+    /// Exchanges the credentials' refresh token for an access token
+    pub async fn connect(credentials: &SnowflakeCredentials) -> Result<Self> {
+        credentials.validate()?;
+        let (Some(client_id), Some(client_secret), Some(refresh_token)) = (
+            credentials.oauth_client_id.as_deref(),
+            credentials.oauth_client_secret.as_deref(),
+            credentials.oauth_refresh_token.as_deref(),
+        ) else {
+            return Err(anyhow!("Snowflake OAuth credentials are incomplete"));
+        };
+
+        let token_url = snowflake_url(&credentials.account_id, "oauth/token-request")?;
+        let response = reqwest::Client::new()
+            .post(token_url)
+            .basic_auth(client_id, Some(client_secret))
+            .form(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", refresh_token),
+            ])
+            .timeout(Duration::from_secs(30))
+            .send()
+            .await
+            .map_err(|e| anyhow!("Error requesting Snowflake access token: {}", e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            // The body can echo the request, so it's only logged
+            let body = response.text().await.unwrap_or_default();
+            tracing::error!(
+                "Snowflake rejected the OAuth refresh token ({}): {}",
+                status,
+                body
+            );
+            return Err(anyhow!(
+                "Snowflake rejected the OAuth refresh token ({})",
+                status
+            ));
+        }
+        let token = response
+            .json::<OAuthTokenResponse>()
+            .await
+            .map_err(|e| anyhow!("Invalid Snowflake access token response: {}", e))?;
+
+        Ok(SnowflakeSqlApi {
+            account_id: credentials.account_id.clone(),
+            access_token: token.access_token,
+            warehouse: credentials.warehouse_id.clone(),
+            database: credentials.default_database.clone(),
+            schema: credentials.default_schema.clone(),
+            role: credentials.role.clone(),
+        })
+    }
+
+    /// Runs a statement and polls until it finishes, fetching result partitions until at
+    /// least `limit` rows are loaded. If `cancellation` fires first the statement is
+    /// cancelled on the warehouse.
+    pub async fn query(
+        &self,
+        statement: &str,
+        limit: usize,
+        cancellation: &CancellationToken,
+    ) -> Result<SqlApiResult> {
+        let client = reqwest::Client::new();
+        let statements_url = snowflake_url(&self.account_id, "api/v2/statements")?;
+
+        // Submitting asynchronously returns the handle straight away, so the statement can be
+        // cancelled while it runs
+        let request = client
+            .post(statements_url.clone())
+            .query(&[
+                ("requestId", Uuid::new_v4().to_string()),
+                ("async", "true".to_string()),
+            ])
+            .json(&SqlApiStatement {
+                statement,
+                warehouse: &self.warehouse,
+                database: &self.database,
+                schema: self.schema.as_deref(),
+                role: self.role.as_deref(),
+            });
+        let (mut status, mut response) = self.send(request).await?;
+
+        while status == StatusCode::ACCEPTED {
+            let handle = response
+                .statement_handle
+                .clone()
+                .ok_or_else(|| anyhow!("Snowflake accepted the statement without a handle"))?;
+
+            tokio::select! {
+                biased;
+                _ = cancellation.cancelled() => {
+                    let cancel_url = format!("{}/{}/cancel", statements_url, handle);
+                    if let Err(e) = self.authorize(client.post(cancel_url)).send().await {
+                        tracing::warn!("Failed to cancel Snowflake statement {}: {}", handle, e);
+                    }
+                    return Err(query_cancelled());
+                }
+                _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+            }
+
+            (status, response) = self
+                .send(client.get(format!("{}/{}", statements_url, handle)))
+                .await?;
+        }
+
+        if status != StatusCode::OK {
+            return Err(anyhow!(
+                "Snowflake statement failed ({}): {}",
+                response.code.unwrap_or_else(|| status.to_string()),
+                response.message.unwrap_or_default()
+            ));
+        }
+
+        let metadata = response
+            .result_set_meta_data
+            .ok_or_else(|| anyhow!("Snowflake returned rows without a schema"))?;
+        let mut rows = response.data;
+
+        if let Some(handle) = response.statement_handle {
+            for partition in 1..metadata.partition_info.len() {
+                if rows.len() >= limit {
+                    break;
+                }
+
+                let request = client
+                    .get(format!("{}/{}", statements_url, handle))
+                    .query(&[("partition", partition)]);
+                let (status, partition_response) = self.send(request).await?;
+                if status != StatusCode::OK {
+                    return Err(anyhow!(
+                        "Error fetching Snowflake result partition {}: {}",
+                        partition,
+                        partition_response.message.unwrap_or_default()
+                    ));
+                }
+                rows.extend(partition_response.data);
+            }
+        }
+
+        Ok(SqlApiResult {
+            columns: metadata.row_type,
+            rows,
+        })
+    }
+
+    fn authorize(&self, request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        request
+            .header(
+                reqwest::header::AUTHORIZATION,
+                format!("Bearer {}", self.access_token),
+            )
+            .header("X-Snowflake-Authorization-Token-Type", "OAUTH")
+            .header(reqwest::header::ACCEPT, "application/json")
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<(StatusCode, SqlApiResponse)> {
+        let response = match self
+            .authorize(request)
+            .timeout(Duration::from_secs(300))
+            .send()
+            .await
+        {
+            Ok(res) => res,
+            Err(e) => return Err(anyhow!(e.to_string())),
+        };
+
+        let status = response.status();
+        let body = match response.text().await {
+            Ok(body) => body,
+            Err(e) => return Err(anyhow!(e.to_string())),
+        };
+
+        match serde_json::from_str::<SqlApiResponse>(&body) {
+            Ok(response) => Ok((status, response)),
+            // Auth failures come back from the gateway without a statement body
+            Err(_) => Err(anyhow!("Snowflake SQL API returned {}: {}", status, body)),
+        }
+    }
 }
@@ -1,54 +1,29 @@
 use anyhow::{anyhow, Error};
-use std::process::Child;
-use tempfile::NamedTempFile;
+use std::sync::Arc;
 use tiberius::{AuthMethod, Client, Config};
 use tokio::net::TcpStream;
 use tokio_util::compat::{Compat, TokioAsyncWriteCompatExt};
 
 use crate::{
-    credentials::SqlServerCredentials, data_source_connections::ssh_tunneling::establish_ssh_tunnel,
+    credentials::SqlServerCredentials,
+    data_source_connections::ssh_tunneling::{get_ssh_tunnel, SshTunnel, SshTunnelConfig},
 };
 
 pub async fn get_sql_server_connection(
     credentials: &SqlServerCredentials,
-) -> Result<
-    (
-        Client<Compat<TcpStream>>,
-        Option<std::process::Child>,
-        Option<Vec<NamedTempFile>>,
-    ),
-    Error,
-> {
-    let mut parent_ssh_tunnel: Option<Child> = None;
-    let mut parent_temp_files: Option<Vec<NamedTempFile>> = None;
-    let mut parent_local_port: Option<u16> = None;
-
-    if let (Some(jump_host), Some(ssh_private_key), Some(ssh_username)) = (
-        credentials.jump_host.clone(),
-        credentials.ssh_private_key.clone(),
-        credentials.ssh_username.clone(),
+) -> Result<(Client<Compat<TcpStream>>, Option<Arc<SshTunnel>>), Error> {
+    let ssh_tunnel = match SshTunnelConfig::from_credentials(
+        &credentials.jump_host,
+        &credentials.ssh_username,
+        &credentials.ssh_private_key,
+        &credentials.ssh_host_key_fingerprint,
+        &credentials.host,
+        credentials.port,
     ) {
-        let (ssh_tunnel, local_port, temp_files) = match establish_ssh_tunnel(
-            ssh_private_key,
-            jump_host,
-            ssh_username,
-            credentials.host.clone(),
-            credentials.port.to_string(),
-        ) {
-            Ok((ssh_tunnel, local_port, temp_files)) => (Some(ssh_tunnel), local_port, temp_files),
-            Err(e) => {
-                tracing::error!(
-                    "There was an issue while establishing the ssh tunnel: {}",
-                    e
-                );
-                return Err(anyhow!(e));
-            }
-        };
-
-        parent_local_port = Some(local_port);
-        parent_ssh_tunnel = ssh_tunnel;
-        parent_temp_files = Some(temp_files);
-    }
+        Some(config) => Some(get_ssh_tunnel(config).await?),
+        None => None,
+    };
+    let parent_local_port = ssh_tunnel.as_ref().map(|tunnel| tunnel.local_port());
 
     let mut config = Config::new();
 
@@ -60,7 +35,7 @@ pub async fn get_sql_server_connection(
     config.database(credentials.default_database.clone());
 
     if let Some(local_port) = parent_local_port {
-        config.host("localhost");
+        config.host("127.0.0.1");
         config.port(local_port)
     } else {
         config.host(credentials.host.clone());
@@ -85,5 +60,5 @@ pub async fn get_sql_server_connection(
         }
     };
 
-    Ok((client, parent_ssh_tunnel, parent_temp_files))
+    Ok((client, ssh_tunnel))
 }
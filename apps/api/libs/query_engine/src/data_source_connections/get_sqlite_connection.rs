@@ -0,0 +1,32 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use sqlx::{
+    sqlite::{SqliteConnectOptions, SqlitePoolOptions},
+    Pool, Sqlite,
+};
+
+use crate::credentials::{resolve_data_file, SqliteCredentials};
+
+pub async fn get_sqlite_connection(credentials: &SqliteCredentials) -> Result<Pool<Sqlite>> {
+    let database_path = resolve_data_file(&credentials.database_path)?;
+    let options = SqliteConnectOptions::new()
+        .filename(&database_path)
+        .read_only(true)
+        .create_if_missing(false);
+
+    let sqlite_pool = match SqlitePoolOptions::new()
+        .max_connections(1)
+        .acquire_timeout(Duration::from_secs(5))
+        .connect_with(options)
+        .await
+    {
+        Ok(sqlite_pool) => sqlite_pool,
+        Err(e) => {
+            tracing::error!("There was an issue while connecting to SQLite: {}", e);
+            return Err(anyhow!(e));
+        }
+    };
+
+    Ok(sqlite_pool)
+}
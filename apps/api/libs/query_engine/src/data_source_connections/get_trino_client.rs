@@ -0,0 +1,166 @@
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::{
+    credentials::TrinoCredentials,
+    data_source_query_routes::query_cancellation::{query_cancelled, CancellationToken},
+};
+
+pub async fn get_trino_client(credentials: &TrinoCredentials) -> Result<Trino> {
+    let trino_client = Trino::new(credentials)?;
+
+    Ok(trino_client)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrinoColumn {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub type_name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrinoError {
+    pub message: String,
+    #[serde(rename = "errorName")]
+    pub error_name: Option<String>,
+}
+
+/// One page of the client protocol; results are fetched by following `nextUri`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TrinoQueryResults {
+    pub id: String,
+    #[serde(rename = "nextUri")]
+    pub next_uri: Option<String>,
+    pub columns: Option<Vec<TrinoColumn>>,
+    pub data: Option<Vec<Vec<Value>>>,
+    pub error: Option<TrinoError>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TrinoResponse {
+    pub columns: Vec<TrinoColumn>,
+    pub data: Vec<Vec<Value>>,
+}
+
+#[derive(Clone)]
+pub struct Trino {
+    pub base_url: String,
+    pub username: String,
+    pub password: Option<String>,
+    pub catalog: String,
+    pub schema: Option<String>,
+    header_prefix: &'static str,
+    client: reqwest::Client,
+}
+
+impl Trino {
+    pub fn new(credentials: &TrinoCredentials) -> Result<Self> {
+        let scheme = if credentials.secure { "https" } else { "http" };
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(300))
+            .build()
+            .map_err(|e| anyhow!("Failed to build Trino HTTP client: {}", e))?;
+
+        Ok(Trino {
+            base_url: format!("{}://{}:{}", scheme, credentials.host, credentials.port),
+            username: credentials.username.clone(),
+            password: credentials.password.clone(),
+            catalog: credentials.default_catalog.clone(),
+            schema: credentials.default_schema.clone(),
+            header_prefix: if credentials.presto { "X-Presto" } else { "X-Trino" },
+            client,
+        })
+    }
+
+    /// Runs a statement and follows `nextUri` until the query finishes or `max_rows` rows have
+    /// been collected, in which case the rest of the query is cancelled. The query is also
+    /// cancelled if `cancellation` fires first.
+    pub async fn query(
+        &self,
+        statement: String,
+        max_rows: Option<usize>,
+        cancellation: &CancellationToken,
+    ) -> Result<TrinoResponse> {
+        let mut request = self
+            .client
+            .post(format!("{}/v1/statement", self.base_url))
+            .header(format!("{}-User", self.header_prefix), &self.username)
+            .header(format!("{}-Catalog", self.header_prefix), &self.catalog)
+            .body(statement);
+        if let Some(schema) = &self.schema {
+            request = request.header(format!("{}-Schema", self.header_prefix), schema);
+        }
+        if let Some(password) = &self.password {
+            request = request.basic_auth(&self.username, Some(password));
+        }
+
+        let mut page = self.send(request).await?;
+        let mut response = TrinoResponse::default();
+
+        loop {
+            if let Some(error) = page.error {
+                return Err(anyhow!(
+                    "Trino query {} failed: {}",
+                    page.id,
+                    error.message
+                ));
+            }
+            if response.columns.is_empty() {
+                if let Some(columns) = page.columns.take() {
+                    response.columns = columns;
+                }
+            }
+            if let Some(data) = page.data.take() {
+                response.data.extend(data);
+            }
+
+            let next_uri = match page.next_uri {
+                Some(next_uri) => next_uri,
+                None => break,
+            };
+
+            if max_rows.is_some_and(|max_rows| response.data.len() >= max_rows) {
+                // Cancelling frees the cluster; failures here don't affect the result
+                let _ = self.client.delete(&next_uri).send().await;
+                break;
+            }
+
+            let mut request = self.client.get(&next_uri);
+            if let Some(password) = &self.password {
+                request = request.basic_auth(&self.username, Some(password));
+            }
+            page = tokio::select! {
+                page = self.send(request) => page?,
+                _ = cancellation.cancelled() => {
+                    if let Err(e) = self.client.delete(&next_uri).send().await {
+                        tracing::warn!("Failed to cancel Trino query {}: {}", page.id, e);
+                    }
+                    return Err(query_cancelled());
+                }
+            };
+        }
+
+        Ok(response)
+    }
+
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<TrinoQueryResults> {
+        let response = match request.send().await {
+            Ok(res) => res,
+            Err(e) => return Err(anyhow!(e.to_string())),
+        };
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Trino returned {}: {}", status, body.trim()));
+        }
+
+        match response.json::<TrinoQueryResults>().await {
+            Ok(res) => Ok(res),
+            Err(e) => Err(anyhow!(e.to_string())),
+        }
+    }
+}
@@ -1,103 +1,445 @@
-use anyhow::{anyhow, Error};
-use rand::Rng;
+//! In-process SSH tunnels to databases that are only reachable through a jump host.
+//!
+//! A tunnel listens on a local port and forwards each connection over a `direct-tcpip`
+//! channel of one SSH session. Tunnels are pooled by their configuration and reused across
+//! queries; the session sends keepalives and is reconnected when it drops. Tunnels that
+//! haven't forwarded a connection for a while are closed.
+
 use std::{
-    fs,
-    io::Write,
-    net::TcpListener,
-    process::{Child, Command},
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
 };
-use tempfile::NamedTempFile;
 
-pub fn establish_ssh_tunnel(
-    ssh_private_key: String,
-    jump_host: String,
-    ssh_username: String,
-    db_host: String,
-    db_port: String,
-) -> Result<(std::process::Child, u16, Vec<NamedTempFile>), Error> {
-    let mut temp_ssh_key = match NamedTempFile::new() {
-        Ok(f) => f,
-        Err(e) => {
-            tracing::error!("There was a problem while creating the temp file: {}", e);
-            return Err(anyhow!(e));
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use once_cell::sync::Lazy;
+use russh::{
+    client::{self, Handle, Msg},
+    Channel, Disconnect,
+};
+use russh_keys::{decode_secret_key, key::PublicKey};
+use sha2::{Digest, Sha256};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+use crate::credentials::Credential;
+
+const SSH_PORT: u16 = 22;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(30);
+// Missed keepalives before the session is considered dead and reconnected
+const KEEPALIVE_MAX: usize = 3;
+const IDLE_TUNNEL_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+static SSH_TUNNELS: Lazy<tokio::sync::Mutex<HashMap<String, Arc<SshTunnel>>>> =
+    Lazy::new(|| tokio::sync::Mutex::new(HashMap::new()));
+
+#[derive(Clone)]
+pub struct SshTunnelConfig {
+    pub jump_host: String,
+    pub ssh_username: String,
+    pub ssh_private_key: String,
+    /// Pinned `SHA256:` fingerprint of the jump host's key. Without one, the key seen on the
+    /// first connection is trusted for the life of the tunnel.
+    pub host_key_fingerprint: Option<String>,
+    pub db_host: String,
+    pub db_port: u16,
+}
+
+impl SshTunnelConfig {
+    /// The tunnel for a data source's credentials, or `None` if it's reached directly
+    pub fn from_credentials(
+        jump_host: &Option<String>,
+        ssh_username: &Option<String>,
+        ssh_private_key: &Option<String>,
+        host_key_fingerprint: &Option<String>,
+        db_host: &str,
+        db_port: u16,
+    ) -> Option<Self> {
+        match (jump_host, ssh_username, ssh_private_key) {
+            (Some(jump_host), Some(ssh_username), Some(ssh_private_key)) => Some(SshTunnelConfig {
+                jump_host: jump_host.clone(),
+                ssh_username: ssh_username.clone(),
+                ssh_private_key: ssh_private_key.clone(),
+                host_key_fingerprint: host_key_fingerprint.clone(),
+                db_host: db_host.to_string(),
+                db_port,
+            }),
+            _ => None,
         }
-    };
+    }
 
-    temp_ssh_key.write_all(ssh_private_key.as_bytes()).unwrap();
+    // Hashed so the private key isn't kept around as a map key; any credential change gets a
+    // new tunnel
+    fn pool_key(&self) -> String {
+        let mut hasher = Sha256::new();
+        for part in [
+            self.jump_host.as_str(),
+            self.ssh_username.as_str(),
+            self.ssh_private_key.as_str(),
+            self.host_key_fingerprint.as_deref().unwrap_or_default(),
+            self.db_host.as_str(),
+            &self.db_port.to_string(),
+        ] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+/// A local port forwarded to the database through the jump host. The tunnel stays open while
+/// it's pooled or anyone holds it.
+pub struct SshTunnel {
+    local_port: u16,
+    session: Arc<TunnelSession>,
+    listener_task: JoinHandle<()>,
+}
+
+impl SshTunnel {
+    pub fn local_port(&self) -> u16 {
+        self.local_port
+    }
+
+    async fn open(config: SshTunnelConfig) -> Result<Self> {
+        let session = Arc::new(TunnelSession::new(config));
+
+        // Connecting up front makes bad credentials or a changed host key fail the query,
+        // rather than the database driver's first connection
+        let handle = session.connect().await?;
+        *session.handle.lock().await = Some(handle);
 
-    let output = match Command::new("ssh-keyscan").arg(jump_host.clone()).output() {
-        Ok(o) => o,
+        let listener = TcpListener::bind(("127.0.0.1", 0))
+            .await
+            .map_err(|e| anyhow!("Error binding local port for SSH tunnel: {}", e))?;
+        let local_port = listener.local_addr()?.port();
+
+        let listener_task = tokio::spawn(accept_connections(listener, session.clone()));
+
+        Ok(SshTunnel {
+            local_port,
+            session,
+            listener_task,
+        })
+    }
+
+    fn is_idle(&self) -> bool {
+        self.session.active_connections.load(Ordering::SeqCst) == 0
+            && self.session.last_used.lock().unwrap().elapsed() > IDLE_TUNNEL_TIMEOUT
+    }
+}
+
+impl Drop for SshTunnel {
+    fn drop(&mut self) {
+        self.listener_task.abort();
+    }
+}
+
+/// Returns the pooled tunnel for the config, opening one if there isn't one yet
+pub async fn get_ssh_tunnel(config: SshTunnelConfig) -> Result<Arc<SshTunnel>> {
+    let key = config.pool_key();
+
+    {
+        let mut tunnels = SSH_TUNNELS.lock().await;
+        tunnels.retain(|_, tunnel| !tunnel.is_idle());
+        if let Some(tunnel) = tunnels.get(&key) {
+            tunnel.session.touch();
+            return Ok(tunnel.clone());
+        }
+    }
+
+    // Opened without holding the pool lock so a slow jump host doesn't block other tunnels
+    let tunnel = match SshTunnel::open(config.clone()).await {
+        Ok(tunnel) => Arc::new(tunnel),
         Err(e) => {
-            tracing::error!("There was a problem while running ssh-keyscan: {}", e);
-            return Err(anyhow!(e));
+            tracing::error!(
+                "There was an issue while establishing the ssh tunnel through {}: {}",
+                config.jump_host,
+                e
+            );
+            return Err(e);
         }
     };
 
-    let output_str = String::from_utf8_lossy(&output.stdout);
+    let mut tunnels = SSH_TUNNELS.lock().await;
+    // Another query may have opened the same tunnel in the meantime; keep the first one
+    Ok(tunnels.entry(key).or_insert(tunnel).clone())
+}
 
-    let mut temp_file = match NamedTempFile::new() {
-        Ok(f) => f,
-        Err(e) => {
-            tracing::error!("There was a problem while creating the temp file: {}", e);
-            return Err(anyhow!(e));
-        }
+/// Fills in the jump host's key fingerprint on SSH credentials that don't have one pinned
+/// yet, so later connections reject a different key
+pub async fn pin_ssh_host_key(credential: &mut Credential) -> Result<()> {
+    let (jump_host, host_key_fingerprint) = match credential {
+        Credential::Postgres(credential) => (
+            &credential.jump_host,
+            &mut credential.ssh_host_key_fingerprint,
+        ),
+        Credential::MySql(credential) => (
+            &credential.jump_host,
+            &mut credential.ssh_host_key_fingerprint,
+        ),
+        Credential::SqlServer(credential) => (
+            &credential.jump_host,
+            &mut credential.ssh_host_key_fingerprint,
+        ),
+        _ => return Ok(()),
     };
 
-    match temp_file.write_all(output_str.as_bytes()) {
-        Ok(_) => {}
-        Err(e) => {
-            tracing::error!("There was a problem while writing to the temp file: {}", e);
+    if host_key_fingerprint.is_none() {
+        if let Some(jump_host) = jump_host {
+            *host_key_fingerprint = Some(fetch_host_key_fingerprint(jump_host).await?);
         }
+    }
+
+    Ok(())
+}
+
+async fn fetch_host_key_fingerprint(jump_host: &str) -> Result<String> {
+    let observed = Arc::new(Mutex::new(None));
+    let handler = HostKeyCheck {
+        jump_host: jump_host.to_string(),
+        pinned: observed.clone(),
     };
 
-    let known_hosts_path = temp_file.path();
+    let handle = connect_ssh(jump_host, handler).await?;
+    let _ = handle.disconnect(Disconnect::ByApplication, "", "en").await;
 
-    let local_port = loop {
-        let port = rand::thread_rng().gen_range(1024..=65535);
-        let listener = TcpListener::bind(("localhost", port));
-        if listener.is_ok() {
-            break port;
+    let fingerprint = observed.lock().unwrap().clone();
+    fingerprint.ok_or_else(|| anyhow!("SSH jump host {} didn't present a host key", jump_host))
+}
+
+struct TunnelSession {
+    config: SshTunnelConfig,
+    // Starts as the stored pin; if there isn't one, holds the key seen on the first
+    // connection so reconnects can't be pointed at a different host
+    pinned_fingerprint: Arc<Mutex<Option<String>>>,
+    handle: tokio::sync::Mutex<Option<Handle<HostKeyCheck>>>,
+    active_connections: AtomicUsize,
+    last_used: Mutex<Instant>,
+}
+
+impl TunnelSession {
+    fn new(config: SshTunnelConfig) -> Self {
+        TunnelSession {
+            pinned_fingerprint: Arc::new(Mutex::new(config.host_key_fingerprint.clone())),
+            config,
+            handle: tokio::sync::Mutex::new(None),
+            active_connections: AtomicUsize::new(0),
+            last_used: Mutex::new(Instant::now()),
         }
-    };
+    }
 
-    let ssh_tunnel = match Command::new("ssh")
-        .arg("-T")
-        .arg("-i")
-        .arg(temp_ssh_key.path())
-        .arg("-L")
-        .arg(format!(
-            "{local_port}:{db_host}:{db_port}",
-            local_port = local_port.clone(),
-            db_host = db_host.clone(),
-            db_port = db_port.clone(),
-        ))
-        .arg("-o")
-        .arg(format!("UserKnownHostsFile={}", known_hosts_path.display()))
-        .arg(format!(
-            "{ssh_username}@{jump_host}",
-            ssh_username = ssh_username.clone(),
-            jump_host = jump_host.clone(),
-        ))
-        .spawn()
-    {
-        Ok(s) => s,
-        Err(e) => {
-            tracing::error!("There was a problem while spawning the ssh tunnel: {}", e);
-            return Err(anyhow!(e));
+    fn touch(&self) {
+        *self.last_used.lock().unwrap() = Instant::now();
+    }
+
+    async fn connect(&self) -> Result<Handle<HostKeyCheck>> {
+        let key_pair = decode_secret_key(&self.config.ssh_private_key, None)
+            .map_err(|e| anyhow!("Invalid SSH private key: {}", e))?;
+
+        let handler = HostKeyCheck {
+            jump_host: self.config.jump_host.clone(),
+            pinned: self.pinned_fingerprint.clone(),
+        };
+        let mut handle = connect_ssh(&self.config.jump_host, handler).await?;
+
+        let authenticated = handle
+            .authenticate_publickey(self.config.ssh_username.clone(), Arc::new(key_pair))
+            .await
+            .map_err(|e| anyhow!("Error authenticating with SSH jump host: {}", e))?;
+        if !authenticated {
+            return Err(anyhow!(
+                "SSH jump host {} rejected the key for user {}",
+                self.config.jump_host,
+                self.config.ssh_username
+            ));
         }
-    };
 
-    Ok((ssh_tunnel, local_port, vec![temp_ssh_key, temp_file]))
+        Ok(handle)
+    }
+
+    async fn open_channel(&self) -> Result<Channel<Msg>> {
+        let mut handle = self.handle.lock().await;
+
+        if let Some(session) = handle.as_ref().filter(|session| !session.is_closed()) {
+            match self.open_direct_tcpip(session).await {
+                Ok(channel) => return Ok(channel),
+                Err(e) => {
+                    tracing::warn!(
+                        "SSH session to {} failed to open a channel, reconnecting: {}",
+                        self.config.jump_host,
+                        e
+                    );
+                }
+            }
+        }
+
+        let session = self.connect().await?;
+        let channel = self.open_direct_tcpip(&session).await?;
+        *handle = Some(session);
+
+        Ok(channel)
+    }
+
+    async fn open_direct_tcpip(&self, session: &Handle<HostKeyCheck>) -> Result<Channel<Msg>> {
+        session
+            .channel_open_direct_tcpip(
+                self.config.db_host.clone(),
+                self.config.db_port as u32,
+                "127.0.0.1",
+                0,
+            )
+            .await
+            .map_err(|e| {
+                anyhow!(
+                    "Error forwarding to {}:{} through SSH: {}",
+                    self.config.db_host,
+                    self.config.db_port,
+                    e
+                )
+            })
+    }
+}
+
+async fn connect_ssh(jump_host: &str, handler: HostKeyCheck) -> Result<Handle<HostKeyCheck>> {
+    let config = Arc::new(client::Config {
+        keepalive_interval: Some(KEEPALIVE_INTERVAL),
+        keepalive_max: KEEPALIVE_MAX,
+        ..Default::default()
+    });
+
+    match tokio::time::timeout(
+        CONNECT_TIMEOUT,
+        client::connect(config, (jump_host, SSH_PORT), handler),
+    )
+    .await
+    {
+        Ok(Ok(handle)) => Ok(handle),
+        Ok(Err(e)) => Err(anyhow!(
+            "Error connecting to SSH jump host {}: {}",
+            jump_host,
+            e
+        )),
+        Err(_) => Err(anyhow!(
+            "Timed out connecting to SSH jump host {}",
+            jump_host
+        )),
+    }
 }
 
-pub async fn kill_ssh_tunnel(
-    ssh_tunnel: &mut Child,
-    temp_files: Vec<NamedTempFile>,
-) -> Result<(), Error> {
-    let _ = ssh_tunnel.kill();
-    for file in temp_files {
-        let _ = file.close();
+async fn accept_connections(listener: TcpListener, session: Arc<TunnelSession>) {
+    loop {
+        let socket = match listener.accept().await {
+            Ok((socket, _)) => socket,
+            Err(e) => {
+                tracing::warn!("SSH tunnel failed to accept a connection: {}", e);
+                continue;
+            }
+        };
+
+        let session = session.clone();
+        tokio::spawn(async move {
+            session.active_connections.fetch_add(1, Ordering::SeqCst);
+            session.touch();
+
+            if let Err(e) = forward_connection(socket, &session).await {
+                tracing::warn!(
+                    "SSH tunnel connection to {}:{} failed: {}",
+                    session.config.db_host,
+                    session.config.db_port,
+                    e
+                );
+            }
+
+            session.touch();
+            session.active_connections.fetch_sub(1, Ordering::SeqCst);
+        });
     }
+}
+
+async fn forward_connection(mut socket: TcpStream, session: &TunnelSession) -> Result<()> {
+    let channel = session.open_channel().await?;
+    let mut stream = channel.into_stream();
+
+    tokio::io::copy_bidirectional(&mut socket, &mut stream).await?;
 
     Ok(())
 }
+
+struct HostKeyCheck {
+    jump_host: String,
+    pinned: Arc<Mutex<Option<String>>>,
+}
+
+#[async_trait]
+impl client::Handler for HostKeyCheck {
+    type Error = russh::Error;
+
+    async fn check_server_key(
+        &mut self,
+        server_public_key: &PublicKey,
+    ) -> Result<bool, Self::Error> {
+        let fingerprint = format!("SHA256:{}", server_public_key.fingerprint());
+        let mut pinned = self.pinned.lock().unwrap();
+
+        match pinned.as_deref() {
+            Some(expected) if fingerprints_match(expected, &fingerprint) => Ok(true),
+            Some(expected) => {
+                tracing::error!(
+                    "Host key of SSH jump host {} doesn't match the pinned key: expected {}, got {}",
+                    self.jump_host,
+                    expected,
+                    fingerprint
+                );
+                Ok(false)
+            }
+            None => {
+                tracing::warn!(
+                    "No host key pinned for SSH jump host {}, trusting {}",
+                    self.jump_host,
+                    fingerprint
+                );
+                *pinned = Some(fingerprint);
+                Ok(true)
+            }
+        }
+    }
+}
+
+// Accepts fingerprints as `ssh-keygen -lf` prints them or without the prefix and padding
+fn fingerprints_match(expected: &str, actual: &str) -> bool {
+    fn normalize(fingerprint: &str) -> &str {
+        fingerprint
+            .trim()
+            .trim_start_matches("SHA256:")
+            .trim_end_matches('=')
+    }
+
+    normalize(expected) == normalize(actual)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fingerprints_match() {
+        let fingerprint = "SHA256:nThbg6kXUpJWGl7E1IGOCspRomTxdCARLviKw6E5SY8";
+
+        assert!(fingerprints_match(fingerprint, fingerprint));
+        assert!(fingerprints_match(
+            "nThbg6kXUpJWGl7E1IGOCspRomTxdCARLviKw6E5SY8=",
+            fingerprint
+        ));
+        assert!(!fingerprints_match(
+            "SHA256:AAAAg6kXUpJWGl7E1IGOCspRomTxdCARLviKw6E5SY8",
+            fingerprint
+        ));
+    }
+}
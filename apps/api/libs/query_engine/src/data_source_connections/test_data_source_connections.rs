@@ -1,10 +1,15 @@
 use crate::credentials::Credential;
 use crate::data_source_connections::{
-    get_bigquery_client::get_bigquery_client, get_databricks_client::get_databricks_client,
-    get_mysql_connection::get_mysql_connection, get_postgres_connection::get_postgres_connection,
-    get_redshift_connection::get_redshift_connection, get_snowflake_client::get_snowflake_client,
+    get_bigquery_client::get_bigquery_client, get_clickhouse_client::get_clickhouse_client,
+    get_databricks_client::get_databricks_client,
+    get_duckdb_connection::get_duckdb_connection, get_mysql_connection::get_mysql_connection,
+    get_postgres_connection::get_postgres_connection,
+    get_redshift_connection::get_redshift_connection,
+    get_snowflake_client::{get_snowflake_client, SnowflakeClient},
     get_sql_server_connection::get_sql_server_connection,
+    get_sqlite_connection::get_sqlite_connection, get_trino_client::get_trino_client,
 };
+use crate::data_source_query_routes::query_cancellation::CancellationToken;
 use anyhow::{anyhow, Result};
 
 pub async fn test_data_source_connection(credential: &Credential) -> Result<()> {
@@ -23,7 +28,7 @@ pub async fn test_data_source_connection(credential: &Credential) -> Result<()>
                 Err(e) => return Err(anyhow!("Error getting databricks client: {:?}", e)),
             };
 
-            match client.query("SELECT 1".to_string()).await {
+            match client.query("SELECT 1".to_string(), &CancellationToken::new()).await {
                 Ok(_) => (),
                 Err(e) => return Err(anyhow!("Error executing test query: {:?}", e)),
             }
@@ -56,11 +61,29 @@ pub async fn test_data_source_connection(credential: &Credential) -> Result<()>
             Ok(())
         }
         Credential::Snowflake(credential) => {
-            match get_snowflake_client(credential).await {
+            credential.validate()?;
+
+            let client = match get_snowflake_client(credential).await {
                 Ok(client) => client,
                 Err(e) => return Err(anyhow!("Error getting snowflake client: {:?}", e)),
             };
 
+            // Creating the client doesn't log in, so run a query to check the credentials
+            match client {
+                SnowflakeClient::Connector(mut client) => {
+                    let result = client.exec("SELECT 1").await;
+                    let _ = client.close_session().await;
+                    if let Err(e) = result {
+                        return Err(anyhow!("Error executing test query: {:?}", e));
+                    }
+                }
+                SnowflakeClient::SqlApi(client) => {
+                    if let Err(e) = client.query("SELECT 1", 1, &CancellationToken::new()).await {
+                        return Err(anyhow!("Error executing test query: {:?}", e));
+                    }
+                }
+            }
+
             Ok(())
         }
         Credential::SqlServer(credential) => {
@@ -69,6 +92,60 @@ pub async fn test_data_source_connection(credential: &Credential) -> Result<()>
                 Err(e) => return Err(anyhow!("Error getting sqlserver client: {:?}", e)),
             };
 
+            Ok(())
+        }
+        Credential::DuckDb(credential) => {
+            let connection = match get_duckdb_connection(credential).await {
+                Ok(connection) => connection,
+                Err(e) => return Err(anyhow!("Error getting duckdb connection: {:?}", e)),
+            };
+
+            // Also catches external files that were registered but can't be read
+            match tokio::task::spawn_blocking(move || connection.execute_batch("SELECT 1")).await {
+                Ok(Ok(_)) => (),
+                Ok(Err(e)) => return Err(anyhow!("Error executing test query: {:?}", e)),
+                Err(e) => return Err(anyhow!("Error executing test query: {:?}", e)),
+            }
+
+            Ok(())
+        }
+        Credential::Sqlite(credential) => {
+            let pool = match get_sqlite_connection(credential).await {
+                Ok(pool) => pool,
+                Err(e) => return Err(anyhow!("Error getting sqlite connection: {:?}", e)),
+            };
+
+            match sqlx::query("SELECT 1").execute(&pool).await {
+                Ok(_) => (),
+                Err(e) => return Err(anyhow!("Error executing test query: {:?}", e)),
+            }
+
+            Ok(())
+        }
+        Credential::ClickHouse(credential) => {
+            let client = match get_clickhouse_client(credential).await {
+                Ok(client) => client,
+                Err(e) => return Err(anyhow!("Error getting clickhouse client: {:?}", e)),
+            };
+
+            match client.query("SELECT 1".to_string(), None, &CancellationToken::new()).await {
+                Ok(_) => (),
+                Err(e) => return Err(anyhow!("Error executing test query: {:?}", e)),
+            }
+
+            Ok(())
+        }
+        Credential::Trino(credential) => {
+            let client = match get_trino_client(credential).await {
+                Ok(client) => client,
+                Err(e) => return Err(anyhow!("Error getting trino client: {:?}", e)),
+            };
+
+            match client.query("SELECT 1".to_string(), None, &CancellationToken::new()).await {
+                Ok(_) => (),
+                Err(e) => return Err(anyhow!("Error executing test query: {:?}", e)),
+            }
+
             Ok(())
         }
     }
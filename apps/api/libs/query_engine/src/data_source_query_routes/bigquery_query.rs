@@ -2,18 +2,30 @@ use indexmap::IndexMap;
 
 use anyhow::{anyhow, Result};
 use chrono::{NaiveDate, NaiveTime};
-use gcp_bigquery_client::{model::query_request::QueryRequest, Client};
+use gcp_bigquery_client::{
+    model::{
+        get_query_results_parameters::GetQueryResultsParameters, query_request::QueryRequest,
+        table_row::TableRow, table_schema::TableSchema,
+    },
+    Client,
+};
 use serde_json::{Number, Value};
 
 
 use crate::data_types::DataType;
 
+use super::query_cancellation::{query_cancelled, CancellationToken};
+
 pub async fn bigquery_query(
     client: Client,
     project_id: String,
     query: String,
     limit: Option<i64>,
+    max_bytes_billed: Option<i64>,
+    cancellation: CancellationToken,
 ) -> Result<Vec<IndexMap<String, DataType>>> {
+    let max_results = limit.unwrap_or(5000).min(i32::MAX as i64) as i32;
+
     let query_request = QueryRequest {
         connection_properties: None,
         default_dataset: None,
@@ -21,14 +33,16 @@ pub async fn bigquery_query(
         kind: None,
         labels: None,
         location: None,
-        max_results: Some(limit.unwrap_or(5000).min(i32::MAX as i64) as i32),
-        maximum_bytes_billed: None,
+        max_results: Some(max_results),
+        // BigQuery fails the job before it runs if it would bill more
+        maximum_bytes_billed: max_bytes_billed.map(|bytes| bytes.to_string()),
         parameter_mode: None,
         preserve_nulls: None,
         query,
         query_parameters: None,
         request_id: None,
-        timeout_ms: Some(120000),
+        // Long-running jobs are polled below so they can be cancelled while they run
+        timeout_ms: Some(JOB_POLL_TIMEOUT_MS),
         use_legacy_sql: false,
         use_query_cache: None,
         format_options: None,
@@ -42,13 +56,61 @@ pub async fn bigquery_query(
         }
     };
 
-    let fields = result.schema
-        .as_ref()
+    if result.job_complete.unwrap_or(true) {
+        return parse_rows(result.schema.as_ref(), result.rows.as_ref());
+    }
+
+    let job_reference = result
+        .job_reference
+        .ok_or_else(|| anyhow!("BigQuery did not return a job reference"))?;
+    let job_id = job_reference
+        .job_id
+        .ok_or_else(|| anyhow!("BigQuery did not return a job id"))?;
+    let location = job_reference.location;
+
+    loop {
+        let parameters = GetQueryResultsParameters {
+            location: location.clone(),
+            max_results: Some(max_results),
+            timeout_ms: Some(JOB_POLL_TIMEOUT_MS),
+            ..Default::default()
+        };
+
+        let results = tokio::select! {
+            biased;
+            _ = cancellation.cancelled() => {
+                if let Err(e) = client
+                    .job()
+                    .cancel_job(project_id.as_str(), &job_id, location.as_deref())
+                    .await
+                {
+                    tracing::warn!("Failed to cancel BigQuery job {}: {}", job_id, e);
+                }
+                return Err(query_cancelled());
+            }
+            results = client.job().get_query_results(project_id.as_str(), &job_id, parameters) => {
+                results.map_err(|e| anyhow!("Failed to fetch BigQuery job results: {}", e))?
+            }
+        };
+
+        if results.job_complete.unwrap_or(false) {
+            return parse_rows(results.schema.as_ref(), results.rows.as_ref());
+        }
+    }
+}
+
+// How long each request waits on the job before returning to check for cancellation
+const JOB_POLL_TIMEOUT_MS: i32 = 10_000;
+
+fn parse_rows(
+    schema: Option<&TableSchema>,
+    rows: Option<&Vec<TableRow>>,
+) -> Result<Vec<IndexMap<String, DataType>>> {
+    let fields = schema
         .and_then(|schema| schema.fields.as_ref())
         .ok_or_else(|| anyhow!("No schema found in response"))?;
 
-    let typed_rows = result.rows
-        .as_ref()
+    let typed_rows = rows
         .map(|rows| {
             rows.iter()
                 .map(|row| {
@@ -0,0 +1,147 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use indexmap::IndexMap;
+
+use anyhow::{anyhow, Error};
+use serde_json::Value;
+
+use crate::{
+    data_source_connections::get_clickhouse_client::ClickHouse, data_types::DataType,
+};
+
+use super::query_cancellation::CancellationToken;
+
+pub async fn clickhouse_query(
+    clickhouse_client: ClickHouse,
+    query: String,
+    limit: Option<i64>,
+    cancellation: CancellationToken,
+) -> Result<Vec<IndexMap<std::string::String, DataType>>, Error> {
+    // Get the limit value, defaulting to 5000 if not specified
+    let default_limit = 5000;
+    let limit_value = limit.unwrap_or(default_limit) as usize;
+
+    // The server stops producing rows at the limit, so no LIMIT is appended
+    let results = match clickhouse_client.query(query, Some(limit_value), &cancellation).await {
+        Ok(results) => results,
+        Err(e) => {
+            tracing::error!("Error executing ClickHouse query: {}", e);
+            return Err(anyhow!(e.to_string()));
+        }
+    };
+
+    let mut result: Vec<IndexMap<String, DataType>> = Vec::with_capacity(limit_value);
+
+    for row in results.data {
+        // Stop processing if we've reached the limit
+        if result.len() >= limit_value {
+            break;
+        }
+
+        let mut row_map: IndexMap<String, DataType> = IndexMap::with_capacity(results.meta.len());
+
+        for (column, value) in results.meta.iter().zip(row) {
+            row_map.insert(column.name.clone(), convert_value(&column.type_name, value));
+        }
+
+        result.push(row_map);
+    }
+
+    Ok(result)
+}
+
+fn convert_value(type_name: &str, value: Value) -> DataType {
+    if value.is_null() {
+        return DataType::Null;
+    }
+
+    let base_type = unwrap_type_modifiers(type_name);
+    let type_head = base_type.split('(').next().unwrap_or(base_type);
+
+    match type_head {
+        "Bool" => DataType::Bool(value.as_bool()),
+        "Int8" | "Int16" | "UInt8" => DataType::Int2(value.as_i64().map(|v| v as i16)),
+        "Int32" | "UInt16" => DataType::Int4(value.as_i64().map(|v| v as i32)),
+        "Int64" | "UInt32" => DataType::Int8(value.as_i64()),
+        // Wider integers are sent as strings by the HTTP interface
+        "UInt64" | "Int128" | "UInt128" | "Int256" | "UInt256" => match value.as_i64() {
+            Some(v) => DataType::Int8(Some(v)),
+            None => DataType::Float8(value_as_f64(&value)),
+        },
+        "Float32" => DataType::Float4(value_as_f64(&value).map(|v| v as f32)),
+        "Float64" | "Decimal" | "Decimal32" | "Decimal64" | "Decimal128" | "Decimal256" => {
+            DataType::Float8(value_as_f64(&value))
+        }
+        "String" | "FixedString" | "Enum8" | "Enum16" | "IPv4" | "IPv6" => {
+            DataType::Text(value.as_str().map(|v| v.to_string()))
+        }
+        "UUID" => DataType::Uuid(value.as_str().and_then(|v| v.parse::<uuid::Uuid>().ok())),
+        "Date" | "Date32" => DataType::Date(
+            value
+                .as_str()
+                .and_then(|v| NaiveDate::parse_from_str(v, "%Y-%m-%d").ok()),
+        ),
+        "DateTime" | "DateTime64" => DataType::Timestamp(
+            value
+                .as_str()
+                .and_then(|v| NaiveDateTime::parse_from_str(v, "%Y-%m-%d %H:%M:%S%.f").ok()),
+        ),
+        "Array" | "Map" | "Tuple" | "Nested" | "JSON" | "Object" => DataType::Json(Some(value)),
+        _ => DataType::Unknown(Some(match value {
+            Value::String(v) => v,
+            other => other.to_string(),
+        })),
+    }
+}
+
+// `Nullable(LowCardinality(String))` -> `String`
+fn unwrap_type_modifiers(type_name: &str) -> &str {
+    let mut current = type_name.trim();
+    loop {
+        let inner = ["Nullable(", "LowCardinality("]
+            .iter()
+            .find_map(|prefix| current.strip_prefix(prefix))
+            .and_then(|rest| rest.strip_suffix(')'));
+        match inner {
+            Some(inner) => current = inner.trim(),
+            None => return current,
+        }
+    }
+}
+
+fn value_as_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse::<f64>().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_convert_clickhouse_values() {
+        assert_eq!(convert_value("UInt8", json!(3)), DataType::Int2(Some(3)));
+        assert_eq!(convert_value("Nullable(Int64)", Value::Null), DataType::Null);
+        assert_eq!(
+            convert_value("LowCardinality(Nullable(String))", json!("web")),
+            DataType::Text(Some("web".to_string()))
+        );
+        assert_eq!(
+            convert_value("UInt64", json!(u64::MAX.to_string())),
+            DataType::Float8(Some(u64::MAX as f64))
+        );
+        assert_eq!(convert_value("Decimal(18, 2)", json!(12.5)), DataType::Float8(Some(12.5)));
+        assert_eq!(
+            convert_value("DateTime64(3, 'UTC')", json!("2024-03-01 10:15:00.123")),
+            DataType::Timestamp(NaiveDateTime::parse_from_str("2024-03-01 10:15:00.123", "%Y-%m-%d %H:%M:%S%.f").ok())
+        );
+        assert_eq!(
+            convert_value("Date", json!("2024-03-01")),
+            DataType::Date(NaiveDate::from_ymd_opt(2024, 3, 1))
+        );
+        assert_eq!(convert_value("Array(String)", json!(["a"])), DataType::Json(Some(json!(["a"]))));
+    }
+}
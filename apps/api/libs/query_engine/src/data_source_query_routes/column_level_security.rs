@@ -0,0 +1,160 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{anyhow, Result};
+use dataset_security::{get_restricted_columns, RestrictedColumn};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use sql_analyzer::{apply_column_masks as apply_masks, ColumnMask};
+use uuid::Uuid;
+
+use database::{
+    enums::{ColumnPolicyAction, DataSourceType},
+    pool::get_pg_pool,
+    schema::datasets,
+};
+
+/// Column masks a user's queries on one data source are rewritten with, keyed by table name
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ColumnMasks {
+    table_masks: BTreeMap<String, BTreeMap<String, ColumnMask>>,
+}
+
+impl ColumnMasks {
+    pub fn is_empty(&self) -> bool {
+        self.table_masks.is_empty()
+    }
+}
+
+/// Resolves the column policies on a data source's datasets that apply to a user.
+///
+/// Masked SQL differs from the original, so masks don't need their own cache fingerprint:
+/// the rewritten query already keys the result cache.
+pub async fn get_column_masks(user_id: &Uuid, data_source_id: &Uuid) -> Result<ColumnMasks> {
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .map_err(|e| anyhow!("Failed to get database connection: {}", e))?;
+
+    let dataset_ids = datasets::table
+        .filter(datasets::data_source_id.eq(data_source_id))
+        .filter(datasets::deleted_at.is_null())
+        .select(datasets::id)
+        .load::<Uuid>(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch data source datasets: {}", e))?;
+    drop(conn);
+
+    let restricted = get_restricted_columns(user_id, &dataset_ids)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch column policies: {}", e))?;
+
+    Ok(build_column_masks(restricted))
+}
+
+/// Rejects queries that touch denied columns and masks the rest of the protected columns
+pub(crate) async fn apply_column_masks(
+    sql: &str,
+    column_masks: &ColumnMasks,
+    data_source_type: &DataSourceType,
+) -> Result<String> {
+    if column_masks.is_empty() {
+        return Ok(sql.to_string());
+    }
+
+    let table_masks: HashMap<String, HashMap<String, ColumnMask>> = column_masks
+        .table_masks
+        .iter()
+        .map(|(table, columns)| {
+            let columns = columns
+                .iter()
+                .map(|(column, mask)| (column.clone(), *mask))
+                .collect();
+            (table.clone(), columns)
+        })
+        .collect();
+
+    apply_masks(sql.to_string(), table_masks, data_source_type.to_str())
+        .await
+        .map_err(|e| anyhow!("Failed to apply column-level security: {}", e))
+}
+
+// Datasets that share a table name share masks, so the most restrictive one wins
+fn build_column_masks(restricted: Vec<RestrictedColumn>) -> ColumnMasks {
+    let mut table_masks: BTreeMap<String, BTreeMap<String, ColumnMask>> = BTreeMap::new();
+    for column in restricted {
+        let mask = column_mask(column.action);
+        table_masks
+            .entry(column.table_name)
+            .or_default()
+            .entry(column.column_name)
+            .and_modify(|current| *current = (*current).max(mask))
+            .or_insert(mask);
+    }
+    ColumnMasks { table_masks }
+}
+
+fn column_mask(action: ColumnPolicyAction) -> ColumnMask {
+    match action {
+        ColumnPolicyAction::Partial => ColumnMask::Partial,
+        ColumnPolicyAction::Hash => ColumnMask::Hash,
+        ColumnPolicyAction::Null => ColumnMask::Null,
+        ColumnPolicyAction::Deny => ColumnMask::Deny,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn restricted(table: &str, column: &str, action: ColumnPolicyAction) -> RestrictedColumn {
+        RestrictedColumn {
+            dataset_id: Uuid::new_v4(),
+            table_name: table.to_string(),
+            column_name: column.to_string(),
+            action,
+        }
+    }
+
+    #[test]
+    fn test_most_restrictive_mask_wins_across_datasets() {
+        let masks = build_column_masks(vec![
+            restricted("users", "email", ColumnPolicyAction::Partial),
+            restricted("users", "email", ColumnPolicyAction::Null),
+            restricted("users", "phone", ColumnPolicyAction::Hash),
+        ]);
+
+        let users = &masks.table_masks["users"];
+        assert_eq!(users["email"], ColumnMask::Null);
+        assert_eq!(users["phone"], ColumnMask::Hash);
+        assert!(build_column_masks(Vec::new()).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_denied_columns_reject_the_query() {
+        let masks = build_column_masks(vec![
+            restricted("users", "ssn", ColumnPolicyAction::Deny),
+            restricted("users", "email", ColumnPolicyAction::Hash),
+        ]);
+
+        let masked = apply_column_masks(
+            "SELECT u.id, u.email FROM public.users u",
+            &masks,
+            &DataSourceType::Postgres,
+        )
+        .await
+        .unwrap();
+        assert!(
+            masked.contains("MD5(CAST(u.email AS VARCHAR)) AS email"),
+            "{}",
+            masked
+        );
+
+        assert!(apply_column_masks(
+            "SELECT u.id FROM public.users u WHERE u.ssn IS NOT NULL",
+            &masks,
+            &DataSourceType::Postgres,
+        )
+        .await
+        .is_err());
+    }
+}
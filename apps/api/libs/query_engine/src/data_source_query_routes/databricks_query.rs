@@ -7,17 +7,20 @@ use crate::{
     data_source_connections::get_databricks_client::Databricks, data_types::DataType,
 };
 
+use super::query_cancellation::CancellationToken;
+
 pub async fn databricks_query(
     databricks_client: Databricks,
     query: String,
     limit: Option<i64>,
+    cancellation: CancellationToken,
 ) -> Result<Vec<IndexMap<std::string::String, DataType>>, Error> {
     // Get the limit value, defaulting to 5000 if not specified
     let default_limit = 5000;
     let limit_value = limit.unwrap_or(default_limit) as usize;
     
     // Execute the query without appending a LIMIT
-    let results = match databricks_client.query(query).await {
+    let results = match databricks_client.query(query, &cancellation).await {
         Ok(results) => results,
         Err(e) => {
             tracing::error!("Error executing Databricks query: {}", e);
@@ -29,12 +32,15 @@ pub async fn databricks_query(
     let mut result: Vec<IndexMap<String, DataType>> = Vec::with_capacity(limit_value);
 
     // Get rows from results
-    let rows = match results.result.data_array {
+    let rows = match results.result.and_then(|result| result.data_array) {
         Some(rows) => rows,
         None => return Ok(Vec::new()),
     };
 
-    let columns = results.manifest.schema.columns;
+    let columns = match results.manifest {
+        Some(manifest) => manifest.schema.columns,
+        None => return Err(anyhow!("Databricks returned rows without a schema")),
+    };
 
     // Process rows with optimized type conversions
     for row in rows {
@@ -0,0 +1,185 @@
+use chrono::{DateTime, NaiveDate, NaiveTime};
+use duckdb::types::{TimeUnit, Value};
+use duckdb::Connection;
+use indexmap::IndexMap;
+
+use anyhow::{anyhow, Error};
+
+use crate::data_types::DataType;
+
+use super::query_cancellation::{query_cancelled, CancellationToken};
+
+pub async fn duckdb_query(
+    connection: Connection,
+    query: String,
+    limit: Option<i64>,
+    cancellation: CancellationToken,
+) -> Result<Vec<IndexMap<std::string::String, DataType>>, Error> {
+    // Get the limit value, defaulting to 5000 if not specified
+    let default_limit = 5000;
+    let limit_value = limit.unwrap_or(default_limit) as usize;
+
+    let interrupt_handle = connection.interrupt_handle();
+
+    // DuckDB executes synchronously, so keep it off the async runtime
+    let task = tokio::task::spawn_blocking(move || run_query(&connection, &query, limit_value));
+
+    tokio::select! {
+        result = task => result.map_err(|e| anyhow!("DuckDB query task failed: {}", e))?,
+        _ = cancellation.cancelled() => {
+            interrupt_handle.interrupt();
+            Err(query_cancelled())
+        }
+    }
+}
+
+fn run_query(
+    connection: &Connection,
+    query: &str,
+    limit_value: usize,
+) -> Result<Vec<IndexMap<String, DataType>>, Error> {
+    let mut statement = connection.prepare(query)?;
+    let mut rows = statement.query([])?;
+
+    // Column names are only known once the statement has executed
+    let column_names = rows
+        .as_ref()
+        .map(|statement| statement.column_names())
+        .unwrap_or_default();
+
+    let mut result: Vec<IndexMap<String, DataType>> = Vec::with_capacity(limit_value);
+
+    while let Some(row) = rows.next()? {
+        let mut row_map: IndexMap<String, DataType> = IndexMap::with_capacity(column_names.len());
+
+        for (i, column_name) in column_names.iter().enumerate() {
+            let value = row.get::<_, Value>(i).unwrap_or(Value::Null);
+            row_map.insert(column_name.to_string(), convert_value(value));
+        }
+
+        result.push(row_map);
+
+        // Stop processing if we've reached the limit
+        if result.len() >= limit_value {
+            break;
+        }
+    }
+
+    Ok(result)
+}
+
+fn convert_value(value: Value) -> DataType {
+    match value {
+        Value::Null => DataType::Null,
+        Value::Boolean(v) => DataType::Bool(Some(v)),
+        Value::TinyInt(v) => DataType::Int2(Some(v as i16)),
+        Value::SmallInt(v) => DataType::Int2(Some(v)),
+        Value::Int(v) => DataType::Int4(Some(v)),
+        Value::BigInt(v) => DataType::Int8(Some(v)),
+        Value::UTinyInt(v) => DataType::Int2(Some(v as i16)),
+        Value::USmallInt(v) => DataType::Int4(Some(v as i32)),
+        Value::UInt(v) => DataType::Int8(Some(v as i64)),
+        // Values outside the i64 range lose precision rather than failing the query
+        Value::HugeInt(v) => i64::try_from(v)
+            .map(|v| DataType::Int8(Some(v)))
+            .unwrap_or(DataType::Float8(Some(v as f64))),
+        Value::UBigInt(v) => i64::try_from(v)
+            .map(|v| DataType::Int8(Some(v)))
+            .unwrap_or(DataType::Float8(Some(v as f64))),
+        Value::Float(v) => DataType::Float4(Some(v)),
+        Value::Double(v) => DataType::Float8(Some(v)),
+        Value::Decimal(v) => DataType::Float8(v.to_string().parse::<f64>().ok()),
+        Value::Text(v) => DataType::Text(Some(v)),
+        Value::Enum(v) => DataType::Text(Some(v)),
+        Value::Blob(v) => DataType::Bytea(Some(v)),
+        Value::Date32(days) => DataType::Date(
+            NaiveDate::from_ymd_opt(1970, 1, 1)
+                .and_then(|epoch| epoch.checked_add_signed(chrono::Duration::days(days as i64))),
+        ),
+        Value::Timestamp(unit, v) => DataType::Timestamp(
+            DateTime::from_timestamp_micros(to_micros(unit, v)).map(|ts| ts.naive_utc()),
+        ),
+        Value::Time64(unit, v) => {
+            let micros = to_micros(unit, v);
+            DataType::Time(NaiveTime::from_num_seconds_from_midnight_opt(
+                (micros / 1_000_000) as u32,
+                ((micros % 1_000_000) * 1_000) as u32,
+            ))
+        }
+        other => DataType::Unknown(Some(format!("{:?}", other))),
+    }
+}
+
+fn to_micros(unit: TimeUnit, value: i64) -> i64 {
+    match unit {
+        TimeUnit::Second => value * 1_000_000,
+        TimeUnit::Millisecond => value * 1_000,
+        TimeUnit::Microsecond => value,
+        TimeUnit::Nanosecond => value / 1_000,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_duckdb_query_converts_types() {
+        let connection = Connection::open_in_memory().unwrap();
+        let results = duckdb_query(
+            connection,
+            "SELECT 1::INTEGER AS id, 'a' AS name, 2.5::DOUBLE AS amount, DATE '2024-03-01' AS day, TRUE AS active, NULL AS missing"
+                .to_string(),
+            None,
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(results.len(), 1);
+        let row = &results[0];
+        assert_eq!(row["id"], DataType::Int4(Some(1)));
+        assert_eq!(row["name"], DataType::Text(Some("a".to_string())));
+        assert_eq!(row["amount"], DataType::Float8(Some(2.5)));
+        assert_eq!(row["day"], DataType::Date(NaiveDate::from_ymd_opt(2024, 3, 1)));
+        assert_eq!(row["active"], DataType::Bool(Some(true)));
+        assert_eq!(row["missing"], DataType::Null);
+    }
+
+    #[tokio::test]
+    async fn test_duckdb_query_respects_limit() {
+        let connection = Connection::open_in_memory().unwrap();
+        let results = duckdb_query(
+            connection,
+            "SELECT * FROM range(100)".to_string(),
+            Some(10),
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(results.len(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_duckdb_query_cancellation_interrupts_query() {
+        let connection = Connection::open_in_memory().unwrap();
+        let cancellation = CancellationToken::new();
+
+        let trigger = cancellation.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            trigger.cancel();
+        });
+
+        let result = duckdb_query(
+            connection,
+            "SELECT count(*) FROM range(10000000000) t(i) WHERE i % 7 = 3".to_string(),
+            None,
+            cancellation,
+        )
+        .await;
+
+        assert_eq!(result.unwrap_err().to_string(), "Query was cancelled");
+    }
+}
@@ -1,9 +1,23 @@
 pub mod bigquery_query;
+pub mod clickhouse_query;
+pub mod column_level_security;
 pub mod databricks_query;
+pub mod duckdb_query;
 pub mod mysql_query;
 pub mod postgres_query;
+pub mod query_audit;
+pub mod query_cache;
+pub mod query_cancellation;
+pub mod query_complexity;
 pub mod query_engine;
+pub mod query_pagination;
+pub mod query_quotas;
+pub mod query_stream;
 pub mod redshift_query;
+pub mod row_level_security;
+pub mod semantic_query;
 pub mod snowflake_query;
 pub mod sql_server_query;
+pub mod sqlite_query;
+pub mod trino_query;
 mod security_utils;
\ No newline at end of file
@@ -3,57 +3,92 @@ use indexmap::IndexMap;
 
 use anyhow::Error;
 use futures::TryStreamExt;
-use sqlx::{Column, MySql, Pool, Row};
+use sqlx::{mysql::MySqlRow, Column, Connection, MySql, MySqlConnection, Pool, Row};
 
 use crate::data_types::DataType;
 
+use super::{
+    query_cancellation::{query_cancelled, CancellationToken},
+    query_stream::RowBatchSender,
+};
+
 pub async fn mysql_query(
     pool: Pool<MySql>,
     query: String,
     limit: Option<i64>,
+    cancellation: CancellationToken,
 ) -> Result<Vec<IndexMap<std::string::String, DataType>>, Error> {
     // Get the limit value, defaulting to 5000 if not specified
     let default_limit = 5000;
     let limit_value = limit.unwrap_or(default_limit) as usize;
-    
+
+    // The connection id identifies the running query to KILL QUERY
+    let mut conn = pool.acquire().await?;
+    let connection_id: u64 = sqlx::query_scalar("SELECT CONNECTION_ID()")
+        .fetch_one(&mut *conn)
+        .await?;
+
+    tokio::select! {
+        result = fetch_rows(&mut conn, &query, limit_value) => result,
+        _ = cancellation.cancelled() => {
+            kill_query(&pool, connection_id).await;
+            Err(query_cancelled())
+        }
+    }
+}
+
+/// Same as `mysql_query`, but rows are handed to `batches` as they're read instead of being
+/// collected
+pub(crate) async fn mysql_stream(
+    pool: Pool<MySql>,
+    query: String,
+    batches: &mut RowBatchSender,
+    cancellation: CancellationToken,
+) -> Result<(), Error> {
+    let mut conn = pool.acquire().await?;
+    let connection_id: u64 = sqlx::query_scalar("SELECT CONNECTION_ID()")
+        .fetch_one(&mut *conn)
+        .await?;
+
+    tokio::select! {
+        result = stream_rows(&mut conn, &query, batches) => result,
+        _ = cancellation.cancelled() => {
+            kill_query(&pool, connection_id).await;
+            Err(query_cancelled())
+        }
+    }
+}
+
+// KILL QUERY stops the statement but leaves the connection open
+async fn kill_query(pool: &Pool<MySql>, connection_id: u64) {
+    let options = pool.connect_options();
+    let result = async {
+        let mut conn = MySqlConnection::connect_with(&options).await?;
+        sqlx::raw_sql(&format!("KILL QUERY {}", connection_id))
+            .execute(&mut conn)
+            .await
+    }
+    .await;
+
+    if let Err(e) = result {
+        tracing::warn!("Failed to kill MySQL query on connection {}: {}", connection_id, e);
+    }
+}
+
+async fn fetch_rows(
+    conn: &mut MySqlConnection,
+    query: &str,
+    limit_value: usize,
+) -> Result<Vec<IndexMap<String, DataType>>, Error> {
     // Create query stream without appending LIMIT
-    let mut stream = sqlx::query(&query).fetch(&pool);
+    let mut stream = sqlx::query(query).fetch(conn);
 
     // Pre-allocate result vector with estimated capacity to reduce allocations
     let mut result: Vec<IndexMap<String, DataType>> = Vec::with_capacity(limit_value);
 
     // Process all rows without spawning tasks per row
     while let Some(row) = stream.try_next().await? {
-        let mut row_map: IndexMap<String, DataType> = IndexMap::with_capacity(row.len());
-
-        for (i, column) in row.columns().iter().enumerate() {
-            let column_name = column.name();
-            let type_info = column.type_info().clone().to_string();
-
-            let column_value = match type_info.as_str() {
-                "BOOL" | "BOOLEAN" => DataType::Bool(row.try_get::<bool, _>(i).ok()),
-                "BIT" => DataType::Bytea(row.try_get::<Vec<u8>, _>(i).ok()),
-                "CHAR" => DataType::Char(row.try_get::<String, _>(i).ok()),
-                "BIGINT" => DataType::Int8(row.try_get::<i64, _>(i).ok()),
-                "MEDIUMINT" | "INT" | "INTEGER" => DataType::Int4(row.try_get::<i32, _>(i).ok()),
-                "TINYINT" | "SMALLINT" => DataType::Int2(row.try_get::<i16, _>(i).ok()),
-                "TEXT" | "VARCHAR" => DataType::Text(row.try_get::<String, _>(i).ok()),
-                "FLOAT" => DataType::Float4(row.try_get::<f32, _>(i).ok()),
-                "DOUBLE" => DataType::Float8(row.try_get::<f64, _>(i).ok()),
-                "DECIMAL" | "DEC" => DataType::Float8(row.try_get::<f64, _>(i).ok()),
-                "UUID" => DataType::Uuid(row.try_get::<uuid::Uuid, _>(i).ok()),
-                "TIMESTAMP" | "DATETIME" => DataType::Timestamp(row.try_get::<chrono::NaiveDateTime, _>(i).ok()),
-                "DATE" => DataType::Date(row.try_get::<chrono::NaiveDate, _>(i).ok()),
-                "TIME" => DataType::Time(row.try_get::<chrono::NaiveTime, _>(i).ok()),
-                "TIMESTAMPTZ" => DataType::Timestamptz(row.try_get::<chrono::DateTime<Utc>, _>(i).ok()),
-                "JSON" | "JSONB" => DataType::Json(row.try_get::<serde_json::Value, _>(i).ok()),
-                _ => DataType::Unknown(row.try_get::<String, _>(i).ok()),
-            };
-
-            row_map.insert(column_name.to_string(), column_value);
-        }
-
-        result.push(row_map);
+        result.push(convert_row(&row));
         
         // Stop processing if we've reached the limit
         if result.len() >= limit_value {
@@ -63,3 +98,54 @@ pub async fn mysql_query(
     
     Ok(result)
 }
+
+async fn stream_rows(
+    conn: &mut MySqlConnection,
+    query: &str,
+    batches: &mut RowBatchSender,
+) -> Result<(), Error> {
+    let mut stream = sqlx::query(query).fetch(conn);
+
+    while let Some(row) = stream.try_next().await? {
+        batches.push(convert_row(&row)).await?;
+
+        if batches.is_full() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn convert_row(row: &MySqlRow) -> IndexMap<String, DataType> {
+    let mut row_map: IndexMap<String, DataType> = IndexMap::with_capacity(row.len());
+
+    for (i, column) in row.columns().iter().enumerate() {
+        let column_name = column.name();
+        let type_info = column.type_info().clone().to_string();
+
+        let column_value = match type_info.as_str() {
+            "BOOL" | "BOOLEAN" => DataType::Bool(row.try_get::<bool, _>(i).ok()),
+            "BIT" => DataType::Bytea(row.try_get::<Vec<u8>, _>(i).ok()),
+            "CHAR" => DataType::Char(row.try_get::<String, _>(i).ok()),
+            "BIGINT" => DataType::Int8(row.try_get::<i64, _>(i).ok()),
+            "MEDIUMINT" | "INT" | "INTEGER" => DataType::Int4(row.try_get::<i32, _>(i).ok()),
+            "TINYINT" | "SMALLINT" => DataType::Int2(row.try_get::<i16, _>(i).ok()),
+            "TEXT" | "VARCHAR" => DataType::Text(row.try_get::<String, _>(i).ok()),
+            "FLOAT" => DataType::Float4(row.try_get::<f32, _>(i).ok()),
+            "DOUBLE" => DataType::Float8(row.try_get::<f64, _>(i).ok()),
+            "DECIMAL" | "DEC" => DataType::Float8(row.try_get::<f64, _>(i).ok()),
+            "UUID" => DataType::Uuid(row.try_get::<uuid::Uuid, _>(i).ok()),
+            "TIMESTAMP" | "DATETIME" => DataType::Timestamp(row.try_get::<chrono::NaiveDateTime, _>(i).ok()),
+            "DATE" => DataType::Date(row.try_get::<chrono::NaiveDate, _>(i).ok()),
+            "TIME" => DataType::Time(row.try_get::<chrono::NaiveTime, _>(i).ok()),
+            "TIMESTAMPTZ" => DataType::Timestamptz(row.try_get::<chrono::DateTime<Utc>, _>(i).ok()),
+            "JSON" | "JSONB" => DataType::Json(row.try_get::<serde_json::Value, _>(i).ok()),
+            _ => DataType::Unknown(row.try_get::<String, _>(i).ok()),
+        };
+
+        row_map.insert(column_name.to_string(), column_value);
+    }
+
+    row_map
+}
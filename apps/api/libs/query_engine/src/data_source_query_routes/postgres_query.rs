@@ -5,9 +5,14 @@ use futures::TryStreamExt;
 use indexmap::IndexMap;
 
 use anyhow::{Error, Result};
-use sqlx::{Column, Pool, Postgres, Row};
+use sqlx::{postgres::PgRow, Column, Connection, PgConnection, Pool, Postgres, Row};
 
 use crate::data_types::DataType;
+
+use super::{
+    query_cancellation::{query_cancelled, CancellationToken},
+    query_stream::RowBatchSender,
+};
 use sqlparser::ast::{Expr, Ident, ObjectName, VisitMut, VisitorMut};
 use sqlparser::dialect::PostgreSqlDialect;
 use sqlparser::parser::Parser;
@@ -57,67 +62,97 @@ pub async fn postgres_query(
     pg_pool: Pool<Postgres>,
     query: String,
     limit: Option<i64>,
+    cancellation: CancellationToken,
 ) -> Result<Vec<IndexMap<std::string::String, DataType>>, Error> {
-    // Parse the query and quote identifiers
+    let formatted_sql = quote_identifiers(&query)?;
+
+    // Get the limit value, defaulting to 5000 if not specified
+    let default_limit = 5000;
+    let limit_value = limit.unwrap_or(default_limit) as usize;
+
+    // The backend pid identifies the running query to pg_cancel_backend
+    let mut conn = pg_pool.acquire().await?;
+    let pid: i32 = sqlx::query_scalar("SELECT pg_backend_pid()")
+        .fetch_one(&mut *conn)
+        .await?;
+
+    tokio::select! {
+        result = fetch_rows(&mut conn, &formatted_sql, limit_value) => result,
+        _ = cancellation.cancelled() => {
+            cancel_backend(&pg_pool, pid).await;
+            Err(query_cancelled())
+        }
+    }
+}
+
+/// Same as `postgres_query`, but rows are handed to `batches` as they're read instead of
+/// being collected
+pub(crate) async fn postgres_stream(
+    pg_pool: Pool<Postgres>,
+    query: String,
+    batches: &mut RowBatchSender,
+    cancellation: CancellationToken,
+) -> Result<(), Error> {
+    let formatted_sql = quote_identifiers(&query)?;
+
+    let mut conn = pg_pool.acquire().await?;
+    let pid: i32 = sqlx::query_scalar("SELECT pg_backend_pid()")
+        .fetch_one(&mut *conn)
+        .await?;
+
+    tokio::select! {
+        result = stream_rows(&mut conn, &formatted_sql, batches) => result,
+        _ = cancellation.cancelled() => {
+            cancel_backend(&pg_pool, pid).await;
+            Err(query_cancelled())
+        }
+    }
+}
+
+// Parse the query and quote identifiers
+fn quote_identifiers(query: &str) -> Result<String, Error> {
     let dialect = PostgreSqlDialect {};
-    let mut ast = Parser::parse_sql(&dialect, &query)?;
+    let mut ast = Parser::parse_sql(&dialect, query)?;
 
     let mut column_visitor = QuotedIdentifierColumnVisitor;
     ast.visit(&mut column_visitor);
     let mut table_visitor = QuotedIdentifierTableVisitor;
     ast.visit(&mut table_visitor);
 
-    let formatted_sql = ast[0].to_string();
+    Ok(ast[0].to_string())
+}
 
-    // Get the limit value, defaulting to 5000 if not specified
-    let default_limit = 5000;
-    let limit_value = limit.unwrap_or(default_limit) as usize;
-    
+/// Cancels the query running on backend `pid` from a separate connection
+pub(crate) async fn cancel_backend(pg_pool: &Pool<Postgres>, pid: i32) {
+    let options = pg_pool.connect_options();
+    let result = async {
+        let mut conn = PgConnection::connect_with(&options).await?;
+        sqlx::query("SELECT pg_cancel_backend($1)")
+            .bind(pid)
+            .execute(&mut conn)
+            .await
+    }
+    .await;
+
+    if let Err(e) = result {
+        tracing::warn!("Failed to cancel Postgres backend {}: {}", pid, e);
+    }
+}
+
+async fn fetch_rows(
+    conn: &mut PgConnection,
+    sql: &str,
+    limit_value: usize,
+) -> Result<Vec<IndexMap<String, DataType>>, Error> {
     // Create query stream without appending LIMIT
-    let mut stream = sqlx::raw_sql(&formatted_sql).fetch(&pg_pool);
+    let mut stream = sqlx::raw_sql(sql).fetch(conn);
 
     // Pre-allocate result vector with estimated capacity to reduce allocations
     let mut result: Vec<IndexMap<String, DataType>> = Vec::with_capacity(limit_value);
 
     // Process all rows without spawning tasks per row
     while let Some(row) = stream.try_next().await? {
-        let mut row_map: IndexMap<String, DataType> = IndexMap::with_capacity(row.len());
-
-        for (i, column) in row.columns().iter().enumerate() {
-            let column_name = column.name();
-            let type_info = column.type_info().clone().to_string();
-            let column_value = match type_info.as_str() {
-                "BOOL" => DataType::Bool(row.try_get::<bool, _>(i).ok()),
-                "BYTEA" => DataType::Bytea(row.try_get::<Vec<u8>, _>(i).ok()),
-                "CHAR" => DataType::Char(row.try_get::<String, _>(i).ok()),
-                "INT8" => DataType::Int8(row.try_get::<i64, _>(i).ok()),
-                "INT4" => DataType::Int4(row.try_get::<i32, _>(i).ok()),
-                "INT2" => DataType::Int2(row.try_get::<i16, _>(i).ok()),
-                "TEXT" | "VARCHAR" | "USER-DEFINED" => DataType::Text(row.try_get::<String, _>(i).ok()),
-                "FLOAT4" => DataType::Float4(row.try_get::<f32, _>(i).ok()),
-                "FLOAT8" => DataType::Float8(row.try_get::<f64, _>(i).ok()),
-                "NUMERIC" => {
-                    DataType::Float8(row.try_get(i).ok().and_then(
-                        |v: sqlx::types::BigDecimal| v.to_string().parse::<f64>().ok(),
-                    ))
-                }
-                "UUID" => DataType::Uuid(row.try_get::<uuid::Uuid, _>(i).ok()),
-                "TIMESTAMP" => {
-                    DataType::Timestamp(row.try_get::<chrono::NaiveDateTime, _>(i).ok())
-                }
-                "DATE" => DataType::Date(row.try_get::<chrono::NaiveDate, _>(i).ok()),
-                "TIME" => DataType::Time(row.try_get::<chrono::NaiveTime, _>(i).ok()),
-                "TIMESTAMPTZ" => {
-                    DataType::Timestamptz(row.try_get::<chrono::DateTime<Utc>, _>(i).ok())
-                }
-                "JSON" | "JSONB" => DataType::Json(row.try_get::<serde_json::Value, _>(i).ok()),
-                _ => DataType::Unknown(row.try_get::<String, _>(i).ok()),
-            };
-
-            row_map.insert(column_name.to_string(), column_value);
-        }
-
-        result.push(row_map);
+        result.push(convert_row(&row));
         
         // Stop processing if we've reached the limit
         if result.len() >= limit_value {
@@ -127,3 +162,61 @@ pub async fn postgres_query(
 
     Ok(result)
 }
+
+async fn stream_rows(
+    conn: &mut PgConnection,
+    sql: &str,
+    batches: &mut RowBatchSender,
+) -> Result<(), Error> {
+    let mut stream = sqlx::raw_sql(sql).fetch(conn);
+
+    while let Some(row) = stream.try_next().await? {
+        batches.push(convert_row(&row)).await?;
+
+        if batches.is_full() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn convert_row(row: &PgRow) -> IndexMap<String, DataType> {
+    let mut row_map: IndexMap<String, DataType> = IndexMap::with_capacity(row.len());
+
+    for (i, column) in row.columns().iter().enumerate() {
+        let column_name = column.name();
+        let type_info = column.type_info().clone().to_string();
+        let column_value = match type_info.as_str() {
+            "BOOL" => DataType::Bool(row.try_get::<bool, _>(i).ok()),
+            "BYTEA" => DataType::Bytea(row.try_get::<Vec<u8>, _>(i).ok()),
+            "CHAR" => DataType::Char(row.try_get::<String, _>(i).ok()),
+            "INT8" => DataType::Int8(row.try_get::<i64, _>(i).ok()),
+            "INT4" => DataType::Int4(row.try_get::<i32, _>(i).ok()),
+            "INT2" => DataType::Int2(row.try_get::<i16, _>(i).ok()),
+            "TEXT" | "VARCHAR" | "USER-DEFINED" => DataType::Text(row.try_get::<String, _>(i).ok()),
+            "FLOAT4" => DataType::Float4(row.try_get::<f32, _>(i).ok()),
+            "FLOAT8" => DataType::Float8(row.try_get::<f64, _>(i).ok()),
+            "NUMERIC" => {
+                DataType::Float8(row.try_get(i).ok().and_then(
+                    |v: sqlx::types::BigDecimal| v.to_string().parse::<f64>().ok(),
+                ))
+            }
+            "UUID" => DataType::Uuid(row.try_get::<uuid::Uuid, _>(i).ok()),
+            "TIMESTAMP" => {
+                DataType::Timestamp(row.try_get::<chrono::NaiveDateTime, _>(i).ok())
+            }
+            "DATE" => DataType::Date(row.try_get::<chrono::NaiveDate, _>(i).ok()),
+            "TIME" => DataType::Time(row.try_get::<chrono::NaiveTime, _>(i).ok()),
+            "TIMESTAMPTZ" => {
+                DataType::Timestamptz(row.try_get::<chrono::DateTime<Utc>, _>(i).ok())
+            }
+            "JSON" | "JSONB" => DataType::Json(row.try_get::<serde_json::Value, _>(i).ok()),
+            _ => DataType::Unknown(row.try_get::<String, _>(i).ok()),
+        };
+
+        row_map.insert(column_name.to_string(), column_value);
+    }
+
+    row_map
+}
@@ -0,0 +1,192 @@
+use std::future::Future;
+use std::time::Instant;
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use database::{
+    enums::{AssetType, AuditEventType, AuditSurface, DataSourceType},
+    helpers::audit_logs::record_audit_log,
+    models::AuditLog,
+    pool::get_pg_pool,
+    schema::data_sources,
+};
+
+use super::{query_cache::normalize_sql, query_engine::QueryResult, query_pagination::QueryPage};
+
+/// Who ran an audited query and where it came from
+#[derive(Debug, Clone, Copy)]
+pub struct QueryAuditContext {
+    pub user_id: Option<Uuid>, // None for anonymous public-link access
+    pub surface: AuditSurface,
+    /// Asset the query was run for, e.g. the metric whose data was requested
+    pub asset: Option<(Uuid, AssetType)>,
+}
+
+impl QueryAuditContext {
+    pub fn new(user_id: Uuid, surface: AuditSurface) -> Self {
+        Self {
+            user_id: Some(user_id),
+            surface,
+            asset: None,
+        }
+    }
+
+    pub fn with_asset(mut self, asset_id: Uuid, asset_type: AssetType) -> Self {
+        self.asset = Some((asset_id, asset_type));
+        self
+    }
+}
+
+/// Query results whose row count goes in the audit log
+pub trait AuditedRows {
+    fn audited_row_count(&self) -> i64;
+}
+
+impl AuditedRows for QueryResult {
+    fn audited_row_count(&self) -> i64 {
+        self.data.len() as i64
+    }
+}
+
+impl AuditedRows for (QueryResult, bool) {
+    fn audited_row_count(&self) -> i64 {
+        self.0.audited_row_count()
+    }
+}
+
+impl AuditedRows for QueryPage {
+    fn audited_row_count(&self) -> i64 {
+        self.data.len() as i64
+    }
+}
+
+/// Runs a query and appends it to the audit log with its duration and row count, or the
+/// error it failed with. `sql` is the query as submitted, before any security rewrites.
+/// Audit failures are logged and never change the query's outcome.
+pub async fn audit_query<T, F>(
+    context: &QueryAuditContext,
+    data_source_id: &Uuid,
+    sql: &str,
+    query: F,
+) -> Result<T>
+where
+    T: AuditedRows,
+    F: Future<Output = Result<T>>,
+{
+    let started_at = Instant::now();
+    let result = query.await;
+    let duration_ms = started_at.elapsed().as_millis() as i64;
+
+    let (row_count, error) = match &result {
+        Ok(rows) => (Some(rows.audited_row_count()), None),
+        Err(e) => (None, Some(e.to_string())),
+    };
+
+    if let Err(e) = record_query(context, data_source_id, sql, row_count, duration_ms, error).await
+    {
+        tracing::error!(
+            "Failed to record audit log for query on data source {}: {}",
+            data_source_id,
+            e
+        );
+    }
+
+    result
+}
+
+/// Appends a streamed query to the audit log once its stream has ended, with the rows read and
+/// the error that ended it, if any. Queries that failed before streaming have no row count.
+/// Audit failures are logged and never change the query's outcome.
+pub async fn audit_streamed_query(
+    context: &QueryAuditContext,
+    data_source_id: &Uuid,
+    sql: &str,
+    started_at: Instant,
+    row_count: Option<i64>,
+    error: Option<String>,
+) {
+    let duration_ms = started_at.elapsed().as_millis() as i64;
+
+    if let Err(e) = record_query(context, data_source_id, sql, row_count, duration_ms, error).await
+    {
+        tracing::error!(
+            "Failed to record audit log for query on data source {}: {}",
+            data_source_id,
+            e
+        );
+    }
+}
+
+async fn record_query(
+    context: &QueryAuditContext,
+    data_source_id: &Uuid,
+    sql: &str,
+    row_count: Option<i64>,
+    duration_ms: i64,
+    error: Option<String>,
+) -> Result<()> {
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .map_err(|e| anyhow!("Failed to get database connection: {}", e))?;
+
+    let (data_source_type, organization_id) = data_sources::table
+        .filter(data_sources::id.eq(data_source_id))
+        .select((data_sources::type_, data_sources::organization_id))
+        .first::<(DataSourceType, Uuid)>(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch data source: {}", e))?;
+    drop(conn);
+
+    record_audit_log(&AuditLog {
+        id: Uuid::new_v4(),
+        organization_id,
+        user_id: context.user_id,
+        event_type: AuditEventType::Query,
+        surface: context.surface,
+        data_source_id: Some(*data_source_id),
+        query_text: Some(sql.to_string()),
+        query_hash: Some(query_hash(sql, data_source_type.to_str())),
+        row_count,
+        duration_ms: Some(duration_ms),
+        error,
+        asset_id: context.asset.map(|(asset_id, _)| asset_id),
+        asset_type: context.asset.map(|(_, asset_type)| asset_type),
+        details: serde_json::json!({}),
+        created_at: Utc::now(),
+    })
+    .await
+}
+
+// Identical queries hash the same regardless of formatting or keyword case
+fn query_hash(sql: &str, data_source_dialect: &str) -> String {
+    Sha256::digest(normalize_sql(sql, data_source_dialect).as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_hash_ignores_formatting() {
+        let hash = query_hash("SELECT id FROM users WHERE id = 1", "postgres");
+
+        assert_eq!(hash.len(), 64);
+        assert_eq!(
+            hash,
+            query_hash("select id\n  from users\n where id = 1;", "postgres")
+        );
+        assert_ne!(
+            hash,
+            query_hash("SELECT id FROM users WHERE id = 2", "postgres")
+        );
+    }
+}
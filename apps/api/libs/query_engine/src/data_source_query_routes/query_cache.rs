@@ -0,0 +1,420 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use indexmap::IndexMap;
+use redis::AsyncCommands;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use sql_analyzer::analysis::get_dialect;
+use sqlparser::parser::Parser;
+use tiberius::numeric::Decimal;
+use uuid::Uuid;
+
+use database::{
+    pool::{get_pg_pool, get_redis_pool},
+    schema::data_source_cache_settings,
+    types::data_metadata::DataMetadata,
+};
+
+use crate::data_types::DataType;
+
+use super::{
+    column_level_security::ColumnMasks,
+    query_cancellation::CancellationToken,
+    query_engine::{compute_data_metadata, prepare_query, route_to_query, QueryResult},
+    query_quotas::DEFAULT_ROW_LIMIT,
+    row_level_security::RowFilters,
+};
+
+// Larger results are served uncached rather than filling Redis
+const MAX_CACHED_RESULT_BYTES: usize = 5 * 1024 * 1024;
+
+/// Everything besides the SQL that decides whether two queries may share a cached result
+#[derive(Debug, Clone, Default)]
+pub struct QueryCacheContext {
+    /// Metric the query belongs to; its cached results are dropped when the metric changes
+    pub metric_id: Option<Uuid>,
+    /// Row filters applied to the query; users with different filters never share results
+    pub row_filters: RowFilters,
+    /// Column masks applied to the query. They change the SQL itself, which already keys the cache.
+    pub column_masks: ColumnMasks,
+}
+
+/// Same as `query_engine`, but serves repeated queries from the result cache. Returns whether
+/// the result came from the cache. Cache failures are logged and the query runs uncached.
+///
+/// Caching is off until a TTL is set in the data source's cache settings.
+pub async fn query_engine_cached(
+    data_source_id: &Uuid,
+    sql: &str,
+    limit: Option<i64>,
+    context: &QueryCacheContext,
+    cancellation: &CancellationToken,
+) -> Result<(QueryResult, bool)> {
+    // Column masks, row filters, the safety filter and the complexity policy apply to cached results too
+    let prepared = prepare_query(
+        data_source_id,
+        sql,
+        &context.row_filters,
+        &context.column_masks,
+    )
+    .await?;
+
+    let ttl_seconds = match get_cache_ttl(data_source_id).await {
+        Ok(ttl_seconds) => ttl_seconds,
+        Err(e) => {
+            tracing::warn!("Failed to load cache settings for data source {}: {}", data_source_id, e);
+            0
+        }
+    };
+
+    // Results are keyed on the rows the query actually returns: when the row quota changes, a
+    // result capped at the old quota must not be served
+    let effective_limit = prepared.quotas.cap_limit(limit);
+    let cache_key = (ttl_seconds > 0).then(|| {
+        let normalized_sql = normalize_sql(&prepared.sql, prepared.data_source_type.to_str());
+        cache_key(
+            data_source_id,
+            &normalized_sql,
+            effective_limit,
+            context.row_filters.fingerprint().as_deref(),
+        )
+    });
+
+    if let Some(cache_key) = &cache_key {
+        match read_cached_result(cache_key).await {
+            Ok(Some((data, metadata))) => {
+                tracing::debug!("Query cache hit for data source {}", data_source_id);
+                let mut warnings = prepared.warnings;
+                warnings.extend(
+                    prepared
+                        .quotas
+                        .truncation_warning(limit.unwrap_or(DEFAULT_ROW_LIMIT), data.len()),
+                );
+                return Ok((
+                    QueryResult {
+                        data,
+                        metadata,
+                        warnings,
+                    },
+                    true,
+                ));
+            }
+            Ok(None) => (),
+            Err(e) => tracing::warn!("Failed to read query cache: {}", e),
+        }
+    }
+
+    let data = match route_to_query(
+        data_source_id,
+        &prepared.sql,
+        limit,
+        &prepared.quotas,
+        cancellation,
+    )
+    .await
+    {
+        Ok(data) => data,
+        Err(e) => {
+            tracing::error!(
+                "There was an issue while querying the parent data source: {}",
+                e
+            );
+            return Err(anyhow!(e));
+        }
+    };
+    let metadata = compute_data_metadata(&data);
+
+    let mut warnings = prepared.warnings;
+    warnings.extend(
+        prepared
+            .quotas
+            .truncation_warning(limit.unwrap_or(DEFAULT_ROW_LIMIT), data.len()),
+    );
+
+    if let Some(cache_key) = &cache_key {
+        if let Err(e) =
+            write_cached_result(cache_key, &data, &metadata, ttl_seconds, context.metric_id).await
+        {
+            tracing::warn!("Failed to write query cache: {}", e);
+        }
+    }
+
+    Ok((
+        QueryResult {
+            data,
+            metadata,
+            warnings,
+        },
+        false,
+    ))
+}
+
+/// Drops every cached result recorded for a metric. Call whenever a new metric version is saved.
+pub async fn invalidate_metric_cache(metric_id: &Uuid) -> Result<()> {
+    let mut redis_conn = get_redis_pool()
+        .get()
+        .await
+        .map_err(|e| anyhow!("Error getting redis connection: {}", e))?;
+
+    let tag_key = metric_tag_key(metric_id);
+    let mut keys: Vec<String> = redis_conn
+        .smembers(&tag_key)
+        .await
+        .map_err(|e| anyhow!("Failed to read cached queries for metric: {}", e))?;
+    keys.push(tag_key);
+
+    redis_conn
+        .del::<_, ()>(keys)
+        .await
+        .map_err(|e| anyhow!("Failed to invalidate cached queries for metric: {}", e))?;
+
+    Ok(())
+}
+
+// Data sources without a cache settings row aren't cached
+async fn get_cache_ttl(data_source_id: &Uuid) -> Result<u64> {
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .map_err(|e| anyhow!("Failed to get database connection: {}", e))?;
+
+    let ttl_seconds = data_source_cache_settings::table
+        .filter(data_source_cache_settings::data_source_id.eq(data_source_id))
+        .select(data_source_cache_settings::ttl_seconds)
+        .first::<i32>(&mut conn)
+        .await
+        .optional()
+        .map_err(|e| anyhow!("Failed to fetch data source cache settings: {}", e))?;
+
+    Ok(ttl_seconds.map_or(0, |ttl| ttl.max(0) as u64))
+}
+
+async fn read_cached_result(
+    cache_key: &str,
+) -> Result<Option<(Vec<IndexMap<String, DataType>>, DataMetadata)>> {
+    let mut redis_conn = get_redis_pool()
+        .get()
+        .await
+        .map_err(|e| anyhow!("Error getting redis connection: {}", e))?;
+
+    let cached: Option<Vec<u8>> = redis_conn.get(cache_key).await?;
+    let cached = match cached {
+        Some(cached) => cached,
+        None => return Ok(None),
+    };
+
+    let entry: CacheEntry = serde_json::from_slice(&cached)?;
+    let data = entry
+        .data
+        .into_iter()
+        .map(|row| row.into_iter().map(|(k, v)| (k, v.0)).collect())
+        .collect();
+
+    Ok(Some((data, entry.metadata)))
+}
+
+async fn write_cached_result(
+    cache_key: &str,
+    data: &[IndexMap<String, DataType>],
+    metadata: &DataMetadata,
+    ttl_seconds: u64,
+    metric_id: Option<Uuid>,
+) -> Result<()> {
+    let encoded = serde_json::to_vec(&CacheEntryRef { data, metadata })?;
+    if encoded.len() > MAX_CACHED_RESULT_BYTES {
+        tracing::debug!("Query result of {} bytes is too large to cache", encoded.len());
+        return Ok(());
+    }
+
+    let mut redis_conn = get_redis_pool()
+        .get()
+        .await
+        .map_err(|e| anyhow!("Error getting redis connection: {}", e))?;
+
+    redis_conn
+        .set_ex::<_, _, ()>(cache_key, encoded, ttl_seconds)
+        .await?;
+
+    if let Some(metric_id) = metric_id {
+        let tag_key = metric_tag_key(&metric_id);
+        redis_conn.sadd::<_, _, ()>(&tag_key, cache_key).await?;
+        redis_conn
+            .expire::<_, ()>(&tag_key, ttl_seconds as i64)
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Renders SQL in a canonical form so formatting and keyword case don't split the cache.
+/// SQL the dialect can't parse falls back to collapsing whitespace.
+pub fn normalize_sql(sql: &str, data_source_dialect: &str) -> String {
+    match Parser::parse_sql(get_dialect(data_source_dialect), sql) {
+        Ok(statements) if !statements.is_empty() => statements
+            .iter()
+            .map(|statement| statement.to_string())
+            .collect::<Vec<_>>()
+            .join("; "),
+        _ => sql
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .trim_end_matches(';')
+            .to_string(),
+    }
+}
+
+fn cache_key(
+    data_source_id: &Uuid,
+    normalized_sql: &str,
+    limit: Option<i64>,
+    row_filter_context: Option<&str>,
+) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(normalized_sql.as_bytes());
+    hasher.update([0]);
+    hasher.update(limit.map(|l| l.to_string()).unwrap_or_default().as_bytes());
+    hasher.update([0]);
+    hasher.update(row_filter_context.unwrap_or_default().as_bytes());
+
+    let digest: String = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    format!("query_cache:{}:{}", data_source_id, digest)
+}
+
+fn metric_tag_key(metric_id: &Uuid) -> String {
+    format!("query_cache:metric:{}", metric_id)
+}
+
+#[derive(Deserialize)]
+struct CacheEntry {
+    data: Vec<IndexMap<String, CachedValue>>,
+    metadata: DataMetadata,
+}
+
+#[derive(Serialize)]
+struct CacheEntryRef<'a> {
+    #[serde(serialize_with = "serialize_rows")]
+    data: &'a [IndexMap<String, DataType>],
+    metadata: &'a DataMetadata,
+}
+
+fn serialize_rows<S: Serializer>(
+    rows: &&[IndexMap<String, DataType>],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_seq(rows.iter().map(CachedRowRef))
+}
+
+struct CachedRowRef<'a>(&'a IndexMap<String, DataType>);
+
+impl Serialize for CachedRowRef<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.0.iter().map(|(k, v)| (k, CachedValueRef(v))))
+    }
+}
+
+struct CachedValueRef<'a>(&'a DataType);
+
+impl Serialize for CachedValueRef<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        DataTypeDef::serialize(self.0, serializer)
+    }
+}
+
+struct CachedValue(DataType);
+
+impl<'de> Deserialize<'de> for CachedValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        DataTypeDef::deserialize(deserializer).map(CachedValue)
+    }
+}
+
+// `DataType` serializes untagged, which can't be read back into the same variants, so
+// cached values keep their variant name
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "DataType")]
+enum DataTypeDef {
+    Bool(Option<bool>),
+    Bytea(Option<Vec<u8>>),
+    Char(Option<String>),
+    Int8(Option<i64>),
+    Int4(Option<i32>),
+    Int2(Option<i16>),
+    Text(Option<String>),
+    Oid(Option<u32>),
+    Float4(Option<f32>),
+    Float8(Option<f64>),
+    Decimal(Option<Decimal>),
+    Uuid(Option<Uuid>),
+    Timestamp(Option<NaiveDateTime>),
+    Timestamptz(Option<DateTime<Utc>>),
+    Date(Option<NaiveDate>),
+    Time(Option<NaiveTime>),
+    Json(Option<Value>),
+    Unknown(Option<String>),
+    Null,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_sql_ignores_formatting() {
+        let a = normalize_sql("select id,\n  name from public.users\nwhere id = 1;", "postgres");
+        let b = normalize_sql("SELECT id, name FROM public.users WHERE id = 1", "postgres");
+        assert_eq!(a, b);
+
+        // Unparseable SQL still has its whitespace collapsed
+        assert_eq!(
+            normalize_sql("SELECT  id\nFROM users WHERE;", "postgres"),
+            "SELECT id FROM users WHERE"
+        );
+    }
+
+    #[test]
+    fn test_cache_key_separates_context() {
+        let data_source_id = Uuid::new_v4();
+        let sql = "SELECT id FROM public.users";
+
+        let base = cache_key(&data_source_id, sql, Some(5001), None);
+        assert_eq!(base, cache_key(&data_source_id, sql, Some(5001), None));
+        assert!(base.starts_with(&format!("query_cache:{}:", data_source_id)));
+
+        assert_ne!(base, cache_key(&Uuid::new_v4(), sql, Some(5001), None));
+        assert_ne!(base, cache_key(&data_source_id, sql, Some(100), None));
+        assert_ne!(base, cache_key(&data_source_id, sql, Some(5001), Some("region = 'EU'")));
+    }
+
+    #[test]
+    fn test_cached_values_keep_their_type() {
+        let mut row = IndexMap::new();
+        row.insert("price".to_string(), DataType::Float4(Some(1.5)));
+        row.insert("day".to_string(), DataType::Date(NaiveDate::from_ymd_opt(2024, 3, 1)));
+        row.insert("name".to_string(), DataType::Text(Some("a".to_string())));
+        row.insert("missing".to_string(), DataType::Null);
+        let data = vec![row];
+        let metadata = compute_data_metadata(&data);
+
+        let encoded = serde_json::to_vec(&CacheEntryRef {
+            data: &data,
+            metadata: &metadata,
+        })
+        .unwrap();
+        let entry: CacheEntry = serde_json::from_slice(&encoded).unwrap();
+        let decoded: Vec<IndexMap<String, DataType>> = entry
+            .data
+            .into_iter()
+            .map(|row| row.into_iter().map(|(k, v)| (k, v.0)).collect())
+            .collect();
+
+        assert_eq!(decoded, data);
+    }
+}
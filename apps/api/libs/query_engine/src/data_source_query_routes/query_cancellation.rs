@@ -0,0 +1,35 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Error, Result};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use uuid::Uuid;
+
+use database::{pool::get_pg_pool, schema::data_source_query_settings};
+
+pub use tokio_util::sync::CancellationToken;
+
+/// Error returned by a query route once its cancellation token fires
+pub(crate) fn query_cancelled() -> Error {
+    anyhow!("Query was cancelled")
+}
+
+/// Longest a single query on the data source may run, if a limit is configured
+pub(crate) async fn get_statement_timeout(data_source_id: &Uuid) -> Result<Option<Duration>> {
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .map_err(|e| anyhow!("Failed to get database connection: {}", e))?;
+
+    let timeout_seconds = data_source_query_settings::table
+        .filter(data_source_query_settings::data_source_id.eq(data_source_id))
+        .select(data_source_query_settings::statement_timeout_seconds)
+        .first::<i32>(&mut conn)
+        .await
+        .optional()
+        .map_err(|e| anyhow!("Failed to fetch data source query settings: {}", e))?;
+
+    Ok(timeout_seconds
+        .filter(|seconds| *seconds > 0)
+        .map(|seconds| Duration::from_secs(seconds as u64)))
+}
@@ -0,0 +1,190 @@
+use anyhow::{anyhow, Result};
+use database::{
+    enums::QueryPolicyEnforcement, models::QueryComplexityPolicy, pool::get_pg_pool,
+    schema::query_complexity_policies,
+};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use sql_analyzer::{analyze_query_complexity, QueryComplexityReport};
+use uuid::Uuid;
+
+/// Outcome of checking a query against its organization's complexity policy
+#[derive(Debug, Clone, PartialEq)]
+pub enum ComplexityCheck {
+    Allowed,
+    Warned(Vec<String>),
+    Rejected(Vec<String>),
+}
+
+/// Analyzes the query and applies the organization's complexity policy, if one is configured.
+///
+/// Queries that fail to parse for complexity analysis are allowed through; the safety filter
+/// has already validated them and the warehouse is the final authority on syntax.
+pub async fn check_query_complexity(
+    organization_id: &Uuid,
+    sql: &str,
+    data_source_dialect: &str,
+) -> Result<ComplexityCheck> {
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .map_err(|e| anyhow!("Failed to get database connection: {}", e))?;
+
+    let policy = query_complexity_policies::table
+        .filter(query_complexity_policies::organization_id.eq(organization_id))
+        .select(QueryComplexityPolicy::as_select())
+        .first::<QueryComplexityPolicy>(&mut conn)
+        .await
+        .optional()
+        .map_err(|e| anyhow!("Failed to fetch query complexity policy: {}", e))?;
+
+    let policy = match policy {
+        Some(policy) => policy,
+        None => return Ok(ComplexityCheck::Allowed),
+    };
+
+    let report = match analyze_query_complexity(sql.to_string(), data_source_dialect).await {
+        Ok(report) => report,
+        Err(e) => {
+            tracing::debug!("Skipping complexity check, query could not be analyzed: {}", e);
+            return Ok(ComplexityCheck::Allowed);
+        }
+    };
+
+    Ok(evaluate_policy(&report, &policy))
+}
+
+fn evaluate_policy(report: &QueryComplexityReport, policy: &QueryComplexityPolicy) -> ComplexityCheck {
+    let mut violations = Vec::new();
+
+    if let Some(max_score) = policy.max_score {
+        if report.score as i64 > max_score as i64 {
+            violations.push(format!(
+                "Query complexity score {} exceeds the limit of {}",
+                report.score, max_score
+            ));
+        }
+    }
+
+    if let Some(max_joins) = policy.max_joins {
+        if report.join_count as i64 > max_joins as i64 {
+            violations.push(format!(
+                "Query has {} joins, more than the limit of {}",
+                report.join_count, max_joins
+            ));
+        }
+    }
+
+    if let Some(max_depth) = policy.max_subquery_depth {
+        if report.max_subquery_depth as i64 > max_depth as i64 {
+            violations.push(format!(
+                "Query nests subqueries {} levels deep, more than the limit of {}",
+                report.max_subquery_depth, max_depth
+            ));
+        }
+    }
+
+    if policy.reject_cartesian_products {
+        if report.cross_join_count > 0 {
+            violations.push(format!(
+                "Query contains {} CROSS JOIN(s), which produce a cartesian product",
+                report.cross_join_count
+            ));
+        }
+        for missing in &report.missing_join_predicates {
+            violations.push(format!("Cartesian product: {}", missing));
+        }
+    }
+
+    for table in &report.unbounded_scans {
+        if is_large_table(table, &policy.large_tables) {
+            violations.push(format!(
+                "Table {} is scanned without a WHERE clause",
+                table
+            ));
+        }
+    }
+
+    if violations.is_empty() {
+        ComplexityCheck::Allowed
+    } else {
+        match policy.enforcement {
+            QueryPolicyEnforcement::Warn => ComplexityCheck::Warned(violations),
+            QueryPolicyEnforcement::Reject => ComplexityCheck::Rejected(violations),
+        }
+    }
+}
+
+// Large tables may be configured fully qualified or by bare name
+fn is_large_table(scanned: &str, large_tables: &[String]) -> bool {
+    let scanned = scanned.to_lowercase();
+    large_tables.iter().any(|large| {
+        let large = large.to_lowercase();
+        scanned == large || scanned.ends_with(&format!(".{}", large))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn policy(enforcement: QueryPolicyEnforcement) -> QueryComplexityPolicy {
+        QueryComplexityPolicy {
+            organization_id: Uuid::new_v4(),
+            max_score: Some(20),
+            max_joins: Some(3),
+            max_subquery_depth: None,
+            reject_cartesian_products: true,
+            large_tables: vec!["events".to_string()],
+            enforcement,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_cartesian_product_rejected() {
+        let report = QueryComplexityReport {
+            join_count: 1,
+            missing_join_predicates: vec!["c joined to o without a join condition".to_string()],
+            score: 11,
+            ..Default::default()
+        };
+
+        match evaluate_policy(&report, &policy(QueryPolicyEnforcement::Reject)) {
+            ComplexityCheck::Rejected(violations) => assert_eq!(violations.len(), 1),
+            other => panic!("Expected rejection, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_large_table_scan_warned() {
+        let report = QueryComplexityReport {
+            unbounded_scans: vec!["analytics.events".to_string(), "public.users".to_string()],
+            score: 4,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            evaluate_policy(&report, &policy(QueryPolicyEnforcement::Warn)),
+            ComplexityCheck::Warned(vec![
+                "Table analytics.events is scanned without a WHERE clause".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_within_limits_allowed() {
+        let report = QueryComplexityReport {
+            join_count: 2,
+            score: 2,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            evaluate_policy(&report, &policy(QueryPolicyEnforcement::Reject)),
+            ComplexityCheck::Allowed
+        );
+    }
+}
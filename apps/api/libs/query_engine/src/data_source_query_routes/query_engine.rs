@@ -7,12 +7,13 @@ use uuid::Uuid;
 use crate::{
     credentials::Credential,
     data_source_connections::{
-        get_bigquery_client::get_bigquery_client, get_databricks_client::get_databricks_client,
-        get_mysql_connection::get_mysql_connection,
+        get_bigquery_client::get_bigquery_client, get_clickhouse_client::get_clickhouse_client,
+        get_databricks_client::get_databricks_client, get_duckdb_connection::get_duckdb_connection, get_mysql_connection::get_mysql_connection,
         get_postgres_connection::get_postgres_connection,
         get_redshift_connection::get_redshift_connection,
         get_snowflake_client::get_snowflake_client,
-        get_sql_server_connection::get_sql_server_connection, ssh_tunneling::kill_ssh_tunnel,
+        get_sql_server_connection::get_sql_server_connection,
+        get_sqlite_connection::get_sqlite_connection, get_trino_client::get_trino_client,
     },
     data_types::DataType,
 };
@@ -28,10 +29,24 @@ use diesel::prelude::*;
 use diesel_async::RunQueryDsl;
 
 use super::{
-    bigquery_query::bigquery_query, databricks_query::databricks_query, mysql_query::mysql_query,
-    postgres_query::postgres_query, redshift_query::redshift_query,
+    bigquery_query::bigquery_query, clickhouse_query::clickhouse_query,
+    column_level_security::{apply_column_masks, get_column_masks, ColumnMasks},
+    databricks_query::databricks_query,
+    duckdb_query::duckdb_query, mysql_query::mysql_query,
+    postgres_query::postgres_query,
+    query_cancellation::{get_statement_timeout, CancellationToken},
+    query_complexity::{check_query_complexity, ComplexityCheck},
+    query_quotas::{
+        acquire_query_permit, get_query_quotas, is_bytes_limit_error, QueryQuotaError,
+        QueryQuotas, DEFAULT_ROW_LIMIT,
+    },
+    redshift_query::redshift_query,
+    row_level_security::{apply_row_filters, get_row_filters, RowFilters},
+    semantic_query::{
+        apply_semantic_query_policy, get_semantic_query_policy, prepare_semantic_query,
+    },
     security_utils::query_safety_filter_with_dialect, snowflake_query::{snowflake_query, ProcessingResult},
-    sql_server_query::sql_server_query,
+    sql_server_query::sql_server_query, sqlite_query::sqlite_query, trino_query::trino_query,
 };
 
 // Define a QueryResult structure to hold both results and metadata
@@ -39,36 +54,100 @@ use super::{
 pub struct QueryResult {
     pub data: Vec<IndexMap<String, DataType>>,
     pub metadata: DataMetadata,
+    pub warnings: Vec<String>, // Complexity policy violations that didn't block execution
 }
 
+/// Runs the query without row- or column-level security. Use `query_engine_for_user` for
+/// queries run on behalf of a user.
 pub async fn query_engine(
     data_source_id: &Uuid,
     sql: &str,
     limit: Option<i64>,
 ) -> Result<QueryResult> {
-    let corrected_sql = sql.to_owned();
+    query_engine_with_cancellation(
+        data_source_id,
+        sql,
+        limit,
+        &RowFilters::default(),
+        &ColumnMasks::default(),
+        CancellationToken::new(),
+    )
+    .await
+}
 
-    // Fetch the data source type from the database
-    let mut conn = get_pg_pool().get().await
-        .map_err(|e| anyhow!("Failed to get database connection: {}", e))?;
-    
-    let data_source_type = data_sources::table
-        .filter(data_sources::id.eq(data_source_id))
-        .select(data_sources::type_)
-        .first::<DataSourceType>(&mut conn)
-        .await
-        .map_err(|e| anyhow!("Failed to fetch data source type: {}", e))?;
-    
-    let data_source_dialect = data_source_type.to_str();
+/// Same as `query_engine`, but the query only sees the rows the user's row filter policies allow
+/// and the columns their column policies leave visible. Users their organization limits to
+/// semantic queries have the SQL validated and substituted as in `semantic_query_engine_for_user`.
+///
+/// Cancelling `cancellation`, e.g. when the request is dropped or the chat is stopped, stops the
+/// query on the warehouse.
+pub async fn query_engine_for_user(
+    user_id: &Uuid,
+    data_source_id: &Uuid,
+    sql: &str,
+    limit: Option<i64>,
+    cancellation: &CancellationToken,
+) -> Result<QueryResult> {
+    let sql = apply_semantic_query_policy(user_id, data_source_id, sql).await?;
+    run_for_user(user_id, data_source_id, &sql, limit, cancellation).await
+}
 
-    let secure_sql = corrected_sql.clone();
+/// Runs SQL written against the metrics and filters of the data source's deployed models. The
+/// query is validated in the mode the user's organization chose, its metrics and filters are
+/// substituted, and the result runs with the user's row- and column-level security.
+pub async fn semantic_query_engine_for_user(
+    user_id: &Uuid,
+    data_source_id: &Uuid,
+    sql: &str,
+    limit: Option<i64>,
+    cancellation: &CancellationToken,
+) -> Result<QueryResult> {
+    let policy = get_semantic_query_policy(user_id, data_source_id).await?;
+    let semantic_sql = prepare_semantic_query(data_source_id, sql, policy.mode).await?;
+    run_for_user(user_id, data_source_id, &semantic_sql, limit, cancellation).await
+}
 
-    // Use the dialect-aware security filter
-    if let Some(warning) = query_safety_filter_with_dialect(secure_sql.clone(), data_source_dialect).await { 
-        return Err(anyhow!(warning)) 
-    };
+async fn run_for_user(
+    user_id: &Uuid,
+    data_source_id: &Uuid,
+    sql: &str,
+    limit: Option<i64>,
+    cancellation: &CancellationToken,
+) -> Result<QueryResult> {
+    let row_filters = get_row_filters(user_id, data_source_id).await?;
+    let column_masks = get_column_masks(user_id, data_source_id).await?;
+    query_engine_with_cancellation(
+        data_source_id,
+        sql,
+        limit,
+        &row_filters,
+        &column_masks,
+        cancellation.clone(),
+    )
+    .await
+}
 
-    let results = match route_to_query(data_source_id, &secure_sql, limit).await {
+/// Same as `query_engine`, but cancelling `cancellation` stops the query on the warehouse.
+/// Dropping the returned future has the same effect.
+pub async fn query_engine_with_cancellation(
+    data_source_id: &Uuid,
+    sql: &str,
+    limit: Option<i64>,
+    row_filters: &RowFilters,
+    column_masks: &ColumnMasks,
+    cancellation: CancellationToken,
+) -> Result<QueryResult> {
+    let prepared = prepare_query(data_source_id, sql, row_filters, column_masks).await?;
+
+    let results = match route_to_query(
+        data_source_id,
+        &prepared.sql,
+        limit,
+        &prepared.quotas,
+        &cancellation,
+    )
+    .await
+    {
         Ok(results) => results,
         Err(e) => {
             tracing::error!(
@@ -81,146 +160,249 @@ pub async fn query_engine(
 
     // Compute metadata from results
     let metadata = compute_data_metadata(&results);
+
+    let mut warnings = prepared.warnings;
+    warnings.extend(
+        prepared
+            .quotas
+            .truncation_warning(limit.unwrap_or(DEFAULT_ROW_LIMIT), results.len()),
+    );
     
     // Return both results and metadata in the QueryResult structure
     Ok(QueryResult {
         data: results,
         metadata,
+        warnings,
+    })
+}
+
+/// A query rewritten through the user's column masks and row filters that passed the safety
+/// filter and complexity policy for its data source, with the quotas it runs under
+pub(crate) struct PreparedQuery {
+    pub sql: String,
+    pub data_source_type: DataSourceType,
+    pub warnings: Vec<String>,
+    pub quotas: QueryQuotas,
+}
+
+pub(crate) async fn prepare_query(
+    data_source_id: &Uuid,
+    sql: &str,
+    row_filters: &RowFilters,
+    column_masks: &ColumnMasks,
+) -> Result<PreparedQuery> {
+    // Fetch the data source type from the database
+    let mut conn = get_pg_pool().get().await
+        .map_err(|e| anyhow!("Failed to get database connection: {}", e))?;
+    
+    let (data_source_type, organization_id) = data_sources::table
+        .filter(data_sources::id.eq(data_source_id))
+        .select((data_sources::type_, data_sources::organization_id))
+        .first::<(DataSourceType, Uuid)>(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch data source type: {}", e))?;
+    
+    let data_source_dialect = data_source_type.to_str();
+
+    // Column masks apply to the user's own SQL, before row filter CTEs reference protected columns.
+    // Both go in first so every later check sees the query that will actually run.
+    let masked_sql = apply_column_masks(sql, column_masks, &data_source_type).await?;
+    let secure_sql = apply_row_filters(&masked_sql, row_filters, &data_source_type).await?;
+
+    // Use the dialect-aware security filter
+    if let Some(warning) = query_safety_filter_with_dialect(secure_sql.clone(), data_source_dialect).await { 
+        return Err(anyhow!(warning)) 
+    };
+
+    // Apply the organization's complexity policy before the query reaches the warehouse
+    let warnings = match check_query_complexity(&organization_id, &secure_sql, data_source_dialect).await? {
+        ComplexityCheck::Allowed => Vec::new(),
+        ComplexityCheck::Warned(violations) => {
+            tracing::warn!(
+                "Query on data source {} exceeds complexity policy: {}",
+                data_source_id,
+                violations.join("; ")
+            );
+            violations
+        }
+        ComplexityCheck::Rejected(violations) => {
+            return Err(anyhow!(
+                "Query rejected by complexity policy: {}",
+                violations.join("; ")
+            ));
+        }
+    };
+
+    let quotas = get_query_quotas(&organization_id, data_source_id).await?;
+
+    Ok(PreparedQuery {
+        sql: secure_sql,
+        data_source_type,
+        warnings,
+        quotas,
     })
 }
 
 // Consolidated metadata calculation function
-fn compute_data_metadata(data: &[IndexMap<String, DataType>]) -> DataMetadata {
-    if data.is_empty() {
-        return DataMetadata {
-            column_count: 0,
-            row_count: 0,
-            column_metadata: vec![],
-        };
+pub(crate) fn compute_data_metadata(data: &[IndexMap<String, DataType>]) -> DataMetadata {
+    let mut accumulator = DataMetadataAccumulator::default();
+    accumulator.update(data);
+    accumulator.finish()
+}
+
+/// Builds `DataMetadata` one batch of rows at a time, so streamed results never have to be
+/// held in memory at once. Feeding every row through `update` and calling `finish` gives the
+/// same metadata as computing it over the full result.
+#[derive(Debug, Default, Clone)]
+pub struct DataMetadataAccumulator {
+    row_count: i64,
+    columns: Vec<ColumnMetadataAccumulator>,
+}
+
+#[derive(Debug, Clone)]
+struct ColumnMetadataAccumulator {
+    name: String,
+    value_map: HashSet<String>,
+    min_value_numeric: Option<f64>,
+    max_value_numeric: Option<f64>,
+    min_value_str: Option<String>,
+    max_value_str: Option<String>,
+    determined_type: Option<(SimpleType, ColumnType)>,
+}
+
+impl DataMetadataAccumulator {
+    pub fn update(&mut self, rows: &[IndexMap<String, DataType>]) {
+        // Columns are taken from the first row seen
+        if self.columns.is_empty() {
+            if let Some(first_row) = rows.first() {
+                self.columns = first_row
+                    .keys()
+                    .map(|name| ColumnMetadataAccumulator::new(name.clone()))
+                    .collect();
+            }
+        }
+
+        for row in rows {
+            for column in &mut self.columns {
+                if let Some(value) = row.get(&column.name) {
+                    column.update(value);
+                }
+            }
+        }
+
+        self.row_count += rows.len() as i64;
     }
 
-    let first_row = &data[0];
-    let column_count = first_row.len() as i64;
-    let row_count = data.len() as i64;
-    let column_metadata = compute_column_metadata(data);
+    pub fn row_count(&self) -> i64 {
+        self.row_count
+    }
 
-    DataMetadata {
-        column_count,
-        row_count,
-        column_metadata,
+    pub fn finish(self) -> DataMetadata {
+        DataMetadata {
+            column_count: self.columns.len() as i64,
+            row_count: self.row_count,
+            column_metadata: self
+                .columns
+                .into_iter()
+                .map(ColumnMetadataAccumulator::finish)
+                .collect(),
+        }
     }
 }
 
-// Helper function for computing column metadata
-fn compute_column_metadata(data: &[IndexMap<String, DataType>]) -> Vec<ColumnMetaData> {
-    if data.is_empty() {
-        return vec![];
+impl ColumnMetadataAccumulator {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            value_map: HashSet::new(),
+            min_value_numeric: None,
+            max_value_numeric: None,
+            min_value_str: None,
+            max_value_str: None,
+            determined_type: None,
+        }
     }
 
-    let first_row = &data[0];
-    let columns: Vec<_> = first_row.keys().cloned().collect();
-
-    columns.iter().map(|column_name| {
-        let mut value_map = HashSet::new();
-        let mut min_value_numeric: Option<f64> = None; // Use specific name
-        let mut max_value_numeric: Option<f64> = None; // Use specific name
-        let mut min_value_str: Option<String> = None;
-        let mut max_value_str: Option<String> = None;
-        let mut determined_type: Option<(SimpleType, ColumnType)> = None;
-
-        for row in data {
-            if let Some(value) = row.get(column_name) {
-                // Track unique values (up to a reasonable limit)
-                if value_map.len() < 100 {
-                    value_map.insert(format!("{:?}", value)); // format! handles nulls acceptably
-                }
+    fn update(&mut self, value: &DataType) {
+        // Track unique values (up to a reasonable limit)
+        if self.value_map.len() < 100 {
+            self.value_map.insert(format!("{:?}", value)); // format! handles nulls acceptably
+        }
 
-                 // Determine type from first non-null value encountered
-                 if determined_type.is_none() {
-                     match value {
-                         // Check for non-null variants using matches! for conciseness
-                         DataType::Int2(Some(_)) | DataType::Int4(Some(_)) | DataType::Int8(Some(_)) |
-                         DataType::Float4(Some(_)) | DataType::Float8(Some(_)) | DataType::Text(Some(_)) |
-                         DataType::Bool(Some(_)) | DataType::Date(Some(_)) | DataType::Timestamp(Some(_)) |
-                         DataType::Timestamptz(Some(_)) | DataType::Json(Some(_)) | DataType::Uuid(Some(_)) |
-                          DataType::Decimal(Some(_)) | DataType::Time(Some(_)) => {
-                             determined_type = Some(determine_types(value));
-                         }
-                         // If it's a Null variant or Unknown, keep looking
-                         _ => {}
-                     }
-                 }
-
-                // Calculate min/max based on value's actual type in this row
-                match value {
-                    DataType::Int2(Some(v)) => {
-                        let n = *v as f64;
-                        min_value_numeric = Some(min_value_numeric.map_or(n, |min| min.min(n)));
-                        max_value_numeric = Some(max_value_numeric.map_or(n, |max| max.max(n)));
-                    }
-                    DataType::Int4(Some(v)) => {
-                        let n = *v as f64;
-                        min_value_numeric = Some(min_value_numeric.map_or(n, |min| min.min(n)));
-                        max_value_numeric = Some(max_value_numeric.map_or(n, |max| max.max(n)));
-                    }
-                    DataType::Int8(Some(v)) => {
-                        let n = *v as f64;
-                        min_value_numeric = Some(min_value_numeric.map_or(n, |min| min.min(n)));
-                        max_value_numeric = Some(max_value_numeric.map_or(n, |max| max.max(n)));
-                    }
-                    DataType::Float4(Some(v)) => {
-                        let n = *v as f64;
-                        min_value_numeric = Some(min_value_numeric.map_or(n, |min| min.min(n)));
-                        max_value_numeric = Some(max_value_numeric.map_or(n, |max| max.max(n)));
-                    }
-                    DataType::Float8(Some(v)) => {
-                        let n = *v as f64;
-                        min_value_numeric = Some(min_value_numeric.map_or(n, |min| min.min(n)));
-                        max_value_numeric = Some(max_value_numeric.map_or(n, |max| max.max(n)));
-                    }
-                    DataType::Date(Some(date)) => {
-                        update_date_min_max(&date.to_string(), &mut min_value_str, &mut max_value_str);
-                    }
-                    DataType::Timestamp(Some(ts)) => {
-                        update_date_min_max(&ts.to_string(), &mut min_value_str, &mut max_value_str);
-                    }
-                    DataType::Timestamptz(Some(ts)) => {
-                        update_date_min_max(&ts.to_string(), &mut min_value_str, &mut max_value_str);
-                    }
-                    // Ignore nulls and non-comparable types for min/max calculation
-                    _ => {}
+        // Determine type from first non-null value encountered
+        if self.determined_type.is_none() {
+            match value {
+                // Check for non-null variants using matches! for conciseness
+                DataType::Int2(Some(_)) | DataType::Int4(Some(_)) | DataType::Int8(Some(_)) |
+                DataType::Float4(Some(_)) | DataType::Float8(Some(_)) | DataType::Text(Some(_)) |
+                DataType::Bool(Some(_)) | DataType::Date(Some(_)) | DataType::Timestamp(Some(_)) |
+                DataType::Timestamptz(Some(_)) | DataType::Json(Some(_)) | DataType::Uuid(Some(_)) |
+                DataType::Decimal(Some(_)) | DataType::Time(Some(_)) => {
+                    self.determined_type = Some(determine_types(value));
                 }
+                // If it's a Null variant or Unknown, keep looking
+                _ => {}
+            }
+        }
+
+        // Calculate min/max based on value's actual type in this row
+        let numeric = match value {
+            DataType::Int2(Some(v)) => Some(*v as f64),
+            DataType::Int4(Some(v)) => Some(*v as f64),
+            DataType::Int8(Some(v)) => Some(*v as f64),
+            DataType::Float4(Some(v)) => Some(*v as f64),
+            DataType::Float8(Some(v)) => Some(*v),
+            DataType::Date(Some(date)) => {
+                update_date_min_max(&date.to_string(), &mut self.min_value_str, &mut self.max_value_str);
+                None
+            }
+            DataType::Timestamp(Some(ts)) => {
+                update_date_min_max(&ts.to_string(), &mut self.min_value_str, &mut self.max_value_str);
+                None
+            }
+            DataType::Timestamptz(Some(ts)) => {
+                update_date_min_max(&ts.to_string(), &mut self.min_value_str, &mut self.max_value_str);
+                None
             }
+            // Ignore nulls and non-comparable types for min/max calculation
+            _ => None,
+        };
+
+        if let Some(n) = numeric {
+            self.min_value_numeric = Some(self.min_value_numeric.map_or(n, |min| min.min(n)));
+            self.max_value_numeric = Some(self.max_value_numeric.map_or(n, |max| max.max(n)));
         }
+    }
 
+    fn finish(self) -> ColumnMetaData {
         // Finalize types - default if no non-null value was found
-        let (simple_type, column_type) = determined_type.unwrap_or((SimpleType::Other, ColumnType::Other));
+        let (simple_type, column_type) = self.determined_type.unwrap_or((SimpleType::Other, ColumnType::Other));
 
         // Format min/max values appropriately based on determined simple_type
         let (min_value_json, max_value_json) = match simple_type {
             SimpleType::Number => (
-                min_value_numeric.and_then(|v| serde_json::Number::from_f64(v).map(serde_json::Value::Number))
+                self.min_value_numeric.and_then(|v| serde_json::Number::from_f64(v).map(serde_json::Value::Number))
                                 .unwrap_or(serde_json::Value::Null),
-                max_value_numeric.and_then(|v| serde_json::Number::from_f64(v).map(serde_json::Value::Number))
+                self.max_value_numeric.and_then(|v| serde_json::Number::from_f64(v).map(serde_json::Value::Number))
                                 .unwrap_or(serde_json::Value::Null),
             ),
             SimpleType::Date => (
-                min_value_str.map_or(serde_json::Value::Null, serde_json::Value::String),
-                max_value_str.map_or(serde_json::Value::Null, serde_json::Value::String),
+                self.min_value_str.map_or(serde_json::Value::Null, serde_json::Value::String),
+                self.max_value_str.map_or(serde_json::Value::Null, serde_json::Value::String),
             ),
             // Don't provide min/max for other types
             _ => (serde_json::Value::Null, serde_json::Value::Null),
         };
 
         ColumnMetaData {
-            name: column_name.to_lowercase(),
+            name: self.name.to_lowercase(),
             min_value: min_value_json,
             max_value: max_value_json,
-            unique_values: value_map.len() as i32, // Count includes distinct null representations
+            unique_values: self.value_map.len() as i32, // Count includes distinct null representations
             simple_type,
             column_type,
         }
-    }).collect()
+    }
 }
 
 // Helper function to update min/max date values
@@ -292,6 +474,7 @@ mod tests {
             pool.clone(),
             "SELECT generate_series(1, 100) AS num".to_string(),
             Some(10),
+            CancellationToken::new(),
         )
         .await
         .expect("Query should succeed");
@@ -303,6 +486,7 @@ mod tests {
             pool.clone(),
             "SELECT generate_series(1, 6000) AS num".to_string(),
             None,
+            CancellationToken::new(),
         )
         .await
         .expect("Query should succeed");
@@ -314,6 +498,7 @@ mod tests {
             pool,
             "SELECT generate_series(1, 6000) AS num".to_string(),
             Some(6000),
+            CancellationToken::new(),
         )
         .await
         .expect("Query should succeed");
@@ -345,6 +530,7 @@ mod tests {
             pool.clone(),
             "SELECT * FROM (SELECT 1 AS num UNION SELECT 2 UNION SELECT 3 UNION SELECT 4 UNION SELECT 5 UNION SELECT 6 UNION SELECT 7 UNION SELECT 8 UNION SELECT 9 UNION SELECT 10) AS t".to_string(),
             Some(5),
+            CancellationToken::new(),
         )
         .await
         .expect("Query should succeed");
@@ -352,6 +538,38 @@ mod tests {
         assert_eq!(results.len(), 5, "Should return exactly 5 rows with limit 5");
     }
     
+    // Metadata built batch by batch must match metadata over the whole result
+    #[test]
+    fn test_incremental_metadata_matches_full_result() {
+        let rows: Vec<IndexMap<String, DataType>> = (0..10)
+            .map(|i| {
+                let mut row = IndexMap::new();
+                row.insert("Id".to_string(), DataType::Int4(Some(i)));
+                row.insert(
+                    "day".to_string(),
+                    DataType::Date(chrono::NaiveDate::from_ymd_opt(2024, 1, 1 + i as u32)),
+                );
+                row
+            })
+            .collect();
+
+        let mut accumulator = DataMetadataAccumulator::default();
+        for batch in rows.chunks(3) {
+            accumulator.update(batch);
+        }
+        let incremental = accumulator.finish();
+        let full = compute_data_metadata(&rows);
+
+        assert_eq!(incremental.row_count, 10);
+        assert_eq!(incremental.column_count, full.column_count);
+        assert_eq!(
+            serde_json::to_value(&incremental.column_metadata).unwrap(),
+            serde_json::to_value(&full.column_metadata).unwrap()
+        );
+        assert_eq!(incremental.column_metadata[0].name, "id");
+        assert_eq!(incremental.column_metadata[0].max_value, serde_json::json!(9.0));
+    }
+
     // Test parsing functions in the bigquery connector
     #[test]
     fn test_bigquery_string_parsing() {
@@ -383,24 +601,75 @@ mod tests {
     }
 }
 
-async fn route_to_query(
+/// Runs the query on the data source within its quotas. The row limit is lowered to the row
+/// quota, and the query waits for no one: it fails straight away if a concurrency or rate
+/// quota is used up.
+pub(crate) async fn route_to_query(
     data_source_id: &Uuid,
     sql: &str,
     limit: Option<i64>,
+    quotas: &QueryQuotas,
+    cancellation: &CancellationToken,
 ) -> Result<Vec<IndexMap<String, DataType>>> {
-    let credentials_string = match read_secret(data_source_id).await {
-        Ok(credentials) => credentials,
-        Err(e) => return Err(anyhow!(e)),
+    let credentials = read_credentials(data_source_id).await?;
+
+    let statement_timeout = get_statement_timeout(data_source_id).await?;
+    let _permit = acquire_query_permit(quotas, statement_timeout).await?;
+    let limit = quotas.cap_limit(limit);
+
+    // The query runs in its own task so the warehouse-side cancel still goes out when this
+    // future is dropped, e.g. because the client disconnected or the chat was stopped
+    let query_cancellation = cancellation.child_token();
+    let mut query_task = tokio::spawn(dispatch_query(
+        credentials,
+        sql.to_owned(),
+        limit,
+        quotas.max_bytes_scanned(),
+        query_cancellation.clone(),
+    ));
+    let _cancel_on_drop = query_cancellation.clone().drop_guard();
+
+    let result = match statement_timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, &mut query_task).await {
+            Ok(result) => result,
+            Err(_) => {
+                query_cancellation.cancel();
+                // Wait for the route to cancel the query on the warehouse
+                let _ = query_task.await;
+                return Err(anyhow!(
+                    "Query exceeded the statement timeout of {} seconds",
+                    timeout.as_secs()
+                ));
+            }
+        },
+        None => query_task.await,
     };
 
-    let credentials: Credential = match serde_json::from_str(&credentials_string) {
+    result.map_err(|e| anyhow!("Query task failed: {}", e))?
+}
+
+pub(crate) async fn read_credentials(data_source_id: &Uuid) -> Result<Credential> {
+    let credentials_string = match read_secret(data_source_id).await {
         Ok(credentials) => credentials,
         Err(e) => return Err(anyhow!(e)),
     };
 
+    match serde_json::from_str(&credentials_string) {
+        Ok(credentials) => Ok(credentials),
+        Err(e) => Err(anyhow!(e)),
+    }
+}
+
+pub(crate) async fn dispatch_query(
+    credentials: Credential,
+    sql: String,
+    limit: Option<i64>,
+    max_bytes_scanned: Option<i64>,
+    cancellation: CancellationToken,
+) -> Result<Vec<IndexMap<String, DataType>>> {
     let results = match credentials {
         Credential::Postgres(credentials) => {
-            let (pg_pool, ssh_tunnel, temp_files) = match get_postgres_connection(&credentials)
+            let (pg_pool, _ssh_tunnel) = match get_postgres_connection(&credentials)
                 .await
             {
                 Ok(pg_pool) => pg_pool,
@@ -410,25 +679,19 @@ async fn route_to_query(
                 }
             };
 
-            let results = match postgres_query(pg_pool, sql.to_owned(), limit).await {
+            match postgres_query(pg_pool, sql.to_owned(), limit, cancellation).await {
                 Ok(results) => results,
                 Err(e) => {
                     return Err(anyhow!(e));
                 }
-            };
-
-            if let (Some(mut ssh_tunnel), Some(temp_files)) = (ssh_tunnel, temp_files) {
-                let _ = kill_ssh_tunnel(&mut ssh_tunnel, temp_files).await;
-            };
-
-            results
+            }
         }
         Credential::Redshift(credentials) => {
             let redshift_client = get_redshift_connection(&credentials).await?;
 
             
 
-            match redshift_query(redshift_client, sql.to_owned(), limit).await {
+            match redshift_query(redshift_client, sql.to_owned(), limit, cancellation).await {
                 Ok(results) => results,
                 Err(e) => {
                     tracing::error!("There was an issue while fetching the tables: {}", e);
@@ -437,7 +700,7 @@ async fn route_to_query(
             }
         }
         Credential::MySql(credentials) => {
-            let (mysql_pool, ssh_tunnel, temp_files) = match get_mysql_connection(&credentials)
+            let (mysql_pool, _ssh_tunnel) = match get_mysql_connection(&credentials)
                 .await
             {
                 Ok(mysql_pool) => mysql_pool,
@@ -447,19 +710,13 @@ async fn route_to_query(
                 }
             };
 
-            let results = match mysql_query(mysql_pool, sql.to_owned(), limit).await {
+            match mysql_query(mysql_pool, sql.to_owned(), limit, cancellation).await {
                 Ok(results) => results,
                 Err(e) => {
                     tracing::error!("There was an issue while fetching the tables: {}", e);
                     return Err(anyhow!(e));
                 }
-            };
-
-            if let (Some(mut ssh_tunnel), Some(temp_files)) = (ssh_tunnel, temp_files) {
-                let _ = kill_ssh_tunnel(&mut ssh_tunnel, temp_files).await;
-            };
-
-            results
+            }
         }
         Credential::Bigquery(credentials) => {
             let (bq_client, project_id) = match get_bigquery_client(&credentials).await {
@@ -472,16 +729,30 @@ async fn route_to_query(
 
             
 
-            match bigquery_query(bq_client, project_id, sql.to_owned(), limit).await {
+            match bigquery_query(
+                bq_client,
+                project_id,
+                sql.to_owned(),
+                limit,
+                max_bytes_scanned,
+                cancellation,
+            )
+            .await
+            {
                 Ok(results) => results,
                 Err(e) => {
+                    if let Some(limit) = max_bytes_scanned {
+                        if is_bytes_limit_error(&e.to_string()) {
+                            return Err(QueryQuotaError::BytesScannedLimit { limit }.into());
+                        }
+                    }
                     tracing::error!("There was an issue while fetching the tables: {}", e);
                     return Err(anyhow!(e));
                 }
             }
         }
         Credential::SqlServer(credentials) => {
-            let (sql_server_pool, ssh_tunnel, temp_files) = match get_sql_server_connection(
+            let (sql_server_pool, _ssh_tunnel) = match get_sql_server_connection(
                 &credentials,
             )
             .await
@@ -493,19 +764,13 @@ async fn route_to_query(
                 }
             };
 
-            let results = match sql_server_query(sql_server_pool, sql.to_owned(), limit).await {
+            match sql_server_query(sql_server_pool, &credentials, sql.to_owned(), limit, cancellation).await {
                 Ok(results) => results,
                 Err(e) => {
                     tracing::error!("There was an issue while fetching the tables: {}", e);
                     return Err(anyhow!(e));
                 }
-            };
-
-            if let (Some(mut ssh_tunnel), Some(temp_files)) = (ssh_tunnel, temp_files) {
-                let _ = kill_ssh_tunnel(&mut ssh_tunnel, temp_files).await;
-            };
-
-            results
+            }
         }
         Credential::Databricks(credentials) => {
             let databricks_client = match get_databricks_client(&credentials).await {
@@ -518,7 +783,7 @@ async fn route_to_query(
 
             
 
-            match databricks_query(databricks_client, sql.to_owned(), limit).await {
+            match databricks_query(databricks_client, sql.to_owned(), limit, cancellation).await {
                 Ok(results) => results,
                 Err(e) => {
                     tracing::error!("There was an issue while fetching the tables: {}", e);
@@ -537,7 +802,7 @@ async fn route_to_query(
 
             
 
-            match snowflake_query(snowflake_client, sql.to_owned(), limit).await {
+            match snowflake_query(snowflake_client, &credentials, sql.to_owned(), limit, cancellation).await {
                 Ok(processing_result) => {
                     match processing_result {
                         ProcessingResult::Processed(results) => results,
@@ -554,6 +819,74 @@ async fn route_to_query(
                 }
             }
         }
+        Credential::DuckDb(credentials) => {
+            let duckdb_connection = match get_duckdb_connection(&credentials).await {
+                Ok(duckdb_connection) => duckdb_connection,
+                Err(e) => {
+                    tracing::error!("There was an issue while establishing a connection to the parent data source: {}", e);
+                    return Err(anyhow!(e));
+                }
+            };
+
+            match duckdb_query(duckdb_connection, sql.to_owned(), limit, cancellation).await {
+                Ok(results) => results,
+                Err(e) => {
+                    tracing::error!("There was an issue while fetching the tables: {}", e);
+                    return Err(anyhow!(e));
+                }
+            }
+        }
+        Credential::Sqlite(credentials) => {
+            let sqlite_pool = match get_sqlite_connection(&credentials).await {
+                Ok(sqlite_pool) => sqlite_pool,
+                Err(e) => {
+                    tracing::error!("There was an issue while establishing a connection to the parent data source: {}", e);
+                    return Err(anyhow!(e));
+                }
+            };
+
+            match sqlite_query(sqlite_pool, sql.to_owned(), limit, cancellation).await {
+                Ok(results) => results,
+                Err(e) => {
+                    tracing::error!("There was an issue while fetching the tables: {}", e);
+                    return Err(anyhow!(e));
+                }
+            }
+        }
+        Credential::ClickHouse(credentials) => {
+            let clickhouse_client = match get_clickhouse_client(&credentials).await {
+                Ok(clickhouse_client) => clickhouse_client,
+                Err(e) => {
+                    tracing::error!("There was an issue while establishing a connection to the parent data source: {}", e);
+                    return Err(anyhow!(e));
+                }
+            };
+
+            match clickhouse_query(clickhouse_client, sql.to_owned(), limit, cancellation).await {
+                Ok(results) => results,
+                Err(e) => {
+                    tracing::error!("There was an issue while fetching the tables: {}", e);
+                    return Err(anyhow!(e));
+                }
+            }
+        }
+        Credential::Trino(credentials) => {
+            let trino_client = match get_trino_client(&credentials).await {
+                Ok(trino_client) => trino_client,
+                Err(e) => {
+                    tracing::error!("There was an issue while establishing a connection to the parent data source: {}", e);
+                    return Err(anyhow!(e));
+                }
+            };
+
+            match trino_query(trino_client, sql.to_owned(), limit, cancellation).await {
+                Ok(results) => results,
+                Err(e) => {
+                    tracing::error!("There was an issue while fetching the tables: {}", e);
+                    return Err(anyhow!(e));
+                }
+            }
+        }
     };
 
     Ok(results)
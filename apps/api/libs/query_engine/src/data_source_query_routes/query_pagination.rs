@@ -0,0 +1,351 @@
+use anyhow::{anyhow, Result};
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use indexmap::IndexMap;
+use sha2::{Digest, Sha256};
+use sql_analyzer::analysis::get_dialect;
+use sqlparser::ast::{Expr, Query, SetExpr, Statement, TopQuantity, Value};
+use sqlparser::parser::Parser;
+use uuid::Uuid;
+
+use database::{
+    enums::DataSourceType, pool::get_pg_pool, schema::data_sources,
+    types::data_metadata::DataMetadata,
+};
+
+use crate::data_types::DataType;
+
+use super::{
+    column_level_security::ColumnMasks,
+    query_cancellation::CancellationToken,
+    query_engine::{compute_data_metadata, prepare_query, route_to_query},
+    query_quotas::{QueryQuotaError, QueryQuotas},
+    row_level_security::RowFilters,
+};
+
+/// One page of a paginated query
+#[derive(Debug, Clone)]
+pub struct QueryPage {
+    pub data: Vec<IndexMap<String, DataType>>,
+    pub metadata: DataMetadata,
+    pub next_cursor: Option<String>, // None once the last page has been returned
+    pub warnings: Vec<String>,
+}
+
+/// Runs a single page of a query. Pass the `next_cursor` of the previous page to continue;
+/// cursors are tied to the SQL they were issued for and are rejected for any other query.
+///
+/// Each page re-runs the query with an offset, so only queries with an ORDER BY can be paged.
+/// The ORDER BY should end on a unique key: rows that tie on every sort key can otherwise move
+/// between pages.
+pub async fn query_engine_page(
+    data_source_id: &Uuid,
+    sql: &str,
+    page_size: i64,
+    cursor: Option<&str>,
+    row_filters: &RowFilters,
+    column_masks: &ColumnMasks,
+    cancellation: &CancellationToken,
+) -> Result<QueryPage> {
+    let offset = match cursor {
+        Some(cursor) => decode_cursor(cursor, sql)?,
+        None => 0,
+    };
+
+    let prepared = prepare_query(data_source_id, sql, row_filters, column_masks).await?;
+    let (data, has_more) = fetch_page(
+        data_source_id,
+        &prepared.sql,
+        prepared.data_source_type,
+        &prepared.quotas,
+        offset,
+        page_size,
+        cancellation,
+    )
+    .await?;
+
+    let next_cursor = has_more.then(|| encode_cursor(sql, offset + data.len() as u64));
+    let metadata = compute_data_metadata(&data);
+
+    let mut warnings = prepared.warnings;
+    if next_cursor.is_none() {
+        let rows_returned = offset as usize + data.len();
+        warnings.extend(prepared.quotas.truncation_warning(i64::MAX, rows_returned));
+    }
+
+    Ok(QueryPage {
+        data,
+        metadata,
+        next_cursor,
+        warnings,
+    })
+}
+
+// Fetches one extra row to learn whether another page exists. The row quota covers all pages
+// of a query together: the last page ends where the quota runs out.
+async fn fetch_page(
+    data_source_id: &Uuid,
+    sql: &str,
+    data_source_type: DataSourceType,
+    quotas: &QueryQuotas,
+    offset: u64,
+    page_size: i64,
+    cancellation: &CancellationToken,
+) -> Result<(Vec<IndexMap<String, DataType>>, bool)> {
+    if page_size <= 0 {
+        return Err(anyhow!("Page size must be greater than zero"));
+    }
+
+    let page_size = match quotas.max_rows_returned() {
+        Some(max_rows) if offset >= max_rows as u64 => {
+            return Err(QueryQuotaError::RowLimit { limit: max_rows }.into());
+        }
+        Some(max_rows) => page_size.min(max_rows - offset as i64),
+        None => page_size,
+    };
+
+    let paged_sql = paginate_sql(sql, data_source_type.to_str(), offset, page_size as u64 + 1)?;
+    let mut rows = route_to_query(
+        data_source_id,
+        &paged_sql,
+        Some(page_size + 1),
+        quotas,
+        cancellation,
+    )
+    .await?;
+
+    let quota_reached = quotas
+        .max_rows_returned()
+        .is_some_and(|max_rows| offset + page_size as u64 >= max_rows as u64);
+    let has_more = rows.len() > page_size as usize && !quota_reached;
+    rows.truncate(page_size as usize);
+    Ok((rows, has_more))
+}
+
+/// Applies an offset and row limit to an ordered SELECT in the data source's dialect. A row
+/// limit or offset the query already has is folded into the page's, so the page never reaches
+/// past the query's own rows.
+pub fn paginate_sql(
+    sql: &str,
+    data_source_dialect: &str,
+    offset: u64,
+    limit: u64,
+) -> Result<String> {
+    let statements = Parser::parse_sql(get_dialect(data_source_dialect), sql)
+        .map_err(|e| anyhow!("Failed to parse SQL for pagination: {}", e))?;
+
+    let mut query = match statements.as_slice() {
+        [Statement::Query(query)] => query.as_ref().clone(),
+        _ => return Err(anyhow!("Only a single SELECT query can be paginated")),
+    };
+
+    if query
+        .order_by
+        .as_ref()
+        .is_none_or(|order_by| order_by.exprs.is_empty())
+    {
+        return Err(anyhow!(
+            "Only queries with an ORDER BY can be paginated; order by a unique key so pages stay stable"
+        ));
+    }
+
+    let (own_offset, own_limit) = take_row_limits(&mut query)?;
+    let limit = match own_limit {
+        Some(own_limit) => own_limit.saturating_sub(offset).min(limit),
+        None => limit,
+    };
+    let offset = own_offset + offset;
+
+    Ok(match data_source_dialect {
+        // SQL Server has no LIMIT
+        "sqlserver" => format!(
+            "{} OFFSET {} ROWS FETCH NEXT {} ROWS ONLY",
+            query, offset, limit
+        ),
+        // Trino requires OFFSET before LIMIT
+        "trino" => format!("{} OFFSET {} LIMIT {}", query, offset, limit),
+        _ => format!("{} LIMIT {} OFFSET {}", query, limit, offset),
+    })
+}
+
+/// Removes the query's own OFFSET and LIMIT, FETCH or TOP, returning the offset and row limit
+/// they applied
+fn take_row_limits(query: &mut Query) -> Result<(u64, Option<u64>)> {
+    let own_offset = match query.offset.take() {
+        Some(offset) => constant_rows(&offset.value)?,
+        None => 0,
+    };
+
+    let mut own_limit = match query.limit.take() {
+        Some(limit) => Some(constant_rows(&limit)?),
+        None => None,
+    };
+
+    if let Some(fetch) = query.fetch.take() {
+        if fetch.percent || fetch.with_ties {
+            return Err(anyhow!(
+                "Queries using FETCH PERCENT or WITH TIES can't be paginated"
+            ));
+        }
+        if let Some(quantity) = fetch.quantity {
+            own_limit = Some(constant_rows(&quantity)?);
+        }
+    }
+
+    if let SetExpr::Select(select) = query.body.as_mut() {
+        if let Some(top) = select.top.take() {
+            if top.percent || top.with_ties {
+                return Err(anyhow!(
+                    "Queries using TOP PERCENT or WITH TIES can't be paginated"
+                ));
+            }
+            own_limit = match top.quantity {
+                Some(TopQuantity::Constant(rows)) => Some(rows),
+                Some(TopQuantity::Expr(rows)) => Some(constant_rows(&rows)?),
+                None => own_limit,
+            };
+        }
+    }
+
+    Ok((own_offset, own_limit))
+}
+
+fn constant_rows(expr: &Expr) -> Result<u64> {
+    match expr {
+        Expr::Value(Value::Number(rows, _)) => rows
+            .parse::<u64>()
+            .map_err(|_| anyhow!("Invalid row limit: {}", rows)),
+        Expr::Nested(expr) => constant_rows(expr),
+        _ => Err(anyhow!(
+            "Only queries with a constant row limit and offset can be paginated"
+        )),
+    }
+}
+
+/// Cursor for the page that starts after `offset` rows of `sql`, or None when the query can't
+/// be paged
+pub async fn next_page_cursor(
+    data_source_id: &Uuid,
+    sql: &str,
+    offset: u64,
+) -> Result<Option<String>> {
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .map_err(|e| anyhow!("Failed to get database connection: {}", e))?;
+
+    let data_source_type = data_sources::table
+        .filter(data_sources::id.eq(data_source_id))
+        .select(data_sources::type_)
+        .first::<DataSourceType>(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch data source type: {}", e))?;
+
+    Ok(paginate_sql(sql, data_source_type.to_str(), offset, 1)
+        .is_ok()
+        .then(|| encode_cursor(sql, offset)))
+}
+
+/// Encodes the position after `offset` rows of `sql` as an opaque cursor
+pub fn encode_cursor(sql: &str, offset: u64) -> String {
+    format!("{}.{}", offset, sql_fingerprint(sql))
+}
+
+/// Returns the row offset a cursor points at, checking it was issued for `sql`
+pub fn decode_cursor(cursor: &str, sql: &str) -> Result<u64> {
+    let (offset, fingerprint) = cursor
+        .split_once('.')
+        .ok_or_else(|| anyhow!("Invalid cursor"))?;
+
+    if fingerprint != sql_fingerprint(sql) {
+        return Err(anyhow!("Cursor does not belong to this query"));
+    }
+
+    offset.parse::<u64>().map_err(|_| anyhow!("Invalid cursor"))
+}
+
+fn sql_fingerprint(sql: &str) -> String {
+    let digest = Sha256::digest(sql.trim().as_bytes());
+    digest[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paginate_sql_appends_limit_and_offset() {
+        let sql = "SELECT id, name FROM public.users ORDER BY id";
+
+        assert_eq!(
+            paginate_sql(sql, "postgres", 100, 51).unwrap(),
+            "SELECT id, name FROM public.users ORDER BY id LIMIT 51 OFFSET 100"
+        );
+        assert_eq!(
+            paginate_sql(sql, "trino", 100, 51).unwrap(),
+            "SELECT id, name FROM public.users ORDER BY id OFFSET 100 LIMIT 51"
+        );
+        assert_eq!(
+            paginate_sql(sql, "sqlserver", 100, 51).unwrap(),
+            "SELECT id, name FROM public.users ORDER BY id OFFSET 100 ROWS FETCH NEXT 51 ROWS ONLY"
+        );
+    }
+
+    #[test]
+    fn test_paginate_sql_folds_in_own_limits() {
+        assert_eq!(
+            paginate_sql(
+                "SELECT id FROM public.users ORDER BY id LIMIT 10",
+                "postgres",
+                5,
+                6
+            )
+            .unwrap(),
+            "SELECT id FROM public.users ORDER BY id LIMIT 5 OFFSET 5"
+        );
+        assert_eq!(
+            paginate_sql(
+                "SELECT id FROM public.users ORDER BY id LIMIT 10 OFFSET 20",
+                "postgres",
+                0,
+                6
+            )
+            .unwrap(),
+            "SELECT id FROM public.users ORDER BY id LIMIT 6 OFFSET 20"
+        );
+        assert_eq!(
+            paginate_sql(
+                "SELECT TOP 10 id FROM dbo.users ORDER BY id",
+                "sqlserver",
+                6,
+                6
+            )
+            .unwrap(),
+            "SELECT id FROM dbo.users ORDER BY id OFFSET 6 ROWS FETCH NEXT 4 ROWS ONLY"
+        );
+    }
+
+    #[test]
+    fn test_paginate_sql_requires_order_by() {
+        let err = paginate_sql("SELECT id FROM public.users", "postgres", 0, 51).unwrap_err();
+        assert!(err.to_string().contains("ORDER BY"));
+
+        assert!(paginate_sql(
+            "SELECT id FROM public.users ORDER BY id LIMIT 5 + 5",
+            "postgres",
+            0,
+            51
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let sql = "SELECT id FROM public.users";
+        let cursor = encode_cursor(sql, 5000);
+
+        assert_eq!(decode_cursor(&cursor, sql).unwrap(), 5000);
+        assert!(decode_cursor(&cursor, "SELECT id FROM public.orders").is_err());
+        assert!(decode_cursor("garbage", sql).is_err());
+    }
+}
@@ -0,0 +1,414 @@
+use std::fmt;
+use std::time::Duration;
+
+use anyhow::{anyhow, Error, Result};
+use chrono::Utc;
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use once_cell::sync::Lazy;
+use redis::Script;
+use uuid::Uuid;
+
+use database::{
+    models::QueryQuota,
+    pool::{get_pg_pool, get_redis_pool},
+    schema::query_quotas,
+};
+
+// Row limit the query routes apply when the caller doesn't pass one
+pub(crate) const DEFAULT_ROW_LIMIT: i64 = 5000;
+
+// How long a running query holds its concurrency slot when the data source has no statement
+// timeout. Slots of queries whose process died are freed once their lease runs out.
+const DEFAULT_CONCURRENCY_LEASE: Duration = Duration::from_secs(60 * 60);
+
+// Checks every scope before taking anything, so a query rejected by the data source quota
+// doesn't use up the organization's. Returns the 1-based scope and limit kind that rejected
+// the query, or {0, 0} once a slot and a rate count were taken in every scope.
+static ACQUIRE_SCRIPT: Lazy<Script> = Lazy::new(|| {
+    Script::new(
+        r#"
+local now = tonumber(ARGV[1])
+local expires_at = tonumber(ARGV[2])
+local permit_id = ARGV[3]
+local scopes = #KEYS / 2
+
+for i = 1, scopes do
+    local max_concurrent = tonumber(ARGV[2 + 2 * i])
+    local max_per_minute = tonumber(ARGV[3 + 2 * i])
+    if max_concurrent > 0 then
+        redis.call('ZREMRANGEBYSCORE', KEYS[2 * i - 1], '-inf', now)
+        if redis.call('ZCARD', KEYS[2 * i - 1]) >= max_concurrent then
+            return {i, 1}
+        end
+    end
+    if max_per_minute > 0 then
+        local count = tonumber(redis.call('GET', KEYS[2 * i]) or '0')
+        if count >= max_per_minute then
+            return {i, 2}
+        end
+    end
+end
+
+for i = 1, scopes do
+    if tonumber(ARGV[2 + 2 * i]) > 0 then
+        redis.call('ZADD', KEYS[2 * i - 1], expires_at, permit_id)
+        redis.call('PEXPIREAT', KEYS[2 * i - 1], expires_at)
+    end
+    if tonumber(ARGV[3 + 2 * i]) > 0 then
+        if redis.call('INCR', KEYS[2 * i]) == 1 then
+            redis.call('EXPIRE', KEYS[2 * i], 60)
+        end
+    end
+end
+
+return {0, 0}
+"#,
+    )
+});
+
+/// What a quota applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuotaScope {
+    Organization,
+    DataSource,
+}
+
+impl fmt::Display for QuotaScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuotaScope::Organization => write!(f, "organization"),
+            QuotaScope::DataSource => write!(f, "data source"),
+        }
+    }
+}
+
+/// A query the organization's quotas don't allow. Every message starts with
+/// "Query quota exceeded" so routes and agent tools can tell these apart from query errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryQuotaError {
+    ConcurrencyLimit { scope: QuotaScope, limit: i32 },
+    RateLimit { scope: QuotaScope, limit: i32 },
+    RowLimit { limit: i64 },
+    BytesScannedLimit { limit: i64 },
+}
+
+impl fmt::Display for QueryQuotaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryQuotaError::ConcurrencyLimit { scope, limit } => write!(
+                f,
+                "Query quota exceeded: the {} allows {} concurrent queries. Try again once a running query finishes.",
+                scope, limit
+            ),
+            QueryQuotaError::RateLimit { scope, limit } => write!(
+                f,
+                "Query quota exceeded: the {} allows {} queries per minute. Try again in a minute.",
+                scope, limit
+            ),
+            QueryQuotaError::RowLimit { limit } => write!(
+                f,
+                "Query quota exceeded: at most {} rows can be returned for a query",
+                limit
+            ),
+            QueryQuotaError::BytesScannedLimit { limit } => write!(
+                f,
+                "Query quota exceeded: the query would scan more than the {} bytes allowed",
+                limit
+            ),
+        }
+    }
+}
+
+impl std::error::Error for QueryQuotaError {}
+
+/// The organization-wide and data source quotas that apply to a query. Row and byte limits
+/// take the lower of the two; concurrency and rate limits are counted in each scope.
+#[derive(Debug, Clone, Default)]
+pub struct QueryQuotas {
+    pub organization: Option<QueryQuota>,
+    pub data_source: Option<QueryQuota>,
+}
+
+impl QueryQuotas {
+    pub fn max_rows_returned(&self) -> Option<i64> {
+        min_limit(self.scoped().map(|(_, quota)| quota.max_rows_returned))
+    }
+
+    pub fn max_bytes_scanned(&self) -> Option<i64> {
+        min_limit(self.scoped().map(|(_, quota)| quota.max_bytes_scanned))
+    }
+
+    /// The row limit to run a query with: the requested limit, lowered to the row quota
+    pub fn cap_limit(&self, limit: Option<i64>) -> Option<i64> {
+        match self.max_rows_returned() {
+            Some(max_rows) => Some(limit.unwrap_or(DEFAULT_ROW_LIMIT).min(max_rows)),
+            None => limit,
+        }
+    }
+
+    /// Warning to return with a result the row quota may have cut short
+    pub fn truncation_warning(&self, requested_rows: i64, row_count: usize) -> Option<String> {
+        let max_rows = self.max_rows_returned()?;
+        (max_rows < requested_rows && row_count as i64 >= max_rows).then(|| {
+            format!(
+                "Result limited to {} rows by the organization's query quota",
+                max_rows
+            )
+        })
+    }
+
+    fn scoped(&self) -> impl Iterator<Item = (QuotaScope, &QueryQuota)> {
+        [
+            (QuotaScope::Organization, self.organization.as_ref()),
+            (QuotaScope::DataSource, self.data_source.as_ref()),
+        ]
+        .into_iter()
+        .filter_map(|(scope, quota)| quota.map(|quota| (scope, quota)))
+    }
+}
+
+fn min_limit(limits: impl Iterator<Item = Option<i64>>) -> Option<i64> {
+    limits.flatten().min()
+}
+
+/// Loads the quotas of the data source and of the organization it belongs to
+pub(crate) async fn get_query_quotas(
+    organization_id: &Uuid,
+    data_source_id: &Uuid,
+) -> Result<QueryQuotas> {
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .map_err(|e| anyhow!("Failed to get database connection: {}", e))?;
+
+    let quotas = query_quotas::table
+        .filter(query_quotas::organization_id.eq(organization_id))
+        .filter(
+            query_quotas::data_source_id
+                .eq(data_source_id)
+                .or(query_quotas::data_source_id.is_null()),
+        )
+        .select(QueryQuota::as_select())
+        .load::<QueryQuota>(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch query quotas: {}", e))?;
+
+    let mut result = QueryQuotas::default();
+    for quota in quotas {
+        match quota.data_source_id {
+            Some(_) => result.data_source = Some(quota),
+            None => result.organization = Some(quota),
+        }
+    }
+
+    Ok(result)
+}
+
+/// A concurrency slot held for a running query. Dropping it frees the slot.
+pub(crate) struct QuotaPermit {
+    id: String,
+    concurrency_keys: Vec<String>,
+}
+
+impl Drop for QuotaPermit {
+    fn drop(&mut self) {
+        if self.concurrency_keys.is_empty() {
+            return;
+        }
+
+        let id = std::mem::take(&mut self.id);
+        let keys = std::mem::take(&mut self.concurrency_keys);
+        tokio::spawn(async move {
+            if let Err(e) = release_slots(&id, &keys).await {
+                // The slot is freed anyway once its lease runs out
+                tracing::warn!("Failed to release query concurrency slot: {}", e);
+            }
+        });
+    }
+}
+
+async fn release_slots(id: &str, keys: &[String]) -> Result<()> {
+    let mut redis_conn = get_redis_pool()
+        .get()
+        .await
+        .map_err(|e| anyhow!("Error getting redis connection: {}", e))?;
+
+    let mut pipe = redis::pipe();
+    for key in keys {
+        pipe.zrem(key, id).ignore();
+    }
+    pipe.query_async::<()>(&mut *redis_conn).await?;
+
+    Ok(())
+}
+
+/// Takes a concurrency slot and counts the query against the per-minute rate in every scope
+/// with a limit, or fails with `QueryQuotaError` if any scope is at its limit. Quotas are
+/// counted in Redis so they hold across API instances; if Redis can't be reached the query
+/// runs unlimited rather than failing.
+pub(crate) async fn acquire_query_permit(
+    quotas: &QueryQuotas,
+    statement_timeout: Option<Duration>,
+) -> Result<QuotaPermit> {
+    let mut permit = QuotaPermit {
+        id: Uuid::new_v4().to_string(),
+        concurrency_keys: Vec::new(),
+    };
+
+    let scopes: Vec<(QuotaScope, &QueryQuota)> = quotas
+        .scoped()
+        .filter(|(_, quota)| {
+            quota.max_concurrent_queries.is_some() || quota.max_queries_per_minute.is_some()
+        })
+        .collect();
+    if scopes.is_empty() {
+        return Ok(permit);
+    }
+
+    let now = Utc::now().timestamp_millis();
+    let lease = statement_timeout.unwrap_or(DEFAULT_CONCURRENCY_LEASE) + Duration::from_secs(60);
+    let minute = now / 60_000;
+
+    let mut script = ACQUIRE_SCRIPT.prepare_invoke();
+    script
+        .arg(now)
+        .arg(now + lease.as_millis() as i64)
+        .arg(&permit.id);
+    let mut concurrency_keys = Vec::new();
+    for (_, quota) in &scopes {
+        let concurrency_key = format!("query_quota:{}:concurrent", quota.id);
+        script
+            .key(&concurrency_key)
+            .key(format!("query_quota:{}:rate:{}", quota.id, minute))
+            .arg(quota.max_concurrent_queries.unwrap_or(0))
+            .arg(quota.max_queries_per_minute.unwrap_or(0));
+        if quota.max_concurrent_queries.is_some() {
+            concurrency_keys.push(concurrency_key);
+        }
+    }
+
+    let outcome: Result<Vec<i64>> = async {
+        let mut redis_conn = get_redis_pool()
+            .get()
+            .await
+            .map_err(|e| anyhow!("Error getting redis connection: {}", e))?;
+        Ok(script.invoke_async(&mut *redis_conn).await?)
+    }
+    .await;
+
+    match outcome.as_deref() {
+        Ok([0, 0]) => {
+            permit.concurrency_keys = concurrency_keys;
+            Ok(permit)
+        }
+        Ok([scope, kind]) => {
+            let (scope, quota) = scopes
+                .get((*scope - 1) as usize)
+                .ok_or_else(|| anyhow!("Unexpected query quota check result"))?;
+            Err(Error::new(if *kind == 1 {
+                QueryQuotaError::ConcurrencyLimit {
+                    scope: *scope,
+                    limit: quota.max_concurrent_queries.unwrap_or_default(),
+                }
+            } else {
+                QueryQuotaError::RateLimit {
+                    scope: *scope,
+                    limit: quota.max_queries_per_minute.unwrap_or_default(),
+                }
+            }))
+        }
+        Ok(_) => Err(anyhow!("Unexpected query quota check result")),
+        Err(e) => {
+            tracing::warn!(
+                "Failed to check query quotas, running query unlimited: {}",
+                e
+            );
+            Ok(permit)
+        }
+    }
+}
+
+/// Whether a warehouse error means the query was stopped by the bytes scanned limit
+pub(crate) fn is_bytes_limit_error(error: &str) -> bool {
+    error.contains("bytesBilledLimitExceeded") || error.contains("limit for bytes billed")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quota(max_rows_returned: Option<i64>, max_bytes_scanned: Option<i64>) -> QueryQuota {
+        QueryQuota {
+            id: Uuid::new_v4(),
+            organization_id: Uuid::new_v4(),
+            data_source_id: None,
+            max_concurrent_queries: None,
+            max_queries_per_minute: None,
+            max_rows_returned,
+            max_bytes_scanned,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_limits_take_the_lower_of_both_scopes() {
+        let quotas = QueryQuotas {
+            organization: Some(quota(Some(1000), None)),
+            data_source: Some(quota(Some(200), Some(1 << 30))),
+        };
+
+        assert_eq!(quotas.max_rows_returned(), Some(200));
+        assert_eq!(quotas.max_bytes_scanned(), Some(1 << 30));
+        assert_eq!(QueryQuotas::default().max_rows_returned(), None);
+    }
+
+    #[test]
+    fn test_cap_limit() {
+        let quotas = QueryQuotas {
+            organization: Some(quota(Some(1000), None)),
+            data_source: None,
+        };
+
+        assert_eq!(quotas.cap_limit(None), Some(1000));
+        assert_eq!(quotas.cap_limit(Some(50)), Some(50));
+        assert_eq!(quotas.cap_limit(Some(10_000)), Some(1000));
+        assert_eq!(QueryQuotas::default().cap_limit(None), None);
+    }
+
+    #[test]
+    fn test_truncation_warning_only_when_quota_cut_the_result() {
+        let quotas = QueryQuotas {
+            organization: Some(quota(Some(100), None)),
+            data_source: None,
+        };
+
+        assert!(quotas.truncation_warning(500, 100).is_some());
+        assert!(quotas.truncation_warning(500, 40).is_none());
+        assert!(quotas.truncation_warning(100, 100).is_none());
+    }
+
+    #[test]
+    fn test_error_messages_are_recognizable() {
+        let errors = [
+            QueryQuotaError::ConcurrencyLimit {
+                scope: QuotaScope::DataSource,
+                limit: 2,
+            },
+            QueryQuotaError::RateLimit {
+                scope: QuotaScope::Organization,
+                limit: 60,
+            },
+            QueryQuotaError::RowLimit { limit: 10 },
+            QueryQuotaError::BytesScannedLimit { limit: 10 },
+        ];
+
+        for error in errors {
+            assert!(error.to_string().starts_with("Query quota exceeded"));
+        }
+        assert!(is_bytes_limit_error(
+            "Query exceeded limit for bytes billed: 1000. 10485760 or higher required."
+        ));
+    }
+}
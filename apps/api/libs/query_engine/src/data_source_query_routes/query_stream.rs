@@ -0,0 +1,344 @@
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use anyhow::{anyhow, Error, Result};
+use futures::Stream;
+use indexmap::IndexMap;
+use tokio::sync::mpsc;
+use tokio_util::sync::DropGuard;
+use uuid::Uuid;
+
+use database::types::data_metadata::DataMetadata;
+
+use crate::{
+    credentials::Credential,
+    data_source_connections::{
+        get_mysql_connection::get_mysql_connection,
+        get_postgres_connection::get_postgres_connection,
+        get_redshift_connection::get_redshift_connection,
+        get_sqlite_connection::get_sqlite_connection,
+    },
+    data_types::DataType,
+};
+
+use super::{
+    column_level_security::ColumnMasks,
+    mysql_query::mysql_stream,
+    postgres_query::postgres_stream,
+    query_cancellation::{get_statement_timeout, CancellationToken},
+    query_engine::{dispatch_query, prepare_query, read_credentials, DataMetadataAccumulator},
+    query_quotas::acquire_query_permit,
+    redshift_query::redshift_stream,
+    row_level_security::RowFilters,
+    sqlite_query::sqlite_stream,
+};
+
+/// Most rows read from a data source whose driver can only return complete results. Those
+/// results are held in memory before they're streamed, so they're capped like audit exports.
+pub const MAX_BUFFERED_STREAM_ROWS: i64 = 100_000;
+
+// Batches buffered between the query and the reader. The query waits while the reader is behind.
+const BUFFERED_BATCHES: usize = 2;
+
+/// One batch of a streamed query's rows
+pub type RowBatch = Vec<IndexMap<String, DataType>>;
+
+/// A query's rows as batches, read from the data source while the stream is consumed. Metadata
+/// is built from the batches as they pass, so the full result is never held in memory.
+///
+/// Dropping the stream stops the query on the warehouse.
+pub struct QueryStream {
+    receiver: mpsc::Receiver<Result<RowBatch>>,
+    metadata: DataMetadataAccumulator,
+    /// Complexity policy and row limit warnings raised while preparing the query
+    pub warnings: Vec<String>,
+    _cancel_on_drop: DropGuard,
+}
+
+impl QueryStream {
+    /// Metadata of the rows streamed so far. Once the stream has ended it covers the whole result.
+    pub fn metadata(&self) -> DataMetadata {
+        self.metadata.clone().finish()
+    }
+
+    /// Rows streamed so far
+    pub fn row_count(&self) -> i64 {
+        self.metadata.row_count()
+    }
+}
+
+impl Stream for QueryStream {
+    type Item = Result<RowBatch>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let batch = this.receiver.poll_recv(cx);
+        if let Poll::Ready(Some(Ok(rows))) = &batch {
+            this.metadata.update(rows);
+        }
+        batch
+    }
+}
+
+/// Collects the rows a query route reads into batches and hands each full batch to the
+/// stream's reader, stopping once the row limit is reached
+pub(crate) struct RowBatchSender {
+    sender: mpsc::Sender<Result<RowBatch>>,
+    batch: RowBatch,
+    batch_size: usize,
+    remaining_rows: Option<usize>,
+}
+
+impl RowBatchSender {
+    fn new(sender: mpsc::Sender<Result<RowBatch>>, batch_size: usize, limit: Option<i64>) -> Self {
+        Self {
+            sender,
+            batch: Vec::with_capacity(batch_size),
+            batch_size,
+            remaining_rows: limit.map(|limit| limit.max(0) as usize),
+        }
+    }
+
+    /// Adds a row, waiting for the reader when a full batch is handed over. Fails once the
+    /// reader has dropped the stream, which ends the route's query.
+    pub(crate) async fn push(&mut self, row: IndexMap<String, DataType>) -> Result<()> {
+        if self.is_full() {
+            return Ok(());
+        }
+
+        self.batch.push(row);
+        if let Some(remaining_rows) = self.remaining_rows.as_mut() {
+            *remaining_rows -= 1;
+        }
+
+        if self.batch.len() >= self.batch_size {
+            self.flush().await?;
+        }
+        Ok(())
+    }
+
+    /// True once the row limit has been reached and the route can stop reading
+    pub(crate) fn is_full(&self) -> bool {
+        self.remaining_rows == Some(0)
+    }
+
+    async fn flush(&mut self) -> Result<()> {
+        if self.batch.is_empty() {
+            return Ok(());
+        }
+
+        let batch = std::mem::replace(&mut self.batch, Vec::with_capacity(self.batch_size));
+        self.sender
+            .send(Ok(batch))
+            .await
+            .map_err(|_| anyhow!("Query stream was dropped"))
+    }
+}
+
+/// Streams a query's result in batches of at most `batch_size` rows, for results too large to
+/// return at once such as exports. The query runs once; Postgres, Redshift, MySQL and SQLite
+/// rows are read from the connection as the stream is consumed, other data sources return
+/// complete results that are capped at `MAX_BUFFERED_STREAM_ROWS`.
+///
+/// `limit` caps the rows streamed, on top of the organization's row quota. Row filters,
+/// column masks, the safety filter and the complexity policy apply as in `query_engine`.
+pub async fn query_engine_stream(
+    data_source_id: &Uuid,
+    sql: &str,
+    batch_size: usize,
+    limit: Option<i64>,
+    row_filters: &RowFilters,
+    column_masks: &ColumnMasks,
+    cancellation: &CancellationToken,
+) -> Result<QueryStream> {
+    if batch_size == 0 {
+        return Err(anyhow!("Batch size must be greater than zero"));
+    }
+
+    let prepared = prepare_query(data_source_id, sql, row_filters, column_masks).await?;
+    let credentials = read_credentials(data_source_id).await?;
+
+    // Unlike `cap_limit`, an unlimited stream with a quota gets every row the quota allows
+    let mut limit = match (limit, prepared.quotas.max_rows_returned()) {
+        (Some(limit), Some(max_rows)) => Some(limit.min(max_rows)),
+        (limit, max_rows) => limit.or(max_rows),
+    };
+
+    let mut warnings = prepared.warnings;
+    if !streams_natively(&credentials) && limit.is_none_or(|limit| limit > MAX_BUFFERED_STREAM_ROWS)
+    {
+        limit = Some(MAX_BUFFERED_STREAM_ROWS);
+        warnings.push(format!(
+            "Result limited to {} rows: {} results can't be streamed",
+            MAX_BUFFERED_STREAM_ROWS,
+            credentials.get_type_string()
+        ));
+    }
+
+    let statement_timeout = get_statement_timeout(data_source_id).await?;
+    let permit = acquire_query_permit(&prepared.quotas, statement_timeout).await?;
+    let max_bytes_scanned = prepared.quotas.max_bytes_scanned();
+
+    let (sender, receiver) = mpsc::channel(BUFFERED_BATCHES);
+    let query_cancellation = cancellation.child_token();
+    let task_cancellation = query_cancellation.clone();
+    let secure_sql = prepared.sql;
+
+    tokio::spawn(async move {
+        // Held until the last row has been read
+        let _permit = permit;
+        let errors = sender.clone();
+        let batches = RowBatchSender::new(sender, batch_size, limit);
+
+        let streamed = dispatch_stream(
+            credentials,
+            secure_sql,
+            limit,
+            max_bytes_scanned,
+            batches,
+            task_cancellation.clone(),
+        );
+        tokio::pin!(streamed);
+
+        // The timeout covers the whole stream, since the warehouse query stays open until the
+        // reader is done
+        let result = match statement_timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, &mut streamed).await {
+                Ok(result) => result,
+                Err(_) => {
+                    task_cancellation.cancel();
+                    // Wait for the route to cancel the query on the warehouse
+                    let _ = streamed.await;
+                    Err(anyhow!(
+                        "Query exceeded the statement timeout of {} seconds",
+                        timeout.as_secs()
+                    ))
+                }
+            },
+            None => streamed.await,
+        };
+
+        if let Err(e) = result {
+            tracing::error!("There was an issue while streaming the query: {}", e);
+            // Nobody to tell when the reader is gone
+            let _ = errors.send(Err(e)).await;
+        }
+    });
+
+    Ok(QueryStream {
+        receiver,
+        metadata: DataMetadataAccumulator::default(),
+        warnings,
+        _cancel_on_drop: query_cancellation.drop_guard(),
+    })
+}
+
+fn streams_natively(credentials: &Credential) -> bool {
+    matches!(
+        credentials,
+        Credential::Postgres(_)
+            | Credential::Redshift(_)
+            | Credential::MySql(_)
+            | Credential::Sqlite(_)
+    )
+}
+
+async fn dispatch_stream(
+    credentials: Credential,
+    sql: String,
+    limit: Option<i64>,
+    max_bytes_scanned: Option<i64>,
+    mut batches: RowBatchSender,
+    cancellation: CancellationToken,
+) -> Result<(), Error> {
+    match credentials {
+        Credential::Postgres(credentials) => {
+            let (pg_pool, _ssh_tunnel) = get_postgres_connection(&credentials).await?;
+            postgres_stream(pg_pool, sql, &mut batches, cancellation).await?;
+        }
+        Credential::Redshift(credentials) => {
+            let redshift_client = get_redshift_connection(&credentials).await?;
+            redshift_stream(redshift_client, sql, &mut batches, cancellation).await?;
+        }
+        Credential::MySql(credentials) => {
+            let (mysql_pool, _ssh_tunnel) = get_mysql_connection(&credentials).await?;
+            mysql_stream(mysql_pool, sql, &mut batches, cancellation).await?;
+        }
+        Credential::Sqlite(credentials) => {
+            let sqlite_pool = get_sqlite_connection(&credentials).await?;
+            sqlite_stream(sqlite_pool, sql, &mut batches, cancellation).await?;
+        }
+        credentials => {
+            let rows =
+                dispatch_query(credentials, sql, limit, max_bytes_scanned, cancellation).await?;
+            for row in rows {
+                batches.push(row).await?;
+            }
+        }
+    }
+
+    batches.flush().await
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::StreamExt;
+
+    use super::*;
+
+    fn row(id: i64) -> IndexMap<String, DataType> {
+        IndexMap::from([("id".to_string(), DataType::Int8(Some(id)))])
+    }
+
+    fn stream(receiver: mpsc::Receiver<Result<RowBatch>>) -> QueryStream {
+        QueryStream {
+            receiver,
+            metadata: DataMetadataAccumulator::default(),
+            warnings: Vec::new(),
+            _cancel_on_drop: CancellationToken::new().drop_guard(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_row_batch_sender_batches_rows_up_to_limit() {
+        let (sender, receiver) = mpsc::channel(10);
+        let mut batches = RowBatchSender::new(sender, 2, Some(5));
+
+        for id in 0..7 {
+            batches.push(row(id)).await.unwrap();
+        }
+        assert!(batches.is_full());
+        batches.flush().await.unwrap();
+        drop(batches);
+
+        let mut stream = stream(receiver);
+        let mut sizes = Vec::new();
+        while let Some(batch) = stream.next().await {
+            sizes.push(batch.unwrap().len());
+        }
+
+        assert_eq!(sizes, vec![2, 2, 1]);
+        assert_eq!(stream.row_count(), 5);
+
+        let metadata = stream.metadata();
+        assert_eq!(metadata.row_count, 5);
+        assert_eq!(metadata.column_count, 1);
+        assert_eq!(
+            metadata.column_metadata[0].min_value,
+            serde_json::json!(0.0)
+        );
+        assert_eq!(
+            metadata.column_metadata[0].max_value,
+            serde_json::json!(4.0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_row_batch_sender_fails_once_stream_is_dropped() {
+        let (sender, receiver) = mpsc::channel(10);
+        let mut batches = RowBatchSender::new(sender, 1, None);
+        drop(receiver);
+
+        assert!(batches.push(row(1)).await.is_err());
+    }
+}
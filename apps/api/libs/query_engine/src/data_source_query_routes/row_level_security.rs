@@ -0,0 +1,465 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{anyhow, Result};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use sql_analyzer::{analysis::get_dialect, apply_row_level_filters_with_dialect};
+use sqlparser::{
+    parser::Parser,
+    tokenizer::{Token, Tokenizer, Whitespace},
+};
+use uuid::Uuid;
+
+use database::{
+    enums::DataSourceType,
+    pool::get_pg_pool,
+    schema::{data_sources, dataset_row_filter_policies, datasets, users},
+};
+
+// Used in place of a policy that can't be resolved for the user, so no rows leak
+const DENY_ALL_CONDITION: &str = "1 = 0";
+
+/// Row filters a user's queries on one data source must run through, keyed by `schema.table`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RowFilters {
+    table_filters: BTreeMap<String, String>,
+}
+
+impl RowFilters {
+    pub fn is_empty(&self) -> bool {
+        self.table_filters.is_empty()
+    }
+
+    /// True when a policy restricts the rows of `schema.table`
+    pub fn filters_table(&self, schema: &str, table: &str) -> bool {
+        let table = format!("{}.{}", schema, table);
+        self.table_filters
+            .keys()
+            .any(|filtered| filtered.eq_ignore_ascii_case(&table))
+    }
+
+    /// Digest of the resolved filters, so users with different filters never share cached results
+    pub fn fingerprint(&self) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let mut hasher = Sha256::new();
+        for (table, condition) in &self.table_filters {
+            hasher.update(table.as_bytes());
+            hasher.update([0]);
+            hasher.update(condition.as_bytes());
+            hasher.update([0]);
+        }
+        Some(format!("{:x}", hasher.finalize()))
+    }
+}
+
+/// Resolves the row filter policies on a data source's datasets for a user.
+///
+/// Placeholders such as `{{user.attributes.region}}` are replaced with the user's values as
+/// SQL literals. A policy that references an attribute the user doesn't have, or that doesn't
+/// form a valid condition, denies every row of its table rather than being skipped.
+pub async fn get_row_filters(user_id: &Uuid, data_source_id: &Uuid) -> Result<RowFilters> {
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .map_err(|e| anyhow!("Failed to get database connection: {}", e))?;
+
+    let policies = dataset_row_filter_policies::table
+        .inner_join(datasets::table)
+        .filter(datasets::data_source_id.eq(data_source_id))
+        .filter(datasets::deleted_at.is_null())
+        .filter(dataset_row_filter_policies::deleted_at.is_null())
+        .select((
+            datasets::schema,
+            datasets::database_name,
+            dataset_row_filter_policies::filter_expression,
+        ))
+        .order(dataset_row_filter_policies::created_at.asc())
+        .load::<(String, String, String)>(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch row filter policies: {}", e))?
+        .into_iter()
+        // Keyed on the schema too, so a policy doesn't leak to or from a same-named table
+        .map(|(schema, table, filter_expression)| {
+            (format!("{}.{}", schema, table), filter_expression)
+        })
+        .collect::<Vec<_>>();
+
+    if policies.is_empty() {
+        return Ok(RowFilters::default());
+    }
+
+    let (email, attributes) = users::table
+        .filter(users::id.eq(user_id))
+        .select((users::email, users::attributes))
+        .first::<(String, Value)>(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch user attributes: {}", e))?;
+
+    let data_source_type = data_sources::table
+        .filter(data_sources::id.eq(data_source_id))
+        .select(data_sources::type_)
+        .first::<DataSourceType>(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch data source type: {}", e))?;
+
+    let user = UserContext {
+        id: user_id,
+        email: &email,
+        attributes: &attributes,
+    };
+
+    Ok(build_row_filters(policies, &user, &data_source_type))
+}
+
+/// Checks that a policy expression only uses known placeholders and is a single SQL condition
+pub fn validate_filter_expression(
+    filter_expression: &str,
+    data_source_type: &DataSourceType,
+) -> Result<()> {
+    let rendered = render_placeholders(filter_expression, |placeholder| {
+        if placeholder == "user.id"
+            || placeholder == "user.email"
+            || attribute_name(placeholder).is_some()
+        {
+            Ok("NULL".to_string())
+        } else {
+            Err(anyhow!("Unknown placeholder '{{{{{}}}}}'", placeholder))
+        }
+    })?;
+
+    parse_condition(&rendered, data_source_type)
+}
+
+/// Rewrites the query so every filtered table is read through its row filters
+pub(crate) async fn apply_row_filters(
+    sql: &str,
+    row_filters: &RowFilters,
+    data_source_type: &DataSourceType,
+) -> Result<String> {
+    if row_filters.is_empty() {
+        return Ok(sql.to_string());
+    }
+
+    let table_filters: HashMap<String, String> = row_filters
+        .table_filters
+        .iter()
+        .map(|(table, condition)| (table.clone(), condition.clone()))
+        .collect();
+
+    apply_row_level_filters_with_dialect(sql.to_string(), table_filters, data_source_type.to_str())
+        .await
+        .map_err(|e| anyhow!("Failed to apply row-level security filters: {}", e))
+}
+
+struct UserContext<'a> {
+    id: &'a Uuid,
+    email: &'a str,
+    attributes: &'a Value,
+}
+
+fn build_row_filters(
+    policies: Vec<(String, String)>,
+    user: &UserContext,
+    data_source_type: &DataSourceType,
+) -> RowFilters {
+    let mut conditions: BTreeMap<String, Vec<String>> = BTreeMap::new();
+
+    for (table, filter_expression) in policies {
+        let condition = render_filter_expression(&filter_expression, user, data_source_type)
+            .and_then(|condition| {
+                parse_condition(&condition, data_source_type)?;
+                Ok(condition)
+            })
+            .unwrap_or_else(|e| {
+                tracing::warn!(
+                    "Row filter policy on {} denies all rows for user {}: {}",
+                    table,
+                    user.id,
+                    e
+                );
+                DENY_ALL_CONDITION.to_string()
+            });
+
+        conditions
+            .entry(table)
+            .or_default()
+            .push(format!("({})", condition));
+    }
+
+    RowFilters {
+        table_filters: conditions
+            .into_iter()
+            .map(|(table, conditions)| (table, conditions.join(" AND ")))
+            .collect(),
+    }
+}
+
+fn render_filter_expression(
+    filter_expression: &str,
+    user: &UserContext,
+    data_source_type: &DataSourceType,
+) -> Result<String> {
+    render_placeholders(filter_expression, |placeholder| match placeholder {
+        "user.id" => Ok(string_literal(&user.id.to_string(), data_source_type)),
+        "user.email" => Ok(string_literal(user.email, data_source_type)),
+        _ => {
+            let name = attribute_name(placeholder)
+                .ok_or_else(|| anyhow!("Unknown placeholder '{{{{{}}}}}'", placeholder))?;
+            let value = user
+                .attributes
+                .get(name)
+                .ok_or_else(|| anyhow!("User has no '{}' attribute", name))?;
+            attribute_literal(name, value, data_source_type)
+        }
+    })
+}
+
+// Replaces each `{{ placeholder }}` with the SQL text returned for it
+fn render_placeholders(
+    filter_expression: &str,
+    mut resolve: impl FnMut(&str) -> Result<String>,
+) -> Result<String> {
+    let mut rendered = String::with_capacity(filter_expression.len());
+    let mut rest = filter_expression;
+
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..]
+            .find("}}")
+            .map(|end| start + end)
+            .ok_or_else(|| anyhow!("Unclosed placeholder in row filter"))?;
+
+        rendered.push_str(&rest[..start]);
+        rendered.push_str(&resolve(rest[start + 2..end].trim())?);
+        rest = &rest[end + 2..];
+    }
+    rendered.push_str(rest);
+
+    Ok(rendered)
+}
+
+fn attribute_name(placeholder: &str) -> Option<&str> {
+    placeholder
+        .strip_prefix("user.attributes.")
+        .filter(|name| !name.is_empty())
+}
+
+fn attribute_literal(
+    name: &str,
+    value: &Value,
+    data_source_type: &DataSourceType,
+) -> Result<String> {
+    match value {
+        Value::String(value) => Ok(string_literal(value, data_source_type)),
+        Value::Number(value) => Ok(value.to_string()),
+        Value::Bool(value) => Ok(boolean_literal(*value, data_source_type)),
+        // Lists render as comma-separated literals, for use in `column IN ({{...}})`
+        Value::Array(values) if !values.is_empty() => values
+            .iter()
+            .map(|value| match value {
+                Value::Array(_) | Value::Object(_) | Value::Null => Err(anyhow!(
+                    "Attribute '{}' contains a value that isn't a scalar",
+                    name
+                )),
+                value => attribute_literal(name, value, data_source_type),
+            })
+            .collect::<Result<Vec<_>>>()
+            .map(|literals| literals.join(", ")),
+        Value::Array(_) => Err(anyhow!("Attribute '{}' is an empty list", name)),
+        Value::Null | Value::Object(_) => {
+            Err(anyhow!("Attribute '{}' can't be used as a SQL value", name))
+        }
+    }
+}
+
+fn string_literal(value: &str, data_source_type: &DataSourceType) -> String {
+    match data_source_type {
+        // These treat backslashes inside string literals as escapes
+        DataSourceType::MySql
+        | DataSourceType::Mariadb
+        | DataSourceType::BigQuery
+        | DataSourceType::Databricks
+        | DataSourceType::ClickHouse => {
+            format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'"))
+        }
+        _ => format!("'{}'", value.replace('\'', "''")),
+    }
+}
+
+fn boolean_literal(value: bool, data_source_type: &DataSourceType) -> String {
+    match (data_source_type, value) {
+        (DataSourceType::SqlServer, true) => "1".to_string(),
+        (DataSourceType::SqlServer, false) => "0".to_string(),
+        (_, true) => "TRUE".to_string(),
+        (_, false) => "FALSE".to_string(),
+    }
+}
+
+// Rejects anything but a single condition, so a policy can't escape the filtered CTE
+fn parse_condition(condition: &str, data_source_type: &DataSourceType) -> Result<()> {
+    let dialect = get_dialect(data_source_type.to_str());
+
+    // A trailing line comment would swallow the rest of the rewritten query
+    let tokens = Tokenizer::new(dialect, condition)
+        .tokenize()
+        .map_err(|e| anyhow!("Invalid row filter: {}", e))?;
+    if tokens.iter().any(|token| {
+        matches!(
+            token,
+            Token::Whitespace(Whitespace::SingleLineComment { .. })
+                | Token::Whitespace(Whitespace::MultiLineComment(_))
+        )
+    }) {
+        return Err(anyhow!("Invalid row filter: comments are not allowed"));
+    }
+
+    let mut parser = Parser::new(dialect)
+        .try_with_sql(condition)
+        .map_err(|e| anyhow!("Invalid row filter: {}", e))?;
+    parser
+        .parse_expr()
+        .map_err(|e| anyhow!("Invalid row filter: {}", e))?;
+    parser
+        .expect_token(&Token::EOF)
+        .map_err(|e| anyhow!("Invalid row filter: {}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn user_context<'a>(id: &'a Uuid, attributes: &'a Value) -> UserContext<'a> {
+        UserContext {
+            id,
+            email: "ana@example.com",
+            attributes,
+        }
+    }
+
+    #[test]
+    fn test_renders_attributes_as_literals() {
+        let id = Uuid::new_v4();
+        let attributes = json!({"region": "o'hare", "level": 3, "regions": ["emea", "apac"]});
+        let user = user_context(&id, &attributes);
+
+        assert_eq!(
+            render_filter_expression(
+                "region = {{user.attributes.region}} AND level >= {{ user.attributes.level }}",
+                &user,
+                &DataSourceType::Postgres
+            )
+            .unwrap(),
+            "region = 'o''hare' AND level >= 3"
+        );
+        assert_eq!(
+            render_filter_expression(
+                "region = {{user.attributes.region}}",
+                &user,
+                &DataSourceType::BigQuery
+            )
+            .unwrap(),
+            "region = 'o\\'hare'"
+        );
+        assert_eq!(
+            render_filter_expression(
+                "region IN ({{user.attributes.regions}}) OR owner = {{user.email}}",
+                &user,
+                &DataSourceType::Snowflake
+            )
+            .unwrap(),
+            "region IN ('emea', 'apac') OR owner = 'ana@example.com'"
+        );
+    }
+
+    #[test]
+    fn test_unresolvable_policies_deny_all_rows() {
+        let id = Uuid::new_v4();
+        let attributes = json!({"region": "emea' OR 1 = 1 --"});
+        let user = user_context(&id, &attributes);
+
+        let row_filters = build_row_filters(
+            vec![
+                (
+                    "sales.orders".to_string(),
+                    "team = {{user.attributes.team}}".to_string(),
+                ),
+                (
+                    "sales.orders".to_string(),
+                    "region = {{user.attributes.region}}".to_string(),
+                ),
+                (
+                    "sales.customers".to_string(),
+                    "1 = 1) OR (1 = 1".to_string(),
+                ),
+            ],
+            &user,
+            &DataSourceType::Postgres,
+        );
+
+        assert_eq!(
+            row_filters.table_filters.get("sales.orders").unwrap(),
+            "(1 = 0) AND (region = 'emea'' OR 1 = 1 --')"
+        );
+        assert_eq!(
+            row_filters.table_filters.get("sales.customers").unwrap(),
+            "(1 = 0)"
+        );
+        assert!(row_filters.filters_table("SALES", "Orders"));
+        assert!(!row_filters.filters_table("marketing", "orders"));
+    }
+
+    #[test]
+    fn test_validate_filter_expression() {
+        assert!(validate_filter_expression(
+            "region = {{user.attributes.region}}",
+            &DataSourceType::Postgres
+        )
+        .is_ok());
+        assert!(
+            validate_filter_expression("region = {{user.region}}", &DataSourceType::Postgres)
+                .is_err()
+        );
+        assert!(validate_filter_expression(
+            "region = 'emea') UNION SELECT * FROM secrets --",
+            &DataSourceType::Postgres
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_fingerprint_depends_on_filters() {
+        let id = Uuid::new_v4();
+        let emea = json!({"region": "emea"});
+        let apac = json!({"region": "apac"});
+        let policies = || {
+            vec![(
+                "orders".to_string(),
+                "region = {{user.attributes.region}}".to_string(),
+            )]
+        };
+
+        let emea_filters = build_row_filters(
+            policies(),
+            &user_context(&id, &emea),
+            &DataSourceType::Postgres,
+        );
+        let apac_filters = build_row_filters(
+            policies(),
+            &user_context(&id, &apac),
+            &DataSourceType::Postgres,
+        );
+
+        assert_eq!(RowFilters::default().fingerprint(), None);
+        assert_ne!(emea_filters.fingerprint(), apac_filters.fingerprint());
+        assert_eq!(
+            emea_filters.fingerprint(),
+            emea_filters.clone().fingerprint()
+        );
+    }
+}
@@ -1,7 +1,7 @@
 use sqlparser::dialect::{
     GenericDialect, SnowflakeDialect, PostgreSqlDialect, MySqlDialect, 
     BigQueryDialect, MsSqlDialect, DatabricksDialect, SQLiteDialect,
-    AnsiDialect, Dialect
+    DuckDbDialect, ClickHouseDialect, AnsiDialect, Dialect
 };
 use sqlparser::parser::Parser;
 use sqlparser::ast::{Statement, SetExpr, Query};
@@ -16,6 +16,8 @@ fn get_dialect(data_source_type: &str) -> Box<dyn Dialect> {
         "snowflake" => Box::new(SnowflakeDialect {}),
         "sqlserver" | "mssql" => Box::new(MsSqlDialect {}),
         "sqlite" => Box::new(SQLiteDialect {}),
+        "duckdb" => Box::new(DuckDbDialect {}),
+        "clickhouse" => Box::new(ClickHouseDialect {}),
         "ansi" => Box::new(AnsiDialect {}),
         _ => Box::new(GenericDialect {}),
     }
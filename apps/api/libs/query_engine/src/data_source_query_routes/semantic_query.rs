@@ -0,0 +1,626 @@
+use std::collections::{HashMap, HashSet};
+use std::ops::ControlFlow;
+
+use anyhow::{anyhow, Result};
+use diesel::prelude::*;
+use diesel_async::RunQueryDsl;
+use semantic_layer::models::{Argument, Model};
+use sql_analyzer::{
+    analysis::get_dialect, validate_and_substitute_semantic_query, Cardinality, Filter, Metric,
+    Parameter, ParameterType, Relationship, SemanticLayer, ValidationMode,
+};
+use sqlparser::{
+    ast::{Ident, ObjectName, Query, TableAlias, TableFactor, VisitMut, VisitorMut},
+    parser::Parser,
+};
+use uuid::Uuid;
+
+use database::{
+    enums::{DataSourceType, SemanticValidationMode, UserOrganizationRole},
+    models::SemanticQuerySettings,
+    pool::get_pg_pool,
+    schema::{data_sources, datasets, semantic_query_settings, users_to_organizations},
+};
+
+/// How a user's queries on one data source are checked against the semantic layer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SemanticQueryPolicy {
+    pub mode: ValidationMode,
+    /// Every query must be a semantic query, not just the ones run in semantic mode
+    pub required: bool,
+}
+
+/// Resolves the organization's semantic query settings for a user. Organizations without
+/// settings validate semantic queries in flexible mode and don't require them.
+pub async fn get_semantic_query_policy(
+    user_id: &Uuid,
+    data_source_id: &Uuid,
+) -> Result<SemanticQueryPolicy> {
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .map_err(|e| anyhow!("Failed to get database connection: {}", e))?;
+
+    let organization_id = data_sources::table
+        .filter(data_sources::id.eq(data_source_id))
+        .select(data_sources::organization_id)
+        .first::<Uuid>(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch data source organization: {}", e))?;
+
+    let settings = semantic_query_settings::table
+        .filter(semantic_query_settings::organization_id.eq(organization_id))
+        .select(SemanticQuerySettings::as_select())
+        .first::<SemanticQuerySettings>(&mut conn)
+        .await
+        .optional()
+        .map_err(|e| anyhow!("Failed to fetch semantic query settings: {}", e))?;
+
+    let role = users_to_organizations::table
+        .filter(users_to_organizations::user_id.eq(user_id))
+        .filter(users_to_organizations::organization_id.eq(organization_id))
+        .filter(users_to_organizations::deleted_at.is_null())
+        .select(users_to_organizations::role)
+        .first::<UserOrganizationRole>(&mut conn)
+        .await
+        .optional()
+        .map_err(|e| anyhow!("Failed to fetch user role: {}", e))?;
+
+    Ok(resolve_policy(settings.as_ref(), role))
+}
+
+/// The SQL a user's query runs as: unchanged, or validated and substituted as a semantic query
+/// when their organization limits them to semantic queries
+pub async fn apply_semantic_query_policy(
+    user_id: &Uuid,
+    data_source_id: &Uuid,
+    sql: &str,
+) -> Result<String> {
+    let policy = get_semantic_query_policy(user_id, data_source_id).await?;
+    if !policy.required {
+        return Ok(sql.to_string());
+    }
+    prepare_semantic_query(data_source_id, sql, policy.mode).await
+}
+
+/// Validates SQL written against the metrics and filters deployed on a data source's datasets
+/// and substitutes their definitions.
+///
+/// Metrics and filters are referenced as `metric_<name>` and `filter_<name>`, tables by their
+/// model name. The returned SQL reads from the datasets' warehouse tables and still has to go
+/// through `query_engine` for row- and column-level security.
+pub async fn prepare_semantic_query(
+    data_source_id: &Uuid,
+    sql: &str,
+    mode: ValidationMode,
+) -> Result<String> {
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .map_err(|e| anyhow!("Failed to get database connection: {}", e))?;
+
+    let data_source_type = data_sources::table
+        .filter(data_sources::id.eq(data_source_id))
+        .select(data_sources::type_)
+        .first::<DataSourceType>(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch data source type: {}", e))?;
+
+    let deployed = datasets::table
+        .filter(datasets::data_source_id.eq(data_source_id))
+        .filter(datasets::deleted_at.is_null())
+        .filter(datasets::yml_file.is_not_null())
+        .select((
+            datasets::name,
+            datasets::database_identifier,
+            datasets::schema,
+            datasets::database_name,
+            datasets::yml_file,
+        ))
+        .load::<(String, Option<String>, String, String, Option<String>)>(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Failed to fetch datasets: {}", e))?;
+    drop(conn);
+
+    let mut models = Vec::with_capacity(deployed.len());
+    let mut tables = HashMap::new();
+    for (name, database, schema, database_name, yml_file) in deployed {
+        let Some(yml_file) = yml_file else {
+            continue;
+        };
+        let model = match serde_yaml::from_str::<Model>(&yml_file) {
+            Ok(model) => model,
+            Err(e) => {
+                tracing::warn!(
+                    "Skipping dataset {} in semantic layer, invalid model: {}",
+                    name,
+                    e
+                );
+                continue;
+            }
+        };
+        let table: Vec<String> = database
+            .into_iter()
+            .chain([schema, database_name])
+            .filter(|part| !part.is_empty())
+            .collect();
+        tables.insert(model.name.clone(), table);
+        models.push(model);
+    }
+
+    let semantic_layer = build_semantic_layer(&models);
+    let substituted = validate_and_substitute_semantic_query(sql.to_string(), semantic_layer, mode)
+        .await
+        .map_err(|e| anyhow!("Invalid semantic query: {}", e))?;
+
+    qualify_tables(&substituted, &tables, &data_source_type)
+}
+
+fn resolve_policy(
+    settings: Option<&SemanticQuerySettings>,
+    role: Option<UserOrganizationRole>,
+) -> SemanticQueryPolicy {
+    let Some(settings) = settings else {
+        return SemanticQueryPolicy {
+            mode: ValidationMode::Flexible,
+            required: false,
+        };
+    };
+
+    if settings.strict_for_restricted_queriers
+        && role == Some(UserOrganizationRole::RestrictedQuerier)
+    {
+        return SemanticQueryPolicy {
+            mode: ValidationMode::Strict,
+            required: true,
+        };
+    }
+
+    SemanticQueryPolicy {
+        mode: match settings.validation_mode {
+            SemanticValidationMode::Strict => ValidationMode::Strict,
+            SemanticValidationMode::Flexible => ValidationMode::Flexible,
+        },
+        required: false,
+    }
+}
+
+/// Converts deployed models into the semantic layer `sql_analyzer` validates against
+fn build_semantic_layer(models: &[Model]) -> SemanticLayer {
+    let mut semantic_layer = SemanticLayer::new();
+
+    for model in models {
+        semantic_layer.add_table(&model.name, model_columns(model));
+
+        for metric in &model.metrics {
+            let name = semantic_name("metric_", &metric.name);
+            if semantic_layer.has_metric(&name) {
+                tracing::warn!("Skipping duplicate metric {} in model {}", name, model.name);
+                continue;
+            }
+            semantic_layer.add_metric(Metric {
+                name,
+                table: model.name.clone(),
+                expression: semantic_expression(model, &metric.expr),
+                parameters: metric.args.iter().map(parameter).collect(),
+                description: metric.description.clone(),
+            });
+        }
+
+        for filter in &model.filters {
+            let name = semantic_name("filter_", &filter.name);
+            if semantic_layer.has_filter(&name) {
+                tracing::warn!("Skipping duplicate filter {} in model {}", name, model.name);
+                continue;
+            }
+            semantic_layer.add_filter(Filter {
+                name,
+                table: model.name.clone(),
+                expression: semantic_expression(model, &filter.expr),
+                parameters: filter.args.iter().map(parameter).collect(),
+                description: filter.description.clone(),
+            });
+        }
+
+        for relationship in &model.relationships {
+            semantic_layer.add_relationship(Relationship {
+                from_table: model.name.clone(),
+                from_column: relationship.source_col.clone(),
+                to_table: relationship.name.clone(),
+                to_column: relationship.ref_col.clone(),
+                cardinality: relationship.cardinality.as_deref().and_then(cardinality),
+            });
+        }
+    }
+
+    semantic_layer
+}
+
+// Dimensions, measures and join keys, in declaration order
+fn model_columns(model: &Model) -> Vec<&str> {
+    let mut seen = HashSet::new();
+    model
+        .dimensions
+        .iter()
+        .map(|dimension| dimension.name.as_str())
+        .chain(model.measures.iter().map(|measure| measure.name.as_str()))
+        .chain(
+            model
+                .relationships
+                .iter()
+                .map(|relationship| relationship.source_col.as_str()),
+        )
+        .filter(|column| seen.insert(*column))
+        .collect()
+}
+
+// sql_analyzer only recognizes metrics and filters by their prefix
+fn semantic_name(prefix: &str, name: &str) -> String {
+    if name.starts_with(prefix) {
+        name.to_string()
+    } else {
+        format!("{}{}", prefix, name)
+    }
+}
+
+/// Rewrites a model expression for substitution: `{arg}` placeholders become the `{{arg}}`
+/// form sql_analyzer fills in, and bare columns of the model are qualified with its name so
+/// they stay unambiguous once the expression is inlined into a join
+fn semantic_expression(model: &Model, expr: &str) -> String {
+    let columns: HashSet<&str> = model_columns(model).into_iter().collect();
+    let chars: Vec<char> = expr.chars().collect();
+    let mut sql = String::with_capacity(expr.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c == '{' {
+            if chars.get(i + 1) == Some(&'{') {
+                // Already in sql_analyzer's form
+                let close = chars[i..]
+                    .windows(2)
+                    .position(|pair| pair == ['}', '}'])
+                    .map(|offset| i + offset + 2);
+                if let Some(close) = close {
+                    sql.extend(&chars[i..close]);
+                    i = close;
+                    continue;
+                }
+            }
+            let end = chars[i + 1..]
+                .iter()
+                .position(|c| !(c.is_ascii_alphanumeric() || *c == '_'))
+                .map(|offset| i + 1 + offset);
+            if let Some(end) = end.filter(|&end| end > i + 1 && chars[end] == '}') {
+                let name: String = chars[i + 1..end].iter().collect();
+                sql.push_str(&format!("{{{{{}}}}}", name));
+                i = end + 1;
+                continue;
+            }
+        }
+
+        if c == '\'' || c == '"' || c == '`' {
+            // Literals and quoted identifiers are copied untouched
+            sql.push(c);
+            i += 1;
+            while i < chars.len() {
+                sql.push(chars[i]);
+                i += 1;
+                if chars[i - 1] == c {
+                    break;
+                }
+            }
+            continue;
+        }
+
+        if c.is_ascii_digit() {
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.') {
+                sql.push(chars[i]);
+                i += 1;
+            }
+            continue;
+        }
+
+        if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let ident: String = chars[start..i].iter().collect();
+            let after_dot = sql.trim_end().ends_with('.');
+            let before_dot = chars.get(i) == Some(&'.');
+            let is_call = chars[i..]
+                .iter()
+                .find(|c| !c.is_whitespace())
+                .is_some_and(|c| *c == '(');
+
+            if !after_dot && !before_dot && !is_call && columns.contains(ident.as_str()) {
+                sql.push_str(&format!("{}.{}", model.name, ident));
+            } else {
+                sql.push_str(&ident);
+            }
+            continue;
+        }
+
+        sql.push(c);
+        i += 1;
+    }
+
+    sql
+}
+
+fn parameter(argument: &Argument) -> Parameter {
+    let param_type = match argument.type_.trim().to_lowercase().as_str() {
+        "number" | "integer" | "int" | "float" | "decimal" | "numeric" => ParameterType::Number,
+        "date" | "datetime" | "timestamp" => ParameterType::Date,
+        "boolean" | "bool" => ParameterType::Boolean,
+        _ => ParameterType::String,
+    };
+    Parameter {
+        name: argument.name.clone(),
+        param_type,
+        default: None,
+    }
+}
+
+fn cardinality(cardinality: &str) -> Option<Cardinality> {
+    match cardinality
+        .trim()
+        .to_lowercase()
+        .replace(['_', ' '], "-")
+        .as_str()
+    {
+        "one-to-one" => Some(Cardinality::OneToOne),
+        "one-to-many" => Some(Cardinality::OneToMany),
+        "many-to-one" => Some(Cardinality::ManyToOne),
+        "many-to-many" => Some(Cardinality::ManyToMany),
+        _ => None,
+    }
+}
+
+/// Points model names at the datasets' warehouse tables, aliased to the model name so the
+/// substituted `model.column` references keep resolving
+fn qualify_tables(
+    sql: &str,
+    tables: &HashMap<String, Vec<String>>,
+    data_source_type: &DataSourceType,
+) -> Result<String> {
+    let dialect = get_dialect(data_source_type.to_str());
+    let mut statements = Parser::parse_sql(dialect, sql)
+        .map_err(|e| anyhow!("Failed to parse substituted semantic query: {}", e))?;
+
+    let mut qualifier = TableQualifier {
+        tables,
+        ctes: HashSet::new(),
+    };
+    let _ = statements.visit(&mut qualifier);
+
+    Ok(statements
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; "))
+}
+
+struct TableQualifier<'a> {
+    tables: &'a HashMap<String, Vec<String>>,
+    // CTEs shadow models of the same name
+    ctes: HashSet<String>,
+}
+
+impl VisitorMut for TableQualifier<'_> {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<Self::Break> {
+        if let Some(with) = &query.with {
+            for cte in &with.cte_tables {
+                self.ctes.insert(cte.alias.name.value.clone());
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(
+        &mut self,
+        table_factor: &mut TableFactor,
+    ) -> ControlFlow<Self::Break> {
+        if let TableFactor::Table { name, alias, .. } = table_factor {
+            if let [model_name] = name.0.as_slice() {
+                if self.ctes.contains(&model_name.value) {
+                    return ControlFlow::Continue(());
+                }
+                if let Some(table) = self.tables.get(&model_name.value) {
+                    if alias.is_none() {
+                        *alias = Some(TableAlias {
+                            name: model_name.clone(),
+                            columns: Vec::new(),
+                        });
+                    }
+                    *name = ObjectName(table.iter().map(Ident::new).collect());
+                }
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    const ORDERS_YML: &str = r#"
+name: orders
+dimensions:
+  - name: status
+    type: string
+  - name: created_at
+    type: timestamp
+measures:
+  - name: amount
+    type: number
+metrics:
+  - name: total_revenue
+    expr: "SUM(amount)"
+  - name: revenue_over
+    expr: "SUM(CASE WHEN amount > {threshold} THEN amount END)"
+    args:
+      - name: threshold
+        type: number
+filters:
+  - name: completed
+    expr: "status = 'completed'"
+relationships:
+  - name: customers
+    source_col: customer_id
+    ref_col: id
+    cardinality: many-to-one
+"#;
+
+    const CUSTOMERS_YML: &str = r#"
+name: customers
+dimensions:
+  - name: id
+  - name: region
+"#;
+
+    fn settings(
+        validation_mode: SemanticValidationMode,
+        strict_for_restricted_queriers: bool,
+    ) -> SemanticQuerySettings {
+        SemanticQuerySettings {
+            organization_id: Uuid::new_v4(),
+            validation_mode,
+            strict_for_restricted_queriers,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn semantic_layer() -> SemanticLayer {
+        let models: Vec<Model> = [ORDERS_YML, CUSTOMERS_YML]
+            .iter()
+            .map(|yml| serde_yaml::from_str(yml).unwrap())
+            .collect();
+        build_semantic_layer(&models)
+    }
+
+    #[test]
+    fn test_restricted_queriers_can_be_locked_to_strict_mode() {
+        let locked = settings(SemanticValidationMode::Flexible, true);
+        assert_eq!(
+            resolve_policy(Some(&locked), Some(UserOrganizationRole::RestrictedQuerier)),
+            SemanticQueryPolicy {
+                mode: ValidationMode::Strict,
+                required: true,
+            }
+        );
+        assert_eq!(
+            resolve_policy(Some(&locked), Some(UserOrganizationRole::Querier)),
+            SemanticQueryPolicy {
+                mode: ValidationMode::Flexible,
+                required: false,
+            }
+        );
+
+        let strict = settings(SemanticValidationMode::Strict, false);
+        assert_eq!(
+            resolve_policy(Some(&strict), Some(UserOrganizationRole::RestrictedQuerier)).mode,
+            ValidationMode::Strict
+        );
+        assert!(!resolve_policy(None, Some(UserOrganizationRole::RestrictedQuerier)).required);
+    }
+
+    #[test]
+    fn test_builds_semantic_layer_from_models() {
+        let semantic_layer = semantic_layer();
+
+        assert!(semantic_layer.has_column("orders", "customer_id"));
+        assert!(semantic_layer.has_column("customers", "region"));
+        assert!(semantic_layer.are_tables_related("orders", "customers"));
+
+        let revenue = semantic_layer.get_metric("metric_total_revenue").unwrap();
+        assert_eq!(revenue.expression, "SUM(orders.amount)");
+
+        let revenue_over = semantic_layer.get_metric("metric_revenue_over").unwrap();
+        assert_eq!(
+            revenue_over.expression,
+            "SUM(CASE WHEN orders.amount > {{threshold}} THEN orders.amount END)"
+        );
+        assert_eq!(revenue_over.parameters[0].param_type, ParameterType::Number);
+
+        let completed = semantic_layer.get_filter("filter_completed").unwrap();
+        assert_eq!(completed.expression, "orders.status = 'completed'");
+    }
+
+    #[tokio::test]
+    async fn test_substituted_query_reads_warehouse_tables() {
+        let sql = "SELECT customers.region, metric_total_revenue FROM orders \
+                   JOIN customers ON orders.customer_id = customers.id \
+                   WHERE filter_completed GROUP BY customers.region";
+        let substituted = validate_and_substitute_semantic_query(
+            sql.to_string(),
+            semantic_layer(),
+            ValidationMode::Flexible,
+        )
+        .await
+        .unwrap();
+
+        let tables = HashMap::from([
+            (
+                "orders".to_string(),
+                vec!["analytics".to_string(), "orders".to_string()],
+            ),
+            (
+                "customers".to_string(),
+                vec!["analytics".to_string(), "customers".to_string()],
+            ),
+        ]);
+        let qualified = qualify_tables(&substituted, &tables, &DataSourceType::Postgres).unwrap();
+
+        assert!(
+            qualified.contains("FROM analytics.orders AS orders"),
+            "{}",
+            qualified
+        );
+        assert!(
+            qualified.contains("JOIN analytics.customers AS customers"),
+            "{}",
+            qualified
+        );
+        assert!(qualified.contains("SUM(orders.amount)"), "{}", qualified);
+        assert!(
+            qualified.contains("orders.status = 'completed'"),
+            "{}",
+            qualified
+        );
+    }
+
+    #[tokio::test]
+    async fn test_strict_mode_rejects_ad_hoc_calculations() {
+        let sql = "SELECT SUM(orders.amount) * 2 FROM orders";
+        let result = validate_and_substitute_semantic_query(
+            sql.to_string(),
+            semantic_layer(),
+            ValidationMode::Strict,
+        )
+        .await;
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_ctes_shadow_model_names() {
+        let tables = HashMap::from([(
+            "orders".to_string(),
+            vec!["analytics".to_string(), "orders".to_string()],
+        )]);
+        let qualified = qualify_tables(
+            "WITH orders AS (SELECT 1 AS id) SELECT o.id FROM orders o",
+            &tables,
+            &DataSourceType::Postgres,
+        )
+        .unwrap();
+        assert!(!qualified.contains("analytics"), "{}", qualified);
+    }
+}
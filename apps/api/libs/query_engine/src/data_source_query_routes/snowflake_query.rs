@@ -18,8 +18,17 @@ use snowflake_api::{QueryResult, SnowflakeApi};
 use serde_json::{Map as JsonMap, Value};
 
 use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::{
+    credentials::SnowflakeCredentials,
+    data_source_connections::get_snowflake_client::{
+        get_snowflake_client, SnowflakeClient, SqlApiColumn,
+    },
+    data_types::DataType,
+};
 
-use crate::data_types::DataType;
+use super::query_cancellation::{query_cancelled, CancellationToken};
 
 // -------------------------
 // String & JSON Processing
@@ -1691,6 +1700,107 @@ fn prepare_query(query: &str) -> String {
     query.to_string()
 }
 
+// -------------------------
+// SQL API Results
+// -------------------------
+
+fn process_sql_api_rows(
+    columns: &[SqlApiColumn],
+    rows: Vec<Vec<Option<String>>>,
+    limit: usize,
+) -> Vec<IndexMap<String, DataType>> {
+    rows.into_iter()
+        .take(limit)
+        .map(|row| {
+            columns
+                .iter()
+                .zip(row)
+                .map(|(column, value)| {
+                    (column.name.to_lowercase(), convert_sql_api_value(column, value))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// The SQL API encodes every value as a string: dates as days since the epoch, times and
+// timestamps as seconds with a fraction, and TIMESTAMP_TZ with a trailing offset
+fn convert_sql_api_value(column: &SqlApiColumn, value: Option<String>) -> DataType {
+    let value = match value {
+        Some(value) => value,
+        None => return DataType::Null,
+    };
+
+    match column.type_.as_str() {
+        "fixed" if column.scale.unwrap_or(0) == 0 => match value.parse::<i64>() {
+            Ok(int) => DataType::Int8(Some(int)),
+            // NUMBER(38, 0) can overflow i64, keep the exact digits
+            Err(_) => DataType::Text(Some(value)),
+        },
+        "fixed" | "real" => DataType::Float8(value.parse::<f64>().ok()),
+        "boolean" => DataType::Bool(value.parse::<bool>().ok()),
+        "date" => DataType::Date(
+            value
+                .parse::<i64>()
+                .ok()
+                .and_then(|days| DateTime::from_timestamp(days * 86_400, 0))
+                .map(|dt| dt.date_naive()),
+        ),
+        "time" => DataType::Time(parse_epoch_seconds(&value).and_then(|(secs, nanos)| {
+            NaiveTime::from_num_seconds_from_midnight_opt(secs as u32, nanos)
+        })),
+        "timestamp_ntz" => DataType::Timestamp(
+            parse_epoch_seconds(&value)
+                .and_then(|(secs, nanos)| DateTime::from_timestamp(secs, nanos))
+                .map(|dt| dt.naive_utc()),
+        ),
+        "timestamp_ltz" | "timestamp_tz" => DataType::Timestamptz(
+            value
+                .split_whitespace()
+                .next()
+                .and_then(parse_epoch_seconds)
+                .and_then(|(secs, nanos)| DateTime::from_timestamp(secs, nanos)),
+        ),
+        "variant" | "object" | "array" => DataType::Json(
+            serde_json::from_str::<Value>(&value)
+                .ok()
+                .map(process_json_value),
+        ),
+        "binary" => DataType::Bytea(decode_hex(&value)),
+        _ => DataType::Text(Some(process_string_value(value))),
+    }
+}
+
+// "-1.5" is half a second before the epoch, i.e. (-2, 500_000_000)
+fn parse_epoch_seconds(value: &str) -> Option<(i64, u32)> {
+    let (whole, fraction) = value.split_once('.').unwrap_or((value, ""));
+    let secs = whole.parse::<i64>().ok()?;
+
+    let fraction = fraction.get(..9).unwrap_or(fraction);
+    let nanos = if fraction.is_empty() {
+        0
+    } else {
+        fraction.parse::<u32>().ok()? * 10_u32.pow(9 - fraction.len() as u32)
+    };
+
+    if whole.starts_with('-') && nanos > 0 {
+        Some((secs - 1, 1_000_000_000 - nanos))
+    } else {
+        Some((secs, nanos))
+    }
+}
+
+fn decode_hex(value: &str) -> Option<Vec<u8>> {
+    value
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| match std::str::from_utf8(pair) {
+            Ok(pair) if pair.len() == 2 => u8::from_str_radix(pair, 16).ok(),
+            _ => None,
+        })
+        .collect()
+}
+
 // Add a simpler error handling approach
 #[derive(Debug)]
 pub enum ProcessingResult {
@@ -1762,9 +1872,11 @@ fn safe_process_record_batch(batch: &RecordBatch) -> ProcessingResult {
 
 // Update the main function signature and basic error handling
 pub async fn snowflake_query(
-    mut snowflake_client: SnowflakeApi,
+    snowflake_client: SnowflakeClient,
+    credentials: &SnowflakeCredentials,
     query: String,
     limit: Option<i64>,
+    cancellation: CancellationToken,
 ) -> Result<ProcessingResult, Error> {
     // Get the limit value, defaulting to 5000 if not specified
     let default_limit = 5000;
@@ -1772,7 +1884,58 @@ pub async fn snowflake_query(
     
     let limited_query = prepare_query(&query);
 
-    let result = match snowflake_client.exec(&limited_query).await {
+    match snowflake_client {
+        SnowflakeClient::Connector(snowflake_client) => {
+            connector_query(snowflake_client, credentials, limited_query, limit_value, cancellation)
+                .await
+        }
+        SnowflakeClient::SqlApi(snowflake_client) => {
+            let result = match snowflake_client
+                .query(&limited_query, limit_value, &cancellation)
+                .await
+            {
+                Ok(result) => result,
+                Err(e) => {
+                    tracing::error!("There was an issue while fetching the tables: {}", e);
+                    return Err(e);
+                }
+            };
+
+            Ok(ProcessingResult::Processed(process_sql_api_rows(
+                &result.columns,
+                result.rows,
+                limit_value,
+            )))
+        }
+    }
+}
+
+async fn connector_query(
+    mut snowflake_client: SnowflakeApi,
+    credentials: &SnowflakeCredentials,
+    limited_query: String,
+    limit_value: usize,
+    cancellation: CancellationToken,
+) -> Result<ProcessingResult, Error> {
+    // The tag lets another session find this query in the query history to cancel it
+    let query_tag = format!("buster-{}", Uuid::new_v4());
+    if let Err(e) = snowflake_client
+        .exec(&format!("ALTER SESSION SET QUERY_TAG = '{}'", query_tag))
+        .await
+    {
+        tracing::warn!("Failed to tag Snowflake session, query will not be cancellable: {}", e);
+    }
+
+    let exec_result = tokio::select! {
+        result = snowflake_client.exec(&limited_query) => result,
+        _ = cancellation.cancelled() => {
+            cancel_tagged_queries(credentials, &query_tag).await;
+            let _ = snowflake_client.close_session().await;
+            return Err(query_cancelled());
+        }
+    };
+
+    let result = match exec_result {
         Ok(result) => match result {
             QueryResult::Arrow(result) => {
                 let mut all_rows = Vec::with_capacity(limit_value);
@@ -1855,6 +2018,32 @@ pub async fn snowflake_query(
     Ok(result)
 }
 
+// Runs from a new session, since the query's own session is busy until the query returns
+async fn cancel_tagged_queries(credentials: &SnowflakeCredentials, query_tag: &str) {
+    let mut cancel_client = match get_snowflake_client(credentials).await {
+        Ok(SnowflakeClient::Connector(client)) => client,
+        // SQL API statements are cancelled by their handle instead
+        Ok(SnowflakeClient::SqlApi(_)) => return,
+        Err(e) => {
+            tracing::warn!("Failed to connect to Snowflake to cancel query {}: {}", query_tag, e);
+            return;
+        }
+    };
+
+    let cancel_sql = format!(
+        "SELECT SYSTEM$CANCEL_QUERY(query_id) \
+         FROM TABLE(INFORMATION_SCHEMA.QUERY_HISTORY_BY_USER(RESULT_LIMIT => 1000)) \
+         WHERE query_tag = '{}' \
+         AND execution_status IN ('RUNNING', 'QUEUED', 'RESUMING_WAREHOUSE', 'BLOCKED')",
+        query_tag
+    );
+    if let Err(e) = cancel_client.exec(&cancel_sql).await {
+        tracing::warn!("Failed to cancel Snowflake query {}: {}", query_tag, e);
+    }
+
+    let _ = cancel_client.close_session().await;
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -3594,5 +3783,83 @@ mod tests {
 
         println!("✓ Verified Struct Timestamps with various scales and TZ/NTZ");
     }
-}
 
+    fn sql_api_column(type_: &str, scale: Option<i64>) -> SqlApiColumn {
+        SqlApiColumn {
+            name: "VALUE".to_string(),
+            type_: type_.to_string(),
+            scale,
+        }
+    }
+
+    #[test]
+    fn test_sql_api_value_conversion() {
+        let convert = |type_: &str, scale: Option<i64>, value: &str| {
+            convert_sql_api_value(&sql_api_column(type_, scale), Some(value.to_string()))
+        };
+
+        assert_eq!(convert("fixed", Some(0), "42"), DataType::Int8(Some(42)));
+        assert_eq!(
+            convert("fixed", Some(0), "99999999999999999999999999999999999999"),
+            DataType::Text(Some("99999999999999999999999999999999999999".to_string()))
+        );
+        assert_eq!(convert("fixed", Some(2), "12.34"), DataType::Float8(Some(12.34)));
+        assert_eq!(convert("boolean", None, "true"), DataType::Bool(Some(true)));
+        assert_eq!(
+            convert("date", None, "19723"),
+            DataType::Date(NaiveDate::from_ymd_opt(2024, 1, 1))
+        );
+        assert_eq!(
+            convert("time", Some(9), "45296.500000000"),
+            DataType::Time(NaiveTime::from_hms_milli_opt(12, 34, 56, 500))
+        );
+        assert_eq!(
+            convert("timestamp_ntz", Some(9), "1704067200.250000000"),
+            DataType::Timestamp(
+                NaiveDate::from_ymd_opt(2024, 1, 1)
+                    .unwrap()
+                    .and_hms_milli_opt(0, 0, 0, 250)
+            )
+        );
+        assert_eq!(
+            convert("timestamp_tz", Some(9), "1704067200.000000000 1500"),
+            DataType::Timestamptz(DateTime::from_timestamp(1_704_067_200, 0))
+        );
+        assert_eq!(
+            convert("variant", None, "{\"a\": [1, 2]}"),
+            DataType::Json(Some(serde_json::json!({"a": [1, 2]})))
+        );
+        assert_eq!(
+            convert("binary", None, "00ff10"),
+            DataType::Bytea(Some(vec![0x00, 0xff, 0x10]))
+        );
+        assert_eq!(
+            convert_sql_api_value(&sql_api_column("text", None), None),
+            DataType::Null
+        );
+    }
+
+    #[test]
+    fn test_parse_epoch_seconds() {
+        assert_eq!(parse_epoch_seconds("10"), Some((10, 0)));
+        assert_eq!(parse_epoch_seconds("10.5"), Some((10, 500_000_000)));
+        assert_eq!(parse_epoch_seconds("-1.5"), Some((-2, 500_000_000)));
+        assert_eq!(parse_epoch_seconds("-0.000000001"), Some((-1, 999_999_999)));
+        assert_eq!(parse_epoch_seconds("abc"), None);
+    }
+
+    #[test]
+    fn test_process_sql_api_rows_applies_limit() {
+        let columns = vec![sql_api_column("fixed", Some(0))];
+        let rows = vec![
+            vec![Some("1".to_string())],
+            vec![Some("2".to_string())],
+            vec![None],
+        ];
+
+        let processed = process_sql_api_rows(&columns, rows, 2);
+        assert_eq!(processed.len(), 2);
+        assert_eq!(processed[0]["value"], DataType::Int8(Some(1)));
+        assert_eq!(processed[1]["value"], DataType::Int8(Some(2)));
+    }
+}
@@ -1,4 +1,8 @@
-use crate::data_types::DataType;
+use crate::{
+    credentials::SqlServerCredentials,
+    data_source_connections::get_sql_server_connection::get_sql_server_connection,
+    data_types::DataType,
+};
 use anyhow::{anyhow, Error, Result};
 use chrono::NaiveDateTime;
 use indexmap::IndexMap;
@@ -6,10 +10,14 @@ use tiberius::{numeric::Decimal, Client, ColumnType};
 use tokio::net::TcpStream;
 use tokio_util::compat::Compat;
 
+use super::query_cancellation::{query_cancelled, CancellationToken};
+
 pub async fn sql_server_query(
     mut client: Client<Compat<TcpStream>>,
+    credentials: &SqlServerCredentials,
     query: String,
     limit: Option<i64>,
+    cancellation: CancellationToken,
 ) -> Result<Vec<IndexMap<std::string::String, DataType>>, Error> {
     // Apply the limit directly at the database level
     let default_limit = 5000;
@@ -32,8 +40,47 @@ pub async fn sql_server_query(
         query
     };
     
+    // The session id identifies the running query to KILL
+    let spid = match client.query("SELECT @@SPID", &[]).await {
+        Ok(stream) => stream
+            .into_row()
+            .await?
+            .and_then(|row| row.get::<i16, _>(0))
+            .ok_or_else(|| anyhow!("Unable to determine SQL Server session id"))?,
+        Err(e) => return Err(anyhow!("Unable to determine SQL Server session id: {}", e)),
+    };
+
+    tokio::select! {
+        result = fetch_rows(&mut client, &sql_with_limit, limit_value) => result,
+        _ = cancellation.cancelled() => {
+            kill_session(credentials, spid).await;
+            Err(query_cancelled())
+        }
+    }
+}
+
+// KILL has to come from another session; the killed session's client is unusable afterwards
+async fn kill_session(credentials: &SqlServerCredentials, spid: i16) {
+    let (mut client, _ssh_tunnel) = match get_sql_server_connection(credentials).await {
+        Ok(connection) => connection,
+        Err(e) => {
+            tracing::warn!("Failed to connect to SQL Server to kill session {}: {}", spid, e);
+            return;
+        }
+    };
+
+    if let Err(e) = client.execute(format!("KILL {}", spid), &[]).await {
+        tracing::warn!("Failed to kill SQL Server session {}: {}", spid, e);
+    }
+}
+
+async fn fetch_rows(
+    client: &mut Client<Compat<TcpStream>>,
+    sql_with_limit: &str,
+    limit_value: i64,
+) -> Result<Vec<IndexMap<std::string::String, DataType>>, Error> {
     // Execute the query with limit
-    let rows = match client.query(&sql_with_limit, &[]).await {
+    let rows = match client.query(sql_with_limit, &[]).await {
         Ok(rows) => rows,
         Err(e) => {
             tracing::error!("Unable to execute query: {:?}", e);
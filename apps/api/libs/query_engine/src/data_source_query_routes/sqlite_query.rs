@@ -0,0 +1,160 @@
+use indexmap::IndexMap;
+
+use anyhow::Error;
+use futures::TryStreamExt;
+use sqlx::{sqlite::SqliteRow, Column, Pool, Row, Sqlite, TypeInfo, ValueRef};
+
+use crate::data_types::DataType;
+
+use super::{
+    query_cancellation::{query_cancelled, CancellationToken},
+    query_stream::RowBatchSender,
+};
+
+pub async fn sqlite_query(
+    pool: Pool<Sqlite>,
+    query: String,
+    limit: Option<i64>,
+    cancellation: CancellationToken,
+) -> Result<Vec<IndexMap<std::string::String, DataType>>, Error> {
+    // Get the limit value, defaulting to 5000 if not specified
+    let default_limit = 5000;
+    let limit_value = limit.unwrap_or(default_limit) as usize;
+
+    // SQLite runs in-process; dropping the row stream stops the statement
+    tokio::select! {
+        result = fetch_rows(&pool, &query, limit_value) => result,
+        _ = cancellation.cancelled() => Err(query_cancelled()),
+    }
+}
+
+/// Same as `sqlite_query`, but rows are handed to `batches` as they're read instead of being
+/// collected
+pub(crate) async fn sqlite_stream(
+    pool: Pool<Sqlite>,
+    query: String,
+    batches: &mut RowBatchSender,
+    cancellation: CancellationToken,
+) -> Result<(), Error> {
+    tokio::select! {
+        result = stream_rows(&pool, &query, batches) => result,
+        _ = cancellation.cancelled() => Err(query_cancelled()),
+    }
+}
+
+async fn fetch_rows(
+    pool: &Pool<Sqlite>,
+    query: &str,
+    limit_value: usize,
+) -> Result<Vec<IndexMap<String, DataType>>, Error> {
+    // Create query stream without appending LIMIT
+    let mut stream = sqlx::query(query).fetch(pool);
+
+    // Pre-allocate result vector with estimated capacity to reduce allocations
+    let mut result: Vec<IndexMap<String, DataType>> = Vec::with_capacity(limit_value);
+
+    while let Some(row) = stream.try_next().await? {
+        result.push(convert_row(&row));
+
+        // Stop processing if we've reached the limit
+        if result.len() >= limit_value {
+            break;
+        }
+    }
+
+    Ok(result)
+}
+
+async fn stream_rows(
+    pool: &Pool<Sqlite>,
+    query: &str,
+    batches: &mut RowBatchSender,
+) -> Result<(), Error> {
+    let mut stream = sqlx::query(query).fetch(pool);
+
+    while let Some(row) = stream.try_next().await? {
+        batches.push(convert_row(&row)).await?;
+
+        if batches.is_full() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+fn convert_row(row: &SqliteRow) -> IndexMap<String, DataType> {
+    let mut row_map: IndexMap<String, DataType> = IndexMap::with_capacity(row.len());
+
+    for (i, column) in row.columns().iter().enumerate() {
+        let column_name = column.name();
+        let column_value = convert_value(row, i, column.type_info().name());
+
+        row_map.insert(column_name.to_string(), column_value);
+    }
+
+    row_map
+}
+
+fn convert_value(row: &SqliteRow, i: usize, declared_type: &str) -> DataType {
+    // SQLite is dynamically typed: expressions have no declared type, so fall back to
+    // the storage class of the value itself
+    let type_name = match row.try_get_raw(i) {
+        Ok(raw) if raw.is_null() => return DataType::Null,
+        Ok(raw) if declared_type == "NULL" => raw.type_info().name().to_string(),
+        _ => declared_type.to_string(),
+    };
+
+    match type_name.to_uppercase().as_str() {
+        "BOOLEAN" => DataType::Bool(row.try_get::<bool, _>(i).ok()),
+        "INTEGER" => DataType::Int8(row.try_get::<i64, _>(i).ok()),
+        "REAL" | "NUMERIC" => DataType::Float8(row.try_get::<f64, _>(i).ok()),
+        "TEXT" => DataType::Text(row.try_get::<String, _>(i).ok()),
+        "BLOB" => DataType::Bytea(row.try_get::<Vec<u8>, _>(i).ok()),
+        "DATE" => DataType::Date(row.try_get::<chrono::NaiveDate, _>(i).ok()),
+        "TIME" => DataType::Time(row.try_get::<chrono::NaiveTime, _>(i).ok()),
+        "DATETIME" => DataType::Timestamp(row.try_get::<chrono::NaiveDateTime, _>(i).ok()),
+        _ => DataType::Unknown(row.try_get::<String, _>(i).ok()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[tokio::test]
+    async fn test_sqlite_query_converts_types() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+
+        sqlx::query("CREATE TABLE orders (id INTEGER, customer TEXT, amount REAL, created_at DATETIME)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO orders VALUES (1, 'acme', 12.5, '2024-03-01 10:00:00'), (2, NULL, 3.0, NULL)")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let results = sqlite_query(
+            pool,
+            "SELECT id, customer, amount, created_at, amount * 2 AS doubled FROM orders ORDER BY id".to_string(),
+            None,
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["id"], DataType::Int8(Some(1)));
+        assert_eq!(results[0]["customer"], DataType::Text(Some("acme".to_string())));
+        assert_eq!(results[0]["amount"], DataType::Float8(Some(12.5)));
+        assert_eq!(results[0]["doubled"], DataType::Float8(Some(25.0)));
+        assert!(matches!(results[0]["created_at"], DataType::Timestamp(Some(_))));
+        assert_eq!(results[1]["customer"], DataType::Null);
+    }
+}
@@ -0,0 +1,163 @@
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use indexmap::IndexMap;
+
+use anyhow::{anyhow, Error};
+use serde_json::Value;
+
+use crate::{data_source_connections::get_trino_client::Trino, data_types::DataType};
+
+use super::query_cancellation::CancellationToken;
+
+pub async fn trino_query(
+    trino_client: Trino,
+    query: String,
+    limit: Option<i64>,
+    cancellation: CancellationToken,
+) -> Result<Vec<IndexMap<std::string::String, DataType>>, Error> {
+    // Get the limit value, defaulting to 5000 if not specified
+    let default_limit = 5000;
+    let limit_value = limit.unwrap_or(default_limit) as usize;
+
+    // The client cancels the query once enough rows have been paged in, or on cancellation
+    let results = match trino_client.query(query, Some(limit_value), &cancellation).await {
+        Ok(results) => results,
+        Err(e) => {
+            tracing::error!("Error executing Trino query: {}", e);
+            return Err(anyhow!(e.to_string()));
+        }
+    };
+
+    let mut result: Vec<IndexMap<String, DataType>> = Vec::with_capacity(limit_value);
+
+    for row in results.data {
+        // Stop processing if we've reached the limit
+        if result.len() >= limit_value {
+            break;
+        }
+
+        let mut row_map: IndexMap<String, DataType> =
+            IndexMap::with_capacity(results.columns.len());
+
+        for (column, value) in results.columns.iter().zip(row) {
+            row_map.insert(column.name.clone(), convert_value(&column.type_name, value));
+        }
+
+        result.push(row_map);
+    }
+
+    Ok(result)
+}
+
+fn convert_value(type_name: &str, value: Value) -> DataType {
+    if value.is_null() {
+        return DataType::Null;
+    }
+
+    let type_head = type_name.split('(').next().unwrap_or(type_name).trim();
+
+    match type_head {
+        "boolean" => DataType::Bool(value.as_bool()),
+        "tinyint" | "smallint" => DataType::Int2(value.as_i64().map(|v| v as i16)),
+        "integer" => DataType::Int4(value.as_i64().map(|v| v as i32)),
+        "bigint" => DataType::Int8(value.as_i64()),
+        "real" => DataType::Float4(value_as_f64(&value).map(|v| v as f32)),
+        // Decimals are sent as strings to preserve precision
+        "double" | "decimal" => DataType::Float8(value_as_f64(&value)),
+        "varchar" | "char" | "varbinary" | "ipaddress" => {
+            DataType::Text(value.as_str().map(|v| v.to_string()))
+        }
+        "uuid" => DataType::Uuid(value.as_str().and_then(|v| v.parse::<uuid::Uuid>().ok())),
+        "date" => DataType::Date(
+            value
+                .as_str()
+                .and_then(|v| NaiveDate::parse_from_str(v, "%Y-%m-%d").ok()),
+        ),
+        "time" => DataType::Time(
+            value
+                .as_str()
+                .and_then(|v| NaiveTime::parse_from_str(v, "%H:%M:%S%.f").ok()),
+        ),
+        "timestamp" if type_name.ends_with("with time zone") => {
+            parse_timestamp_with_zone(value.as_str().unwrap_or_default())
+        }
+        "timestamp" => DataType::Timestamp(
+            value
+                .as_str()
+                .and_then(|v| NaiveDateTime::parse_from_str(v, "%Y-%m-%d %H:%M:%S%.f").ok()),
+        ),
+        // json values arrive as their serialized text
+        "json" => DataType::Json(
+            value
+                .as_str()
+                .and_then(|v| serde_json::from_str::<Value>(v).ok()),
+        ),
+        "array" | "map" | "row" => DataType::Json(Some(value)),
+        _ => DataType::Unknown(Some(match value {
+            Value::String(v) => v,
+            other => other.to_string(),
+        })),
+    }
+}
+
+// Trino renders zones as `UTC`, a numeric offset or a region name; region names have no
+// offset available without a tz database, so those are kept as text
+fn parse_timestamp_with_zone(raw: &str) -> DataType {
+    let (timestamp, zone) = match raw.rsplit_once(' ') {
+        Some(parts) => parts,
+        None => return DataType::Unknown(Some(raw.to_string())),
+    };
+
+    if zone == "UTC" || zone == "Z" {
+        return DataType::Timestamptz(
+            NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S%.f")
+                .ok()
+                .map(|ts| ts.and_utc()),
+        );
+    }
+
+    match DateTime::parse_from_str(raw, "%Y-%m-%d %H:%M:%S%.f %:z") {
+        Ok(ts) => DataType::Timestamptz(Some(ts.with_timezone(&Utc))),
+        Err(_) => DataType::Unknown(Some(raw.to_string())),
+    }
+}
+
+fn value_as_f64(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse::<f64>().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_convert_trino_values() {
+        assert_eq!(convert_value("bigint", json!(42)), DataType::Int8(Some(42)));
+        assert_eq!(convert_value("decimal(10,2)", json!("19.99")), DataType::Float8(Some(19.99)));
+        assert_eq!(
+            convert_value("varchar(255)", json!("abc")),
+            DataType::Text(Some("abc".to_string()))
+        );
+        assert_eq!(convert_value("integer", Value::Null), DataType::Null);
+        assert_eq!(
+            convert_value("timestamp(3)", json!("2024-03-01 10:15:00.000")),
+            DataType::Timestamp(NaiveDateTime::parse_from_str("2024-03-01 10:15:00", "%Y-%m-%d %H:%M:%S").ok())
+        );
+        assert_eq!(
+            convert_value("timestamp(3) with time zone", json!("2024-03-01 10:15:00.000 UTC")),
+            DataType::Timestamptz(
+                NaiveDateTime::parse_from_str("2024-03-01 10:15:00", "%Y-%m-%d %H:%M:%S")
+                    .ok()
+                    .map(|ts| ts.and_utc())
+            )
+        );
+        assert_eq!(
+            convert_value("json", json!("{\"a\":1}")),
+            DataType::Json(Some(json!({"a": 1})))
+        );
+    }
+}
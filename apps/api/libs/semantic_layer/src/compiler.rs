@@ -0,0 +1,1264 @@
+//! Compiles structured semantic queries against deployed models into SQL.
+//!
+//! A [`SemanticQuery`] names measures, dimensions, filters, an optional time
+//! grain, ordering and a limit in terms of `model.field` references. The
+//! compiler resolves those references against the deployed [`Model`]s, joins the
+//! models it needs through their [`Relationship`] definitions and renders SQL for
+//! the target data source dialect.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::models::{Argument, Model, Relationship};
+
+#[derive(Error, Debug, PartialEq)]
+pub enum CompileError {
+    #[error("Query must select at least one measure or dimension")]
+    EmptyQuery,
+
+    #[error("Unknown model: {0}")]
+    UnknownModel(String),
+
+    #[error("Unknown field: {0}")]
+    UnknownField(String),
+
+    #[error("Ambiguous field '{0}', qualify it as model.field")]
+    AmbiguousField(String),
+
+    #[error("Unknown filter: {0}")]
+    UnknownFilter(String),
+
+    #[error("Field '{0}' is selected more than once")]
+    DuplicateField(String),
+
+    #[error("Missing required argument '{argument}' for {field}")]
+    MissingArgument { field: String, argument: String },
+
+    #[error("Unexpected argument '{argument}' for {field}")]
+    UnexpectedArgument { field: String, argument: String },
+
+    #[error("Invalid value for argument '{argument}' of {field}: {message}")]
+    InvalidArgument {
+        field: String,
+        argument: String,
+        message: String,
+    },
+
+    #[error("Invalid filter on {field}: {message}")]
+    InvalidFilter { field: String, message: String },
+
+    #[error("Invalid expression for {field}: {message}")]
+    InvalidExpression { field: String, message: String },
+
+    #[error("Aggregation cannot be applied to metric {0}, metrics define their own")]
+    AggregationOnMetric(String),
+
+    #[error("Invalid relationship from {from} to {to}: {message}")]
+    InvalidRelationship {
+        from: String,
+        to: String,
+        message: String,
+    },
+
+    #[error("No relationship path joins {from} to {to}")]
+    NoJoinPath { from: String, to: String },
+
+    #[error(
+        "Joining {from} to {to} duplicates {from} rows and would inflate aggregates; \
+         filter on {to} instead of selecting from it"
+    )]
+    FanOut { from: String, to: String },
+
+    #[error("Cannot order by {0}, it is not selected")]
+    InvalidOrder(String),
+}
+
+/// A request for data expressed in terms of semantic models.
+///
+/// Field references are either `model.field` or a bare `field` that is unique
+/// across all models.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
+pub struct SemanticQuery {
+    #[serde(default)]
+    pub measures: Vec<MeasureSelection>,
+    #[serde(default)]
+    pub dimensions: Vec<String>,
+    #[serde(default)]
+    pub filters: Vec<FilterSelection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_dimension: Option<TimeDimension>,
+    #[serde(default)]
+    pub order_by: Vec<OrderBy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<u64>,
+}
+
+/// A metric, or a raw measure with an aggregation applied to it.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct MeasureSelection {
+    pub name: String,
+    /// Aggregation for a raw measure, `sum` when omitted. Metrics already aggregate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aggregation: Option<Aggregation>,
+    /// Values for the metric's parameterized arguments
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub args: BTreeMap<String, ArgValue>,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Aggregation {
+    Sum,
+    Avg,
+    Min,
+    Max,
+    Count,
+    CountDistinct,
+}
+
+/// Either a condition on a single field or one of the model's named filters.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(untagged)]
+pub enum FilterSelection {
+    Condition {
+        field: String,
+        operator: FilterOperator,
+        #[serde(default)]
+        values: Vec<ArgValue>,
+    },
+    Named {
+        filter: String,
+        #[serde(default)]
+        args: BTreeMap<String, ArgValue>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterOperator {
+    Eq,
+    NotEq,
+    Gt,
+    Gte,
+    Lt,
+    Lte,
+    In,
+    NotIn,
+    IsNull,
+    IsNotNull,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[serde(untagged)]
+pub enum ArgValue {
+    Boolean(bool),
+    Integer(i64),
+    Number(f64),
+    String(String),
+}
+
+/// A dimension truncated to a time grain and grouped on.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct TimeDimension {
+    pub dimension: String,
+    pub grain: TimeGrain,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeGrain {
+    Hour,
+    Day,
+    Week,
+    Month,
+    Quarter,
+    Year,
+}
+
+impl TimeGrain {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TimeGrain::Hour => "hour",
+            TimeGrain::Day => "day",
+            TimeGrain::Week => "week",
+            TimeGrain::Month => "month",
+            TimeGrain::Quarter => "quarter",
+            TimeGrain::Year => "year",
+        }
+    }
+}
+
+/// Orders by a selected field, matched by reference or output column name.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct OrderBy {
+    pub field: String,
+    #[serde(default)]
+    pub direction: SortDirection,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Compiles `query` against `models` into SQL for the given data source dialect
+/// (`postgres`, `snowflake`, `bigquery`, ...). Unknown dialects get ANSI-style SQL.
+pub fn compile_query(
+    models: &[Model],
+    query: &SemanticQuery,
+    dialect: &str,
+) -> Result<String, CompileError> {
+    Compiler::new(models, Dialect::from_name(dialect)).compile(query)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Dialect {
+    Generic,
+    Postgres,
+    Snowflake,
+    BigQuery,
+    MySql,
+    SqlServer,
+    Databricks,
+    DuckDb,
+    ClickHouse,
+    Trino,
+    Sqlite,
+}
+
+impl Dialect {
+    fn from_name(name: &str) -> Self {
+        match name.to_lowercase().as_str() {
+            "postgres" | "redshift" | "supabase" => Dialect::Postgres,
+            "snowflake" => Dialect::Snowflake,
+            "bigquery" => Dialect::BigQuery,
+            "mysql" | "mariadb" => Dialect::MySql,
+            "sqlserver" => Dialect::SqlServer,
+            "databricks" => Dialect::Databricks,
+            "duckdb" => Dialect::DuckDb,
+            "clickhouse" => Dialect::ClickHouse,
+            "trino" | "presto" => Dialect::Trino,
+            "sqlite" => Dialect::Sqlite,
+            _ => Dialect::Generic,
+        }
+    }
+
+    fn quote_ident(&self, ident: &str) -> String {
+        let is_simple = ident
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && ident.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if is_simple {
+            return ident.to_string();
+        }
+
+        match self {
+            Dialect::MySql | Dialect::BigQuery | Dialect::Databricks | Dialect::ClickHouse => {
+                format!("`{}`", ident.replace('`', "``"))
+            }
+            Dialect::SqlServer => format!("[{}]", ident.replace(']', "]]")),
+            _ => format!("\"{}\"", ident.replace('"', "\"\"")),
+        }
+    }
+
+    /// Escapes text for use inside a single-quoted string literal
+    fn escape_string(&self, value: &str) -> String {
+        match self {
+            Dialect::MySql | Dialect::BigQuery | Dialect::Databricks | Dialect::ClickHouse => {
+                value.replace('\\', "\\\\").replace('\'', "\\'")
+            }
+            _ => value.replace('\'', "''"),
+        }
+    }
+
+    fn boolean(&self, value: bool) -> &'static str {
+        match (self, value) {
+            (Dialect::SqlServer, true) => "1",
+            (Dialect::SqlServer, false) => "0",
+            (_, true) => "TRUE",
+            (_, false) => "FALSE",
+        }
+    }
+
+    /// Truncates a date or timestamp expression to the start of its grain.
+    /// Weeks start on Monday in every dialect.
+    fn truncate(&self, grain: TimeGrain, expr: &str) -> String {
+        match self {
+            Dialect::BigQuery => {
+                let part = match grain {
+                    TimeGrain::Week => "ISOWEEK".to_string(),
+                    _ => grain.as_str().to_uppercase(),
+                };
+                format!("DATE_TRUNC({expr}, {part})")
+            }
+            Dialect::MySql => match grain {
+                TimeGrain::Hour => format!("DATE_FORMAT({expr}, '%Y-%m-%d %H:00:00')"),
+                TimeGrain::Day => format!("DATE({expr})"),
+                TimeGrain::Week => format!("DATE_SUB(DATE({expr}), INTERVAL WEEKDAY({expr}) DAY)"),
+                TimeGrain::Month => format!("DATE_FORMAT({expr}, '%Y-%m-01')"),
+                TimeGrain::Quarter => format!(
+                    "MAKEDATE(YEAR({expr}), 1) + INTERVAL (QUARTER({expr}) - 1) QUARTER"
+                ),
+                TimeGrain::Year => format!("DATE_FORMAT({expr}, '%Y-01-01')"),
+            },
+            Dialect::SqlServer => {
+                // DATETRUNC needs SQL Server 2022; day 0 (1900-01-01) is a Monday
+                let part = grain.as_str();
+                format!("DATEADD({part}, DATEDIFF({part}, 0, {expr}), 0)")
+            }
+            Dialect::ClickHouse => match grain {
+                TimeGrain::Hour => format!("toStartOfHour({expr})"),
+                TimeGrain::Day => format!("toStartOfDay({expr})"),
+                TimeGrain::Week => format!("toMonday({expr})"),
+                TimeGrain::Month => format!("toStartOfMonth({expr})"),
+                TimeGrain::Quarter => format!("toStartOfQuarter({expr})"),
+                TimeGrain::Year => format!("toStartOfYear({expr})"),
+            },
+            Dialect::Sqlite => match grain {
+                TimeGrain::Hour => format!("strftime('%Y-%m-%d %H:00:00', {expr})"),
+                TimeGrain::Day => format!("date({expr})"),
+                TimeGrain::Week => format!("date({expr}, '-6 days', 'weekday 1')"),
+                TimeGrain::Month => format!("date({expr}, 'start of month')"),
+                TimeGrain::Quarter => format!(
+                    "date({expr}, 'start of month', printf('-%d months', (CAST(strftime('%m', {expr}) AS INTEGER) - 1) % 3))"
+                ),
+                TimeGrain::Year => format!("date({expr}, 'start of year')"),
+            },
+            Dialect::Generic
+            | Dialect::Postgres
+            | Dialect::Snowflake
+            | Dialect::Databricks
+            | Dialect::DuckDb
+            | Dialect::Trino => format!("DATE_TRUNC('{}', {expr})", grain.as_str()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ValueKind {
+    Integer,
+    Number,
+    Boolean,
+    Text,
+    Inferred,
+}
+
+impl ValueKind {
+    fn from_type(type_: Option<&str>) -> Self {
+        let Some(type_) = type_ else {
+            return ValueKind::Inferred;
+        };
+        let base = type_
+            .split('(')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        match base.as_str() {
+            "int" | "integer" | "bigint" | "smallint" | "tinyint" | "int32" | "int64" | "long" => {
+                ValueKind::Integer
+            }
+            "number" | "numeric" | "decimal" | "float" | "float64" | "double" | "real" => {
+                ValueKind::Number
+            }
+            "bool" | "boolean" => ValueKind::Boolean,
+            _ => ValueKind::Text,
+        }
+    }
+}
+
+/// Renders a value as a SQL literal of the given declared type
+fn render_literal(
+    dialect: Dialect,
+    type_: Option<&str>,
+    value: &ArgValue,
+) -> Result<String, String> {
+    let quoted = |text: &str| format!("'{}'", dialect.escape_string(text));
+
+    match (ValueKind::from_type(type_), value) {
+        (ValueKind::Integer, ArgValue::Integer(i)) => Ok(i.to_string()),
+        (ValueKind::Integer, ArgValue::Number(n)) if n.fract() == 0.0 => {
+            Ok((*n as i64).to_string())
+        }
+        (ValueKind::Integer, ArgValue::String(s)) => s
+            .trim()
+            .parse::<i64>()
+            .map(|i| i.to_string())
+            .map_err(|_| format!("expected an integer, got '{s}'")),
+        (ValueKind::Integer, other) => Err(format!("expected an integer, got {other:?}")),
+
+        (ValueKind::Number | ValueKind::Inferred, ArgValue::Integer(i)) => Ok(i.to_string()),
+        (ValueKind::Number | ValueKind::Inferred, ArgValue::Number(n)) => Ok(n.to_string()),
+        (ValueKind::Number, ArgValue::String(s)) => match s.trim().parse::<f64>() {
+            Ok(n) if n.is_finite() => Ok(s.trim().to_string()),
+            _ => Err(format!("expected a number, got '{s}'")),
+        },
+        (ValueKind::Number, other) => Err(format!("expected a number, got {other:?}")),
+
+        (ValueKind::Boolean | ValueKind::Inferred, ArgValue::Boolean(b)) => {
+            Ok(dialect.boolean(*b).to_string())
+        }
+        (ValueKind::Boolean, ArgValue::String(s)) => match s.trim().to_lowercase().as_str() {
+            "true" => Ok(dialect.boolean(true).to_string()),
+            "false" => Ok(dialect.boolean(false).to_string()),
+            _ => Err(format!("expected a boolean, got '{s}'")),
+        },
+        (ValueKind::Boolean, other) => Err(format!("expected a boolean, got {other:?}")),
+
+        (ValueKind::Text | ValueKind::Inferred, ArgValue::String(s)) => Ok(quoted(s)),
+        (ValueKind::Text, ArgValue::Integer(i)) => Ok(quoted(&i.to_string())),
+        (ValueKind::Text, ArgValue::Number(n)) => Ok(quoted(&n.to_string())),
+        (ValueKind::Text, ArgValue::Boolean(b)) => Ok(quoted(&b.to_string())),
+    }
+}
+
+/// Text of a value when it is spliced inside an existing string literal
+fn render_in_string(dialect: Dialect, value: &ArgValue) -> String {
+    let text = match value {
+        ArgValue::Boolean(b) => b.to_string(),
+        ArgValue::Integer(i) => i.to_string(),
+        ArgValue::Number(n) => n.to_string(),
+        ArgValue::String(s) => s.clone(),
+    };
+    dialect.escape_string(&text)
+}
+
+/// A join step from one model to a related one
+#[derive(Debug, Clone, Copy)]
+struct Edge<'a> {
+    from: &'a Model,
+    to: &'a Model,
+    from_col: &'a str,
+    to_col: &'a str,
+    relationship: &'a Relationship,
+    /// Whether the step walks a relationship declared on `to` backwards
+    reversed: bool,
+}
+
+impl Edge<'_> {
+    /// Whether each `from` row can match several `to` rows
+    fn fans_out(&self) -> bool {
+        let cardinality = self
+            .relationship
+            .cardinality
+            .as_deref()
+            .unwrap_or_default()
+            .to_lowercase()
+            .replace(['_', ' '], "-");
+        matches!(
+            (cardinality.as_str(), self.reversed),
+            ("many-to-many", _) | ("one-to-many", false) | ("many-to-one", true)
+        )
+    }
+
+    fn join_type(&self) -> Result<&'static str, CompileError> {
+        let declared = self
+            .relationship
+            .type_
+            .as_deref()
+            .map(|t| t.trim().to_uppercase());
+        let join_type = match declared.as_deref() {
+            None | Some("LEFT") | Some("LEFT OUTER") => "LEFT",
+            Some("INNER") => "INNER",
+            Some("RIGHT") | Some("RIGHT OUTER") => "RIGHT",
+            Some("FULL") | Some("FULL OUTER") => "FULL",
+            Some(other) => {
+                let (from, to) = if self.reversed {
+                    (self.to, self.from)
+                } else {
+                    (self.from, self.to)
+                };
+                return Err(CompileError::InvalidRelationship {
+                    from: from.name.clone(),
+                    to: to.name.clone(),
+                    message: format!("unsupported join type '{other}'"),
+                });
+            }
+        };
+
+        // Walked backwards, outer joins keep the rows of the model we start from
+        Ok(match (join_type, self.reversed) {
+            ("RIGHT", true) => "LEFT",
+            (join_type, _) => join_type,
+        })
+    }
+}
+
+/// A rendered expression and the models it reads from
+struct Rendered<'a> {
+    sql: String,
+    models: Vec<&'a Model>,
+}
+
+struct SelectItem<'a> {
+    model: &'a Model,
+    name: String,
+    sql: String,
+    alias: String,
+    is_measure: bool,
+}
+
+struct Compiler<'a> {
+    models: &'a [Model],
+    dialect: Dialect,
+}
+
+impl<'a> Compiler<'a> {
+    fn new(models: &'a [Model], dialect: Dialect) -> Self {
+        Self { models, dialect }
+    }
+
+    fn compile(&self, query: &SemanticQuery) -> Result<String, CompileError> {
+        if query.measures.is_empty()
+            && query.dimensions.is_empty()
+            && query.time_dimension.is_none()
+        {
+            return Err(CompileError::EmptyQuery);
+        }
+
+        let mut items: Vec<SelectItem<'a>> = Vec::new();
+        let mut referenced: Vec<&'a Model> = Vec::new();
+
+        if let Some(time_dimension) = &query.time_dimension {
+            let (model, dimension) =
+                self.resolve_field(&time_dimension.dimension, has_dimension)?;
+            let column = self.column(model, dimension);
+            items.push(SelectItem {
+                model,
+                name: dimension.to_string(),
+                sql: self.dialect.truncate(time_dimension.grain, &column),
+                alias: format!("{}_{}", dimension, time_dimension.grain.as_str()),
+                is_measure: false,
+            });
+            referenced.push(model);
+        }
+
+        for reference in &query.dimensions {
+            let (model, dimension) = self.resolve_field(reference, has_dimension)?;
+            items.push(SelectItem {
+                model,
+                name: dimension.to_string(),
+                sql: self.column(model, dimension),
+                alias: dimension.to_string(),
+                is_measure: false,
+            });
+            referenced.push(model);
+        }
+
+        for selection in &query.measures {
+            let (item, models) = self.measure(selection)?;
+            items.push(item);
+            referenced.extend(models);
+        }
+
+        let has_measures = !query.measures.is_empty();
+        let base = items
+            .iter()
+            .find(|item| item.is_measure)
+            .unwrap_or(&items[0])
+            .model;
+
+        let tree = JoinTree::build(self.models, base);
+
+        let mut conditions = Vec::new();
+        for filter in &query.filters {
+            let (field, rendered) = self.filter(filter)?;
+            let fanned_out: Vec<&'a Model> = rendered
+                .models
+                .iter()
+                .copied()
+                .filter(|model| tree.fans_out(model))
+                .collect();
+
+            if fanned_out.is_empty() {
+                referenced.extend(rendered.models);
+                conditions.push(rendered.sql);
+            } else {
+                let (sql, outer) = self.exists(&tree, &field, &rendered, &fanned_out)?;
+                referenced.extend(outer);
+                conditions.push(sql);
+            }
+        }
+
+        let mut joined: HashSet<&str> = HashSet::from([base.name.as_str()]);
+        let mut joins: Vec<Edge<'a>> = Vec::new();
+        for model in referenced {
+            let path = tree.path(model)?;
+            for edge in path {
+                if has_measures && edge.fans_out() {
+                    return Err(CompileError::FanOut {
+                        from: edge.from.name.clone(),
+                        to: edge.to.name.clone(),
+                    });
+                }
+                if joined.insert(edge.to.name.as_str()) {
+                    joins.push(edge);
+                }
+            }
+        }
+        // Parents are always discovered before children, so join in discovery order
+        joins.sort_by_key(|edge| tree.depth(edge.to));
+
+        self.assign_aliases(&mut items)?;
+        let order_by = self.order_by(&items, &query.order_by)?;
+
+        let mut sql = String::from("SELECT ");
+        if let (Dialect::SqlServer, Some(limit)) = (self.dialect, query.limit) {
+            sql.push_str(&format!("TOP {limit} "));
+        }
+        sql.push_str(
+            &items
+                .iter()
+                .map(|item| format!("{} AS {}", item.sql, self.dialect.quote_ident(&item.alias)))
+                .collect::<Vec<_>>()
+                .join(", "),
+        );
+        sql.push_str(&format!("\nFROM {}", self.table(base)));
+        for edge in &joins {
+            sql.push_str(&format!(
+                "\n{} JOIN {} ON {} = {}",
+                edge.join_type()?,
+                self.table(edge.to),
+                self.column(edge.from, edge.from_col),
+                self.column(edge.to, edge.to_col)
+            ));
+        }
+        if !conditions.is_empty() {
+            sql.push_str(&format!("\nWHERE {}", conditions.join(" AND ")));
+        }
+
+        let group_by: Vec<&str> = items
+            .iter()
+            .filter(|item| !item.is_measure)
+            .map(|item| item.sql.as_str())
+            .collect();
+        if !group_by.is_empty() {
+            sql.push_str(&format!("\nGROUP BY {}", group_by.join(", ")));
+        }
+        if !order_by.is_empty() {
+            sql.push_str(&format!("\nORDER BY {}", order_by.join(", ")));
+        }
+        if let Some(limit) = query.limit {
+            if self.dialect != Dialect::SqlServer {
+                sql.push_str(&format!("\nLIMIT {limit}"));
+            }
+        }
+
+        Ok(sql)
+    }
+
+    /// Resolves `model.field` or a bare `field` to the model that defines it
+    fn resolve_field<'r>(
+        &self,
+        reference: &'r str,
+        defines: impl Fn(&Model, &str) -> bool,
+    ) -> Result<(&'a Model, &'r str), CompileError> {
+        if let Some((model_name, field)) = reference.split_once('.') {
+            let model = self
+                .model(model_name)
+                .ok_or_else(|| CompileError::UnknownModel(model_name.to_string()))?;
+            if !defines(model, field) {
+                return Err(CompileError::UnknownField(reference.to_string()));
+            }
+            return Ok((model, field));
+        }
+
+        let mut candidates = self.models.iter().filter(|m| defines(m, reference));
+        match (candidates.next(), candidates.next()) {
+            (Some(model), None) => Ok((model, reference)),
+            (Some(_), Some(_)) => Err(CompileError::AmbiguousField(reference.to_string())),
+            (None, _) => Err(CompileError::UnknownField(reference.to_string())),
+        }
+    }
+
+    fn model(&self, name: &str) -> Option<&'a Model> {
+        self.models.iter().find(|m| m.name == name)
+    }
+
+    fn measure(
+        &self,
+        selection: &MeasureSelection,
+    ) -> Result<(SelectItem<'a>, Vec<&'a Model>), CompileError> {
+        let (model, name) = self.resolve_field(&selection.name, |m, f| {
+            m.metrics.iter().any(|metric| metric.name == f) || has_measure(m, f)
+        })?;
+
+        if let Some(metric) = model.metrics.iter().find(|metric| metric.name == name) {
+            if selection.aggregation.is_some() {
+                return Err(CompileError::AggregationOnMetric(selection.name.clone()));
+            }
+            let rendered = self.expression(
+                model,
+                &selection.name,
+                &metric.expr,
+                &metric.args,
+                &selection.args,
+            )?;
+            let item = SelectItem {
+                model,
+                name: name.to_string(),
+                sql: rendered.sql,
+                alias: name.to_string(),
+                is_measure: true,
+            };
+            return Ok((item, rendered.models));
+        }
+
+        if let Some(argument) = selection.args.keys().next() {
+            return Err(CompileError::UnexpectedArgument {
+                field: selection.name.clone(),
+                argument: argument.clone(),
+            });
+        }
+
+        let column = self.column(model, name);
+        let aggregation = selection.aggregation.unwrap_or(Aggregation::Sum);
+        let (sql, alias) = match aggregation {
+            Aggregation::Sum => (format!("SUM({column})"), name.to_string()),
+            Aggregation::Avg => (format!("AVG({column})"), format!("{name}_avg")),
+            Aggregation::Min => (format!("MIN({column})"), format!("{name}_min")),
+            Aggregation::Max => (format!("MAX({column})"), format!("{name}_max")),
+            Aggregation::Count => (format!("COUNT({column})"), format!("{name}_count")),
+            Aggregation::CountDistinct => (
+                format!("COUNT(DISTINCT {column})"),
+                format!("{name}_count_distinct"),
+            ),
+        };
+        let item = SelectItem {
+            model,
+            name: name.to_string(),
+            sql,
+            alias,
+            is_measure: true,
+        };
+        Ok((item, vec![model]))
+    }
+
+    /// Renders a filter as a boolean condition, returning the field it names
+    fn filter(&self, filter: &FilterSelection) -> Result<(String, Rendered<'a>), CompileError> {
+        match filter {
+            FilterSelection::Named { filter, args } => {
+                let (model, name) = self
+                    .resolve_field(filter, |m, f| m.filters.iter().any(|x| x.name == f))
+                    .map_err(|e| match e {
+                        CompileError::UnknownField(name) => CompileError::UnknownFilter(name),
+                        other => other,
+                    })?;
+                let definition = model
+                    .filters
+                    .iter()
+                    .find(|x| x.name == name)
+                    .expect("resolved filter exists");
+                let rendered =
+                    self.expression(model, filter, &definition.expr, &definition.args, args)?;
+                Ok((
+                    filter.clone(),
+                    Rendered {
+                        sql: format!("({})", rendered.sql),
+                        models: rendered.models,
+                    },
+                ))
+            }
+            FilterSelection::Condition {
+                field,
+                operator,
+                values,
+            } => {
+                let (model, name) =
+                    self.resolve_field(field, |m, f| has_dimension(m, f) || has_measure(m, f))?;
+                let type_ = column_type(model, name);
+                let column = self.column(model, name);
+
+                let literals = values
+                    .iter()
+                    .map(|value| render_literal(self.dialect, type_, value))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|message| CompileError::InvalidFilter {
+                        field: field.clone(),
+                        message,
+                    })?;
+                let invalid = |message: &str| CompileError::InvalidFilter {
+                    field: field.clone(),
+                    message: message.to_string(),
+                };
+
+                let sql = match operator {
+                    FilterOperator::IsNull | FilterOperator::IsNotNull => {
+                        if !literals.is_empty() {
+                            return Err(invalid("null checks take no values"));
+                        }
+                        let check = if *operator == FilterOperator::IsNull {
+                            "IS NULL"
+                        } else {
+                            "IS NOT NULL"
+                        };
+                        format!("{column} {check}")
+                    }
+                    FilterOperator::In | FilterOperator::NotIn => {
+                        if literals.is_empty() {
+                            return Err(invalid("expected at least one value"));
+                        }
+                        let keyword = if *operator == FilterOperator::In {
+                            "IN"
+                        } else {
+                            "NOT IN"
+                        };
+                        format!("{column} {keyword} ({})", literals.join(", "))
+                    }
+                    comparison => {
+                        let [literal] = literals.as_slice() else {
+                            return Err(invalid("expected exactly one value"));
+                        };
+                        let symbol = match comparison {
+                            FilterOperator::Eq => "=",
+                            FilterOperator::NotEq => "<>",
+                            FilterOperator::Gt => ">",
+                            FilterOperator::Gte => ">=",
+                            FilterOperator::Lt => "<",
+                            _ => "<=",
+                        };
+                        format!("{column} {symbol} {literal}")
+                    }
+                };
+                Ok((
+                    field.clone(),
+                    Rendered {
+                        sql,
+                        models: vec![model],
+                    },
+                ))
+            }
+        }
+    }
+
+    /// Wraps a filter on fanned-out models in `EXISTS` so it cannot duplicate base
+    /// rows. Returns the condition and the models it correlates with outside.
+    fn exists(
+        &self,
+        tree: &JoinTree<'a>,
+        field: &str,
+        rendered: &Rendered<'a>,
+        fanned_out: &[&'a Model],
+    ) -> Result<(String, Vec<&'a Model>), CompileError> {
+        let mut pivot: Option<Edge<'a>> = None;
+        let mut inner: Vec<Edge<'a>> = Vec::new();
+        let mut inner_models: HashSet<&str> = HashSet::new();
+
+        for model in fanned_out {
+            let path = tree.path(model)?;
+            let first = path
+                .iter()
+                .position(|edge| edge.fans_out())
+                .expect("fanned out model has a fanning edge");
+
+            match pivot {
+                None => {
+                    pivot = Some(path[first]);
+                    inner_models.insert(path[first].to.name.as_str());
+                }
+                Some(existing) if existing.to.name == path[first].to.name => {}
+                Some(_) => {
+                    return Err(CompileError::InvalidFilter {
+                        field: field.to_string(),
+                        message: "references several one-to-many relationships".to_string(),
+                    })
+                }
+            }
+            for edge in &path[first + 1..] {
+                if inner_models.insert(edge.to.name.as_str()) {
+                    inner.push(*edge);
+                }
+            }
+        }
+        let pivot = pivot.expect("at least one fanned out model");
+        inner.sort_by_key(|edge| tree.depth(edge.to));
+
+        let mut sql = format!("EXISTS (SELECT 1 FROM {}", self.table(pivot.to));
+        for edge in &inner {
+            sql.push_str(&format!(
+                " INNER JOIN {} ON {} = {}",
+                self.table(edge.to),
+                self.column(edge.from, edge.from_col),
+                self.column(edge.to, edge.to_col)
+            ));
+        }
+        sql.push_str(&format!(
+            " WHERE {} = {} AND {})",
+            self.column(pivot.to, pivot.to_col),
+            self.column(pivot.from, pivot.from_col),
+            rendered.sql
+        ));
+
+        let mut outer = vec![pivot.from];
+        outer.extend(
+            rendered
+                .models
+                .iter()
+                .copied()
+                .filter(|model| !inner_models.contains(model.name.as_str())),
+        );
+        Ok((sql, outer))
+    }
+
+    /// Expands `{arg}` placeholders in a metric or filter expression and
+    /// qualifies bare columns of the owning model
+    fn expression(
+        &self,
+        owner: &'a Model,
+        field: &str,
+        expr: &str,
+        params: &[Argument],
+        args: &BTreeMap<String, ArgValue>,
+    ) -> Result<Rendered<'a>, CompileError> {
+        for argument in args.keys() {
+            if !params.iter().any(|param| &param.name == argument) {
+                return Err(CompileError::UnexpectedArgument {
+                    field: field.to_string(),
+                    argument: argument.clone(),
+                });
+            }
+        }
+        let argument = |name: &str| -> Result<(&Argument, &ArgValue), CompileError> {
+            let param = params
+                .iter()
+                .find(|param| param.name == name)
+                .ok_or_else(|| CompileError::InvalidExpression {
+                    field: field.to_string(),
+                    message: format!("references undeclared argument '{name}'"),
+                })?;
+            let value = args
+                .get(name)
+                .ok_or_else(|| CompileError::MissingArgument {
+                    field: field.to_string(),
+                    argument: name.to_string(),
+                })?;
+            Ok((param, value))
+        };
+
+        let chars: Vec<char> = expr.chars().collect();
+        let mut sql = String::with_capacity(expr.len());
+        let mut models = vec![owner];
+        let mut i = 0;
+
+        while i < chars.len() {
+            let c = chars[i];
+
+            if c == '\'' {
+                // String literal, placeholders inside it splice raw text
+                sql.push(c);
+                i += 1;
+                while i < chars.len() {
+                    if chars[i] == '\'' {
+                        sql.push('\'');
+                        i += 1;
+                        if chars.get(i) == Some(&'\'') {
+                            sql.push('\'');
+                            i += 1;
+                            continue;
+                        }
+                        break;
+                    }
+                    if let Some((name, end)) = placeholder(&chars, i) {
+                        let (_, value) = argument(&name)?;
+                        sql.push_str(&render_in_string(self.dialect, value));
+                        i = end;
+                        continue;
+                    }
+                    sql.push(chars[i]);
+                    i += 1;
+                }
+                continue;
+            }
+
+            if c == '"' || c == '`' || c == '[' {
+                // Quoted identifiers are copied untouched
+                let close = if c == '[' { ']' } else { c };
+                sql.push(c);
+                i += 1;
+                while i < chars.len() {
+                    sql.push(chars[i]);
+                    i += 1;
+                    if chars[i - 1] == close {
+                        break;
+                    }
+                }
+                continue;
+            }
+
+            if let Some((name, end)) = placeholder(&chars, i) {
+                let (param, value) = argument(&name)?;
+                let literal =
+                    render_literal(self.dialect, Some(&param.type_), value).map_err(|message| {
+                        CompileError::InvalidArgument {
+                            field: field.to_string(),
+                            argument: name.clone(),
+                            message,
+                        }
+                    })?;
+                sql.push_str(&literal);
+                i = end;
+                continue;
+            }
+
+            if c.is_ascii_digit() {
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.') {
+                    sql.push(chars[i]);
+                    i += 1;
+                }
+                continue;
+            }
+
+            if c.is_ascii_alphabetic() || c == '_' {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                let ident: String = chars[start..i].iter().collect();
+                let after_dot = sql.trim_end().ends_with('.');
+                let before_dot = chars.get(i) == Some(&'.');
+                let is_call = chars[i..]
+                    .iter()
+                    .find(|c| !c.is_whitespace())
+                    .is_some_and(|c| *c == '(');
+
+                if before_dot && !after_dot {
+                    if let Some(model) = self.model(&ident) {
+                        if !models.iter().any(|m| m.name == model.name) {
+                            models.push(model);
+                        }
+                    }
+                    sql.push_str(&ident);
+                } else if !after_dot && !is_call && is_column(owner, &ident) {
+                    sql.push_str(&self.column(owner, &ident));
+                } else {
+                    sql.push_str(&ident);
+                }
+                continue;
+            }
+
+            sql.push(c);
+            i += 1;
+        }
+
+        Ok(Rendered { sql, models })
+    }
+
+    /// Gives every output column a unique name, qualifying clashes with the model
+    fn assign_aliases(&self, items: &mut [SelectItem<'a>]) -> Result<(), CompileError> {
+        let mut used: HashSet<String> = HashSet::new();
+        for item in items.iter_mut() {
+            if used.contains(&item.alias) {
+                let qualified = format!("{}_{}", item.model.name, item.alias);
+                if used.contains(&qualified) {
+                    return Err(CompileError::DuplicateField(format!(
+                        "{}.{}",
+                        item.model.name, item.name
+                    )));
+                }
+                item.alias = qualified;
+            }
+            used.insert(item.alias.clone());
+        }
+        Ok(())
+    }
+
+    fn order_by(
+        &self,
+        items: &[SelectItem<'a>],
+        order_by: &[OrderBy],
+    ) -> Result<Vec<String>, CompileError> {
+        order_by
+            .iter()
+            .map(|order| {
+                let item = items
+                    .iter()
+                    .find(|item| item.alias == order.field)
+                    .or_else(|| {
+                        items.iter().find(|item| {
+                            order.field == item.name
+                                || order.field == format!("{}.{}", item.model.name, item.name)
+                        })
+                    })
+                    .ok_or_else(|| CompileError::InvalidOrder(order.field.clone()))?;
+                let direction = match order.direction {
+                    SortDirection::Asc => "ASC",
+                    SortDirection::Desc => "DESC",
+                };
+                Ok(format!(
+                    "{} {direction}",
+                    self.dialect.quote_ident(&item.alias)
+                ))
+            })
+            .collect()
+    }
+
+    /// The model's table, aliased to the model name when qualified
+    fn table(&self, model: &Model) -> String {
+        let alias = self.dialect.quote_ident(&model.name);
+        let qualifiers: Vec<String> = [model.database.as_deref(), model.schema.as_deref()]
+            .into_iter()
+            .flatten()
+            .map(|part| self.dialect.quote_ident(part))
+            .collect();
+        if qualifiers.is_empty() {
+            alias
+        } else {
+            format!("{}.{alias} AS {alias}", qualifiers.join("."))
+        }
+    }
+
+    fn column(&self, model: &Model, column: &str) -> String {
+        format!(
+            "{}.{}",
+            self.dialect.quote_ident(&model.name),
+            self.dialect.quote_ident(column)
+        )
+    }
+}
+
+/// Breadth-first join paths from a base model to every model reachable from it.
+/// Relationships can be walked in either direction; declared directions win.
+struct JoinTree<'a> {
+    root: &'a Model,
+    parents: HashMap<&'a str, Edge<'a>>,
+    depths: HashMap<&'a str, usize>,
+}
+
+impl<'a> JoinTree<'a> {
+    fn build(models: &'a [Model], root: &'a Model) -> Self {
+        let mut parents = HashMap::new();
+        let mut depths = HashMap::from([(root.name.as_str(), 0)]);
+        let mut queue = VecDeque::from([root]);
+
+        while let Some(model) = queue.pop_front() {
+            let depth = depths[model.name.as_str()];
+            for edge in edges(models, model) {
+                if depths.contains_key(edge.to.name.as_str()) {
+                    continue;
+                }
+                depths.insert(edge.to.name.as_str(), depth + 1);
+                parents.insert(edge.to.name.as_str(), edge);
+                queue.push_back(edge.to);
+            }
+        }
+
+        Self {
+            root,
+            parents,
+            depths,
+        }
+    }
+
+    /// Edges leading from the root down to `to`
+    fn path(&self, to: &'a Model) -> Result<Vec<Edge<'a>>, CompileError> {
+        let mut path = Vec::new();
+        let mut current = to.name.as_str();
+        while current != self.root.name {
+            let edge = self
+                .parents
+                .get(current)
+                .ok_or_else(|| CompileError::NoJoinPath {
+                    from: self.root.name.clone(),
+                    to: to.name.clone(),
+                })?;
+            path.push(*edge);
+            current = edge.from.name.as_str();
+        }
+        path.reverse();
+        Ok(path)
+    }
+
+    /// Whether joining `model` in from the root can duplicate root rows
+    fn fans_out(&self, model: &'a Model) -> bool {
+        self.path(model)
+            .map(|path| path.iter().any(|edge| edge.fans_out()))
+            .unwrap_or(false)
+    }
+
+    fn depth(&self, model: &Model) -> usize {
+        self.depths.get(model.name.as_str()).copied().unwrap_or(0)
+    }
+}
+
+/// Join steps out of `model`: its own relationships, then ones declared towards it
+fn edges<'a>(models: &'a [Model], model: &'a Model) -> Vec<Edge<'a>> {
+    let forward = model.relationships.iter().filter_map(|relationship| {
+        let to = models.iter().find(|m| m.name == relationship.name)?;
+        Some(Edge {
+            from: model,
+            to,
+            from_col: &relationship.source_col,
+            to_col: &relationship.ref_col,
+            relationship,
+            reversed: false,
+        })
+    });
+
+    let reverse = models.iter().flat_map(|other| {
+        other
+            .relationships
+            .iter()
+            .filter(|relationship| relationship.name == model.name)
+            .map(move |relationship| Edge {
+                from: model,
+                to: other,
+                from_col: &relationship.ref_col,
+                to_col: &relationship.source_col,
+                relationship,
+                reversed: true,
+            })
+    });
+
+    forward.chain(reverse).collect()
+}
+
+/// Parses a `{name}` placeholder starting at `start`, returning the name and the
+/// index just past the closing brace
+fn placeholder(chars: &[char], start: usize) -> Option<(String, usize)> {
+    if chars.get(start) != Some(&'{') {
+        return None;
+    }
+    let mut end = start + 1;
+    while end < chars.len() && (chars[end].is_ascii_alphanumeric() || chars[end] == '_') {
+        end += 1;
+    }
+    if end == start + 1 || chars.get(end) != Some(&'}') {
+        return None;
+    }
+    Some((chars[start + 1..end].iter().collect(), end + 1))
+}
+
+fn has_dimension(model: &Model, name: &str) -> bool {
+    model.dimensions.iter().any(|d| d.name == name)
+}
+
+fn has_measure(model: &Model, name: &str) -> bool {
+    model.measures.iter().any(|m| m.name == name)
+}
+
+fn is_column(model: &Model, name: &str) -> bool {
+    has_dimension(model, name)
+        || has_measure(model, name)
+        || model.relationships.iter().any(|r| r.source_col == name)
+}
+
+fn column_type<'m>(model: &'m Model, name: &str) -> Option<&'m str> {
+    model
+        .dimensions
+        .iter()
+        .find(|d| d.name == name)
+        .and_then(|d| d.type_.as_deref())
+        .or_else(|| {
+            model
+                .measures
+                .iter()
+                .find(|m| m.name == name)
+                .and_then(|m| m.type_.as_deref())
+        })
+}
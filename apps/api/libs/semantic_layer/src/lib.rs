@@ -1,2 +1,2 @@
+pub mod compiler;
 pub mod models;
-// Placeholder for semantic_layer library code
@@ -0,0 +1,257 @@
+use std::collections::BTreeMap;
+
+use semantic_layer::compiler::{
+    compile_query, Aggregation, ArgValue, CompileError, FilterOperator, FilterSelection,
+    MeasureSelection, OrderBy, SemanticQuery, SortDirection, TimeDimension, TimeGrain,
+};
+use semantic_layer::models::Model;
+
+fn models() -> Vec<Model> {
+    let yaml = r#"
+- name: orders
+  database: analytics
+  schema: public
+  dimensions:
+    - name: order_id
+    - name: status
+      type: string
+    - name: created_at
+      type: timestamp
+  measures:
+    - name: amount
+      type: number
+  metrics:
+    - name: revenue
+      expr: SUM(amount)
+    - name: large_order_count
+      expr: COUNT(CASE WHEN amount > {threshold} THEN order_id END)
+      args:
+        - name: threshold
+          type: integer
+  filters:
+    - name: is_complete
+      expr: status = 'complete'
+  relationships:
+    - name: customers
+      source_col: customer_id
+      ref_col: id
+      cardinality: many-to-one
+- name: customers
+  database: analytics
+  schema: public
+  dimensions:
+    - name: id
+    - name: region
+  filters:
+    - name: has_recent_ticket
+      expr: support_tickets.opened_at > {since}
+      args:
+        - name: since
+          type: date
+  relationships:
+    - name: support_tickets
+      source_col: id
+      ref_col: customer_id
+      cardinality: one-to-many
+- name: support_tickets
+  database: analytics
+  schema: public
+  dimensions:
+    - name: customer_id
+    - name: opened_at
+"#;
+    serde_yaml::from_str(yaml).unwrap()
+}
+
+fn measure(name: &str) -> MeasureSelection {
+    MeasureSelection {
+        name: name.to_string(),
+        aggregation: None,
+        args: BTreeMap::new(),
+    }
+}
+
+#[test]
+fn test_revenue_by_region_by_month() {
+    let query = SemanticQuery {
+        measures: vec![measure("orders.revenue")],
+        dimensions: vec!["customers.region".to_string()],
+        filters: vec![FilterSelection::Named {
+            filter: "orders.is_complete".to_string(),
+            args: BTreeMap::new(),
+        }],
+        time_dimension: Some(TimeDimension {
+            dimension: "orders.created_at".to_string(),
+            grain: TimeGrain::Month,
+        }),
+        order_by: vec![OrderBy {
+            field: "revenue".to_string(),
+            direction: SortDirection::Desc,
+        }],
+        limit: Some(100),
+    };
+
+    let sql = compile_query(&models(), &query, "postgres").unwrap();
+
+    assert_eq!(
+        sql,
+        "SELECT DATE_TRUNC('month', orders.created_at) AS created_at_month, customers.region AS region, SUM(orders.amount) AS revenue\n\
+         FROM analytics.public.orders AS orders\n\
+         LEFT JOIN analytics.public.customers AS customers ON orders.customer_id = customers.id\n\
+         WHERE (orders.status = 'complete')\n\
+         GROUP BY DATE_TRUNC('month', orders.created_at), customers.region\n\
+         ORDER BY revenue DESC\n\
+         LIMIT 100"
+    );
+}
+
+#[test]
+fn test_dialect_specific_time_grain_and_limit() {
+    let query = SemanticQuery {
+        measures: vec![measure("revenue")],
+        time_dimension: Some(TimeDimension {
+            dimension: "created_at".to_string(),
+            grain: TimeGrain::Week,
+        }),
+        limit: Some(10),
+        ..Default::default()
+    };
+
+    let bigquery = compile_query(&models(), &query, "bigquery").unwrap();
+    assert!(bigquery.contains("DATE_TRUNC(orders.created_at, ISOWEEK) AS created_at_week"));
+    assert!(bigquery.ends_with("LIMIT 10"));
+
+    let sql_server = compile_query(&models(), &query, "sqlserver").unwrap();
+    assert!(sql_server.starts_with(
+        "SELECT TOP 10 DATEADD(week, DATEDIFF(week, 0, orders.created_at), 0) AS created_at_week"
+    ));
+    assert!(!sql_server.contains("LIMIT"));
+
+    let clickhouse = compile_query(&models(), &query, "clickhouse").unwrap();
+    assert!(clickhouse.contains("toMonday(orders.created_at)"));
+}
+
+#[test]
+fn test_metric_arguments_are_typed() {
+    let mut selection = measure("orders.large_order_count");
+    selection
+        .args
+        .insert("threshold".to_string(), ArgValue::Integer(500));
+    let query = SemanticQuery {
+        measures: vec![selection.clone()],
+        ..Default::default()
+    };
+
+    let sql = compile_query(&models(), &query, "snowflake").unwrap();
+    assert!(sql.starts_with(
+        "SELECT COUNT(CASE WHEN orders.amount > 500 THEN orders.order_id END) AS large_order_count"
+    ));
+
+    selection.args.insert(
+        "threshold".to_string(),
+        ArgValue::String("500; DROP TABLE orders".to_string()),
+    );
+    let query = SemanticQuery {
+        measures: vec![selection],
+        ..Default::default()
+    };
+    assert!(matches!(
+        compile_query(&models(), &query, "snowflake"),
+        Err(CompileError::InvalidArgument { .. })
+    ));
+
+    let query = SemanticQuery {
+        measures: vec![measure("orders.large_order_count")],
+        ..Default::default()
+    };
+    assert_eq!(
+        compile_query(&models(), &query, "snowflake"),
+        Err(CompileError::MissingArgument {
+            field: "orders.large_order_count".to_string(),
+            argument: "threshold".to_string(),
+        })
+    );
+}
+
+#[test]
+fn test_filter_on_one_to_many_relationship_uses_exists() {
+    let mut args = BTreeMap::new();
+    args.insert(
+        "since".to_string(),
+        ArgValue::String("2025-01-01".to_string()),
+    );
+    let query = SemanticQuery {
+        measures: vec![MeasureSelection {
+            name: "orders.amount".to_string(),
+            aggregation: Some(Aggregation::Avg),
+            args: BTreeMap::new(),
+        }],
+        filters: vec![
+            FilterSelection::Named {
+                filter: "customers.has_recent_ticket".to_string(),
+                args,
+            },
+            FilterSelection::Condition {
+                field: "orders.status".to_string(),
+                operator: FilterOperator::In,
+                values: vec![
+                    ArgValue::String("complete".to_string()),
+                    ArgValue::String("o'pen".to_string()),
+                ],
+            },
+        ],
+        ..Default::default()
+    };
+
+    let sql = compile_query(&models(), &query, "postgres").unwrap();
+
+    assert_eq!(
+        sql,
+        "SELECT AVG(orders.amount) AS amount_avg\n\
+         FROM analytics.public.orders AS orders\n\
+         LEFT JOIN analytics.public.customers AS customers ON orders.customer_id = customers.id\n\
+         WHERE EXISTS (SELECT 1 FROM analytics.public.support_tickets AS support_tickets \
+         WHERE support_tickets.customer_id = customers.id AND (support_tickets.opened_at > '2025-01-01')) \
+         AND orders.status IN ('complete', 'o''pen')"
+    );
+}
+
+#[test]
+fn test_selecting_across_one_to_many_relationship_is_rejected() {
+    let query = SemanticQuery {
+        measures: vec![measure("orders.revenue")],
+        dimensions: vec!["support_tickets.opened_at".to_string()],
+        ..Default::default()
+    };
+
+    assert_eq!(
+        compile_query(&models(), &query, "postgres"),
+        Err(CompileError::FanOut {
+            from: "customers".to_string(),
+            to: "support_tickets".to_string(),
+        })
+    );
+}
+
+#[test]
+fn test_query_deserializes_from_json() {
+    let query: SemanticQuery = serde_json::from_str(
+        r#"{
+            "measures": [{"name": "orders.revenue"}],
+            "dimensions": ["customers.region"],
+            "filters": [{"field": "orders.status", "operator": "eq", "values": ["complete"]}],
+            "time_dimension": {"dimension": "orders.created_at", "grain": "quarter"},
+            "order_by": [{"field": "orders.created_at"}],
+            "limit": 12
+        }"#,
+    )
+    .unwrap();
+
+    let sql = compile_query(&models(), &query, "mysql").unwrap();
+
+    assert!(sql.contains("WHERE orders.status = 'complete'"));
+    assert!(sql.contains(
+        "MAKEDATE(YEAR(orders.created_at), 1) + INTERVAL (QUARTER(orders.created_at) - 1) QUARTER AS created_at_quarter"
+    ));
+    assert!(sql.contains("ORDER BY created_at_quarter ASC"));
+}
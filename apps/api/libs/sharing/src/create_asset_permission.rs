@@ -1,7 +1,7 @@
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use database::{
-    client::SupabaseClient, enums::{AssetPermissionRole, AssetType, IdentityType}, models::AssetPermission, pool::get_pg_pool, schema::asset_permissions
+    client::SupabaseClient, enums::{AssetPermissionRole, AssetType, AuditEventType, IdentityType}, helpers::audit_logs::record_asset_access, models::AssetPermission, pool::get_pg_pool, schema::asset_permissions
 };
 use diesel::{prelude::*, upsert::excluded};
 use diesel_async::RunQueryDsl;
@@ -127,7 +127,7 @@ pub async fn create_share_by_email(
     };
 
     // Create the share for the user
-    create_share(
+    let permission = create_share(
         asset_id,
         asset_type,
         user.id,
@@ -135,7 +135,18 @@ pub async fn create_share_by_email(
         role,
         created_by,
     )
-    .await
+    .await?;
+
+    record_asset_access(
+        &created_by,
+        &asset_id,
+        asset_type,
+        AuditEventType::AssetShare,
+        serde_json::json!({ "email": email, "role": role }),
+    )
+    .await;
+
+    Ok(permission)
 }
 
 /// Creates multiple sharing records in bulk
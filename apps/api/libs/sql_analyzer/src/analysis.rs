@@ -1,4 +1,5 @@
 use crate::errors::SqlAnalyzerError;
+use crate::lineage::trace_query_lineage;
 use crate::types::{CteSummary, JoinInfo, QuerySummary, TableInfo, TableKind};
 use anyhow::Result;
 use rand;
@@ -30,14 +31,34 @@ pub async fn analyze_query(sql: String, data_source_dialect: &str) -> Result<Que
     }
 
     // If all statements are okay, proceed with analysis
-    for stmt in ast {
+    for stmt in &ast {
         if let Statement::Query(query) = stmt {
-            analyzer.process_query(&query, &HashMap::new())?;
+            analyzer.process_query(query, &HashMap::new())?;
         }
         // No need for else, we already checked above
     }
 
-    analyzer.into_summary()
+    let mut summary = analyzer.into_summary()?;
+
+    // Column lineage is traced for the final statement's projection
+    if let Some(Statement::Query(query)) = ast.last() {
+        let lineage = trace_query_lineage(query);
+        for cte in summary.ctes.iter_mut() {
+            if let Some(cte_columns) = lineage.ctes.get(&cte.name) {
+                for column in cte_columns {
+                    if let [source] = column.sources.as_slice() {
+                        cte.column_mappings.insert(
+                            column.column.clone(),
+                            (source.table_identifier.clone(), source.column.clone()),
+                        );
+                    }
+                }
+            }
+        }
+        summary.column_lineage = lineage.columns;
+    }
+
+    Ok(summary)
 }
 
 pub fn get_dialect(data_source_dialect: &str) -> &'static dyn Dialect {
@@ -999,6 +1020,7 @@ impl QueryAnalyzer {
             tables: final_tables.into_values().collect(),
             joins: self.joins,
             ctes: self.ctes,
+            column_lineage: Vec::new(),
         })
     }
 
@@ -0,0 +1,450 @@
+use crate::analysis::get_dialect;
+use crate::errors::SqlAnalyzerError;
+use crate::lineage::{trace_query_lineage, wildcard_arguments};
+use crate::types::ColumnMask;
+use sqlparser::ast::{
+    Expr, ObjectName, Query, SelectItem, SetExpr, Statement, TableFactor, Visit, Visitor,
+};
+use sqlparser::dialect::Dialect;
+use sqlparser::parser::Parser;
+use std::collections::{HashMap, HashSet};
+use std::ops::ControlFlow;
+
+/// Enforces column-level policies on a query before it is sent to a warehouse.
+///
+/// `column_masks` maps table names to the protected columns of that table. Queries that
+/// reference a `Deny` column anywhere are rejected, as are wildcards over a table with
+/// protected columns and whole rows read through a table alias (`row_to_json(u)`,
+/// `to_jsonb(u.*)`, `OBJECT_CONSTRUCT(*)`), which would carry every column along. Every
+/// column of the final projection that is computed from a masked column, traced through CTEs
+/// and derived tables with the same lineage `analyze_query` reports, is wrapped in a
+/// dialect-specific mask and keeps its output name. Lineage can't follow a column into a table
+/// function, UNNEST, lateral subquery or VALUES list, so queries that pass a protected column
+/// through one are rejected.
+///
+/// Dialects without a built-in hash function (SQLite) null out hashed columns instead.
+///
+/// # Examples
+/// ```no_run
+/// use sql_analyzer::{apply_column_masks, ColumnMask};
+/// use std::collections::HashMap;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let sql = "SELECT u.id, u.email FROM public.users u";
+///     let mut masks = HashMap::new();
+///     masks.insert(
+///         "users".to_string(),
+///         HashMap::from([("email".to_string(), ColumnMask::Hash)]),
+///     );
+///
+///     let masked_sql = apply_column_masks(sql.to_string(), masks, "postgres").await?;
+///     println!("Masked SQL: {}", masked_sql);
+///     Ok(())
+/// }
+/// ```
+pub async fn apply_column_masks(
+    sql: String,
+    column_masks: HashMap<String, HashMap<String, ColumnMask>>,
+    data_source_dialect: &str,
+) -> Result<String, SqlAnalyzerError> {
+    if column_masks.is_empty() {
+        return Ok(sql);
+    }
+
+    let data_source_dialect = data_source_dialect.to_lowercase();
+    let dialect = get_dialect(&data_source_dialect);
+    let mut statements = Parser::parse_sql(dialect, &sql)?;
+    let query = match statements.as_mut_slice() {
+        [Statement::Query(query)] => query,
+        _ => {
+            return Err(SqlAnalyzerError::UnsupportedStatement(
+                "Column policies can only be applied to a single SELECT query".to_string(),
+            ))
+        }
+    };
+
+    // Table and column names are matched case-insensitively; over-matching only masks more
+    let column_masks: HashMap<String, HashMap<String, ColumnMask>> = column_masks
+        .into_iter()
+        .map(|(table, columns)| {
+            let columns = columns
+                .into_iter()
+                .map(|(column, mask)| (column.to_lowercase(), mask))
+                .collect();
+            (table.to_lowercase(), columns)
+        })
+        .collect();
+
+    let mut references = ReferenceCollector::default();
+    let _ = query.visit(&mut references);
+
+    // Only the policies of tables the query reads apply
+    let mut masks_by_column: HashMap<&str, ColumnMask> = HashMap::new();
+    for table in &references.tables {
+        if let Some(columns) = column_masks.get(table) {
+            for (column, mask) in columns {
+                let entry = masks_by_column.entry(column.as_str()).or_insert(*mask);
+                *entry = (*entry).max(*mask);
+            }
+        }
+    }
+    if masks_by_column.is_empty() {
+        return Ok(sql);
+    }
+
+    // Whole rows carry every column past the masks, so they can't be read anywhere in the query
+    if references.wildcard_arguments {
+        return Err(SqlAnalyzerError::ColumnAccessDenied(
+            "the query reads protected columns through * in a function call; list the columns explicitly"
+                .to_string(),
+        ));
+    }
+    // A CTE column named like a relation, as in `WITH total AS (...) SELECT total FROM total`,
+    // is read as the column
+    let lineage = trace_query_lineage(query);
+    let mut row_values: Vec<&str> = references
+        .identifiers
+        .intersection(&references.relation_names)
+        .map(String::as_str)
+        .filter(|name| {
+            !lineage
+                .ctes
+                .values()
+                .flatten()
+                .any(|column| column.column.eq_ignore_ascii_case(name))
+        })
+        .collect();
+    if !row_values.is_empty() {
+        row_values.sort();
+        return Err(SqlAnalyzerError::ColumnAccessDenied(format!(
+            "the query reads whole rows of {}, which include protected columns; list the columns explicitly",
+            row_values.join(", ")
+        )));
+    }
+
+    // Denied columns can't be used anywhere, or they could be inferred through filters and joins
+    let mut denied: Vec<&str> = references
+        .columns
+        .iter()
+        .map(String::as_str)
+        .filter(|column| masks_by_column.get(column) == Some(&ColumnMask::Deny))
+        .collect();
+    if !denied.is_empty() {
+        denied.sort();
+        return Err(SqlAnalyzerError::ColumnAccessDenied(format!(
+            "the query references protected columns: {}",
+            denied.join(", ")
+        )));
+    }
+
+    // Lineage stops at table functions, UNNEST, PIVOT and VALUES, so a masked column passed
+    // through one could come out unmasked under another name
+    let mut opaque: Vec<&str> = references
+        .columns
+        .iter()
+        .map(String::as_str)
+        .filter(|column| masks_by_column.contains_key(column))
+        .collect();
+    if references.opaque_relations && !opaque.is_empty() {
+        opaque.sort();
+        return Err(SqlAnalyzerError::ColumnAccessDenied(format!(
+            "the query passes protected columns through a table function, UNNEST or VALUES: {}",
+            opaque.join(", ")
+        )));
+    }
+
+    let mut position_masks = Vec::with_capacity(lineage.columns.len());
+    for column in &lineage.columns {
+        let mut column_mask = None;
+        for source in &column.sources {
+            let table = source.table_identifier.to_lowercase();
+            let Some(table_masks) = column_masks.get(&table) else {
+                continue;
+            };
+            if source.column == "*" {
+                return Err(SqlAnalyzerError::ColumnAccessDenied(format!(
+                    "{} has protected columns, so it can't be selected with *; list the columns explicitly",
+                    source.table_identifier
+                )));
+            }
+            if let Some(mask) = table_masks.get(&source.column.to_lowercase()) {
+                column_mask = column_mask.max(Some(*mask));
+            }
+        }
+        if column_mask == Some(ColumnMask::Deny) {
+            return Err(SqlAnalyzerError::ColumnAccessDenied(format!(
+                "the query references protected columns: {}",
+                column.column
+            )));
+        }
+        position_masks.push(column_mask);
+    }
+
+    let masker = ProjectionMasker {
+        dialect,
+        data_source_dialect: &data_source_dialect,
+        position_masks: &position_masks,
+        masks_by_column: &masks_by_column,
+    };
+    if !masker.mask_body(&mut query.body)? {
+        return Ok(sql);
+    }
+
+    Ok(statements[0].to_string())
+}
+
+// Every relation name and column name the query mentions, lowercased
+#[derive(Default)]
+struct ReferenceCollector {
+    tables: HashSet<String>,
+    columns: HashSet<String>,
+    // Unqualified identifiers, which may name a relation rather than a column
+    identifiers: HashSet<String>,
+    // Names and aliases rows can be referenced by, including CTEs and derived tables
+    relation_names: HashSet<String>,
+    // Whether a function other than COUNT takes `*` or `alias.*`
+    wildcard_arguments: bool,
+    // Whether rows come from somewhere lineage can't trace, e.g. UNNEST or a VALUES list
+    opaque_relations: bool,
+}
+
+impl Visitor for ReferenceCollector {
+    type Break = ();
+
+    fn pre_visit_relation(&mut self, relation: &ObjectName) -> ControlFlow<Self::Break> {
+        if let Some(table) = relation.0.last() {
+            self.tables.insert(table.value.to_lowercase());
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<Self::Break> {
+        self.visit_body(&query.body);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, table_factor: &TableFactor) -> ControlFlow<Self::Break> {
+        // Lateral subqueries read the outer row, which their lineage doesn't see
+        if !matches!(
+            table_factor,
+            TableFactor::Table { args: None, .. }
+                | TableFactor::Derived { lateral: false, .. }
+                | TableFactor::NestedJoin { .. }
+        ) {
+            self.opaque_relations = true;
+        }
+
+        let (name, alias) = match table_factor {
+            TableFactor::Table { name, alias, .. } => (name.0.last(), alias),
+            TableFactor::Derived { alias, .. }
+            | TableFactor::TableFunction { alias, .. }
+            | TableFactor::Function { alias, .. }
+            | TableFactor::UNNEST { alias, .. } => (None, alias),
+            _ => return ControlFlow::Continue(()),
+        };
+        for ident in name
+            .into_iter()
+            .chain(alias.iter().map(|alias| &alias.name))
+        {
+            self.relation_names.insert(ident.value.to_lowercase());
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        match expr {
+            Expr::Identifier(ident) => {
+                self.columns.insert(ident.value.to_lowercase());
+                self.identifiers.insert(ident.value.to_lowercase());
+            }
+            Expr::CompoundIdentifier(idents) => {
+                if let Some(ident) = idents.last() {
+                    self.columns.insert(ident.value.to_lowercase());
+                }
+            }
+            Expr::Function(function) if !wildcard_arguments(function).is_empty() => {
+                self.wildcard_arguments = true;
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+impl ReferenceCollector {
+    // `TABLE name` reads a relation without naming it as one
+    fn visit_body(&mut self, body: &SetExpr) {
+        match body {
+            SetExpr::Select(_) | SetExpr::Query(_) => {}
+            SetExpr::SetOperation { left, right, .. } => {
+                self.visit_body(left);
+                self.visit_body(right);
+            }
+            SetExpr::Table(table) => {
+                if let Some(name) = &table.table_name {
+                    self.tables.insert(name.to_lowercase());
+                }
+                self.opaque_relations = true;
+            }
+            SetExpr::Values(_) | SetExpr::Insert(_) | SetExpr::Update(_) => {
+                self.opaque_relations = true;
+            }
+        }
+    }
+}
+
+// Wraps the outermost projection items in their masks
+struct ProjectionMasker<'a> {
+    dialect: &'static dyn Dialect,
+    data_source_dialect: &'a str,
+    // From lineage: the mask of each output column, by position
+    position_masks: &'a [Option<ColumnMask>],
+    // Fallback for references lineage can't resolve, e.g. unqualified columns in a join
+    masks_by_column: &'a HashMap<&'a str, ColumnMask>,
+}
+
+impl ProjectionMasker<'_> {
+    // Returns whether anything was masked. Set operations are masked by position in every branch.
+    fn mask_body(&self, body: &mut SetExpr) -> Result<bool, SqlAnalyzerError> {
+        match body {
+            SetExpr::Select(select) => {
+                let has_wildcard = select.projection.iter().any(|item| {
+                    matches!(
+                        item,
+                        SelectItem::Wildcard(_) | SelectItem::QualifiedWildcard(..)
+                    )
+                });
+                if has_wildcard && self.position_masks.iter().any(Option::is_some) {
+                    return Err(SqlAnalyzerError::ColumnAccessDenied(
+                        "the query selects masked columns through *; list the columns explicitly"
+                            .to_string(),
+                    ));
+                }
+
+                let mut masked = false;
+                for (position, item) in select.projection.iter_mut().enumerate() {
+                    let (expr, alias) = match item {
+                        SelectItem::UnnamedExpr(expr) => {
+                            let alias = match expr {
+                                Expr::Identifier(ident) => Some(ident.clone()),
+                                Expr::CompoundIdentifier(idents) => idents.last().cloned(),
+                                _ => None,
+                            };
+                            (expr, alias)
+                        }
+                        SelectItem::ExprWithAlias { expr, alias } => (expr, Some(alias.clone())),
+                        _ => continue,
+                    };
+
+                    let mut references = ReferenceCollector::default();
+                    let _ = expr.visit(&mut references);
+                    let name_mask = references
+                        .columns
+                        .iter()
+                        .filter_map(|column| self.masks_by_column.get(column.as_str()).copied())
+                        .max();
+                    let position_mask = self.position_masks.get(position).copied().flatten();
+
+                    let Some(mask) = name_mask.max(position_mask) else {
+                        continue;
+                    };
+                    let masked_expr = self.masked_expr(expr, mask)?;
+                    *item = match alias {
+                        Some(alias) => SelectItem::ExprWithAlias {
+                            expr: masked_expr,
+                            alias,
+                        },
+                        None => SelectItem::UnnamedExpr(masked_expr),
+                    };
+                    masked = true;
+                }
+                Ok(masked)
+            }
+            SetExpr::SetOperation { left, right, .. } => {
+                let left_masked = self.mask_body(left)?;
+                let right_masked = self.mask_body(right)?;
+                Ok(left_masked || right_masked)
+            }
+            SetExpr::Query(inner) => self.mask_body(&mut inner.body),
+            // Without a projection there's nothing to mask, so these can't read protected columns
+            SetExpr::Table(_) => Err(SqlAnalyzerError::ColumnAccessDenied(
+                "TABLE reads every column of a table; select the columns explicitly".to_string(),
+            )),
+            _ => {
+                let mut references = ReferenceCollector::default();
+                let _ = body.visit(&mut references);
+                let mut protected: Vec<&str> = references
+                    .columns
+                    .iter()
+                    .map(String::as_str)
+                    .filter(|column| self.masks_by_column.contains_key(column))
+                    .collect();
+                if protected.is_empty() {
+                    return Ok(false);
+                }
+                protected.sort();
+                Err(SqlAnalyzerError::ColumnAccessDenied(format!(
+                    "the query reads protected columns outside a SELECT: {}",
+                    protected.join(", ")
+                )))
+            }
+        }
+    }
+
+    fn masked_expr(&self, expr: &Expr, mask: ColumnMask) -> Result<Expr, SqlAnalyzerError> {
+        let masked_sql = match mask {
+            ColumnMask::Hash => match hash_expression(self.data_source_dialect, expr) {
+                Some(hashed) => hashed,
+                None => "NULL".to_string(),
+            },
+            ColumnMask::Partial => partial_mask_expression(self.data_source_dialect, expr),
+            ColumnMask::Null | ColumnMask::Deny => "NULL".to_string(),
+        };
+
+        Ok(Parser::new(self.dialect)
+            .try_with_sql(&masked_sql)?
+            .parse_expr()?)
+    }
+}
+
+// Hex digest of the value's text; None when the warehouse has no hash function
+fn hash_expression(data_source_dialect: &str, expr: &Expr) -> Option<String> {
+    let hashed = match data_source_dialect {
+        "postgres" | "redshift" | "supabase" => format!("MD5(CAST({} AS VARCHAR))", expr),
+        "snowflake" => format!("SHA2(CAST({} AS VARCHAR), 256)", expr),
+        "bigquery" => format!("TO_HEX(SHA256(CAST({} AS STRING)))", expr),
+        "mysql" | "mariadb" => format!("SHA2(CAST({} AS CHAR), 256)", expr),
+        "databricks" => format!("SHA2(CAST({} AS STRING), 256)", expr),
+        "sqlserver" => format!(
+            "CONVERT(VARCHAR(64), HASHBYTES('SHA2_256', CAST({} AS NVARCHAR(4000))), 2)",
+            expr
+        ),
+        "duckdb" => format!("SHA256(CAST({} AS VARCHAR))", expr),
+        "clickhouse" => format!("hex(SHA256(toString({})))", expr),
+        "trino" => format!("to_hex(sha256(to_utf8(CAST({} AS VARCHAR))))", expr),
+        _ => return None,
+    };
+    Some(hashed)
+}
+
+// Keeps the last four characters, e.g. `****1234`, and leaves NULLs as NULL
+fn partial_mask_expression(data_source_dialect: &str, expr: &Expr) -> String {
+    let text = match data_source_dialect {
+        "bigquery" | "databricks" => format!("CAST({} AS STRING)", expr),
+        "mysql" | "mariadb" => format!("CAST({} AS CHAR)", expr),
+        "sqlserver" => format!("CAST({} AS NVARCHAR(4000))", expr),
+        "clickhouse" => format!("toString({})", expr),
+        "sqlite" => format!("CAST({} AS TEXT)", expr),
+        _ => format!("CAST({} AS VARCHAR)", expr),
+    };
+    let suffix = match data_source_dialect {
+        "sqlite" | "trino" | "clickhouse" => format!("SUBSTR({}, -4)", text),
+        _ => format!("RIGHT({}, 4)", text),
+    };
+    let masked = match data_source_dialect {
+        "sqlite" => format!("'****' || {}", suffix),
+        _ => format!("CONCAT('****', {})", suffix),
+    };
+    format!("CASE WHEN {} IS NULL THEN NULL ELSE {} END", expr, masked)
+}
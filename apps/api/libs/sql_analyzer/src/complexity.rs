@@ -0,0 +1,270 @@
+use crate::analysis::get_dialect;
+use crate::errors::SqlAnalyzerError;
+use crate::types::QueryComplexityReport;
+use sqlparser::ast::{
+    BinaryOperator, Expr, JoinConstraint, JoinOperator, Query, Select, SetExpr, Statement,
+    TableFactor, Visit, Visitor,
+};
+use sqlparser::parser::Parser;
+use std::collections::HashSet;
+use std::ops::ControlFlow;
+
+/// Produces a static complexity report for a query without executing it.
+///
+/// The report counts joins, cartesian products (explicit `CROSS JOIN`s and comma joins or
+/// `ON` clauses that never relate the two sides), tables scanned without a `WHERE` clause,
+/// subquery nesting depth and window functions, and rolls them into a single weighted score.
+///
+/// # Examples
+/// ```no_run
+/// use sql_analyzer::analyze_query_complexity;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let sql = "SELECT o.id, c.name FROM sales.orders o, sales.customers c";
+///     let report = analyze_query_complexity(sql.to_string(), "snowflake").await?;
+///     println!("Cartesian joins: {:?}", report.missing_join_predicates);
+///     Ok(())
+/// }
+/// ```
+pub async fn analyze_query_complexity(
+    sql: String,
+    data_source_dialect: &str,
+) -> Result<QueryComplexityReport, SqlAnalyzerError> {
+    let ast = Parser::parse_sql(get_dialect(data_source_dialect), &sql)?;
+
+    let mut analyzer = ComplexityAnalyzer::default();
+    for stmt in &ast {
+        if !matches!(stmt, Statement::Query(_)) {
+            return Err(SqlAnalyzerError::UnsupportedStatement(format!(
+                "Only SELECT queries are supported. Found: {}",
+                stmt
+            )));
+        }
+        let _ = stmt.visit(&mut analyzer);
+    }
+
+    let mut report = analyzer.report;
+    report.unbounded_scans.sort();
+    report.unbounded_scans.dedup();
+    report.score = report.compute_score();
+    Ok(report)
+}
+
+#[derive(Default)]
+struct ComplexityAnalyzer {
+    report: QueryComplexityReport,
+    query_depth: usize,
+    cte_names: Vec<HashSet<String>>,
+}
+
+impl ComplexityAnalyzer {
+    fn is_cte(&self, name: &str) -> bool {
+        self.cte_names.iter().any(|scope| scope.contains(name))
+    }
+
+    fn analyze_body(&mut self, body: &SetExpr) {
+        match body {
+            SetExpr::Select(select) => self.analyze_select(select),
+            SetExpr::SetOperation { left, right, .. } => {
+                self.analyze_body(left);
+                self.analyze_body(right);
+            }
+            // Nested queries are visited on their own
+            _ => {}
+        }
+    }
+
+    fn analyze_select(&mut self, select: &Select) {
+        let predicate_pairs = select
+            .selection
+            .as_ref()
+            .map(equality_qualifier_pairs)
+            .unwrap_or_default();
+
+        let mut seen_relations: Vec<String> = Vec::new();
+        for (idx, table_with_joins) in select.from.iter().enumerate() {
+            let relation_name = relation_name(&table_with_joins.relation);
+
+            // Comma joins are cartesian products unless the WHERE clause relates them
+            if idx > 0 {
+                self.report.join_count += 1;
+                let connected = relation_name.as_ref().is_some_and(|name| {
+                    seen_relations.iter().any(|seen| {
+                        predicate_pairs.contains(&(seen.clone(), name.clone()))
+                            || predicate_pairs.contains(&(name.clone(), seen.clone()))
+                    })
+                });
+                if !connected {
+                    self.report.missing_join_predicates.push(format!(
+                        "{} joined to {} without a join condition",
+                        relation_name.clone().unwrap_or_else(|| "subquery".to_string()),
+                        seen_relations.join(", ")
+                    ));
+                }
+            }
+            if let Some(name) = &relation_name {
+                seen_relations.push(name.clone());
+            }
+            self.record_scan(&table_with_joins.relation, select);
+
+            for join in &table_with_joins.joins {
+                self.report.join_count += 1;
+                let right_name = relation_name_or_default(&join.relation);
+                self.record_scan(&join.relation, select);
+
+                match &join.join_operator {
+                    JoinOperator::CrossJoin => {
+                        self.report.cross_join_count += 1;
+                    }
+                    JoinOperator::Inner(constraint)
+                    | JoinOperator::LeftOuter(constraint)
+                    | JoinOperator::RightOuter(constraint)
+                    | JoinOperator::FullOuter(constraint) => {
+                        let has_predicate = match constraint {
+                            JoinConstraint::On(expr) => {
+                                let qualifiers = column_qualifiers(expr);
+                                // `ON 1 = 1`, `ON TRUE` and predicates that only touch one side
+                                // don't constrain the join
+                                (qualifiers.contains(&right_name) && qualifiers.len() > 1)
+                                    || (qualifiers.is_empty() && has_unqualified_columns(expr))
+                            }
+                            JoinConstraint::Using(_) | JoinConstraint::Natural => true,
+                            JoinConstraint::None => false,
+                        };
+                        if !has_predicate {
+                            self.report.missing_join_predicates.push(format!(
+                                "{} joined without a predicate relating both sides",
+                                right_name
+                            ));
+                        }
+                    }
+                    _ => {}
+                }
+                seen_relations.push(right_name);
+            }
+        }
+    }
+
+    fn record_scan(&mut self, factor: &TableFactor, select: &Select) {
+        if select.selection.is_some() {
+            return;
+        }
+        if let TableFactor::Table { name, .. } = factor {
+            let table = name.to_string();
+            if name.0.len() == 1 && self.is_cte(&table) {
+                return;
+            }
+            self.report.unbounded_scans.push(table);
+        }
+    }
+}
+
+impl Visitor for ComplexityAnalyzer {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<Self::Break> {
+        self.query_depth += 1;
+        // The outermost query is depth 1, so nesting depth is one less
+        self.report.max_subquery_depth = self.report.max_subquery_depth.max(self.query_depth - 1);
+
+        let mut scope = HashSet::new();
+        if let Some(with) = &query.with {
+            for cte in &with.cte_tables {
+                scope.insert(cte.alias.name.value.clone());
+            }
+        }
+        self.cte_names.push(scope);
+
+        self.analyze_body(&query.body);
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, _query: &Query) -> ControlFlow<Self::Break> {
+        self.query_depth -= 1;
+        self.cte_names.pop();
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        if let Expr::Function(function) = expr {
+            if function.over.is_some() {
+                self.report.window_function_count += 1;
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+// Name a relation is referred to by in predicates: its alias, or the bare table name
+fn relation_name(factor: &TableFactor) -> Option<String> {
+    match factor {
+        TableFactor::Table { name, alias, .. } => Some(
+            alias
+                .as_ref()
+                .map(|a| a.name.value.clone())
+                .unwrap_or_else(|| name.0.last().map(|i| i.value.clone()).unwrap_or_default()),
+        ),
+        TableFactor::Derived { alias, .. }
+        | TableFactor::TableFunction { alias, .. }
+        | TableFactor::Function { alias, .. }
+        | TableFactor::UNNEST { alias, .. } => alias.as_ref().map(|a| a.name.value.clone()),
+        _ => None,
+    }
+}
+
+fn relation_name_or_default(factor: &TableFactor) -> String {
+    relation_name(factor).unwrap_or_else(|| "subquery".to_string())
+}
+
+// Qualifier pairs of `a.x = b.y` predicates anywhere in an expression
+fn equality_qualifier_pairs(expr: &Expr) -> HashSet<(String, String)> {
+    let mut pairs = HashSet::new();
+    let _ = sqlparser::ast::visit_expressions(expr, |e| {
+        if let Expr::BinaryOp {
+            left,
+            op: BinaryOperator::Eq,
+            right,
+        } = e
+        {
+            if let (Some(l), Some(r)) = (qualifier_of(left), qualifier_of(right)) {
+                if l != r {
+                    pairs.insert((l, r));
+                }
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    pairs
+}
+
+fn column_qualifiers(expr: &Expr) -> HashSet<String> {
+    let mut qualifiers = HashSet::new();
+    let _ = sqlparser::ast::visit_expressions(expr, |e| {
+        if let Some(q) = qualifier_of(e) {
+            qualifiers.insert(q);
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    qualifiers
+}
+
+fn has_unqualified_columns(expr: &Expr) -> bool {
+    let mut found = false;
+    let _ = sqlparser::ast::visit_expressions(expr, |e| {
+        if matches!(e, Expr::Identifier(_)) {
+            found = true;
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    found
+}
+
+fn qualifier_of(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::CompoundIdentifier(idents) if idents.len() >= 2 => {
+            Some(idents[idents.len() - 2].value.clone())
+        }
+        _ => None,
+    }
+}
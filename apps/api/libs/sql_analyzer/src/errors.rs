@@ -35,6 +35,12 @@ pub enum SqlAnalyzerError {
     #[error("Unsupported statement type found: {0}")]
     UnsupportedStatement(String),
 
+    #[error("Transpilation error: {0}")]
+    Transpilation(String),
+
+    #[error("Column access denied: {0}")]
+    ColumnAccessDenied(String),
+
     #[error("Internal error: {0}")]
     Internal(#[from] anyhow::Error),
 }
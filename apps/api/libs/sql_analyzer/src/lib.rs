@@ -1,26 +1,36 @@
 //! SQL Analyzer Library
 //!
 //! This library provides functionality to parse and analyze SQL queries,
-//! extracting tables, columns, joins, and CTEs with lineage tracing.
+//! extracting tables, columns, joins, and CTEs with lineage tracing down to
+//! individual projected columns.
 //! It also includes semantic layer validation and substitution capabilities
-//! to support querying with predefined metrics and filters.
+//! to support querying with predefined metrics and filters, and can transpile
+//! queries between the warehouse dialects we connect to.
 //! Designed for integration with a Tokio-based web server.
 
 mod errors;
+mod lineage;
 pub mod types;
 pub mod utils;
 
 pub mod analysis;
+pub mod column_masking;
+pub mod complexity;
 pub mod semantic;
 pub mod row_filtering;
+pub mod transpile;
 
 pub use errors::SqlAnalyzerError;
 pub use types::{
-    QuerySummary, TableInfo, JoinInfo, CteSummary, 
+    QuerySummary, TableInfo, JoinInfo, CteSummary, ColumnLineage, SourceColumn,
+    LineageStep, TransformationKind, QueryComplexityReport, ColumnMask,
     SemanticLayer, ValidationMode, Metric, Filter, 
-    Parameter, ParameterType, Relationship
+    Parameter, ParameterType, Relationship, Cardinality, JoinPath, JoinStep
 };
 
 pub use analysis::analyze_query;
+pub use column_masking::apply_column_masks;
+pub use complexity::analyze_query_complexity;
 pub use semantic::{validate_semantic_query, substitute_semantic_query, validate_and_substitute_semantic_query};
-pub use row_filtering::apply_row_level_filters;
\ No newline at end of file
+pub use row_filtering::{apply_row_level_filters, apply_row_level_filters_with_dialect};
+pub use transpile::transpile_query;
\ No newline at end of file
@@ -0,0 +1,603 @@
+use crate::types::{ColumnLineage, LineageStep, SourceColumn, TransformationKind};
+use sqlparser::ast::{
+    BinaryOperator, Expr, Function, FunctionArg, FunctionArgExpr, FunctionArguments, Query, Select,
+    SelectItem, SetExpr, TableAlias, TableFactor, UnaryOperator, Visit, Visitor,
+};
+use std::collections::{BTreeSet, HashMap};
+use std::ops::ControlFlow;
+
+// Aggregate function names recognised across the supported dialects
+const AGGREGATE_FUNCTIONS: &[&str] = &[
+    "sum", "count", "avg", "min", "max", "median", "mode", "stddev", "stddev_pop",
+    "stddev_samp", "variance", "var_pop", "var_samp", "array_agg", "string_agg", "listagg",
+    "group_concat", "approx_count_distinct", "count_if", "countif", "any_value", "bool_and",
+    "bool_or", "percentile_cont", "percentile_disc", "approx_percentile",
+];
+
+/// Column lineage for the final SELECT of a query plus every CTE defined along the way.
+pub(crate) struct QueryLineage {
+    pub columns: Vec<ColumnLineage>,
+    pub ctes: HashMap<String, Vec<ColumnLineage>>,
+}
+
+/// Traces every projected column of `query` back to the base table columns it is computed from.
+pub(crate) fn trace_query_lineage(query: &Query) -> QueryLineage {
+    let mut tracer = LineageTracer::default();
+    let columns = tracer.query_lineage(query, "query");
+    QueryLineage {
+        columns,
+        ctes: tracer.traced_ctes,
+    }
+}
+
+// A relation visible in the FROM clause of the SELECT being traced
+#[derive(Debug, Clone)]
+enum Relation {
+    Base {
+        database: Option<String>,
+        schema: Option<String>,
+        table: String,
+    },
+    // CTE references and derived tables expose already-traced columns
+    Traced(Vec<ColumnLineage>),
+    // Table functions, pivots, etc. whose columns we can't trace
+    Opaque,
+}
+
+#[derive(Default)]
+struct LineageTracer {
+    cte_scopes: Vec<HashMap<String, Vec<ColumnLineage>>>,
+    traced_ctes: HashMap<String, Vec<ColumnLineage>>,
+}
+
+impl LineageTracer {
+    fn query_lineage(&mut self, query: &Query, scope: &str) -> Vec<ColumnLineage> {
+        let has_with = query.with.is_some();
+        if let Some(with) = &query.with {
+            self.cte_scopes.push(HashMap::new());
+            for cte in &with.cte_tables {
+                let cte_name = cte.alias.name.value.clone();
+                let columns = self.query_lineage(&cte.query, &format!("CTE:{}", cte_name));
+                let columns = apply_alias_columns(columns, &cte.alias);
+                self.traced_ctes.insert(cte_name.clone(), columns.clone());
+                if let Some(current) = self.cte_scopes.last_mut() {
+                    current.insert(cte_name, columns);
+                }
+            }
+        }
+
+        let columns = self.set_expr_lineage(&query.body, scope);
+
+        if has_with {
+            self.cte_scopes.pop();
+        }
+        columns
+    }
+
+    fn set_expr_lineage(&mut self, body: &SetExpr, scope: &str) -> Vec<ColumnLineage> {
+        match body {
+            SetExpr::Select(select) => self.select_lineage(select, scope),
+            SetExpr::Query(inner) => self.query_lineage(inner, scope),
+            SetExpr::SetOperation { left, right, op, .. } => {
+                let left_columns = self.set_expr_lineage(left, scope);
+                let right_columns = self.set_expr_lineage(right, scope);
+
+                // Set operations match columns by position and take their names from the left side
+                left_columns
+                    .into_iter()
+                    .enumerate()
+                    .map(|(idx, left_col)| {
+                        let mut sources: BTreeSet<SourceColumn> =
+                            left_col.sources.into_iter().collect();
+                        let mut steps = left_col.steps;
+                        if let Some(right_col) = right_columns.get(idx) {
+                            sources.extend(right_col.sources.iter().cloned());
+                            merge_steps(&mut steps, &right_col.steps);
+                        }
+                        steps.push(LineageStep {
+                            scope: scope.to_string(),
+                            column: left_col.column.clone(),
+                            expression: op.to_string(),
+                            transformations: vec![TransformationKind::SetOperation],
+                        });
+                        ColumnLineage {
+                            column: left_col.column,
+                            sources: sources.into_iter().collect(),
+                            steps,
+                        }
+                    })
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn select_lineage(&mut self, select: &Select, scope: &str) -> Vec<ColumnLineage> {
+        let mut relations: Vec<(String, Relation)> = Vec::new();
+        for table_with_joins in &select.from {
+            self.register_relation(&table_with_joins.relation, &mut relations);
+            for join in &table_with_joins.joins {
+                self.register_relation(&join.relation, &mut relations);
+            }
+        }
+
+        let mut columns = Vec::new();
+        for item in &select.projection {
+            match item {
+                SelectItem::UnnamedExpr(expr) => {
+                    let name = output_name_for_expr(expr);
+                    columns.push(self.expr_lineage(expr, &name, scope, &relations));
+                }
+                SelectItem::ExprWithAlias { expr, alias } => {
+                    columns.push(self.expr_lineage(expr, &alias.value, scope, &relations));
+                }
+                SelectItem::QualifiedWildcard(object_name, _) => {
+                    let qualifier = object_name
+                        .0
+                        .last()
+                        .map(|i| i.value.clone())
+                        .unwrap_or_default();
+                    for (alias, relation) in &relations {
+                        if alias.eq_ignore_ascii_case(&qualifier) {
+                            columns.extend(expand_wildcard(relation, scope));
+                        }
+                    }
+                }
+                SelectItem::Wildcard(_) => {
+                    for (_, relation) in &relations {
+                        columns.extend(expand_wildcard(relation, scope));
+                    }
+                }
+            }
+        }
+        columns
+    }
+
+    fn register_relation(&mut self, factor: &TableFactor, relations: &mut Vec<(String, Relation)>) {
+        match factor {
+            TableFactor::Table { name, alias, .. } => {
+                let idents: Vec<String> = name.0.iter().map(|i| i.value.clone()).collect();
+                let table = idents.last().cloned().unwrap_or_default();
+                let key = alias
+                    .as_ref()
+                    .map(|a| a.name.value.clone())
+                    .unwrap_or_else(|| table.clone());
+
+                let relation = if idents.len() == 1 {
+                    match self.lookup_cte(&table) {
+                        Some(columns) => Relation::Traced(columns),
+                        None => Relation::Base {
+                            database: None,
+                            schema: None,
+                            table,
+                        },
+                    }
+                } else {
+                    let (database, schema) = match idents.len() {
+                        2 => (None, Some(idents[0].clone())),
+                        _ => (
+                            Some(idents[idents.len() - 3].clone()),
+                            Some(idents[idents.len() - 2].clone()),
+                        ),
+                    };
+                    Relation::Base {
+                        database,
+                        schema,
+                        table,
+                    }
+                };
+
+                let relation = match (relation, alias) {
+                    (Relation::Traced(columns), Some(a)) => {
+                        Relation::Traced(apply_alias_columns(columns, a))
+                    }
+                    (relation, _) => relation,
+                };
+                relations.push((key, relation));
+            }
+            TableFactor::Derived {
+                subquery, alias, ..
+            } => {
+                let key = alias
+                    .as_ref()
+                    .map(|a| a.name.value.clone())
+                    .unwrap_or_else(|| "_derived".to_string());
+                let columns = self.query_lineage(subquery, &format!("derived:{}", key));
+                let columns = match alias {
+                    Some(a) => apply_alias_columns(columns, a),
+                    None => columns,
+                };
+                relations.push((key, Relation::Traced(columns)));
+            }
+            TableFactor::NestedJoin {
+                table_with_joins, ..
+            } => {
+                self.register_relation(&table_with_joins.relation, relations);
+                for join in &table_with_joins.joins {
+                    self.register_relation(&join.relation, relations);
+                }
+            }
+            TableFactor::TableFunction { alias, .. }
+            | TableFactor::Function { alias, .. }
+            | TableFactor::UNNEST { alias, .. }
+            | TableFactor::Pivot { alias, .. }
+            | TableFactor::Unpivot { alias, .. } => {
+                if let Some(a) = alias {
+                    relations.push((a.name.value.clone(), Relation::Opaque));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn lookup_cte(&self, name: &str) -> Option<Vec<ColumnLineage>> {
+        self.cte_scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name).cloned())
+    }
+
+    fn expr_lineage(
+        &mut self,
+        expr: &Expr,
+        column: &str,
+        scope: &str,
+        relations: &[(String, Relation)],
+    ) -> ColumnLineage {
+        let mut collector = ExprCollector::default();
+        let _ = expr.visit(&mut collector);
+
+        let mut sources = BTreeSet::new();
+        let mut steps = Vec::new();
+
+        for (qualifier, name) in &collector.column_refs {
+            if let Some(resolved) = resolve_column(qualifier.as_deref(), name, relations) {
+                sources.extend(resolved.sources);
+                merge_steps(&mut steps, &resolved.steps);
+            }
+        }
+
+        // Scalar subqueries contribute the lineage of their single projected column
+        for subquery in &collector.subqueries {
+            for sub_column in self.query_lineage(subquery, scope) {
+                sources.extend(sub_column.sources);
+                merge_steps(&mut steps, &sub_column.steps);
+            }
+        }
+
+        steps.push(LineageStep {
+            scope: scope.to_string(),
+            column: column.to_string(),
+            expression: expr.to_string(),
+            transformations: collector.transformations,
+        });
+
+        ColumnLineage {
+            column: column.to_string(),
+            sources: sources.into_iter().collect(),
+            steps,
+        }
+    }
+}
+
+/// The rows a function reads whole through `*` or `alias.*` arguments, e.g. `to_jsonb(u.*)`,
+/// as the qualifier of each wildcard (None for a bare `*`). `COUNT(*)` only counts rows.
+pub(crate) fn wildcard_arguments(function: &Function) -> Vec<Option<String>> {
+    let is_count = function
+        .name
+        .0
+        .last()
+        .is_some_and(|name| name.value.eq_ignore_ascii_case("count"));
+    let FunctionArguments::List(list) = &function.args else {
+        return Vec::new();
+    };
+    if is_count {
+        return Vec::new();
+    }
+
+    list.args
+        .iter()
+        .filter_map(|arg| match arg {
+            FunctionArg::Named { arg, .. }
+            | FunctionArg::ExprNamed { arg, .. }
+            | FunctionArg::Unnamed(arg) => match arg {
+                FunctionArgExpr::Wildcard => Some(None),
+                FunctionArgExpr::QualifiedWildcard(name) => {
+                    Some(name.0.last().map(|i| i.value.clone()))
+                }
+                FunctionArgExpr::Expr(_) => None,
+            },
+        })
+        .collect()
+}
+
+// Resolves a (possibly qualified) column reference against the relations of the current SELECT.
+// `*` and bare relation names, as in `row_to_json(u)`, resolve to every column of the relation.
+fn resolve_column(
+    qualifier: Option<&str>,
+    column: &str,
+    relations: &[(String, Relation)],
+) -> Option<ColumnLineage> {
+    if column == "*" {
+        let rows = relations
+            .iter()
+            .filter(|(alias, _)| qualifier.map_or(true, |q| alias.eq_ignore_ascii_case(q)))
+            .filter_map(|(_, relation)| whole_row_lineage(relation, column));
+        return merge_lineage(column, rows);
+    }
+    if qualifier.is_none() {
+        if let Some((_, relation)) = relations
+            .iter()
+            .find(|(alias, _)| alias.eq_ignore_ascii_case(column))
+        {
+            // Columns take precedence over relations of the same name
+            let is_column = matches!(relation, Relation::Traced(columns)
+                if columns.iter().any(|c| c.column.eq_ignore_ascii_case(column)));
+            if !is_column {
+                return whole_row_lineage(relation, column);
+            }
+        }
+    }
+
+    match qualifier {
+        Some(q) => relations
+            .iter()
+            .find(|(alias, _)| alias.eq_ignore_ascii_case(q))
+            .and_then(|(_, relation)| column_from_relation(relation, column)),
+        None => {
+            if relations.len() == 1 {
+                return column_from_relation(&relations[0].1, column);
+            }
+
+            // Prefer a traced relation that explicitly exposes the column
+            let mut traced_matches = relations.iter().filter_map(|(_, relation)| match relation {
+                Relation::Traced(columns) => columns
+                    .iter()
+                    .find(|c| c.column.eq_ignore_ascii_case(column))
+                    .cloned(),
+                _ => None,
+            });
+            if let Some(found) = traced_matches.next() {
+                if traced_matches.next().is_none() {
+                    return Some(found);
+                }
+                return None; // Ambiguous
+            }
+
+            let mut base_relations = relations
+                .iter()
+                .filter(|(_, relation)| matches!(relation, Relation::Base { .. }));
+            match (base_relations.next(), base_relations.next()) {
+                (Some((_, relation)), None) => column_from_relation(relation, column),
+                _ => None,
+            }
+        }
+    }
+}
+
+fn column_from_relation(relation: &Relation, column: &str) -> Option<ColumnLineage> {
+    match relation {
+        Relation::Base {
+            database,
+            schema,
+            table,
+        } => Some(ColumnLineage {
+            column: column.to_string(),
+            sources: vec![SourceColumn {
+                database_identifier: database.clone(),
+                schema_identifier: schema.clone(),
+                table_identifier: table.clone(),
+                column: column.to_string(),
+            }],
+            steps: Vec::new(),
+        }),
+        Relation::Traced(columns) => {
+            if let Some(found) = columns
+                .iter()
+                .find(|c| c.column.eq_ignore_ascii_case(column))
+            {
+                return Some(found.clone());
+            }
+            // Columns selected through an unexpanded `*` map straight onto the underlying table
+            columns.iter().find(|c| c.column == "*").map(|wildcard| ColumnLineage {
+                column: column.to_string(),
+                sources: wildcard
+                    .sources
+                    .iter()
+                    .map(|s| SourceColumn {
+                        column: column.to_string(),
+                        ..s.clone()
+                    })
+                    .collect(),
+                steps: wildcard.steps.clone(),
+            })
+        }
+        Relation::Opaque => None,
+    }
+}
+
+fn whole_row_lineage(relation: &Relation, column: &str) -> Option<ColumnLineage> {
+    match relation {
+        Relation::Base { .. } => column_from_relation(relation, "*").map(|mut row| {
+            row.column = column.to_string();
+            row
+        }),
+        Relation::Traced(columns) => merge_lineage(column, columns.iter().cloned()),
+        Relation::Opaque => None,
+    }
+}
+
+// Combines the sources and steps of several lineages into one column
+fn merge_lineage(
+    column: &str,
+    lineages: impl IntoIterator<Item = ColumnLineage>,
+) -> Option<ColumnLineage> {
+    let mut merged: Option<(BTreeSet<SourceColumn>, Vec<LineageStep>)> = None;
+    for lineage in lineages {
+        let (sources, steps) = merged.get_or_insert_with(Default::default);
+        sources.extend(lineage.sources);
+        merge_steps(steps, &lineage.steps);
+    }
+    merged.map(|(sources, steps)| ColumnLineage {
+        column: column.to_string(),
+        sources: sources.into_iter().collect(),
+        steps,
+    })
+}
+
+fn expand_wildcard(relation: &Relation, scope: &str) -> Vec<ColumnLineage> {
+    match relation {
+        Relation::Traced(columns) => columns
+            .iter()
+            .cloned()
+            .map(|mut c| {
+                c.steps.push(LineageStep {
+                    scope: scope.to_string(),
+                    column: c.column.clone(),
+                    expression: c.column.clone(),
+                    transformations: Vec::new(),
+                });
+                c
+            })
+            .collect(),
+        Relation::Base { .. } => column_from_relation(relation, "*")
+            .map(|mut c| {
+                c.steps.push(LineageStep {
+                    scope: scope.to_string(),
+                    column: "*".to_string(),
+                    expression: "*".to_string(),
+                    transformations: Vec::new(),
+                });
+                vec![c]
+            })
+            .unwrap_or_default(),
+        Relation::Opaque => Vec::new(),
+    }
+}
+
+// Renames traced columns positionally when an alias declares a column list, e.g. `cte(a, b)`
+fn apply_alias_columns(columns: Vec<ColumnLineage>, alias: &TableAlias) -> Vec<ColumnLineage> {
+    if alias.columns.is_empty() {
+        return columns;
+    }
+    columns
+        .into_iter()
+        .enumerate()
+        .map(|(idx, mut c)| {
+            if let Some(renamed) = alias.columns.get(idx) {
+                c.column = renamed.name.value.clone();
+            }
+            c
+        })
+        .collect()
+}
+
+fn output_name_for_expr(expr: &Expr) -> String {
+    match expr {
+        Expr::Identifier(ident) => ident.value.clone(),
+        Expr::CompoundIdentifier(idents) => idents
+            .last()
+            .map(|i| i.value.clone())
+            .unwrap_or_default(),
+        _ => expr.to_string(),
+    }
+}
+
+fn merge_steps(steps: &mut Vec<LineageStep>, other: &[LineageStep]) {
+    for step in other {
+        if !steps.contains(step) {
+            steps.push(step.clone());
+        }
+    }
+}
+
+// Collects column references and transformation kinds from a single projected expression.
+// Nested subqueries are recorded for separate tracing rather than walked in place.
+#[derive(Default)]
+struct ExprCollector {
+    column_refs: Vec<(Option<String>, String)>,
+    transformations: Vec<TransformationKind>,
+    subqueries: Vec<Query>,
+    query_depth: usize,
+}
+
+impl ExprCollector {
+    fn record(&mut self, kind: TransformationKind) {
+        if !self.transformations.contains(&kind) {
+            self.transformations.push(kind);
+        }
+    }
+}
+
+impl Visitor for ExprCollector {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, _query: &Query) -> ControlFlow<Self::Break> {
+        self.query_depth += 1;
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_query(&mut self, _query: &Query) -> ControlFlow<Self::Break> {
+        self.query_depth -= 1;
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        if self.query_depth > 0 {
+            return ControlFlow::Continue(());
+        }
+
+        match expr {
+            Expr::Identifier(ident) => {
+                self.column_refs.push((None, ident.value.clone()));
+            }
+            Expr::CompoundIdentifier(idents) if idents.len() >= 2 => {
+                let column = idents[idents.len() - 1].value.clone();
+                let qualifier = idents[idents.len() - 2].value.clone();
+                self.column_refs.push((Some(qualifier), column));
+            }
+            Expr::Function(function) => {
+                for qualifier in wildcard_arguments(function) {
+                    self.column_refs.push((qualifier, "*".to_string()));
+                }
+                let name = function
+                    .name
+                    .0
+                    .last()
+                    .map(|i| i.value.to_lowercase())
+                    .unwrap_or_default();
+                if function.over.is_some() {
+                    self.record(TransformationKind::Window);
+                } else if AGGREGATE_FUNCTIONS.contains(&name.as_str()) {
+                    self.record(TransformationKind::Aggregate);
+                } else {
+                    self.record(TransformationKind::Function);
+                }
+            }
+            Expr::Case { .. } => self.record(TransformationKind::Case),
+            Expr::BinaryOp { op, .. } => {
+                if matches!(
+                    op,
+                    BinaryOperator::Plus
+                        | BinaryOperator::Minus
+                        | BinaryOperator::Multiply
+                        | BinaryOperator::Divide
+                        | BinaryOperator::Modulo
+                ) {
+                    self.record(TransformationKind::Arithmetic);
+                }
+            }
+            Expr::UnaryOp {
+                op: UnaryOperator::Minus,
+                ..
+            } => self.record(TransformationKind::Arithmetic),
+            Expr::Cast { .. } => self.record(TransformationKind::Cast),
+            Expr::Subquery(subquery) => {
+                self.record(TransformationKind::Subquery);
+                self.subqueries.push(subquery.as_ref().clone());
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
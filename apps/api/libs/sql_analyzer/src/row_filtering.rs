@@ -39,4 +39,20 @@ pub async fn apply_row_level_filters(
     .map_err(|e| SqlAnalyzerError::Internal(anyhow::anyhow!("Task join error: {}", e)))??;
 
     Ok(result)
-} 
\ No newline at end of file
+} 
+/// Same as `apply_row_level_filters`, but parses the query with the data source's dialect so
+/// warehouse-specific syntax is understood. Queries that can't be parsed are rejected.
+pub async fn apply_row_level_filters_with_dialect(
+    sql: String,
+    table_filters: HashMap<String, String>,
+    data_source_dialect: &str,
+) -> Result<String, SqlAnalyzerError> {
+    let data_source_dialect = data_source_dialect.to_string();
+    let result = tokio::task::spawn_blocking(move || {
+        semantic::apply_row_level_filters_with_dialect(&sql, table_filters, &data_source_dialect)
+    })
+    .await
+    .map_err(|e| SqlAnalyzerError::Internal(anyhow::anyhow!("Task join error: {}", e)))??;
+
+    Ok(result)
+}
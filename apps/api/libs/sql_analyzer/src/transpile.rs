@@ -0,0 +1,613 @@
+use crate::analysis::get_dialect;
+use crate::errors::SqlAnalyzerError;
+use sqlparser::ast::{
+    BinaryOperator, CastKind, DataType, Expr, Fetch, FunctionArg, FunctionArgExpr,
+    FunctionArguments, Ident, ObjectName, OffsetRows, Query, SelectItem, SetExpr, Statement,
+    TableFactor, Top, TopQuantity, Value, VisitMut, VisitorMut,
+};
+use sqlparser::parser::Parser;
+use std::ops::ControlFlow;
+
+/// Rewrites a query written for one warehouse so it runs on another.
+///
+/// Handles the incompatibilities we hit most often when migrating data sources:
+/// `DATE_TRUNC` argument order, `LIMIT` vs `TOP`, identifier quoting, `ILIKE`,
+/// `::` casts, `QUALIFY` and interval literals. Supported dialects are Postgres
+/// (plus Redshift and Supabase), Snowflake, BigQuery, SQL Server, MySQL (plus
+/// MariaDB) and Databricks.
+///
+/// `QUALIFY` is rewritten into a filtered derived table for warehouses that lack it.
+/// When the original query selects `*`, the helper window columns are visible in the output.
+///
+/// # Examples
+/// ```no_run
+/// use sql_analyzer::transpile_query;
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let sql = "SELECT DATE_TRUNC('month', o.created_at) AS month FROM sales.orders o LIMIT 10";
+///     let transpiled = transpile_query(sql.to_string(), "postgres", "bigquery").await?;
+///     println!("BigQuery SQL: {}", transpiled);
+///     Ok(())
+/// }
+/// ```
+pub async fn transpile_query(
+    sql: String,
+    from_dialect: &str,
+    to_dialect: &str,
+) -> Result<String, SqlAnalyzerError> {
+    let source = Warehouse::from_dialect(from_dialect)?;
+    let target = Warehouse::from_dialect(to_dialect)?;
+
+    let mut statements = Parser::parse_sql(get_dialect(source.dialect_name()), &sql)?;
+    if source == target {
+        return Ok(render_statements(&statements));
+    }
+
+    for stmt in statements.iter_mut() {
+        if !matches!(stmt, Statement::Query(_)) {
+            return Err(SqlAnalyzerError::UnsupportedStatement(format!(
+                "Only SELECT queries can be transpiled. Found: {}",
+                stmt
+            )));
+        }
+
+        let mut transpiler = Transpiler { source, target };
+        if let ControlFlow::Break(e) = stmt.visit(&mut transpiler) {
+            return Err(e);
+        }
+    }
+
+    Ok(render_statements(&statements))
+}
+
+fn render_statements(statements: &[Statement]) -> String {
+    statements
+        .iter()
+        .map(|s| s.to_string())
+        .collect::<Vec<_>>()
+        .join(";\n")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Warehouse {
+    Postgres,
+    Snowflake,
+    BigQuery,
+    SqlServer,
+    MySql,
+    Databricks,
+}
+
+impl Warehouse {
+    fn from_dialect(dialect: &str) -> Result<Self, SqlAnalyzerError> {
+        match dialect.to_lowercase().as_str() {
+            "postgres" | "redshift" | "supabase" => Ok(Warehouse::Postgres),
+            "snowflake" => Ok(Warehouse::Snowflake),
+            "bigquery" => Ok(Warehouse::BigQuery),
+            "sqlserver" => Ok(Warehouse::SqlServer),
+            "mysql" | "mariadb" => Ok(Warehouse::MySql),
+            "databricks" => Ok(Warehouse::Databricks),
+            other => Err(SqlAnalyzerError::Transpilation(format!(
+                "Unsupported dialect for transpilation: {}",
+                other
+            ))),
+        }
+    }
+
+    fn dialect_name(&self) -> &'static str {
+        match self {
+            Warehouse::Postgres => "postgres",
+            Warehouse::Snowflake => "snowflake",
+            Warehouse::BigQuery => "bigquery",
+            Warehouse::SqlServer => "sqlserver",
+            Warehouse::MySql => "mysql",
+            Warehouse::Databricks => "databricks",
+        }
+    }
+
+    fn quote_char(&self) -> char {
+        match self {
+            Warehouse::Postgres | Warehouse::Snowflake => '"',
+            Warehouse::BigQuery | Warehouse::MySql | Warehouse::Databricks => '`',
+            Warehouse::SqlServer => '[',
+        }
+    }
+
+    fn supports_ilike(&self) -> bool {
+        matches!(
+            self,
+            Warehouse::Postgres | Warehouse::Snowflake | Warehouse::Databricks
+        )
+    }
+
+    fn supports_double_colon_cast(&self) -> bool {
+        matches!(
+            self,
+            Warehouse::Postgres | Warehouse::Snowflake | Warehouse::Databricks
+        )
+    }
+
+    fn supports_qualify(&self) -> bool {
+        matches!(
+            self,
+            Warehouse::Snowflake | Warehouse::BigQuery | Warehouse::Databricks
+        )
+    }
+
+    // Whether interval literals take the `INTERVAL '7 day'` form rather than `INTERVAL 7 DAY`
+    fn uses_quoted_intervals(&self) -> bool {
+        matches!(self, Warehouse::Postgres | Warehouse::Snowflake)
+    }
+}
+
+const DATE_PARTS: &[&str] = &[
+    "year", "quarter", "month", "week", "day", "hour", "minute", "second",
+];
+
+struct Transpiler {
+    source: Warehouse,
+    target: Warehouse,
+}
+
+impl Transpiler {
+    fn parse_expr(&self, sql: &str) -> Result<Expr, SqlAnalyzerError> {
+        Parser::new(get_dialect(self.target.dialect_name()))
+            .try_with_sql(sql)?
+            .parse_expr()
+            .map_err(|e| {
+                SqlAnalyzerError::Transpilation(format!(
+                    "Failed to build expression '{}': {}",
+                    sql, e
+                ))
+            })
+    }
+
+    fn requote(&self, ident: &mut Ident) {
+        if ident.quote_style.is_some() {
+            ident.quote_style = Some(self.target.quote_char());
+        }
+    }
+
+    fn rewrite_limit(&self, query: &mut Query) {
+        if self.target == Warehouse::SqlServer {
+            let Some(limit) = query.limit.take() else {
+                return;
+            };
+            match (&mut query.offset, query.body.as_mut()) {
+                (None, SetExpr::Select(select)) if select.top.is_none() => {
+                    let quantity = match &limit {
+                        Expr::Value(Value::Number(n, _)) => match n.parse::<u64>() {
+                            Ok(n) => TopQuantity::Constant(n),
+                            Err(_) => TopQuantity::Expr(limit.clone()),
+                        },
+                        _ => TopQuantity::Expr(limit.clone()),
+                    };
+                    select.top = Some(Top {
+                        with_ties: false,
+                        percent: false,
+                        quantity: Some(quantity),
+                    });
+                }
+                (Some(offset), _) => {
+                    // SQL Server pages with OFFSET ... ROWS FETCH NEXT ... ROWS ONLY
+                    offset.rows = OffsetRows::Rows;
+                    query.fetch = Some(Fetch {
+                        with_ties: false,
+                        percent: false,
+                        quantity: Some(limit),
+                    });
+                }
+                _ => query.limit = Some(limit),
+            }
+        } else if query.limit.is_none() {
+            if let SetExpr::Select(select) = query.body.as_mut() {
+                if let Some(top) = select.top.take() {
+                    query.limit = match top.quantity {
+                        Some(TopQuantity::Constant(n)) => {
+                            Some(Expr::Value(Value::Number(n.to_string(), false)))
+                        }
+                        Some(TopQuantity::Expr(expr)) => Some(expr),
+                        None => None,
+                    };
+                }
+            }
+        }
+    }
+
+    fn requote_query_aliases(&self, query: &mut Query) {
+        if let Some(with) = &mut query.with {
+            for cte in with.cte_tables.iter_mut() {
+                self.requote(&mut cte.alias.name);
+            }
+        }
+        let mut selects = Vec::new();
+        collect_selects(query.body.as_mut(), &mut selects);
+        for select in selects {
+            for item in select.projection.iter_mut() {
+                if let SelectItem::ExprWithAlias { alias, .. } = item {
+                    self.requote(alias);
+                }
+            }
+        }
+    }
+
+    // Rewrites `SELECT ... QUALIFY cond` as `SELECT cols FROM (SELECT ..., window AS _qualify_n) WHERE cond`
+    fn rewrite_qualify(&self, query: &mut Query) -> Result<(), SqlAnalyzerError> {
+        let SetExpr::Select(select) = query.body.as_mut() else {
+            return Ok(());
+        };
+        let Some(mut condition) = select.qualify.take() else {
+            return Ok(());
+        };
+
+        let mut output_columns = Vec::new();
+        for item in &select.projection {
+            match item {
+                SelectItem::UnnamedExpr(Expr::Identifier(ident)) => {
+                    output_columns.push(ident.to_string())
+                }
+                SelectItem::UnnamedExpr(Expr::CompoundIdentifier(idents)) => {
+                    if let Some(last) = idents.last() {
+                        output_columns.push(last.to_string());
+                    }
+                }
+                SelectItem::ExprWithAlias { alias, .. } => output_columns.push(alias.to_string()),
+                SelectItem::Wildcard(_) | SelectItem::QualifiedWildcard(..) => {
+                    output_columns.push("*".to_string())
+                }
+                SelectItem::UnnamedExpr(expr) => {
+                    return Err(SqlAnalyzerError::Transpilation(format!(
+                        "QUALIFY can only be rewritten when every selected expression is named. Add an alias to: {}",
+                        expr
+                    )))
+                }
+            }
+        }
+        if output_columns.iter().any(|c| c == "*") {
+            output_columns = vec!["*".to_string()];
+        }
+
+        // Move each window function in the condition into the inner projection
+        let mut window_columns = Vec::new();
+        let _ = sqlparser::ast::visit_expressions_mut(&mut condition, |expr| {
+            if let Expr::Function(function) = expr {
+                if function.over.is_some() {
+                    let helper = Ident::new(format!("_qualify_{}", window_columns.len() + 1));
+                    let window = std::mem::replace(expr, Expr::Identifier(helper.clone()));
+                    window_columns.push(SelectItem::ExprWithAlias {
+                        expr: window,
+                        alias: helper,
+                    });
+                }
+            }
+            ControlFlow::<()>::Continue(())
+        });
+        strip_qualifiers(&mut condition, &output_columns);
+        select.projection.extend(window_columns);
+
+        let template = format!(
+            "SELECT {} FROM (SELECT 1) AS _qualified WHERE {}",
+            output_columns.join(", "),
+            condition
+        );
+        let mut outer = Parser::parse_sql(get_dialect(self.target.dialect_name()), &template)?;
+        let Some(Statement::Query(mut outer_query)) = outer.pop() else {
+            return Err(SqlAnalyzerError::Transpilation(
+                "Failed to build QUALIFY rewrite".to_string(),
+            ));
+        };
+
+        let inner_query = Query {
+            with: None,
+            body: Box::new(std::mem::replace(
+                query.body.as_mut(),
+                SetExpr::Values(sqlparser::ast::Values {
+                    explicit_row: false,
+                    rows: vec![],
+                }),
+            )),
+            order_by: None,
+            limit: None,
+            limit_by: vec![],
+            offset: None,
+            fetch: None,
+            locks: vec![],
+            for_clause: None,
+            settings: None,
+            format_clause: None,
+        };
+
+        if let SetExpr::Select(outer_select) = outer_query.body.as_mut() {
+            if let Some(TableFactor::Derived { subquery, .. }) =
+                outer_select.from.first_mut().map(|f| &mut f.relation)
+            {
+                **subquery = inner_query;
+            }
+        }
+        query.body = outer_query.body;
+
+        // ORDER BY now applies to the outer select, which only knows the output column names
+        if let Some(order_by) = &mut query.order_by {
+            for order_expr in order_by.exprs.iter_mut() {
+                strip_qualifiers(&mut order_expr.expr, &output_columns);
+            }
+        }
+        Ok(())
+    }
+
+    fn rewrite_expr(&self, expr: &mut Expr) -> Result<(), SqlAnalyzerError> {
+        match expr {
+            Expr::Identifier(ident) => self.requote(ident),
+            Expr::CompoundIdentifier(idents) => {
+                for ident in idents.iter_mut() {
+                    self.requote(ident);
+                }
+            }
+            Expr::Function(function) => {
+                let name = function.name.to_string().to_lowercase();
+                if name == "date_trunc" || name == "datetrunc" {
+                    if let Some(rewritten) = self.rewrite_date_trunc(&function.args)? {
+                        *expr = rewritten;
+                    }
+                }
+            }
+            Expr::ILike {
+                negated,
+                expr: inner,
+                pattern,
+                escape_char,
+                ..
+            } if !self.target.supports_ilike() => {
+                let escape = escape_char
+                    .as_ref()
+                    .map(|c| format!(" ESCAPE '{}'", c))
+                    .unwrap_or_default();
+                *expr = self.parse_expr(&format!(
+                    "LOWER({}) {}LIKE LOWER({}){}",
+                    inner,
+                    if *negated { "NOT " } else { "" },
+                    pattern,
+                    escape
+                ))?;
+            }
+            Expr::Cast {
+                kind, data_type, ..
+            } => {
+                if *kind == CastKind::DoubleColon && !self.target.supports_double_colon_cast() {
+                    *kind = CastKind::Cast;
+                }
+                if let Some(mapped) = self.map_data_type(data_type) {
+                    *data_type = mapped;
+                }
+            }
+            Expr::Interval(_) if self.target != Warehouse::SqlServer => {
+                if let Some((amount, unit)) = interval_parts(expr) {
+                    *expr = if self.target.uses_quoted_intervals() {
+                        self.parse_expr(&format!("INTERVAL '{} {}'", amount, unit))?
+                    } else {
+                        self.parse_expr(&format!("INTERVAL {} {}", amount, unit.to_uppercase()))?
+                    };
+                }
+            }
+            Expr::BinaryOp { left, op, right } if self.target == Warehouse::SqlServer => {
+                // SQL Server has no interval type; date arithmetic goes through DATEADD
+                let sign = match op {
+                    BinaryOperator::Plus => "",
+                    BinaryOperator::Minus => "-",
+                    _ => return Ok(()),
+                };
+                if let Some((amount, unit)) = interval_parts(right) {
+                    *expr = self.parse_expr(&format!(
+                        "DATEADD({}, {}{}, {})",
+                        unit, sign, amount, left
+                    ))?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn rewrite_date_trunc(&self, args: &FunctionArguments) -> Result<Option<Expr>, SqlAnalyzerError> {
+        let FunctionArguments::List(list) = args else {
+            return Ok(None);
+        };
+        let exprs: Vec<&Expr> = list
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(e)) => Some(e),
+                _ => None,
+            })
+            .collect();
+        let [first, second] = exprs.as_slice() else {
+            return Ok(None);
+        };
+
+        // BigQuery puts the date part last; everyone else puts it first
+        let (unit, value) = match self.source {
+            Warehouse::BigQuery => match date_part(second) {
+                Some(unit) => (unit, *first),
+                None => return Ok(None),
+            },
+            _ => match date_part(first) {
+                Some(unit) => (unit, *second),
+                None => return Ok(None),
+            },
+        };
+
+        let sql = match self.target {
+            Warehouse::Postgres | Warehouse::Snowflake | Warehouse::Databricks => {
+                format!("DATE_TRUNC('{}', {})", unit, value)
+            }
+            Warehouse::BigQuery => format!("DATE_TRUNC({}, {})", value, unit.to_uppercase()),
+            Warehouse::SqlServer => format!("DATETRUNC({}, {})", unit, value),
+            Warehouse::MySql => match unit.as_str() {
+                "year" => format!("DATE_FORMAT({}, '%Y-01-01')", value),
+                "quarter" => format!(
+                    "MAKEDATE(YEAR({v}), 1) + INTERVAL (QUARTER({v}) - 1) QUARTER",
+                    v = value
+                ),
+                "month" => format!("DATE_FORMAT({}, '%Y-%m-01')", value),
+                "week" => format!("DATE_SUB(DATE({v}), INTERVAL WEEKDAY({v}) DAY)", v = value),
+                "day" => format!("DATE({})", value),
+                "hour" => format!("DATE_FORMAT({}, '%Y-%m-%d %H:00:00')", value),
+                "minute" => format!("DATE_FORMAT({}, '%Y-%m-%d %H:%i:00')", value),
+                _ => format!("DATE_FORMAT({}, '%Y-%m-%d %H:%i:%s')", value),
+            },
+        };
+        self.parse_expr(&sql).map(Some)
+    }
+
+    fn map_data_type(&self, data_type: &DataType) -> Option<DataType> {
+        let name = data_type.to_string().to_uppercase();
+        let base = name.split('(').next().unwrap_or_default().trim().to_string();
+        let mapped = match self.target {
+            Warehouse::BigQuery => match base.as_str() {
+                "INT" | "INTEGER" | "BIGINT" | "SMALLINT" => "INT64",
+                "TEXT" | "VARCHAR" | "CHAR" | "CHARACTER VARYING" => "STRING",
+                "FLOAT" | "DOUBLE" | "DOUBLE PRECISION" | "REAL" => "FLOAT64",
+                "DECIMAL" => "NUMERIC",
+                "BOOLEAN" => "BOOL",
+                _ => return None,
+            },
+            Warehouse::MySql => match base.as_str() {
+                "INT" | "INTEGER" | "BIGINT" | "SMALLINT" | "INT64" => "SIGNED",
+                "TEXT" | "VARCHAR" | "STRING" | "CHARACTER VARYING" => "CHAR",
+                "FLOAT64" | "DOUBLE PRECISION" => "DOUBLE",
+                "NUMERIC" => "DECIMAL",
+                _ => return None,
+            },
+            Warehouse::SqlServer => match base.as_str() {
+                "INT64" | "INTEGER" => "INT",
+                "TEXT" | "STRING" => "NVARCHAR(MAX)",
+                "FLOAT64" | "DOUBLE" | "DOUBLE PRECISION" => "FLOAT",
+                "BOOLEAN" | "BOOL" => "BIT",
+                "TIMESTAMP" => "DATETIME2",
+                _ => return None,
+            },
+            Warehouse::Postgres | Warehouse::Snowflake | Warehouse::Databricks => {
+                match base.as_str() {
+                    "INT64" => "BIGINT",
+                    "FLOAT64" => "DOUBLE PRECISION",
+                    "STRING" if self.target == Warehouse::Postgres => "TEXT",
+                    "BOOL" => "BOOLEAN",
+                    "NVARCHAR" | "NVARCHAR(MAX)" => "VARCHAR",
+                    "DATETIME2" => "TIMESTAMP",
+                    _ => return None,
+                }
+            }
+        };
+        Some(DataType::Custom(
+            ObjectName(vec![Ident::new(mapped)]),
+            vec![],
+        ))
+    }
+}
+
+impl VisitorMut for Transpiler {
+    type Break = SqlAnalyzerError;
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<Self::Break> {
+        self.rewrite_limit(query);
+        self.requote_query_aliases(query);
+        if !self.target.supports_qualify() {
+            if let Err(e) = self.rewrite_qualify(query) {
+                return ControlFlow::Break(e);
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_relation(&mut self, relation: &mut ObjectName) -> ControlFlow<Self::Break> {
+        for ident in relation.0.iter_mut() {
+            self.requote(ident);
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, table_factor: &mut TableFactor) -> ControlFlow<Self::Break> {
+        if let TableFactor::Table {
+            alias: Some(alias), ..
+        }
+        | TableFactor::Derived {
+            alias: Some(alias), ..
+        } = table_factor
+        {
+            self.requote(&mut alias.name);
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn post_visit_expr(&mut self, expr: &mut Expr) -> ControlFlow<Self::Break> {
+        match self.rewrite_expr(expr) {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(e) => ControlFlow::Break(e),
+        }
+    }
+}
+
+fn collect_selects<'a>(body: &'a mut SetExpr, selects: &mut Vec<&'a mut sqlparser::ast::Select>) {
+    match body {
+        SetExpr::Select(select) => selects.push(select.as_mut()),
+        SetExpr::SetOperation { left, right, .. } => {
+            collect_selects(left, selects);
+            collect_selects(right, selects);
+        }
+        _ => {}
+    }
+}
+
+// Drops table qualifiers (`t.col` -> `col`) for columns exposed by name from a derived table
+fn strip_qualifiers(expr: &mut Expr, output_columns: &[String]) {
+    let _ = sqlparser::ast::visit_expressions_mut(expr, |e| {
+        if let Expr::CompoundIdentifier(idents) = e {
+            if let Some(last) = idents.last() {
+                let name = last.to_string();
+                if output_columns.iter().any(|c| c == &name || c == "*") {
+                    *e = Expr::Identifier(last.clone());
+                }
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+}
+
+fn date_part(expr: &Expr) -> Option<String> {
+    let part = match expr {
+        Expr::Value(Value::SingleQuotedString(s)) => s.to_lowercase(),
+        Expr::Identifier(ident) => ident.value.to_lowercase(),
+        _ => return None,
+    };
+    DATE_PARTS.contains(&part.as_str()).then_some(part)
+}
+
+// Extracts (amount, unit) from `INTERVAL '7 days'`, `INTERVAL '7' DAY` and `INTERVAL 7 DAY`
+fn interval_parts(expr: &Expr) -> Option<(String, String)> {
+    let Expr::Interval(interval) = expr else {
+        return None;
+    };
+    let raw_value = match interval.value.as_ref() {
+        Expr::Value(Value::SingleQuotedString(s)) => s.trim().to_string(),
+        Expr::Value(Value::Number(n, _)) => n.clone(),
+        _ => return None,
+    };
+
+    let (amount, unit) = match &interval.leading_field {
+        Some(field) if interval.last_field.is_none() => (raw_value, field.to_string()),
+        None => {
+            let mut parts = raw_value.split_whitespace();
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(amount), Some(unit), None) => (amount.to_string(), unit.to_string()),
+                _ => return None,
+            }
+        }
+        _ => return None,
+    };
+
+    amount.parse::<f64>().ok()?;
+    let unit = unit.to_lowercase();
+    let unit = unit.strip_suffix('s').unwrap_or(&unit).to_string();
+    DATE_PARTS.contains(&unit.as_str()).then_some((amount, unit))
+}
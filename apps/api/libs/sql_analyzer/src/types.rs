@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::hash::Hash;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -18,6 +18,8 @@ pub struct QuerySummary {
     pub joins: HashSet<JoinInfo>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub ctes: Vec<CteSummary>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub column_lineage: Vec<ColumnLineage>, // One entry per projected column of the final SELECT
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -66,6 +68,115 @@ pub struct CteSummary {
     pub column_mappings: HashMap<String, (String, String)>, // Optional: Map CTE output column to source
 }
 
+/// Lineage of a single projected output column, traced back to base table columns
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ColumnLineage {
+    pub column: String,
+    pub sources: Vec<SourceColumn>,
+    pub steps: Vec<LineageStep>, // Ordered from the innermost CTE/derived table out to the final SELECT
+}
+
+impl ColumnLineage {
+    /// Returns true if any of the steps applied the given transformation
+    pub fn has_transformation(&self, kind: &TransformationKind) -> bool {
+        self.steps.iter().any(|s| s.transformations.contains(kind))
+    }
+
+    /// Returns true if the column is derived from the given base table column
+    pub fn depends_on(&self, table: &str, column: &str) -> bool {
+        self.sources.iter().any(|s| {
+            s.table_identifier.eq_ignore_ascii_case(table) && s.column.eq_ignore_ascii_case(column)
+        })
+    }
+}
+
+/// A base table column that feeds into a projected column
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SourceColumn {
+    pub database_identifier: Option<String>,
+    pub schema_identifier: Option<String>,
+    pub table_identifier: String,
+    pub column: String,
+}
+
+impl SourceColumn {
+    /// Returns the dotted `database.schema.table.column` name, omitting missing qualifiers
+    pub fn qualified_name(&self) -> String {
+        [
+            self.database_identifier.as_deref(),
+            self.schema_identifier.as_deref(),
+            Some(self.table_identifier.as_str()),
+            Some(self.column.as_str()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(".")
+    }
+}
+
+/// A single hop in a column's lineage: the expression that produced the column in one scope
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct LineageStep {
+    pub scope: String, // "CTE:<name>", "derived:<alias>" or "query" for the final SELECT
+    pub column: String,
+    pub expression: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub transformations: Vec<TransformationKind>, // Empty when the column is passed through unchanged
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub enum TransformationKind {
+    Aggregate,
+    Window,
+    Case,
+    Arithmetic,
+    Cast,
+    Function,
+    SetOperation,
+    Subquery,
+}
+
+/// Static cost signals for a query, computed before it is sent to a warehouse
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct QueryComplexityReport {
+    pub join_count: usize,
+    pub cross_join_count: usize,
+    pub missing_join_predicates: Vec<String>, // Joins that produce a cartesian product
+    pub unbounded_scans: Vec<String>,         // Tables read by a SELECT without a WHERE clause
+    pub max_subquery_depth: usize,
+    pub window_function_count: usize,
+    pub score: u32,
+}
+
+impl QueryComplexityReport {
+    /// Weighted score; cartesian products dominate because they are what runs for minutes
+    pub fn compute_score(&self) -> u32 {
+        (self.join_count
+            + self.cross_join_count * 10
+            + self.missing_join_predicates.len() * 10
+            + self.unbounded_scans.len() * 2
+            + self.max_subquery_depth * 2
+            + self.window_function_count) as u32
+    }
+
+    /// Returns true if the query contains a join that multiplies rows without a predicate
+    pub fn has_cartesian_product(&self) -> bool {
+        self.cross_join_count > 0 || !self.missing_join_predicates.is_empty()
+    }
+}
+
+/// How a protected column is shown in query results. Variants are ordered from least to most
+/// restrictive, so the `max` of several applicable masks is the one to enforce.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ColumnMask {
+    Partial, // Only the last four characters are visible
+    Hash,    // Replaced by a hex digest, so values can still be grouped and joined on
+    Null,    // Always returned as NULL
+    Deny,    // Queries that reference the column are rejected
+}
+
 /// A parameter definition for parameterized metrics and filters
 #[derive(Serialize, Debug, Clone)]
 pub struct Parameter {
@@ -114,6 +225,74 @@ pub struct Relationship {
     pub from_column: String,
     pub to_table: String,
     pub to_column: String,
+    /// How many `to_table` rows match each `from_table` row, if known
+    pub cardinality: Option<Cardinality>,
+}
+
+/// Cardinality of a relationship, read from its `from_table` to its `to_table`
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cardinality {
+    #[serde(rename = "one-to-one")]
+    OneToOne,
+    #[serde(rename = "one-to-many")]
+    OneToMany,
+    #[serde(rename = "many-to-one")]
+    ManyToOne,
+    #[serde(rename = "many-to-many")]
+    ManyToMany,
+}
+
+impl Cardinality {
+    /// The same relationship read from the other side
+    pub fn reversed(self) -> Self {
+        match self {
+            Cardinality::OneToMany => Cardinality::ManyToOne,
+            Cardinality::ManyToOne => Cardinality::OneToMany,
+            other => other,
+        }
+    }
+}
+
+/// A single join in a join path, oriented in the direction it is walked
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct JoinStep {
+    pub from_table: String,
+    pub from_column: String,
+    pub to_table: String,
+    pub to_column: String,
+    pub cardinality: Option<Cardinality>,
+}
+
+impl JoinStep {
+    /// Returns true if a `from_table` row can match several `to_table` rows
+    pub fn fans_out(&self) -> bool {
+        matches!(
+            self.cardinality,
+            Some(Cardinality::OneToMany) | Some(Cardinality::ManyToMany)
+        )
+    }
+}
+
+/// The joins needed to reach one table from another through defined relationships
+#[derive(Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct JoinPath {
+    pub steps: Vec<JoinStep>,
+}
+
+impl JoinPath {
+    /// Returns true if following the path duplicates rows of the starting table,
+    /// which double counts any aggregate computed over it
+    pub fn fans_out(&self) -> bool {
+        self.steps.iter().any(|step| step.fans_out())
+    }
+
+    /// Tables the path passes through between its start and end
+    pub fn intermediate_tables(&self) -> Vec<&str> {
+        match self.steps.split_last() {
+            Some((_, rest)) => rest.iter().map(|step| step.to_table.as_str()).collect(),
+            None => Vec::new(),
+        }
+    }
 }
 
 /// Validation modes for semantic layer queries
@@ -176,6 +355,72 @@ impl SemanticLayer {
         })
     }
 
+    /// Finds the shortest join path between two tables, following relationships
+    /// in either direction
+    pub fn find_join_path(&self, from_table: &str, to_table: &str) -> Option<JoinPath> {
+        self.find_join_path_from_any(&[from_table], to_table)
+    }
+
+    /// Finds the shortest join path to `to_table` starting from whichever of
+    /// `from_tables` is closest, e.g. any table already joined in a query
+    pub fn find_join_path_from_any(&self, from_tables: &[&str], to_table: &str) -> Option<JoinPath> {
+        let mut previous: HashMap<&str, Option<JoinStep>> = HashMap::new();
+        let mut queue = VecDeque::new();
+        for &table in from_tables {
+            if previous.insert(table, None).is_none() {
+                queue.push_back(table);
+            }
+        }
+
+        while let Some(table) = queue.pop_front() {
+            if table == to_table {
+                let mut steps = Vec::new();
+                let mut current = table;
+                while let Some(Some(step)) = previous.get(current) {
+                    current = step.from_table.as_str();
+                    steps.push(step.clone());
+                }
+                steps.reverse();
+                return Some(JoinPath { steps });
+            }
+
+            for relationship in &self.relationships {
+                let (next, step) = if relationship.from_table == table {
+                    (
+                        relationship.to_table.as_str(),
+                        JoinStep {
+                            from_table: relationship.from_table.clone(),
+                            from_column: relationship.from_column.clone(),
+                            to_table: relationship.to_table.clone(),
+                            to_column: relationship.to_column.clone(),
+                            cardinality: relationship.cardinality,
+                        },
+                    )
+                } else if relationship.to_table == table {
+                    (
+                        relationship.from_table.as_str(),
+                        JoinStep {
+                            from_table: relationship.to_table.clone(),
+                            from_column: relationship.to_column.clone(),
+                            to_table: relationship.from_table.clone(),
+                            to_column: relationship.from_column.clone(),
+                            cardinality: relationship.cardinality.map(Cardinality::reversed),
+                        },
+                    )
+                } else {
+                    continue;
+                };
+
+                if !previous.contains_key(next) {
+                    previous.insert(next, Some(step));
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        None
+    }
+
     /// Gets the metric by its name
     pub fn get_metric(&self, name: &str) -> Option<&Metric> {
         self.metrics.get(name)
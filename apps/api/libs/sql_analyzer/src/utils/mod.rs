@@ -364,6 +364,7 @@ impl QueryAnalyzer {
             tables: self.tables.into_values().collect(),
             joins: self.joins,
             ctes: self.ctes,
+            column_lineage: Vec::new(),
         })
     }
 
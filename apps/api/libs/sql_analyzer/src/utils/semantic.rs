@@ -1,14 +1,16 @@
 use crate::errors::SqlAnalyzerError;
 use crate::types::{
-    SemanticLayer, ValidationMode, Metric, Filter, Parameter, ParameterType
+    SemanticLayer, ValidationMode, Metric, Filter, Parameter, ParameterType, JoinPath
 };
 use sqlparser::ast::{
     Expr, SelectItem, SetExpr, Statement, TableFactor, 
-    Query, Visit, Visitor, Function, FunctionArg,
+    Query, Visit, Visitor, VisitMut, VisitorMut, Function, FunctionArg,
     FunctionArgExpr, FunctionArguments, ObjectName,
-    OrderByExpr
+    OrderByExpr, Join, JoinConstraint, JoinOperator, TableWithJoins,
+    visit_expressions
 };
 use sqlparser::dialect::GenericDialect;
+use crate::analysis::get_dialect;
 use sqlparser::parser::Parser;
 use std::collections::{HashMap, HashSet};
 use std::ops::ControlFlow;
@@ -26,6 +28,7 @@ pub struct ValidationVisitor<'a> {
     tables: HashSet<String>,
     calculated_expressions: Vec<String>,
     joins: Vec<(String, String)>,
+    metrics_used: Vec<String>,
 }
 
 impl<'a> ValidationVisitor<'a> {
@@ -37,6 +40,7 @@ impl<'a> ValidationVisitor<'a> {
             tables: HashSet::new(),
             calculated_expressions: Vec::new(),
             joins: Vec::new(),
+            metrics_used: Vec::new(),
         }
     }
 
@@ -65,16 +69,73 @@ impl<'a> ValidationVisitor<'a> {
         }
     }
 
-    fn validate_join(&mut self, left_table: &str, right_table: &str) {
-        // Only validate joins if we know about both tables
-        if self.tables.contains(left_table) && self.tables.contains(right_table) {
-            if !self.semantic_layer.are_tables_related(left_table, right_table) {
+    fn validate_join(&mut self, joined_tables: &[String], right_table: &str, join_operator: &JoinOperator) {
+        // Only validate joins if we know about the tables involved
+        let known: Vec<&str> = joined_tables
+            .iter()
+            .map(|t| t.as_str())
+            .filter(|t| self.tables.contains(*t))
+            .collect();
+        if known.is_empty() || !self.tables.contains(right_table) {
+            return;
+        }
+
+        match resolve_join(self.semantic_layer, &known, right_table, join_operator) {
+            JoinResolution::Direct(left_table) => {
+                self.joins.push((left_table, right_table.to_string()));
+            }
+            JoinResolution::Indirect(path) => {
+                // The intermediate tables get joined in during substitution
+                for step in &path.steps {
+                    self.tables.insert(step.to_table.clone());
+                    self.joins.push((step.from_table.clone(), step.to_table.clone()));
+                }
+            }
+            JoinResolution::Invalid(Some(path)) => {
+                let left_table = path.steps.first().map(|s| s.from_table.as_str()).unwrap_or(known[0]);
                 self.add_error(format!(
-                    "Invalid join between '{}' and '{}' - no direct relationship defined in semantic layer",
-                    left_table, right_table
+                    "Invalid join between '{}' and '{}' - no direct relationship defined in semantic layer, join through {} first",
+                    left_table,
+                    right_table,
+                    path.intermediate_tables().join(", ")
+                ));
+            }
+            JoinResolution::Invalid(None) => {
+                self.add_error(format!(
+                    "Invalid join between '{}' and '{}' - no relationship path defined in semantic layer",
+                    known[0], right_table
                 ));
-            } else {
-                self.joins.push((left_table.to_string(), right_table.to_string()));
+            }
+        }
+    }
+
+    /// Flags joins that duplicate the rows a metric aggregates over
+    fn validate_fan_out(&mut self) {
+        let mut metric_names = self.metrics_used.clone();
+        metric_names.sort();
+        metric_names.dedup();
+
+        let mut tables: Vec<String> = self.tables.iter().cloned().collect();
+        tables.sort();
+
+        for name in metric_names {
+            let Some(metric) = self.semantic_layer.get_metric(&name) else {
+                continue;
+            };
+            for table in &tables {
+                if table == &metric.table {
+                    continue;
+                }
+                let fans_out = self
+                    .semantic_layer
+                    .find_join_path(&metric.table, table)
+                    .is_some_and(|path| path.fans_out());
+                if fans_out {
+                    self.add_error(format!(
+                        "Joining '{}' duplicates rows of '{}' and would double count metric '{}'",
+                        table, metric.table, name
+                    ));
+                }
             }
         }
     }
@@ -123,6 +184,8 @@ impl<'a> ValidationVisitor<'a> {
             if !self.semantic_layer.has_metric(name) {
                 self.add_error(format!("Unknown metric '{}'", name));
             } else {
+                self.metrics_used.push(name.to_string());
+
                 // Validate that a metric has the required tables in the query
                 let metric = self.semantic_layer.get_metric(name).unwrap();
                 let metric_table = &metric.table;
@@ -182,16 +245,17 @@ impl<'a> Visitor for ValidationVisitor<'a> {
                     _ => "".to_string() // Not a simple table
                 };
 
-                // Process joins
+                // Process joins, each of which must connect to a table joined before it
+                let mut joined_tables = vec![base_table_name.clone()];
                 for join in &table_with_joins.joins {
                     if let TableFactor::Table { name, .. } = &join.relation {
                         let join_table_name = name.0.last().unwrap().to_string();
                         self.validate_table(&join_table_name);
 
-                        // Validate join relationship if it's a direct join
                         if !base_table_name.is_empty() {
-                            self.validate_join(&base_table_name, &join_table_name);
+                            self.validate_join(&joined_tables, &join_table_name, &join.join_operator);
                         }
+                        joined_tables.push(join_table_name);
                     }
                 }
             }
@@ -1466,6 +1530,253 @@ impl SemanticObject for Filter {
     }
 }
 
+///////////////////////////////////////////////////////////////////////////////
+// JOIN PATHS
+///////////////////////////////////////////////////////////////////////////////
+
+/// How a join connects to the tables joined before it
+enum JoinResolution {
+    /// Directly related to the named table
+    Direct(String),
+    /// Reachable through intermediate tables that the join relies on
+    Indirect(JoinPath),
+    /// Not connected as written, with the path it could take if there is one
+    Invalid(Option<JoinPath>),
+}
+
+/// The condition a join was written with
+enum JoinCondition<'a> {
+    Missing,
+    On(&'a Expr),
+    Other,
+}
+
+fn join_condition(join_operator: &JoinOperator) -> JoinCondition<'_> {
+    match join_operator {
+        JoinOperator::Inner(constraint)
+        | JoinOperator::LeftOuter(constraint)
+        | JoinOperator::RightOuter(constraint)
+        | JoinOperator::FullOuter(constraint) => match constraint {
+            JoinConstraint::On(expr) => JoinCondition::On(expr),
+            JoinConstraint::None => JoinCondition::Missing,
+            _ => JoinCondition::Other,
+        },
+        _ => JoinCondition::Other,
+    }
+}
+
+/// Table names or aliases that qualify columns in an expression
+fn expression_qualifiers(expr: &Expr) -> HashSet<String> {
+    let mut qualifiers = HashSet::new();
+    let _ = visit_expressions(expr, |e| {
+        if let Expr::CompoundIdentifier(idents) = e {
+            if idents.len() >= 2 {
+                qualifiers.insert(idents[idents.len() - 2].value.clone());
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    qualifiers
+}
+
+/// Resolves a join of `table` against the tables already joined. A table with no
+/// direct relationship is accepted when its join either has no condition or
+/// refers to one of the intermediate tables on the shortest path to it, as in
+/// `FROM users JOIN order_items ON orders.id = order_items.order_id`.
+fn resolve_join(
+    semantic_layer: &SemanticLayer,
+    joined_tables: &[&str],
+    table: &str,
+    join_operator: &JoinOperator,
+) -> JoinResolution {
+    if let Some(left_table) = joined_tables
+        .iter()
+        .find(|joined| semantic_layer.are_tables_related(joined, table))
+    {
+        return JoinResolution::Direct(left_table.to_string());
+    }
+
+    let path = match semantic_layer.find_join_path_from_any(joined_tables, table) {
+        Some(path) if path.steps.len() > 1 => path,
+        _ => return JoinResolution::Invalid(None),
+    };
+
+    let relies_on_path = match join_condition(join_operator) {
+        JoinCondition::Missing => true,
+        JoinCondition::On(expr) => {
+            let qualifiers = expression_qualifiers(expr);
+            path.intermediate_tables()
+                .iter()
+                .any(|intermediate| qualifiers.contains(*intermediate))
+        }
+        JoinCondition::Other => false,
+    };
+
+    if relies_on_path {
+        JoinResolution::Indirect(path)
+    } else {
+        JoinResolution::Invalid(Some(path))
+    }
+}
+
+/// Inserts the intermediate tables of indirect joins so the query runs as written
+struct JoinPathCompleter<'a> {
+    semantic_layer: &'a SemanticLayer,
+    changed: bool,
+}
+
+impl JoinPathCompleter<'_> {
+    fn complete_joins(&mut self, table_with_joins: &mut TableWithJoins) {
+        let TableFactor::Table { name, alias, .. } = &table_with_joins.relation else {
+            return;
+        };
+        let base_table = name.0.last().unwrap().to_string();
+        let base_reference = alias.as_ref().map(|a| a.name.value.clone()).unwrap_or(base_table.clone());
+
+        // (table name, name it is referenced by in the query)
+        let mut joined: Vec<(String, String)> = vec![(base_table, base_reference)];
+        let mut joins = Vec::with_capacity(table_with_joins.joins.len());
+
+        for mut join in std::mem::take(&mut table_with_joins.joins) {
+            let TableFactor::Table { name, alias, .. } = &join.relation else {
+                joins.push(join);
+                continue;
+            };
+            let table = name.0.last().unwrap().to_string();
+            let reference = alias.as_ref().map(|a| a.name.value.clone()).unwrap_or(table.clone());
+            let qualifier: String = name.0[..name.0.len() - 1]
+                .iter()
+                .map(|part| format!("{}.", part))
+                .collect();
+
+            let known: Vec<&str> = joined
+                .iter()
+                .map(|(t, _)| t.as_str())
+                .filter(|t| self.semantic_layer.has_table(t))
+                .collect();
+            if !known.is_empty() && self.semantic_layer.has_table(&table) {
+                if let JoinResolution::Indirect(path) =
+                    resolve_join(self.semantic_layer, &known, &table, &join.join_operator)
+                {
+                    let keyword = match join.join_operator {
+                        JoinOperator::LeftOuter(_) => "LEFT JOIN",
+                        JoinOperator::RightOuter(_) => "RIGHT JOIN",
+                        JoinOperator::FullOuter(_) => "FULL JOIN",
+                        _ => "JOIN",
+                    };
+                    let (last, intermediate) = path.steps.split_last().unwrap();
+
+                    // Intermediate tables are referenced by their bare names
+                    let intermediate_joins: Option<Vec<Join>> = intermediate
+                        .iter()
+                        .map(|step| {
+                            let from = reference_for(&joined, &step.from_table);
+                            parse_first_join(&format!(
+                                "SELECT 1 FROM {from} {keyword} {qualifier}{to} ON {from}.{} = {to}.{}",
+                                step.from_column,
+                                step.to_column,
+                                to = step.to_table
+                            ))
+                        })
+                        .collect();
+                    let Some(intermediate_joins) = intermediate_joins else {
+                        joined.push((table, reference));
+                        joins.push(join);
+                        continue;
+                    };
+                    joins.extend(intermediate_joins);
+                    for step in intermediate {
+                        joined.push((step.to_table.clone(), step.to_table.clone()));
+                    }
+
+                    if let JoinCondition::Missing = join_condition(&join.join_operator) {
+                        let condition = format!(
+                            "{}.{} = {}.{}",
+                            reference_for(&joined, &last.from_table),
+                            last.from_column,
+                            reference,
+                            last.to_column
+                        );
+                        if let Ok(expr) = Parser::new(&GenericDialect {})
+                            .try_with_sql(&condition)
+                            .and_then(|mut parser| parser.parse_expr())
+                        {
+                            if let JoinOperator::Inner(constraint)
+                            | JoinOperator::LeftOuter(constraint)
+                            | JoinOperator::RightOuter(constraint)
+                            | JoinOperator::FullOuter(constraint) = &mut join.join_operator
+                            {
+                                *constraint = JoinConstraint::On(expr);
+                            }
+                        }
+                    }
+                    self.changed = true;
+                }
+            }
+
+            joined.push((table, reference));
+            joins.push(join);
+        }
+
+        table_with_joins.joins = joins;
+    }
+}
+
+impl VisitorMut for JoinPathCompleter<'_> {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<Self::Break> {
+        if let SetExpr::Select(select) = query.body.as_mut() {
+            for table_with_joins in &mut select.from {
+                self.complete_joins(table_with_joins);
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// The name a joined table is referenced by, its alias if it has one
+fn reference_for(joined: &[(String, String)], table: &str) -> String {
+    joined
+        .iter()
+        .find(|(t, _)| t == table)
+        .map(|(_, reference)| reference.clone())
+        .unwrap_or_else(|| table.to_string())
+}
+
+fn parse_first_join(sql: &str) -> Option<Join> {
+    let mut ast = Parser::parse_sql(&GenericDialect {}, sql).ok()?;
+    let Statement::Query(query) = ast.pop()? else {
+        return None;
+    };
+    let SetExpr::Select(mut select) = *query.body else {
+        return None;
+    };
+    select.from.pop()?.joins.pop()
+}
+
+/// Joins in the intermediate tables of indirect joins, leaving the query
+/// untouched when every join is direct or it cannot be parsed
+fn complete_join_paths(sql: &str, semantic_layer: &SemanticLayer) -> String {
+    let Ok(mut ast) = Parser::parse_sql(&GenericDialect {}, sql) else {
+        return sql.to_string();
+    };
+
+    let mut completer = JoinPathCompleter {
+        semantic_layer,
+        changed: false,
+    };
+    for stmt in &mut ast {
+        let _ = VisitMut::visit(stmt, &mut completer);
+    }
+
+    if completer.changed {
+        ast.iter().map(|stmt| stmt.to_string()).collect::<Vec<_>>().join(";\n")
+    } else {
+        sql.to_string()
+    }
+}
+
 ///////////////////////////////////////////////////////////////////////////////
 // PUBLIC API FUNCTIONS
 ///////////////////////////////////////////////////////////////////////////////
@@ -1484,6 +1795,7 @@ pub fn validate_query(
     for stmt in &ast {
         stmt.visit(&mut validator);
     }
+    validator.validate_fan_out();
     
     let errors = validator.get_errors();
     if errors.is_empty() {
@@ -1998,6 +2310,9 @@ pub fn substitute_query(
         result = ast.iter().map(|stmt| stmt.to_string()).collect::<Vec<_>>().join(";\n");
     }
     
+    // Join in the intermediate tables of any indirect joins
+    result = complete_join_paths(&result, semantic_layer);
+    
     Ok(result)
 }
 
@@ -2229,473 +2544,393 @@ pub fn validate_and_substitute(
 // ROW LEVEL FILTERING FUNCTIONS
 ///////////////////////////////////////////////////////////////////////////////
 
-/// Applies row-level filters to a SQL query by replacing table references with filtered CTEs
+
+/// Applies row-level filters to a SQL query by replacing table references with filtered CTEs.
+///
+/// See `apply_row_level_filters_with_dialect`; the query is parsed with the generic dialect.
 pub fn apply_row_level_filters(
-    sql: &str, 
-    table_filters: HashMap<String, String>
+    sql: &str,
+    table_filters: HashMap<String, String>,
+) -> Result<String, SqlAnalyzerError> {
+    apply_row_level_filters_with_dialect(sql, table_filters, "generic")
+}
+
+/// Applies row-level filters to a SQL query by replacing table references with filtered CTEs.
+///
+/// `table_filters` maps a `schema.table` name, or a bare table name that applies in every
+/// schema, to a condition on that table. Names are compared the way the dialect resolves
+/// identifiers, so `"ORDERS"` matches a filter on `orders` on Snowflake but not on Postgres.
+/// Unqualified references match filters on the table in any schema; when several apply, all
+/// of their conditions do. Every reference to a filtered table, including references inside
+/// CTEs and subqueries, is pointed at a `filtered_<alias>` CTE selecting from the table through
+/// its condition. References to
+/// CTEs that shadow a table name are left alone. The rewrite edits the original text, so
+/// formatting and comments outside the rewritten references are preserved.
+///
+/// Queries that can't be parsed are rejected rather than passed through unfiltered.
+pub fn apply_row_level_filters_with_dialect(
+    sql: &str,
+    table_filters: HashMap<String, String>,
+    data_source_dialect: &str,
 ) -> Result<String, SqlAnalyzerError> {
     // If no filters provided, return the original query
     if table_filters.is_empty() {
         return Ok(sql.to_string());
     }
-    
-    // Parse the SQL query to extract table information
-    let table_info = extract_table_references(sql);
-    
-    // If no tables found, return the original query
-    if table_info.is_empty() {
-        return Ok(sql.to_string());
-    }
-    
-    // Use the simplified direct approach for better reliability
-    apply_row_level_filters_directly(sql, &table_info, &table_filters)
-}
 
-// Extract table references from a SQL query string
-fn extract_table_references(sql: &str) -> Vec<(String, String, String)> {
-    // Extract (table_name, table_alias, full_name_with_schema)
-    let mut results = Vec::new();
-    
-    // Try to parse the SQL
-    let dialect = GenericDialect {};
-    let ast = match Parser::parse_sql(&dialect, sql) {
-        Ok(stmts) => stmts,
-        Err(_) => return results,
-    };
-    
-    // Process each statement
-    for stmt in ast {
-        if let Statement::Query(query) = stmt {
-            process_query_for_tables(&query, &mut results);
+    let statements = Parser::parse_sql(get_dialect(data_source_dialect), sql)?;
+    let query = match statements.as_slice() {
+        [Statement::Query(query)] => query,
+        _ => {
+            return Err(SqlAnalyzerError::UnsupportedStatement(
+                "Row-level filters can only be applied to a single SELECT query".to_string(),
+            ))
         }
+    };
+
+    let mut filters: Vec<TableFilter> = table_filters
+        .iter()
+        .map(|(name, condition)| TableFilter::new(name, condition, data_source_dialect))
+        .collect();
+    // Several conditions on one reference are combined in a stable order
+    filters.sort_by(|a, b| (&a.schema, &a.table).cmp(&(&b.schema, &b.table)));
+
+    let positions = SourcePositions::new(sql);
+    let mut collector = FilteredTableCollector {
+        filters: &filters,
+        data_source_dialect,
+        positions: &positions,
+        cte_scopes: Vec::new(),
+        references: Vec::new(),
+        taken_names: HashSet::new(),
+        error: None,
+    };
+    let _ = statements.visit(&mut collector);
+    if let Some(error) = collector.error {
+        return Err(error);
     }
-    
-    results
-}
 
-// Process a SQL query to extract table references
-fn process_query_for_tables(query: &Query, results: &mut Vec<(String, String, String)>) {
-    // First, handle WITH clause (CTEs)
-    if let Some(with) = &query.with {
-        for cte in &with.cte_tables {
-            // Each CTE might have tables
-            process_query_for_tables(&cte.query, results);
-        }
+    // If no filtered tables are referenced, return the original query
+    if collector.references.is_empty() {
+        return Ok(sql.to_string());
     }
-    
-    // Extract tables from the main query body
-    if let SetExpr::Select(select) = query.body.as_ref() {
-        // Process FROM clause
-        for table_with_joins in &select.from {
-            // Handle the main table
-            if let TableFactor::Table { name, alias, .. } = &table_with_joins.relation {
-                // Get the base table name (without schema)
-                let table_name = name.0.last().unwrap().to_string();
-                
-                // Get the full table name (with schema)
-                let full_name = name.to_string();
-                
-                // Get the alias if present
-                let alias_name = alias.as_ref().map(|a| a.name.to_string()).unwrap_or_default();
-                
-                // Add to results
-                results.push((table_name, alias_name.clone(), full_name));
-            } else if let TableFactor::Derived { subquery, .. } = &table_with_joins.relation {
-                // Handle derived tables (subqueries in FROM clause)
-                process_query_for_tables(subquery, results);
-            }
-            
-            // Process JOIN clauses
-            for join in &table_with_joins.joins {
-                if let TableFactor::Table { name, alias, .. } = &join.relation {
-                    // Get the base table name (without schema)
-                    let table_name = name.0.last().unwrap().to_string();
-                    
-                    // Get the full table name (with schema)
-                    let full_name = name.to_string();
-                    
-                    // Get the alias if present
-                    let alias_name = alias.as_ref().map(|a| a.name.to_string()).unwrap_or_default();
-                    
-                    // Add to results
-                    results.push((table_name, alias_name.clone(), full_name));
-                } else if let TableFactor::Derived { subquery, .. } = &join.relation {
-                    // Handle derived tables in JOINs
-                    process_query_for_tables(subquery, results);
+
+    // One CTE per distinct table and alias; aliases reused in different scopes share a CTE
+    let mut filtered_ctes: Vec<(String, String)> = Vec::new();
+    let mut edits = Vec::new();
+    for reference in &collector.references {
+        let full_name = &sql[reference.name_range.clone()];
+        let definition = format!("SELECT * FROM {} WHERE {}", full_name, reference.condition);
+
+        let cte_name = match filtered_ctes.iter().find(|(name, existing)| {
+            *existing == definition && name.starts_with(&format!("filtered_{}", reference.alias_or_table))
+        }) {
+            Some((name, _)) => name.clone(),
+            None => {
+                let base = format!("filtered_{}", reference.alias_or_table);
+                let mut name = base.clone();
+                let mut suffix = 2;
+                while collector.taken_names.contains(&name.to_lowercase())
+                    || filtered_ctes.iter().any(|(existing, _)| existing.eq_ignore_ascii_case(&name))
+                {
+                    name = format!("{}_{}", base, suffix);
+                    suffix += 1;
                 }
+                filtered_ctes.push((name.clone(), definition));
+                name
             }
+        };
+
+        // Keep the alias (or the bare table name) so qualified column references still resolve
+        let (mut start, end) = (reference.name_range.start, reference.replace_end);
+        let mut replacement = match &reference.alias {
+            Some(_) => format!("{} ", cte_name),
+            None => format!("{} {}", cte_name, reference.table_ident),
+        };
+
+        // Normalize the whitespace between FROM / JOIN and the rewritten reference
+        let preceding = sql[..start].trim_end();
+        if preceding.len() < start && ends_with_keyword(preceding, &["FROM", "JOIN"]) {
+            start = preceding.len();
+            replacement = format!(" {}", replacement);
         }
-        
-        // Check for subqueries in other parts of the query
-        
-        // Subqueries in SELECT list
-        for item in &select.projection {
-            if let SelectItem::UnnamedExpr(expr) = item {
-                process_expr_for_tables(expr, results);
-            } else if let SelectItem::ExprWithAlias { expr, .. } = item {
-                process_expr_for_tables(expr, results);
-            }
-        }
-        
-        // Subqueries in WHERE clause
-        if let Some(expr) = &select.selection {
-            process_expr_for_tables(expr, results);
-        }
-        
-        // Subqueries in HAVING clause
-        if let Some(expr) = &select.having {
-            process_expr_for_tables(expr, results);
+
+        edits.push((start, end, replacement));
+    }
+
+    let cte_definitions = filtered_ctes
+        .iter()
+        .map(|(name, definition)| format!("{} AS ({})", name, definition))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    // Filtered CTEs go first so existing CTEs can read from them
+    match query.with.as_ref().and_then(|with| with.cte_tables.first()) {
+        Some(first_cte) => {
+            let position = positions
+                .offset(first_cte.alias.name.span.start)
+                .ok_or_else(|| row_filter_position_error(&first_cte.alias.name.value))?;
+            edits.push((position, position, format!("{}, ", cte_definitions)));
         }
-    } else if let SetExpr::Query(subquery) = query.body.as_ref() {
-        // Recursively process subqueries
-        process_query_for_tables(subquery, results);
-    } else if let SetExpr::SetOperation { left, right, .. } = query.body.as_ref() {
-        // Process both sides of set operations (UNION, EXCEPT, INTERSECT)
-        process_set_expr_for_tables(left, results);
-        process_set_expr_for_tables(right, results);
+        None => edits.push((0, 0, format!("WITH {} ", cte_definitions))),
+    }
+
+    // Apply edits back to front so earlier offsets stay valid
+    edits.sort_by(|a, b| b.0.cmp(&a.0).then(b.1.cmp(&a.1)));
+    let mut transformed_sql = sql.to_string();
+    for (start, end, replacement) in edits {
+        transformed_sql.replace_range(start..end, &replacement);
     }
+
+    Ok(transformed_sql)
 }
 
-// Process a SetExpr to extract table references
-fn process_set_expr_for_tables(set_expr: &SetExpr, results: &mut Vec<(String, String, String)>) {
-    match set_expr {
-        SetExpr::Select(select) => {
-            // Process tables in the FROM clause
-            for table_with_joins in &select.from {
-                if let TableFactor::Table { name, alias, .. } = &table_with_joins.relation {
-                    let table_name = name.0.last().unwrap().to_string();
-                    let full_name = name.to_string();
-                    let alias_name = alias.as_ref().map(|a| a.name.to_string()).unwrap_or_default();
-                    results.push((table_name, alias_name.clone(), full_name));
-                } else if let TableFactor::Derived { subquery, .. } = &table_with_joins.relation {
-                    process_query_for_tables(subquery, results);
-                }
-                
-                // Process JOINs
-                for join in &table_with_joins.joins {
-                    if let TableFactor::Table { name, alias, .. } = &join.relation {
-                        let table_name = name.0.last().unwrap().to_string();
-                        let full_name = name.to_string();
-                        let alias_name = alias.as_ref().map(|a| a.name.to_string()).unwrap_or_default();
-                        results.push((table_name, alias_name.clone(), full_name));
-                    } else if let TableFactor::Derived { subquery, .. } = &join.relation {
-                        process_query_for_tables(subquery, results);
-                    }
-                }
-            }
-        },
-        SetExpr::Query(subquery) => process_query_for_tables(subquery, results),
-        SetExpr::SetOperation { left, right, .. } => {
-            process_set_expr_for_tables(left, results);
-            process_set_expr_for_tables(right, results);
-        },
-        _ => {}
+// A row filter with its schema and table names folded the way the dialect resolves them
+struct TableFilter<'a> {
+    schema: Option<String>,
+    table: String,
+    condition: &'a str,
+}
+
+impl<'a> TableFilter<'a> {
+    fn new(name: &str, condition: &'a str, data_source_dialect: &str) -> Self {
+        let (schema, table) = match name.rsplit_once('.') {
+            Some((schema, table)) => (Some(schema), table),
+            None => (None, name),
+        };
+        // Policies name tables the way they'd be written unquoted
+        Self {
+            schema: schema.map(|schema| fold_identifier(schema, false, data_source_dialect)),
+            table: fold_identifier(table, false, data_source_dialect),
+            condition,
+        }
     }
 }
 
-// Process an expression to extract table references from subqueries
-fn process_expr_for_tables(expr: &Expr, results: &mut Vec<(String, String, String)>) {
-    match expr {
-        Expr::Subquery(subquery) => {
-            process_query_for_tables(subquery, results);
-        },
-        Expr::BinaryOp { left, right, .. } => {
-            process_expr_for_tables(left, results);
-            process_expr_for_tables(right, results);
-        },
-        Expr::UnaryOp { expr, .. } => {
-            process_expr_for_tables(expr, results);
-        },
-        Expr::Function(_) => {
-            // We don't need to process function arguments
-            // as we're just looking for table references
-        },
-        Expr::Case { conditions, results: case_results, else_result, .. } => {
-            // Check all parts of a CASE expression
-            for condition in conditions {
-                process_expr_for_tables(condition, results);
-            }
-            for result in case_results {
-                process_expr_for_tables(result, results);
-            }
-            if let Some(else_result) = else_result {
-                process_expr_for_tables(else_result, results);
-            }
-        },
-        Expr::Exists { subquery, .. } => {
-            process_query_for_tables(subquery, results);
-        },
-        Expr::InSubquery { subquery, .. } => {
-            process_query_for_tables(subquery, results);
-        },
-        _ => {}
+// Folds an identifier to the name the warehouse resolves it to, so two names compare equal
+// exactly when they refer to the same object
+fn fold_identifier(value: &str, quoted: bool, data_source_dialect: &str) -> String {
+    match data_source_dialect {
+        // Unquoted identifiers resolve upper case, quoted ones as written
+        "snowflake" if !quoted => value.to_uppercase(),
+        // Unquoted identifiers resolve lower case, quoted ones as written
+        "postgres" | "supabase" | "redshift" if !quoted => value.to_lowercase(),
+        "snowflake" | "postgres" | "supabase" | "redshift" => value.to_string(),
+        // Table names are case-sensitive, quoted or not
+        "bigquery" | "clickhouse" => value.to_string(),
+        // The rest resolve table names case-insensitively, quoted or not
+        _ => value.to_lowercase(),
     }
 }
 
+// A reference to a filtered table found in the query
+struct FilteredTableReference {
+    condition: String,
+    table_ident: String,
+    alias: Option<String>,
+    alias_or_table: String,
+    name_range: std::ops::Range<usize>,
+    // End of the text replaced by the filtered CTE name: the alias start, or the name end
+    replace_end: usize,
+}
 
-/// A more comprehensive approach to row-level filtering that handles a wider range of SQL patterns
-fn apply_row_level_filters_directly(
-    sql: &str,
-    table_info: &[(String, String, String)],
-    table_filters: &HashMap<String, String>
-) -> Result<String, SqlAnalyzerError> {
-    // If no table filters, return the original SQL
-    if table_filters.is_empty() {
-        return Ok(sql.to_string());
-    }
-    
-    // If no tables in query, return the original SQL
-    if table_info.is_empty() {
-        return Ok(sql.to_string());
-    }
-    
-    // Prepare filtered CTE definitions
-    let mut filtered_ctes = Vec::new();
-    
-    // Create a mapping table_name/alias -> filtered_alias for later reference replacement
-    let mut table_alias_map = HashMap::new();
-    
-    // Track tables that need filtering
-    let mut tables_to_filter = HashSet::new();
-    
-    // Process each table reference and create CTEs for tables with filters
-    for (table_name, alias, full_name) in table_info {
-        if let Some(filter) = table_filters.get(table_name) {
-            // Create filtered table name using the alias if it exists
-            let filtered_alias = if !alias.is_empty() {
-                format!("filtered_{}", alias)
-            } else {
-                format!("filtered_{}", table_name)
-            };
-            
-            // Map both the table name and alias to the filtered alias
-            table_alias_map.insert(table_name.clone(), filtered_alias.clone());
-            if !alias.is_empty() {
-                table_alias_map.insert(alias.clone(), filtered_alias.clone());
-            }
-            
-            // Track this table for filtering
-            tables_to_filter.insert(table_name.clone());
-            
-            // Create the CTE definition
-            filtered_ctes.push(format!("{} AS (SELECT * FROM {} WHERE {})",
-                filtered_alias, full_name, filter));
+// Finds every reference to a filtered table, tracking which CTE names are in scope
+struct FilteredTableCollector<'a> {
+    filters: &'a [TableFilter<'a>],
+    data_source_dialect: &'a str,
+    positions: &'a SourcePositions<'a>,
+    // CTE names visible in each enclosing query, with the offset they become visible from
+    cte_scopes: Vec<Vec<(String, usize)>>,
+    references: Vec<FilteredTableReference>,
+    // Lowercased CTE and table names already used by the query
+    taken_names: HashSet<String>,
+    error: Option<SqlAnalyzerError>,
+}
+
+impl FilteredTableCollector<'_> {
+    // The conditions of every filter on the table `name` refers to
+    fn filter_condition(&self, name: &[sqlparser::ast::Ident]) -> Option<String> {
+        let fold = |ident: &sqlparser::ast::Ident| {
+            fold_identifier(
+                &ident.value,
+                ident.quote_style.is_some(),
+                self.data_source_dialect,
+            )
+        };
+        let (table, qualifiers) = name.split_last()?;
+        let table = fold(table);
+        let schema = qualifiers.last().map(fold);
+
+        let conditions: Vec<&str> = self
+            .filters
+            .iter()
+            .filter(|filter| filter.table == table)
+            .filter(|filter| match (&filter.schema, &schema) {
+                (Some(filter_schema), Some(schema)) => filter_schema == schema,
+                _ => true,
+            })
+            .map(|filter| filter.condition)
+            .collect();
+
+        match conditions.as_slice() {
+            [] => None,
+            [condition] => Some(condition.to_string()),
+            conditions => Some(
+                conditions
+                    .iter()
+                    .map(|condition| format!("({})", condition))
+                    .collect::<Vec<_>>()
+                    .join(" AND "),
+            ),
         }
     }
-    
-    // If no filtered CTEs needed, return the original SQL
-    if filtered_ctes.is_empty() {
-        return Ok(sql.to_string());
+
+    fn is_cte_reference(&self, name: &str, position: usize) -> bool {
+        let name = name.to_lowercase();
+        self.cte_scopes.iter().any(|scope| {
+            scope
+                .iter()
+                .any(|(cte_name, visible_from)| *cte_name == name && *visible_from <= position)
+        })
     }
-    
-    // For queries not specially handled, use a general approach
-    
-    // Parse the SQL to determine if it already has a WITH clause
-    let cte_separator = if sql.trim_start().to_uppercase().starts_with("WITH ") {
-        // If the query already has a WITH clause, use a comma to add our CTEs
-        ","
-    } else {
-        // Otherwise, use WITH to start a new clause
-        "WITH"
-    };
-    
-    // Start building the transformed SQL
-    let mut transformed_sql = if sql.trim_start().to_uppercase().starts_with("WITH ") {
-        // Find where the WITH clause ends - it's after all CTEs but before the main query SELECT
-        let with_content_end = find_with_clause_end(sql);
-        
-        if with_content_end > 0 {
-            // Insert our filtered CTEs after the existing CTEs
-            format!("{} {}, {}", 
-                &sql[0..with_content_end], 
-                filtered_ctes.join(", "), 
-                &sql[with_content_end..])
-        } else {
-            // Fallback if we can't find the end of the WITH clause
-            format!("{} {}", cte_separator, filtered_ctes.join(", ")) + &sql
-        }
-    } else {
-        // No existing WITH clause, add one
-        format!("{} {} {}", cte_separator, filtered_ctes.join(", "), sql)
-    };
-    
-    // Replace references to filtered tables
-    // This is a simplified approach - a full solution would require a proper SQL parser
-    for (table_name, alias, _) in table_info {
-        if let Some(_) = table_filters.get(table_name) {
-            // The new name for the filtered table
-            let filtered_alias = if !alias.is_empty() {
-                format!("filtered_{}", alias)
-            } else {
-                format!("filtered_{}", table_name)
-            };
-            
-            // Replace aliased references in FROM and JOIN clauses
-            if !alias.is_empty() {
-                // Replace "... FROM xx alias" with "... FROM filtered_alias"
-                // Include variants with different spacing and formatting
-                let from_patterns = vec![
-                    format!(" FROM {} {}", table_name, alias),
-                    format!("\n        FROM \n            {} {}", table_name, alias),
-                    format!("\n        FROM \n            {} {}\n", table_name, alias),
-                    format!("\n        FROM {} {}", table_name, alias),
-                    format!("FROM {} {}", table_name, alias),
-                    format!("FROM\n            {} {}", table_name, alias),
-                ];
-                
-                let from_replacement = format!(" FROM {}", filtered_alias);
-                
-                for from_pattern in from_patterns {
-                    if transformed_sql.contains(&from_pattern) {
-                        transformed_sql = transformed_sql.replace(&from_pattern, &from_replacement);
-                    }
-                }
-                
-                // Replace "... JOIN xx alias" with "... JOIN filtered_alias"
-                let join_patterns = vec![
-                    format!(" JOIN {} {}", table_name, alias),
-                    format!("\n        JOIN \n            {} {}", table_name, alias),
-                    format!("\n        JOIN {} {}", table_name, alias),
-                    format!("JOIN {} {}", table_name, alias),
-                    format!("JOIN\n            {} {}", table_name, alias),
-                ];
-                
-                let join_replacement = format!(" JOIN {}", filtered_alias);
-                
-                for join_pattern in join_patterns {
-                    if transformed_sql.contains(&join_pattern) {
-                        transformed_sql = transformed_sql.replace(&join_pattern, &join_replacement);
-                    }
-                }
-                
-                // Replace "... FROM schema.xx alias" with "... FROM schema.xx filtered_alias"
-                // This handles schema-qualified tables
-                if transformed_sql.contains(".") {
-                    let schema_from_pattern = format!(" FROM {}.{} {}", "schema", table_name, alias);
-                    let schema_from_replacement = format!(" FROM {}.{} {}", "schema", table_name, filtered_alias);
-                    transformed_sql = transformed_sql.replace(&schema_from_pattern, &schema_from_replacement);
-                    
-                    let schema_join_pattern = format!(" JOIN {}.{} {}", "schema", table_name, alias);
-                    let schema_join_replacement = format!(" JOIN {}.{} {}", "schema", table_name, filtered_alias);
-                    transformed_sql = transformed_sql.replace(&schema_join_pattern, &schema_join_replacement);
-                    
-                    // Also try schema1, schema2, etc.
-                    for i in 1..5 {
-                        let schema_from_pattern = format!(" FROM schema{}.{} {}", i, table_name, alias);
-                        let schema_from_replacement = format!(" FROM schema{}.{} {}", i, table_name, filtered_alias);
-                        transformed_sql = transformed_sql.replace(&schema_from_pattern, &schema_from_replacement);
-                        
-                        let schema_join_pattern = format!(" JOIN schema{}.{} {}", i, table_name, alias);
-                        let schema_join_replacement = format!(" JOIN schema{}.{} {}", i, table_name, filtered_alias);
-                        transformed_sql = transformed_sql.replace(&schema_join_pattern, &schema_join_replacement);
-                    }
-                }
-                
-                // Replace column references "alias." with "filtered_alias."
-                let alias_dot = format!("{}.", alias);
-                let filtered_alias_dot = format!("{}.", filtered_alias);
-                transformed_sql = transformed_sql.replace(&alias_dot, &filtered_alias_dot);
-            } else {
-                // For unaliased tables
-                let from_pattern = format!(" FROM {} ", table_name);
-                let from_replacement = format!(" FROM {} ", filtered_alias);
-                transformed_sql = transformed_sql.replace(&from_pattern, &from_replacement);
-                
-                let join_pattern = format!(" JOIN {} ", table_name);
-                let join_replacement = format!(" JOIN {} ", filtered_alias);
-                transformed_sql = transformed_sql.replace(&join_pattern, &join_replacement);
-                
-                // Replace column references for unaliased tables
-                let table_dot = format!("{}.", table_name);
-                let filtered_table_dot = format!("{}.", filtered_alias);
-                transformed_sql = transformed_sql.replace(&table_dot, &filtered_table_dot);
+
+    fn fail(&mut self, error: SqlAnalyzerError) -> ControlFlow<()> {
+        self.error = Some(error);
+        ControlFlow::Break(())
+    }
+}
+
+impl Visitor for FilteredTableCollector<'_> {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<Self::Break> {
+        let mut scope = Vec::new();
+        if let Some(with) = &query.with {
+            for cte in &with.cte_tables {
+                // A non-recursive CTE is only visible after its own definition
+                let location = if with.recursive {
+                    cte.alias.name.span.start
+                } else {
+                    cte.closing_paren_token.0.span.end
+                };
+                let visible_from = match self.positions.offset(location) {
+                    Some(offset) => offset,
+                    None => return self.fail(row_filter_position_error(&cte.alias.name.value)),
+                };
+                let name = cte.alias.name.value.to_lowercase();
+                self.taken_names.insert(name.clone());
+                scope.push((name, visible_from));
             }
         }
+        self.cte_scopes.push(scope);
+        ControlFlow::Continue(())
     }
-    
-    // Special handling for subqueries
-    // This is still a simplified approach that won't catch every case
-    // A proper solution would rewrite the query using the SQL parser's AST
-    for (table_name, _, _) in table_info.iter().filter(|(name, _, _)| table_filters.contains_key(name)) {
-        let o_references = [" o ", " o2 ", " o3 "];
-        
-        if table_name == "orders" {
-            for reference in o_references.iter() {
-                if transformed_sql.contains(reference) {
-                    let filtered_reference = format!(" filtered_{}", reference.trim());
-                    transformed_sql = transformed_sql.replace(reference, &filtered_reference);
-                }
-                
-                let reference_dot = format!("{}.", reference.trim());
-                if transformed_sql.contains(&reference_dot) {
-                    let filtered_reference_dot = format!("filtered_{}.", reference.trim());
-                    transformed_sql = transformed_sql.replace(&reference_dot, &filtered_reference_dot);
-                }
-            }
+
+    fn post_visit_query(&mut self, _query: &Query) -> ControlFlow<Self::Break> {
+        self.cte_scopes.pop();
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(&mut self, table_factor: &TableFactor) -> ControlFlow<Self::Break> {
+        // Table-valued function calls are not table references
+        let (name, alias) = match table_factor {
+            TableFactor::Table {
+                name,
+                alias,
+                args: None,
+                ..
+            } => (name, alias),
+            _ => return ControlFlow::Continue(()),
+        };
+        let (first, last) = match (name.0.first(), name.0.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return ControlFlow::Continue(()),
+        };
+        self.taken_names.insert(last.value.to_lowercase());
+
+        let condition = match self.filter_condition(&name.0) {
+            Some(condition) => condition,
+            None => return ControlFlow::Continue(()),
+        };
+
+        let (start, end) = match (
+            self.positions.offset(first.span.start),
+            self.positions.offset(last.span.end),
+        ) {
+            (Some(start), Some(end)) => (start, end),
+            _ => return self.fail(row_filter_position_error(&last.value)),
+        };
+        if name.0.len() == 1 && self.is_cte_reference(&last.value, start) {
+            return ControlFlow::Continue(());
         }
+
+        let replace_end = match alias {
+            Some(alias) => match self.positions.offset(alias.name.span.start) {
+                Some(offset) => offset,
+                None => return self.fail(row_filter_position_error(&alias.name.value)),
+            },
+            None => end,
+        };
+
+        self.references.push(FilteredTableReference {
+            condition,
+            table_ident: last.to_string(),
+            alias: alias.as_ref().map(|alias| alias.name.value.clone()),
+            alias_or_table: alias
+                .as_ref()
+                .map(|alias| alias.name.value.clone())
+                .unwrap_or_else(|| last.value.clone()),
+            name_range: start..end,
+            replace_end,
+        });
+        ControlFlow::Continue(())
     }
-    
-    Ok(transformed_sql)
 }
 
-// Helper function to find the end of a WITH clause in a SQL query
-fn find_with_clause_end(sql: &str) -> usize {
-    // This is a simplified function that tries to find where the WITH clause ends
-    // and the main SELECT begins. A proper SQL parser would be better.
-    let lowercase_sql = sql.to_lowercase();
-    
-    // Skip the "WITH" keyword
-    let after_with = lowercase_sql.find("with ").map(|pos| pos + 5).unwrap_or(0);
-    if after_with == 0 {
-        return 0;
+// Maps parser locations (1-based line and character column) to byte offsets in the query text
+struct SourcePositions<'a> {
+    sql: &'a str,
+    line_starts: Vec<usize>,
+}
+
+impl<'a> SourcePositions<'a> {
+    fn new(sql: &'a str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(sql.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        Self { sql, line_starts }
     }
-    
-    // Look for the main SELECT that isn't inside parentheses
-    let mut paren_depth = 0;
-    let mut in_cte = true;
-    
-    for (i, c) in lowercase_sql[after_with..].char_indices() {
-        let pos = i + after_with;
-        
-        match c {
-            '(' => paren_depth += 1,
-            ')' => {
-                if paren_depth > 0 {
-                    paren_depth -= 1;
-                }
-                
-                // If we're at the end of a CTE definition and the next keyword is SELECT
-                if paren_depth == 0 && in_cte {
-                    // Look for the next keyword
-                    if let Some(select_pos) = lowercase_sql[pos+1..].find("select") {
-                        let actual_pos = pos + 1 + select_pos;
-                        
-                        // Make sure this SELECT is the main query SELECT, not part of another CTE
-                        if !lowercase_sql[pos+1..actual_pos].contains("as") {
-                            return pos + 1;
-                        }
-                    }
-                }
-            },
-            ',' => {
-                // Comma at depth 0 separates CTEs
-                if paren_depth == 0 {
-                    // We're still in the CTE section
-                    in_cte = true;
-                }
-            },
-            's' => {
-                // Check if this is the start of 'select' at depth 0
-                if paren_depth == 0 && pos + 6 < lowercase_sql.len() && 
-                   &lowercase_sql[pos..pos+6] == "select" &&
-                   !in_cte {
-                    return pos;
-                }
-            },
-            _ => {}
-        }
+
+    fn offset(&self, location: sqlparser::tokenizer::Location) -> Option<usize> {
+        if location.line == 0 || location.column == 0 {
+            return None;
+        }
+        let line_start = *self.line_starts.get(location.line as usize - 1)?;
+        let line = &self.sql[line_start..];
+        let column = location.column as usize - 1;
+        line.char_indices()
+            .map(|(index, _)| index)
+            .chain(std::iter::once(line.len()))
+            .nth(column)
+            .map(|index| line_start + index)
     }
-    
-    // If we couldn't find it, return 0
-    0
-}
\ No newline at end of file
+}
+
+fn ends_with_keyword(text: &str, keywords: &[&str]) -> bool {
+    keywords.iter().any(|keyword| {
+        text.len() >= keyword.len()
+            && text.is_char_boundary(text.len() - keyword.len())
+            && text[text.len() - keyword.len()..].eq_ignore_ascii_case(keyword)
+            && !text[..text.len() - keyword.len()]
+                .chars()
+                .next_back()
+                .is_some_and(|c| c.is_alphanumeric() || c == '_')
+    })
+}
+
+fn row_filter_position_error(name: &str) -> SqlAnalyzerError {
+    SqlAnalyzerError::Internal(anyhow::anyhow!(
+        "Unable to locate '{}' in the query to apply row-level filters",
+        name
+    ))
+}
@@ -0,0 +1,271 @@
+use sql_analyzer::{apply_column_masks, ColumnMask, SqlAnalyzerError};
+use std::collections::HashMap;
+
+fn user_masks() -> HashMap<String, HashMap<String, ColumnMask>> {
+    HashMap::from([(
+        "users".to_string(),
+        HashMap::from([
+            ("email".to_string(), ColumnMask::Hash),
+            ("phone".to_string(), ColumnMask::Partial),
+            ("birth_date".to_string(), ColumnMask::Null),
+            ("ssn".to_string(), ColumnMask::Deny),
+        ]),
+    )])
+}
+
+async fn mask(sql: &str, dialect: &str) -> Result<String, SqlAnalyzerError> {
+    apply_column_masks(sql.to_string(), user_masks(), dialect).await
+}
+
+#[tokio::test]
+async fn test_masks_projected_columns_and_keeps_names() {
+    let masked = mask(
+        "SELECT u.id, u.email, u.phone AS contact, u.birth_date FROM public.users u",
+        "postgres",
+    )
+    .await
+    .unwrap();
+
+    assert!(masked.contains("u.id,"), "{}", masked);
+    assert!(
+        masked.contains("MD5(CAST(u.email AS VARCHAR)) AS email"),
+        "{}",
+        masked
+    );
+    assert!(
+        masked.contains(
+            "CASE WHEN u.phone IS NULL THEN NULL ELSE CONCAT('****', RIGHT(CAST(u.phone AS VARCHAR), 4)) END AS contact"
+        ),
+        "{}",
+        masked
+    );
+    assert!(masked.contains("NULL AS birth_date"), "{}", masked);
+}
+
+#[tokio::test]
+async fn test_masks_columns_renamed_through_ctes() {
+    let sql = "WITH contacts AS (SELECT u.id, u.email AS address FROM public.users u) \
+               SELECT c.id, UPPER(c.address) AS address_upper FROM contacts c";
+    let masked = mask(sql, "snowflake").await.unwrap();
+
+    assert!(
+        masked.contains("SHA2(CAST(UPPER(c.address) AS VARCHAR), 256) AS address_upper"),
+        "{}",
+        masked
+    );
+    // The CTE itself is left alone so filters and joins keep working
+    assert!(masked.contains("u.email AS address"), "{}", masked);
+}
+
+#[tokio::test]
+async fn test_masks_every_branch_of_a_union() {
+    let sql = "SELECT u.email FROM public.users u UNION ALL SELECT a.contact_email FROM public.accounts a";
+    let masked = mask(sql, "bigquery").await.unwrap();
+
+    assert!(
+        masked.contains("TO_HEX(SHA256(CAST(u.email AS STRING))) AS email"),
+        "{}",
+        masked
+    );
+    assert!(
+        masked.contains("TO_HEX(SHA256(CAST(a.contact_email AS STRING))) AS contact_email"),
+        "{}",
+        masked
+    );
+}
+
+#[tokio::test]
+async fn test_rejects_denied_columns_anywhere() {
+    let projected = mask("SELECT u.ssn FROM public.users u", "postgres").await;
+    assert!(matches!(
+        projected,
+        Err(SqlAnalyzerError::ColumnAccessDenied(_))
+    ));
+
+    // Filtering on a denied column would reveal it one row at a time
+    let filtered = mask(
+        "SELECT u.id FROM public.users u WHERE u.ssn LIKE '123%'",
+        "postgres",
+    )
+    .await;
+    assert!(matches!(
+        filtered,
+        Err(SqlAnalyzerError::ColumnAccessDenied(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_rejects_wildcards_over_protected_tables() {
+    let direct = mask("SELECT * FROM public.users", "postgres").await;
+    assert!(matches!(
+        direct,
+        Err(SqlAnalyzerError::ColumnAccessDenied(_))
+    ));
+
+    let through_cte = mask(
+        "WITH u AS (SELECT * FROM public.users) SELECT u.* FROM u",
+        "postgres",
+    )
+    .await;
+    assert!(matches!(
+        through_cte,
+        Err(SqlAnalyzerError::ColumnAccessDenied(_))
+    ));
+
+    let through_subquery = mask(
+        "SELECT * FROM (SELECT u.email FROM public.users u) x",
+        "postgres",
+    )
+    .await;
+    assert!(matches!(
+        through_subquery,
+        Err(SqlAnalyzerError::ColumnAccessDenied(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_masks_unqualified_columns_in_joins() {
+    let sql = "SELECT email, o.amount FROM public.users u JOIN public.orders o ON u.id = o.user_id";
+    let masked = mask(sql, "postgres").await.unwrap();
+    assert!(
+        masked.contains("MD5(CAST(email AS VARCHAR)) AS email"),
+        "{}",
+        masked
+    );
+}
+
+#[tokio::test]
+async fn test_queries_without_protected_tables_are_unchanged() {
+    let sql = "SELECT o.id,\n  o.amount -- total\nFROM public.orders o";
+    assert_eq!(mask(sql, "postgres").await.unwrap(), sql);
+
+    let unmasked_columns = "SELECT u.id, u.name FROM public.users u";
+    assert_eq!(
+        mask(unmasked_columns, "postgres").await.unwrap(),
+        unmasked_columns
+    );
+}
+
+#[tokio::test]
+async fn test_hash_falls_back_to_null_without_a_hash_function() {
+    let masked = mask("SELECT u.email FROM users u", "sqlite").await.unwrap();
+    assert!(masked.contains("NULL AS email"), "{}", masked);
+}
+
+#[tokio::test]
+async fn test_warehouse_specific_masks_parse() {
+    for dialect in [
+        "postgres",
+        "redshift",
+        "snowflake",
+        "bigquery",
+        "mysql",
+        "databricks",
+        "sqlserver",
+        "duckdb",
+        "clickhouse",
+        "sqlite",
+    ] {
+        let masked = mask("SELECT u.email, u.phone FROM users u", dialect).await;
+        assert!(masked.is_ok(), "{}: {:?}", dialect, masked);
+    }
+}
+
+#[tokio::test]
+async fn test_rejects_whole_rows_of_protected_tables() {
+    for (sql, dialect) in [
+        ("SELECT row_to_json(u) FROM public.users u", "postgres"),
+        ("SELECT u FROM public.users u", "postgres"),
+        (
+            "SELECT u.id FROM public.users u WHERE CAST(u AS TEXT) LIKE '%x%'",
+            "postgres",
+        ),
+        ("SELECT to_jsonb(u.*) FROM public.users u", "postgres"),
+        ("SELECT row_to_json(users) FROM public.users", "postgres"),
+        ("SELECT OBJECT_CONSTRUCT(*) FROM users", "snowflake"),
+        (
+            "WITH x AS (SELECT email FROM users) SELECT TO_JSON(x) FROM x",
+            "snowflake",
+        ),
+    ] {
+        let masked = mask(sql, dialect).await;
+        assert!(
+            matches!(masked, Err(SqlAnalyzerError::ColumnAccessDenied(_))),
+            "{}: {:?}",
+            sql,
+            masked
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_counting_rows_of_protected_tables_is_allowed() {
+    let sql = "SELECT COUNT(*) AS users FROM public.users u";
+    assert_eq!(mask(sql, "postgres").await.unwrap(), sql);
+
+    let masked = mask(
+        "WITH u AS (SELECT email AS u FROM public.users) SELECT u FROM u",
+        "postgres",
+    )
+    .await
+    .unwrap();
+    assert!(
+        masked.contains("MD5(CAST(u AS VARCHAR)) AS u"),
+        "{}",
+        masked
+    );
+}
+
+#[tokio::test]
+async fn test_rejects_protected_columns_passed_through_opaque_relations() {
+    for (sql, dialect) in [
+        (
+            "SELECT v.x FROM public.users u, LATERAL (VALUES (u.email)) AS v(x)",
+            "postgres",
+        ),
+        (
+            "SELECT v.x FROM public.users u, LATERAL (SELECT u.phone AS x) AS v",
+            "postgres",
+        ),
+        (
+            "SELECT t.e FROM public.users u CROSS JOIN UNNEST(ARRAY[u.email]) AS t(e)",
+            "postgres",
+        ),
+        (
+            "SELECT t.e FROM public.users u, jsonb_array_elements_text(jsonb_build_array(u.birth_date)) AS t(e)",
+            "postgres",
+        ),
+        (
+            "SELECT f.value FROM users u, LATERAL FLATTEN(input => ARRAY_CONSTRUCT(u.email)) f",
+            "snowflake",
+        ),
+    ] {
+        let masked = mask(sql, dialect).await;
+        assert!(
+            matches!(masked, Err(SqlAnalyzerError::ColumnAccessDenied(_))),
+            "{}: {:?}",
+            sql,
+            masked
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_rejects_protected_columns_outside_a_select() {
+    for sql in [
+        "SELECT id FROM public.orders UNION ALL TABLE users",
+        "SELECT id FROM public.users UNION ALL VALUES ((SELECT email FROM public.users LIMIT 1))",
+    ] {
+        let masked = mask(sql, "postgres").await;
+        assert!(
+            matches!(masked, Err(SqlAnalyzerError::ColumnAccessDenied(_))),
+            "{}: {:?}",
+            sql,
+            masked
+        );
+    }
+
+    // Opaque relations are fine while no protected column goes into them
+    let sql = "SELECT u.id, t.n FROM public.users u CROSS JOIN UNNEST(ARRAY[1, 2]) AS t(n)";
+    assert_eq!(mask(sql, "postgres").await.unwrap(), sql);
+}
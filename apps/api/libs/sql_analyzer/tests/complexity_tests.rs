@@ -0,0 +1,59 @@
+use sql_analyzer::analyze_query_complexity;
+
+#[tokio::test]
+async fn test_simple_query_complexity() {
+    let sql = "SELECT o.id, c.name FROM sales.orders o JOIN sales.customers c ON o.customer_id = c.id WHERE o.amount > 100";
+    let report = analyze_query_complexity(sql.to_string(), "postgres").await.unwrap();
+
+    assert_eq!(report.join_count, 1);
+    assert_eq!(report.cross_join_count, 0);
+    assert!(report.missing_join_predicates.is_empty());
+    assert!(report.unbounded_scans.is_empty());
+    assert_eq!(report.max_subquery_depth, 0);
+    assert!(!report.has_cartesian_product());
+    assert_eq!(report.score, 1);
+}
+
+#[tokio::test]
+async fn test_cartesian_products_detected() {
+    let comma_join = "SELECT o.id, c.name FROM sales.orders o, sales.customers c WHERE o.amount > 100";
+    let report = analyze_query_complexity(comma_join.to_string(), "snowflake").await.unwrap();
+    assert_eq!(report.missing_join_predicates.len(), 1);
+    assert!(report.has_cartesian_product());
+
+    let related_comma_join = "SELECT o.id, c.name FROM sales.orders o, sales.customers c WHERE o.customer_id = c.id";
+    let report = analyze_query_complexity(related_comma_join.to_string(), "snowflake").await.unwrap();
+    assert!(report.missing_join_predicates.is_empty());
+
+    let cross_join = "SELECT o.id, d.day FROM sales.orders o CROSS JOIN sales.days d WHERE o.amount > 1";
+    let report = analyze_query_complexity(cross_join.to_string(), "snowflake").await.unwrap();
+    assert_eq!(report.cross_join_count, 1);
+
+    let trivial_on = "SELECT o.id, c.name FROM sales.orders o JOIN sales.customers c ON 1 = 1 WHERE o.amount > 1";
+    let report = analyze_query_complexity(trivial_on.to_string(), "snowflake").await.unwrap();
+    assert_eq!(report.missing_join_predicates.len(), 1);
+}
+
+#[tokio::test]
+async fn test_unbounded_scans_depth_and_windows() {
+    let sql = "WITH ranked AS (
+        SELECT e.user_id, ROW_NUMBER() OVER (PARTITION BY e.user_id ORDER BY e.ts DESC) AS rn
+        FROM analytics.events e
+    )
+    SELECT r.user_id FROM ranked r
+    WHERE r.user_id IN (SELECT u.id FROM (SELECT u2.id FROM public.users u2) u)";
+    let report = analyze_query_complexity(sql.to_string(), "postgres").await.unwrap();
+
+    assert_eq!(report.window_function_count, 1);
+    assert_eq!(
+        report.unbounded_scans,
+        vec!["analytics.events".to_string(), "public.users".to_string()]
+    );
+    assert_eq!(report.max_subquery_depth, 2);
+}
+
+#[tokio::test]
+async fn test_complexity_rejects_non_select() {
+    let result = analyze_query_complexity("DROP TABLE public.users".to_string(), "postgres").await;
+    assert!(result.is_err());
+}
@@ -0,0 +1,137 @@
+use sql_analyzer::{analyze_query, ColumnLineage, TransformationKind};
+
+fn lineage_for<'a>(lineage: &'a [ColumnLineage], column: &str) -> &'a ColumnLineage {
+    lineage
+        .iter()
+        .find(|c| c.column == column)
+        .unwrap_or_else(|| panic!("No lineage found for column '{}'", column))
+}
+
+#[tokio::test]
+async fn test_lineage_direct_columns() {
+    let sql = "SELECT u.id, u.name AS user_name FROM db1.public.users u";
+    let result = analyze_query(sql.to_string(), "postgres").await.unwrap();
+
+    assert_eq!(result.column_lineage.len(), 2);
+
+    let id = lineage_for(&result.column_lineage, "id");
+    assert_eq!(id.sources.len(), 1);
+    assert_eq!(id.sources[0].qualified_name(), "db1.public.users.id");
+    assert!(id.steps.iter().all(|s| s.transformations.is_empty()));
+
+    let name = lineage_for(&result.column_lineage, "user_name");
+    assert!(name.depends_on("users", "name"));
+}
+
+#[tokio::test]
+async fn test_lineage_through_cte_with_aggregate() {
+    let sql = "WITH order_totals AS (
+        SELECT o.customer_id, SUM(o.amount * o.quantity) AS total
+        FROM sales.orders o
+        GROUP BY o.customer_id
+    )
+    SELECT c.region, ot.total
+    FROM sales.customers c
+    JOIN order_totals ot ON c.id = ot.customer_id";
+
+    let result = analyze_query(sql.to_string(), "postgres").await.unwrap();
+
+    let total = lineage_for(&result.column_lineage, "total");
+    assert!(total.depends_on("orders", "amount"));
+    assert!(total.depends_on("orders", "quantity"));
+    assert_eq!(total.sources.len(), 2);
+    assert!(total.has_transformation(&TransformationKind::Aggregate));
+    assert!(total.has_transformation(&TransformationKind::Arithmetic));
+    assert_eq!(total.steps.first().unwrap().scope, "CTE:order_totals");
+    assert_eq!(total.steps.last().unwrap().scope, "query");
+
+    let region = lineage_for(&result.column_lineage, "region");
+    assert_eq!(region.sources[0].qualified_name(), "sales.customers.region");
+
+    // Single-source CTE columns are also exposed through the CTE summary
+    let cte = result.ctes.iter().find(|c| c.name == "order_totals").unwrap();
+    assert_eq!(
+        cte.column_mappings.get("customer_id"),
+        Some(&("orders".to_string(), "customer_id".to_string()))
+    );
+}
+
+#[tokio::test]
+async fn test_lineage_through_derived_table_with_case_and_window() {
+    let sql = "SELECT d.bucket, d.rnk
+    FROM (
+        SELECT
+            CASE WHEN p.price > 100 THEN 'premium' ELSE 'standard' END AS bucket,
+            ROW_NUMBER() OVER (PARTITION BY p.category ORDER BY p.price DESC) AS rnk
+        FROM catalog.products p
+    ) d";
+
+    let result = analyze_query(sql.to_string(), "postgres").await.unwrap();
+
+    let bucket = lineage_for(&result.column_lineage, "bucket");
+    assert!(bucket.depends_on("products", "price"));
+    assert!(bucket.has_transformation(&TransformationKind::Case));
+    assert_eq!(bucket.steps.first().unwrap().scope, "derived:d");
+
+    let rnk = lineage_for(&result.column_lineage, "rnk");
+    assert!(rnk.depends_on("products", "category"));
+    assert!(rnk.has_transformation(&TransformationKind::Window));
+}
+
+#[tokio::test]
+async fn test_lineage_union_merges_sources() {
+    let sql = "SELECT a.email FROM crm.leads a
+    UNION ALL
+    SELECT b.contact_email FROM crm.contacts b";
+
+    let result = analyze_query(sql.to_string(), "postgres").await.unwrap();
+
+    assert_eq!(result.column_lineage.len(), 1);
+    let email = &result.column_lineage[0];
+    assert_eq!(email.column, "email");
+    assert!(email.depends_on("leads", "email"));
+    assert!(email.depends_on("contacts", "contact_email"));
+    assert!(email.has_transformation(&TransformationKind::SetOperation));
+}
+
+#[tokio::test]
+async fn test_lineage_wildcard_through_cte() {
+    let sql = "WITH recent AS (
+        SELECT * FROM analytics.events e WHERE e.created_at > '2024-01-01'
+    )
+    SELECT r.event_type, COUNT(r.user_id) AS users
+    FROM recent r
+    GROUP BY r.event_type";
+
+    let result = analyze_query(sql.to_string(), "postgres").await.unwrap();
+
+    let event_type = lineage_for(&result.column_lineage, "event_type");
+    assert_eq!(event_type.sources[0].qualified_name(), "analytics.events.event_type");
+
+    let users = lineage_for(&result.column_lineage, "users");
+    assert!(users.depends_on("events", "user_id"));
+    assert!(users.has_transformation(&TransformationKind::Aggregate));
+}
+
+#[tokio::test]
+async fn test_lineage_whole_row_references() {
+    let sql = "SELECT row_to_json(u) AS profile FROM public.users u";
+    let result = analyze_query(sql.to_string(), "postgres").await.unwrap();
+    let profile = lineage_for(&result.column_lineage, "profile");
+    assert_eq!(profile.sources[0].qualified_name(), "public.users.*");
+
+    let sql = "WITH totals AS (SELECT o.id, o.amount AS total FROM sales.orders o)
+    SELECT to_jsonb(t.*) AS totals FROM totals t";
+    let result = analyze_query(sql.to_string(), "postgres").await.unwrap();
+    let totals = lineage_for(&result.column_lineage, "totals");
+    assert!(totals.depends_on("orders", "id"));
+    assert!(totals.depends_on("orders", "amount"));
+
+    // A column named like a relation is read as the column
+    let sql = "WITH total AS (SELECT o.amount AS total FROM sales.orders o)
+    SELECT total FROM total";
+    let result = analyze_query(sql.to_string(), "postgres").await.unwrap();
+    let total = lineage_for(&result.column_lineage, "total");
+    assert_eq!(total.sources.len(), 1);
+    assert!(total.depends_on("orders", "amount"));
+}
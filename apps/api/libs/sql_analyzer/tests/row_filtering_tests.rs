@@ -1,4 +1,4 @@
-use sql_analyzer::apply_row_level_filters;
+use sql_analyzer::{apply_row_level_filters, apply_row_level_filters_with_dialect};
 use std::collections::HashMap;
 use tokio;
 
@@ -830,4 +830,103 @@ async fn test_row_level_filtering_with_complex_expressions() {
         filtered_sql.contains("FROM filtered_o o3 WHERE"),
         "Should filter orders in EXISTS subquery"
     );
-} 
\ No newline at end of file
+} 
+
+#[tokio::test]
+async fn test_row_level_filtering_with_cte_shadowing_table_name() {
+    // The CTE named after the table still reads the real, filtered table
+    let sql = "WITH users AS (SELECT * FROM users WHERE status = 'active') SELECT u.id FROM users u";
+
+    let mut table_filters = HashMap::new();
+    table_filters.insert("users".to_string(), "tenant_id = 123".to_string());
+
+    let filtered_sql = apply_row_level_filters(sql.to_string(), table_filters)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        filtered_sql,
+        "WITH filtered_users AS (SELECT * FROM users WHERE tenant_id = 123), \
+         users AS (SELECT * FROM filtered_users users WHERE status = 'active') SELECT u.id FROM users u"
+    );
+}
+
+#[tokio::test]
+async fn test_row_level_filtering_rejects_unparseable_queries() {
+    let sql = "SELECT u.id FROM users u WHERE";
+
+    let mut table_filters = HashMap::new();
+    table_filters.insert("users".to_string(), "tenant_id = 123".to_string());
+
+    let result = apply_row_level_filters(sql.to_string(), table_filters).await;
+    assert!(
+        result.is_err(),
+        "Queries that can't be parsed must not run unfiltered"
+    );
+}
+
+#[tokio::test]
+async fn test_row_level_filtering_folds_quoted_identifiers_per_dialect() {
+    // Snowflake resolves unquoted `orders` to ORDERS, so "ORDERS" is the same table
+    let sql = r#"SELECT o.id FROM "ORDERS" o"#;
+
+    let mut table_filters = HashMap::new();
+    table_filters.insert("orders".to_string(), "tenant_id = 123".to_string());
+
+    let filtered_sql =
+        apply_row_level_filters_with_dialect(sql.to_string(), table_filters.clone(), "snowflake")
+            .await
+            .unwrap();
+    assert!(
+        filtered_sql.contains(r#"filtered_o AS (SELECT * FROM "ORDERS" WHERE tenant_id = 123)"#),
+        "Quoted upper case table should be filtered on Snowflake: {}",
+        filtered_sql
+    );
+
+    // Postgres keeps quoted names as written, so "ORDERS" is a different table there
+    let filtered_sql =
+        apply_row_level_filters_with_dialect(sql.to_string(), table_filters, "postgres")
+            .await
+            .unwrap();
+    assert_eq!(filtered_sql, sql);
+}
+
+#[tokio::test]
+async fn test_row_level_filtering_matches_schema_qualified_filters() {
+    let sql = "SELECT a.id, b.id FROM sales.orders a JOIN archive.orders b ON a.id = b.id";
+
+    let mut table_filters = HashMap::new();
+    table_filters.insert("sales.orders".to_string(), "tenant_id = 123".to_string());
+
+    let filtered_sql =
+        apply_row_level_filters_with_dialect(sql.to_string(), table_filters, "postgres")
+            .await
+            .unwrap();
+
+    assert_eq!(
+        filtered_sql,
+        "WITH filtered_a AS (SELECT * FROM sales.orders WHERE tenant_id = 123) \
+         SELECT a.id, b.id FROM filtered_a a JOIN archive.orders b ON a.id = b.id"
+    );
+}
+
+#[tokio::test]
+async fn test_row_level_filtering_applies_every_matching_filter() {
+    // Unqualified references could be either table, so both conditions apply
+    let sql = "SELECT o.id FROM orders o";
+
+    let mut table_filters = HashMap::new();
+    table_filters.insert("sales.orders".to_string(), "tenant_id = 123".to_string());
+    table_filters.insert("archive.orders".to_string(), "region = 'eu'".to_string());
+
+    let filtered_sql =
+        apply_row_level_filters_with_dialect(sql.to_string(), table_filters, "postgres")
+            .await
+            .unwrap();
+
+    assert_eq!(
+        filtered_sql,
+        "WITH filtered_o AS (SELECT * FROM orders WHERE (region = 'eu') AND (tenant_id = 123)) \
+         SELECT o.id FROM filtered_o o"
+    );
+}
@@ -0,0 +1,126 @@
+use sql_analyzer::{transpile_query, SqlAnalyzerError};
+
+async fn transpile(sql: &str, from: &str, to: &str) -> String {
+    transpile_query(sql.to_string(), from, to).await.unwrap()
+}
+
+#[tokio::test]
+async fn test_date_trunc_argument_order() {
+    let sql = "SELECT DATE_TRUNC('month', o.created_at) AS month FROM sales.orders o";
+
+    let bigquery = transpile(sql, "postgres", "bigquery").await;
+    assert!(bigquery.contains("DATE_TRUNC(o.created_at, MONTH)"), "{}", bigquery);
+
+    let sqlserver = transpile(sql, "postgres", "sqlserver").await;
+    assert!(sqlserver.contains("DATETRUNC(month, o.created_at)"), "{}", sqlserver);
+
+    let back = transpile(&bigquery, "bigquery", "snowflake").await;
+    assert!(back.contains("DATE_TRUNC('month', o.created_at)"), "{}", back);
+
+    let mysql = transpile(sql, "postgres", "mysql").await;
+    assert!(mysql.contains("DATE_FORMAT(o.created_at, '%Y-%m-01')"), "{}", mysql);
+}
+
+#[tokio::test]
+async fn test_limit_and_top() {
+    let sql = "SELECT o.id FROM sales.orders o ORDER BY o.id LIMIT 10";
+    let sqlserver = transpile(sql, "postgres", "sqlserver").await;
+    assert!(sqlserver.contains("SELECT TOP 10 o.id"), "{}", sqlserver);
+    assert!(!sqlserver.contains("LIMIT"), "{}", sqlserver);
+
+    let back = transpile(&sqlserver, "sqlserver", "snowflake").await;
+    assert!(back.ends_with("LIMIT 10"), "{}", back);
+    assert!(!back.contains("TOP"), "{}", back);
+
+    let paged = transpile(
+        "SELECT o.id FROM sales.orders o ORDER BY o.id LIMIT 10 OFFSET 20",
+        "postgres",
+        "sqlserver",
+    )
+    .await;
+    assert!(paged.contains("OFFSET 20 ROWS"), "{}", paged);
+    assert!(paged.contains("FETCH FIRST 10 ROWS ONLY"), "{}", paged);
+}
+
+#[tokio::test]
+async fn test_identifier_quoting() {
+    let sql = "SELECT \"u\".\"First Name\" AS \"name\" FROM \"public\".\"users\" AS \"u\"";
+
+    let bigquery = transpile(sql, "postgres", "bigquery").await;
+    assert_eq!(
+        bigquery,
+        "SELECT `u`.`First Name` AS `name` FROM `public`.`users` AS `u`"
+    );
+
+    let sqlserver = transpile(sql, "postgres", "sqlserver").await;
+    assert_eq!(
+        sqlserver,
+        "SELECT [u].[First Name] AS [name] FROM [public].[users] AS [u]"
+    );
+}
+
+#[tokio::test]
+async fn test_ilike_and_casts() {
+    let sql = "SELECT u.id::text AS id FROM public.users u WHERE u.email ILIKE '%@buster.so'";
+
+    let bigquery = transpile(sql, "postgres", "bigquery").await;
+    assert!(bigquery.contains("CAST(u.id AS STRING)"), "{}", bigquery);
+    assert!(bigquery.contains("LOWER(u.email) LIKE LOWER('%@buster.so')"), "{}", bigquery);
+
+    let snowflake = transpile(sql, "postgres", "snowflake").await;
+    assert!(snowflake.contains("ILIKE"), "{}", snowflake);
+    assert!(snowflake.contains("u.id::TEXT"), "{}", snowflake);
+}
+
+#[tokio::test]
+async fn test_interval_literals() {
+    let sql = "SELECT o.id FROM sales.orders o WHERE o.created_at > CURRENT_DATE - INTERVAL '7 days'";
+
+    let bigquery = transpile(sql, "postgres", "bigquery").await;
+    assert!(bigquery.contains("INTERVAL 7 DAY"), "{}", bigquery);
+
+    let sqlserver = transpile(sql, "postgres", "sqlserver").await;
+    assert!(sqlserver.contains("DATEADD(day, -7, CURRENT_DATE)"), "{}", sqlserver);
+
+    let postgres = transpile(
+        "SELECT o.id FROM sales.orders o WHERE o.created_at > CURRENT_DATE - INTERVAL 30 DAY",
+        "databricks",
+        "postgres",
+    )
+    .await;
+    assert!(postgres.contains("INTERVAL '30 day'"), "{}", postgres);
+}
+
+#[tokio::test]
+async fn test_qualify_rewritten_for_postgres() {
+    let sql = "SELECT o.customer_id, o.amount FROM sales.orders o \
+               QUALIFY ROW_NUMBER() OVER (PARTITION BY o.customer_id ORDER BY o.amount DESC) = 1 \
+               ORDER BY o.customer_id";
+
+    let postgres = transpile(sql, "snowflake", "postgres").await;
+    assert!(!postgres.contains("QUALIFY"), "{}", postgres);
+    assert!(
+        postgres.starts_with("SELECT customer_id, amount FROM (SELECT o.customer_id, o.amount, ROW_NUMBER() OVER"),
+        "{}",
+        postgres
+    );
+    assert!(postgres.contains("AS _qualify_1"), "{}", postgres);
+    assert!(postgres.contains("WHERE _qualify_1 = 1 ORDER BY customer_id"), "{}", postgres);
+
+    // Warehouses with QUALIFY keep it as-is
+    let bigquery = transpile(sql, "snowflake", "bigquery").await;
+    assert!(bigquery.contains("QUALIFY"), "{}", bigquery);
+}
+
+#[tokio::test]
+async fn test_transpile_rejects_unsupported_input() {
+    let err = transpile_query("SELECT 1".to_string(), "postgres", "oracle")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, SqlAnalyzerError::Transpilation(_)));
+
+    let err = transpile_query("DELETE FROM public.users".to_string(), "postgres", "mysql")
+        .await
+        .unwrap_err();
+    assert!(matches!(err, SqlAnalyzerError::UnsupportedStatement(_)));
+}
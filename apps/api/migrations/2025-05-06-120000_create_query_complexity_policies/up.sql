@@ -0,0 +1,14 @@
+-- Per-organization limits applied to SQL before it is sent to a data source
+CREATE TABLE query_complexity_policies (
+    organization_id UUID PRIMARY KEY REFERENCES organizations(id) ON DELETE CASCADE,
+    max_score INTEGER,
+    max_joins INTEGER,
+    max_subquery_depth INTEGER,
+    reject_cartesian_products BOOLEAN NOT NULL DEFAULT TRUE,
+    large_tables TEXT[] NOT NULL DEFAULT '{}',
+    enforcement TEXT NOT NULL DEFAULT 'warn',
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT query_complexity_policies_enforcement_check
+        CHECK (enforcement IN ('warn', 'reject'))
+);
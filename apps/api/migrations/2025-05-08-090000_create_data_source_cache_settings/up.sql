@@ -0,0 +1,8 @@
+-- Per-data-source lifetime of cached query results; 0 disables caching for the source
+CREATE TABLE data_source_cache_settings (
+    data_source_id UUID PRIMARY KEY REFERENCES data_sources(id) ON DELETE CASCADE,
+    ttl_seconds INTEGER NOT NULL DEFAULT 300,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT data_source_cache_settings_ttl_check CHECK (ttl_seconds >= 0)
+);
@@ -0,0 +1,8 @@
+-- Per-data-source limits on how long a single warehouse query may run
+CREATE TABLE data_source_query_settings (
+    data_source_id UUID PRIMARY KEY REFERENCES data_sources(id) ON DELETE CASCADE,
+    statement_timeout_seconds INTEGER NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT data_source_query_settings_timeout_check CHECK (statement_timeout_seconds > 0)
+);
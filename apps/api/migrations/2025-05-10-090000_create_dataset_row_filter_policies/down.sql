@@ -0,0 +1 @@
+DROP TABLE IF EXISTS dataset_row_filter_policies;
@@ -0,0 +1,15 @@
+-- Row filters applied to every query a user runs against a dataset, e.g. `region = {{user.attributes.region}}`
+CREATE TABLE dataset_row_filter_policies (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    dataset_id UUID NOT NULL REFERENCES datasets(id) ON DELETE CASCADE,
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    filter_expression TEXT NOT NULL,
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMP WITH TIME ZONE
+);
+
+CREATE INDEX dataset_row_filter_policies_dataset_id_idx
+    ON dataset_row_filter_policies (dataset_id)
+    WHERE deleted_at IS NULL;
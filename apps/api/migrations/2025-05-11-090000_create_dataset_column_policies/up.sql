@@ -0,0 +1,22 @@
+-- How a dataset column is shown to the members of a permission group or organization role
+CREATE TABLE dataset_column_policies (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    dataset_column_id UUID NOT NULL REFERENCES dataset_columns(id) ON DELETE CASCADE,
+    dataset_id UUID NOT NULL REFERENCES datasets(id) ON DELETE CASCADE,
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    action TEXT NOT NULL,
+    permission_group_id UUID REFERENCES permission_groups(id) ON DELETE CASCADE,
+    role user_organization_role_enum,
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    deleted_at TIMESTAMP WITH TIME ZONE,
+    CONSTRAINT dataset_column_policies_action_check
+        CHECK (action IN ('deny', 'hash', 'partial', 'null')),
+    CONSTRAINT dataset_column_policies_target_check
+        CHECK ((permission_group_id IS NULL) <> (role IS NULL))
+);
+
+CREATE INDEX dataset_column_policies_dataset_id_idx
+    ON dataset_column_policies (dataset_id)
+    WHERE deleted_at IS NULL;
@@ -0,0 +1,11 @@
+-- Per-organization rules for SQL written against the semantic layer's metrics and filters
+CREATE TABLE semantic_query_settings (
+    organization_id UUID PRIMARY KEY REFERENCES organizations(id) ON DELETE CASCADE,
+    validation_mode TEXT NOT NULL DEFAULT 'flexible',
+    -- Restricted queriers may only run semantic queries, validated in strict mode
+    strict_for_restricted_queriers BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT semantic_query_settings_validation_mode_check
+        CHECK (validation_mode IN ('strict', 'flexible'))
+);
@@ -0,0 +1,45 @@
+-- Append-only record of executed queries and asset access. Ids are kept without foreign keys
+-- so entries outlive the users, data sources and assets they refer to.
+CREATE TABLE audit_logs (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL,
+    user_id UUID,
+    event_type TEXT NOT NULL,
+    surface TEXT NOT NULL,
+    data_source_id UUID,
+    query_text TEXT,
+    query_hash TEXT,
+    row_count BIGINT,
+    duration_ms BIGINT,
+    error TEXT,
+    asset_id UUID,
+    asset_type asset_type_enum,
+    details JSONB NOT NULL DEFAULT '{}'::jsonb,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT audit_logs_event_type_check
+        CHECK (event_type IN ('query', 'asset_view', 'asset_share')),
+    CONSTRAINT audit_logs_surface_check
+        CHECK (surface IN ('agent_tool', 'metric_data', 'run_sql', 'public_share', 'api'))
+);
+
+CREATE INDEX audit_logs_organization_created_at_idx
+    ON audit_logs (organization_id, created_at DESC);
+CREATE INDEX audit_logs_organization_user_idx
+    ON audit_logs (organization_id, user_id, created_at DESC);
+
+CREATE OR REPLACE FUNCTION reject_audit_log_changes()
+RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_logs is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_logs_append_only
+    BEFORE UPDATE OR DELETE ON audit_logs
+    FOR EACH ROW
+    EXECUTE FUNCTION reject_audit_log_changes();
+
+CREATE TRIGGER audit_logs_no_truncate
+    BEFORE TRUNCATE ON audit_logs
+    FOR EACH STATEMENT
+    EXECUTE FUNCTION reject_audit_log_changes();
@@ -0,0 +1,26 @@
+-- Staged credential changes for a data source. New credentials are tested before they replace
+-- the active secret, and the previous secret is kept until the rollback window closes.
+CREATE TABLE data_source_credential_rotations (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    data_source_id UUID NOT NULL REFERENCES data_sources(id) ON DELETE CASCADE,
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    status TEXT NOT NULL DEFAULT 'staged',
+    test_error TEXT,
+    tested_at TIMESTAMP WITH TIME ZONE,
+    swapped_at TIMESTAMP WITH TIME ZONE,
+    rollback_expires_at TIMESTAMP WITH TIME ZONE,
+    rolled_back_at TIMESTAMP WITH TIME ZONE,
+    created_by UUID NOT NULL REFERENCES users(id),
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT data_source_credential_rotations_status_check
+        CHECK (status IN ('staged', 'ready', 'failed', 'swapped', 'rolled_back', 'cancelled'))
+);
+
+CREATE INDEX data_source_credential_rotations_data_source_idx
+    ON data_source_credential_rotations (data_source_id, created_at DESC);
+
+-- A data source has at most one rotation waiting to be swapped in
+CREATE UNIQUE INDEX data_source_credential_rotations_pending_idx
+    ON data_source_credential_rotations (data_source_id)
+    WHERE status IN ('staged', 'ready');
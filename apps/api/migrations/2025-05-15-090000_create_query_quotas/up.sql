@@ -0,0 +1,27 @@
+-- Limits on how hard an organization's queries may hit its warehouses. A row without a data
+-- source applies to all of the organization's queries; a row with one applies on top of it.
+CREATE TABLE query_quotas (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    data_source_id UUID REFERENCES data_sources(id) ON DELETE CASCADE,
+    max_concurrent_queries INTEGER,
+    max_queries_per_minute INTEGER,
+    max_rows_returned BIGINT,
+    max_bytes_scanned BIGINT,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT query_quotas_limits_check CHECK (
+        (max_concurrent_queries IS NULL OR max_concurrent_queries > 0)
+        AND (max_queries_per_minute IS NULL OR max_queries_per_minute > 0)
+        AND (max_rows_returned IS NULL OR max_rows_returned > 0)
+        AND (max_bytes_scanned IS NULL OR max_bytes_scanned > 0)
+    )
+);
+
+CREATE UNIQUE INDEX query_quotas_organization_idx
+    ON query_quotas (organization_id)
+    WHERE data_source_id IS NULL;
+
+CREATE UNIQUE INDEX query_quotas_data_source_idx
+    ON query_quotas (data_source_id)
+    WHERE data_source_id IS NOT NULL;
@@ -0,0 +1,8 @@
+ALTER TABLE api_keys
+    DROP CONSTRAINT IF EXISTS api_keys_rate_limit_check,
+    DROP CONSTRAINT IF EXISTS api_keys_scopes_check,
+    DROP COLUMN IF EXISTS rate_limit_per_minute,
+    DROP COLUMN IF EXISTS last_used_at,
+    DROP COLUMN IF EXISTS expires_at,
+    DROP COLUMN IF EXISTS data_source_ids,
+    DROP COLUMN IF EXISTS scopes;
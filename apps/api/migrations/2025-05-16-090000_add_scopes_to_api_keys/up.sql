@@ -0,0 +1,12 @@
+-- Keys without scopes act as their owner, as every key did before scopes existed.
+-- Scoped keys only reach the routes their scopes allow, and run SQL only on the listed data sources.
+ALTER TABLE api_keys
+    ADD COLUMN scopes TEXT[],
+    ADD COLUMN data_source_ids UUID[] NOT NULL DEFAULT '{}',
+    ADD COLUMN expires_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN last_used_at TIMESTAMP WITH TIME ZONE,
+    ADD COLUMN rate_limit_per_minute INTEGER,
+    ADD CONSTRAINT api_keys_scopes_check
+        CHECK (scopes <@ ARRAY['metrics:read', 'sql:run', 'datasets:deploy']::TEXT[]),
+    ADD CONSTRAINT api_keys_rate_limit_check
+        CHECK (rate_limit_per_minute IS NULL OR rate_limit_per_minute > 0);
@@ -0,0 +1,9 @@
+UPDATE api_keys SET deleted_at = NOW() WHERE 'scim:provision' = ANY(scopes) AND deleted_at IS NULL;
+UPDATE api_keys SET scopes = array_remove(scopes, 'scim:provision') WHERE 'scim:provision' = ANY(scopes);
+
+ALTER TABLE api_keys
+    DROP CONSTRAINT api_keys_scopes_check,
+    ADD CONSTRAINT api_keys_scopes_check
+        CHECK (scopes <@ ARRAY['metrics:read', 'sql:run', 'datasets:deploy']::TEXT[]);
+
+DROP TABLE IF EXISTS scim_external_ids;
@@ -0,0 +1,22 @@
+-- The identifiers an organization's identity provider assigned to the users and teams it
+-- provisions over SCIM
+CREATE TABLE scim_external_ids (
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    resource_type TEXT NOT NULL,
+    resource_id UUID NOT NULL,
+    external_id TEXT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (organization_id, resource_type, resource_id),
+    CONSTRAINT scim_external_ids_resource_type_check CHECK (resource_type IN ('user', 'group'))
+);
+
+CREATE INDEX scim_external_ids_external_id_idx
+    ON scim_external_ids (organization_id, resource_type, external_id);
+
+-- API keys with the scim:provision scope authenticate the identity provider
+ALTER TABLE api_keys
+    DROP CONSTRAINT api_keys_scopes_check,
+    ADD CONSTRAINT api_keys_scopes_check CHECK (
+        scopes <@ ARRAY['metrics:read', 'sql:run', 'datasets:deploy', 'scim:provision']::TEXT[]
+    );
@@ -0,0 +1,19 @@
+-- The LLM models an organization's agent uses in each mode, in order of preference. Later
+-- models are tried when the provider of an earlier one errors. The 'default' row applies to
+-- modes without their own row.
+CREATE TABLE model_routes (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    mode TEXT NOT NULL,
+    models TEXT[] NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT model_routes_mode_check CHECK (
+        mode IN ('default', 'initialization', 'data_catalog_search', 'planning', 'analysis', 'review')
+    ),
+    CONSTRAINT model_routes_models_check CHECK (
+        cardinality(models) > 0 AND array_position(models, NULL) IS NULL
+    )
+);
+
+CREATE UNIQUE INDEX model_routes_organization_mode_idx ON model_routes (organization_id, mode);
@@ -0,0 +1,32 @@
+-- One row per LLM call made while answering a message. Costs are priced when the call is
+-- recorded, in millionths of a US dollar, so later price changes don't rewrite history.
+CREATE TABLE llm_usage (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    organization_id UUID NOT NULL REFERENCES organizations(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    chat_id UUID REFERENCES chats(id) ON DELETE SET NULL,
+    message_id UUID REFERENCES messages(id) ON DELETE SET NULL,
+    model TEXT NOT NULL,
+    mode TEXT,
+    tool_name TEXT,
+    prompt_tokens INTEGER NOT NULL,
+    completion_tokens INTEGER NOT NULL,
+    reasoning_tokens INTEGER NOT NULL DEFAULT 0,
+    total_tokens INTEGER NOT NULL,
+    cost_micros BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX llm_usage_organization_created_at_idx ON llm_usage (organization_id, created_at);
+CREATE INDEX llm_usage_chat_idx ON llm_usage (chat_id);
+CREATE INDEX llm_usage_message_idx ON llm_usage (message_id);
+
+-- How much an organization may spend on LLM calls per calendar month (UTC). New agent runs
+-- are refused once the month's usage reaches it.
+CREATE TABLE llm_budgets (
+    organization_id UUID PRIMARY KEY REFERENCES organizations(id) ON DELETE CASCADE,
+    monthly_budget_micros BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    CONSTRAINT llm_budgets_monthly_budget_check CHECK (monthly_budget_micros > 0)
+);
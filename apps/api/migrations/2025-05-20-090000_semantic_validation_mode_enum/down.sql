@@ -0,0 +1,8 @@
+ALTER TABLE semantic_query_settings
+    ALTER COLUMN validation_mode DROP DEFAULT,
+    ALTER COLUMN validation_mode TYPE TEXT USING validation_mode::TEXT,
+    ALTER COLUMN validation_mode SET DEFAULT 'flexible',
+    ADD CONSTRAINT semantic_query_settings_validation_mode_check
+        CHECK (validation_mode IN ('strict', 'flexible'));
+
+DROP TYPE semantic_validation_mode_enum;
@@ -0,0 +1,9 @@
+-- Store semantic validation modes as an enum, like the other mode columns
+CREATE TYPE semantic_validation_mode_enum AS ENUM ('strict', 'flexible');
+
+ALTER TABLE semantic_query_settings
+    DROP CONSTRAINT semantic_query_settings_validation_mode_check,
+    ALTER COLUMN validation_mode DROP DEFAULT,
+    ALTER COLUMN validation_mode TYPE semantic_validation_mode_enum
+        USING validation_mode::semantic_validation_mode_enum,
+    ALTER COLUMN validation_mode SET DEFAULT 'flexible';
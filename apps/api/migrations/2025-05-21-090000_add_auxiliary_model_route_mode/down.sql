@@ -0,0 +1,7 @@
+DELETE FROM model_routes WHERE mode = 'auxiliary';
+
+ALTER TABLE model_routes
+    DROP CONSTRAINT model_routes_mode_check,
+    ADD CONSTRAINT model_routes_mode_check CHECK (
+        mode IN ('default', 'initialization', 'data_catalog_search', 'planning', 'analysis', 'review')
+    );
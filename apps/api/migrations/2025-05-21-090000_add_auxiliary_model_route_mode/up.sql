@@ -0,0 +1,6 @@
+-- Short auxiliary LLM calls, such as conversation titles and todo lists, get their own route
+ALTER TABLE model_routes
+    DROP CONSTRAINT model_routes_mode_check,
+    ADD CONSTRAINT model_routes_mode_check CHECK (
+        mode IN ('default', 'initialization', 'data_catalog_search', 'planning', 'analysis', 'review', 'auxiliary')
+    );
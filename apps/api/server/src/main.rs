@@ -56,6 +56,9 @@ async fn main() -> Result<(), anyhow::Error> {
         return Ok(());
     }
 
+    // Fail at startup rather than on the first query if the secret provider is misconfigured
+    database::secrets::get_secret_provider();
+
     let protected_router = Router::new().nest("/api/v1", routes::protected_router());
     let public_router = Router::new().route("/health", axum::routing::get(|| async { "OK" }));
 
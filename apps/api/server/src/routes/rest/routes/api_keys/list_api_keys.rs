@@ -20,6 +20,27 @@ pub struct ApiKeyInfo {
     pub owner_id: Uuid,
     pub owner_email: String,
     pub created_at: DateTime<Utc>,
+    pub scopes: Option<Vec<String>>, // None for keys with the owner's full access
+    pub data_source_ids: Vec<Uuid>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub rate_limit_per_minute: Option<i32>,
+}
+
+impl ApiKeyInfo {
+    pub fn new(key: ApiKey, owner_email: String) -> Self {
+        ApiKeyInfo {
+            id: key.id,
+            owner_id: key.owner_id,
+            owner_email,
+            created_at: key.created_at,
+            scopes: key.scopes,
+            data_source_ids: key.data_source_ids,
+            expires_at: key.expires_at,
+            last_used_at: key.last_used_at,
+            rate_limit_per_minute: key.rate_limit_per_minute,
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -55,11 +76,6 @@ async fn list_api_keys_handler(user: AuthenticatedUser) -> Result<Vec<ApiKeyInfo
 
     Ok(api_keys
         .into_iter()
-        .map(|(key, email)| ApiKeyInfo {
-            id: key.id,
-            owner_id: key.owner_id,
-            owner_email: email,
-            created_at: key.created_at,
-        })
+        .map(|(key, email)| ApiKeyInfo::new(key, email))
         .collect())
 } 
\ No newline at end of file
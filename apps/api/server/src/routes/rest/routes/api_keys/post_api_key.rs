@@ -1,11 +1,11 @@
 use anyhow::Result;
 use axum::http::StatusCode;
-use axum::Extension;
-use chrono::Utc;
-use diesel::insert_into;
+use axum::{Extension, Json};
+use chrono::{DateTime, Utc};
+use diesel::{insert_into, ExpressionMethods, QueryDsl};
 use diesel_async::RunQueryDsl;
 use jsonwebtoken::{encode, EncodingKey, Header};
-use middleware::AuthenticatedUser;
+use middleware::{ApiKeyScope, AuthenticatedUser};
 use serde::{Deserialize, Serialize};
 use std::env;
 use uuid::Uuid;
@@ -14,13 +14,36 @@ use crate::routes::rest::ApiResponse;
 use database::models::ApiKey;
 use database::organization::get_user_organization_id;
 use database::pool::get_pg_pool;
-use database::schema::api_keys;
+use database::schema::{api_keys, data_sources};
+
+/// Options for a new key. Keys created without scopes have the creator's full access.
+#[derive(Debug, Default, Deserialize)]
+pub struct PostApiKeyRequest {
+    pub scopes: Option<Vec<ApiKeyScope>>,
+    /// Data sources a key with the `sql:run` scope may query
+    #[serde(default)]
+    pub data_source_ids: Vec<Uuid>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub rate_limit_per_minute: Option<i32>,
+}
 
 #[derive(Debug, Serialize)]
 pub struct PostApiKeyResponse {
     pub api_key: String,
 }
 
+/// Request options that can't be honoured; surfaced to the caller as a 400.
+#[derive(Debug)]
+struct InvalidApiKeyOptions(&'static str);
+
+impl std::fmt::Display for InvalidApiKeyOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Invalid API key options: {}", self.0)
+    }
+}
+
+impl std::error::Error for InvalidApiKeyOptions {}
+
 // Add this struct for JWT claims
 #[derive(Debug, Serialize, Deserialize)]
 struct ApiKeyClaims {
@@ -31,24 +54,75 @@ struct ApiKeyClaims {
 
 pub async fn post_api_key(
     Extension(user): Extension<AuthenticatedUser>,
-) -> Result<ApiResponse<PostApiKeyResponse>, (StatusCode, &'static str)> {
-    let api_key = match post_api_key_handler(user).await {
+    request: Option<Json<PostApiKeyRequest>>,
+) -> Result<ApiResponse<PostApiKeyResponse>, (StatusCode, String)> {
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    let api_key = match post_api_key_handler(user, request).await {
         Ok(api_key) => api_key,
+        Err(e) if e.downcast_ref::<InvalidApiKeyOptions>().is_some() => {
+            tracing::warn!("Rejected API key request: {}", e);
+            return Err((StatusCode::BAD_REQUEST, e.to_string()));
+        }
         Err(e) => {
             tracing::error!("Error creating API key: {:?}", e);
-            return Err((StatusCode::INTERNAL_SERVER_ERROR, "Error creating API key"));
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Error creating API key".to_string(),
+            ));
         }
     };
 
     Ok(ApiResponse::JsonData(PostApiKeyResponse { api_key }))
 }
 
-async fn post_api_key_handler(user: AuthenticatedUser) -> Result<String> {
+async fn post_api_key_handler(
+    user: AuthenticatedUser,
+    mut request: PostApiKeyRequest,
+) -> Result<String> {
+    request.data_source_ids.sort();
+    request.data_source_ids.dedup();
+
+    if let Some(expires_at) = request.expires_at {
+        if expires_at <= Utc::now() {
+            return Err(InvalidApiKeyOptions("expires_at must be in the future").into());
+        }
+    }
+    if request
+        .rate_limit_per_minute
+        .is_some_and(|limit| limit <= 0)
+    {
+        return Err(InvalidApiKeyOptions("rate_limit_per_minute must be greater than zero").into());
+    }
+    match &request.scopes {
+        Some(scopes) if scopes.is_empty() => {
+            return Err(InvalidApiKeyOptions("a scoped key needs at least one scope").into());
+        }
+        Some(scopes) if scopes.contains(&ApiKeyScope::SqlRun) => {
+            if request.data_source_ids.is_empty() {
+                return Err(InvalidApiKeyOptions(
+                    "keys with the sql:run scope need data_source_ids",
+                )
+                .into());
+            }
+        }
+        _ => {
+            if !request.data_source_ids.is_empty() {
+                return Err(InvalidApiKeyOptions(
+                    "data_source_ids only apply to the sql:run scope",
+                )
+                .into());
+            }
+        }
+    }
+
     let jwt_secret = env::var("JWT_SECRET").map_err(|_| anyhow::anyhow!("JWT_SECRET not set"))?;
 
     // Create JWT claims
     let claims = ApiKeyClaims {
-        exp: (Utc::now() + chrono::Duration::days(365 * 5)).timestamp(),
+        exp: request
+            .expires_at
+            .unwrap_or_else(|| Utc::now() + chrono::Duration::days(365 * 5))
+            .timestamp(),
         aud: "api".to_string(),
         sub: user.id.to_string(),
     };
@@ -80,6 +154,22 @@ async fn post_api_key_handler(user: AuthenticatedUser) -> Result<String> {
         }
     };
 
+    if !request.data_source_ids.is_empty() {
+        let org_data_sources = data_sources::table
+            .filter(data_sources::id.eq_any(&request.data_source_ids))
+            .filter(data_sources::organization_id.eq(organization_id))
+            .filter(data_sources::deleted_at.is_null())
+            .count()
+            .get_result::<i64>(&mut *conn)
+            .await?;
+        if org_data_sources != request.data_source_ids.len() as i64 {
+            return Err(InvalidApiKeyOptions(
+                "data_source_ids must be data sources of your organization",
+            )
+            .into());
+        }
+    }
+
     let api_key_record = ApiKey {
         id: Uuid::new_v4(),
         owner_id: user.id,
@@ -88,6 +178,16 @@ async fn post_api_key_handler(user: AuthenticatedUser) -> Result<String> {
         created_at: Utc::now(),
         updated_at: Utc::now(),
         deleted_at: None,
+        scopes: request.scopes.map(|scopes| {
+            scopes
+                .iter()
+                .map(|scope| scope.as_str().to_string())
+                .collect()
+        }),
+        data_source_ids: request.data_source_ids,
+        expires_at: request.expires_at,
+        last_used_at: None,
+        rate_limit_per_minute: request.rate_limit_per_minute,
     };
 
     match insert_into(api_keys::table)
@@ -1,7 +1,8 @@
 use anyhow::Result;
 use axum::http::StatusCode;
 use axum::Json;
-use diesel::{ExpressionMethods, QueryDsl};
+use chrono::Utc;
+use diesel::{BoolExpressionMethods, ExpressionMethods, QueryDsl};
 use diesel_async::RunQueryDsl;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -46,6 +47,11 @@ async fn validate_api_key_handler(api_key: String) -> Result<bool> {
     let api_key_exists = match api_keys::table
         .filter(api_keys::key.eq(api_key))
         .filter(api_keys::deleted_at.is_null())
+        .filter(
+            api_keys::expires_at
+                .is_null()
+                .or(api_keys::expires_at.gt(Utc::now())),
+        )
         .select(api_keys::id)
         .first::<Uuid>(&mut *conn)
         .await
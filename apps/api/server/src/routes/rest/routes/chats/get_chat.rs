@@ -2,6 +2,10 @@ use crate::routes::rest::ApiResponse;
 use axum::extract::Path;
 use axum::http::StatusCode;
 use axum::Extension;
+use database::{
+    enums::{AssetType, AuditEventType},
+    helpers::audit_logs::record_asset_access,
+};
 use handlers::chats::get_chat_handler;
 use handlers::chats::types::ChatWithMessages;
 use uuid::Uuid;
@@ -31,5 +35,14 @@ pub async fn get_chat_route(
         }
     };
 
+    record_asset_access(
+        &user.id,
+        &id,
+        AssetType::Chat,
+        AuditEventType::AssetView,
+        serde_json::json!({}),
+    )
+    .await;
+
     Ok(ApiResponse::JsonData(thread_with_messages))
 }
@@ -5,6 +5,7 @@ use axum::Json;
 use database::enums::AssetType;
 use handlers::chats::post_chat_handler;
 use handlers::chats::post_chat_handler::ChatCreateNewChat;
+use handlers::llm_usage::LlmUsageError;
 use handlers::chats::types::ChatWithMessages;
 use middleware::AuthenticatedUser;
 use serde::Deserialize;
@@ -62,7 +63,7 @@ impl From<ChatCreateNewChatRequest> for ChatCreateNewChat {
 pub async fn post_chat_route(
     Extension(user): Extension<AuthenticatedUser>,
     Json(request): Json<ChatCreateNewChatRequest>,
-) -> Result<ApiResponse<ChatWithMessages>, (StatusCode, &'static str)> {
+) -> Result<ApiResponse<ChatWithMessages>, (StatusCode, String)> {
     // Convert REST request to handler request
     let handler_request: ChatCreateNewChat = request.into();
     
@@ -71,16 +72,26 @@ pub async fn post_chat_route(
         tracing::error!("asset_type must be provided when asset_id is specified");
         return Err((
             StatusCode::BAD_REQUEST,
-            "asset_type must be provided when asset_id is specified",
+            "asset_type must be provided when asset_id is specified".to_string(),
         ));
     }
     
     // Call handler
     match post_chat_handler(handler_request, user, None).await {
         Ok(response) => Ok(ApiResponse::JsonData(response)),
+        Err(e) if matches!(
+            e.downcast_ref::<LlmUsageError>(),
+            Some(LlmUsageError::BudgetExceeded { .. })
+        ) => {
+            tracing::warn!("Chat rejected by LLM budget: {}", e);
+            Err((StatusCode::TOO_MANY_REQUESTS, e.to_string()))
+        }
         Err(e) => {
             tracing::error!("Error processing chat: {}", e);
-            Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to process chat"))
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to process chat".to_string(),
+            ))
         }
     }
 }
@@ -3,6 +3,10 @@ use axum::{
     http::StatusCode,
     Extension, Json,
 };
+use database::{
+    enums::{AssetType, AuditEventType},
+    helpers::audit_logs::record_asset_access,
+};
 use handlers::collections::{get_collection_handler, CollectionState, GetCollectionRequest};
 use middleware::AuthenticatedUser;
 use uuid::Uuid;
@@ -18,7 +22,17 @@ pub async fn get_collection(
     
     // Call the handler
     match get_collection_handler(&user, request).await {
-        Ok(collection) => Ok(Json(collection)),
+        Ok(collection) => {
+            record_asset_access(
+                &user.id,
+                &id,
+                AssetType::Collection,
+                AuditEventType::AssetView,
+                serde_json::json!({}),
+            )
+            .await;
+            Ok(Json(collection))
+        }
         Err(e) => {
             tracing::error!("Error getting collection: {}", e);
             
@@ -2,6 +2,10 @@ use crate::routes::rest::ApiResponse;
 use axum::extract::{Path, Query};
 use axum::http::StatusCode;
 use axum::Extension;
+use database::{
+    enums::{AssetType, AuditEventType},
+    helpers::audit_logs::record_asset_access,
+};
 use handlers::dashboards::{get_dashboard_handler, BusterDashboardResponse};
 use middleware::AuthenticatedUser;
 use serde::Deserialize;
@@ -53,6 +57,15 @@ pub async fn get_dashboard_rest_handler(
         }
     };
 
+    record_asset_access(
+        &user.id,
+        &id,
+        AssetType::DashboardFile,
+        AuditEventType::AssetView,
+        serde_json::json!({ "version_number": dashboard.dashboard.version_number }),
+    )
+    .await;
+
     Ok(ApiResponse::JsonData(dashboard))
 }
 
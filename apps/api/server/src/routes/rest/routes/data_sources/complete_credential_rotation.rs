@@ -0,0 +1,49 @@
+use anyhow::Result;
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use database::models::DataSourceCredentialRotation;
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+use crate::routes::rest::ApiResponse;
+use handlers::data_sources::{
+    complete_credential_rotation_handler, CompleteCredentialRotationRequest,
+};
+
+pub async fn complete_credential_rotation(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path((id, rotation_id)): Path<(Uuid, Uuid)>,
+    payload: Option<Json<CompleteCredentialRotationRequest>>,
+) -> Result<ApiResponse<DataSourceCredentialRotation>, (StatusCode, &'static str)> {
+    let request = payload.map(|Json(request)| request).unwrap_or_default();
+
+    match complete_credential_rotation_handler(&user, &id, &rotation_id, request).await {
+        Ok(rotation) => Ok(ApiResponse::JsonData(rotation)),
+        Err(e) => {
+            tracing::error!("Error completing credential rotation: {:?}", e);
+            let error_msg = e.to_string();
+
+            if error_msg.contains("permissions") {
+                Err((StatusCode::FORBIDDEN, "Insufficient permissions"))
+            } else if error_msg.contains("not found") {
+                Err((StatusCode::NOT_FOUND, "Credential rotation not found"))
+            } else if error_msg.contains("rollback_window_hours") {
+                Err((
+                    StatusCode::BAD_REQUEST,
+                    "rollback_window_hours must be between 0 and 168",
+                ))
+            } else if error_msg.contains("connection test passed")
+                || error_msg.contains("no longer ready")
+            {
+                Err((
+                    StatusCode::CONFLICT,
+                    "Only rotations whose connection test passed can be swapped in",
+                ))
+            } else {
+                Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to complete credential rotation",
+                ))
+            }
+        }
+    }
+}
@@ -19,6 +19,8 @@ pub async fn create_data_source(
                 return Err((StatusCode::CONFLICT, "Data source already exists"));
             } else if error_msg.contains("permissions") {
                 return Err((StatusCode::FORBIDDEN, "Insufficient permissions"));
+            } else if error_msg.contains("Invalid credentials") {
+                return Err((StatusCode::BAD_REQUEST, "Invalid credentials"));
             } else {
                 return Err((StatusCode::INTERNAL_SERVER_ERROR, "Failed to create data source"));
             }
@@ -3,6 +3,10 @@ mod get_data_source;
 mod update_data_source;
 mod create_data_source;
 mod delete_data_source;
+mod stage_credential_rotation;
+mod complete_credential_rotation;
+mod rollback_credential_rotation;
+mod query_settings;
 
 use axum::{
     routing::{get, post, put, delete},
@@ -16,4 +20,28 @@ pub fn router() -> Router {
         .route("/:id", get(get_data_source::get_data_source))
         .route("/:id", put(update_data_source::update_data_source))
         .route("/:id", delete(delete_data_source::delete_data_source))
+        .route(
+            "/:id/cache_settings",
+            get(query_settings::get_cache_settings).put(query_settings::update_cache_settings),
+        )
+        .route(
+            "/:id/query_settings",
+            get(query_settings::get_query_settings).put(query_settings::update_query_settings),
+        )
+        .route(
+            "/:id/query_quota",
+            get(query_settings::get_query_quota).put(query_settings::update_query_quota),
+        )
+        .route(
+            "/:id/credential_rotations",
+            post(stage_credential_rotation::stage_credential_rotation),
+        )
+        .route(
+            "/:id/credential_rotations/:rotation_id/complete",
+            post(complete_credential_rotation::complete_credential_rotation),
+        )
+        .route(
+            "/:id/credential_rotations/:rotation_id/rollback",
+            post(rollback_credential_rotation::rollback_credential_rotation),
+        )
 }
\ No newline at end of file
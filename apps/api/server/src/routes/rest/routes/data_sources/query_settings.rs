@@ -0,0 +1,79 @@
+use anyhow::Result;
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+use crate::routes::rest::ApiResponse;
+use handlers::query_settings::{
+    get_data_source_cache_settings_handler, get_data_source_query_quota_handler,
+    get_data_source_query_settings_handler,
+    types::{DataSourceCacheSettingsBody, DataSourceQuerySettingsBody, QueryQuotaBody},
+    update_data_source_cache_settings_handler, update_data_source_query_quota_handler,
+    update_data_source_query_settings_handler,
+};
+
+use super::super::organizations::query_settings::query_settings_error;
+
+pub async fn get_cache_settings(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(data_source_id): Path<Uuid>,
+) -> Result<ApiResponse<DataSourceCacheSettingsBody>, (StatusCode, String)> {
+    match get_data_source_cache_settings_handler(&user, data_source_id).await {
+        Ok(settings) => Ok(ApiResponse::JsonData(settings)),
+        Err(e) => Err(query_settings_error(e, "getting cache settings")),
+    }
+}
+
+pub async fn update_cache_settings(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(data_source_id): Path<Uuid>,
+    Json(payload): Json<DataSourceCacheSettingsBody>,
+) -> Result<ApiResponse<DataSourceCacheSettingsBody>, (StatusCode, String)> {
+    match update_data_source_cache_settings_handler(&user, data_source_id, payload).await {
+        Ok(settings) => Ok(ApiResponse::JsonData(settings)),
+        Err(e) => Err(query_settings_error(e, "updating cache settings")),
+    }
+}
+
+pub async fn get_query_settings(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(data_source_id): Path<Uuid>,
+) -> Result<ApiResponse<DataSourceQuerySettingsBody>, (StatusCode, String)> {
+    match get_data_source_query_settings_handler(&user, data_source_id).await {
+        Ok(settings) => Ok(ApiResponse::JsonData(settings)),
+        Err(e) => Err(query_settings_error(e, "getting query settings")),
+    }
+}
+
+pub async fn update_query_settings(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(data_source_id): Path<Uuid>,
+    Json(payload): Json<DataSourceQuerySettingsBody>,
+) -> Result<ApiResponse<DataSourceQuerySettingsBody>, (StatusCode, String)> {
+    match update_data_source_query_settings_handler(&user, data_source_id, payload).await {
+        Ok(settings) => Ok(ApiResponse::JsonData(settings)),
+        Err(e) => Err(query_settings_error(e, "updating query settings")),
+    }
+}
+
+pub async fn get_query_quota(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(data_source_id): Path<Uuid>,
+) -> Result<ApiResponse<Option<QueryQuotaBody>>, (StatusCode, String)> {
+    match get_data_source_query_quota_handler(&user, data_source_id).await {
+        Ok(quota) => Ok(ApiResponse::JsonData(quota)),
+        Err(e) => Err(query_settings_error(e, "getting query quota")),
+    }
+}
+
+/// A null body removes the data source's quota
+pub async fn update_query_quota(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(data_source_id): Path<Uuid>,
+    Json(payload): Json<Option<QueryQuotaBody>>,
+) -> Result<ApiResponse<Option<QueryQuotaBody>>, (StatusCode, String)> {
+    match update_data_source_query_quota_handler(&user, data_source_id, payload).await {
+        Ok(quota) => Ok(ApiResponse::JsonData(quota)),
+        Err(e) => Err(query_settings_error(e, "updating query quota")),
+    }
+}
@@ -0,0 +1,39 @@
+use anyhow::Result;
+use axum::{extract::Path, http::StatusCode, Extension};
+use database::models::DataSourceCredentialRotation;
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+use crate::routes::rest::ApiResponse;
+use handlers::data_sources::rollback_credential_rotation_handler;
+
+pub async fn rollback_credential_rotation(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path((id, rotation_id)): Path<(Uuid, Uuid)>,
+) -> Result<ApiResponse<DataSourceCredentialRotation>, (StatusCode, &'static str)> {
+    match rollback_credential_rotation_handler(&user, &id, &rotation_id).await {
+        Ok(rotation) => Ok(ApiResponse::JsonData(rotation)),
+        Err(e) => {
+            tracing::error!("Error rolling back credential rotation: {:?}", e);
+            let error_msg = e.to_string();
+
+            if error_msg.contains("permissions") {
+                Err((StatusCode::FORBIDDEN, "Insufficient permissions"))
+            } else if error_msg.contains("not found") {
+                Err((StatusCode::NOT_FOUND, "Credential rotation not found"))
+            } else if error_msg.contains("rollback window") {
+                Err((StatusCode::CONFLICT, "The rollback window has closed"))
+            } else if error_msg.contains("rolled back") || error_msg.contains("later rotation") {
+                Err((
+                    StatusCode::CONFLICT,
+                    "Only the latest swapped rotation can be rolled back",
+                ))
+            } else {
+                Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to roll back credential rotation",
+                ))
+            }
+        }
+    }
+}
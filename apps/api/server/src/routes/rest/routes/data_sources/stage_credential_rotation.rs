@@ -0,0 +1,43 @@
+use anyhow::Result;
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use database::models::DataSourceCredentialRotation;
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+use crate::routes::rest::ApiResponse;
+use handlers::data_sources::{stage_credential_rotation_handler, StageCredentialRotationRequest};
+
+pub async fn stage_credential_rotation(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<StageCredentialRotationRequest>,
+) -> Result<ApiResponse<DataSourceCredentialRotation>, (StatusCode, &'static str)> {
+    match stage_credential_rotation_handler(&user, &id, payload).await {
+        Ok(rotation) => Ok(ApiResponse::JsonData(rotation)),
+        Err(e) => {
+            tracing::error!("Error staging credential rotation: {:?}", e);
+            let error_msg = e.to_string();
+
+            if error_msg.contains("permissions") {
+                Err((StatusCode::FORBIDDEN, "Insufficient permissions"))
+            } else if error_msg.contains("not found") {
+                Err((StatusCode::NOT_FOUND, "Data source not found"))
+            } else if error_msg.contains("doesn't match") {
+                Err((
+                    StatusCode::BAD_REQUEST,
+                    "Credential type doesn't match the data source type",
+                ))
+            } else if error_msg.contains("managed outside the application") {
+                Err((
+                    StatusCode::CONFLICT,
+                    "Credentials are managed by the secret provider and can't be rotated here",
+                ))
+            } else {
+                Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to stage credential rotation",
+                ))
+            }
+        }
+    }
+}
@@ -15,6 +15,9 @@ pub async fn update_data_source(
         Ok(_) => Ok(ApiResponse::NoContent),
         Err(e) => {
             tracing::error!("Error updating data source: {:?}", e);
+            if e.to_string().contains("Invalid credentials") {
+                return Err((StatusCode::BAD_REQUEST, "Invalid credentials"));
+            }
             Err((
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Failed to update data source",
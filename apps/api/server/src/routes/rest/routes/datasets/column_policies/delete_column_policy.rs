@@ -0,0 +1,52 @@
+use anyhow::{anyhow, Result};
+use axum::{extract::Path, http::StatusCode, Extension};
+use chrono::Utc;
+use diesel::{update, ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+use database::{pool::get_pg_pool, schema::dataset_column_policies};
+
+use super::get_dataset_organization_for_admin;
+
+pub async fn delete_column_policy(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path((dataset_id, policy_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    match delete_column_policy_handler(&user, &dataset_id, &policy_id).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => {
+            tracing::error!("Error deleting column policy: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+async fn delete_column_policy_handler(
+    user: &AuthenticatedUser,
+    dataset_id: &Uuid,
+    policy_id: &Uuid,
+) -> Result<()> {
+    get_dataset_organization_for_admin(user, dataset_id).await?;
+
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .map_err(|e| anyhow!("Unable to get connection from pool: {}", e))?;
+
+    let deleted = update(dataset_column_policies::table)
+        .filter(dataset_column_policies::id.eq(policy_id))
+        .filter(dataset_column_policies::dataset_id.eq(dataset_id))
+        .filter(dataset_column_policies::deleted_at.is_null())
+        .set(dataset_column_policies::deleted_at.eq(Some(Utc::now())))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Error deleting column policy: {}", e))?;
+
+    if deleted == 0 {
+        return Err(anyhow!("Column policy not found"));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use anyhow::{anyhow, Result};
+use axum::{extract::Path, http::StatusCode, Extension};
+use diesel::{ExpressionMethods, QueryDsl, SelectableHelper};
+use diesel_async::RunQueryDsl;
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+use database::{models::DatasetColumnPolicy, pool::get_pg_pool, schema::dataset_column_policies};
+
+use super::get_dataset_organization_for_admin;
+use crate::routes::rest::ApiResponse;
+
+pub async fn list_column_policies(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(dataset_id): Path<Uuid>,
+) -> Result<ApiResponse<Vec<DatasetColumnPolicy>>, (StatusCode, String)> {
+    match list_column_policies_handler(&user, &dataset_id).await {
+        Ok(policies) => Ok(ApiResponse::JsonData(policies)),
+        Err(e) => {
+            tracing::error!("Error listing column policies: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+async fn list_column_policies_handler(
+    user: &AuthenticatedUser,
+    dataset_id: &Uuid,
+) -> Result<Vec<DatasetColumnPolicy>> {
+    get_dataset_organization_for_admin(user, dataset_id).await?;
+
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .map_err(|e| anyhow!("Unable to get connection from pool: {}", e))?;
+
+    dataset_column_policies::table
+        .filter(dataset_column_policies::dataset_id.eq(dataset_id))
+        .filter(dataset_column_policies::deleted_at.is_null())
+        .order(dataset_column_policies::created_at.asc())
+        .select(DatasetColumnPolicy::as_select())
+        .load::<DatasetColumnPolicy>(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Error getting column policies: {}", e))
+}
@@ -0,0 +1,58 @@
+mod delete_column_policy;
+mod list_column_policies;
+mod post_column_policy;
+
+use anyhow::{anyhow, Result};
+use axum::{
+    routing::{delete, get},
+    Router,
+};
+use diesel::{ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+use database::{pool::get_pg_pool, schema::datasets};
+
+use crate::utils::security::checks::is_user_workspace_admin_or_data_admin;
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/",
+            get(list_column_policies::list_column_policies)
+                .post(post_column_policy::post_column_policy),
+        )
+        .route(
+            "/:policy_id",
+            delete(delete_column_policy::delete_column_policy),
+        )
+}
+
+/// Returns the dataset's organization, failing unless the user is a workspace or data admin of it
+async fn get_dataset_organization_for_admin(
+    user: &AuthenticatedUser,
+    dataset_id: &Uuid,
+) -> Result<Uuid> {
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .map_err(|e| anyhow!("Unable to get connection from pool: {}", e))?;
+
+    let organization_id = datasets::table
+        .filter(datasets::id.eq(dataset_id))
+        .filter(datasets::deleted_at.is_null())
+        .select(datasets::organization_id)
+        .first::<Uuid>(&mut conn)
+        .await
+        .map_err(|e| match e {
+            diesel::result::Error::NotFound => anyhow!("Dataset not found"),
+            _ => anyhow!("Error getting dataset: {}", e),
+        })?;
+
+    if !is_user_workspace_admin_or_data_admin(user, &organization_id).await? {
+        return Err(anyhow!("User does not have required permissions"));
+    }
+
+    Ok(organization_id)
+}
@@ -0,0 +1,107 @@
+use anyhow::{anyhow, Result};
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use chrono::Utc;
+use diesel::{insert_into, ExpressionMethods, OptionalExtension, QueryDsl};
+use diesel_async::RunQueryDsl;
+use middleware::AuthenticatedUser;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use database::{
+    enums::{ColumnPolicyAction, UserOrganizationRole},
+    models::DatasetColumnPolicy,
+    pool::get_pg_pool,
+    schema::{dataset_column_policies, dataset_columns, permission_groups},
+};
+
+use super::get_dataset_organization_for_admin;
+use crate::routes::rest::ApiResponse;
+
+#[derive(Debug, Deserialize)]
+pub struct PostColumnPolicyRequest {
+    pub column_name: String,
+    pub action: ColumnPolicyAction,
+    /// Exactly one of `permission_group_id` and `role` selects who the policy applies to
+    pub permission_group_id: Option<Uuid>,
+    pub role: Option<UserOrganizationRole>,
+}
+
+pub async fn post_column_policy(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(dataset_id): Path<Uuid>,
+    Json(request): Json<PostColumnPolicyRequest>,
+) -> Result<ApiResponse<DatasetColumnPolicy>, (StatusCode, String)> {
+    match post_column_policy_handler(&user, &dataset_id, request).await {
+        Ok(policy) => Ok(ApiResponse::JsonData(policy)),
+        Err(e) => {
+            tracing::error!("Error creating column policy: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+async fn post_column_policy_handler(
+    user: &AuthenticatedUser,
+    dataset_id: &Uuid,
+    request: PostColumnPolicyRequest,
+) -> Result<DatasetColumnPolicy> {
+    let organization_id = get_dataset_organization_for_admin(user, dataset_id).await?;
+
+    if request.permission_group_id.is_some() == request.role.is_some() {
+        return Err(anyhow!(
+            "A column policy applies to either a permission group or a role"
+        ));
+    }
+
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .map_err(|e| anyhow!("Unable to get connection from pool: {}", e))?;
+
+    let dataset_column_id = dataset_columns::table
+        .filter(dataset_columns::dataset_id.eq(dataset_id))
+        .filter(dataset_columns::name.eq(request.column_name.trim()))
+        .filter(dataset_columns::deleted_at.is_null())
+        .select(dataset_columns::id)
+        .first::<Uuid>(&mut conn)
+        .await
+        .optional()
+        .map_err(|e| anyhow!("Error getting dataset column: {}", e))?
+        .ok_or_else(|| anyhow!("Column '{}' not found in dataset", request.column_name))?;
+
+    if let Some(permission_group_id) = request.permission_group_id {
+        permission_groups::table
+            .filter(permission_groups::id.eq(permission_group_id))
+            .filter(permission_groups::organization_id.eq(organization_id))
+            .filter(permission_groups::deleted_at.is_null())
+            .select(permission_groups::id)
+            .first::<Uuid>(&mut conn)
+            .await
+            .optional()
+            .map_err(|e| anyhow!("Error getting permission group: {}", e))?
+            .ok_or_else(|| anyhow!("Permission group not found"))?;
+    }
+
+    let now = Utc::now();
+    let policy = DatasetColumnPolicy {
+        id: Uuid::new_v4(),
+        dataset_column_id,
+        dataset_id: *dataset_id,
+        organization_id,
+        action: request.action,
+        permission_group_id: request.permission_group_id,
+        role: request.role,
+        created_by: user.id,
+        created_at: now,
+        updated_at: now,
+        deleted_at: None,
+    };
+
+    insert_into(dataset_column_policies::table)
+        .values(&policy)
+        .execute(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Error creating column policy: {}", e))?;
+
+    Ok(policy)
+}
@@ -9,7 +9,7 @@ use middleware::AuthenticatedUser;
 
 use crate::{
     database::{
-        enums::UserOrganizationRole,
+        enums::{AuditSurface, UserOrganizationRole},
         pool::get_pg_pool,
         models::Dataset,
         schema::{datasets, users_to_organizations},
@@ -18,7 +18,10 @@ use crate::{
 };
 
 use query_engine::data_types::DataType;
-use query_engine::data_source_query_routes::query_engine::query_engine;
+use query_engine::data_source_query_routes::query_audit::{audit_query, QueryAuditContext};
+use query_engine::data_source_query_routes::query_cancellation::CancellationToken;
+use query_engine::data_source_query_routes::query_engine::query_engine_for_user;
+use query_engine::data_source_query_routes::query_quotas::QueryQuotaError;
 
 #[derive(Serialize)]
 pub struct GetDatasetOwner {
@@ -36,16 +39,22 @@ pub struct GetDatasetDataSource {
 pub async fn get_dataset_data_sample(
     Extension(user): Extension<AuthenticatedUser>,
     Path(dataset_id): Path<Uuid>,
-) -> Result<ApiResponse<Vec<IndexMap<String, DataType>>>, (axum::http::StatusCode, &'static str)> {
+) -> Result<ApiResponse<Vec<IndexMap<String, DataType>>>, (axum::http::StatusCode, String)> {
     match get_dataset_data_sample_handler(&dataset_id, &user).await {
         Ok(data) => Ok(ApiResponse::JsonData(data)),
-        Err(e) => {
-            tracing::error!("Error getting dataset: {:?}", e);
-            Err((
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                "Failed to get dataset",
-            ))
-        }
+        Err(e) => match e.downcast_ref::<QueryQuotaError>() {
+            Some(quota_error) => Err((
+                axum::http::StatusCode::TOO_MANY_REQUESTS,
+                quota_error.to_string(),
+            )),
+            None => {
+                tracing::error!("Error getting dataset: {:?}", e);
+                Err((
+                    axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                    "Failed to get dataset".to_string(),
+                ))
+            }
+        },
     }
 }
 
@@ -100,11 +109,22 @@ async fn get_dataset_data_sample_handler(
         let schema = dataset.schema.clone();
         let database_name = dataset.database_name.clone();
         let sql = format!("SELECT * FROM {}.{} LIMIT 25", schema, database_name);
-        match query_engine(&dataset.data_source_id, &sql, None).await {
+        let audit_context = QueryAuditContext::new(user.id, AuditSurface::Api);
+        // Stops the query if the request is dropped
+        let cancellation = CancellationToken::new();
+        let _cancel_on_drop = cancellation.clone().drop_guard();
+        match audit_query(
+            &audit_context,
+            &dataset.data_source_id,
+            &sql,
+            query_engine_for_user(&user.id, &dataset.data_source_id, &sql, None, &cancellation),
+        )
+        .await
+        {
             Ok(data) => data.data,
             Err(e) => {
                 tracing::error!("Error getting dataset data: {:?}", e);
-                return Err(anyhow!("Error getting dataset data: {:?}", e));
+                return Err(e.context("Error getting dataset data"));
             }
         }
     };
@@ -1,4 +1,5 @@
 mod assets;
+mod column_policies;
 mod delete_dataset;
 mod deploy_datasets;
 // mod generate_datasets;
@@ -6,6 +7,7 @@ mod get_dataset;
 mod get_dataset_data_sample;
 mod list_datasets;
 mod post_dataset;
+mod row_filters;
 
 use axum::{
     routing::{get, post, delete},
@@ -24,5 +26,7 @@ pub fn router() -> Router {
             "/:dataset_id/data/sample",
             get(get_dataset_data_sample::get_dataset_data_sample),
         )
+        .nest("/:dataset_id/row_filters", row_filters::router())
+        .nest("/:dataset_id/column_policies", column_policies::router())
         .nest("/:dataset_id", assets::router())
 }
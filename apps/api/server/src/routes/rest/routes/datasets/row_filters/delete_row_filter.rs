@@ -0,0 +1,52 @@
+use anyhow::{anyhow, Result};
+use axum::{extract::Path, http::StatusCode, Extension};
+use chrono::Utc;
+use diesel::{update, ExpressionMethods, QueryDsl};
+use diesel_async::RunQueryDsl;
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+use database::{pool::get_pg_pool, schema::dataset_row_filter_policies};
+
+use super::get_dataset_for_admin;
+
+pub async fn delete_row_filter(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path((dataset_id, policy_id)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, (StatusCode, String)> {
+    match delete_row_filter_handler(&user, &dataset_id, &policy_id).await {
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => {
+            tracing::error!("Error deleting row filter: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+async fn delete_row_filter_handler(
+    user: &AuthenticatedUser,
+    dataset_id: &Uuid,
+    policy_id: &Uuid,
+) -> Result<()> {
+    get_dataset_for_admin(user, dataset_id).await?;
+
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .map_err(|e| anyhow!("Unable to get connection from pool: {}", e))?;
+
+    let deleted = update(dataset_row_filter_policies::table)
+        .filter(dataset_row_filter_policies::id.eq(policy_id))
+        .filter(dataset_row_filter_policies::dataset_id.eq(dataset_id))
+        .filter(dataset_row_filter_policies::deleted_at.is_null())
+        .set(dataset_row_filter_policies::deleted_at.eq(Some(Utc::now())))
+        .execute(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Error deleting row filter: {}", e))?;
+
+    if deleted == 0 {
+        return Err(anyhow!("Row filter not found"));
+    }
+
+    Ok(())
+}
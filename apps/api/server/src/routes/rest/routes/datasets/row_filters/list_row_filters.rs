@@ -0,0 +1,47 @@
+use anyhow::{anyhow, Result};
+use axum::{extract::Path, http::StatusCode, Extension};
+use diesel::{ExpressionMethods, QueryDsl, SelectableHelper};
+use diesel_async::RunQueryDsl;
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+use database::{
+    models::DatasetRowFilterPolicy, pool::get_pg_pool, schema::dataset_row_filter_policies,
+};
+
+use super::get_dataset_for_admin;
+use crate::routes::rest::ApiResponse;
+
+pub async fn list_row_filters(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(dataset_id): Path<Uuid>,
+) -> Result<ApiResponse<Vec<DatasetRowFilterPolicy>>, (StatusCode, String)> {
+    match list_row_filters_handler(&user, &dataset_id).await {
+        Ok(policies) => Ok(ApiResponse::JsonData(policies)),
+        Err(e) => {
+            tracing::error!("Error listing row filters: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+async fn list_row_filters_handler(
+    user: &AuthenticatedUser,
+    dataset_id: &Uuid,
+) -> Result<Vec<DatasetRowFilterPolicy>> {
+    get_dataset_for_admin(user, dataset_id).await?;
+
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .map_err(|e| anyhow!("Unable to get connection from pool: {}", e))?;
+
+    dataset_row_filter_policies::table
+        .filter(dataset_row_filter_policies::dataset_id.eq(dataset_id))
+        .filter(dataset_row_filter_policies::deleted_at.is_null())
+        .order(dataset_row_filter_policies::created_at.asc())
+        .select(DatasetRowFilterPolicy::as_select())
+        .load::<DatasetRowFilterPolicy>(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Error getting row filters: {}", e))
+}
@@ -0,0 +1,65 @@
+mod delete_row_filter;
+mod list_row_filters;
+mod post_row_filter;
+
+use anyhow::{anyhow, Result};
+use axum::{
+    routing::{delete, get},
+    Router,
+};
+use diesel::{ExpressionMethods, JoinOnDsl, QueryDsl};
+use diesel_async::RunQueryDsl;
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+use database::{
+    enums::DataSourceType,
+    pool::get_pg_pool,
+    schema::{data_sources, datasets},
+};
+
+use crate::utils::security::checks::is_user_workspace_admin_or_data_admin;
+
+pub fn router() -> Router {
+    Router::new()
+        .route(
+            "/",
+            get(list_row_filters::list_row_filters).post(post_row_filter::post_row_filter),
+        )
+        .route("/:policy_id", delete(delete_row_filter::delete_row_filter))
+}
+
+/// The dataset a row filter policy belongs to
+struct PolicyDataset {
+    organization_id: Uuid,
+    data_source_type: DataSourceType,
+}
+
+/// Loads the dataset, failing unless the user is a workspace or data admin of its organization
+async fn get_dataset_for_admin(user: &AuthenticatedUser, dataset_id: &Uuid) -> Result<PolicyDataset> {
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .map_err(|e| anyhow!("Unable to get connection from pool: {}", e))?;
+
+    let (organization_id, data_source_type) = datasets::table
+        .inner_join(data_sources::table.on(datasets::data_source_id.eq(data_sources::id)))
+        .filter(datasets::id.eq(dataset_id))
+        .filter(datasets::deleted_at.is_null())
+        .select((datasets::organization_id, data_sources::type_))
+        .first::<(Uuid, DataSourceType)>(&mut conn)
+        .await
+        .map_err(|e| match e {
+            diesel::result::Error::NotFound => anyhow!("Dataset not found"),
+            _ => anyhow!("Error getting dataset: {}", e),
+        })?;
+
+    if !is_user_workspace_admin_or_data_admin(user, &organization_id).await? {
+        return Err(anyhow!("User does not have required permissions"));
+    }
+
+    Ok(PolicyDataset {
+        organization_id,
+        data_source_type,
+    })
+}
@@ -0,0 +1,72 @@
+use anyhow::{anyhow, Result};
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use chrono::Utc;
+use diesel::insert_into;
+use diesel_async::RunQueryDsl;
+use middleware::AuthenticatedUser;
+use query_engine::data_source_query_routes::row_level_security::validate_filter_expression;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use database::{
+    models::DatasetRowFilterPolicy, pool::get_pg_pool, schema::dataset_row_filter_policies,
+};
+
+use super::get_dataset_for_admin;
+use crate::routes::rest::ApiResponse;
+
+#[derive(Debug, Deserialize)]
+pub struct PostRowFilterRequest {
+    /// Condition on the dataset's columns, e.g. `region = {{user.attributes.region}}`
+    pub filter_expression: String,
+}
+
+pub async fn post_row_filter(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(dataset_id): Path<Uuid>,
+    Json(request): Json<PostRowFilterRequest>,
+) -> Result<ApiResponse<DatasetRowFilterPolicy>, (StatusCode, String)> {
+    match post_row_filter_handler(&user, &dataset_id, request).await {
+        Ok(policy) => Ok(ApiResponse::JsonData(policy)),
+        Err(e) => {
+            tracing::error!("Error creating row filter: {}", e);
+            Err((StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))
+        }
+    }
+}
+
+async fn post_row_filter_handler(
+    user: &AuthenticatedUser,
+    dataset_id: &Uuid,
+    request: PostRowFilterRequest,
+) -> Result<DatasetRowFilterPolicy> {
+    let dataset = get_dataset_for_admin(user, dataset_id).await?;
+
+    let filter_expression = request.filter_expression.trim().to_string();
+    validate_filter_expression(&filter_expression, &dataset.data_source_type)?;
+
+    let now = Utc::now();
+    let policy = DatasetRowFilterPolicy {
+        id: Uuid::new_v4(),
+        dataset_id: *dataset_id,
+        organization_id: dataset.organization_id,
+        filter_expression,
+        created_by: user.id,
+        created_at: now,
+        updated_at: now,
+        deleted_at: None,
+    };
+
+    let mut conn = get_pg_pool()
+        .get()
+        .await
+        .map_err(|e| anyhow!("Unable to get connection from pool: {}", e))?;
+
+    insert_into(dataset_row_filter_policies::table)
+        .values(&policy)
+        .execute(&mut conn)
+        .await
+        .map_err(|e| anyhow!("Error creating row filter: {}", e))?;
+
+    Ok(policy)
+}
@@ -0,0 +1,39 @@
+use axum::{
+    extract::Query,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Extension,
+};
+use handlers::logs::export_audit_logs_handler::export_audit_logs_handler;
+use middleware::AuthenticatedUser;
+
+use super::{get_audit_log_organization_id, list_audit_logs::AuditLogQuery};
+
+/// Downloads the audit log entries matching the filters as a CSV file.
+/// Pagination parameters are ignored.
+pub async fn export_audit_logs_route(
+    Extension(user): Extension<AuthenticatedUser>,
+    Query(query): Query<AuditLogQuery>,
+) -> Result<impl IntoResponse, (StatusCode, &'static str)> {
+    let organization_id = get_audit_log_organization_id(&user)?;
+
+    match export_audit_logs_handler(query.filters(), organization_id).await {
+        Ok(csv) => Ok((
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8"),
+                (
+                    header::CONTENT_DISPOSITION,
+                    "attachment; filename=\"audit_logs.csv\"",
+                ),
+            ],
+            csv,
+        )),
+        Err(e) => {
+            tracing::error!("Error exporting audit logs: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to export audit logs",
+            ))
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use axum::{extract::Query, http::StatusCode, Extension};
+use chrono::{DateTime, Utc};
+use database::enums::{AuditEventType, AuditSurface};
+use handlers::logs::list_audit_logs_handler::{
+    list_audit_logs_handler, AuditLogFilters, ListAuditLogsRequest, ListAuditLogsResponse,
+};
+use middleware::AuthenticatedUser;
+use serde::Deserialize;
+use uuid::Uuid;
+
+use super::get_audit_log_organization_id;
+use crate::routes::rest::ApiResponse;
+
+#[derive(Debug, Deserialize)]
+pub struct AuditLogQuery {
+    pub user_id: Option<Uuid>,
+    pub event_type: Option<AuditEventType>,
+    pub surface: Option<AuditSurface>,
+    pub data_source_id: Option<Uuid>,
+    pub asset_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub page: Option<i32>,
+    #[serde(default = "default_page_size")]
+    pub page_size: i32,
+}
+
+fn default_page_size() -> i32 {
+    50
+}
+
+impl AuditLogQuery {
+    pub fn filters(&self) -> AuditLogFilters {
+        AuditLogFilters {
+            user_id: self.user_id,
+            event_type: self.event_type,
+            surface: self.surface,
+            data_source_id: self.data_source_id,
+            asset_id: self.asset_id,
+            from: self.from,
+            to: self.to,
+        }
+    }
+}
+
+pub async fn list_audit_logs_route(
+    Extension(user): Extension<AuthenticatedUser>,
+    Query(query): Query<AuditLogQuery>,
+) -> Result<ApiResponse<ListAuditLogsResponse>, (StatusCode, &'static str)> {
+    let organization_id = get_audit_log_organization_id(&user)?;
+
+    if !(1..=500).contains(&query.page_size) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            "page_size must be between 1 and 500",
+        ));
+    }
+
+    let request = ListAuditLogsRequest {
+        filters: query.filters(),
+        page: query.page,
+        page_size: query.page_size,
+    };
+
+    match list_audit_logs_handler(request, organization_id).await {
+        Ok(response) => Ok(ApiResponse::JsonData(response)),
+        Err(e) => {
+            tracing::error!("Error listing audit logs: {}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "Failed to list audit logs",
+            ))
+        }
+    }
+}
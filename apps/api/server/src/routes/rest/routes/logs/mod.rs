@@ -1,7 +1,37 @@
-use axum::{routing::get, Router};
+use axum::{http::StatusCode, routing::get, Router};
+use database::enums::UserOrganizationRole;
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
 
+mod export_audit_logs;
+mod list_audit_logs;
 mod list_logs;
 
 pub fn router() -> Router {
-    Router::new().route("/", get(list_logs::list_logs_route))
+    Router::new()
+        .route("/", get(list_logs::list_logs_route))
+        .route("/audit", get(list_audit_logs::list_audit_logs_route))
+        .route(
+            "/audit/export",
+            get(export_audit_logs::export_audit_logs_route),
+        )
+}
+
+// The audit log is only visible to workspace admins of the user's organization
+fn get_audit_log_organization_id(
+    user: &AuthenticatedUser,
+) -> Result<Uuid, (StatusCode, &'static str)> {
+    match user.organizations.get(0) {
+        Some(organization) if organization.role == UserOrganizationRole::WorkspaceAdmin => {
+            Ok(organization.id)
+        }
+        Some(_) => Err((
+            StatusCode::FORBIDDEN,
+            "Only workspace admins can view the audit log",
+        )),
+        None => Err((
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Error getting organization id",
+        )),
+    }
 }
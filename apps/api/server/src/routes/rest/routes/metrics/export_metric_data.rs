@@ -0,0 +1,65 @@
+use axum::body::Body;
+use axum::extract::{Path, Query};
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use axum::Extension;
+use handlers::metrics::export_metric_data_handler::export_metric_data_handler;
+use middleware::AuthenticatedUser;
+use query_engine::data_source_query_routes::query_quotas::QueryQuotaError;
+use serde::Deserialize;
+use uuid::Uuid;
+
+#[derive(Deserialize)]
+pub struct ExportMetricDataParams {
+    pub version_number: Option<i32>,
+    pub password: Option<String>,
+}
+
+/// Downloads every row of the metric's query as a CSV file. The file is streamed as the rows
+/// are read from the data source.
+pub async fn export_metric_data_rest_handler(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(metric_id): Path<Uuid>,
+    Query(params): Query<ExportMetricDataParams>,
+) -> Result<impl IntoResponse, (StatusCode, String)> {
+    tracing::info!(
+        "Processing GET request to export metric data with ID: {}",
+        metric_id
+    );
+
+    match export_metric_data_handler(metric_id, user, params.version_number, params.password).await
+    {
+        Ok(export) => Ok((
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+                (
+                    header::CONTENT_DISPOSITION,
+                    format!("attachment; filename=\"{}\"", export.file_name),
+                ),
+            ],
+            Body::from_stream(export.csv),
+        )),
+        Err(e) => {
+            let error_message = format!("{:#}", e);
+            tracing::error!("Error exporting metric data: {}", error_message);
+
+            if let Some(quota_error) = e.downcast_ref::<QueryQuotaError>() {
+                return Err((StatusCode::TOO_MANY_REQUESTS, quota_error.to_string()));
+            }
+
+            // Same statuses as loading the metric's data
+            if error_message.contains("Incorrect password")
+                || error_message.contains("public_password required")
+            {
+                Err((StatusCode::IM_A_TEAPOT, error_message))
+            } else if error_message.contains("don't have permission")
+                || error_message.contains("not found")
+                || error_message.contains("expired")
+            {
+                Err((StatusCode::FORBIDDEN, error_message))
+            } else {
+                Err((StatusCode::INTERNAL_SERVER_ERROR, error_message))
+            }
+        }
+    }
+}
@@ -3,6 +3,10 @@ use axum::{
     http::StatusCode,
     Extension,
 };
+use database::{
+    enums::{AssetType, AuditEventType},
+    helpers::audit_logs::record_asset_access,
+};
 use handlers::metrics::{get_metric_handler, BusterMetric};
 use serde::Deserialize;
 use uuid::Uuid;
@@ -56,6 +60,15 @@ pub async fn get_metric_rest_handler(
         }
     };
 
+    record_asset_access(
+        &user.id,
+        &id,
+        AssetType::MetricFile,
+        AuditEventType::AssetView,
+        serde_json::json!({ "version_number": metric.version_number }),
+    )
+    .await;
+
     Ok(ApiResponse::JsonData(metric))
 }
 
@@ -4,6 +4,7 @@ use axum::http::StatusCode;
 use axum::Extension;
 use handlers::metrics::get_metric_data_handler::{GetMetricDataRequest, MetricDataResponse};
 use middleware::AuthenticatedUser;
+use query_engine::data_source_query_routes::query_quotas::QueryQuotaError;
 use serde::Deserialize;
 use uuid::Uuid;
 
@@ -12,6 +13,7 @@ pub struct GetMetricDataParams {
     pub version_number: Option<i32>,
     pub limit: Option<i64>,
     pub password: Option<String>,
+    pub cursor: Option<String>,
 }
 
 pub async fn get_metric_data_rest_handler(
@@ -29,14 +31,20 @@ pub async fn get_metric_data_rest_handler(
         version_number: params.version_number,
         limit: params.limit,
         password: params.password,
+        cursor: params.cursor,
     };
 
     match handlers::metrics::get_metric_data_handler(request, user).await {
         Ok(response) => Ok(ApiResponse::JsonData(response)),
         Err(e) => {
-            let error_message = e.to_string();
+            // Includes the causes of errors wrapped with context, e.g. the query's own error
+            let error_message = format!("{:#}", e);
             tracing::error!("Error getting metric data: {}", error_message);
-            
+
+            if let Some(quota_error) = e.downcast_ref::<QueryQuotaError>() {
+                return Err((StatusCode::TOO_MANY_REQUESTS, quota_error.to_string()));
+            }
+
             // Check for specific password-related errors
             if error_message.contains("Incorrect password") || error_message.contains("public_password required") {
                 Err((StatusCode::IM_A_TEAPOT, error_message))
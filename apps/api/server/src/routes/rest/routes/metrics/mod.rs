@@ -6,6 +6,7 @@ use axum::{
 // Import modules
 mod bulk_update_metrics;
 mod delete_metric;
+mod export_metric_data;
 mod get_metric;
 mod get_metric_data;
 mod list_metrics;
@@ -24,5 +25,9 @@ pub fn router() -> Router {
             "/:id/data",
             get(get_metric_data::get_metric_data_rest_handler),
         )
+        .route(
+            "/:id/data/export",
+            get(export_metric_data::export_metric_data_rest_handler),
+        )
         .nest("/:id/sharing", sharing::router())
 }
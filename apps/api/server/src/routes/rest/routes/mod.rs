@@ -11,6 +11,7 @@ mod messages;
 mod metrics;
 mod organizations;
 mod permission_groups;
+mod scim;
 mod search;
 mod sql;
 mod users;
@@ -39,6 +40,7 @@ pub fn router() -> Router {
             .nest("/logs", logs::router())
             .nest("/search", search::router())
             .nest("/helpers", helpers::router())
+            .nest("/scim/v2", scim::router())
             .route_layer(axum_middleware::from_fn(auth)),
     )
 }
@@ -0,0 +1,57 @@
+use anyhow::Result;
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    Extension, Json,
+};
+use uuid::Uuid;
+
+use handlers::llm_usage::{
+    get_llm_usage_handler,
+    types::{LlmBudgetStatus, LlmUsageQuery, LlmUsageResponse, UpdateLlmBudgetRequest},
+    update_llm_budget_handler, LlmUsageError,
+};
+
+use crate::routes::rest::ApiResponse;
+use middleware::AuthenticatedUser;
+
+fn llm_usage_error(e: anyhow::Error, action: &str) -> (StatusCode, String) {
+    match e.downcast_ref::<LlmUsageError>() {
+        Some(LlmUsageError::NotMember | LlmUsageError::NotWorkspaceAdmin) => {
+            (StatusCode::FORBIDDEN, e.to_string())
+        }
+        Some(LlmUsageError::Invalid(_)) => (StatusCode::BAD_REQUEST, e.to_string()),
+        Some(LlmUsageError::BudgetExceeded { .. }) => {
+            (StatusCode::TOO_MANY_REQUESTS, e.to_string())
+        }
+        None => {
+            tracing::error!("Error {}: {:?}", action, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error {}", action),
+            )
+        }
+    }
+}
+
+pub async fn get_llm_usage(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(organization_id): Path<Uuid>,
+    Query(query): Query<LlmUsageQuery>,
+) -> Result<ApiResponse<LlmUsageResponse>, (StatusCode, String)> {
+    match get_llm_usage_handler(&user, organization_id, query).await {
+        Ok(usage) => Ok(ApiResponse::JsonData(usage)),
+        Err(e) => Err(llm_usage_error(e, "getting LLM usage")),
+    }
+}
+
+pub async fn update_llm_budget(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(organization_id): Path<Uuid>,
+    Json(payload): Json<UpdateLlmBudgetRequest>,
+) -> Result<ApiResponse<Option<LlmBudgetStatus>>, (StatusCode, String)> {
+    match update_llm_budget_handler(&user, organization_id, payload).await {
+        Ok(budget) => Ok(ApiResponse::JsonData(budget)),
+        Err(e) => Err(llm_usage_error(e, "updating LLM budget")),
+    }
+}
@@ -3,13 +3,36 @@ use axum::{
     Router,
 };
 
+mod llm_usage;
+mod model_routes;
 pub mod post_organization;
+pub(crate) mod query_settings;
 mod update_organization;
 mod users;
 
 pub fn router() -> Router {
     Router::new()
         .route("/:id/users", get(users::list_organization_users))
+        .route("/:id/llm_usage", get(llm_usage::get_llm_usage))
+        .route("/:id/llm_budget", put(llm_usage::update_llm_budget))
+        .route(
+            "/:id/query_complexity_policy",
+            get(query_settings::get_query_complexity_policy)
+                .put(query_settings::update_query_complexity_policy),
+        )
+        .route(
+            "/:id/query_quota",
+            get(query_settings::get_query_quota).put(query_settings::update_query_quota),
+        )
+        .route(
+            "/:id/model_routes",
+            get(model_routes::get_model_routes).put(model_routes::update_model_routes),
+        )
+        .route(
+            "/:id/semantic_query_settings",
+            get(query_settings::get_semantic_query_settings)
+                .put(query_settings::update_semantic_query_settings),
+        )
         .route("/:id", put(update_organization::update_organization))
         .route("/", post(post_organization::post_organization))
 }
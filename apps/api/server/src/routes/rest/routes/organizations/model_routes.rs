@@ -0,0 +1,33 @@
+use anyhow::Result;
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use uuid::Uuid;
+
+use handlers::organizations::{
+    get_model_routes_handler, types::ModelRouteBody, update_model_routes_handler,
+};
+
+use super::query_settings::query_settings_error;
+use crate::routes::rest::ApiResponse;
+use middleware::AuthenticatedUser;
+
+pub async fn get_model_routes(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(organization_id): Path<Uuid>,
+) -> Result<ApiResponse<Vec<ModelRouteBody>>, (StatusCode, String)> {
+    match get_model_routes_handler(&user, organization_id).await {
+        Ok(routes) => Ok(ApiResponse::JsonData(routes)),
+        Err(e) => Err(query_settings_error(e, "getting model routes")),
+    }
+}
+
+/// Replaces all of the organization's routes. An empty list removes them.
+pub async fn update_model_routes(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(organization_id): Path<Uuid>,
+    Json(payload): Json<Vec<ModelRouteBody>>,
+) -> Result<ApiResponse<Vec<ModelRouteBody>>, (StatusCode, String)> {
+    match update_model_routes_handler(&user, organization_id, payload).await {
+        Ok(routes) => Ok(ApiResponse::JsonData(routes)),
+        Err(e) => Err(query_settings_error(e, "updating model routes")),
+    }
+}
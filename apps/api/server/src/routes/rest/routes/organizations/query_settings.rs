@@ -0,0 +1,98 @@
+use anyhow::Result;
+use axum::{extract::Path, http::StatusCode, Extension, Json};
+use uuid::Uuid;
+
+use handlers::query_settings::{
+    get_organization_query_quota_handler, get_query_complexity_policy_handler,
+    get_semantic_query_settings_handler,
+    types::{QueryComplexityPolicySettings, QueryQuotaBody, SemanticQuerySettingsBody},
+    update_organization_query_quota_handler, update_query_complexity_policy_handler,
+    update_semantic_query_settings_handler, QuerySettingsError,
+};
+
+use crate::routes::rest::ApiResponse;
+use middleware::AuthenticatedUser;
+
+pub(crate) fn query_settings_error(e: anyhow::Error, action: &str) -> (StatusCode, String) {
+    match e.downcast_ref::<QuerySettingsError>() {
+        Some(
+            QuerySettingsError::NotMember
+            | QuerySettingsError::NotWorkspaceAdmin
+            | QuerySettingsError::NotDataAdmin,
+        ) => (StatusCode::FORBIDDEN, e.to_string()),
+        Some(QuerySettingsError::DataSourceNotFound) => (StatusCode::NOT_FOUND, e.to_string()),
+        Some(QuerySettingsError::Invalid(_)) => (StatusCode::BAD_REQUEST, e.to_string()),
+        None => {
+            tracing::error!("Error {}: {:?}", action, e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                format!("Error {}", action),
+            )
+        }
+    }
+}
+
+pub async fn get_query_complexity_policy(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(organization_id): Path<Uuid>,
+) -> Result<ApiResponse<Option<QueryComplexityPolicySettings>>, (StatusCode, String)> {
+    match get_query_complexity_policy_handler(&user, organization_id).await {
+        Ok(policy) => Ok(ApiResponse::JsonData(policy)),
+        Err(e) => Err(query_settings_error(e, "getting query complexity policy")),
+    }
+}
+
+/// A null body removes the organization's policy
+pub async fn update_query_complexity_policy(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(organization_id): Path<Uuid>,
+    Json(payload): Json<Option<QueryComplexityPolicySettings>>,
+) -> Result<ApiResponse<Option<QueryComplexityPolicySettings>>, (StatusCode, String)> {
+    match update_query_complexity_policy_handler(&user, organization_id, payload).await {
+        Ok(policy) => Ok(ApiResponse::JsonData(policy)),
+        Err(e) => Err(query_settings_error(e, "updating query complexity policy")),
+    }
+}
+
+pub async fn get_query_quota(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(organization_id): Path<Uuid>,
+) -> Result<ApiResponse<Option<QueryQuotaBody>>, (StatusCode, String)> {
+    match get_organization_query_quota_handler(&user, organization_id).await {
+        Ok(quota) => Ok(ApiResponse::JsonData(quota)),
+        Err(e) => Err(query_settings_error(e, "getting query quota")),
+    }
+}
+
+/// A null body removes the organization's quota
+pub async fn update_query_quota(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(organization_id): Path<Uuid>,
+    Json(payload): Json<Option<QueryQuotaBody>>,
+) -> Result<ApiResponse<Option<QueryQuotaBody>>, (StatusCode, String)> {
+    match update_organization_query_quota_handler(&user, organization_id, payload).await {
+        Ok(quota) => Ok(ApiResponse::JsonData(quota)),
+        Err(e) => Err(query_settings_error(e, "updating query quota")),
+    }
+}
+
+pub async fn get_semantic_query_settings(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(organization_id): Path<Uuid>,
+) -> Result<ApiResponse<SemanticQuerySettingsBody>, (StatusCode, String)> {
+    match get_semantic_query_settings_handler(&user, organization_id).await {
+        Ok(settings) => Ok(ApiResponse::JsonData(settings)),
+        Err(e) => Err(query_settings_error(e, "getting semantic query settings")),
+    }
+}
+
+pub async fn update_semantic_query_settings(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(organization_id): Path<Uuid>,
+    Json(payload): Json<SemanticQuerySettingsBody>,
+) -> Result<ApiResponse<SemanticQuerySettingsBody>, (StatusCode, String)> {
+    match update_semantic_query_settings_handler(&user, organization_id, payload).await {
+        Ok(settings) => Ok(ApiResponse::JsonData(settings)),
+        Err(e) => Err(query_settings_error(e, "updating semantic query settings")),
+    }
+}
@@ -0,0 +1,76 @@
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use handlers::scim::{
+    create_scim_group, delete_scim_group, get_scim_group, list_scim_groups, patch_scim_group,
+    replace_scim_group, ScimGroup, ScimListRequest, ScimPatchRequest,
+};
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+use super::{scim_error, scim_json};
+
+pub async fn list_groups(
+    Extension(user): Extension<AuthenticatedUser>,
+    Query(request): Query<ScimListRequest>,
+) -> Response {
+    match list_scim_groups(&user, request).await {
+        Ok(groups) => scim_json(StatusCode::OK, groups),
+        Err(e) => scim_error(e, "listing groups"),
+    }
+}
+
+pub async fn get_group(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+) -> Response {
+    match get_scim_group(&user, id).await {
+        Ok(scim_group) => scim_json(StatusCode::OK, scim_group),
+        Err(e) => scim_error(e, "getting group"),
+    }
+}
+
+pub async fn create_group(
+    Extension(user): Extension<AuthenticatedUser>,
+    Json(request): Json<ScimGroup>,
+) -> Response {
+    match create_scim_group(&user, request).await {
+        Ok(scim_group) => scim_json(StatusCode::CREATED, scim_group),
+        Err(e) => scim_error(e, "provisioning group"),
+    }
+}
+
+pub async fn replace_group(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<ScimGroup>,
+) -> Response {
+    match replace_scim_group(&user, id, request).await {
+        Ok(scim_group) => scim_json(StatusCode::OK, scim_group),
+        Err(e) => scim_error(e, "updating group"),
+    }
+}
+
+pub async fn patch_group(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<ScimPatchRequest>,
+) -> Response {
+    match patch_scim_group(&user, id, request).await {
+        Ok(scim_group) => scim_json(StatusCode::OK, scim_group),
+        Err(e) => scim_error(e, "updating group"),
+    }
+}
+
+pub async fn delete_group(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+) -> Response {
+    match delete_scim_group(&user, id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => scim_error(e, "deleting group"),
+    }
+}
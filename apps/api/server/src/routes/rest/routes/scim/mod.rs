@@ -0,0 +1,90 @@
+use axum::{
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use handlers::scim::ScimError;
+use serde::Serialize;
+use serde_json::json;
+
+mod groups;
+mod users;
+
+/// SCIM 2.0 endpoints for identity providers. Mounted at `/scim/v2`, the base URL identity
+/// providers are configured with.
+pub fn router() -> Router {
+    Router::new()
+        .route("/ServiceProviderConfig", get(service_provider_config))
+        .route("/Users", get(users::list_users).post(users::create_user))
+        .route(
+            "/Users/:id",
+            get(users::get_user)
+                .put(users::replace_user)
+                .patch(users::patch_user)
+                .delete(users::delete_user),
+        )
+        .route(
+            "/Groups",
+            get(groups::list_groups).post(groups::create_group),
+        )
+        .route(
+            "/Groups/:id",
+            get(groups::get_group)
+                .put(groups::replace_group)
+                .patch(groups::patch_group)
+                .delete(groups::delete_group),
+        )
+}
+
+const SCIM_CONTENT_TYPE: &str = "application/scim+json";
+
+fn scim_json<T: Serialize>(status: StatusCode, body: T) -> Response {
+    (
+        status,
+        [(header::CONTENT_TYPE, SCIM_CONTENT_TYPE)],
+        Json(body),
+    )
+        .into_response()
+}
+
+/// Reports a handler error in SCIM's error format. Errors other than `ScimError` are
+/// internal and their details aren't returned.
+fn scim_error(error: anyhow::Error, action: &str) -> Response {
+    let scim_error = match error.downcast_ref::<ScimError>() {
+        Some(scim_error) => scim_error.clone(),
+        None => {
+            tracing::error!("Error {}: {}", action, error);
+            ScimError {
+                status: 500,
+                scim_type: None,
+                detail: format!("Error {}", action),
+            }
+        }
+    };
+
+    let status =
+        StatusCode::from_u16(scim_error.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+    scim_json(status, scim_error.body())
+}
+
+async fn service_provider_config() -> Response {
+    scim_json(
+        StatusCode::OK,
+        json!({
+            "schemas": ["urn:ietf:params:scim:schemas:core:2.0:ServiceProviderConfig"],
+            "patch": { "supported": true },
+            "bulk": { "supported": false, "maxOperations": 0, "maxPayloadSize": 0 },
+            "filter": { "supported": true, "maxResults": 1000 },
+            "changePassword": { "supported": false },
+            "sort": { "supported": false },
+            "etag": { "supported": false },
+            "authenticationSchemes": [{
+                "type": "oauthbearertoken",
+                "name": "API key",
+                "description": "An API key with the scim:provision scope, created by a workspace admin",
+                "primary": true
+            }]
+        }),
+    )
+}
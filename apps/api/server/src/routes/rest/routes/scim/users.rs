@@ -0,0 +1,76 @@
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use handlers::scim::{
+    create_scim_user, delete_scim_user, get_scim_user, list_scim_users, patch_scim_user,
+    replace_scim_user, ScimListRequest, ScimPatchRequest, ScimUser,
+};
+use middleware::AuthenticatedUser;
+use uuid::Uuid;
+
+use super::{scim_error, scim_json};
+
+pub async fn list_users(
+    Extension(user): Extension<AuthenticatedUser>,
+    Query(request): Query<ScimListRequest>,
+) -> Response {
+    match list_scim_users(&user, request).await {
+        Ok(users) => scim_json(StatusCode::OK, users),
+        Err(e) => scim_error(e, "listing users"),
+    }
+}
+
+pub async fn get_user(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+) -> Response {
+    match get_scim_user(&user, id).await {
+        Ok(scim_user) => scim_json(StatusCode::OK, scim_user),
+        Err(e) => scim_error(e, "getting user"),
+    }
+}
+
+pub async fn create_user(
+    Extension(user): Extension<AuthenticatedUser>,
+    Json(request): Json<ScimUser>,
+) -> Response {
+    match create_scim_user(&user, request).await {
+        Ok(scim_user) => scim_json(StatusCode::CREATED, scim_user),
+        Err(e) => scim_error(e, "provisioning user"),
+    }
+}
+
+pub async fn replace_user(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<ScimUser>,
+) -> Response {
+    match replace_scim_user(&user, id, request).await {
+        Ok(scim_user) => scim_json(StatusCode::OK, scim_user),
+        Err(e) => scim_error(e, "updating user"),
+    }
+}
+
+pub async fn patch_user(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+    Json(request): Json<ScimPatchRequest>,
+) -> Response {
+    match patch_scim_user(&user, id, request).await {
+        Ok(scim_user) => scim_json(StatusCode::OK, scim_user),
+        Err(e) => scim_error(e, "updating user"),
+    }
+}
+
+pub async fn delete_user(
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<Uuid>,
+) -> Response {
+    match delete_scim_user(&user, id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => scim_error(e, "deprovisioning user"),
+    }
+}
@@ -2,7 +2,12 @@ use anyhow::{anyhow, Result};
 use axum::{Extension, Json};
 use diesel::{BoolExpressionMethods, ExpressionMethods, JoinOnDsl, QueryDsl};
 use indexmap::IndexMap;
-use query_engine::data_source_query_routes::query_engine::query_engine;
+use query_engine::data_source_query_routes::query_audit::{audit_query, QueryAuditContext};
+use query_engine::data_source_query_routes::query_cancellation::CancellationToken;
+use query_engine::data_source_query_routes::query_engine::{
+    query_engine_for_user, semantic_query_engine_for_user, QueryResult,
+};
+use query_engine::data_source_query_routes::query_quotas::QueryQuotaError;
 use query_engine::data_types::DataType;
 use reqwest::StatusCode;
 use uuid::Uuid;
@@ -11,14 +16,14 @@ use diesel_async::RunQueryDsl;
 use serde::{Deserialize, Serialize};
 
 use database::{
-    enums::UserOrganizationRole,
+    enums::{AuditSurface, UserOrganizationRole},
     pool::get_pg_pool,
     schema::{data_sources, datasets, users_to_organizations},
     types::DataMetadata,
 };
 
 use dataset_security::has_dataset_access;
-use middleware::AuthenticatedUser;
+use middleware::{ApiKeyAccess, AuthenticatedUser};
 
 use crate::routes::rest::ApiResponse;
 
@@ -29,24 +34,61 @@ pub struct RunSqlRequest {
     pub dataset_id: Option<Uuid>,
     pub data_source_id: Option<Uuid>,
     pub sql: String,
+    /// The SQL is written against the semantic layer's metrics and filters
+    #[serde(default)]
+    pub semantic: bool,
+}
+
+// A scoped API key was used on a data source it wasn't created for
+#[derive(Debug)]
+struct ApiKeyScopeError {
+    data_source_id: Uuid,
 }
 
+impl std::fmt::Display for ApiKeyScopeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "API key can't query data source {}", self.data_source_id)
+    }
+}
+
+impl std::error::Error for ApiKeyScopeError {}
+
 pub async fn run_sql(
     Extension(user): Extension<AuthenticatedUser>,
+    api_key: Option<Extension<ApiKeyAccess>>,
     Json(req): Json<RunSqlRequest>,
-) -> Result<ApiResponse<DataObject>, (StatusCode, &'static str)> {
-    let data_object =
-        match run_sql_handler(&req.sql, &req.data_source_id, &req.dataset_id, &user.id).await {
-            Ok(data_object) => data_object,
-            Err(e) => {
+) -> Result<ApiResponse<DataObject>, (StatusCode, String)> {
+    let api_key = api_key.map(|Extension(api_key)| api_key);
+    let data_object = match run_sql_handler(
+        &req.sql,
+        &req.data_source_id,
+        &req.dataset_id,
+        &user.id,
+        req.semantic,
+        api_key.as_ref(),
+    )
+    .await
+    {
+        Ok(data_object) => data_object,
+        Err(e) if e.downcast_ref::<ApiKeyScopeError>().is_some() => {
+            tracing::warn!("Rejected SQL run: {}", e);
+            return Err((
+                StatusCode::FORBIDDEN,
+                "API key can't query this data source".to_string(),
+            ));
+        }
+        Err(e) => match e.downcast_ref::<QueryQuotaError>() {
+            Some(quota_error) => {
+                tracing::warn!("Query rejected by quota: {}", quota_error);
+                return Err((StatusCode::TOO_MANY_REQUESTS, quota_error.to_string()));
+            }
+            None => {
                 tracing::error!("Error running SQL: {:?}", e);
                 let err_msg = format!("Error running SQL: {:?}", e);
-                return Err((
-                    StatusCode::INTERNAL_SERVER_ERROR,
-                    Box::leak(err_msg.into_boxed_str()),
-                ));
+                return Err((StatusCode::INTERNAL_SERVER_ERROR, err_msg));
             }
-        };
+        },
+    };
 
     Ok(ApiResponse::JsonData(data_object))
 }
@@ -56,11 +98,13 @@ async fn run_sql_handler(
     data_source_id: &Option<Uuid>,
     dataset_id: &Option<Uuid>,
     user_id: &Uuid,
+    semantic: bool,
+    api_key: Option<&ApiKeyAccess>,
 ) -> Result<DataObject> {
     if let Some(data_source_id) = data_source_id {
-        return run_data_source_sql_handler(sql, &data_source_id, user_id).await;
+        return run_data_source_sql_handler(sql, &data_source_id, user_id, semantic, api_key).await;
     } else if let Some(dataset_id) = dataset_id {
-        return run_dataset_sql_handler(sql, &dataset_id, user_id).await;
+        return run_dataset_sql_handler(sql, &dataset_id, user_id, semantic, api_key).await;
     } else {
         return Err(anyhow!("No data source or dataset id provided"));
     }
@@ -70,6 +114,8 @@ async fn run_dataset_sql_handler(
     sql: &String,
     dataset_id: &Uuid,
     user_id: &Uuid,
+    semantic: bool,
+    api_key: Option<&ApiKeyAccess>,
 ) -> Result<DataObject> {
     let has_dataset_access = match has_dataset_access(user_id, dataset_id).await {
         Ok(has_access) => has_access,
@@ -99,7 +145,14 @@ async fn run_dataset_sql_handler(
         .is_ok();
 
     let results = if is_org_admin_or_owner || has_dataset_access {
-        match fetch_data(sql, dataset_id).await {
+        let data_source_id = datasets::table
+            .filter(datasets::id.eq(dataset_id))
+            .select(datasets::data_source_id)
+            .first::<Uuid>(&mut conn)
+            .await
+            .map_err(|e| anyhow!("Error getting dataset data source: {}", e))?;
+
+        match fetch_data(sql, &data_source_id, user_id, semantic, api_key).await {
             Ok(results) => results,
             Err(e) => return Err(e),
         }
@@ -114,10 +167,18 @@ async fn run_dataset_sql_handler(
 pub struct DataObject {
     pub data: Vec<IndexMap<String, DataType>>,
     pub data_metadata: DataMetadata,
+    /// Complexity policy and row limit warnings raised while running the query
+    pub warnings: Vec<String>,
 }
 
-pub async fn fetch_data(sql: &String, dataset_id: &Uuid) -> Result<DataObject> {
-    let query_result = match query_engine(&dataset_id, &sql, None).await {
+pub async fn fetch_data(
+    sql: &String,
+    data_source_id: &Uuid,
+    user_id: &Uuid,
+    semantic: bool,
+    api_key: Option<&ApiKeyAccess>,
+) -> Result<DataObject> {
+    let query_result = match run_query(sql, data_source_id, user_id, semantic, api_key).await {
         Ok(result) => result,
         Err(e) => {
             return Err(anyhow!(e));
@@ -127,6 +188,7 @@ pub async fn fetch_data(sql: &String, dataset_id: &Uuid) -> Result<DataObject> {
     Ok(DataObject {
         data: query_result.data,
         data_metadata: query_result.metadata,
+        warnings: query_result.warnings,
     })
 }
 
@@ -134,8 +196,10 @@ async fn run_data_source_sql_handler(
     sql: &String,
     data_source_id: &Uuid,
     user_id: &Uuid,
+    semantic: bool,
+    api_key: Option<&ApiKeyAccess>,
 ) -> Result<DataObject> {
-    let query_result = match query_engine(&data_source_id, &sql, None).await {
+    let query_result = match run_query(sql, data_source_id, user_id, semantic, api_key).await {
         Ok(result) => result,
         Err(e) => return Err(e),
     };
@@ -143,5 +207,47 @@ async fn run_data_source_sql_handler(
     Ok(DataObject {
         data: query_result.data,
         data_metadata: query_result.metadata,
+        warnings: query_result.warnings,
     })
 }
+
+async fn run_query(
+    sql: &str,
+    data_source_id: &Uuid,
+    user_id: &Uuid,
+    semantic: bool,
+    api_key: Option<&ApiKeyAccess>,
+) -> Result<QueryResult> {
+    // Scoped keys only run SQL on the data sources they were created for
+    if let Some(api_key) = api_key {
+        if !api_key.allows_data_source(data_source_id) {
+            return Err(ApiKeyScopeError {
+                data_source_id: *data_source_id,
+            }
+            .into());
+        }
+    }
+
+    // Stops the warehouse query when the client disconnects and axum drops the request
+    let cancellation = CancellationToken::new();
+    let _cancel_on_drop = cancellation.clone().drop_guard();
+
+    let audit_context = QueryAuditContext::new(*user_id, AuditSurface::RunSql);
+    if semantic {
+        audit_query(
+            &audit_context,
+            data_source_id,
+            sql,
+            semantic_query_engine_for_user(user_id, data_source_id, sql, None, &cancellation),
+        )
+        .await
+    } else {
+        audit_query(
+            &audit_context,
+            data_source_id,
+            sql,
+            query_engine_for_user(user_id, data_source_id, sql, None, &cancellation),
+        )
+        .await
+    }
+}
@@ -1,6 +1,7 @@
 use anyhow::Result;
 use handlers::chats::post_chat_handler::ChatCreateNewChat;
 use handlers::chats::post_chat_handler::{self, ThreadEvent};
+use handlers::llm_usage::LlmUsageError;
 use middleware::AuthenticatedUser;
 use tokio::sync::mpsc;
 
@@ -109,6 +110,19 @@ pub async fn post_thread(
             // its BusterContainer::Chat with the Completed event.
             Ok(())
         }
+        Err(e) if matches!(
+            e.downcast_ref::<LlmUsageError>(),
+            Some(LlmUsageError::BudgetExceeded { .. })
+        ) => {
+            send_error_message(
+                &user.id.to_string(),
+                WsRoutes::Chats(ChatsRoute::Post),
+                WsEvent::Threads(WSThreadEvent::PostThread),
+                WsErrorCode::BadRequest,
+                e.to_string(),
+                user,
+            ).await
+        }
         Err(e) => {
             send_error_message(
                 &user.id.to_string(),